@@ -37,6 +37,9 @@ impl ParameterKind {
 
 struct ArgAttribute {
     kind: ParameterKind,
+    /// The python name of the parameter, if it differs from the field name
+    /// (e.g. because it is a rust keyword).
+    name: Option<String>,
     default: Option<Expr>,
     optional: bool,
 }
@@ -58,6 +61,7 @@ impl ArgAttribute {
 
                 let mut attribute = ArgAttribute {
                     kind,
+                    name: None,
                     default: None,
                     optional: false,
                 };
@@ -103,6 +107,13 @@ impl ArgAttribute {
                         }
                         _ => panic!("Expected string value for default argument"),
                     }
+                } else if name_value.ident == "name" {
+                    match name_value.lit {
+                        Lit::Str(ref val) => {
+                            self.name = Some(val.value());
+                        }
+                        _ => panic!("Expected string value for name argument"),
+                    }
                 } else if name_value.ident == "optional" {
                     match name_value.lit {
                         Lit::Bool(ref val) => {
@@ -128,6 +139,7 @@ fn generate_field(field: &Field) -> TokenStream2 {
     let attr = if pyarg_attrs.is_empty() {
         ArgAttribute {
             kind: ParameterKind::PositionalOrKeyword,
+            name: None,
             default: None,
             optional: false,
         }
@@ -141,6 +153,10 @@ fn generate_field(field: &Field) -> TokenStream2 {
     };
 
     let name = &field.ident;
+    let pyname = match attr.name {
+        Some(ref pyname) => quote! { #pyname },
+        None => quote! { stringify!(#name) },
+    };
     let middle = quote! {
         .map(|x| crate::pyobject::TryFromObject::try_from_object(vm, x)).transpose()?
     };
@@ -159,7 +175,7 @@ fn generate_field(field: &Field) -> TokenStream2 {
                 crate::function::ArgumentError::TooFewArgs
             },
             ParameterKind::KeywordOnly => quote! {
-                crate::function::ArgumentError::RequiredKeywordArgument(tringify!(#name))
+                crate::function::ArgumentError::RequiredKeywordArgument(#pyname.to_string())
             },
         };
         quote! {
@@ -175,12 +191,12 @@ fn generate_field(field: &Field) -> TokenStream2 {
         }
         ParameterKind::PositionalOrKeyword => {
             quote! {
-                #name: args.take_positional_keyword(stringify!(#name))#middle#ending,
+                #name: args.take_positional_keyword(#pyname)#middle#ending,
             }
         }
         ParameterKind::KeywordOnly => {
             quote! {
-                #name: args.take_keyword(stringify!(#name))#middle#ending,
+                #name: args.take_keyword(#pyname)#middle#ending,
             }
         }
     }
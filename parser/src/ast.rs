@@ -89,10 +89,12 @@ pub enum Statement {
         orelse: Option<Vec<LocatedStatement>>,
     },
     With {
+        is_async: bool,
         items: Vec<WithItem>,
         body: Vec<LocatedStatement>,
     },
     For {
+        is_async: bool,
         target: Expression,
         iter: Vec<Expression>,
         body: Vec<LocatedStatement>,
@@ -117,6 +119,7 @@ pub enum Statement {
         // TODO: docstring: String,
     },
     FunctionDef {
+        is_async: bool,
         name: String,
         args: Parameters,
         // docstring: String,
@@ -152,6 +155,9 @@ pub enum Expression {
         op: UnaryOperator,
         a: Box<Expression>,
     },
+    Await {
+        value: Box<Expression>,
+    },
     Yield {
         value: Option<Box<Expression>>,
     },
@@ -230,6 +236,7 @@ impl Expression {
         match self {
             BoolOp { .. } | Binop { .. } | Unop { .. } => "operator",
             Subscript { .. } => "subscript",
+            Await { .. } => "await expression",
             Yield { .. } | YieldFrom { .. } => "yield expression",
             Compare { .. } => "comparison",
             Attribute { .. } => "attribute",
@@ -296,6 +303,7 @@ pub enum ComprehensionKind {
 
 #[derive(Debug, PartialEq)]
 pub struct Comprehension {
+    pub is_async: bool,
     pub target: Expression,
     pub iter: Expression,
     pub ifs: Vec<Expression>,
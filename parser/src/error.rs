@@ -23,8 +23,21 @@ pub enum ParseError {
     InvalidToken(Location),
     /// Parser encountered an unexpected token
     UnrecognizedToken(TokSpan, Vec<String>),
-    /// Maps to `User` type from `lalrpop-util`
-    Other,
+    /// Lexer rejected the input, maps to `User` type from `lalrpop-util`
+    Lexical(LexicalError),
+}
+
+impl ParseError {
+    /// The location in the source where the error was detected, if known.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            ParseError::EOF(location) => location.clone(),
+            ParseError::ExtraToken((location, ..))
+            | ParseError::InvalidToken(location)
+            | ParseError::UnrecognizedToken((location, ..), _) => Some(location.clone()),
+            ParseError::Lexical(error) => Some(error.location.clone()),
+        }
+    }
 }
 
 /// Convert `lalrpop_util::ParseError` to our internal type
@@ -34,8 +47,7 @@ impl From<InnerError<Location, Tok, LexicalError>> for ParseError {
             // TODO: Are there cases where this isn't an EOF?
             InnerError::InvalidToken { location } => ParseError::EOF(Some(location)),
             InnerError::ExtraToken { token } => ParseError::ExtraToken(token),
-            // Inner field is a unit-like enum `LexicalError::StringError` with no useful info
-            InnerError::User { .. } => ParseError::Other,
+            InnerError::User { error } => ParseError::Lexical(error),
             InnerError::UnrecognizedToken { token, expected } => {
                 match token {
                     Some(tok) => ParseError::UnrecognizedToken(tok, expected),
@@ -66,8 +78,7 @@ impl fmt::Display for ParseError {
             ParseError::UnrecognizedToken(ref t_span, _) => {
                 write!(f, "Got unexpected token: {:?} at {:?}", t_span.1, t_span.0)
             }
-            // This is user defined, it probably means a more useful error should have been given upstream.
-            ParseError::Other => write!(f, "Got unsupported token(s)"),
+            ParseError::Lexical(ref error) => write!(f, "{} at: {:?}", error.error, error.location),
         }
     }
 }
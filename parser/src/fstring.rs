@@ -5,7 +5,7 @@ use std::str;
 use lalrpop_util::ParseError as LalrpopError;
 
 use crate::ast::{ConversionFlag, StringGroup};
-use crate::lexer::{LexicalError, Location, Tok};
+use crate::lexer::{LexicalError, LexicalErrorType, Location, Tok};
 use crate::parser::parse_expression;
 
 use self::FStringError::*;
@@ -22,10 +22,14 @@ pub enum FStringError {
     MismatchedDelimiter,
 }
 
-impl From<FStringError> for LalrpopError<Location, Tok, LexicalError> {
-    fn from(_err: FStringError) -> Self {
-        lalrpop_util::ParseError::User {
-            error: LexicalError::StringError,
+impl FStringError {
+    /// Report this error as an invalid string starting at `location`.
+    pub fn at(self, location: Location) -> LalrpopError<Location, Tok, LexicalError> {
+        LalrpopError::User {
+            error: LexicalError {
+                error: LexicalErrorType::StringError,
+                location,
+            },
         }
     }
 }
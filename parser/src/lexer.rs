@@ -9,6 +9,7 @@ use num_bigint::BigInt;
 use num_traits::Num;
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
 use std::str::FromStr;
 use unic_emoji_char::is_emoji_presentation;
 use unicode_xid::UnicodeXID;
@@ -56,13 +57,29 @@ pub struct Lexer<T: Iterator<Item = char>> {
     location: Location,
 }
 
-#[derive(Debug)]
-pub enum LexicalError {
+#[derive(Debug, PartialEq)]
+pub struct LexicalError {
+    pub error: LexicalErrorType,
+    pub location: Location,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LexicalErrorType {
     StringError,
     NestingError,
     UnrecognizedToken { tok: char },
 }
 
+impl fmt::Display for LexicalErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexicalErrorType::StringError => write!(f, "invalid string literal"),
+            LexicalErrorType::NestingError => write!(f, "unmatched bracket"),
+            LexicalErrorType::UnrecognizedToken { tok } => write!(f, "invalid character '{}'", tok),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Location {
     row: usize,
@@ -95,6 +112,8 @@ pub fn get_keywords() -> HashMap<String, Tok> {
     keywords.insert(String::from("and"), Tok::And);
     keywords.insert(String::from("as"), Tok::As);
     keywords.insert(String::from("assert"), Tok::Assert);
+    keywords.insert(String::from("async"), Tok::Async);
+    keywords.insert(String::from("await"), Tok::Await);
     keywords.insert(String::from("break"), Tok::Break);
     keywords.insert(String::from("class"), Tok::Class);
     keywords.insert(String::from("continue"), Tok::Continue);
@@ -489,12 +508,18 @@ where
                                 string_content.push('\t');
                             }
                             Some('v') => string_content.push('\x0b'),
+                            Some(o @ '0'..='7') => {
+                                string_content.push(self.octal_escape(o, is_bytes)?)
+                            }
+                            Some('x') => string_content.push(self.hex_escape(2)?),
+                            Some('u') if !is_bytes => string_content.push(self.hex_escape(4)?),
+                            Some('U') if !is_bytes => string_content.push(self.hex_escape(8)?),
                             Some(c) => {
                                 string_content.push('\\');
                                 string_content.push(c);
                             }
                             None => {
-                                return Err(LexicalError::StringError);
+                                return Err(self.error(LexicalErrorType::StringError));
                             }
                         }
                     }
@@ -515,9 +540,13 @@ where
                             break;
                         }
                     } else {
+                        if is_bytes && !c.is_ascii() {
+                            // bytes can only contain ASCII literal characters
+                            return Err(self.error(LexicalErrorType::StringError));
+                        }
                         if c == '\n' {
                             if !triple_quoted {
-                                return Err(LexicalError::StringError);
+                                return Err(self.error(LexicalErrorType::StringError));
                             }
                             self.new_line();
                         }
@@ -525,15 +554,16 @@ where
                     }
                 }
                 None => {
-                    return Err(LexicalError::StringError);
+                    return Err(self.error(LexicalErrorType::StringError));
                 }
             }
         }
         let end_pos = self.get_pos();
 
         let tok = if is_bytes {
+            // Every char is either ASCII or the result of an escape below 256
             Tok::Bytes {
-                value: string_content.as_bytes().to_vec(),
+                value: string_content.chars().map(|c| c as u8).collect(),
             }
         } else {
             Tok::String {
@@ -545,6 +575,38 @@ where
         Ok((start_pos, tok, end_pos))
     }
 
+    /// Lex the digits of a `\x`, `\u` or `\U` escape sequence.
+    fn hex_escape(&mut self, digits: usize) -> Result<char, LexicalError> {
+        let mut code_point = 0;
+        for _ in 0..digits {
+            match self.next_char().and_then(|c| c.to_digit(16)) {
+                Some(digit) => code_point = code_point * 16 + digit,
+                None => return Err(self.error(LexicalErrorType::StringError)),
+            }
+        }
+        std::char::from_u32(code_point).ok_or_else(|| self.error(LexicalErrorType::StringError))
+    }
+
+    /// Lex an octal escape sequence of up to three digits, the first of
+    /// which has already been consumed.
+    fn octal_escape(&mut self, first: char, is_bytes: bool) -> Result<char, LexicalError> {
+        let mut code_point = first.to_digit(8).unwrap();
+        for _ in 1..3 {
+            match self.chr0.and_then(|c| c.to_digit(8)) {
+                Some(digit) => {
+                    self.next_char();
+                    code_point = code_point * 8 + digit;
+                }
+                None => break,
+            }
+        }
+        if is_bytes {
+            // Like CPython, values above 0o377 wrap around in bytes
+            code_point &= 0xff;
+        }
+        std::char::from_u32(code_point).ok_or_else(|| self.error(LexicalErrorType::StringError))
+    }
+
     fn is_identifier_start(&self, c: char) -> bool {
         match c {
             '_' => true,
@@ -598,6 +660,14 @@ where
         self.location.clone()
     }
 
+    /// An error detected at the current position.
+    fn error(&self, error: LexicalErrorType) -> LexicalError {
+        LexicalError {
+            error,
+            location: self.get_pos(),
+        }
+    }
+
     fn new_line(&mut self) {
         self.location.row += 1;
         self.location.column = 1;
@@ -815,7 +885,11 @@ where
                                         }
                                         _ => {
                                             let tok_end = self.get_pos();
-                                            return Some(Ok((tok_start, Tok::DoubleSlash, tok_end)));
+                                            return Some(Ok((
+                                                tok_start,
+                                                Tok::DoubleSlash,
+                                                tok_end,
+                                            )));
                                         }
                                     }
                                 }
@@ -913,7 +987,10 @@ where
                                 let tok_end = self.get_pos();
                                 return Some(Ok((tok_start, Tok::NotEqual, tok_end)));
                             } else {
-                                return Some(Err(LexicalError::UnrecognizedToken { tok: '!' }));
+                                return Some(Err(LexicalError {
+                                    error: LexicalErrorType::UnrecognizedToken { tok: '!' },
+                                    location: tok_start,
+                                }));
                             }
                         }
                         '~' => {
@@ -927,7 +1004,7 @@ where
                         ')' => {
                             let result = self.eat_single_char(Tok::Rpar);
                             if self.nesting == 0 {
-                                return Some(Err(LexicalError::NestingError));
+                                return Some(Err(self.error(LexicalErrorType::NestingError)));
                             }
                             self.nesting -= 1;
                             return Some(result);
@@ -940,7 +1017,7 @@ where
                         ']' => {
                             let result = self.eat_single_char(Tok::Rsqb);
                             if self.nesting == 0 {
-                                return Some(Err(LexicalError::NestingError));
+                                return Some(Err(self.error(LexicalErrorType::NestingError)));
                             }
                             self.nesting -= 1;
                             return Some(result);
@@ -953,7 +1030,7 @@ where
                         '}' => {
                             let result = self.eat_single_char(Tok::Rbrace);
                             if self.nesting == 0 {
-                                return Some(Err(LexicalError::NestingError));
+                                return Some(Err(self.error(LexicalErrorType::NestingError)));
                             }
                             self.nesting -= 1;
                             return Some(result);
@@ -1069,8 +1146,12 @@ where
                             continue;
                         }
                         _ => {
+                            let tok_start = self.get_pos();
                             let c = self.next_char();
-                            return Some(Err(LexicalError::UnrecognizedToken { tok: c.unwrap() }));
+                            return Some(Err(LexicalError {
+                                error: LexicalErrorType::UnrecognizedToken { tok: c.unwrap() },
+                                location: tok_start,
+                            }));
                         } // Ignore all the rest..
                     }
                 }
@@ -1471,6 +1552,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_string_escapes() {
+        let source = String::from(r#"'\x41\101\u00e9\U0001F600\0' b'\x00\xff\377\u'"#);
+        let tokens = lex_source(&source);
+        assert_eq!(
+            tokens,
+            vec![
+                Tok::String {
+                    value: String::from("AA\u{e9}\u{1f600}\0"),
+                    is_fstring: false,
+                },
+                Tok::Bytes {
+                    value: vec![0, 255, 255, b'\\', b'u'],
+                },
+            ]
+        );
+    }
+
     macro_rules! test_string_continuation {
         ($($name:ident: $eol:expr,)*) => {
         $(
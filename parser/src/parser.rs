@@ -337,6 +337,7 @@ mod tests {
                         ast::LocatedStatement {
                             location: ast::Location::new(2, 2),
                             node: ast::Statement::FunctionDef {
+                                is_async: false,
                                 name: String::from("__init__"),
                                 args: ast::Parameters {
                                     args: vec![ast::Parameter {
@@ -360,6 +361,7 @@ mod tests {
                         ast::LocatedStatement {
                             location: ast::Location::new(4, 2),
                             node: ast::Statement::FunctionDef {
+                                is_async: false,
                                 name: String::from("method_with_default"),
                                 args: ast::Parameters {
                                     args: vec![
@@ -410,6 +412,7 @@ mod tests {
                     }
                 }),
                 generators: vec![ast::Comprehension {
+                    is_async: false,
                     target: ast::Expression::Identifier {
                         name: "y".to_string()
                     },
@@ -436,6 +439,7 @@ mod tests {
                 }),
                 generators: vec![
                     ast::Comprehension {
+                        is_async: false,
                         target: ast::Expression::Tuple {
                             elements: vec![
                                 ast::Expression::Identifier {
@@ -452,6 +456,7 @@ mod tests {
                         ifs: vec![],
                     },
                     ast::Comprehension {
+                        is_async: false,
                         target: ast::Expression::Identifier {
                             name: "a".to_string()
                         },
@@ -487,4 +492,73 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_parse_async_function() {
+        let source = String::from("async def f():\n    async with a as b:\n        await c\n");
+        let parse_ast = parse_statement(&source).unwrap();
+        assert_eq!(
+            parse_ast,
+            ast::LocatedStatement {
+                location: ast::Location::new(1, 1),
+                node: ast::Statement::FunctionDef {
+                    is_async: true,
+                    name: String::from("f"),
+                    args: ast::Parameters::default(),
+                    body: vec![ast::LocatedStatement {
+                        location: ast::Location::new(2, 5),
+                        node: ast::Statement::With {
+                            is_async: true,
+                            items: vec![ast::WithItem {
+                                context_expr: ast::Expression::Identifier {
+                                    name: String::from("a"),
+                                },
+                                optional_vars: Some(ast::Expression::Identifier {
+                                    name: String::from("b"),
+                                }),
+                            }],
+                            body: vec![ast::LocatedStatement {
+                                location: ast::Location::new(3, 9),
+                                node: ast::Statement::Expression {
+                                    expression: ast::Expression::Await {
+                                        value: Box::new(ast::Expression::Identifier {
+                                            name: String::from("c"),
+                                        }),
+                                    },
+                                },
+                            }],
+                        },
+                    }],
+                    decorator_list: vec![],
+                    returns: None,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_async_comprehension() {
+        let source = String::from("[x async for x in y]");
+        let parse_ast = parse_expression(&source).unwrap();
+        assert_eq!(
+            parse_ast,
+            ast::Expression::Comprehension {
+                kind: Box::new(ast::ComprehensionKind::List {
+                    element: ast::Expression::Identifier {
+                        name: "x".to_string()
+                    }
+                }),
+                generators: vec![ast::Comprehension {
+                    is_async: true,
+                    target: ast::Expression::Identifier {
+                        name: "x".to_string()
+                    },
+                    iter: ast::Expression::Identifier {
+                        name: "y".to_string()
+                    },
+                    ifs: vec![],
+                }],
+            }
+        );
+    }
 }
@@ -1,20 +1,32 @@
 #[macro_use]
 extern crate clap;
 extern crate env_logger;
+#[cfg(unix)]
+extern crate libc;
 #[macro_use]
 extern crate log;
 extern crate rustpython_parser;
 extern crate rustpython_vm;
 extern crate rustyline;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use num_traits::ToPrimitive;
 use rustpython_parser::error::ParseError;
 use rustpython_vm::{
-    compile, error::CompileError, frame::Scope, import, obj::objstr, print_exception,
-    pyobject::PyResult, util, VirtualMachine,
+    archive::{self, Archive},
+    compile,
+    error::{CompileError, CompileErrorType},
+    frame::Scope,
+    gc, import,
+    obj::{objint, objstr, objtype},
+    print_exception,
+    pyobject::{DictProtocol, IdProtocol, PyObjectRef, PyResult},
+    trigger_keyboard_interrupt, util, VirtualMachine,
 };
 use rustyline::{error::ReadlineError, Editor};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::thread;
 
 fn main() {
     env_logger::init();
@@ -41,11 +53,72 @@ fn main() {
                 .takes_value(true)
                 .help("run library module as script"),
         )
+        .arg(
+            Arg::with_name("optimize")
+                .short("O")
+                .multiple(true)
+                .help("remove assert statements and __debug__-dependent code; given twice, also remove docstrings"),
+        )
+        .arg(
+            Arg::with_name("no-peephole")
+                .long("no-peephole")
+                .help("don't optimize the compiled bytecode, to debug the compiler"),
+        )
         .arg(Arg::from_usage("[pyargs] 'args for python'").multiple(true))
+        .subcommand(
+            SubCommand::with_name("compileall")
+                .about("compile all modules below a directory into an archive, which can be run instead of the sources")
+                .arg(Arg::with_name("dir").required(true))
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("the archive to write"),
+                ),
+        )
         .get_matches();
 
+    // Each Python call nests several native calls, so run the interpreter on
+    // a thread with enough stack to reach the default recursion limit. The VM
+    // raises RecursionError by itself if the native stack runs low first.
+    let interpreter = thread::Builder::new()
+        .name("interpreter".to_string())
+        .stack_size(INTERPRETER_STACK_SIZE)
+        .spawn(move || run(&matches))
+        .expect("Failed to start the interpreter thread");
+    let exit_code = interpreter.join().unwrap_or(1);
+    std::process::exit(exit_code);
+}
+
+/// Native stack size of the thread running the interpreter.
+const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// The level given by `-O` flags, or else by the `PYTHONOPTIMIZE` variable.
+fn optimize_level(matches: &ArgMatches) -> u8 {
+    let level = match matches.occurrences_of("optimize") {
+        0 => match std::env::var("PYTHONOPTIMIZE") {
+            Ok(ref value) if value.is_empty() => 0,
+            Ok(value) => value.parse().unwrap_or(1),
+            Err(_) => 0,
+        },
+        level => level,
+    };
+    std::cmp::min(level, 2) as u8
+}
+
+fn run(matches: &ArgMatches) -> i32 {
     // Construct vm:
     let vm = VirtualMachine::new();
+    let mut compile_options = vm.compile_options.get();
+    compile_options.peephole = !matches.is_present("no-peephole");
+    compile_options.optimize = optimize_level(matches);
+    vm.compile_options.set(compile_options);
+    install_interrupt_handler();
+
+    if let Some(matches) = matches.subcommand_matches("compileall") {
+        return run_compileall(&vm, matches);
+    }
 
     // Figure out if a -c option was given:
     let result = if let Some(command) = matches.value_of("c") {
@@ -61,24 +134,65 @@ fn main() {
     };
 
     // See if any exception leaked out:
-    handle_exception(&vm, result);
+    let exit_code = handle_exception(&vm, result);
+    gc::run_finalizers(&vm);
+    vm.run_atexit_funcs();
+    exit_code
+}
+
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    extern "C" fn handle_sigint(_signum: libc::c_int) {
+        trigger_keyboard_interrupt();
+    }
+
+    unsafe {
+        let handler: extern "C" fn(libc::c_int) = handle_sigint;
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
 }
 
+#[cfg(not(unix))]
+fn install_interrupt_handler() {}
+
 fn _run_string(vm: &VirtualMachine, source: &str, source_path: String) -> PyResult {
-    let code_obj =
-        compile::compile(vm, source, &compile::Mode::Exec, source_path).map_err(|err| {
-            let syntax_error = vm.context().exceptions.syntax_error.clone();
-            vm.new_exception(syntax_error, err.to_string())
-        })?;
+    let code_obj = compile::compile(vm, source, &compile::Mode::Exec, source_path)
+        .map_err(|err| vm.new_syntax_error(&err))?;
     // trace!("Code object: {:?}", code_obj.borrow());
     let vars = vm.ctx.new_scope(); // Keep track of local variables
+    vars.globals.set_item(&vm.ctx, "__doc__", vm.get_none());
     vm.run_code_obj(code_obj, vars)
 }
 
-fn handle_exception(vm: &VirtualMachine, result: PyResult) {
-    if let Err(err) = result {
+/// Determine the exit status of the interpreter, printing any exception
+/// that leaked out. `SystemExit` ends the process without a traceback.
+fn handle_exception(vm: &VirtualMachine, result: PyResult) -> i32 {
+    let err = match result {
+        Ok(_) => return 0,
+        Err(err) => err,
+    };
+    if objtype::isinstance(&err, &vm.ctx.exceptions.system_exit) {
+        let code = vm
+            .get_attribute(err, "code")
+            .unwrap_or_else(|_| vm.get_none());
+        if code.is(&vm.get_none()) {
+            0
+        } else if objtype::isinstance(&code, &vm.ctx.int_type()) {
+            objint::get_value(&code).to_i32().unwrap_or(-1)
+        } else {
+            match vm.to_pystr(&code) {
+                Ok(msg) => eprintln!("{}", msg),
+                Err(_) => eprintln!("<exception str() failed>"),
+            }
+            1
+        }
+    } else if objtype::isinstance(&err, &vm.ctx.exceptions.keyboard_interrupt) {
         print_exception(vm, &err);
-        std::process::exit(1);
+        // The status a shell reports for a process killed by SIGINT:
+        130
+    } else {
+        print_exception(vm, &err);
+        1
     }
 }
 
@@ -96,10 +210,79 @@ fn run_module(vm: &VirtualMachine, module: &str) -> PyResult {
     import::import_module(vm, current_path, module)
 }
 
+fn run_compileall(vm: &VirtualMachine, matches: &ArgMatches) -> i32 {
+    let dir = Path::new(matches.value_of("dir").unwrap());
+    let output = Path::new(matches.value_of("output").unwrap());
+    match Archive::compile_dir(vm, dir) {
+        Ok(Ok(archive)) => match fs::write(output, archive.to_bytes()) {
+            Ok(()) => {
+                println!(
+                    "Compiled {} modules into {}",
+                    archive.len(),
+                    output.display()
+                );
+                0
+            }
+            Err(err) => {
+                eprintln!("Failed writing {}: {}", output.display(), err);
+                1
+            }
+        },
+        Ok(Err(errors)) => {
+            for err in errors {
+                eprintln!(
+                    "{}:{}:{}: SyntaxError: {}",
+                    err.source_path.as_ref().map_or("<unknown>", String::as_str),
+                    err.location.get_row(),
+                    err.location.get_column(),
+                    err
+                );
+            }
+            1
+        }
+        Err(err) => {
+            eprintln!("Failed reading {}: {}", dir.display(), err);
+            1
+        }
+    }
+}
+
+/// Run the `__main__` module of an archive written by `compileall`. Like a
+/// zip application, the archive is put first on `sys.path`.
+fn run_archive(vm: &VirtualMachine, archive_path: &Path) -> PyResult {
+    debug!("Running archive {}", archive_path.display());
+    let import_error = vm.ctx.exceptions.import_error.clone();
+    let archive = Archive::load(archive_path).map_err(|err| {
+        vm.new_exception(
+            import_error.clone(),
+            format!("can't load archive '{}': {}", archive_path.display(), err),
+        )
+    })?;
+    let code = archive.get("__main__").cloned().ok_or_else(|| {
+        vm.new_exception(
+            import_error,
+            format!(
+                "can't find '__main__' module in '{}'",
+                archive_path.display()
+            ),
+        )
+    })?;
+    vm.archives
+        .borrow_mut()
+        .insert(archive_path.to_path_buf(), archive);
+    let sys_path = vm.get_attribute(vm.sys_module.clone(), "path")?;
+    let path_str = vm.new_str(archive_path.to_str().unwrap().to_string());
+    vm.call_method(&sys_path, "insert", vec![vm.new_int(0), path_str])?;
+    vm.run_code_obj(vm.ctx.new_code_object(code), vm.ctx.new_scope())
+}
+
 fn run_script(vm: &VirtualMachine, script_file: &str) -> PyResult {
     debug!("Running file {}", script_file);
-    // Parse an ast from it:
     let file_path = Path::new(script_file);
+    if archive::is_archive(file_path) {
+        return run_archive(vm, file_path);
+    }
+    // Parse an ast from it:
     match util::read_file(file_path) {
         Ok(source) => _run_string(vm, &source, file_path.to_str().unwrap().to_string()),
         Err(err) => {
@@ -109,22 +292,24 @@ fn run_script(vm: &VirtualMachine, script_file: &str) -> PyResult {
     }
 }
 
-fn shell_exec(vm: &VirtualMachine, source: &str, scope: Scope) -> Result<(), CompileError> {
+enum ShellExecResult {
+    Ok,
+    PyErr(PyObjectRef),
+    Continue,
+}
+
+fn shell_exec(vm: &VirtualMachine, source: &str, scope: Scope) -> ShellExecResult {
     match compile::compile(vm, source, &compile::Mode::Single, "<stdin>".to_string()) {
-        Ok(code) => {
-            if let Err(err) = vm.run_code_obj(code, scope) {
-                print_exception(vm, &err);
-            }
-            Ok(())
-        }
+        Ok(code) => match vm.run_code_obj(code, scope) {
+            Ok(_) => ShellExecResult::Ok,
+            Err(err) => ShellExecResult::PyErr(err),
+        },
         // Don't inject syntax errors for line continuation
-        Err(err @ CompileError::Parse(ParseError::EOF(_))) => Err(err),
-        Err(err) => {
-            let syntax_error = vm.context().exceptions.syntax_error.clone();
-            let exc = vm.new_exception(syntax_error, format!("{}", err));
-            print_exception(vm, &exc);
-            Err(err)
-        }
+        Err(CompileError {
+            error: CompileErrorType::Parse(ParseError::EOF(_)),
+            ..
+        }) => ShellExecResult::Continue,
+        Err(err) => ShellExecResult::PyErr(vm.new_syntax_error(&err)),
     }
 }
 
@@ -192,18 +377,28 @@ fn run_shell(vm: &VirtualMachine) -> PyResult {
                 }
 
                 match shell_exec(vm, &input, vars.clone()) {
-                    Err(CompileError::Parse(ParseError::EOF(_))) => {
+                    ShellExecResult::Continue => {
                         continuing = true;
                         continue;
                     }
-                    _ => {
+                    ShellExecResult::Ok => {
+                        input = String::new();
+                    }
+                    ShellExecResult::PyErr(err) => {
                         input = String::new();
+                        if objtype::isinstance(&err, &vm.ctx.exceptions.system_exit) {
+                            repl.save_history(repl_history_path_str).unwrap();
+                            return Err(err);
+                        }
+                        print_exception(vm, &err);
                     }
                 }
             }
             Err(ReadlineError::Interrupted) => {
-                // TODO: Raise a real KeyboardInterrupt exception
-                println!("^C");
+                let keyboard_interrupt = vm.ctx.exceptions.keyboard_interrupt.clone();
+                let exc = vm.new_empty_exception(keyboard_interrupt)?;
+                print_exception(vm, &exc);
+                input = String::new();
                 continuing = false;
                 continue;
             }
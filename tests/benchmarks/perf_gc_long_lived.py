@@ -0,0 +1,8 @@
+# Many long-lived containers shouldn't slow down the collections started by
+# allocating short-lived ones.
+long_lived = [[i] for i in range(100000)]
+
+j = 0
+while j < 200000:
+    short_lived = [j]
+    j += 1
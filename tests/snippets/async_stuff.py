@@ -0,0 +1,292 @@
+import types
+from testutils import assert_raises
+
+
+def run(coro):
+    """Drive a coroutine to completion, sending back whatever it yields."""
+    log = []
+    value = None
+    while True:
+        try:
+            value = coro.send(value)
+        except StopIteration as stop:
+            return stop.value, log
+        log.append(value)
+
+
+class Suspend:
+    def __init__(self, value):
+        self.value = value
+
+    def __await__(self):
+        value = yield self.value
+        return value
+
+
+async def add(a, b):
+    x = await Suspend(a)
+    y = await Suspend(b)
+    return x + y
+
+
+coro = add(1, 2)
+assert isinstance(coro, types.CoroutineType)
+result, log = run(coro)
+assert log == [1, 2]
+assert result == 3
+
+coro = add(1, 2)
+assert coro.send(None) == 1
+assert coro.send(10) == 2
+assert_raises(StopIteration, lambda: coro.send(20))
+assert_raises(RuntimeError, lambda: coro.send(None))
+
+
+async def nested():
+    a = await add(3, 4)
+    return a * 2
+
+
+coro = nested()
+assert coro.send(None) == 3
+assert coro.send(5) == 4
+try:
+    coro.send(6)
+except StopIteration as stop:
+    assert stop.value == 22
+else:
+    assert False
+
+
+# Generator-based coroutines can be awaited too:
+@types.coroutine
+def legacy():
+    x = yield 'legacy'
+    return x
+
+
+async def uses_legacy():
+    return await legacy()
+
+
+assert run(uses_legacy()) == ('legacy', ['legacy'])
+
+
+# Exceptions thrown in travel through the awaits:
+async def catcher():
+    try:
+        await Suspend('waiting')
+    except ValueError as e:
+        return 'caught ' + e.args[0]
+
+
+coro = catcher()
+assert coro.send(None) == 'waiting'
+try:
+    coro.throw(ValueError('boom'))
+except StopIteration as stop:
+    assert stop.value == 'caught boom'
+
+
+class NotAwaitable:
+    pass
+
+
+async def bad_await():
+    await NotAwaitable()
+
+assert_raises(TypeError, lambda: bad_await().send(None))
+
+
+# async with
+events = []
+
+
+class Manager:
+    def __init__(self, swallow=False):
+        self.swallow = swallow
+
+    async def __aenter__(self):
+        events.append('enter')
+        await Suspend('entering')
+        return self
+
+    async def __aexit__(self, exc_type, exc, tb):
+        events.append(('exit', exc_type))
+        await Suspend('exiting')
+        return self.swallow
+
+
+async def use_manager(swallow, fail):
+    async with Manager(swallow) as m:
+        events.append('body')
+        if fail:
+            raise KeyError('x')
+    return 'done'
+
+result, log = run(use_manager(False, False))
+assert result == 'done'
+assert log == ['entering', 'exiting']
+assert events == ['enter', 'body', ('exit', None)]
+
+events = []
+result, log = run(use_manager(True, True))
+assert result == 'done'
+assert events == ['enter', 'body', ('exit', KeyError)]
+
+events = []
+assert_raises(KeyError, lambda: run(use_manager(False, True)))
+assert events == ['enter', 'body', ('exit', KeyError)]
+
+
+async def return_in_with():
+    async with Manager():
+        return 'early'
+
+events = []
+assert run(return_in_with()) == ('early', ['entering', 'exiting'])
+
+
+# async for
+class Counter:
+    def __init__(self, n):
+        self.n = n
+        self.i = 0
+
+    def __aiter__(self):
+        return self
+
+    async def __anext__(self):
+        if self.i >= self.n:
+            raise StopAsyncIteration
+        self.i += 1
+        await Suspend(('next', self.i))
+        return self.i
+
+
+async def total(n):
+    s = 0
+    async for i in Counter(n):
+        if i == 4:
+            continue
+        s += i
+    else:
+        s += 100
+    return s
+
+result, log = run(total(5))
+assert result == 1 + 2 + 3 + 5 + 100
+assert log == [('next', i) for i in range(1, 6)]
+
+
+async def breaking():
+    async for i in Counter(10):
+        if i == 2:
+            break
+    else:
+        return 'no break'
+    return i
+
+assert run(breaking())[0] == 2
+
+
+async def not_async_iterable():
+    async for i in [1, 2]:
+        pass
+
+assert_raises(TypeError, lambda: run(not_async_iterable()))
+
+
+# Async generators
+async def agen(n):
+    for i in range(n):
+        await Suspend(('tick', i))
+        yield i * 10
+
+
+ag = agen(3)
+assert isinstance(ag, types.AsyncGeneratorType)
+assert ag.__aiter__() is ag
+
+
+async def collect():
+    out = []
+    async for x in agen(3):
+        out.append(x)
+    return out
+
+result, log = run(collect())
+assert result == [0, 10, 20]
+assert log == [('tick', 0), ('tick', 1), ('tick', 2)]
+
+
+async def echo():
+    received = yield 'first'
+    yield received
+
+
+async def use_asend():
+    g = echo()
+    first = await g.__anext__()
+    second = await g.asend('hello')
+    try:
+        await g.__anext__()
+    except StopAsyncIteration:
+        return first, second
+
+assert run(use_asend())[0] == ('first', 'hello')
+
+
+closed = []
+
+
+async def cleanup_gen():
+    try:
+        yield 1
+        yield 2
+    finally:
+        closed.append(True)
+
+
+async def use_aclose():
+    g = cleanup_gen()
+    assert await g.__anext__() == 1
+    await g.aclose()
+    return closed
+
+assert run(use_aclose())[0] == [True]
+
+
+async def throwing_gen():
+    try:
+        yield 1
+    except ValueError:
+        yield 'handled'
+
+
+async def use_athrow():
+    g = throwing_gen()
+    await g.__anext__()
+    return await g.athrow(ValueError)
+
+assert run(use_athrow())[0] == 'handled'
+
+
+# Async comprehensions
+async def comprehensions():
+    a = [x async for x in agen(3)]
+    b = {x async for x in agen(2)}
+    c = [await add(x, 1) for x in range(2)]
+    g = (x + 1 async for x in agen(2))
+    d = [y async for y in g]
+    return a, b, c, d
+
+result, log = run(comprehensions())
+assert result == ([0, 10, 20], {0, 10}, [1, 2], [1, 11])
+
+
+# Syntax errors
+for source in ['await x', 'async with x: pass', 'async for x in y: pass',
+               '[x async for x in y]', 'def f():\n    await x\n',
+               'async def f():\n    yield from x\n']:
+    assert_raises(SyntaxError, lambda: compile(source, 'test', 'exec'))
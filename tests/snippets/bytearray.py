@@ -1,7 +1,13 @@
-#__getitem__ not implemented yet
-#a = bytearray(b'abc')
-#assert a[0] == b'a'
-#assert a[1] == b'b'
+from testutils import assert_raises
+
+a = bytearray(b'abc')
+assert a[0] == 97
+assert a[-1] == 99
+assert a[1:] == bytearray(b'bc')
+assert type(a[1:]) is bytearray
+assert list(a) == [97, 98, 99]
+assert repr(a) == "bytearray(b'abc')"
+assert repr(bytearray([0, 255])) == "bytearray(b'\\x00\\xff')"
 
 assert len(bytearray([1,2,3])) == 3
 
@@ -65,3 +71,100 @@ except IndexError:
     pass
 else:
     assert False
+
+# the methods shared with bytes return bytearrays
+b = bytearray(b'a,b')
+assert b.split(b',') == [bytearray(b'a'), bytearray(b'b')]
+assert type(b.split(b',')[0]) is bytearray
+assert type(b.replace(b',', b';')) is bytearray
+assert b + b'!' == bytearray(b'a,b!')
+assert b * 2 == bytearray(b'a,ba,b')
+assert b.find(b',') == 1
+assert b.hex() == '612c62'
+assert bytearray.fromhex('6162') == bytearray(b'ab')
+assert bytearray(b'%d') % 5 == bytearray(b'5')
+assert b == b'a,b'
+assert b',' in b
+
+# mutation
+b = bytearray(b'abcdef')
+b[0] = 65
+assert b == bytearray(b'Abcdef')
+b[-1] = 70
+assert b == bytearray(b'AbcdeF')
+b[1:3] = b'XYZ'
+assert b == bytearray(b'AXYZdeF')
+b[::2] = [49, 50, 51, 52]
+assert b == bytearray(b'1X2Z3e4')
+b[2:2] = bytearray(b'__')
+assert b == bytearray(b'1X__2Z3e4')
+b[:] = b[::-1]
+assert b == bytearray(b'4e3Z2__X1')
+del b[0]
+del b[::2]
+assert b == bytearray(b'32_1')
+del b[1:3]
+assert b == bytearray(b'31')
+assert_raises(IndexError, lambda: b.__setitem__(5, 0))
+assert_raises(ValueError, lambda: b.__setitem__(0, 256))
+assert_raises(TypeError, lambda: b.__setitem__(slice(0, 1), 5))
+
+def assign_extended_slice():
+    b[::2] = b'xyz'
+assert_raises(ValueError, assign_extended_slice)
+
+b = bytearray()
+b.append(33)
+b.extend(b'??')
+b.extend([48, 49])
+b.insert(0, 64)
+b.insert(-1, 35)
+b.insert(100, 36)
+assert b == bytearray(b'@!??0#1$')
+assert_raises(TypeError, lambda: b.append(b'x'))
+assert_raises(TypeError, lambda: b.extend(1))
+assert b.pop() == 36
+assert b.pop(0) == 64
+assert b.pop(-2) == 35
+assert_raises(IndexError, lambda: b.pop(10))
+b.remove(63)
+assert b == bytearray(b'!?01')
+assert_raises(ValueError, lambda: b.remove(120))
+
+c = b
+c += b'++'
+assert c is b
+assert b == bytearray(b'!?01++')
+b.reverse()
+assert b == bytearray(b'++10?!')
+d = b.copy()
+d.clear()
+assert b == bytearray(b'++10?!')
+
+# methods shared with bytes give bytearrays
+a = bytearray(b'hello world')
+assert a.title() == bytearray(b'Hello World')
+assert type(a.capitalize()) is bytearray
+assert type(a.swapcase()) is bytearray
+assert a.center(13, b'*') == bytearray(b'*hello world*')
+assert type(a.ljust(2)) is bytearray
+assert a.rjust(12) == bytearray(b' hello world')
+assert bytearray(b'5').zfill(3) == bytearray(b'005')
+assert bytearray(b'a\tb').expandtabs(2) == bytearray(b'a b')
+assert bytearray(b'a\nb').splitlines() == [bytearray(b'a'), bytearray(b'b')]
+assert a.partition(b' ') == (bytearray(b'hello'), bytearray(b' '), bytearray(b'world'))
+assert a.rpartition(b'x') == (bytearray(b''), bytearray(b''), bytearray(b'hello world'))
+assert type(bytearray.maketrans(b'a', b'b')) is bytes
+assert a.translate(bytearray.maketrans(b'lo', b'01')) == bytearray(b'he001 w1r0d')
+assert type(a.translate(None)) is bytearray
+assert not bytearray(b'\xaa').isalpha()
+assert bytearray(b'%s!') % b'x' == bytearray(b'x!')
+assert bytearray(b'x').__rmod__(b'%s') is NotImplemented
+
+b = bytearray(b'ab')
+c = b
+c *= 3
+assert c is b
+assert b == bytearray(b'ababab')
+b *= 0
+assert b == bytearray()
@@ -0,0 +1,154 @@
+from testutils import assert_raises
+
+# construction and repr
+assert bytes(3) == b'\x00\x00\x00'
+assert bytes(bytearray(b'ab')) == b'ab'
+assert bytes(range(3)) == b'\x00\x01\x02'
+assert_raises(ValueError, lambda: bytes([256]))
+assert_raises(ValueError, lambda: bytes(-1))
+assert_raises(TypeError, lambda: bytes(['a']))
+assert repr(b'\x00\xffab\n') == "b'\\x00\\xffab\\n'"
+assert repr(b"it's") == 'b"it\'s"'
+assert repr(b'\'"') == 'b\'\\\'"\''
+assert b'\x41\101\0' == bytes([65, 65, 0])
+
+# sequence operations
+a = b'hello world'
+assert a[0] == 104
+assert a[-1] == 100
+assert a[1:4] == b'ell'
+assert a[::-2] == b'drwolh'
+assert_raises(IndexError, lambda: a[11])
+assert_raises(TypeError, lambda: a['x'])
+assert a + b'!' == b'hello world!'
+assert type(a + bytearray(b'!')) is bytes
+assert_raises(TypeError, lambda: a + 'x')
+assert b'ab' * 2 == b'abab'
+assert 2 * b'ab' == b'abab'
+assert b'ab' * -1 == b''
+assert b'lo' in a
+assert 111 in a
+assert b'xyz' not in a
+assert_raises(ValueError, lambda: 300 in a)
+assert_raises(TypeError, lambda: 'o' in a)
+
+# comparison
+assert b'a' == bytearray(b'a')
+assert b'a' != b'b'
+assert b'a' < b'b'
+assert b'ab' > b'a'
+assert not b'a' == 'a'
+
+# searching
+assert a.find(b'o') == 4
+assert a.find(111, 5) == 7
+assert a.find(b'o', 5, 7) == -1
+assert a.rfind(b'o') == 7
+assert a.find(b'') == 0
+assert a.index(b'w') == 6
+assert a.rindex(b'l') == 9
+assert_raises(ValueError, lambda: a.index(b'x'))
+assert a.count(b'l') == 3
+assert a.count(b'l', 4) == 1
+assert a.count(b'') == 12
+assert a.startswith(b'he')
+assert a.startswith(b'wo', 6)
+assert a.endswith((b'x', b'ld'))
+assert not a.endswith(b'wo', 0, 7)
+assert_raises(TypeError, lambda: a.startswith('h'))
+
+# splitting and joining
+assert a.split() == [b'hello', b'world']
+assert a.split(b'o') == [b'hell', b' w', b'rld']
+assert a.split(b'o', 1) == [b'hell', b' world']
+assert a.rsplit(b'o', 1) == [b'hello w', b'rld']
+assert b'  a  b '.split(None, 1) == [b'a', b'b ']
+assert b' a b  '.rsplit(maxsplit=1) == [b' a', b'b']
+assert b'\x0ba\x0cb\x0b'.split() == [b'a', b'b']
+assert_raises(ValueError, lambda: a.split(b''))
+assert b'-'.join([b'a', bytearray(b'b'), b'c']) == b'a-b-c'
+assert b''.join([]) == b''
+assert_raises(TypeError, lambda: b'-'.join(['a']))
+
+# stripping and replacing
+assert b'  xy \t\n'.strip() == b'xy'
+assert b'xxaxx'.strip(b'x') == b'a'
+assert b'xxaxx'.lstrip(b'x') == b'axx'
+assert b'xxaxx'.rstrip(b'x') == b'xxa'
+assert a.replace(b'l', b'L') == b'heLLo worLd'
+assert a.replace(b'l', b'L', 2) == b'heLLo world'
+assert b'ab'.replace(b'', b'-') == b'-a-b-'
+
+# case
+assert b'hELLO wORLD 1a\xe9'.upper() == b'HELLO WORLD 1A\xe9'
+assert b'hELLO wORLD 1A\xc9'.lower() == b'hello world 1a\xc9'
+assert b"they're bill's 2nd".title() == b"They'Re Bill'S 2Nd"
+assert b'hELLO wORLD'.capitalize() == b'Hello world'
+assert b'aBc\xe9'.swapcase() == b'AbC\xe9'
+assert b'a1'.isalnum() and not b'a 1'.isalnum()
+assert b'ab'.isalpha() and not b'\xaa'.isalpha()
+assert b'019'.isdigit() and not b''.isdigit()
+assert b' \t\n\r\x0b\x0c'.isspace() and not b'\x1c'.isspace()
+assert b'ab1 '.islower() and not b'1'.islower()
+assert b'AB1 '.isupper() and not b'aB'.isupper()
+assert b'Hello World'.istitle() and b'A1B'.istitle()
+assert not b'Hello world'.istitle() and not b'HEllo'.istitle() and not b'1'.istitle()
+
+# padding
+assert b'ab'.center(5) == b'  ab '
+assert b'ab'.center(6, b'*') == b'**ab**'
+assert b'abc'.center(6, bytearray(b'*')) == b'*abc**'
+assert b'ab'.ljust(4, b'-') == b'ab--'
+assert b'ab'.rjust(4) == b'  ab'
+assert b'abc'.rjust(2) == b'abc'
+assert_raises(TypeError, lambda: b'a'.center(3, b'ab'))
+assert_raises(TypeError, lambda: b'a'.ljust(3, '-'))
+assert b'-42'.zfill(6) == b'-00042'
+assert b'+'.zfill(3) == b'+00'
+assert b'7'.zfill(-1) == b'7'
+assert b'a\tb\r\tc'.expandtabs() == b'a       b\r        c'
+assert b'a\tb'.expandtabs(tabsize=3) == b'a  b'
+assert b'a\tb'.expandtabs(0) == b'ab'
+
+# partitioning
+assert b'a\nb\r\nc\rd\x0be\x1cf'.splitlines() == [b'a', b'b', b'c', b'd\x0be\x1cf']
+assert b'a\nb\r\n'.splitlines(True) == [b'a\n', b'b\r\n']
+assert b'a\nb'.splitlines(keepends=True) == [b'a\n', b'b']
+assert b''.splitlines() == []
+assert b'a,b,c'.partition(b',') == (b'a', b',', b'b,c')
+assert b'a,b,c'.rpartition(bytearray(b',')) == (b'a,b', b',', b'c')
+assert b'ab'.partition(b',') == (b'ab', b'', b'')
+assert b'ab'.rpartition(b',') == (b'', b'', b'ab')
+assert_raises(ValueError, lambda: b'ab'.partition(b''))
+
+# translation
+table = bytes.maketrans(b'ab', b'xy')
+assert type(table) is bytes and len(table) == 256
+assert table[97] == 120 and table[99] == 99
+assert b'abcabc'.translate(table) == b'xycxyc'
+assert b'abcabc'.translate(None, b'b') == b'acac'
+assert b'abc'.translate(table, delete=b'c') == b'xy'
+assert_raises(ValueError, lambda: b'a'.translate(b'x'))
+assert_raises(ValueError, lambda: bytes.maketrans(b'a', b''))
+
+# hex
+assert b'\x0b\xde'.hex() == '0bde'
+assert bytes.fromhex('0b de') == b'\x0b\xde'
+assert bytes.fromhex('') == b''
+assert_raises(ValueError, lambda: bytes.fromhex('a'))
+assert_raises(ValueError, lambda: bytes.fromhex('zz'))
+
+# printf-style formatting
+assert b'%s=%d' % (b'key', 42) == b'key=42'
+assert b'%x %5.2f|%-3c|' % (255, 3.14159, 65) == b'ff  3.14|A  |'
+assert b'%c%b' % (b'A', bytearray(b'B')) == b'AB'
+assert b'%r %a' % (b'r', 'é') == b"b'r' '\\xe9'"
+assert b'%03d%%' % 7 == b'007%'
+assert_raises(TypeError, lambda: b'%s' % 'str')
+assert_raises(OverflowError, lambda: b'%c' % 256)
+assert_raises(TypeError, lambda: b'%c' % b'ab')
+assert_raises(TypeError, lambda: b'%d %d' % 1)
+assert_raises(ValueError, lambda: b'%y' % 1)
+assert b'x'.__rmod__(b'%s') == b'x'
+assert b'x'.__rmod__('%s') is NotImplemented
+assert b'x'.__rmod__(bytearray(b'%s')) is NotImplemented
@@ -0,0 +1,131 @@
+from testutils import assert_raises
+
+
+# Late binding: the closure sees the latest value of the variable.
+def late_binding():
+    fs = []
+    for i in range(3):
+        fs.append(lambda: i)
+    return [f() for f in fs]
+
+assert late_binding() == [2, 2, 2]
+
+
+# A variable assigned later in the enclosing function is not visible early.
+def assigned_later():
+    def inner():
+        return x
+    assert_raises(NameError, inner)
+    x = 1
+    return inner()
+
+assert assigned_later() == 1
+
+
+# Shadowing a global by a later local assignment in the enclosing function.
+x = 'global'
+
+def shadowed():
+    def inner():
+        return x
+    result = []
+    try:
+        inner()
+    except NameError:
+        result.append('unbound')
+    x = 'local'
+    result.append(inner())
+    return result
+
+assert shadowed() == ['unbound', 'local']
+
+
+# Cells are shared between all closures of the same scope.
+def counter():
+    count = 0
+    def increment():
+        nonlocal count
+        count += 1
+        return count
+    def get():
+        return count
+    return increment, get
+
+increment, get = counter()
+increment()
+increment()
+assert get() == 2
+
+
+# Arguments captured by nested functions.
+def make_adder(n):
+    return lambda x: x + n
+
+assert make_adder(3)(4) == 7
+
+
+# __closure__ and cell objects.
+add = make_adder(10)
+assert len(add.__closure__) == 1
+assert add.__closure__[0].cell_contents == 10
+assert add.__code__.co_freevars == ('n',)
+assert make_adder.__code__.co_cellvars == ('n',)
+assert late_binding.__closure__ is None
+
+
+def empty_cell():
+    def inner():
+        return y
+    cell = inner.__closure__[0]
+    assert_raises(ValueError, lambda: cell.cell_contents)
+    y = 5
+    assert cell.cell_contents == 5
+    del y
+    assert_raises(NameError, inner)
+
+empty_cell()
+
+
+# Class bodies do not create a scope for the functions defined in them,
+# but variables of an enclosing function pass through.
+def class_in_function():
+    a = 'outer a'
+    b = 'outer b'
+    class C:
+        a = 'class a'
+        def get_a(self):
+            return a
+        def get_b(self):
+            return b
+        class_b = b
+    return C
+
+C = class_in_function()
+assert C().get_a() == 'outer a'
+assert C().get_b() == 'outer b'
+assert C.a == 'class a'
+assert C.class_b == 'outer b'
+
+
+# Comprehensions close over the enclosing function.
+def comprehension_closure():
+    factor = 3
+    return [factor * i for i in range(3)]
+
+assert comprehension_closure() == [0, 3, 6]
+
+
+# super() and nested functions use the __class__ cell.
+class Base:
+    def name(self):
+        return 'base'
+
+class Derived(Base):
+    def name(self):
+        return super().name() + ' derived'
+
+    def cls(self):
+        return (lambda: __class__)()
+
+assert Derived().name() == 'base derived'
+assert Derived().cls() is Derived
@@ -0,0 +1,72 @@
+from testutils import assert_raises
+
+# Folded constant expressions give the same values as at runtime.
+one, two, three, half, s = 1, 2, 3, 0.5, 'ab'
+assert 1 + 2 * 3 == one + two * three
+assert -1 - 2 == -one - two
+assert ~5 == ~(two + three)
+assert 2 ** 10 == two ** (two * 5)
+assert 1 << 100 == one << 100
+assert -7 >> 1 == -(two * 3 + one) >> one
+assert (6 & 3, 6 | 3, 6 ^ 3) == (6 & three, 6 | three, 6 ^ three)
+assert 1.5 + 2 == one + half + two
+assert 2 * 0.5 - 1 == two * half - one
+assert -2.5 == -(two + half)
+j2 = 2j
+assert 1 + 2j == one + j2
+assert -2j == -j2
+assert 'ab' + 'cd' == s + 'cd'
+assert 'ab' * 3 == s * three
+assert (1, 'a', (2.0, None)) == (one, 'a', (two * 1.0, None))
+assert () == tuple()
+assert type(-0.0) is float
+
+# Operations which fail still fail when they are run.
+def divide():
+    return 1 // 0
+
+assert_raises(ZeroDivisionError, divide)
+assert len('a' * 10000) == 10000
+assert 2 ** 200 == two ** 200
+
+# `not` before a condition.
+def check(x):
+    if not x:
+        return 'falsy'
+    return 'truthy'
+
+assert check(0) == 'falsy'
+assert check([1]) == 'truthy'
+
+def loop(items):
+    found = []
+    while not len(found) == 3:
+        if not items:
+            break
+        found.append(items.pop())
+    else:
+        found.append('done')
+    return found
+
+assert loop([1, 2, 3, 4]) == [4, 3, 2, 'done']
+assert loop([1]) == [1]
+
+# Code after a return or raise is never run.
+def early():
+    return 1
+    raise Exception('unreachable')
+
+assert early() == 1
+
+def nested(n):
+    for i in range(n):
+        if i == 2:
+            continue
+        if i == 4:
+            break
+    else:
+        return 'exhausted'
+    return i
+
+assert nested(3) == 'exhausted'
+assert nested(10) == 4
@@ -1,3 +1,5 @@
+from testutils import assert_raises
+
 def dict_eq(d1, d2):
     return (all(k in d2 and d1[k] == d2[k] for k in d1)
             and all(k in d1 and d1[k] == d2[k] for k in d2))
@@ -34,3 +36,15 @@ res = set()
 for key in a.keys():
 	res.add(key)
 assert res == set(['a','b'])
+
+# int keys
+a = {1: 'x', 'b': 2}
+assert a[1] == 'x'
+assert a[True] == 'x'
+assert 1 in a and 2 not in a and '1' not in a
+a['\x001'] = 'y'
+assert a[1] == 'x'
+assert a.get(2, 'z') == 'z'
+del a[1]
+assert len(a) == 2
+assert_raises(KeyError, lambda: a[1])
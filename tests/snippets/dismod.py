@@ -32,3 +32,38 @@ class A(object):
 
 print("A.f\n")
 dis.dis(A.f)
+
+dis.dis(A)
+
+
+def h(a):
+    if a:
+        return a
+    return None
+
+
+instructions = list(dis.get_instructions(h))
+first = [i for i in instructions if i.argval == "a"][0]
+assert first.starts_line == h.__code__.co_firstlineno + 1
+assert first.opname == first[0]
+assert not first.is_jump_target
+assert [i.offset for i in instructions] == sorted(i.offset for i in instructions)
+assert any(i.is_jump_target for i in instructions)
+assert any(i.argval is None and i.argrepr == "None" for i in instructions)
+lines = [i.starts_line for i in instructions if i.starts_line is not None]
+assert lines[-3:] == [h.__code__.co_firstlineno + n for n in (1, 2, 3)]
+
+info = dis.code_info(h)
+assert "Name:              h" in info
+assert "Argument count:    1" in info
+assert "   0: a" in info
+
+import io
+
+output = io.StringIO()
+dis.dis(h, file=output)
+assert "return" not in output.getvalue()
+assert str(h.__code__.co_firstlineno + 1) in output.getvalue()
+output = io.StringIO()
+dis.show_code("x + 1", file=output)
+assert "Names:" in output.getvalue()
@@ -0,0 +1,53 @@
+"""Docstrings, and the code which -O and -OO remove."""
+import sys
+from testutils import assert_raises
+
+assert __doc__ == "Docstrings, and the code which -O and -OO remove."
+
+
+def documented():
+    """A function."""
+    return 1
+
+
+def undocumented():
+    return 2
+
+
+assert documented.__doc__ == "A function."
+assert documented() == 1
+assert undocumented.__doc__ is None
+
+
+class Documented:
+    """A class."""
+
+    def method(self):
+        "A method."
+
+
+class Undocumented(Documented):
+    pass
+
+
+assert Documented.__doc__ == "A class."
+assert Undocumented.__doc__ is None
+assert Documented.method.__doc__ == "A method."
+assert Documented().method.__doc__ == "A method."
+
+# Without -O, asserts run and __debug__ is set:
+assert __debug__ is True
+assert sys.flags.optimize == 0
+
+
+def fails():
+    assert False, "message"
+
+
+assert_raises(AssertionError, fails)
+
+if __debug__:
+    debug = True
+else:
+    debug = False
+assert debug
@@ -0,0 +1,169 @@
+from testutils import assert_raises
+
+# Implicit chaining sets __context__:
+try:
+    try:
+        raise ValueError('inner')
+    except ValueError:
+        raise KeyError('outer')
+except KeyError as e:
+    assert isinstance(e.__context__, ValueError)
+    assert e.__cause__ is None
+    assert not e.__suppress_context__
+
+# Explicit chaining with raise ... from:
+try:
+    try:
+        raise ValueError('inner')
+    except ValueError as inner:
+        raise KeyError('outer') from inner
+except KeyError as e:
+    assert isinstance(e.__cause__, ValueError)
+    assert isinstance(e.__context__, ValueError)
+    assert e.__suppress_context__
+
+# raise ... from None suppresses the context:
+try:
+    try:
+        raise ValueError('inner')
+    except ValueError:
+        raise KeyError('outer') from None
+except KeyError as e:
+    assert e.__cause__ is None
+    assert isinstance(e.__context__, ValueError)
+    assert e.__suppress_context__
+
+# An exception class as cause is instantiated:
+try:
+    raise KeyError('outer') from ValueError
+except KeyError as e:
+    assert isinstance(e.__cause__, ValueError)
+
+def raise_bad_cause():
+    raise KeyError('outer') from 5
+
+assert_raises(TypeError, raise_bad_cause)
+
+# Bare raise re-raises the exception being handled:
+try:
+    try:
+        raise ValueError('inner')
+    except ValueError:
+        raise
+except ValueError as e:
+    assert e.__context__ is None
+
+def reraise_without_exception():
+    raise
+
+assert_raises(RuntimeError, reraise_without_exception)
+
+# A handled exception is no longer the context of later exceptions:
+try:
+    raise ValueError('first')
+except ValueError:
+    pass
+
+try:
+    raise KeyError('second')
+except KeyError as e:
+    assert e.__context__ is None
+
+# Exceptions raised in a finally clause chain to the pending exception:
+try:
+    try:
+        raise ValueError('inner')
+    finally:
+        raise KeyError('outer')
+except KeyError as e:
+    assert isinstance(e.__context__, ValueError)
+
+# Nested handlers restore the outer exception on exit:
+try:
+    try:
+        raise ValueError('outer')
+    except ValueError:
+        try:
+            raise KeyError('inner')
+        except KeyError:
+            pass
+        raise TypeError('last')
+except TypeError as e:
+    assert isinstance(e.__context__, ValueError)
+
+# Leaving a handler with return pops the exception:
+def handled():
+    try:
+        raise ValueError('handled')
+    except ValueError:
+        return 1
+
+assert handled() == 1
+try:
+    raise KeyError('after return')
+except KeyError as e:
+    assert e.__context__ is None
+
+# Raising an exception again sets its context to the one being handled:
+try:
+    raise TypeError('first')
+except TypeError:
+    try:
+        raise ValueError('saved')
+    except ValueError as e:
+        saved = e
+assert isinstance(saved.__context__, TypeError)
+try:
+    raise KeyError('handling')
+except KeyError as handling:
+    try:
+        raise saved
+    except ValueError as e:
+        assert e.__context__ is handling
+
+# ... but a bare raise keeps it:
+try:
+    try:
+        raise ValueError('bare')
+    except ValueError:
+        try:
+            raise KeyError('other')
+        except KeyError:
+            pass
+        raise
+except ValueError as e:
+    assert e.__context__ is None
+
+# The context chain is cut rather than made into a cycle:
+try:
+    try:
+        raise ValueError('a')
+    except ValueError as e:
+        first = e
+        try:
+            raise KeyError('b')
+        except KeyError as e:
+            second = e
+            assert second.__context__ is first
+            raise first
+except ValueError as e:
+    assert e is first
+    assert e.__context__ is second
+    assert second.__context__ is None
+
+# An exception passing up through a handler in the caller keeps the
+# context it was raised with:
+def raise_in_handler():
+    try:
+        raise ValueError('inner')
+    except ValueError:
+        raise KeyError('raised')
+
+try:
+    raise TypeError('outer')
+except TypeError as outer:
+    try:
+        raise_in_handler()
+    except KeyError as e:
+        assert isinstance(e.__context__, ValueError)
+        assert e.__context__.__context__ is outer
@@ -0,0 +1,125 @@
+import errno
+
+# Every class sits at the same place in the hierarchy as in CPython:
+hierarchy = [
+    (SystemExit, BaseException),
+    (KeyboardInterrupt, BaseException),
+    (GeneratorExit, BaseException),
+    (Exception, BaseException),
+    (StopIteration, Exception),
+    (StopAsyncIteration, Exception),
+    (ArithmeticError, Exception),
+    (FloatingPointError, ArithmeticError),
+    (OverflowError, ArithmeticError),
+    (ZeroDivisionError, ArithmeticError),
+    (AssertionError, Exception),
+    (AttributeError, Exception),
+    (BufferError, Exception),
+    (EOFError, Exception),
+    (ImportError, Exception),
+    (ModuleNotFoundError, ImportError),
+    (LookupError, Exception),
+    (IndexError, LookupError),
+    (KeyError, LookupError),
+    (MemoryError, Exception),
+    (NameError, Exception),
+    (UnboundLocalError, NameError),
+    (OSError, Exception),
+    (BlockingIOError, OSError),
+    (ChildProcessError, OSError),
+    (ConnectionError, OSError),
+    (BrokenPipeError, ConnectionError),
+    (ConnectionAbortedError, ConnectionError),
+    (ConnectionRefusedError, ConnectionError),
+    (ConnectionResetError, ConnectionError),
+    (FileExistsError, OSError),
+    (FileNotFoundError, OSError),
+    (InterruptedError, OSError),
+    (IsADirectoryError, OSError),
+    (NotADirectoryError, OSError),
+    (PermissionError, OSError),
+    (ProcessLookupError, OSError),
+    (TimeoutError, OSError),
+    (ReferenceError, Exception),
+    (RuntimeError, Exception),
+    (NotImplementedError, RuntimeError),
+    (RecursionError, RuntimeError),
+    (SyntaxError, Exception),
+    (IndentationError, SyntaxError),
+    (TabError, IndentationError),
+    (SystemError, Exception),
+    (TypeError, Exception),
+    (ValueError, Exception),
+    (UnicodeError, ValueError),
+    (UnicodeDecodeError, UnicodeError),
+    (UnicodeEncodeError, UnicodeError),
+    (UnicodeTranslateError, UnicodeError),
+    (Warning, Exception),
+    (DeprecationWarning, Warning),
+    (PendingDeprecationWarning, Warning),
+    (RuntimeWarning, Warning),
+    (SyntaxWarning, Warning),
+    (UserWarning, Warning),
+    (FutureWarning, Warning),
+    (ImportWarning, Warning),
+    (UnicodeWarning, Warning),
+    (BytesWarning, Warning),
+    (ResourceWarning, Warning),
+]
+
+for cls, base in hierarchy:
+    assert cls.__mro__[1] is base, cls
+    assert issubclass(cls, BaseException)
+
+assert not issubclass(KeyboardInterrupt, Exception)
+assert not issubclass(SystemExit, Exception)
+assert TabError.__mro__ == (TabError, IndentationError, SyntaxError, Exception, BaseException, object)
+
+assert EnvironmentError is OSError
+assert IOError is OSError
+
+# Handlers catch subclasses by their base:
+try:
+    raise KeyError('k')
+except LookupError as e:
+    assert e.args == ('k',)
+
+try:
+    raise KeyboardInterrupt
+except Exception:
+    assert False
+except BaseException as e:
+    assert isinstance(e, KeyboardInterrupt)
+
+# OSError maps errno values to subclasses:
+e = OSError(errno.ENOENT, 'No such file or directory', 'missing.txt')
+assert type(e) is FileNotFoundError
+assert e.errno == errno.ENOENT
+assert e.strerror == 'No such file or directory'
+assert e.filename == 'missing.txt'
+
+assert type(OSError(errno.EACCES, 'denied')) is PermissionError
+assert type(OSError(errno.EPERM, 'denied')) is PermissionError
+assert type(OSError(errno.EEXIST, 'exists')) is FileExistsError
+assert type(OSError(errno.EISDIR, 'dir')) is IsADirectoryError
+assert type(OSError(errno.ENOTDIR, 'not dir')) is NotADirectoryError
+assert type(OSError(errno.ECONNREFUSED, 'refused')) is ConnectionRefusedError
+assert type(OSError(errno.ECONNRESET, 'reset')) is ConnectionResetError
+assert type(OSError(errno.EPIPE, 'pipe')) is BrokenPipeError
+assert type(OSError(errno.ETIMEDOUT, 'timeout')) is TimeoutError
+assert type(OSError(errno.EAGAIN, 'again')) is BlockingIOError
+
+# Unknown errno values and other arguments keep the base class:
+assert type(OSError(123456, 'unknown')) is OSError
+e = OSError('message')
+assert type(e) is OSError
+assert e.errno is None
+assert e.strerror is None
+
+# Subclasses are never remapped:
+assert type(FileNotFoundError(errno.EACCES, 'x')) is FileNotFoundError
+
+try:
+    raise OSError(errno.ENOENT, 'gone')
+except FileNotFoundError as e:
+    assert e.errno == errno.ENOENT
@@ -0,0 +1,74 @@
+import sys
+
+from testutils import assert_raises
+
+
+def f(a, b=2, *args, c, **kwargs):
+    e = 5
+    return locals()
+
+
+l = f(1, c=3)
+assert sorted(l.keys()) == ['a', 'args', 'b', 'c', 'e', 'kwargs']
+assert (l['a'], l['b'], l['args'], l['c'], l['e']) == (1, 2, (), 3, 5)
+assert len(l['kwargs']) == 0
+
+l = f(1, 7, 8, c=3, z=9)
+assert (l['a'], l['b'], l['args'], l['c'], l['e']) == (1, 7, (8,), 3, 5)
+assert l['kwargs']['z'] == 9
+
+
+def unbound():
+    x = x + 1
+
+assert_raises(UnboundLocalError, unbound)
+
+assert issubclass(UnboundLocalError, NameError)
+
+
+def delete():
+    x = 1
+    del x
+    return x
+
+assert_raises(UnboundLocalError, delete)
+
+
+def delete_after_locals():
+    x = 1
+    assert locals()['x'] == 1
+    del x
+    return locals()
+
+assert 'x' not in delete_after_locals()
+
+
+def frame_locals():
+    y = 'y'
+    return sys._getframe().f_locals
+
+assert frame_locals()['y'] == 'y'
+
+
+def exec_sees_locals():
+    z = 3
+    return eval('z * 2')
+
+assert exec_sees_locals() == 6
+
+
+def captured_argument(a):
+    def inner():
+        return a
+    a += 1
+    return inner()
+
+assert captured_argument(1) == 2
+
+
+def fib(n):
+    if n < 2:
+        return n
+    return fib(n - 1) + fib(n - 2)
+
+assert fib(10) == 55
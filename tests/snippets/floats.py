@@ -106,3 +106,182 @@ assert_raises(OverflowError, float('inf').as_integer_ratio)
 assert_raises(OverflowError, float('-inf').as_integer_ratio)
 assert_raises(ValueError, float('nan').as_integer_ratio)
 
+
+# repr gives the shortest string that round-trips
+assert repr(0.1) == '0.1'
+assert repr(1.0) == '1.0'
+assert repr(-0.0) == '-0.0'
+assert repr(1e16) == '1e+16'
+assert repr(123456789012345.6) == '123456789012345.6'
+assert repr(0.0001) == '0.0001'
+assert repr(0.00001) == '1e-05'
+assert repr(1.5e-300) == '1.5e-300'
+assert repr(5e-324) == '5e-324'
+assert repr(1.7976931348623157e308) == '1.7976931348623157e+308'
+assert repr(float('inf')) == 'inf'
+assert repr(float('-inf')) == '-inf'
+assert repr(float('nan')) == 'nan'
+assert str(0.1 + 0.2) == '0.30000000000000004'
+for x in [0.1, 1/3, 2.5e-8, 1e22, 123.456, 5e-324]:
+    assert float(repr(x)) == x
+
+# construction
+assert float() == 0.0
+assert float('  1.5\n') == 1.5
+assert float('1_000.5') == 1000.5
+assert float('-Infinity') == float('-inf')
+assert float('nAn') != float('nan')
+assert float(b'2.5') == 2.5
+assert float(10**20) == 1e20
+assert float(2**53 + 1) == 2.0**53
+assert float(2**80 + 2**27 + 1) == 2.0**80 + 2.0**28
+assert_raises(ValueError, lambda: float('1__0'))
+assert_raises(ValueError, lambda: float('_1'))
+assert_raises(ValueError, lambda: float('abc'))
+assert_raises(OverflowError, lambda: float(2**1024))
+assert_raises(TypeError, lambda: float([]))
+
+class Floatable:
+    def __float__(self):
+        return 4.25
+
+assert float(Floatable()) == 4.25
+
+# comparisons with ints are exact
+assert 2.0**53 != 2**53 + 1
+assert 2.0**53 < 2**53 + 1
+assert 2**53 + 1 > 2.0**53
+assert 1e300 < 10**301
+assert float('inf') > 10**400
+assert not float('nan') == 0
+assert not float('nan') < 10**400
+assert 1.0 == 1 and 1 == 1.0
+
+# floor division and modulo take the sign of the divisor
+assert 7.5 % 2 == 1.5
+assert -7.5 % 2 == 0.5
+assert 7.5 % -2 == -0.5
+assert -7.5 // 2 == -4.0
+assert divmod(-7.5, 2) == (-4.0, 0.5)
+assert divmod(7, 2.5) == (2.0, 2.0)
+assert str(0.0 % -1) == '-0.0'
+assert 5 % 1.5 == 0.5
+assert 7 // 2.0 == 3.0
+assert 2.0.__rmod__(7) == 1.0
+assert 2.0.__rfloordiv__(7) == 3.0
+assert 2.0.__rdivmod__(7) == (3.0, 1.0)
+assert_raises(ZeroDivisionError, lambda: 1.0 % 0)
+assert_raises(ZeroDivisionError, lambda: 1.0 // 0.0)
+assert_raises(ZeroDivisionError, lambda: divmod(1.0, 0))
+
+# powers
+assert 2.0 ** 10 == 1024.0
+assert 2 ** 0.5 == 1.4142135623730951
+assert 4.0 ** -1 == 0.25
+assert 2.0.__rpow__(3) == 9.0
+assert 0.5.__rpow__(4) == 2.0
+assert 1.0 ** float('nan') == 1.0
+assert isinstance((-8.0) ** (1 / 3), complex)
+assert_raises(ZeroDivisionError, lambda: 0.0 ** -1)
+assert_raises(OverflowError, lambda: 10.0 ** 400)
+assert_raises(TypeError, lambda: pow(2.0, 3, 5))
+
+# conversion to int
+assert int(3.9) == 3
+assert int(-3.9) == -3
+assert (2.5).__trunc__() == 2
+assert (-2.5).__int__() == -2
+assert int(1e20) == 100000000000000000000
+assert_raises(OverflowError, lambda: int(float('inf')))
+assert_raises(ValueError, lambda: int(float('nan')))
+
+# rounding goes to the nearest even value on ties
+assert round(0.5) == 0
+assert round(1.5) == 2
+assert round(2.5) == 2
+assert round(-2.5) == -2
+assert isinstance(round(2.7), int)
+assert round(2.675, 2) == 2.67
+assert round(0.125, 2) == 0.12
+assert round(1.5, 0) == 2.0
+assert isinstance(round(1.5, 0), float)
+assert round(1234.5678, -2) == 1200.0
+assert round(1250.0, -2) == 1200.0
+assert round(1350.0, -2) == 1400.0
+assert round(5e-324, 400) == 5e-324
+assert round(1e300, -400) == 0.0
+assert round(2.5, None) == 2
+assert str(round(-0.4)) == '0'
+assert str(round(-0.1, 0)) == '-0.0'
+assert str(round(-2.5, -1)) == '-0.0'
+assert str(round(-4.0, -1)) == '-0.0'
+assert str(round(-0.001, 2)) == '-0.0'
+assert str(round(0.001, 2)) == '0.0'
+assert_raises(OverflowError, lambda: round(float('inf')))
+assert_raises(ValueError, lambda: round(float('nan')))
+assert_raises(TypeError, lambda: round(1.5, 1.0))
+assert_raises(OverflowError, lambda: round(1.7976931348623157e308, -308))
+
+assert (2.5).conjugate() == 2.5
+assert (2.5).imag == 0.0
+assert (-2.5).__pos__() == -2.5
+assert (2.5).__float__() == 2.5
+assert float('inf').is_integer() == False
+assert hash(2.0) == hash(2)
+assert hash(-0.0) == hash(0.0)
+
+# hex and fromhex
+assert (1.0).hex() == '0x1.0000000000000p+0'
+assert (-0.1).hex() == '-0x1.999999999999ap-4'
+assert (0.0).hex() == '0x0.0p+0'
+assert (-0.0).hex() == '-0x0.0p+0'
+assert (5e-324).hex() == '0x0.0000000000001p-1022'
+assert (1.7976931348623157e308).hex() == '0x1.fffffffffffffp+1023'
+assert float('inf').hex() == 'inf'
+assert float.fromhex('0x1.8p1') == 3.0
+assert float.fromhex(' -0X1P-2 ') == -0.25
+assert float.fromhex('1.') == 1.0
+assert float.fromhex('.8') == 0.5
+assert float.fromhex('ff') == 255.0
+assert float.fromhex('0x1p-1074') == 5e-324
+assert float.fromhex('0x1p-1075') == 0.0
+assert float.fromhex('0x3p-1075') == 1e-323
+assert float.fromhex('0x1.fffffffffffff8p0') == 2.0
+assert float.fromhex('-inf') == float('-inf')
+for x in [0.1, -1.5e-310, 1e300, 123.456]:
+    assert float.fromhex(x.hex()) == x
+assert_raises(ValueError, lambda: float.fromhex('0x'))
+assert_raises(ValueError, lambda: float.fromhex('0x1.2.3'))
+assert_raises(ValueError, lambda: float.fromhex('0x1p'))
+assert_raises(OverflowError, lambda: float.fromhex('0x1p1024'))
+
+# format
+assert format(3.14159, '.2f') == '3.14'
+assert format(-1234.5, '+010.3e') == '-1.234e+03'
+assert format(1234567.891, ',') == '1,234,567.891'
+assert format(1234567.891, '_.1f') == '1_234_567.9'
+assert format(0.125, '.1%') == '12.5%'
+assert format(1e20, 'g') == '1e+20'
+assert format(2.0, '') == '2.0'
+assert format(2.0, '.3') == '2.0'
+assert format(1e16, '') == '1e+16'
+assert format(-1.5, '08.2f') == '-0001.50'
+assert format(1.5, '^9') == '   1.5   '
+assert format(float('inf'), '08') == '00000inf'
+assert '{:.3g}'.format(0.0001234) == '0.000123'
+assert_raises(ValueError, lambda: format(1.5, 'd'))
+
+# Hashes are the same as CPython's
+assert hash(-1.0) == -2
+assert hash(float('inf')) == 314159
+assert hash(float('-inf')) == -314159
+assert hash(1.5) == 1152921504606846977
+assert hash(1e300) == 1224995262755759164
+assert hash(5e-324) == 16777216
+assert hash(-1) == -2
+assert hash(2 ** 61 - 1) == 0
+assert hash(2 ** 70) == hash(2.0 ** 70)
+assert hash(complex(1, 2)) == 2000007
+assert hash(complex(-1.0, 0)) == -2
+assert hash(complex(3, 0)) == hash(3)
+assert hash(True) == 1
@@ -35,3 +35,135 @@ r = list(g3())
 # print(r)
 assert r == [23, 1, 2, 3, 44]
 
+
+
+from testutils import assert_raises
+
+# The return value of a generator is carried by StopIteration:
+def returns():
+    yield 1
+    return 42
+
+g = returns()
+assert next(g) == 1
+try:
+    next(g)
+except StopIteration as e:
+    assert e.value == 42
+else:
+    assert False, "StopIteration not raised"
+assert_raises(StopIteration, lambda: next(g))
+
+# ... and is the value of a yield from expression, which also passes on
+# sent values:
+def inner():
+    x = yield "a"
+    yield x
+    return "done"
+
+def outer():
+    r = yield from inner()
+    yield r
+    r = yield from [1]
+    yield r
+
+o = outer()
+assert next(o) == "a"
+assert o.send("sent") == "sent"
+assert next(o) == "done"
+assert next(o) == 1
+assert next(o) is None
+assert_raises(StopIteration, lambda: next(o))
+
+# Exceptions can be thrown into a suspended generator:
+log = []
+
+def catching():
+    try:
+        yield 1
+    except ValueError as e:
+        log.append(e.args)
+        yield 2
+    finally:
+        log.append("finally")
+
+g = catching()
+assert next(g) == 1
+assert g.throw(ValueError, "boom") == 2
+assert log == [("boom",)]
+assert_raises(StopIteration, lambda: next(g))
+assert log == [("boom",), "finally"]
+
+g = catching()
+next(g)
+assert_raises(KeyError, lambda: g.throw(KeyError()))
+assert log[-1] == "finally"
+assert_raises(StopIteration, lambda: next(g))
+
+g = catching()
+assert_raises(IndexError, lambda: g.throw(IndexError))
+assert_raises(StopIteration, lambda: next(g))
+assert_raises(TypeError, lambda: g.throw(ValueError(), 1))
+assert_raises(TypeError, lambda: g.throw(1))
+
+# close() delivers GeneratorExit into the generator:
+def closing():
+    try:
+        yield 1
+    except GeneratorExit:
+        log.append("exit")
+        raise
+
+log = []
+g = closing()
+next(g)
+g.close()
+assert log == ["exit"]
+g.close()
+assert_raises(StopIteration, lambda: next(g))
+
+def ignoring():
+    try:
+        yield 1
+    except GeneratorExit:
+        yield 2
+
+g = ignoring()
+next(g)
+assert_raises(RuntimeError, lambda: g.close())
+
+# A fresh generator is closed without running it:
+g = closing()
+g.close()
+assert log == ["exit"]
+
+# throw() and close() reach the generator being delegated to:
+def delegating():
+    yield from catching()
+
+log = []
+g = delegating()
+next(g)
+assert g.throw(ValueError("x")) == 2
+assert log == [("x",)]
+g.close()
+
+log = []
+g = delegating()
+next(g)
+g.close()
+assert log == ["finally"]
+
+# A StopIteration escaping a generator body becomes a RuntimeError:
+def stopping():
+    yield 1
+    raise StopIteration
+
+g = stopping()
+next(g)
+assert_raises(RuntimeError, lambda: next(g))
+
+def fresh():
+    yield 1
+
+assert_raises(TypeError, lambda: fresh().send(1))
@@ -0,0 +1,60 @@
+from testutils import assert_raises
+
+# Test global and nonlocal funkyness
+
+a = 2
+
+def b():
+    global a
+    a = 4
+
+assert a == 2
+b()
+assert a == 4
+
+
+def x():
+    def y():
+        nonlocal b
+        b = 3
+    b = 2
+    y()
+    return b
+
+res = x()
+assert res == 3, str(res)
+
+
+def counter():
+    count = 0
+    def increment():
+        nonlocal count
+        count += 1
+        return count
+    return increment
+
+inc = counter()
+assert inc() == 1
+assert inc() == 2
+
+
+# Functions do not see the class namespace:
+y = 'global'
+
+class A:
+    y = 'class'
+    def get_y(self):
+        return y
+
+assert A().get_y() == 'global'
+
+
+# Invalid uses are reported as syntax errors at compile time:
+def compile_error(source):
+    return lambda: compile(source, '<test>', 'exec')
+
+assert_raises(SyntaxError, compile_error('def f():\n x = 1\n global x\n'))
+assert_raises(SyntaxError, compile_error('def f():\n print(x)\n global x\n'))
+assert_raises(SyntaxError, compile_error('def f(x):\n global x\n'))
+assert_raises(SyntaxError, compile_error('nonlocal x\n'))
+assert_raises(SyntaxError, compile_error('def f():\n nonlocal x\n'))
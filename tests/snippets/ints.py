@@ -74,3 +74,139 @@ with assertRaises(TypeError):
 with assertRaises(TypeError):
     # check that first parameter is truly positional only
     int(val_options=1)
+
+assert int(' 12_3 ') == 123
+assert int('-0x1F', 0) == -31
+assert int('0b101', 0) == 5
+assert int('0o17', 8) == 15
+assert int('0x_ff', 16) == 255
+assert int('z', 36) == 35
+assert int('000', 0) == 0
+assert int(b'42') == 42
+assert int(bytearray(b'-7')) == -7
+assert int(3.99) == 3
+assert int(-3.99) == -3
+assert int('+7') == 7
+assert int('9' * 30) == 10**30 - 1
+assert_raises(ValueError, lambda: int('010', 0))
+assert_raises(ValueError, lambda: int('1__0'))
+assert_raises(ValueError, lambda: int('_1'))
+assert_raises(ValueError, lambda: int('0x'))
+assert_raises(ValueError, lambda: int('12', 2))
+assert_raises(ValueError, lambda: int('1', 1))
+assert_raises(ValueError, lambda: int('1', 37))
+assert_raises(TypeError, lambda: int([]))
+
+class Intable:
+    def __int__(self):
+        return 42
+
+class Indexable:
+    def __index__(self):
+        return 7
+
+assert int(Intable()) == 42
+assert int(Indexable()) == 7
+
+# floor division and modulo round towards negative infinity
+assert 7 // 2 == 3
+assert -7 // 2 == -4
+assert 7 // -2 == -4
+assert -7 % 3 == 2
+assert 7 % -3 == -2
+assert divmod(-7, 2) == (-4, 1)
+assert divmod(7, -2) == (-4, -1)
+assert (3).__rfloordiv__(10) == 3
+assert (3).__rmod__(-10) == 2
+assert (3).__rdivmod__(-10) == (-4, 2)
+assert_raises(ZeroDivisionError, lambda: 1 // 0)
+assert_raises(ZeroDivisionError, lambda: 1 % 0)
+assert_raises(ZeroDivisionError, lambda: divmod(1, 0))
+
+# shifts
+assert 1 << 70 == 1180591620717411303424
+assert -5 >> 1 == -3
+assert -1 >> 1000 == -1
+assert 5 >> 1000 == 0
+assert 0 << 2**70 == 0
+assert (1).__rlshift__(3) == 6
+assert (1).__rrshift__(6) == 3
+assert (2).__rlshift__(1.0) == NotImplemented
+assert (6).__rand__(3) == 2
+assert (6).__ror__(3) == 7
+assert (6).__rxor__(3) == 5
+assert_raises(ValueError, lambda: 1 << -1)
+assert_raises(ValueError, lambda: 1 >> -1)
+
+# powers
+assert 2 ** 100 == 1267650600228229401496703205376
+assert 2 ** -2 == 0.25
+assert (-2) ** 3 == -8
+assert 2 ** 0.5 == 1.4142135623730951
+assert (2).__pow__(0.5) == NotImplemented
+assert pow(3, 4, 5) == 1
+assert pow(-3, 3, 7) == 1
+assert pow(3, 3, -7) == -1
+assert pow(3, -1, 7) == 5
+assert pow(38, -1, 97) == 23
+assert pow(4, 0, 1) == 0
+assert pow(2, 10, None) == 1024
+assert (3).__rpow__(2) == 8
+assert (3).__rpow__(2, 5) == 3
+assert_raises(ZeroDivisionError, lambda: 0 ** -1)
+assert_raises(ValueError, lambda: pow(2, 3, 0))
+assert_raises(ValueError, lambda: pow(2, -1, 4))
+assert_raises(TypeError, lambda: pow(2, 3, 5.0))
+
+# rounding to tens, hundreds, etc. goes to the even multiple on ties
+assert round(7) == 7
+assert round(7, 2) == 7
+assert round(1250, -2) == 1200
+assert round(1350, -2) == 1400
+assert round(-1250, -2) == -1200
+assert round(15, -1) == 20
+assert round(1251, -2) == 1300
+assert round(12345, -10) == 0
+assert_raises(TypeError, lambda: round(5, 1.5))
+
+# conversions
+assert float(2**54 + 3) == 18014398509481988.0
+assert_raises(OverflowError, lambda: float(2**1024))
+assert (255).bit_length() == 8
+assert (-256).bit_length() == 9
+assert (0).bit_length() == 0
+assert (5).conjugate() == 5
+assert (5).numerator == 5
+assert (5).denominator == 1
+assert (10).as_integer_ratio() == (10, 1)
+
+# to_bytes and from_bytes
+assert (1024).to_bytes(2, 'big') == b'\x04\x00'
+assert (1024).to_bytes(4, 'little') == b'\x00\x04\x00\x00'
+assert (1024).to_bytes(4, byteorder='big') == b'\x00\x00\x04\x00'
+assert (-1).to_bytes(2, 'big', signed=True) == b'\xff\xff'
+assert (-128).to_bytes(1, 'little', signed=True) == b'\x80'
+assert (128).to_bytes(2, 'big', signed=True) == b'\x00\x80'
+assert (0).to_bytes(0, 'big') == b''
+assert (255).to_bytes() == b'\xff'
+assert_raises(OverflowError, lambda: (256).to_bytes(1, 'big'))
+assert_raises(OverflowError, lambda: (128).to_bytes(1, 'big', signed=True))
+assert_raises(OverflowError, lambda: (-1).to_bytes(1, 'big'))
+assert_raises(ValueError, lambda: (1).to_bytes(1, 'middle'))
+assert_raises(ValueError, lambda: (1).to_bytes(-1, 'big'))
+
+assert int.from_bytes(b'\x00\x10', 'big') == 16
+assert int.from_bytes(b'\x00\x10', 'little') == 4096
+assert int.from_bytes(b'\xff\xff', 'little', signed=True) == -1
+assert int.from_bytes(b'\xff\xff', 'little') == 65535
+assert int.from_bytes([1, 0], 'little') == 1
+assert int.from_bytes(bytearray(b'\x01\x00'), byteorder='big') == 256
+assert int.from_bytes(memoryview(b'\x7f')) == 127
+assert int.from_bytes(b'') == 0
+assert bool.from_bytes(b'\x01') is True
+assert_raises(TypeError, lambda: int.from_bytes('ab'))
+assert_raises(TypeError, lambda: int.from_bytes(5))
+for n in [0, 1, -1, 127, -128, 2**64 - 1, -2**63]:
+    for order in ['big', 'little']:
+        data = n.to_bytes(9, order, signed=True)
+        assert int.from_bytes(data, order, signed=True) == n
@@ -12,9 +12,8 @@ assert "foo" in "foobar"
 assert "whatever" not in "foobar"
 
 # test bytes
-# TODO: uncomment this when bytes are implemented
-# assert b"foo" in b"foobar"
-# assert b"whatever" not in b"foobar"
+assert b"foo" in b"foobar"
+assert b"whatever" not in b"foobar"
 assert b"1" < b"2"
 assert b"1" <= b"2"
 assert b"5" <= b"5"
@@ -0,0 +1,140 @@
+from testutils import assertRaises
+
+b = b'abcdef'
+m = memoryview(b)
+assert len(m) == 6
+assert m[0] == 97
+assert m[-1] == 102
+assert m.tobytes() == b
+assert m.tolist() == [97, 98, 99, 100, 101, 102]
+assert m.obj is b
+assert m.readonly
+assert m.format == 'B'
+assert m.itemsize == 1
+assert m.nbytes == 6
+assert m.ndim == 1
+assert m.shape == (6,)
+assert m.strides == (1,)
+assert m.hex() == '616263646566'
+assert list(m) == [97, 98, 99, 100, 101, 102]
+assert bytes(m) == b
+assert m == b
+assert m == memoryview(b'abcdef')
+assert m != b'abc'
+assert hash(m) == hash(b)
+assert repr(m).startswith('<memory at 0x')
+
+with assertRaises(IndexError):
+    m[6]
+with assertRaises(TypeError):
+    m[0] = 1
+with assertRaises(TypeError):
+    memoryview('abc')
+
+# Slices are views of the same data
+s = m[1:5:2]
+assert s.tobytes() == b'bd'
+assert s.obj is b
+assert s.strides == (2,)
+assert not s.contiguous
+assert m[::-1].tobytes() == b'fedcba'
+assert m[::-1][1:3].tobytes() == b'ed'
+assert m[4:1].tobytes() == b''
+assert memoryview(s).tobytes() == b'bd'
+
+# Writable views of a bytearray
+a = bytearray(b'hello world')
+m = memoryview(a)
+assert not m.readonly
+m[0] = ord('j')
+assert a == bytearray(b'jello world')
+m[6:11] = b'there'
+assert a == bytearray(b'jello there')
+v = m[::2]
+v[:] = b'ABCDEF'
+assert a == bytearray(b'AeBlC DhErF')
+v[1] = ord('x')
+assert a[2] == ord('x')
+assert m.toreadonly().readonly
+with assertRaises(TypeError):
+    m.toreadonly()[0] = 1
+with assertRaises(ValueError):
+    m[0:2] = b'abc'
+with assertRaises(ValueError):
+    m[0] = 256
+with assertRaises(TypeError):
+    m[0] = b'a'
+with assertRaises(ValueError):
+    hash(m)
+
+# The bytearray can't be resized while views of it exist
+with assertRaises(BufferError):
+    a.append(1)
+with assertRaises(BufferError):
+    a.extend(b'abc')
+with assertRaises(BufferError):
+    a.clear()
+a[0:2] = b'ab'
+v.release()
+m.release()
+a.append(33)
+assert a.endswith(b'!')
+
+# Released views
+with assertRaises(ValueError):
+    m.tobytes()
+with assertRaises(ValueError):
+    m[0]
+with assertRaises(ValueError):
+    len(m)
+assert repr(m).startswith('<released memory at 0x')
+m.release()
+
+with memoryview(a) as m:
+    with assertRaises(BufferError):
+        a.pop()
+assert repr(m).startswith('<released memory at 0x')
+a.pop()
+
+# Casts
+data = bytearray(range(8))
+m = memoryview(data)
+i = m.cast('i')
+assert i.format == 'i'
+assert i.itemsize == 4
+assert len(i) == 2
+assert i.shape == (2,)
+assert i.nbytes == 8
+assert i.tolist() == [0x03020100, 0x07060504]
+i[0] = -1
+assert data[:4] == b'\xff\xff\xff\xff'
+assert i.cast('B').tolist() == list(data)
+assert m.cast('@H', [4]).tolist()[2] == 0x0504
+assert m.cast('c')[0] == b'\xff'
+assert m.cast('?')[5]
+assert m.cast('b')[0] == -1
+assert m.cast('d').itemsize == 8
+with assertRaises(TypeError):
+    m.cast('i').cast('h')
+with assertRaises(TypeError):
+    m[::2].cast('B')
+with assertRaises(TypeError):
+    m[:3].cast('i')
+with assertRaises(ValueError):
+    m.cast('<i')
+with assertRaises(TypeError):
+    m.cast('i', [3])
+with assertRaises(ValueError):
+    m.cast('h')[0] = 1 << 16
+
+f = memoryview(bytearray(8)).cast('d')
+f[0] = 1.5
+assert f[0] == 1.5
+f[0] = 2
+assert f.tolist() == [2.0]
+
+# Other buffer consumers
+assert bytearray(memoryview(b'xyz')) == bytearray(b'xyz')
+assert b'ab' + memoryview(b'cd') == b'abcd'
+assert b'-'.join([memoryview(b'a'), b'b']) == b'a-b'
+assert b'abc'.startswith(memoryview(b'ab'))
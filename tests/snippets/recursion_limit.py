@@ -0,0 +1,33 @@
+import sys
+from testutils import assert_raises
+
+assert sys.getrecursionlimit() == 1000
+
+
+def recurse(n):
+    return recurse(n + 1)
+
+
+assert_raises(RecursionError, lambda: recurse(0))
+
+# The interpreter is still usable afterwards.
+def depth(n):
+    if n == 0:
+        return 0
+    return 1 + depth(n - 1)
+
+
+assert depth(100) == 100
+
+old_limit = sys.getrecursionlimit()
+sys.setrecursionlimit(200)
+assert sys.getrecursionlimit() == 200
+assert_raises(RecursionError, lambda: depth(300))
+assert depth(50) == 50
+
+assert_raises(ValueError, lambda: sys.setrecursionlimit(0))
+assert_raises(ValueError, lambda: sys.setrecursionlimit(-5))
+
+sys.setrecursionlimit(old_limit)
+assert sys.getrecursionlimit() == old_limit
+assert depth(300) == 300
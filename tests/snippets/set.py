@@ -229,3 +229,24 @@ assert set([1,2,3]).symmetric_difference(frozenset([1,2])) == set([3])
 
 assert frozenset([1,2,3]) ^ set([4,5]) == frozenset([1,2,3,4,5])
 assert set([1,2,3]) ^ frozenset([4,5]) == set([1,2,3,4,5])
+
+class Plain:
+    pass
+
+p1, p2 = Plain(), Plain()
+assert len(set([p1, p2, p1])) == 2
+assert p1 in set([p1])
+assert hash(p1) == hash(p1)
+assert len(set([1, 1.0, 2.0, 2])) == 2
+assert 1 in set([1.0])
+assert len(set([frozenset([1, 2]), frozenset([2, 1])])) == 1
+assert_raises(TypeError, lambda: hash([]))
+assert_raises(TypeError, lambda: hash({}))
+assert_raises(TypeError, lambda: hash(set()))
+
+class WithEq:
+    def __eq__(self, other):
+        return True
+
+assert WithEq.__hash__ is None
+assert_raises(TypeError, lambda: hash(WithEq()))
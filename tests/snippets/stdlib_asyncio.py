@@ -0,0 +1,233 @@
+import asyncio
+import socket
+import types
+from testutils import assert_raises
+
+
+async def add(a, b):
+    await asyncio.sleep(0)
+    return a + b
+
+assert asyncio.run(add(1, 2)) == 3
+
+
+# Tasks run concurrently, waking up in the order of their timers:
+order = []
+
+
+async def sleeper(name, delay):
+    await asyncio.sleep(delay)
+    order.append(name)
+    return name
+
+
+async def sleepers():
+    first = asyncio.create_task(sleeper('slow', 0.05))
+    second = asyncio.create_task(sleeper('fast', 0.01))
+    assert not first.done()
+    return await first, await second
+
+assert asyncio.run(sleepers()) == ('slow', 'fast')
+assert order == ['fast', 'slow']
+
+
+async def gathering():
+    return await asyncio.gather(sleeper('a', 0.02), add(1, 1), sleeper('b', 0))
+
+assert asyncio.run(gathering()) == ['a', 2, 'b']
+
+
+async def fail():
+    await asyncio.sleep(0)
+    raise ValueError('failed')
+
+
+async def gather_failures():
+    results = await asyncio.gather(fail(), add(2, 3), return_exceptions=True)
+    assert isinstance(results[0], ValueError)
+    assert results[1] == 5
+    try:
+        await asyncio.gather(fail(), add(2, 3))
+    except ValueError:
+        return 'raised'
+
+assert asyncio.run(gather_failures()) == 'raised'
+assert_raises(ValueError, lambda: asyncio.run(fail()))
+
+
+# wait_for
+async def timeouts():
+    assert await asyncio.wait_for(add(1, 2), 1) == 3
+    assert await asyncio.wait_for(add(1, 2), None) == 3
+    try:
+        await asyncio.wait_for(asyncio.sleep(10), 0.01)
+    except asyncio.TimeoutError:
+        return 'timed out'
+
+assert asyncio.run(timeouts()) == 'timed out'
+
+
+# Futures
+async def futures():
+    loop = asyncio.get_running_loop()
+    future = loop.create_future()
+    assert not future.done()
+    assert_raises(asyncio.InvalidStateError, lambda: future.result())
+    loop.call_later(0.01, future.set_result, 'value')
+    assert await future == 'value'
+    assert future.done()
+    assert_raises(asyncio.InvalidStateError, lambda: future.set_result(1))
+
+    callbacks = []
+    future = loop.create_future()
+    future.add_done_callback(lambda f: callbacks.append(f.exception()))
+    future.set_exception(KeyError)
+    await asyncio.sleep(0)
+    assert isinstance(callbacks[0], KeyError)
+    try:
+        await future
+    except KeyError:
+        pass
+    else:
+        assert False
+    return 'ok'
+
+assert asyncio.run(futures()) == 'ok'
+
+
+# Cancellation
+cancelled = []
+
+
+async def forever():
+    try:
+        await asyncio.sleep(10)
+    except asyncio.CancelledError:
+        cancelled.append(True)
+        raise
+
+
+async def cancelling():
+    task = asyncio.create_task(forever())
+    await asyncio.sleep(0)
+    assert task.cancel()
+    try:
+        await task
+    except asyncio.CancelledError:
+        pass
+    assert task.cancelled()
+    assert not task.cancel()
+    return cancelled
+
+assert asyncio.run(cancelling()) == [True]
+
+
+# Event
+async def events():
+    event = asyncio.Event()
+    log = []
+
+    async def waiter(name):
+        await event.wait()
+        log.append(name)
+
+    tasks = [asyncio.create_task(waiter(i)) for i in range(3)]
+    await asyncio.sleep(0.01)
+    assert log == []
+    assert not event.is_set()
+    event.set()
+    await asyncio.gather(*tasks)
+    return sorted(log)
+
+assert asyncio.run(events()) == [0, 1, 2]
+
+
+# Queue
+async def producer_consumer():
+    queue = asyncio.Queue(maxsize=2)
+    consumed = []
+
+    async def producer():
+        for i in range(5):
+            await queue.put(i)
+        await queue.put(None)
+
+    async def consumer():
+        while True:
+            item = await queue.get()
+            queue.task_done()
+            if item is None:
+                break
+            consumed.append(item)
+
+    await asyncio.gather(consumer(), producer())
+    await queue.join()
+    assert queue.empty()
+    assert_raises(asyncio.QueueEmpty, lambda: queue.get_nowait())
+    queue.put_nowait(1)
+    queue.put_nowait(2)
+    assert queue.full()
+    assert_raises(asyncio.QueueFull, lambda: queue.put_nowait(3))
+    return consumed
+
+assert asyncio.run(producer_consumer()) == [0, 1, 2, 3, 4]
+
+
+# Generator based coroutines
+@types.coroutine
+def legacy():
+    loop = asyncio.get_running_loop()
+    future = loop.create_future()
+    loop.call_soon(future.set_result, 'legacy')
+    result = yield from future
+    return result
+
+
+async def use_legacy():
+    return await legacy()
+
+assert asyncio.run(use_legacy()) == 'legacy'
+
+
+# The loop can be driven by hand
+loop = asyncio.new_event_loop()
+calls = []
+loop.call_soon(calls.append, 1)
+loop.call_later(0.01, loop.stop)
+loop.run_forever()
+assert calls == [1]
+assert loop.run_until_complete(add(3, 4)) == 7
+loop.close()
+assert loop.is_closed()
+assert_raises(RuntimeError, lambda: loop.run_forever())
+
+
+# Loopback sockets
+async def echo_server(listener):
+    loop = asyncio.get_running_loop()
+    conn, _ = await loop.sock_accept(listener)
+    data = await loop.sock_recv(conn, 100)
+    await loop.sock_sendall(conn, data)
+    conn.close()
+    return data
+
+
+async def echo():
+    loop = asyncio.get_running_loop()
+    listener = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+    listener.bind(('127.0.0.1', 0))
+    listener.listen(1)
+    listener.setblocking(False)
+    server = asyncio.create_task(echo_server(listener))
+
+    client = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+    await loop.sock_connect(client, listener.getsockname())
+    client.setblocking(False)
+    await loop.sock_sendall(client, b'hello')
+    reply = await loop.sock_recv(client, 100)
+    received = await server
+    client.close()
+    listener.close()
+    return received, reply
+
+assert asyncio.run(echo()) == (b'hello', b'hello')
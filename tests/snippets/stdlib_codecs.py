@@ -0,0 +1,186 @@
+import codecs
+import io
+import os
+from testutils import assert_raises
+
+# Lookup and the registry
+assert codecs.lookup("UTF8").name == "utf-8"
+assert codecs.lookup("utf_16_le").name == "utf-16-le"
+assert codecs.lookup("latin1").name == "iso8859-1"
+assert codecs.lookup("windows-1252").name == "cp1252"
+assert_raises(LookupError, lambda: codecs.lookup("no-such-codec"))
+
+info = codecs.lookup("utf-8")
+assert isinstance(info, tuple)
+assert info.encode("é") == (bytes([0xc3, 0xa9]), 1)
+assert info.decode(bytes([0xc3, 0xa9])) == ("é", 2)
+assert codecs.getencoder("ascii")("ab") == (b"ab", 2)
+assert codecs.getdecoder("ascii")(b"ab") == ("ab", 2)
+
+# The built in codecs
+assert list("aé".encode("utf-16-be")) == [0, 97, 0, 233]
+assert list("aé".encode("utf-16-le")) == [97, 0, 233, 0]
+assert list(chr(0x1f600).encode("utf-16-be")) == [0xd8, 0x3d, 0xde, 0x00]
+assert list("a".encode("utf-32-be")) == [0, 0, 0, 97]
+native = "utf-16-le" if codecs.BOM_UTF16 == codecs.BOM_LE else "utf-16-be"
+assert list("a".encode("utf-16")) == list(codecs.BOM_UTF16) + list("a".encode(native))
+assert list(codecs.BOM_UTF8) == [0xef, 0xbb, 0xbf]
+assert list(codecs.BOM_UTF16_BE) == [0xfe, 0xff]
+assert list(codecs.BOM_UTF32_LE) == [0xff, 0xfe, 0, 0]
+assert list("é".encode("latin-1")) == [0xe9]
+assert list("€".encode("cp1252")) == [0x80]
+
+assert bytes([0xc3, 0xa9]).decode() == "é"
+assert bytes([0xe9]).decode("latin-1") == "é"
+assert bytes([0x80, 0x9f]).decode("cp1252") == "€Ÿ"
+assert bytes([0xff, 0xfe, 97, 0]).decode("utf-16") == "a"
+assert bytes([0xfe, 0xff, 0, 97]).decode("utf-16") == "a"
+assert bytes([0, 0, 0xfe, 0xff, 0, 0, 0, 97]).decode("utf-32") == "a"
+assert bytes([0xd8, 0x3d, 0xde, 0x00]).decode("utf-16-be") == chr(0x1f600)
+assert bytearray([0xc3, 0xa9]).decode("utf-8") == "é"
+assert str(bytes([0xc3, 0xa9]), "utf-8") == "é"
+assert str(bytes([0xe9]), encoding="latin-1") == "é"
+assert list(bytes("é", "utf-8")) == [0xc3, 0xa9]
+assert bytearray("é", "latin-1") == bytearray([0xe9])
+assert_raises(TypeError, lambda: bytes("abc"))
+assert_raises(TypeError, lambda: str("abc", "utf-8"))
+
+# Error handlers
+data = bytes([97, 0xff, 98])
+assert data.decode("utf-8", "replace") == "a�b"
+assert data.decode("utf-8", "ignore") == "ab"
+assert data.decode("utf-8", "backslashreplace") == "a\\xffb"
+assert_raises(TypeError, lambda: data.decode("utf-8", "xmlcharrefreplace"))
+try:
+    escaped = data.decode("utf-8", "surrogateescape")
+except LookupError:
+    # Strings can't hold the lone surrogates this decodes to yet. Handlers
+    # are only looked up on an error, so clean data still decodes.
+    assert b"ab".decode("utf-8", "surrogateescape") == "ab"
+else:
+    assert len(escaped) == 3
+    assert escaped.encode("utf-8", "surrogateescape") == data
+    assert_raises(UnicodeEncodeError, lambda: escaped.encode("ascii"))
+
+assert "aé€".encode("ascii", "replace") == b"a??"
+assert "aé€".encode("ascii", "ignore") == b"a"
+assert "aé€".encode("ascii", "backslashreplace") == b"a\\xe9\\u20ac"
+assert "aé€".encode("ascii", "xmlcharrefreplace") == b"a&#233;&#8364;"
+assert "aé€".encode("latin-1", "replace") == bytes([97, 0xe9, 63])
+
+try:
+    data.decode("utf-8")
+except UnicodeDecodeError as e:
+    assert e.encoding == "utf-8"
+    assert e.start == 1
+    assert e.end == 2
+    assert e.reason == "invalid start byte"
+else:
+    assert False, "decoding did not fail"
+
+try:
+    "aéé".encode("ascii")
+except UnicodeEncodeError as e:
+    assert e.start == 1
+    assert e.end == 3
+    assert e.reason == "ordinal not in range(128)"
+else:
+    assert False, "encoding did not fail"
+
+assert_raises(UnicodeDecodeError, lambda: bytes([0xc3]).decode("utf-8"))
+assert_raises(UnicodeDecodeError, lambda: bytes([0x81]).decode("cp1252"))
+assert_raises(UnicodeDecodeError, lambda: bytes([97]).decode("utf-16-le"))
+assert_raises(LookupError, lambda: "é".encode("ascii", "no-such-handler"))
+
+# Incremental codecs
+decoder = codecs.getincrementaldecoder("utf-8")()
+assert isinstance(decoder, codecs.IncrementalDecoder)
+assert decoder.errors == "strict"
+assert decoder.decode(bytes([97, 0xe2, 0x82])) == "a"
+assert decoder.decode(bytes([0xac]), final=True) == "€"
+assert decoder.decode(bytes([0xe2])) == ""
+assert_raises(UnicodeDecodeError, lambda: decoder.decode(b"", True))
+decoder.reset()
+assert decoder.decode(b"ok") == "ok"
+
+decoder = codecs.getincrementaldecoder("utf-16")(errors="strict")
+assert decoder.decode(bytes([0xfe])) == ""
+assert decoder.decode(bytes([0xff, 0])) == ""
+assert decoder.decode(bytes([97]), True) == "a"
+
+encoder = codecs.getincrementalencoder("utf-16")()
+assert isinstance(encoder, codecs.IncrementalEncoder)
+assert len(encoder.encode("a")) == 4
+assert len(encoder.encode("a")) == 2
+encoder.reset()
+assert len(encoder.encode("a", final=True)) == 4
+
+# codecs.encode and codecs.decode
+assert codecs.encode("é", "latin-1") == bytes([0xe9])
+assert codecs.decode(bytes([0xe9]), "latin-1") == "é"
+assert codecs.decode(b"abc") == "abc"
+
+# A codec found by a registered search function
+def rot13(text):
+    result = ""
+    for c in text:
+        if "a" <= c and c <= "z":
+            c = chr((ord(c) - 97 + 13) % 26 + 97)
+        result += c
+    return result
+
+def search(name):
+    if name == "test.rot13":
+        def encode(text, errors="strict"):
+            return (rot13(text).encode("ascii"), len(text))
+        def decode(data, errors="strict"):
+            return (rot13(bytes(data).decode("ascii")), len(data))
+        return codecs.CodecInfo(encode, decode, name="test.rot13")
+    return None
+
+codecs.register(search)
+assert codecs.lookup("test.rot13").name == "test.rot13"
+assert "hello".encode("test.rot13") == b"uryyb"
+assert b"uryyb".decode("test.rot13") == "hello"
+assert_raises(TypeError, lambda: codecs.register(1))
+
+class Upper(codecs.IncrementalEncoder):
+    def encode(self, input, final=False):
+        return input.upper().encode("ascii", self.errors)
+
+assert Upper().encode("abc") == b"ABC"
+assert Upper("ignore").errors == "ignore"
+
+# Text files
+path = "stdlib_codecs.tmp"
+f = io.open(path, "wt", encoding="latin-1")
+f.write("café")
+del f
+assert list(io.open(path, "rb").read()) == [99, 97, 102, 0xe9]
+assert io.open(path, "rt", encoding="latin-1").read() == "café"
+assert io.open(path, "rt", encoding="utf-8", errors="replace").read() == "caf�"
+assert_raises(LookupError, lambda: io.open(path, "rt", encoding="no-such-codec"))
+
+# Any mode without 'b' is a text mode
+f = open(path, "w", encoding="utf-16")
+f.write("héllo")
+del f
+assert open(path, "rb").read() == "héllo".encode("utf-16")
+assert open(path, "r", encoding="utf-16").read() == "héllo"
+assert open(path, encoding="utf-16").read() == "héllo"
+assert open(path, "r", encoding="utf-8", errors="replace").read().endswith("\x00o\x00")
+assert_raises(ValueError, lambda: open(path, "rb", encoding="utf-8"))
+
+# A text file keeps one encoder, so only the first write gets a BOM
+f = open(path, "w", encoding="utf-16")
+assert f.write("ab") == 2
+assert f.write("cd") == 2
+del f
+assert open(path, "rb").read() == "abcd".encode("utf-16")
+assert open(path, encoding="utf-16").read() == "abcd"
+f = open(path, "w", encoding="utf-32")
+f.write("x")
+f.write("é")
+del f
+assert open(path, encoding="utf-32").read() == "xé"
+os.remove(path)
@@ -0,0 +1,117 @@
+import gc
+from testutils import assert_raises
+from _weakref import ref
+
+
+class X:
+    pass
+
+
+def collected(make):
+    """Build a reference cycle with make(), and check that gc frees it."""
+    obj = make()
+    r = ref(obj)
+    del obj
+    assert r() is not None
+    gc.collect()
+    return r() is None
+
+
+def pair():
+    a = X()
+    b = X()
+    a.other = b
+    b.other = a
+    return a
+
+
+def self_list():
+    x = X()
+    x.items = [x]
+    x.items.append(x.items)
+    return x
+
+
+def bound_method():
+    x = X()
+    x.method = x.__init__
+    return x
+
+
+def dict_and_tuple():
+    x = X()
+    x.d = {'t': (x,)}
+    return x
+
+
+def closure():
+    x = X()
+
+    def inner():
+        return x
+    x.inner = inner
+    return x
+
+
+def traceback_frame():
+    x = X()
+    try:
+        raise ValueError
+    except ValueError as e:
+        x.error = e
+    return x
+
+
+for make in [pair, self_list, bound_method, dict_and_tuple, closure, traceback_frame]:
+    assert collected(make), make
+
+# Objects which can still be reached are kept:
+kept = pair()
+r = ref(kept.other)
+gc.collect()
+assert r() is kept.other
+
+assert gc.collect() == 0
+
+x = X()
+container = [x]
+assert any(referrer is container for referrer in gc.get_referrers(x))
+assert any(referent is x for referent in gc.get_referents(container))
+
+assert gc.isenabled()
+gc.disable()
+assert not gc.isenabled()
+gc.enable()
+assert gc.isenabled()
+
+threshold = gc.get_threshold()
+assert len(threshold) == 3
+gc.set_threshold(100)
+assert gc.get_threshold()[0] == 100
+gc.set_threshold(*threshold)
+assert len(gc.get_count()) == 3
+
+# Cycles are also collected automatically:
+first = ref(pair())
+for i in range(10000):
+    pair()
+assert first() is None
+
+# Objects which survive a collection move to an older generation, which
+# younger collections leave alone:
+old = pair()
+r = ref(old)
+gc.collect()
+del old
+assert gc.collect(0) == 0
+assert r() is not None
+gc.collect()
+assert r() is None
+
+assert_raises(ValueError, lambda: gc.collect(3))
+assert_raises(ValueError, lambda: gc.collect(-1))
+
+gc.set_threshold(500, 5, 5)
+assert gc.get_threshold() == (500, 5, 5)
+gc.set_threshold(*threshold)
+assert gc.get_threshold() == threshold
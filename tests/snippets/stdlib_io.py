@@ -1,4 +1,5 @@
 from io import BufferedReader, FileIO
+from testutils import assertRaises
 
 fi = FileIO('README.md')
 bb = BufferedReader(fi)
@@ -8,3 +9,15 @@ result = bb.read()
 assert len(result) <= 8*1024
 assert len(result) >= 0
 assert isinstance(result, bytes)
+
+# readinto reads into any writable buffer
+data = FileIO('README.md').read()
+fi = FileIO('README.md')
+buffer = bytearray(10)
+assert fi.readinto(memoryview(buffer)[5:]) == 5
+assert buffer[5:] == data[:5]
+assert fi.readinto(buffer) == 10
+assert buffer == data[5:15]
+
+with assertRaises(TypeError):
+    fi.readinto(b'readonly')
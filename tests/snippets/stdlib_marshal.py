@@ -0,0 +1,38 @@
+import marshal
+from testutils import assert_raises
+
+values = [
+    None, True, False, ..., 0, -1, 2 ** 100, -(3 ** 70), 1.5, -0.0, 2j, complex(1.5, -3),
+    '', 'text', 'ünïcödé', b'', b'\x00\xffbytes',
+    (), (1, 'two', (3.0,)), [], [1, [2, [3]]],
+]
+for value in values:
+    assert marshal.loads(marshal.dumps(value)) == value, value
+    assert type(marshal.loads(marshal.dumps(value))) is type(value)
+
+assert marshal.loads(marshal.dumps({1, 2, 3})) == {1, 2, 3}
+assert marshal.loads(marshal.dumps(frozenset(['a', 'b']))) == frozenset(['a', 'b'])
+assert type(marshal.loads(marshal.dumps(frozenset()))) is frozenset
+
+d = marshal.loads(marshal.dumps({'a': 1, 'b': [2]}))
+assert type(d) is dict
+assert sorted(d.keys()) == ['a', 'b']
+assert d['a'] == 1 and d['b'] == [2]
+
+nested = marshal.loads(marshal.dumps({'x': (None, [b'y'])}))
+assert nested['x'][1][0] == b'y'
+
+# Code objects survive the round trip and can still be run.
+code = compile('def f(a, b=2):\n    return a * b\nresult = f(21)\n', '<marshal>', 'exec')
+scope = {}
+exec(marshal.loads(marshal.dumps(code)), scope)
+assert scope['result'] == 42
+
+class C:
+    pass
+
+assert_raises(ValueError, lambda: marshal.dumps(C()))
+assert_raises(ValueError, lambda: marshal.dumps([1, C()]))
+assert_raises(EOFError, lambda: marshal.loads(b''))
+assert_raises(EOFError, lambda: marshal.loads(bytes(list(marshal.dumps('text'))[:-1])))
+assert isinstance(marshal.version, int)
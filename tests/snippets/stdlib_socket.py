@@ -21,6 +21,18 @@ recv_a = connection.recv(len(MESSAGE_A))
 recv_b = connector.recv(len(MESSAGE_B))
 assert recv_a == MESSAGE_A
 assert recv_b == MESSAGE_B
+
+# recv_into accepts any writable buffer
+buffer = bytearray(8)
+connector.send(MESSAGE_A)
+assert connection.recv_into(memoryview(buffer)[2:], 3) == 3
+assert buffer == bytearray(b'\x00\x00aaa\x00\x00\x00')
+assert connection.recv_into(buffer) == 1
+assert buffer[0] == MESSAGE_A[0]
+with assertRaises(TypeError):
+	connection.recv_into(b'readonly')
+with assertRaises(ValueError):
+	connection.recv_into(bytearray(2), 3)
 connection.close()
 connector.close()
 listener.close()
@@ -0,0 +1,165 @@
+import sys
+import traceback
+
+from testutils import assert_raises
+
+
+def raise_value_error():
+    raise ValueError('oops')
+
+
+def call_raise():
+    raise_value_error()
+
+
+try:
+    call_raise()
+except ValueError as e:
+    exc = e
+    tb = e.__traceback__
+    assert sys.exc_info()[1] is e
+    assert sys.exc_info()[2] is tb
+
+# The traceback starts at the frame handling the exception and ends at
+# the frame that raised it:
+assert tb.tb_frame.f_code.co_name == '<module>'
+assert tb.tb_lineno == 16
+assert tb.tb_next.tb_frame.f_code.co_name == 'call_raise'
+assert tb.tb_next.tb_lineno == 12
+assert tb.tb_next.tb_next.tb_lineno == 8
+assert tb.tb_next.tb_next.tb_next is None
+assert isinstance(tb.tb_lasti, int)
+
+entries = traceback.extract_tb(tb)
+assert len(entries) == 3
+filename, lineno, name, line = entries[2]
+assert filename.endswith('stdlib_traceback.py')
+assert lineno == 8
+assert name == 'raise_value_error'
+assert line == "raise ValueError('oops')"
+
+lines = traceback.format_exception(type(exc), exc, tb)
+assert lines[0] == 'Traceback (most recent call last):\n'
+assert lines[-1] == 'ValueError: oops\n'
+assert lines[1].endswith(', line 16, in <module>\n    call_raise()\n')
+
+assert traceback.format_tb(None) == []
+assert traceback.format_exception_only(type(exc), exc) == ['ValueError: oops\n']
+
+# Chained exceptions are formatted before the exception itself:
+try:
+    try:
+        raise_value_error()
+    except ValueError:
+        raise KeyError('second')
+except KeyError:
+    text = traceback.format_exc()
+assert 'ValueError: oops' in text
+assert 'During handling of the above exception' in text
+assert text.endswith("KeyError: 'second'\n") or text.endswith('KeyError: second\n')
+
+assert sys.exc_info() == (None, None, None)
+
+e = ValueError('fresh')
+assert e.__traceback__ is None
+assert e.with_traceback(tb) is e
+assert e.__traceback__ is tb
+assert_raises(TypeError, lambda: e.with_traceback(5))
+
+
+# An exception re-raised in the frame it is unwinding through doesn't get
+# another entry for that frame:
+def reraise_unmatched():
+    try:
+        raise_value_error()
+    except KeyError:
+        pass
+
+
+def reraise_after_finally():
+    try:
+        try:
+            raise_value_error()
+        finally:
+            pass
+    finally:
+        pass
+
+
+def reraise_bare():
+    try:
+        raise_value_error()
+    except ValueError:
+        raise
+
+
+for reraise, expected in [(reraise_unmatched, 'reraise_unmatched'),
+                          (reraise_after_finally, 'reraise_after_finally'),
+                          (reraise_bare, 'reraise_bare')]:
+    try:
+        reraise()
+    except ValueError as e:
+        names = [name for _, _, name, _ in traceback.extract_tb(e.__traceback__)]
+    assert names == ['<module>', expected, 'raise_value_error'], names
+
+
+# Output goes to the given file, or to sys.stderr:
+import io
+
+try:
+    call_raise()
+except ValueError as e:
+    exc = e
+
+out = io.StringIO()
+traceback.print_exception(type(exc), exc, exc.__traceback__, file=out)
+assert out.getvalue() == ''.join(
+    traceback.format_exception(type(exc), exc, exc.__traceback__))
+
+out = io.StringIO()
+traceback.print_tb(exc.__traceback__, file=out)
+assert out.getvalue() == ''.join(traceback.format_tb(exc.__traceback__))
+
+stderr = io.StringIO()
+had_stderr = hasattr(sys, 'stderr')
+old_stderr = getattr(sys, 'stderr', None)
+sys.stderr = stderr
+try:
+    try:
+        call_raise()
+    except ValueError:
+        traceback.print_exc()
+finally:
+    if had_stderr:
+        sys.stderr = old_stderr
+    else:
+        del sys.stderr
+assert stderr.getvalue().startswith('Traceback (most recent call last):\n')
+assert stderr.getvalue().endswith('ValueError: oops\n')
+
+# A limit keeps the outermost entries, a negative one the innermost:
+names = [name for _, _, name, _ in traceback.extract_tb(exc.__traceback__, 1)]
+assert names == ['<module>'], names
+names = [name for _, _, name, _ in
+         traceback.extract_tb(exc.__traceback__, limit=-1)]
+assert names == ['raise_value_error'], names
+assert len(traceback.format_tb(exc.__traceback__, limit=2)) == 2
+
+lines = traceback.format_exception(type(exc), exc, exc.__traceback__, limit=1)
+assert len(lines) == 3, lines
+assert lines[1].endswith('in <module>\n    call_raise()\n'), lines
+
+# Without the chain, only the last exception is formatted:
+try:
+    try:
+        raise_value_error()
+    except ValueError:
+        raise KeyError('second')
+except KeyError as e:
+    chained = e
+lines = traceback.format_exception(type(chained), chained,
+                                   chained.__traceback__, chain=False)
+text = ''.join(lines)
+assert 'During handling' not in text
+assert 'ValueError' not in text
+assert text.startswith('Traceback (most recent call last):\n')
@@ -132,3 +132,155 @@ def try_mutate_str():
    word[0] = 'x'
 
 assert_raises(TypeError, try_mutate_str)
+
+# Operators
+assert 3 * "ab" == "ababab"
+assert "ab" * 0 == ""
+assert "ab" * -2 == ""
+assert "a" != "b"
+assert not "a" != "a"
+assert list("héllo") == ["h", "é", "l", "l", "o"]
+assert [c for c in ""] == []
+assert "é" in "héllo"
+
+# printf-style formatting
+assert "%s" % "x" == "x"
+assert "%s" % (1,) == "1"
+assert "%s" % [1, 2] == "[1, 2]"
+assert "%r" % "x" == "'x'"
+assert "%a" % "é" == "'\\xe9'"
+assert "%5s|%-5s|" % ("ab", "cd") == "   ab|cd   |"
+assert "%.2s" % "hello" == "he"
+assert "%d %i %u" % (1, -2, 3) == "1 -2 3"
+assert "%d" % 3.9 == "3"
+assert "%5d|%-5d|%05d" % (42, 42, -42) == "   42|42   |-0042"
+assert "%+d|% d|%+d" % (5, 5, -5) == "+5| 5|-5"
+assert "%x %X %#x %o %#o" % (255, 255, 255, 8, 8) == "ff FF 0xff 10 0o10"
+assert "%f" % 1.5 == "1.500000"
+assert "%.2f|%8.3f|%-8.1f|" % (3.14159, 2.5, 2.5) == "3.14|   2.500|2.5     |"
+assert "%e" % 12345.678 == "1.234568e+04"
+assert "%.2E" % 0.000123 == "1.23E-04"
+assert "%g %g %g" % (0.0001, 123456789.0, 2.5) == "0.0001 1.23457e+08 2.5"
+assert "%c%c" % (65, "b") == "Ab"
+assert "100%%" % () == "100%"
+assert "%*d|%-*d|%.*f" % (4, 1, 4, 2, 2, 3.14159) == "   1|2   |3.14"
+assert "%(a)s and %(b)d" % {"a": "x", "b": 3} == "x and 3"
+assert "%(a)5s" % {"a": "x"} == "    x"
+
+assert_raises(TypeError, lambda: "%s %s" % (1,))
+assert_raises(TypeError, lambda: "%s" % (1, 2))
+assert_raises(TypeError, lambda: "%d" % "a")
+assert_raises(TypeError, lambda: "%x" % 1.5)
+assert_raises(TypeError, lambda: "%c" % "ab")
+assert_raises(TypeError, lambda: "%(a)s" % (1,))
+assert_raises(ValueError, lambda: "%" % ())
+assert_raises(ValueError, lambda: "%y" % 1)
+assert_raises(ValueError, lambda: "%(a" % {"a": 1})
+assert_raises(TypeError, lambda: "%(a)s %s" % {"a": 1})
+assert "%s %(a)s" % {"a": 1} == "{'a': 1} 1"
+
+# Splitting and partitioning
+assert "a b  c".split() == ["a", "b", "c"]
+assert "  a b c  ".split(None, 1) == ["a", "b c  "]
+assert "a,b,,c".split(",") == ["a", "b", "", "c"]
+assert "a,b,,c".split(",", 2) == ["a", "b", ",c"]
+assert "a,b,c".rsplit(",", 1) == ["a,b", "c"]
+assert "  a b c  ".rsplit(None, 1) == ["  a b", "c"]
+assert_raises(ValueError, lambda: "abc".split(""))
+assert "ab\ncd\r\nef\rg".splitlines() == ["ab", "cd", "ef", "g"]
+assert "ab\ncd\r\n".splitlines(True) == ["ab\n", "cd\r\n"]
+assert "a,b,c".partition(",") == ("a", ",", "b,c")
+assert "abc".partition("x") == ("abc", "", "")
+assert "a,b,c".rpartition(",") == ("a,b", ",", "c")
+assert "abc".rpartition("x") == ("", "", "abc")
+assert_raises(ValueError, lambda: "abc".partition(""))
+assert_raises(ValueError, lambda: "abc".rpartition(""))
+
+# Padding and stripping
+assert "a\tbc\tdef".expandtabs(4) == "a   bc  def"
+assert "ab\tc\nd\te".expandtabs(4) == "ab  c\nd   e"
+assert "a\tb".expandtabs(0) == "ab"
+assert "42".zfill(5) == "00042"
+assert "-42".zfill(6) == "-00042"
+assert "+7".zfill(3) == "+07"
+assert "abc".zfill(2) == "abc"
+assert "ab".center(7, "*") == "***ab**"
+assert "abc".center(6) == " abc  "
+assert "ab".ljust(5, "-") == "ab---"
+assert "ab".rjust(5, "-") == "---ab"
+assert "ab".ljust(-1) == "ab"
+assert_raises(TypeError, lambda: "ab".center(5, "--"))
+assert "xxhixx".strip("x") == "hi"
+assert "xxhixx".lstrip("x") == "hixx"
+assert "xxhixx".rstrip("x") == "xxhi"
+assert "  hi  ".strip(None) == "hi"
+
+# Case handling
+assert "ß".casefold() == "ss"
+assert "ABC".casefold() == "abc"
+assert "hELLO wORLD".capitalize() == "Hello world"
+assert "Hello World".swapcase() == "hELLO wORLD"
+assert "hELLO wORLD".title() == "Hello World"
+assert "abc".startswith(("x", "a"))
+assert "abc".endswith(("x", "c"))
+assert not "abc".endswith(("x", "y"))
+
+# Predicates
+assert "abc_1".isidentifier()
+assert "_".isidentifier()
+assert "héllo".isidentifier()
+assert not "1a".isidentifier()
+assert not "".isidentifier()
+assert not "a-b".isidentifier()
+assert "ab c".isprintable()
+assert "".isprintable()
+assert not "ab\n".isprintable()
+assert not chr(127).isprintable()
+assert "abc".isalpha()
+assert not "ab1".isalpha()
+
+# Translation
+table = str.maketrans("ab", "xy", "c")
+assert "abcd".translate(table) == "xyd"
+assert "abc".translate(str.maketrans({"a": "1", "b": None})) == "1c"
+assert "abc".translate(str.maketrans({"a": 98})) == "bbc"
+table = str.maketrans("ab", "xy", "c")
+assert len(table) == 3
+assert table[97] == 120 and table[98] == 121 and table[99] is None
+table = str.maketrans({97: "b", "c": "d"})
+assert len(table) == 2
+assert table[97] == "b" and table[99] == "d"
+assert "abc".translate(str.maketrans({97: "b"})) == "bbc"
+assert "abc".translate({97: "x", 98: None}) == "xc"
+assert_raises(TypeError, lambda: str.maketrans({1.5: "a"}))
+assert_raises(ValueError, lambda: str.maketrans({"ab": "a"}))
+assert_raises(ValueError, lambda: str.maketrans("ab", "x"))
+assert_raises(TypeError, lambda: str.maketrans("ab"))
+
+# format_map
+assert "{a}-{b}".format_map({"a": 1, "b": 2}) == "1-2"
+assert "{a:>3}".format_map({"a": "x"}) == "  x"
+assert_raises(KeyError, lambda: "{c}".format_map({"a": 1}))
+assert_raises(ValueError, lambda: "{}".format_map({"a": 1}))
+
+# Encoding
+assert list("héllo".encode()) == [104, 195, 169, 108, 108, 111]
+assert list("héllo".encode("utf-8")) == [104, 195, 169, 108, 108, 111]
+assert list("é".encode("latin-1")) == [233]
+assert "héllo".encode("ascii", "replace") == b"h?llo"
+assert "héllo".encode("ascii", "ignore") == b"hllo"
+assert "é".encode("ascii", "xmlcharrefreplace") == b"&#233;"
+assert "é".encode("ascii", "backslashreplace") == b"\\xe9"
+assert_raises(UnicodeEncodeError, lambda: "é".encode("ascii"))
+assert_raises(LookupError, lambda: "a".encode("no-such-codec"))
+
+# __format__
+assert "{:>3}".format("x") == "  x"
+assert "{:3}|{:*^5}|{:.2}".format("x", "ab", "xyz") == "x  |*ab**|xy"
+assert format("ab", "<4") == "ab  "
+assert_raises(ValueError, lambda: format("ab", "+"))
+assert_raises(ValueError, lambda: format("ab", "d"))
+
+# escape sequences
+assert '\x41\101é\U0001F600' == 'AA' + chr(0xe9) + chr(0x1f600)
+assert len('\0') == 1
@@ -0,0 +1,57 @@
+from testutils import assert_raises
+
+
+def compile_error(source, filename='<test>'):
+    try:
+        compile(source, filename, 'exec')
+    except SyntaxError as e:
+        return e
+    assert False, 'expected a SyntaxError'
+
+
+e = compile_error('x = 1\nfor i in []:\n    z = 1 + + * 3\n', 'bad.py')
+assert e.filename == 'bad.py'
+assert e.lineno == 3
+assert e.offset == 15
+assert e.text == '    z = 1 + + * 3\n'
+
+e = compile_error('def f():\n    pass\nbreak\n')
+assert e.lineno == 3
+assert e.offset == 1
+
+# Unexpected end of input points past the end of the last line:
+e = compile_error('y = (1 +\n')
+assert e.lineno == 1
+
+# Errors from the symbol table analysis carry a location too:
+e = compile_error('def f():\n    nonlocal x\n')
+assert e.lineno == 2
+assert e.offset == 5
+assert e.filename == '<test>'
+
+assert_raises(SyntaxError, lambda: eval('1 +'))
+
+e = SyntaxError('plain')
+assert e.lineno is None
+assert e.offset is None
+assert e.text is None
+assert e.filename is None
+
+e = SyntaxError('bad', ('a.py', 2, 5, 'x = $\n'))
+assert e.msg == 'bad'
+assert e.filename == 'a.py'
+assert e.lineno == 2
+assert e.offset == 5
+assert e.text == 'x = $\n'
+assert e.args == ('bad', ('a.py', 2, 5, 'x = $\n'))
+assert_raises(TypeError, lambda: SyntaxError('bad', ('a.py', 2)))
+
+# Errors found by the lexer carry their location too:
+e = compile_error('x = 1\ny = "abc\n')
+assert e.lineno == 2
+assert e.offset is not None and e.offset > 1
+assert e.text.rstrip('\n') == 'y = "abc'
+
+e = compile_error('x = 1\n\ny = (1 ?\n')
+assert e.lineno == 3
+assert e.offset == 8
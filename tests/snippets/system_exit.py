@@ -0,0 +1,64 @@
+import atexit
+import sys
+
+from testutils import assert_raises
+
+assert_raises(SystemExit, sys.exit)
+
+try:
+    sys.exit(3)
+except SystemExit as e:
+    assert e.code == 3
+    assert e.args == (3,)
+
+try:
+    sys.exit()
+except SystemExit as e:
+    assert e.code is None
+
+try:
+    raise SystemExit('message')
+except SystemExit as e:
+    assert e.code == 'message'
+
+assert SystemExit(1, 2).code == (1, 2)
+
+# SystemExit and KeyboardInterrupt are not caught by `except Exception`:
+try:
+    try:
+        sys.exit(1)
+    except Exception:
+        assert False
+except BaseException as e:
+    assert isinstance(e, SystemExit)
+
+# atexit callbacks run most recently registered first:
+calls = []
+
+def record(*args, **kwargs):
+    calls.append((args, kwargs.get('key')))
+
+atexit._clear()
+assert atexit.register(record, 1) is record
+atexit.register(record, 2, key='value')
+assert atexit._ncallbacks() == 2
+atexit._run_exitfuncs()
+assert calls == [((2,), 'value'), ((1,), None)]
+assert atexit._ncallbacks() == 0
+
+def unregistered():
+    calls.append('unregistered')
+
+calls = []
+atexit.register(unregistered)
+atexit.register(record, 'kept')
+atexit.unregister(unregistered)
+atexit._run_exitfuncs()
+assert calls == [(('kept',), None)]
+
+# Errors in callbacks do not stop the remaining callbacks:
+calls = []
+atexit.register(record, 'after error')
+atexit.register(lambda: 1 / 0)
+atexit._run_exitfuncs()
+assert calls == [(('after error',), None)]
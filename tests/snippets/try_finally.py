@@ -0,0 +1,176 @@
+from testutils import assert_raises
+
+
+def return_in_try(log):
+    try:
+        log.append('try')
+        return 'try'
+    finally:
+        log.append('finally')
+
+log = []
+assert return_in_try(log) == 'try'
+assert log == ['try', 'finally']
+
+
+def return_in_finally():
+    try:
+        return 'try'
+    finally:
+        return 'finally'
+
+assert return_in_finally() == 'finally'
+
+
+def finally_swallows_exception():
+    try:
+        raise ValueError()
+    finally:
+        return 'finally'
+
+assert finally_swallows_exception() == 'finally'
+
+
+def break_and_continue(log):
+    for i in range(4):
+        try:
+            if i == 1:
+                continue
+            if i == 3:
+                break
+            log.append(i)
+        finally:
+            log.append('finally ' + str(i))
+    return log
+
+assert break_and_continue([]) == [
+    0, 'finally 0', 'finally 1', 2, 'finally 2', 'finally 3']
+
+
+def while_loop_break():
+    count = 0
+    while True:
+        try:
+            break
+        finally:
+            count += 1
+    return count
+
+assert while_loop_break() == 1
+
+
+def exception_propagates(log):
+    try:
+        try:
+            raise KeyError('x')
+        finally:
+            log.append('inner')
+    except KeyError:
+        log.append('caught')
+    return log
+
+assert exception_propagates([]) == ['inner', 'caught']
+
+
+def unhandled_exception(log):
+    try:
+        raise KeyError()
+    except ValueError:
+        log.append('wrong handler')
+    finally:
+        log.append('finally')
+
+log = []
+assert_raises(KeyError, lambda: unhandled_exception(log))
+assert log == ['finally']
+
+
+def exception_in_handler(log):
+    try:
+        raise KeyError()
+    except KeyError:
+        raise ValueError()
+    finally:
+        log.append('finally')
+
+log = []
+assert_raises(ValueError, lambda: exception_in_handler(log))
+assert log == ['finally']
+
+
+def return_in_handler(log):
+    try:
+        raise KeyError()
+    except KeyError:
+        return 'handler'
+    else:
+        log.append('else')
+    finally:
+        log.append('finally')
+
+log = []
+assert return_in_handler(log) == 'handler'
+assert log == ['finally']
+
+
+def else_and_finally(log):
+    try:
+        log.append('try')
+    except KeyError:
+        log.append('handler')
+    else:
+        log.append('else')
+    finally:
+        log.append('finally')
+    return log
+
+assert else_and_finally([]) == ['try', 'else', 'finally']
+
+
+def nested_finally(log):
+    try:
+        try:
+            return 1
+        finally:
+            log.append('inner')
+    finally:
+        log.append('outer')
+
+log = []
+assert nested_finally(log) == 1
+assert log == ['inner', 'outer']
+
+
+class Manager:
+    def __init__(self, log):
+        self.log = log
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, exc_type, exc_val, exc_tb):
+        self.log.append('exit')
+
+
+def with_and_finally(log):
+    try:
+        with Manager(log):
+            return 'with'
+    finally:
+        log.append('finally')
+
+log = []
+assert with_and_finally(log) == 'with'
+assert log == ['exit', 'finally']
+
+
+def generator_finally(log):
+    try:
+        yield 1
+        yield 2
+    finally:
+        log.append('finally')
+
+log = []
+assert list(generator_finally(log)) == [1, 2]
+assert log == ['finally']
@@ -0,0 +1,238 @@
+import gc
+import weakref
+from testutils import assert_raises
+
+
+class X:
+    pass
+
+
+# __del__ is called once the last reference goes away
+deleted = []
+
+
+class WithDel:
+    def __init__(self, name):
+        self.name = name
+
+    def __del__(self):
+        deleted.append(self.name)
+
+
+a = WithDel('a')
+del a
+assert deleted == ['a']
+
+
+def scope():
+    b = WithDel('b')
+
+scope()
+assert deleted == ['a', 'b']
+
+# ... also for objects freed by the cycle collector
+c = WithDel('c')
+c.me = c
+del c
+assert deleted == ['a', 'b']
+assert gc.collect() > 0
+assert deleted == ['a', 'b', 'c']
+
+
+# __del__ is only ever called once, even when it resurrects the object
+resurrected = []
+
+
+class Lazarus:
+    def __del__(self):
+        resurrected.append(self)
+
+
+l = Lazarus()
+ident = id(l)
+del l
+assert len(resurrected) == 1
+assert id(resurrected[0]) == ident
+resurrected.clear()
+assert len(resurrected) == 0
+
+
+# __del__ is also called for subclasses of builtin types, with the contents
+# still there
+class DelDict(dict):
+    def __del__(self):
+        deleted.append(sorted(self.items()))
+
+
+class DelSet(set):
+    def __del__(self):
+        resurrected.append(self)
+
+
+dd = DelDict(x=1)
+del dd
+assert deleted[-1] == [('x', 1)]
+
+ds = DelSet([1, 2])
+ident = id(ds)
+del ds
+assert len(resurrected) == 1
+assert id(resurrected[0]) == ident
+assert resurrected[0] == {1, 2}
+resurrected.clear()
+
+
+# Weak references still work during __del__, and die afterwards
+class Checked:
+    def __del__(self):
+        deleted.append(checked_ref() is self)
+
+
+checked = Checked()
+checked_ref = weakref.ref(checked)
+del checked
+assert deleted[-1] is True
+assert checked_ref() is None
+
+
+# Objects freed by the cycle collector get __del__ called on themselves, so
+# they can be resurrected; this also works for subclasses of builtin types
+class DelList(list):
+    def __del__(self):
+        resurrected.append(self)
+
+
+d = DelList([1, 2])
+d.append(d)
+ident = id(d)
+del d
+gc.collect()
+assert len(resurrected) == 1
+assert id(resurrected[0]) == ident
+assert resurrected[0][:2] == [1, 2]
+assert resurrected[0][2] is resurrected[0]
+resurrected.clear()
+gc.collect()
+assert len(resurrected) == 0
+
+# Exceptions in __del__ are ignored
+class Failing:
+    def __del__(self):
+        raise ValueError('ignored')
+
+
+f = Failing()
+del f
+
+
+# Weak reference callbacks
+calls = []
+o = X()
+r = weakref.ref(o, calls.append)
+assert r.__callback__ is not None
+assert weakref.ref(o).__callback__ is None
+assert r() is o
+del o
+assert calls == [r]
+assert r() is None
+
+
+# A dead weakref doesn't have its callback called
+o = X()
+r = weakref.ref(o, calls.append)
+del r
+del o
+assert len(calls) == 1
+
+
+# finalize
+log = []
+o = X()
+fin = weakref.finalize(o, log.append, 'finalized')
+assert fin.alive
+assert fin.atexit
+assert fin.peek()[0] is o
+del o
+assert log == ['finalized']
+assert not fin.alive
+assert fin() is None
+assert fin.peek() is None
+
+o = X()
+fin = weakref.finalize(o, lambda *args, **kwargs: (args, kwargs), 1, key=2)
+args, kwargs = fin()
+assert args == (1,)
+assert kwargs['key'] == 2
+assert not fin.alive
+del o
+
+o = X()
+fin = weakref.finalize(o, log.append, 'detached')
+obj, func, args, kwargs = fin.detach()
+assert obj is o
+assert args == ('detached',)
+assert not fin.alive
+del o
+assert log == ['finalized']
+
+# Finalizers left alive run at exit
+o = X()
+at_exit = weakref.finalize(o, print, 'finalizer called at exit')
+
+
+# WeakValueDictionary
+d = weakref.WeakValueDictionary()
+o1, o2 = X(), X()
+d['a'] = o1
+d['b'] = o2
+assert len(d) == 2
+assert d['a'] is o1
+assert 'b' in d
+assert d.get('c') is None
+assert sorted(d.keys()) == ['a', 'b']
+del o1
+assert len(d) == 1
+assert 'a' not in d
+assert_raises(KeyError, lambda: d['a'])
+assert list(d.items()) == [('b', o2)]
+assert d.pop('b') is o2
+assert len(d) == 0
+assert d.setdefault('c', o2) is o2
+assert d.copy()['c'] is o2
+d.clear()
+assert len(d) == 0
+
+
+# WeakKeyDictionary
+d = weakref.WeakKeyDictionary()
+k1, k2 = X(), X()
+d[k1] = 1
+d[k2] = 2
+assert len(d) == 2
+assert d[k1] == 1
+assert k2 in d
+del k1
+assert len(d) == 1
+assert list(d.values()) == [2]
+assert d.get(k2) == 2
+del d[k2]
+assert len(d) == 0
+assert_raises(KeyError, lambda: d[k2])
+
+
+# WeakSet
+s = weakref.WeakSet()
+e1, e2 = X(), X()
+s.add(e1)
+s.add(e2)
+s.add(e2)
+assert len(s) == 2
+assert e1 in s
+del e1
+assert len(s) == 1
+assert list(s) == [e2]
+s.discard(e2)
+assert len(s) == 0
+assert_raises(KeyError, lambda: s.remove(e2))
+s = weakref.WeakSet([e2])
+assert e2 in s
@@ -0,0 +1,96 @@
+# Tests for `rustpython compileall`, which compiles a tree of modules into
+# an archive that can be run without the sources.
+
+
+import os
+import shutil
+import subprocess
+import tempfile
+import textwrap
+import unittest
+
+
+ROOT_DIR = '..'
+
+
+def rustpython_binary():
+    target = 'release'
+    if os.environ.get('CODE_COVERAGE', 'false') == 'true':
+        target = 'debug'
+    return os.path.abspath(os.path.join(ROOT_DIR, 'target', target, 'rustpython'))
+
+
+def write_tree(root, files):
+    for name, source in files.items():
+        path = os.path.join(root, name)
+        os.makedirs(os.path.dirname(path), exist_ok=True)
+        with open(path, 'w') as f:
+            f.write(textwrap.dedent(source))
+
+
+def run(*args):
+    return subprocess.run(
+        [rustpython_binary()] + list(args),
+        stdout=subprocess.PIPE, stderr=subprocess.PIPE,
+        universal_newlines=True)
+
+
+class CompileAllTestCase(unittest.TestCase):
+    @classmethod
+    def setUpClass(cls):
+        subprocess.check_call(['cargo', 'build', '--release'])
+
+    def setUp(self):
+        self.tmp_dir = tempfile.mkdtemp()
+        self.src_dir = os.path.join(self.tmp_dir, 'src')
+        self.archive = os.path.join(self.tmp_dir, 'app.rpa')
+
+    def tearDown(self):
+        shutil.rmtree(self.tmp_dir)
+
+    def test_run_archive_without_sources(self):
+        write_tree(self.src_dir, {
+            '__main__.py': '''
+                import util
+                from pkg import VALUE
+                from pkg.helper import greet
+                print(greet('archive'), VALUE, util.NAME)
+            ''',
+            'util.py': "NAME = 'util'\n",
+            'pkg/__init__.py': 'VALUE = 42\n',
+            'pkg/helper.py': '''
+                def greet(name):
+                    return 'hello ' + name
+            ''',
+        })
+        result = run('compileall', self.src_dir, '-o', self.archive)
+        self.assertEqual(result.returncode, 0, result.stderr)
+
+        # Only the archive is left to import from:
+        shutil.rmtree(self.src_dir)
+        result = run(self.archive)
+        self.assertEqual(result.returncode, 0, result.stdout + result.stderr)
+        self.assertEqual(result.stdout, 'hello archive 42 util\n')
+
+    def test_syntax_error_report(self):
+        write_tree(self.src_dir, {
+            '__main__.py': 'import good\n',
+            'good.py': 'x = 1\n',
+            'bad.py': '''
+                x = 1
+                def f(:
+                    pass
+            ''',
+        })
+        result = run('compileall', self.src_dir, '-o', self.archive)
+        self.assertEqual(result.returncode, 1)
+        self.assertFalse(os.path.exists(self.archive))
+
+        bad_path = os.path.join(self.src_dir, 'bad.py')
+        lines = result.stderr.splitlines()
+        self.assertEqual(len(lines), 1, result.stderr)
+        # The report starts with `file:line:column:`, and the position isn't
+        # repeated in the message:
+        self.assertTrue(
+            lines[0].startswith(bad_path + ':3:7: SyntaxError: '), lines[0])
+        self.assertNotIn('Location', lines[0])
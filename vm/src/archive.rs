@@ -0,0 +1,170 @@
+//! Archives of precompiled modules, to ship and run programs without their sources.
+//!
+//! An archive holds the code of every module of a directory tree, keyed by
+//! module name. A path to an archive can be put on `sys.path`, and the
+//! archive's `__main__` module is run when the archive is run as a script.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::bytecode::CodeObject;
+use crate::compile;
+use crate::error::CompileError;
+use crate::marshal::{self, MarshalError, MarshalResult, Reader, Writer};
+use crate::util;
+use crate::vm::VirtualMachine;
+
+const ARCHIVE_MAGIC: &[u8] = b"RPYA";
+
+#[derive(Default)]
+pub struct Archive {
+    modules: BTreeMap<String, CodeObject>,
+}
+
+impl Archive {
+    pub fn new() -> Self {
+        Archive {
+            modules: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, name: String, code: CodeObject) {
+        self.modules.insert(name, code);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CodeObject> {
+        self.modules.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        for &byte in ARCHIVE_MAGIC {
+            writer.write_u8(byte);
+        }
+        writer.write_u32(marshal::FORMAT_VERSION);
+        writer.write_usize(self.modules.len());
+        for (name, code) in &self.modules {
+            writer.write_str(name);
+            writer.write_code(code);
+        }
+        writer.into_bytes()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> MarshalResult<Archive> {
+        if !data.starts_with(ARCHIVE_MAGIC) {
+            return Err(MarshalError::InvalidTag(
+                data.first().cloned().unwrap_or(0),
+                0,
+            ));
+        }
+        let mut reader = Reader::new(&data[ARCHIVE_MAGIC.len()..]);
+        let version = reader.read_u32()?;
+        if version != marshal::FORMAT_VERSION {
+            return Err(MarshalError::BadVersion(version));
+        }
+        let mut archive = Archive::new();
+        for _ in 0..reader.read_usize()? {
+            let name = reader.read_str()?;
+            archive.insert(name, reader.read_code()?);
+        }
+        Ok(archive)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Archive> {
+        let data = fs::read(path)?;
+        Archive::from_bytes(&data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Compile every `.py` file below `dir`. All syntax errors are collected,
+    /// so that they can be reported at once.
+    pub fn compile_dir(
+        vm: &VirtualMachine,
+        dir: &Path,
+    ) -> io::Result<Result<Archive, Vec<CompileError>>> {
+        let mut archive = Archive::new();
+        let mut errors = vec![];
+        let mut pending = vec![(dir.to_path_buf(), vec![])];
+        while let Some((dir, package)) = pending.pop() {
+            let mut entries = fs::read_dir(&dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+            for path in entries {
+                let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                    Some(file_name) => file_name.to_string(),
+                    None => continue,
+                };
+                if path.is_dir() {
+                    let mut package = package.clone();
+                    package.push(file_name);
+                    pending.push((path, package));
+                    continue;
+                }
+                if !file_name.ends_with(".py") {
+                    continue;
+                }
+                let stem = &file_name[..file_name.len() - 3];
+                let mut name = package.clone();
+                if stem != "__init__" {
+                    name.push(stem.to_string());
+                }
+                if name.is_empty() {
+                    continue;
+                }
+                let source = util::read_file(&path)?;
+                let source_path = path.to_string_lossy().into_owned();
+                match compile::compile(vm, &source, &compile::Mode::Exec, source_path) {
+                    Ok(code) => archive.insert(name.join("."), code.code.clone()),
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        Ok(if errors.is_empty() {
+            Ok(archive)
+        } else {
+            Err(errors)
+        })
+    }
+}
+
+/// Whether the file at `path` is an archive, rather than a source file.
+pub fn is_archive(path: &Path) -> bool {
+    let mut magic = [0; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|()| magic == ARCHIVE_MAGIC)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Archive;
+    use crate::compile;
+    use crate::vm::VirtualMachine;
+
+    #[test]
+    fn test_round_trip() {
+        let vm = VirtualMachine::new();
+        let mut archive = Archive::new();
+        for (name, source) in &[("__main__", "import pkg.mod\n"), ("pkg.mod", "x = 1\n")] {
+            let code =
+                compile::compile(&vm, source, &compile::Mode::Exec, name.to_string()).unwrap();
+            archive.insert(name.to_string(), code.code.clone());
+        }
+        let loaded = Archive::from_bytes(&archive.to_bytes()).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get("pkg.mod"), archive.get("pkg.mod"));
+        assert_eq!(loaded.get("__main__"), archive.get("__main__"));
+        assert!(Archive::from_bytes(b"RPYC").is_err());
+    }
+}
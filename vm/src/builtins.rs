@@ -11,6 +11,7 @@ use num_traits::Signed;
 use crate::compile;
 use crate::import::import_module;
 use crate::obj::objbool;
+use crate::obj::objcell::PyCell;
 use crate::obj::objdict::PyDictRef;
 use crate::obj::objint::{self, PyIntRef};
 use crate::obj::objiter;
@@ -20,8 +21,7 @@ use crate::obj::objtype::{self, PyClassRef};
 use crate::frame::Scope;
 use crate::function::{Args, OptionalArg, PyFuncArgs};
 use crate::pyobject::{
-    DictProtocol, IdProtocol, PyContext, PyIterable, PyObjectRef, PyResult, PyValue, TryFromObject,
-    TypeProtocol,
+    IdProtocol, PyContext, PyIterable, PyObjectRef, PyResult, PyValue, TryFromObject, TypeProtocol,
 };
 use crate::vm::VirtualMachine;
 
@@ -67,8 +67,8 @@ fn builtin_bin(x: PyIntRef, _vm: &VirtualMachine) -> String {
 
 // builtin_breakpoint
 
-fn builtin_callable(obj: PyObjectRef, _vm: &VirtualMachine) -> bool {
-    objtype::class_has_attr(&obj.class(), "__call__")
+fn builtin_callable(obj: PyObjectRef, vm: &VirtualMachine) -> bool {
+    vm.is_callable(&obj)
 }
 
 fn builtin_chr(i: u32, _vm: &VirtualMachine) -> String {
@@ -102,10 +102,8 @@ fn builtin_compile(
         }
     };
 
-    compile::compile(vm, &source, &mode, filename.value.to_string()).map_err(|err| {
-        let syntax_error = vm.context().exceptions.syntax_error.clone();
-        vm.new_exception(syntax_error, err.to_string())
-    })
+    compile::compile(vm, &source, &mode, filename.value.to_string())
+        .map_err(|err| vm.new_syntax_error(&err))
 }
 
 fn builtin_delattr(obj: PyObjectRef, attr: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
@@ -125,10 +123,13 @@ fn builtin_dir(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 
 fn builtin_divmod(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(x, None), (y, None)]);
-    match vm.get_method(x.clone(), "__divmod__") {
-        Ok(attrib) => vm.invoke(attrib, vec![y.clone()]),
-        Err(..) => Err(vm.new_type_error("unsupported operand type(s) for divmod".to_string())),
-    }
+    vm.call_or_reflection(
+        x.clone(),
+        y.clone(),
+        "__divmod__",
+        "__rdivmod__",
+        |vm, x, y| Err(vm.new_unsupported_operand_error(x, y, "divmod()")),
+    )
 }
 
 /// Implements `eval`.
@@ -151,10 +152,8 @@ fn builtin_eval(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         let source = objstr::get_value(source);
         // TODO: fix this newline bug:
         let source = format!("{}\n", source);
-        compile::compile(vm, &source, &mode, "<string>".to_string()).map_err(|err| {
-            let syntax_error = vm.context().exceptions.syntax_error.clone();
-            vm.new_exception(syntax_error, err.to_string())
-        })?
+        compile::compile(vm, &source, &mode, "<string>".to_string())
+            .map_err(|err| vm.new_syntax_error(&err))?
     } else {
         return Err(vm.new_type_error("code argument must be str or code object".to_string()));
     };
@@ -181,10 +180,8 @@ fn builtin_exec(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         let source = objstr::get_value(source);
         // TODO: fix this newline bug:
         let source = format!("{}\n", source);
-        compile::compile(vm, &source, &mode, "<string>".to_string()).map_err(|err| {
-            let syntax_error = vm.context().exceptions.syntax_error.clone();
-            vm.new_exception(syntax_error, err.to_string())
-        })?
+        compile::compile(vm, &source, &mode, "<string>".to_string())
+            .map_err(|err| vm.new_syntax_error(&err))?
     } else if let Ok(code_obj) = PyCodeRef::try_from_object(vm, source.clone()) {
         code_obj
     } else {
@@ -227,7 +224,11 @@ fn make_scope(
     };
     let locals = match locals {
         Some(dict) => dict.clone().downcast().ok(),
-        None => current_scope.get_only_locals(),
+        None => {
+            let frame = vm.current_frame().unwrap();
+            frame.fast_to_locals(vm);
+            current_scope.get_only_locals()
+        }
     };
 
     Ok(Scope::new(locals, globals))
@@ -292,7 +293,12 @@ fn builtin_hasattr(obj: PyObjectRef, attr: PyStringRef, vm: &VirtualMachine) ->
 fn builtin_hash(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(obj, None)]);
 
-    vm.call_method(obj, "__hash__", vec![])
+    match objtype::class_get_attr(&obj.class(), "__hash__") {
+        Some(hash) if hash.is(&vm.get_none()) => {
+            Err(vm.new_type_error(format!("unhashable type: '{}'", obj.class().name)))
+        }
+        _ => vm.call_method(obj, "__hash__", vec![]),
+    }
 }
 
 // builtin_help
@@ -511,27 +517,24 @@ fn builtin_pow(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         vm,
         args,
         required = [(x, None), (y, None)],
-        optional = [(mod_value, Some(vm.ctx.int_type()))]
+        optional = [(mod_value, None)]
     );
-    let pow_method_name = "__pow__";
-    let result = match vm.get_method(x.clone(), pow_method_name) {
-        Ok(attrib) => vm.invoke(attrib, vec![y.clone()]),
-        Err(..) => Err(vm.new_type_error("unsupported operand type(s) for pow".to_string())),
-    };
-    //Check if the 3rd argument is defined and perform modulus on the result
-    //this should be optimized in the future to perform a "power-mod" algorithm in
-    //order to improve performance
     match mod_value {
-        Some(mod_value) => {
-            let mod_method_name = "__mod__";
-            match vm.get_method(result.expect("result not defined").clone(), mod_method_name) {
-                Ok(value) => vm.invoke(value, vec![mod_value.clone()]),
-                Err(..) => {
-                    Err(vm.new_type_error("unsupported operand type(s) for mod".to_string()))
-                }
+        Some(mod_value) if !vm.get_none().is(mod_value) => {
+            // Only the left operand takes part in three-argument pow
+            let result = match vm.get_method(x.clone(), "__pow__") {
+                Ok(method) => vm.invoke(method, vec![y.clone(), mod_value.clone()])?,
+                Err(..) => vm.ctx.not_implemented(),
+            };
+            if result.is(&vm.ctx.not_implemented()) {
+                Err(vm.new_type_error(
+                    "pow() 3rd argument not allowed unless all arguments are integers".to_string(),
+                ))
+            } else {
+                Ok(result)
             }
         }
-        None => result,
+        _ => vm._pow(x.clone(), y.clone()),
     }
 }
 
@@ -598,14 +601,11 @@ fn builtin_round(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         required = [(number, Some(vm.ctx.object()))],
         optional = [(ndigits, None)]
     );
-    if let Some(ndigits) = ndigits {
-        let ndigits = vm.call_method(ndigits, "__int__", vec![])?;
-        let rounded = vm.call_method(number, "__round__", vec![ndigits])?;
-        Ok(rounded)
-    } else {
-        // without a parameter, the result type is coerced to int
-        let rounded = &vm.call_method(number, "__round__", vec![])?;
-        Ok(vm.ctx.new_int(objint::get_value(rounded).clone()))
+    match ndigits {
+        Some(ndigits) if !vm.get_none().is(ndigits) => {
+            vm.call_method(number, "__round__", vec![ndigits.clone()])
+        }
+        _ => vm.call_method(number, "__round__", vec![]),
     }
 }
 
@@ -742,22 +742,70 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
         // Exceptions:
         "BaseException" => ctx.exceptions.base_exception_type.clone(),
         "Exception" => ctx.exceptions.exception_type.clone(),
+        "GeneratorExit" => ctx.exceptions.generator_exit.clone(),
+        "KeyboardInterrupt" => ctx.exceptions.keyboard_interrupt.clone(),
+        "SystemExit" => ctx.exceptions.system_exit.clone(),
         "ArithmeticError" => ctx.exceptions.arithmetic_error.clone(),
         "AssertionError" => ctx.exceptions.assertion_error.clone(),
         "AttributeError" => ctx.exceptions.attribute_error.clone(),
+        "BufferError" => ctx.exceptions.buffer_error.clone(),
+        "EOFError" => ctx.exceptions.eof_error.clone(),
+        "ImportError" => ctx.exceptions.import_error.clone(),
+        "LookupError" => ctx.exceptions.lookup_error.clone(),
+        "MemoryError" => ctx.exceptions.memory_error.clone(),
         "NameError" => ctx.exceptions.name_error.clone(),
-        "OverflowError" => ctx.exceptions.overflow_error.clone(),
+        "OSError" => ctx.exceptions.os_error.clone(),
+        "ReferenceError" => ctx.exceptions.reference_error.clone(),
         "RuntimeError" => ctx.exceptions.runtime_error.clone(),
-        "NotImplementedError" => ctx.exceptions.not_implemented_error.clone(),
+        "StopAsyncIteration" => ctx.exceptions.stop_async_iteration.clone(),
+        "StopIteration" => ctx.exceptions.stop_iteration.clone(),
+        "SyntaxError" => ctx.exceptions.syntax_error.clone(),
+        "SystemError" => ctx.exceptions.system_error.clone(),
         "TypeError" => ctx.exceptions.type_error.clone(),
         "ValueError" => ctx.exceptions.value_error.clone(),
-        "IndexError" => ctx.exceptions.index_error.clone(),
-        "ImportError" => ctx.exceptions.import_error.clone(),
-        "FileNotFoundError" => ctx.exceptions.file_not_found_error.clone(),
-        "StopIteration" => ctx.exceptions.stop_iteration.clone(),
+        "Warning" => ctx.exceptions.warning.clone(),
+        "FloatingPointError" => ctx.exceptions.floating_point_error.clone(),
+        "OverflowError" => ctx.exceptions.overflow_error.clone(),
         "ZeroDivisionError" => ctx.exceptions.zero_division_error.clone(),
+        "ModuleNotFoundError" => ctx.exceptions.module_not_found_error.clone(),
+        "IndexError" => ctx.exceptions.index_error.clone(),
         "KeyError" => ctx.exceptions.key_error.clone(),
-        "OSError" => ctx.exceptions.os_error.clone(),
+        "UnboundLocalError" => ctx.exceptions.unbound_local_error.clone(),
+        "BlockingIOError" => ctx.exceptions.blocking_io_error.clone(),
+        "ChildProcessError" => ctx.exceptions.child_process_error.clone(),
+        "ConnectionError" => ctx.exceptions.connection_error.clone(),
+        "FileExistsError" => ctx.exceptions.file_exists_error.clone(),
+        "FileNotFoundError" => ctx.exceptions.file_not_found_error.clone(),
+        "InterruptedError" => ctx.exceptions.interrupted_error.clone(),
+        "IsADirectoryError" => ctx.exceptions.is_a_directory_error.clone(),
+        "NotADirectoryError" => ctx.exceptions.not_a_directory_error.clone(),
+        "PermissionError" => ctx.exceptions.permission_error.clone(),
+        "ProcessLookupError" => ctx.exceptions.process_lookup_error.clone(),
+        "TimeoutError" => ctx.exceptions.timeout_error.clone(),
+        "BrokenPipeError" => ctx.exceptions.broken_pipe_error.clone(),
+        "ConnectionAbortedError" => ctx.exceptions.connection_aborted_error.clone(),
+        "ConnectionRefusedError" => ctx.exceptions.connection_refused_error.clone(),
+        "ConnectionResetError" => ctx.exceptions.connection_reset_error.clone(),
+        "NotImplementedError" => ctx.exceptions.not_implemented_error.clone(),
+        "RecursionError" => ctx.exceptions.recursion_error.clone(),
+        "IndentationError" => ctx.exceptions.indentation_error.clone(),
+        "TabError" => ctx.exceptions.tab_error.clone(),
+        "UnicodeError" => ctx.exceptions.unicode_error.clone(),
+        "UnicodeDecodeError" => ctx.exceptions.unicode_decode_error.clone(),
+        "UnicodeEncodeError" => ctx.exceptions.unicode_encode_error.clone(),
+        "UnicodeTranslateError" => ctx.exceptions.unicode_translate_error.clone(),
+        "BytesWarning" => ctx.exceptions.bytes_warning.clone(),
+        "DeprecationWarning" => ctx.exceptions.deprecation_warning.clone(),
+        "FutureWarning" => ctx.exceptions.future_warning.clone(),
+        "ImportWarning" => ctx.exceptions.import_warning.clone(),
+        "PendingDeprecationWarning" => ctx.exceptions.pending_deprecation_warning.clone(),
+        "ResourceWarning" => ctx.exceptions.resource_warning.clone(),
+        "RuntimeWarning" => ctx.exceptions.runtime_warning.clone(),
+        "SyntaxWarning" => ctx.exceptions.syntax_warning.clone(),
+        "UnicodeWarning" => ctx.exceptions.unicode_warning.clone(),
+        "UserWarning" => ctx.exceptions.user_warning.clone(),
+        "EnvironmentError" => ctx.exceptions.os_error.clone(),
+        "IOError" => ctx.exceptions.os_error.clone(),
     });
 
     #[cfg(not(target_arch = "wasm32"))]
@@ -792,14 +840,15 @@ pub fn builtin_build_class_(vm: &VirtualMachine, mut args: PyFuncArgs) -> PyResu
 
     let namespace: PyDictRef = TryFromObject::try_from_object(vm, namespace)?;
 
-    let cells = vm.ctx.new_dict();
-
-    vm.invoke_with_locals(function, cells.clone(), namespace.clone())?;
+    // The class body returns the `__class__` cell, if methods make use of it.
+    let class_cell = vm.invoke_with_locals(function, namespace.clone())?;
     let class = vm.call_method(
         metaclass.as_object(),
         "__call__",
         vec![name_arg, bases, namespace.into_object()],
     )?;
-    cells.set_item(&vm.ctx, "__class__", class.clone());
+    if let Some(class_cell) = class_cell.payload::<PyCell>() {
+        class_cell.set(Some(class.clone()));
+    }
     Ok(class)
 }
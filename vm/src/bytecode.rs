@@ -21,17 +21,22 @@ pub struct CodeObject {
     pub arg_names: Vec<String>, // Names of positional arguments
     pub varargs: Varargs,       // *args or *
     pub kwonlyarg_names: Vec<String>,
-    pub varkeywords: Varargs, // **kwargs or **
+    pub varkeywords: Varargs,  // **kwargs or **
+    pub varnames: Vec<String>, // Names of the fast locals, starting with the arguments
+    pub cellvars: Vec<String>, // Names of the locals shared with nested functions
+    pub freevars: Vec<String>, // Names of the variables taken from the closure
     pub source_path: String,
     pub first_line_number: usize,
     pub obj_name: String, // Name of the object that created this code object
     pub is_generator: bool,
+    pub is_coroutine: bool,
 }
 
 bitflags! {
     pub struct FunctionOpArg: u8 {
         const HAS_DEFAULTS = 0x01;
         const HAS_ANNOTATIONS = 0x04;
+        const HAS_CLOSURE = 0x08;
     }
 }
 
@@ -49,12 +54,36 @@ pub enum Instruction {
     },
     LoadName {
         name: String,
+        scope: NameScope,
     },
     StoreName {
         name: String,
+        scope: NameScope,
     },
     DeleteName {
         name: String,
+        scope: NameScope,
+    },
+    LoadFast {
+        idx: usize,
+    },
+    StoreFast {
+        idx: usize,
+    },
+    DeleteFast {
+        idx: usize,
+    },
+    LoadDeref {
+        idx: usize,
+    },
+    StoreDeref {
+        idx: usize,
+    },
+    DeleteDeref {
+        idx: usize,
+    },
+    LoadClosure {
+        idx: usize,
     },
     StoreSubscript,
     DeleteSubscript,
@@ -110,6 +139,10 @@ pub enum Instruction {
     ReturnValue,
     YieldValue,
     YieldFrom,
+    GetAwaitable,
+    GetAIter,
+    GetANext,
+    EndAsyncFor,
     SetupLoop {
         start: Label,
         end: Label,
@@ -117,12 +150,24 @@ pub enum Instruction {
     SetupExcept {
         handler: Label,
     },
+    SetupFinally {
+        handler: Label,
+    },
+    PopException,
+    EnterFinally,
+    EndFinally,
     SetupWith {
         end: Label,
     },
     CleanupWith {
         end: Label,
     },
+    BeforeAsyncWith,
+    SetupAsyncWith {
+        handler: Label,
+    },
+    WithCleanupStart,
+    WithCleanupFinish,
     PopBlock,
     Raise {
         argc: usize,
@@ -176,6 +221,13 @@ pub enum Instruction {
 
 use self::Instruction::*;
 
+/// Where the frame should look up a name, as decided by the symbol table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameScope {
+    Local,
+    Global,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum CallType {
     Positional(usize),
@@ -262,6 +314,14 @@ impl CodeObject {
         first_line_number: usize,
         obj_name: String,
     ) -> CodeObject {
+        let mut varnames = arg_names.clone();
+        varnames.extend(kwonlyarg_names.iter().cloned());
+        if let Varargs::Named(name) = &varargs {
+            varnames.push(name.clone());
+        }
+        if let Varargs::Named(name) = &varkeywords {
+            varnames.push(name.clone());
+        }
         CodeObject {
             instructions: Vec::new(),
             label_map: HashMap::new(),
@@ -270,10 +330,14 @@ impl CodeObject {
             varargs,
             kwonlyarg_names,
             varkeywords,
+            varnames,
+            cellvars: Vec::new(),
+            freevars: Vec::new(),
             source_path,
             first_line_number,
             obj_name,
             is_generator: false,
+            is_coroutine: false,
         }
     }
 
@@ -305,6 +369,21 @@ impl fmt::Display for CodeObject {
 }
 
 impl Instruction {
+    /// The labels this instruction may jump to.
+    pub fn labels(&self) -> Vec<Label> {
+        match *self {
+            Jump { target } | JumpIf { target } | JumpIfFalse { target } | ForIter { target } => {
+                vec![target]
+            }
+            SetupLoop { start, end } => vec![start, end],
+            SetupExcept { handler } | SetupFinally { handler } | SetupAsyncWith { handler } => {
+                vec![handler]
+            }
+            SetupWith { end } | CleanupWith { end } => vec![end],
+            _ => vec![],
+        }
+    }
+
     fn fmt_dis(&self, f: &mut fmt::Formatter, label_map: &HashMap<Label, usize>) -> fmt::Result {
         macro_rules! w {
             ($variant:ident) => {
@@ -321,9 +400,16 @@ impl Instruction {
         match self {
             Import { name, symbol } => w!(Import, name, format!("{:?}", symbol)),
             ImportStar { name } => w!(ImportStar, name),
-            LoadName { name } => w!(LoadName, name),
-            StoreName { name } => w!(StoreName, name),
-            DeleteName { name } => w!(DeleteName, name),
+            LoadName { name, scope } => w!(LoadName, name, format!("{:?}", scope)),
+            StoreName { name, scope } => w!(StoreName, name, format!("{:?}", scope)),
+            DeleteName { name, scope } => w!(DeleteName, name, format!("{:?}", scope)),
+            LoadFast { idx } => w!(LoadFast, idx),
+            StoreFast { idx } => w!(StoreFast, idx),
+            DeleteFast { idx } => w!(DeleteFast, idx),
+            LoadDeref { idx } => w!(LoadDeref, idx),
+            StoreDeref { idx } => w!(StoreDeref, idx),
+            DeleteDeref { idx } => w!(DeleteDeref, idx),
+            LoadClosure { idx } => w!(LoadClosure, idx),
             StoreSubscript => w!(StoreSubscript),
             DeleteSubscript => w!(DeleteSubscript),
             StoreAttr { name } => w!(StoreAttr, name),
@@ -349,10 +435,22 @@ impl Instruction {
             ReturnValue => w!(ReturnValue),
             YieldValue => w!(YieldValue),
             YieldFrom => w!(YieldFrom),
+            GetAwaitable => w!(GetAwaitable),
+            GetAIter => w!(GetAIter),
+            GetANext => w!(GetANext),
+            EndAsyncFor => w!(EndAsyncFor),
             SetupLoop { start, end } => w!(SetupLoop, label_map[start], label_map[end]),
             SetupExcept { handler } => w!(SetupExcept, handler),
+            SetupFinally { handler } => w!(SetupFinally, label_map[handler]),
+            PopException => w!(PopException),
+            EnterFinally => w!(EnterFinally),
+            EndFinally => w!(EndFinally),
             SetupWith { end } => w!(SetupWith, end),
             CleanupWith { end } => w!(CleanupWith, end),
+            BeforeAsyncWith => w!(BeforeAsyncWith),
+            SetupAsyncWith { handler } => w!(SetupAsyncWith, label_map[handler]),
+            WithCleanupStart => w!(WithCleanupStart),
+            WithCleanupFinish => w!(WithCleanupFinish),
             PopBlock => w!(PopBlock),
             Raise { argc } => w!(Raise, argc),
             BuildString { size } => w!(BuildString, size),
@@ -0,0 +1,600 @@
+//! Parsing and formatting of printf-style format strings, as used by the
+//! `%` operator of `str` and `bytes`.
+
+use std::cmp;
+use std::fmt;
+use std::str::FromStr;
+
+use num_bigint::{BigInt, Sign};
+use num_traits::Signed;
+
+#[derive(Debug, PartialEq)]
+pub enum CFormatErrorType {
+    UnmatchedKeyParentheses,
+    UnsupportedFormatChar(char),
+    IncompleteFormat,
+}
+
+/// An error in a format string, at the given character index.
+#[derive(Debug, PartialEq)]
+pub struct CFormatError {
+    pub typ: CFormatErrorType,
+    pub index: usize,
+}
+
+impl fmt::Display for CFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.typ {
+            CFormatErrorType::UnmatchedKeyParentheses => write!(f, "incomplete format key"),
+            CFormatErrorType::IncompleteFormat => write!(f, "incomplete format"),
+            CFormatErrorType::UnsupportedFormatChar(c) => write!(
+                f,
+                "unsupported format character '{}' (0x{:x}) at index {}",
+                c, c as u32, self.index
+            ),
+        }
+    }
+}
+
+bitflags! {
+    pub struct CConversionFlags: u8 {
+        const ALTERNATE_FORM = 0b0000_0001;
+        const ZERO_PAD = 0b0000_0010;
+        const LEFT_ADJUST = 0b0000_0100;
+        const BLANK_SIGN = 0b0000_1000;
+        const SIGN_CHAR = 0b0001_0000;
+    }
+}
+
+/// A width or precision, either given in the format string or taken from
+/// the arguments with `*`.
+#[derive(Debug, PartialEq)]
+pub enum CFormatQuantity {
+    Amount(usize),
+    FromValuesTuple,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CFormatCase {
+    Lowercase,
+    Uppercase,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CNumberType {
+    Decimal,
+    Octal,
+    Hex(CFormatCase),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CFloatType {
+    Exponent(CFormatCase),
+    PointDecimal(CFormatCase),
+    General(CFormatCase),
+}
+
+/// How an object is converted to a string before it is formatted.
+#[derive(Debug, PartialEq)]
+pub enum CFormatPreconversor {
+    Repr,
+    Str,
+    Ascii,
+    /// `%b`, which only `bytes` formatting supports.
+    Bytes,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CFormatType {
+    Number(CNumberType),
+    Float(CFloatType),
+    Character,
+    String(CFormatPreconversor),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CFormatSpec {
+    pub mapping_key: Option<String>,
+    pub flags: CConversionFlags,
+    pub min_field_width: Option<CFormatQuantity>,
+    pub precision: Option<CFormatQuantity>,
+    pub format_type: CFormatType,
+    pub format_char: char,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CFormatPart {
+    Literal(String),
+    Spec(CFormatSpec),
+}
+
+impl CFormatPart {
+    pub fn is_specifier(&self) -> bool {
+        match self {
+            CFormatPart::Spec(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn has_key(&self) -> bool {
+        match self {
+            CFormatPart::Spec(spec) => spec.mapping_key.is_some(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CFormatString {
+    pub format_parts: Vec<CFormatPart>,
+}
+
+/// The text after `sign` and `prefix` is padded with zeros, everything else
+/// with spaces.
+fn fill_number(spec: &CFormatSpec, sign: &str, prefix: &str, digits: &str) -> String {
+    let width = match spec.min_field_width {
+        Some(CFormatQuantity::Amount(width)) => width,
+        _ => 0,
+    };
+    let length = sign.len() + prefix.len() + digits.chars().count();
+    let padding = width.saturating_sub(length);
+    if spec.flags.contains(CConversionFlags::LEFT_ADJUST) {
+        format!("{}{}{}{}", sign, prefix, digits, " ".repeat(padding))
+    } else if spec.flags.contains(CConversionFlags::ZERO_PAD) {
+        format!("{}{}{}{}", sign, prefix, "0".repeat(padding), digits)
+    } else {
+        format!("{}{}{}{}", " ".repeat(padding), sign, prefix, digits)
+    }
+}
+
+impl CFormatSpec {
+    fn sign_string(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else if self.flags.contains(CConversionFlags::SIGN_CHAR) {
+            "+"
+        } else if self.flags.contains(CConversionFlags::BLANK_SIGN) {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    /// Pad `string` to the field width, with spaces.
+    pub fn fill_string(&self, string: String) -> String {
+        let width = match self.min_field_width {
+            Some(CFormatQuantity::Amount(width)) => width,
+            _ => 0,
+        };
+        let padding = width.saturating_sub(string.chars().count());
+        if self.flags.contains(CConversionFlags::LEFT_ADJUST) {
+            format!("{}{}", string, " ".repeat(padding))
+        } else {
+            format!("{}{}", " ".repeat(padding), string)
+        }
+    }
+
+    /// Cut `string` to the precision and pad it to the field width.
+    pub fn format_string(&self, string: String) -> String {
+        let string = match self.precision {
+            Some(CFormatQuantity::Amount(precision)) => string.chars().take(precision).collect(),
+            _ => string,
+        };
+        self.fill_string(string)
+    }
+
+    pub fn format_number(&self, number: &BigInt) -> String {
+        let magnitude = number.abs();
+        let alternate = self.flags.contains(CConversionFlags::ALTERNATE_FORM);
+        let (prefix, mut digits) = match self.format_type {
+            CFormatType::Number(CNumberType::Octal) => {
+                (if alternate { "0o" } else { "" }, magnitude.to_str_radix(8))
+            }
+            CFormatType::Number(CNumberType::Hex(CFormatCase::Lowercase)) => (
+                if alternate { "0x" } else { "" },
+                magnitude.to_str_radix(16),
+            ),
+            CFormatType::Number(CNumberType::Hex(CFormatCase::Uppercase)) => (
+                if alternate { "0X" } else { "" },
+                magnitude.to_str_radix(16).to_uppercase(),
+            ),
+            _ => ("", magnitude.to_str_radix(10)),
+        };
+        // The precision is the minimum number of digits:
+        if let Some(CFormatQuantity::Amount(precision)) = self.precision {
+            if digits.len() < precision {
+                digits = format!("{}{}", "0".repeat(precision - digits.len()), digits);
+            }
+        }
+        let sign = self.sign_string(number.sign() == Sign::Minus);
+        fill_number(self, sign, prefix, &digits)
+    }
+
+    pub fn format_float(&self, number: f64) -> String {
+        let precision = match self.precision {
+            Some(CFormatQuantity::Amount(precision)) => precision,
+            _ => 6,
+        };
+        let alternate = self.flags.contains(CConversionFlags::ALTERNATE_FORM);
+        let digits = match &self.format_type {
+            CFormatType::Float(float_type) => {
+                format_float(float_type, number.abs(), precision, alternate)
+            }
+            _ => format_float(
+                &CFloatType::General(CFormatCase::Lowercase),
+                number.abs(),
+                precision,
+                alternate,
+            ),
+        };
+        let sign = self.sign_string(number.is_sign_negative() && !number.is_nan());
+        if number.is_finite() {
+            fill_number(self, sign, "", &digits)
+        } else {
+            // Infinity and NaN are never padded with zeros:
+            self.fill_string(format!("{}{}", sign, digits))
+        }
+    }
+}
+
+/// Format the non-negative `number` like `%e`, `%f` or `%g` would.
+pub fn format_float(
+    float_type: &CFloatType,
+    number: f64,
+    precision: usize,
+    alternate: bool,
+) -> String {
+    let case = match float_type {
+        CFloatType::Exponent(case) | CFloatType::PointDecimal(case) | CFloatType::General(case) => {
+            case
+        }
+    };
+    let text = if number.is_nan() {
+        "nan".to_string()
+    } else if number.is_infinite() {
+        "inf".to_string()
+    } else {
+        match float_type {
+            CFloatType::PointDecimal(_) => {
+                let text = format!("{:.*}", precision, number);
+                if alternate && precision == 0 {
+                    format!("{}.", text)
+                } else {
+                    text
+                }
+            }
+            CFloatType::Exponent(_) => format_exponent(number, precision, alternate),
+            CFloatType::General(_) => format_general(number, precision, alternate),
+        }
+    };
+    match case {
+        CFormatCase::Lowercase => text,
+        CFormatCase::Uppercase => text.to_uppercase(),
+    }
+}
+
+/// Format like `%e`, with an exponent of at least two digits.
+fn format_exponent(number: f64, precision: usize, alternate: bool) -> String {
+    let text = format!("{:.*e}", precision, number);
+    let (mantissa, exponent) = text.split_at(text.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let point = if alternate && precision == 0 { "." } else { "" };
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}{}e{}{:02}", mantissa, point, sign, exponent.abs())
+}
+
+/// Format like `%g`: with an exponent when it is small or at least the
+/// precision, and without trailing zeros unless in alternate form.
+fn format_general(number: f64, precision: usize, alternate: bool) -> String {
+    let precision = cmp::max(precision, 1);
+    // The exponent after rounding to the precision:
+    let rounded = format!("{:.*e}", precision - 1, number);
+    let exponent: i64 = rounded[rounded.find('e').unwrap() + 1..].parse().unwrap();
+    let text = if exponent < -4 || exponent >= precision as i64 {
+        format_exponent(number, precision - 1, alternate)
+    } else {
+        let decimals = (precision as i64 - 1 - exponent) as usize;
+        let text = format!("{:.*}", decimals, number);
+        if alternate && decimals == 0 {
+            format!("{}.", text)
+        } else {
+            text
+        }
+    };
+    if alternate {
+        return text;
+    }
+    let (mantissa, exponent) = match text.find('e') {
+        Some(index) => text.split_at(index),
+        None => (text.as_str(), ""),
+    };
+    let mantissa = if mantissa.contains('.') {
+        mantissa.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        mantissa
+    };
+    format!("{}{}", mantissa, exponent)
+}
+
+/// A character iterator which keeps track of the character index, for errors.
+struct Chars<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    index: usize,
+    is_bytes: bool,
+}
+
+impl<'a> Chars<'a> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c.is_some() {
+            self.index += 1;
+        }
+        c
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().cloned()
+    }
+
+    fn error(&self, typ: CFormatErrorType) -> CFormatError {
+        CFormatError {
+            typ,
+            index: self.index,
+        }
+    }
+}
+
+fn parse_mapping_key(chars: &mut Chars) -> Result<Option<String>, CFormatError> {
+    if chars.peek() != Some('(') {
+        return Ok(None);
+    }
+    chars.next();
+    // Parentheses nest in a key:
+    let mut depth = 1;
+    let mut key = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(Some(key));
+                }
+            }
+            _ => {}
+        }
+        key.push(c);
+    }
+    Err(chars.error(CFormatErrorType::UnmatchedKeyParentheses))
+}
+
+fn parse_flags(chars: &mut Chars) -> CConversionFlags {
+    let mut flags = CConversionFlags::empty();
+    while let Some(c) = chars.peek() {
+        flags |= match c {
+            '#' => CConversionFlags::ALTERNATE_FORM,
+            '0' => CConversionFlags::ZERO_PAD,
+            '-' => CConversionFlags::LEFT_ADJUST,
+            ' ' => CConversionFlags::BLANK_SIGN,
+            '+' => CConversionFlags::SIGN_CHAR,
+            _ => break,
+        };
+        chars.next();
+    }
+    flags
+}
+
+fn parse_quantity(chars: &mut Chars) -> Option<CFormatQuantity> {
+    if chars.peek() == Some('*') {
+        chars.next();
+        return Some(CFormatQuantity::FromValuesTuple);
+    }
+    let mut amount = None;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        chars.next();
+        amount = Some(amount.unwrap_or(0) * 10 + digit as usize);
+    }
+    amount.map(CFormatQuantity::Amount)
+}
+
+fn parse_precision(chars: &mut Chars) -> Option<CFormatQuantity> {
+    if chars.peek() != Some('.') {
+        return None;
+    }
+    chars.next();
+    // A lone `.` means a precision of zero:
+    Some(parse_quantity(chars).unwrap_or(CFormatQuantity::Amount(0)))
+}
+
+fn parse_format_type(chars: &mut Chars) -> Result<(CFormatType, char), CFormatError> {
+    // Length modifiers are accepted and ignored, like in CPython:
+    while let Some('h') | Some('l') | Some('L') = chars.peek() {
+        chars.next();
+    }
+    let index = chars.index;
+    let c = chars
+        .next()
+        .ok_or_else(|| chars.error(CFormatErrorType::IncompleteFormat))?;
+    let format_type = match c {
+        'd' | 'i' | 'u' => CFormatType::Number(CNumberType::Decimal),
+        'o' => CFormatType::Number(CNumberType::Octal),
+        'x' => CFormatType::Number(CNumberType::Hex(CFormatCase::Lowercase)),
+        'X' => CFormatType::Number(CNumberType::Hex(CFormatCase::Uppercase)),
+        'e' => CFormatType::Float(CFloatType::Exponent(CFormatCase::Lowercase)),
+        'E' => CFormatType::Float(CFloatType::Exponent(CFormatCase::Uppercase)),
+        'f' => CFormatType::Float(CFloatType::PointDecimal(CFormatCase::Lowercase)),
+        'F' => CFormatType::Float(CFloatType::PointDecimal(CFormatCase::Uppercase)),
+        'g' => CFormatType::Float(CFloatType::General(CFormatCase::Lowercase)),
+        'G' => CFormatType::Float(CFloatType::General(CFormatCase::Uppercase)),
+        'c' => CFormatType::Character,
+        'r' => CFormatType::String(CFormatPreconversor::Repr),
+        's' => CFormatType::String(CFormatPreconversor::Str),
+        'a' => CFormatType::String(CFormatPreconversor::Ascii),
+        'b' if chars.is_bytes => CFormatType::String(CFormatPreconversor::Bytes),
+        _ => {
+            return Err(CFormatError {
+                typ: CFormatErrorType::UnsupportedFormatChar(c),
+                index,
+            })
+        }
+    };
+    Ok((format_type, c))
+}
+
+/// Parse the specifier after a `%`, or `None` for an escaped `%%`.
+fn parse_specifier(chars: &mut Chars) -> Result<Option<CFormatSpec>, CFormatError> {
+    if chars.peek() == Some('%') {
+        chars.next();
+        return Ok(None);
+    }
+    let mapping_key = parse_mapping_key(chars)?;
+    let flags = parse_flags(chars);
+    let min_field_width = parse_quantity(chars);
+    let precision = parse_precision(chars);
+    let (format_type, format_char) = parse_format_type(chars)?;
+    Ok(Some(CFormatSpec {
+        mapping_key,
+        flags,
+        min_field_width,
+        precision,
+        format_type,
+        format_char,
+    }))
+}
+
+impl CFormatString {
+    /// Parse the format string of `bytes`, given as the characters with the
+    /// code points of its bytes.
+    pub fn parse_bytes(text: &str) -> Result<Self, CFormatError> {
+        CFormatString::parse(text, true)
+    }
+
+    fn parse(text: &str, is_bytes: bool) -> Result<Self, CFormatError> {
+        let mut chars = Chars {
+            chars: text.chars().peekable(),
+            index: 0,
+            is_bytes,
+        };
+        let mut format_parts = vec![];
+        let mut literal = String::new();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+            match parse_specifier(&mut chars)? {
+                Some(spec) => {
+                    if !literal.is_empty() {
+                        format_parts.push(CFormatPart::Literal(literal));
+                        literal = String::new();
+                    }
+                    format_parts.push(CFormatPart::Spec(spec));
+                }
+                None => literal.push('%'),
+            }
+        }
+        if !literal.is_empty() {
+            format_parts.push(CFormatPart::Literal(literal));
+        }
+        Ok(CFormatString { format_parts })
+    }
+}
+
+impl FromStr for CFormatString {
+    type Err = CFormatError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        CFormatString::parse(text, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(text: &str) -> CFormatSpec {
+        let mut parts = CFormatString::from_str(text).unwrap().format_parts;
+        match parts.pop() {
+            Some(CFormatPart::Spec(spec)) => spec,
+            part => panic!("expected a specifier, got {:?}", part),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let expected = CFormatSpec {
+            mapping_key: Some("a(b)".to_string()),
+            flags: CConversionFlags::LEFT_ADJUST | CConversionFlags::SIGN_CHAR,
+            min_field_width: Some(CFormatQuantity::FromValuesTuple),
+            precision: Some(CFormatQuantity::Amount(3)),
+            format_type: CFormatType::Number(CNumberType::Hex(CFormatCase::Uppercase)),
+            format_char: 'X',
+        };
+        assert_eq!(spec("%(a(b))-+*.3lX"), expected);
+        assert_eq!(
+            CFormatString::from_str("100%% of %s").unwrap().format_parts,
+            vec![
+                CFormatPart::Literal("100% of ".to_string()),
+                CFormatPart::Spec(spec("%s")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            CFormatString::from_str("ab%y"),
+            Err(CFormatError {
+                typ: CFormatErrorType::UnsupportedFormatChar('y'),
+                index: 3,
+            })
+        );
+        assert_eq!(
+            CFormatString::from_str("%b").unwrap_err().typ,
+            CFormatErrorType::UnsupportedFormatChar('b')
+        );
+        assert!(CFormatString::parse_bytes("%b").is_ok());
+        assert_eq!(
+            CFormatString::from_str("%(key").unwrap_err().typ,
+            CFormatErrorType::UnmatchedKeyParentheses
+        );
+        assert_eq!(
+            CFormatString::from_str("%-5").unwrap_err().typ,
+            CFormatErrorType::IncompleteFormat
+        );
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(spec("%5d").format_number(&BigInt::from(-42)), "  -42");
+        assert_eq!(spec("%-5d").format_number(&BigInt::from(42)), "42   ");
+        assert_eq!(spec("%05d").format_number(&BigInt::from(-42)), "-0042");
+        assert_eq!(spec("%+.3d").format_number(&BigInt::from(7)), "+007");
+        assert_eq!(spec("%#x").format_number(&BigInt::from(255)), "0xff");
+        assert_eq!(spec("%#08X").format_number(&BigInt::from(255)), "0X0000FF");
+        assert_eq!(spec("%#o").format_number(&BigInt::from(8)), "0o10");
+    }
+
+    #[test]
+    fn test_format_float() {
+        assert_eq!(spec("%f").format_float(1.5), "1.500000");
+        assert_eq!(spec("%.2f").format_float(-0.125), "-0.12");
+        assert_eq!(spec("%08.3f").format_float(-3.14159), "-003.142");
+        assert_eq!(spec("%e").format_float(12345.678), "1.234568e+04");
+        assert_eq!(spec("%.1E").format_float(0.000123), "1.2E-04");
+        assert_eq!(spec("%g").format_float(0.0001), "0.0001");
+        assert_eq!(spec("%g").format_float(0.00001), "1e-05");
+        assert_eq!(spec("%g").format_float(123456789.0), "1.23457e+08");
+        assert_eq!(spec("%g").format_float(100.0), "100");
+        assert_eq!(spec("%#g").format_float(100.0), "100.000");
+        assert_eq!(spec("%5f").format_float(std::f64::INFINITY), "  inf");
+        assert_eq!(spec("%F").format_float(std::f64::NAN), "NAN");
+    }
+
+    #[test]
+    fn test_format_string() {
+        assert_eq!(spec("%5s").format_string("ab".to_string()), "   ab");
+        assert_eq!(spec("%-5s").format_string("ab".to_string()), "ab   ");
+        assert_eq!(spec("%.1s").format_string("ab".to_string()), "a");
+    }
+}
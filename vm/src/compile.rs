@@ -6,21 +6,28 @@
 //!   https://github.com/micropython/micropython/blob/master/py/compile.c
 
 use crate::bytecode::{self, CallType, CodeObject, Instruction, Varargs};
-use crate::error::CompileError;
+use crate::error::{CompileError, CompileErrorType};
 use crate::obj::objcode;
 use crate::obj::objcode::PyCodeRef;
+use crate::peephole;
 use crate::pyobject::PyValue;
+use crate::symboltable::{
+    make_symbol_table, statements_to_symbol_table, ScopeType, SymbolScope, SymbolTable,
+};
 use crate::VirtualMachine;
 use num_complex::Complex64;
 use rustpython_parser::{ast, parser};
 
 struct Compiler {
     code_object_stack: Vec<CodeObject>,
+    symbol_table_stack: Vec<SymbolTable>,
     nxt_label: usize,
     source_path: Option<String>,
     current_source_location: ast::Location,
     in_loop: bool,
     in_function_def: bool,
+    in_async_function: bool,
+    optimize: u8,
 }
 
 /// Compile a given sourcecode into a bytecode object.
@@ -31,29 +38,60 @@ pub fn compile(
     source_path: String,
 ) -> Result<PyCodeRef, CompileError> {
     let mut compiler = Compiler::new();
-    compiler.source_path = Some(source_path);
+    compiler.source_path = Some(source_path.clone());
+    compiler.optimize = vm.compile_options.get().optimize;
     compiler.push_new_code_object("<module>".to_string());
 
-    match mode {
-        Mode::Exec => {
-            let ast = parser::parse_program(source).map_err(CompileError::Parse)?;
-            compiler.compile_program(&ast)
-        }
-        Mode::Eval => {
-            let statement = parser::parse_statement(source).map_err(CompileError::Parse)?;
-            compiler.compile_statement_eval(&statement)
-        }
-        Mode::Single => {
-            let ast = parser::parse_program(source).map_err(CompileError::Parse)?;
-            compiler.compile_program_single(&ast)
-        }
-    }?;
-
-    let code = compiler.pop_code_object();
+    let result = match mode {
+        Mode::Exec => parser::parse_program(source)
+            .map_err(CompileError::from)
+            .and_then(|ast| {
+                let symbol_table = make_symbol_table(&ast)?;
+                compiler.compile_program(&ast, symbol_table)
+            }),
+        Mode::Eval => parser::parse_statement(source)
+            .map_err(CompileError::from)
+            .and_then(|statement| {
+                let symbol_table = statements_to_symbol_table(std::slice::from_ref(&statement))?;
+                compiler.compile_statement_eval(&statement, symbol_table)
+            }),
+        Mode::Single => parser::parse_program(source)
+            .map_err(CompileError::from)
+            .and_then(|ast| {
+                let symbol_table = make_symbol_table(&ast)?;
+                compiler.compile_program_single(&ast, symbol_table)
+            }),
+    };
+    result.map_err(|err| err.with_source(source, &source_path))?;
+
+    let mut code = compiler.pop_code_object();
+    if vm.compile_options.get().peephole {
+        peephole::optimize(&mut code);
+    }
     trace!("Compilation completed: {:?}", code);
     Ok(objcode::PyCode::new(code).into_ref(vm))
 }
 
+/// Settings which change the code `compile` generates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompileOptions {
+    /// Run the peephole optimizer over the generated code. Turning it off
+    /// keeps the code as close to the source as possible, for debugging.
+    pub peephole: bool,
+    /// The optimization level, as set by `-O`. At 1, asserts are left out and
+    /// `__debug__` is false. At 2, docstrings are left out as well.
+    pub optimize: u8,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            peephole: true,
+            optimize: 0,
+        }
+    }
+}
+
 pub enum Mode {
     Exec,
     Eval,
@@ -72,11 +110,14 @@ impl Compiler {
     fn new() -> Self {
         Compiler {
             code_object_stack: Vec::new(),
+            symbol_table_stack: Vec::new(),
             nxt_label: 0,
             source_path: None,
             current_source_location: ast::Location::default(),
             in_loop: false,
             in_function_def: false,
+            in_async_function: false,
+            optimize: 0,
         }
     }
 
@@ -97,9 +138,19 @@ impl Compiler {
         self.code_object_stack.pop().unwrap()
     }
 
-    fn compile_program(&mut self, program: &ast::Program) -> Result<(), CompileError> {
+    fn compile_program(
+        &mut self,
+        program: &ast::Program,
+        symbol_table: SymbolTable,
+    ) -> Result<(), CompileError> {
         let size_before = self.code_object_stack.len();
-        self.compile_statements(&program.statements)?;
+        self.symbol_table_stack.push(symbol_table);
+        let (doc, statements) = self.split_docstring(&program.statements);
+        if let Some(doc) = doc {
+            self.emit_docstring(doc);
+            self.store_name("__doc__");
+        }
+        self.compile_statements(statements)?;
         assert!(self.code_object_stack.len() == size_before);
 
         // Emit None at end:
@@ -110,7 +161,12 @@ impl Compiler {
         Ok(())
     }
 
-    fn compile_program_single(&mut self, program: &ast::Program) -> Result<(), CompileError> {
+    fn compile_program_single(
+        &mut self,
+        program: &ast::Program,
+        symbol_table: SymbolTable,
+    ) -> Result<(), CompileError> {
+        self.symbol_table_stack.push(symbol_table);
         for statement in &program.statements {
             if let ast::Statement::Expression { ref expression } = statement.node {
                 self.compile_expression(expression)?;
@@ -130,13 +186,15 @@ impl Compiler {
     fn compile_statement_eval(
         &mut self,
         statement: &ast::LocatedStatement,
+        symbol_table: SymbolTable,
     ) -> Result<(), CompileError> {
+        self.symbol_table_stack.push(symbol_table);
         if let ast::Statement::Expression { ref expression } = statement.node {
             self.compile_expression(expression)?;
             self.emit(Instruction::ReturnValue);
             Ok(())
         } else {
-            Err(CompileError::ExpectExpr)
+            Err(self.error(CompileErrorType::ExpectExpr))
         }
     }
 
@@ -173,13 +231,11 @@ impl Compiler {
                                 name: module.clone(),
                                 symbol: symbol.clone(),
                             });
-                            self.emit(Instruction::StoreName {
-                                name: match alias {
-                                    Some(alias) => alias.clone(),
-                                    None => match symbol {
-                                        Some(symbol) => symbol.clone(),
-                                        None => module.clone(),
-                                    },
+                            self.store_name(match alias {
+                                Some(alias) => alias,
+                                None => match symbol {
+                                    Some(symbol) => symbol,
+                                    None => module,
                                 },
                             });
                         }
@@ -192,11 +248,8 @@ impl Compiler {
                 // Pop result of stack, since we not use it:
                 self.emit(Instruction::Pop);
             }
-            ast::Statement::Global { names } => {
-                unimplemented!("global {:?}", names);
-            }
-            ast::Statement::Nonlocal { names } => {
-                unimplemented!("nonlocal {:?}", names);
+            ast::Statement::Global { .. } | ast::Statement::Nonlocal { .. } => {
+                // Handled during symbol table construction.
             }
             ast::Statement::If { test, body, orelse } => {
                 let end_label = self.new_label();
@@ -248,7 +301,17 @@ impl Compiler {
                 }
                 self.set_label(end_label);
             }
-            ast::Statement::With { items, body } => {
+            ast::Statement::With {
+                is_async: true,
+                items,
+                body,
+            } => {
+                if !self.in_async_function {
+                    return Err(self.error(CompileErrorType::InvalidAsync("async with")));
+                }
+                self.compile_async_with(items, body)?;
+            }
+            ast::Statement::With { items, body, .. } => {
                 let end_label = self.new_label();
                 for item in items {
                     self.compile_expression(&item.context_expr)?;
@@ -270,11 +333,17 @@ impl Compiler {
                 self.set_label(end_label);
             }
             ast::Statement::For {
+                is_async,
                 target,
                 iter,
                 body,
                 orelse,
-            } => self.compile_for(target, iter, body, orelse)?,
+            } => {
+                if *is_async && !self.in_async_function {
+                    return Err(self.error(CompileErrorType::InvalidAsync("async for")));
+                }
+                self.compile_for(*is_async, target, iter, body, orelse)?
+            }
             ast::Statement::Raise { exception, cause } => match exception {
                 Some(value) => {
                     self.compile_expression(value)?;
@@ -299,12 +368,13 @@ impl Compiler {
                 finalbody,
             } => self.compile_try_statement(body, handlers, orelse, finalbody)?,
             ast::Statement::FunctionDef {
+                is_async,
                 name,
                 args,
                 body,
                 decorator_list,
                 returns,
-            } => self.compile_function_def(name, args, body, decorator_list, returns)?,
+            } => self.compile_function_def(*is_async, name, args, body, decorator_list, returns)?,
             ast::Statement::ClassDef {
                 name,
                 body,
@@ -312,14 +382,12 @@ impl Compiler {
                 keywords,
                 decorator_list,
             } => self.compile_class_def(name, body, bases, keywords, decorator_list)?,
+            // Asserts are left out when optimizing:
+            ast::Statement::Assert { .. } if self.optimize > 0 => {}
             ast::Statement::Assert { test, msg } => {
-                // TODO: if some flag, ignore all assert statements!
-
                 let end_label = self.new_label();
                 self.compile_test(test, Some(end_label), None, EvalContext::Statement)?;
-                self.emit(Instruction::LoadName {
-                    name: String::from("AssertionError"),
-                });
+                self.load_name("AssertionError");
                 match msg {
                     Some(e) => {
                         self.compile_expression(e)?;
@@ -338,19 +406,19 @@ impl Compiler {
             }
             ast::Statement::Break => {
                 if !self.in_loop {
-                    return Err(CompileError::InvalidBreak);
+                    return Err(self.error(CompileErrorType::InvalidBreak));
                 }
                 self.emit(Instruction::Break);
             }
             ast::Statement::Continue => {
                 if !self.in_loop {
-                    return Err(CompileError::InvalidContinue);
+                    return Err(self.error(CompileErrorType::InvalidContinue));
                 }
                 self.emit(Instruction::Continue);
             }
             ast::Statement::Return { value } => {
                 if !self.in_function_def {
-                    return Err(CompileError::InvalidReturn);
+                    return Err(self.error(CompileErrorType::InvalidReturn));
                 }
                 match value {
                     Some(e) => {
@@ -398,9 +466,7 @@ impl Compiler {
                 for target in targets {
                     match target {
                         ast::Expression::Identifier { name } => {
-                            self.emit(Instruction::DeleteName {
-                                name: name.to_string(),
-                            });
+                            self.delete_name(name);
                         }
                         ast::Expression::Attribute { value, name } => {
                             self.compile_expression(value)?;
@@ -414,7 +480,7 @@ impl Compiler {
                             self.emit(Instruction::DeleteSubscript);
                         }
                         _ => {
-                            return Err(CompileError::Delete(target.name()));
+                            return Err(self.error(CompileErrorType::Delete(target.name())));
                         }
                     }
                 }
@@ -454,6 +520,18 @@ impl Compiler {
             line_number,
             name.to_string(),
         ));
+        self.enter_scope();
+
+        // Arguments used by nested scopes are kept in cells, so move them
+        // out of their fast local slots:
+        let varnames = self.current_code_object().varnames.clone();
+        for (idx, name) in varnames.iter().enumerate() {
+            if self.fast_local_index(name).is_none() {
+                self.emit(Instruction::LoadFast { idx });
+                self.emit(Instruction::DeleteFast { idx });
+                self.store_name(name);
+            }
+        }
 
         let mut flags = bytecode::FunctionOpArg::empty();
         if have_kwargs {
@@ -488,9 +566,48 @@ impl Compiler {
         handlers: &[ast::ExceptHandler],
         orelse: &Option<Vec<ast::LocatedStatement>>,
         finalbody: &Option<Vec<ast::LocatedStatement>>,
+    ) -> Result<(), CompileError> {
+        let finally_handler_label = self.new_label();
+        if finalbody.is_some() {
+            self.emit(Instruction::SetupFinally {
+                handler: finally_handler_label,
+            });
+        }
+
+        if handlers.is_empty() {
+            self.compile_statements(body)?;
+            if let Some(statements) = orelse {
+                self.compile_statements(statements)?;
+            }
+        } else {
+            self.compile_try_except(body, handlers, orelse)?;
+        }
+
+        // finally:
+        if let Some(statements) = finalbody {
+            // The try block and its handlers completed normally:
+            self.emit(Instruction::PopBlock);
+            self.emit(Instruction::EnterFinally);
+
+            // The finally handler is also jumped to by the frame, when it
+            // unwinds the block stack because of return, break, continue or
+            // an exception:
+            self.set_label(finally_handler_label);
+            self.compile_statements(statements)?;
+            self.emit(Instruction::EndFinally);
+        }
+
+        Ok(())
+    }
+
+    fn compile_try_except(
+        &mut self,
+        body: &[ast::LocatedStatement],
+        handlers: &[ast::ExceptHandler],
+        orelse: &Option<Vec<ast::LocatedStatement>>,
     ) -> Result<(), CompileError> {
         let mut handler_label = self.new_label();
-        let finally_label = self.new_label();
+        let end_label = self.new_label();
         let else_label = self.new_label();
         // try:
         self.emit(Instruction::SetupExcept {
@@ -512,9 +629,7 @@ impl Compiler {
                 self.emit(Instruction::Duplicate);
 
                 // Check exception type:
-                self.emit(Instruction::LoadName {
-                    name: String::from("isinstance"),
-                });
+                self.load_name("isinstance");
                 self.emit(Instruction::Rotate { amount: 2 });
                 self.compile_expression(exc_type)?;
                 self.emit(Instruction::CallFunction {
@@ -528,9 +643,7 @@ impl Compiler {
 
                 // We have a match, store in name (except x as y)
                 if let Some(alias) = &handler.name {
-                    self.emit(Instruction::StoreName {
-                        name: alias.clone(),
-                    });
+                    self.store_name(alias);
                 } else {
                     // Drop exception from top of stack:
                     self.emit(Instruction::Pop);
@@ -543,9 +656,8 @@ impl Compiler {
 
             // Handler code:
             self.compile_statements(&handler.body)?;
-            self.emit(Instruction::Jump {
-                target: finally_label,
-            });
+            self.emit(Instruction::PopException);
+            self.emit(Instruction::Jump { target: end_label });
 
             // Emit a new label for the next handler
             self.set_label(handler_label);
@@ -556,14 +668,10 @@ impl Compiler {
         });
         self.set_label(handler_label);
         // If code flows here, we have an unhandled exception,
-        // emit finally code and raise again!
-        // Duplicate finally code here:
-        // TODO: this bytecode is now duplicate, could this be
-        // improved?
-        if let Some(statements) = finalbody {
-            self.compile_statements(statements)?;
-        }
-        self.emit(Instruction::Raise { argc: 1 });
+        // raise it again. An enclosing finally block takes care
+        // of running the finally code:
+        self.emit(Instruction::Pop);
+        self.emit(Instruction::Raise { argc: 0 });
 
         // We successfully ran the try block:
         // else:
@@ -572,18 +680,13 @@ impl Compiler {
             self.compile_statements(statements)?;
         }
 
-        // finally:
-        self.set_label(finally_label);
-        if let Some(statements) = finalbody {
-            self.compile_statements(statements)?;
-        }
-
-        // unimplemented!();
+        self.set_label(end_label);
         Ok(())
     }
 
     fn compile_function_def(
         &mut self,
+        is_async: bool,
         name: &str,
         args: &ast::Parameters,
         body: &[ast::LocatedStatement],
@@ -594,9 +697,15 @@ impl Compiler {
         // remember to restore self.in_loop to the original after the function is compiled
         let was_in_loop = self.in_loop;
         let was_in_function_def = self.in_function_def;
+        let was_in_async_function = self.in_async_function;
         self.in_loop = false;
         self.in_function_def = true;
+        self.in_async_function = is_async;
         let mut flags = self.enter_function(name, args)?;
+        if is_async {
+            self.mark_coroutine();
+        }
+        let (doc, body) = self.split_docstring(body);
         self.compile_statements(body)?;
 
         // Emit None at end:
@@ -605,6 +714,7 @@ impl Compiler {
         });
         self.emit(Instruction::ReturnValue);
         let code = self.pop_code_object();
+        self.leave_scope();
 
         self.prepare_decorators(decorator_list)?;
 
@@ -644,6 +754,7 @@ impl Compiler {
             });
         }
 
+        flags |= self.load_closure(&code);
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::Code {
                 code: Box::new(code),
@@ -657,13 +768,20 @@ impl Compiler {
 
         // Turn code object into function object:
         self.emit(Instruction::MakeFunction { flags });
+        if let Some(doc) = doc {
+            self.emit(Instruction::Duplicate);
+            self.emit_docstring(doc);
+            self.emit(Instruction::Rotate { amount: 2 });
+            self.emit(Instruction::StoreAttr {
+                name: "__doc__".to_string(),
+            });
+        }
         self.apply_decorators(decorator_list);
 
-        self.emit(Instruction::StoreName {
-            name: name.to_string(),
-        });
+        self.store_name(name);
         self.in_loop = was_in_loop;
         self.in_function_def = was_in_function_def;
+        self.in_async_function = was_in_async_function;
         Ok(())
     }
 
@@ -676,7 +794,9 @@ impl Compiler {
         decorator_list: &[ast::Expression],
     ) -> Result<(), CompileError> {
         let was_in_loop = self.in_loop;
+        let was_in_async_function = self.in_async_function;
         self.in_loop = false;
+        self.in_async_function = false;
         self.prepare_decorators(decorator_list)?;
         self.emit(Instruction::LoadBuildClass);
         let line_number = self.get_source_line_number();
@@ -689,13 +809,32 @@ impl Compiler {
             line_number,
             name.to_string(),
         ));
+        self.enter_scope();
+        let (doc, body) = self.split_docstring(body);
+        if let Some(doc) = doc {
+            self.emit_docstring(doc);
+            self.store_name("__doc__");
+        }
         self.compile_statements(body)?;
-        self.emit(Instruction::LoadConst {
-            value: bytecode::Constant::None,
-        });
+
+        // Return the `__class__` cell, so that the class can be stored in it
+        // once it has been created:
+        let class_cell = self
+            .current_code_object()
+            .cellvars
+            .iter()
+            .position(|name| name == "__class__");
+        match class_cell {
+            Some(idx) => self.emit(Instruction::LoadClosure { idx }),
+            None => self.emit(Instruction::LoadConst {
+                value: bytecode::Constant::None,
+            }),
+        }
         self.emit(Instruction::ReturnValue);
 
         let code = self.pop_code_object();
+        self.leave_scope();
+        let flags = self.load_closure(&code);
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::Code {
                 code: Box::new(code),
@@ -708,9 +847,7 @@ impl Compiler {
         });
 
         // Turn code object into function object:
-        self.emit(Instruction::MakeFunction {
-            flags: bytecode::FunctionOpArg::empty(),
-        });
+        self.emit(Instruction::MakeFunction { flags });
 
         self.emit(Instruction::LoadConst {
             value: bytecode::Constant::String {
@@ -752,15 +889,15 @@ impl Compiler {
 
         self.apply_decorators(decorator_list);
 
-        self.emit(Instruction::StoreName {
-            name: name.to_string(),
-        });
+        self.store_name(name);
         self.in_loop = was_in_loop;
+        self.in_async_function = was_in_async_function;
         Ok(())
     }
 
     fn compile_for(
         &mut self,
+        is_async: bool,
         target: &ast::Expression,
         iter: &[ast::Expression],
         body: &[ast::LocatedStatement],
@@ -780,11 +917,24 @@ impl Compiler {
             self.compile_expression(i)?;
         }
 
-        // Retrieve Iterator
-        self.emit(Instruction::GetIter);
+        if is_async {
+            self.emit(Instruction::GetAIter);
+
+            // The loop ends when awaiting the next item raises StopAsyncIteration:
+            self.set_label(start_label);
+            self.emit(Instruction::GetANext);
+            self.emit(Instruction::SetupExcept {
+                handler: else_label,
+            });
+            self.emit_await();
+            self.emit(Instruction::PopBlock);
+        } else {
+            // Retrieve Iterator
+            self.emit(Instruction::GetIter);
 
-        self.set_label(start_label);
-        self.emit(Instruction::ForIter { target: else_label });
+            self.set_label(start_label);
+            self.emit(Instruction::ForIter { target: else_label });
+        }
 
         // Start of loop iteration, set targets:
         self.compile_store(target)?;
@@ -798,6 +948,9 @@ impl Compiler {
             target: start_label,
         });
         self.set_label(else_label);
+        if is_async {
+            self.emit(Instruction::EndAsyncFor);
+        }
         self.emit(Instruction::PopBlock);
         if let Some(orelse) = orelse {
             self.compile_statements(orelse)?;
@@ -806,12 +959,51 @@ impl Compiler {
         Ok(())
     }
 
+    /// Compile an `async with` statement. Each context manager gets a
+    /// finally block, in which its awaited `__aexit__` decides whether an
+    /// exception is swallowed.
+    fn compile_async_with(
+        &mut self,
+        items: &[ast::WithItem],
+        body: &[ast::LocatedStatement],
+    ) -> Result<(), CompileError> {
+        let (item, rest) = match items.split_first() {
+            Some(split) => split,
+            None => return self.compile_statements(body),
+        };
+        let handler_label = self.new_label();
+
+        self.compile_expression(&item.context_expr)?;
+        self.emit(Instruction::BeforeAsyncWith);
+        self.emit_await();
+        self.emit(Instruction::SetupAsyncWith {
+            handler: handler_label,
+        });
+        match &item.optional_vars {
+            Some(var) => {
+                self.compile_store(var)?;
+            }
+            None => {
+                self.emit(Instruction::Pop);
+            }
+        }
+
+        self.compile_async_with(rest, body)?;
+
+        self.emit(Instruction::PopBlock);
+        self.emit(Instruction::EnterFinally);
+        self.set_label(handler_label);
+        self.emit(Instruction::WithCleanupStart);
+        self.emit_await();
+        self.emit(Instruction::WithCleanupFinish);
+        self.emit(Instruction::EndFinally);
+        Ok(())
+    }
+
     fn compile_store(&mut self, target: &ast::Expression) -> Result<(), CompileError> {
         match target {
             ast::Expression::Identifier { name } => {
-                self.emit(Instruction::StoreName {
-                    name: name.to_string(),
-                });
+                self.store_name(name);
             }
             ast::Expression::Subscript { a, b } => {
                 self.compile_expression(a)?;
@@ -831,7 +1023,7 @@ impl Compiler {
                 for (i, element) in elements.iter().enumerate() {
                     if let ast::Expression::Starred { .. } = element {
                         if seen_star {
-                            return Err(CompileError::StarArgs);
+                            return Err(self.error(CompileErrorType::StarArgs));
                         } else {
                             seen_star = true;
                             self.emit(Instruction::UnpackEx {
@@ -857,7 +1049,7 @@ impl Compiler {
                 }
             }
             _ => {
-                return Err(CompileError::Assign(target.name()));
+                return Err(self.error(CompileErrorType::Assign(target.name())));
             }
         }
 
@@ -1063,7 +1255,7 @@ impl Compiler {
             }
             ast::Expression::Yield { value } => {
                 if !self.in_function_def {
-                    return Err(CompileError::InvalidYield);
+                    return Err(self.error(CompileErrorType::InvalidYield));
                 }
                 self.mark_generator();
                 match value {
@@ -1075,6 +1267,9 @@ impl Compiler {
                 self.emit(Instruction::YieldValue);
             }
             ast::Expression::YieldFrom { value } => {
+                if self.in_async_function {
+                    return Err(self.error(CompileErrorType::AsyncYieldFrom));
+                }
                 self.mark_generator();
                 self.compile_expression(value)?;
                 self.emit(Instruction::GetIter);
@@ -1083,6 +1278,14 @@ impl Compiler {
                 });
                 self.emit(Instruction::YieldFrom);
             }
+            ast::Expression::Await { value } => {
+                if !self.in_async_function {
+                    return Err(self.error(CompileErrorType::InvalidAsync("await")));
+                }
+                self.mark_coroutine();
+                self.compile_expression(value)?;
+                self.emit_await();
+            }
             ast::Expression::True => {
                 self.emit(Instruction::LoadConst {
                     value: bytecode::Constant::Boolean { value: true },
@@ -1113,18 +1316,29 @@ impl Compiler {
                     },
                 });
             }
-            ast::Expression::Identifier { name } => {
-                self.emit(Instruction::LoadName {
-                    name: name.to_string(),
+            // `__debug__` is a constant, so that `if __debug__:` blocks can be optimized away.
+            ast::Expression::Identifier { name } if name == "__debug__" => {
+                self.emit(Instruction::LoadConst {
+                    value: bytecode::Constant::Boolean {
+                        value: self.optimize == 0,
+                    },
                 });
             }
+            ast::Expression::Identifier { name } => {
+                self.load_name(name);
+            }
             ast::Expression::Lambda { args, body } => {
                 let name = "<lambda>".to_string();
                 // no need to worry about the self.loop_depth because there are no loops in lambda expressions
-                let flags = self.enter_function(&name, args)?;
+                let was_in_async_function = self.in_async_function;
+                self.in_async_function = false;
+                let mut flags = self.enter_function(&name, args)?;
                 self.compile_expression(body)?;
+                self.in_async_function = was_in_async_function;
                 self.emit(Instruction::ReturnValue);
                 let code = self.pop_code_object();
+                self.leave_scope();
+                flags |= self.load_closure(&code);
                 self.emit(Instruction::LoadConst {
                     value: bytecode::Constant::Code {
                         code: Box::new(code),
@@ -1302,6 +1516,7 @@ impl Compiler {
             line_number,
             name.clone(),
         ));
+        self.enter_scope();
 
         // Create empty object of proper type:
         match kind {
@@ -1326,31 +1541,51 @@ impl Compiler {
             }
         }
 
+        let is_generator_expression = match kind {
+            ast::ComprehensionKind::GeneratorExpression { .. } => true,
+            _ => false,
+        };
+        if generators.iter().any(|generator| generator.is_async) {
+            if !is_generator_expression && !self.in_async_function {
+                return Err(self.error(CompileErrorType::AsyncComprehension));
+            }
+            self.mark_coroutine();
+        }
+
         let mut loop_labels = vec![];
         for generator in generators {
             if loop_labels.is_empty() {
                 // Load iterator onto stack (passed as first argument):
-                self.emit(Instruction::LoadName {
-                    name: String::from(".0"),
-                });
+                self.load_name(".0");
             } else {
                 // Evaluate iterated item:
                 self.compile_expression(&generator.iter)?;
 
                 // Get iterator / turn item into an iterator
-                self.emit(Instruction::GetIter);
+                if generator.is_async {
+                    self.emit(Instruction::GetAIter);
+                } else {
+                    self.emit(Instruction::GetIter);
+                }
             }
 
             // Setup for loop:
             let start_label = self.new_label();
             let end_label = self.new_label();
-            loop_labels.push((start_label, end_label));
+            loop_labels.push((start_label, end_label, generator.is_async));
             self.emit(Instruction::SetupLoop {
                 start: start_label,
                 end: end_label,
             });
             self.set_label(start_label);
-            self.emit(Instruction::ForIter { target: end_label });
+            if generator.is_async {
+                self.emit(Instruction::GetANext);
+                self.emit(Instruction::SetupExcept { handler: end_label });
+                self.emit_await();
+                self.emit(Instruction::PopBlock);
+            } else {
+                self.emit(Instruction::ForIter { target: end_label });
+            }
 
             self.compile_store(&generator.target)?;
 
@@ -1394,7 +1629,7 @@ impl Compiler {
             }
         }
 
-        for (start_label, end_label) in loop_labels.iter().rev() {
+        for (start_label, end_label, is_async) in loop_labels.iter().rev() {
             // Repeat:
             self.emit(Instruction::Jump {
                 target: *start_label,
@@ -1402,14 +1637,26 @@ impl Compiler {
 
             // End of for loop:
             self.set_label(*end_label);
+            if *is_async {
+                self.emit(Instruction::EndAsyncFor);
+            }
             self.emit(Instruction::PopBlock);
         }
 
+        if let ast::ComprehensionKind::GeneratorExpression { .. } = kind {
+            self.emit(Instruction::LoadConst {
+                value: bytecode::Constant::None,
+            });
+        }
+
         // Return freshly filled list:
         self.emit(Instruction::ReturnValue);
 
         // Fetch code for listcomp function:
         let code = self.pop_code_object();
+        let is_coroutine = code.is_coroutine;
+        self.leave_scope();
+        let flags = self.load_closure(&code);
 
         // List comprehension code:
         self.emit(Instruction::LoadConst {
@@ -1424,20 +1671,27 @@ impl Compiler {
         });
 
         // Turn code object into function object:
-        self.emit(Instruction::MakeFunction {
-            flags: bytecode::FunctionOpArg::empty(),
-        });
+        self.emit(Instruction::MakeFunction { flags });
 
         // Evaluate iterated item:
         self.compile_expression(&generators[0].iter)?;
 
         // Get iterator / turn item into an iterator
-        self.emit(Instruction::GetIter);
+        if generators[0].is_async {
+            self.emit(Instruction::GetAIter);
+        } else {
+            self.emit(Instruction::GetIter);
+        }
 
         // Call just created <listcomp> function:
         self.emit(Instruction::CallFunction {
             typ: CallType::Positional(1),
         });
+
+        // A comprehension which awaits runs as a coroutine:
+        if is_coroutine && !is_generator_expression {
+            self.emit_await();
+        }
         Ok(())
     }
 
@@ -1471,6 +1725,168 @@ impl Compiler {
         Ok(())
     }
 
+    fn enter_scope(&mut self) {
+        // Enter the next nested scope, in the order recorded by the symbol table:
+        let table = self
+            .symbol_table_stack
+            .last_mut()
+            .unwrap()
+            .sub_tables
+            .remove(0);
+
+        // Variables shared with nested scopes are accessed through cells:
+        let mut cellvars: Vec<String> = table
+            .symbols
+            .values()
+            .filter(|symbol| symbol.scope == SymbolScope::Cell)
+            .map(|symbol| symbol.name.clone())
+            .collect();
+        cellvars.sort();
+        let mut freevars: Vec<String> = table
+            .symbols
+            .values()
+            .filter(|symbol| symbol.is_free())
+            .map(|symbol| symbol.name.clone())
+            .collect();
+        freevars.sort();
+        let code = self.current_code_object();
+        code.cellvars = cellvars;
+        code.freevars = freevars;
+
+        self.symbol_table_stack.push(table);
+    }
+
+    /// Push the cells a nested code object takes its free variables from.
+    fn load_closure(&mut self, code: &CodeObject) -> bytecode::FunctionOpArg {
+        if code.freevars.is_empty() {
+            return bytecode::FunctionOpArg::empty();
+        }
+
+        for name in &code.freevars {
+            let idx = self
+                .deref_index(name)
+                .expect("free variable of a nested scope must be a cell or free variable");
+            self.emit(Instruction::LoadClosure { idx });
+        }
+        self.emit(Instruction::BuildTuple {
+            size: code.freevars.len(),
+            unpack: false,
+        });
+        bytecode::FunctionOpArg::HAS_CLOSURE
+    }
+
+    fn leave_scope(&mut self) {
+        let table = self.symbol_table_stack.pop().unwrap();
+        assert!(table.sub_tables.is_empty());
+    }
+
+    fn scope_for_name(&self, name: &str) -> bytecode::NameScope {
+        let table = self.symbol_table_stack.last().unwrap();
+        let symbol = match table.lookup(name) {
+            Some(symbol) => symbol,
+            // Names introduced by the compiler itself:
+            None => return bytecode::NameScope::Local,
+        };
+        match symbol.scope {
+            SymbolScope::GlobalExplicit => bytecode::NameScope::Global,
+            SymbolScope::GlobalImplicit if table.typ == ScopeType::Function => {
+                bytecode::NameScope::Global
+            }
+            _ => bytecode::NameScope::Local,
+        }
+    }
+
+    /// Get the fast local slot of a name, if the name is local to a function.
+    fn fast_local_index(&mut self, name: &str) -> Option<usize> {
+        let table = self.symbol_table_stack.last().unwrap();
+        if table.typ != ScopeType::Function {
+            return None;
+        }
+        match table.lookup(name) {
+            Some(symbol) if symbol.scope == SymbolScope::Local => {}
+            _ => return None,
+        }
+
+        let varnames = &mut self.current_code_object().varnames;
+        let idx = match varnames.iter().position(|varname| varname == name) {
+            Some(idx) => idx,
+            None => {
+                varnames.push(name.to_string());
+                varnames.len() - 1
+            }
+        };
+        Some(idx)
+    }
+
+    /// Get the cell index of a name, if the name is a cell or free variable.
+    fn deref_index(&mut self, name: &str) -> Option<usize> {
+        let code = self.current_code_object();
+        code.cellvars
+            .iter()
+            .position(|cellvar| cellvar == name)
+            .or_else(|| {
+                code.freevars
+                    .iter()
+                    .position(|freevar| freevar == name)
+                    .map(|idx| code.cellvars.len() + idx)
+            })
+    }
+
+    /// Get the cell index of a name which the symbol table resolved to a
+    /// cell or free variable.
+    fn deref_name_index(&mut self, name: &str) -> Option<usize> {
+        match self.symbol_table_stack.last().unwrap().lookup(name) {
+            Some(symbol) => match symbol.scope {
+                SymbolScope::Cell | SymbolScope::Free => {}
+                _ => return None,
+            },
+            None => return None,
+        }
+        self.deref_index(name)
+    }
+
+    fn load_name(&mut self, name: &str) {
+        if let Some(idx) = self.fast_local_index(name) {
+            self.emit(Instruction::LoadFast { idx });
+        } else if let Some(idx) = self.deref_name_index(name) {
+            self.emit(Instruction::LoadDeref { idx });
+        } else {
+            let scope = self.scope_for_name(name);
+            self.emit(Instruction::LoadName {
+                name: name.to_string(),
+                scope,
+            });
+        }
+    }
+
+    fn store_name(&mut self, name: &str) {
+        if let Some(idx) = self.fast_local_index(name) {
+            self.emit(Instruction::StoreFast { idx });
+        } else if let Some(idx) = self.deref_name_index(name) {
+            self.emit(Instruction::StoreDeref { idx });
+        } else {
+            let scope = self.scope_for_name(name);
+            self.emit(Instruction::StoreName {
+                name: name.to_string(),
+                scope,
+            });
+        }
+    }
+
+    fn delete_name(&mut self, name: &str) {
+        if let Some(idx) = self.fast_local_index(name) {
+            self.emit(Instruction::DeleteFast { idx });
+        } else if let Some(idx) = self.deref_name_index(name) {
+            self.emit(Instruction::DeleteDeref { idx });
+        } else {
+            let scope = self.scope_for_name(name);
+            self.emit(Instruction::DeleteName {
+                name: name.to_string(),
+                scope,
+            });
+        }
+    }
+
     // Low level helper functions:
     fn emit(&mut self, instruction: Instruction) {
         let location = self.current_source_location.clone();
@@ -1498,6 +1914,10 @@ impl Compiler {
         self.current_code_object().label_map.insert(label, position);
     }
 
+    fn error(&self, error: CompileErrorType) -> CompileError {
+        CompileError::new(error, self.current_source_location.clone())
+    }
+
     fn set_source_location(&mut self, location: &ast::Location) {
         self.current_source_location = location.clone();
     }
@@ -1509,6 +1929,52 @@ impl Compiler {
     fn mark_generator(&mut self) {
         self.current_code_object().is_generator = true;
     }
+
+    fn mark_coroutine(&mut self) {
+        self.current_code_object().is_coroutine = true;
+    }
+
+    /// Await the value on top of the stack.
+    /// Split the docstring off a body. The docstring is dropped at `-OO`,
+    /// but the statement is never compiled as an expression.
+    fn split_docstring<'a>(
+        &self,
+        body: &'a [ast::LocatedStatement],
+    ) -> (Option<&'a str>, &'a [ast::LocatedStatement]) {
+        if let Some((first, rest)) = body.split_first() {
+            if let ast::Statement::Expression {
+                expression:
+                    ast::Expression::String {
+                        value: ast::StringGroup::Constant { value },
+                    },
+            } = &first.node
+            {
+                let doc = if self.optimize >= 2 {
+                    None
+                } else {
+                    Some(value.as_str())
+                };
+                return (doc, rest);
+            }
+        }
+        (None, body)
+    }
+
+    fn emit_docstring(&mut self, doc: &str) {
+        self.emit(Instruction::LoadConst {
+            value: bytecode::Constant::String {
+                value: doc.to_string(),
+            },
+        });
+    }
+
+    fn emit_await(&mut self) {
+        self.emit(Instruction::GetAwaitable);
+        self.emit(Instruction::LoadConst {
+            value: bytecode::Constant::None,
+        });
+        self.emit(Instruction::YieldFrom);
+    }
 }
 
 #[cfg(test)]
@@ -1517,6 +1983,7 @@ mod tests {
     use crate::bytecode::CodeObject;
     use crate::bytecode::Constant::*;
     use crate::bytecode::Instruction::*;
+    use crate::symboltable::make_symbol_table;
     use rustpython_parser::parser;
 
     fn compile_exec(source: &str) -> CodeObject {
@@ -1524,7 +1991,8 @@ mod tests {
         compiler.source_path = Some("source_path".to_string());
         compiler.push_new_code_object("<module>".to_string());
         let ast = parser::parse_program(&source.to_string()).unwrap();
-        compiler.compile_program(&ast).unwrap();
+        let symbol_scope = make_symbol_table(&ast).unwrap();
+        compiler.compile_program(&ast, symbol_scope).unwrap();
         compiler.pop_code_object()
     }
 
@@ -1606,4 +2074,25 @@ mod tests {
             code.instructions
         );
     }
+
+    #[test]
+    fn test_function_fast_locals() {
+        let code = compile_exec("def f(a):\n b = a\n return b\n");
+        let function = match code.get_constants().next() {
+            Some(Code { code }) => code,
+            _ => panic!("expected a code object constant"),
+        };
+        assert_eq!(vec!["a".to_string(), "b".to_string()], function.varnames);
+        assert_eq!(
+            vec![
+                LoadFast { idx: 0 },
+                StoreFast { idx: 1 },
+                LoadFast { idx: 1 },
+                ReturnValue,
+                LoadConst { value: None },
+                ReturnValue
+            ],
+            function.instructions
+        );
+    }
 }
@@ -0,0 +1,695 @@
+//! The text encodings built into the interpreter.
+//!
+//! This works on rust strings and byte slices only. The `codecs` module
+//! builds the codec registry and the python level error reporting on top
+//! of it.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    pub fn native() -> ByteOrder {
+        if cfg!(target_endian = "big") {
+            ByteOrder::Big
+        } else {
+            ByteOrder::Little
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Encoding {
+    Utf8,
+    /// Without a byte order, the encoder writes a byte order mark and the
+    /// decoder reads one.
+    Utf16(Option<ByteOrder>),
+    Utf32(Option<ByteOrder>),
+    Latin1,
+    Ascii,
+    Cp1252,
+}
+
+impl Encoding {
+    /// Find the encoding for one of its names or aliases.
+    pub fn lookup(name: &str) -> Option<Encoding> {
+        let name = name
+            .trim()
+            .to_lowercase()
+            .replace(|c| c == '_' || c == ' ', "-");
+        let encoding = match name.as_str() {
+            "utf-8" | "utf8" | "u8" | "utf" => Encoding::Utf8,
+            "utf-16" | "utf16" | "u16" => Encoding::Utf16(None),
+            "utf-16-le" | "utf-16le" => Encoding::Utf16(Some(ByteOrder::Little)),
+            "utf-16-be" | "utf-16be" => Encoding::Utf16(Some(ByteOrder::Big)),
+            "utf-32" | "utf32" | "u32" => Encoding::Utf32(None),
+            "utf-32-le" | "utf-32le" => Encoding::Utf32(Some(ByteOrder::Little)),
+            "utf-32-be" | "utf-32be" => Encoding::Utf32(Some(ByteOrder::Big)),
+            "latin-1" | "latin1" | "latin" | "l1" | "iso-8859-1" | "iso8859-1" | "8859"
+            | "cp819" => Encoding::Latin1,
+            "ascii" | "us-ascii" | "646" | "us" => Encoding::Ascii,
+            "cp1252" | "windows-1252" | "1252" => Encoding::Cp1252,
+            _ => return None,
+        };
+        Some(encoding)
+    }
+
+    /// The canonical name, as `codecs.lookup` reports it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16(None) => "utf-16",
+            Encoding::Utf16(Some(ByteOrder::Little)) => "utf-16-le",
+            Encoding::Utf16(Some(ByteOrder::Big)) => "utf-16-be",
+            Encoding::Utf32(None) => "utf-32",
+            Encoding::Utf32(Some(ByteOrder::Little)) => "utf-32-le",
+            Encoding::Utf32(Some(ByteOrder::Big)) => "utf-32-be",
+            Encoding::Latin1 => "iso8859-1",
+            Encoding::Ascii => "ascii",
+            Encoding::Cp1252 => "cp1252",
+        }
+    }
+
+    /// The codec name used in error messages.
+    pub fn error_name(self, byte_order: Option<ByteOrder>) -> &'static str {
+        match self {
+            Encoding::Utf16(None) => {
+                Encoding::Utf16(Some(byte_order.unwrap_or_else(ByteOrder::native))).name()
+            }
+            Encoding::Utf32(None) => {
+                Encoding::Utf32(Some(byte_order.unwrap_or_else(ByteOrder::native))).name()
+            }
+            Encoding::Latin1 => "latin-1",
+            Encoding::Cp1252 => "charmap",
+            _ => self.name(),
+        }
+    }
+
+    /// The byte order the encoder uses.
+    fn byte_order(self) -> ByteOrder {
+        match self {
+            Encoding::Utf16(Some(byte_order)) | Encoding::Utf32(Some(byte_order)) => byte_order,
+            _ => ByteOrder::native(),
+        }
+    }
+
+    fn byte_order_mark(self) -> Option<ByteOrderMark> {
+        match self {
+            Encoding::Utf16(None) => Some(ByteOrderMark { width: 2 }),
+            Encoding::Utf32(None) => Some(ByteOrderMark { width: 4 }),
+            _ => None,
+        }
+    }
+
+    fn encode_char(self, c: char, byte_order: ByteOrder, output: &mut Vec<u8>) -> bool {
+        let code = c as u32;
+        match self {
+            Encoding::Utf8 => {
+                let mut buffer = [0; 4];
+                output.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            }
+            Encoding::Utf16(_) => {
+                let mut buffer = [0; 2];
+                for unit in c.encode_utf16(&mut buffer) {
+                    match byte_order {
+                        ByteOrder::Little => output.extend_from_slice(&unit.to_le_bytes()),
+                        ByteOrder::Big => output.extend_from_slice(&unit.to_be_bytes()),
+                    }
+                }
+            }
+            Encoding::Utf32(_) => match byte_order {
+                ByteOrder::Little => output.extend_from_slice(&code.to_le_bytes()),
+                ByteOrder::Big => output.extend_from_slice(&code.to_be_bytes()),
+            },
+            Encoding::Latin1 if code < 0x100 => output.push(code as u8),
+            Encoding::Ascii if code < 0x80 => output.push(code as u8),
+            Encoding::Cp1252 => match cp1252_encode(c) {
+                Some(byte) => output.push(byte),
+                None => return false,
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    fn can_encode(self, c: char) -> bool {
+        self.encode_char(c, ByteOrder::native(), &mut Vec::new())
+    }
+
+    fn encode_reason(self) -> &'static str {
+        match self {
+            Encoding::Ascii => "ordinal not in range(128)",
+            Encoding::Latin1 => "ordinal not in range(256)",
+            _ => "character maps to <undefined>",
+        }
+    }
+
+    /// Decode the character at the start of `input`.
+    fn decode_char(self, input: &[u8], byte_order: ByteOrder) -> Decoded {
+        match self {
+            Encoding::Utf8 => decode_utf8_char(input),
+            Encoding::Utf16(_) => decode_utf16_char(input, byte_order),
+            Encoding::Utf32(_) => {
+                if input.len() < 4 {
+                    return Decoded::Incomplete("truncated data");
+                }
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(&input[..4]);
+                let code = match byte_order {
+                    ByteOrder::Little => u32::from_le_bytes(bytes),
+                    ByteOrder::Big => u32::from_be_bytes(bytes),
+                };
+                match std::char::from_u32(code) {
+                    Some(c) => Decoded::Char(c, 4),
+                    None if code >= 0xd800 && code < 0xe000 => Decoded::Invalid(
+                        4,
+                        "code point in surrogate code point range(0xd800, 0xe000)",
+                    ),
+                    None => Decoded::Invalid(4, "code point not in range(0x110000)"),
+                }
+            }
+            Encoding::Latin1 => Decoded::Char(input[0] as char, 1),
+            Encoding::Ascii if input[0] < 0x80 => Decoded::Char(input[0] as char, 1),
+            Encoding::Ascii => Decoded::Invalid(1, "ordinal not in range(128)"),
+            Encoding::Cp1252 => match cp1252_decode(input[0]) {
+                Some(c) => Decoded::Char(c, 1),
+                None => Decoded::Invalid(1, "character maps to <undefined>"),
+            },
+        }
+    }
+}
+
+struct ByteOrderMark {
+    width: usize,
+}
+
+impl ByteOrderMark {
+    fn bytes(&self, byte_order: ByteOrder) -> Vec<u8> {
+        let mut bytes = vec![];
+        let encoding = if self.width == 2 {
+            Encoding::Utf16(None)
+        } else {
+            Encoding::Utf32(None)
+        };
+        encoding.encode_char('\u{feff}', byte_order, &mut bytes);
+        bytes
+    }
+
+    fn detect(&self, input: &[u8]) -> Option<ByteOrder> {
+        [ByteOrder::Little, ByteOrder::Big]
+            .iter()
+            .cloned()
+            .find(|&byte_order| input.starts_with(&self.bytes(byte_order)))
+    }
+}
+
+enum Decoded {
+    /// A character and the number of bytes it took.
+    Char(char, usize),
+    /// The number of bytes that do not form a character, and why.
+    Invalid(usize, &'static str),
+    /// The input ends in the middle of a character.
+    Incomplete(&'static str),
+}
+
+fn decode_utf8_char(input: &[u8]) -> Decoded {
+    let chunk = &input[..input.len().min(4)];
+    let valid = match std::str::from_utf8(chunk) {
+        Ok(valid) => valid,
+        Err(err) if err.valid_up_to() > 0 => {
+            std::str::from_utf8(&chunk[..err.valid_up_to()]).unwrap()
+        }
+        Err(err) => {
+            return match err.error_len() {
+                Some(length) if is_utf8_start_byte(chunk[0]) => {
+                    Decoded::Invalid(length, "invalid continuation byte")
+                }
+                Some(length) => Decoded::Invalid(length, "invalid start byte"),
+                None => Decoded::Incomplete("unexpected end of data"),
+            };
+        }
+    };
+    let c = valid.chars().next().unwrap();
+    Decoded::Char(c, c.len_utf8())
+}
+
+fn is_utf8_start_byte(byte: u8) -> bool {
+    byte < 0x80 || (byte >= 0xc2 && byte <= 0xf4)
+}
+
+fn decode_utf16_char(input: &[u8], byte_order: ByteOrder) -> Decoded {
+    let unit = |index: usize| {
+        let bytes = [input[index], input[index + 1]];
+        match byte_order {
+            ByteOrder::Little => u16::from_le_bytes(bytes),
+            ByteOrder::Big => u16::from_be_bytes(bytes),
+        }
+    };
+    if input.len() < 2 {
+        return Decoded::Incomplete("truncated data");
+    }
+    let first = unit(0);
+    match first {
+        0xd800..=0xdbff => {
+            if input.len() < 4 {
+                return Decoded::Incomplete("unexpected end of data");
+            }
+            let second = unit(2);
+            if second < 0xdc00 || second > 0xdfff {
+                return Decoded::Invalid(2, "illegal UTF-16 surrogate");
+            }
+            let code = 0x10000 + ((u32::from(first) - 0xd800) << 10) + (u32::from(second) - 0xdc00);
+            Decoded::Char(std::char::from_u32(code).unwrap(), 4)
+        }
+        0xdc00..=0xdfff => Decoded::Invalid(2, "illegal encoding"),
+        _ => Decoded::Char(std::char::from_u32(u32::from(first)).unwrap(), 2),
+    }
+}
+
+/// The characters of bytes 0x80 to 0x9f in windows-1252; the rest of the
+/// code page is the same as latin-1.
+const CP1252_HIGH: [Option<char>; 32] = [
+    Some('\u{20ac}'),
+    None,
+    Some('\u{201a}'),
+    Some('\u{0192}'),
+    Some('\u{201e}'),
+    Some('\u{2026}'),
+    Some('\u{2020}'),
+    Some('\u{2021}'),
+    Some('\u{02c6}'),
+    Some('\u{2030}'),
+    Some('\u{0160}'),
+    Some('\u{2039}'),
+    Some('\u{0152}'),
+    None,
+    Some('\u{017d}'),
+    None,
+    None,
+    Some('\u{2018}'),
+    Some('\u{2019}'),
+    Some('\u{201c}'),
+    Some('\u{201d}'),
+    Some('\u{2022}'),
+    Some('\u{2013}'),
+    Some('\u{2014}'),
+    Some('\u{02dc}'),
+    Some('\u{2122}'),
+    Some('\u{0161}'),
+    Some('\u{203a}'),
+    Some('\u{0153}'),
+    None,
+    Some('\u{017e}'),
+    Some('\u{0178}'),
+];
+
+fn cp1252_decode(byte: u8) -> Option<char> {
+    match byte {
+        0x80..=0x9f => CP1252_HIGH[usize::from(byte - 0x80)],
+        _ => Some(byte as char),
+    }
+}
+
+fn cp1252_encode(c: char) -> Option<u8> {
+    match c as u32 {
+        code if code < 0x80 || (code >= 0xa0 && code < 0x100) => Some(code as u8),
+        _ => CP1252_HIGH
+            .iter()
+            .position(|&high| high == Some(c))
+            .map(|index| 0x80 + index as u8),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ErrorHandler {
+    Strict,
+    Ignore,
+    Replace,
+    BackslashReplace,
+    XmlCharRefReplace,
+}
+
+impl ErrorHandler {
+    /// Find the handler called `name`. There is no `surrogateescape`, since
+    /// strings can't hold the lone surrogates it decodes undecodable bytes to,
+    /// and any stand-in for them would not round trip losslessly.
+    pub fn lookup(name: &str) -> Option<ErrorHandler> {
+        let handler = match name {
+            "strict" => ErrorHandler::Strict,
+            "ignore" => ErrorHandler::Ignore,
+            "replace" => ErrorHandler::Replace,
+            "backslashreplace" => ErrorHandler::BackslashReplace,
+            "xmlcharrefreplace" => ErrorHandler::XmlCharRefReplace,
+            _ => return None,
+        };
+        Some(handler)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorHandler::Strict => "strict",
+            ErrorHandler::Ignore => "ignore",
+            ErrorHandler::Replace => "replace",
+            ErrorHandler::BackslashReplace => "backslashreplace",
+            ErrorHandler::XmlCharRefReplace => "xmlcharrefreplace",
+        }
+    }
+}
+
+/// Escape a character the way `backslashreplace` does.
+pub fn backslash_escape(c: char) -> String {
+    let code = c as u32;
+    if code < 0x100 {
+        format!("\\x{:02x}", code)
+    } else if code < 0x10000 {
+        format!("\\u{:04x}", code)
+    } else {
+        format!("\\U{:08x}", code)
+    }
+}
+
+/// A failure the error handler could not deal with.
+#[derive(Debug, PartialEq)]
+pub struct CodecError {
+    /// The range of the offending characters when encoding, or of the
+    /// offending bytes when decoding.
+    pub start: usize,
+    pub end: usize,
+    pub reason: &'static str,
+    /// The codec name for the error message.
+    pub encoding: &'static str,
+}
+
+pub fn encode(encoding: Encoding, text: &str, errors: ErrorHandler) -> Result<Vec<u8>, CodecError> {
+    IncrementalEncoder::new(encoding, errors).encode(text)
+}
+
+pub fn decode(encoding: Encoding, data: &[u8], errors: ErrorHandler) -> Result<String, CodecError> {
+    IncrementalDecoder::new(encoding, errors).decode(data, true)
+}
+
+#[derive(Debug, Clone)]
+pub struct IncrementalEncoder {
+    pub encoding: Encoding,
+    pub errors: ErrorHandler,
+    /// Whether the byte order mark has been written yet.
+    started: bool,
+}
+
+impl IncrementalEncoder {
+    pub fn new(encoding: Encoding, errors: ErrorHandler) -> IncrementalEncoder {
+        IncrementalEncoder {
+            encoding,
+            errors,
+            started: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.started = false;
+    }
+
+    pub fn encode(&mut self, text: &str) -> Result<Vec<u8>, CodecError> {
+        let encoding = self.encoding;
+        let byte_order = encoding.byte_order();
+        let mut output = Vec::with_capacity(text.len());
+        if !self.started {
+            if let Some(mark) = encoding.byte_order_mark() {
+                output.extend(mark.bytes(byte_order));
+            }
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let mut position = 0;
+        while position < chars.len() {
+            let c = chars[position];
+            if encoding.encode_char(c, byte_order, &mut output) {
+                position += 1;
+                continue;
+            }
+            let end = position
+                + chars[position..]
+                    .iter()
+                    .take_while(|&&c| !encoding.can_encode(c))
+                    .count();
+            let mut replace = |replacement: &str| {
+                for c in replacement.chars() {
+                    encoding.encode_char(c, byte_order, &mut output);
+                }
+            };
+            match self.errors {
+                ErrorHandler::Strict => {
+                    return Err(CodecError {
+                        start: position,
+                        end,
+                        reason: encoding.encode_reason(),
+                        encoding: encoding.error_name(None),
+                    });
+                }
+                ErrorHandler::Ignore => {}
+                ErrorHandler::Replace => replace(&"?".repeat(end - position)),
+                ErrorHandler::BackslashReplace => {
+                    for &c in &chars[position..end] {
+                        replace(&backslash_escape(c));
+                    }
+                }
+                ErrorHandler::XmlCharRefReplace => {
+                    for &c in &chars[position..end] {
+                        replace(&format!("&#{};", c as u32));
+                    }
+                }
+            }
+            position = end;
+        }
+        self.started = true;
+        Ok(output)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IncrementalDecoder {
+    pub encoding: Encoding,
+    pub errors: ErrorHandler,
+    /// The byte order, once the byte order mark has been read.
+    byte_order: Option<ByteOrder>,
+    /// Trailing bytes of an incomplete character from the last call.
+    pending: Vec<u8>,
+}
+
+impl IncrementalDecoder {
+    pub fn new(encoding: Encoding, errors: ErrorHandler) -> IncrementalDecoder {
+        let byte_order = match encoding {
+            Encoding::Utf16(byte_order) | Encoding::Utf32(byte_order) => byte_order,
+            _ => Some(ByteOrder::native()),
+        };
+        IncrementalDecoder {
+            encoding,
+            errors,
+            byte_order,
+            pending: vec![],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = IncrementalDecoder::new(self.encoding, self.errors);
+    }
+
+    /// The bytes held back because they don't form a whole character yet.
+    pub fn pending(&self) -> &[u8] {
+        &self.pending
+    }
+
+    pub fn decode(&mut self, data: &[u8], is_final: bool) -> Result<String, CodecError> {
+        let mut input = std::mem::replace(&mut self.pending, vec![]);
+        input.extend_from_slice(data);
+        let mut position = 0;
+        if self.byte_order.is_none() {
+            let mark = self.encoding.byte_order_mark().unwrap();
+            if input.len() < mark.width && !is_final {
+                self.pending = input;
+                return Ok(String::new());
+            }
+            self.byte_order = Some(match mark.detect(&input) {
+                Some(byte_order) => {
+                    position = mark.width;
+                    byte_order
+                }
+                None => ByteOrder::native(),
+            });
+        }
+        let byte_order = self.byte_order.unwrap();
+        let mut output = String::with_capacity(input.len());
+        while position < input.len() {
+            let (end, reason) = match self.encoding.decode_char(&input[position..], byte_order) {
+                Decoded::Char(c, length) => {
+                    output.push(c);
+                    position += length;
+                    continue;
+                }
+                Decoded::Invalid(length, reason) => (position + length, reason),
+                Decoded::Incomplete(_) if !is_final => break,
+                Decoded::Incomplete(reason) => (input.len(), reason),
+            };
+            let bytes = &input[position..end];
+            match self.errors {
+                ErrorHandler::Ignore => {}
+                ErrorHandler::Replace => output.push('\u{fffd}'),
+                ErrorHandler::BackslashReplace => {
+                    for &byte in bytes {
+                        output.push_str(&format!("\\x{:02x}", byte));
+                    }
+                }
+                _ => {
+                    return Err(CodecError {
+                        start: position,
+                        end,
+                        reason,
+                        encoding: self.encoding.error_name(self.byte_order),
+                    });
+                }
+            }
+            position = end;
+        }
+        self.pending = input[position..].to_vec();
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(Encoding::lookup("UTF_8"), Some(Encoding::Utf8));
+        assert_eq!(
+            Encoding::lookup("utf-16-be"),
+            Some(Encoding::Utf16(Some(ByteOrder::Big)))
+        );
+        assert_eq!(Encoding::lookup("Latin 1"), Some(Encoding::Latin1));
+        assert_eq!(Encoding::lookup("windows-1252"), Some(Encoding::Cp1252));
+        assert_eq!(Encoding::lookup("ebcdic"), None);
+        assert_eq!(Encoding::Latin1.name(), "iso8859-1");
+    }
+
+    #[test]
+    fn test_encode() {
+        use ErrorHandler::*;
+        assert_eq!(encode(Encoding::Utf8, "é", Strict), Ok(vec![0xc3, 0xa9]));
+        assert_eq!(
+            encode(Encoding::Utf16(Some(ByteOrder::Big)), "a\u{1f600}", Strict),
+            Ok(vec![0, 0x61, 0xd8, 0x3d, 0xde, 0x00])
+        );
+        assert_eq!(
+            encode(Encoding::Utf32(Some(ByteOrder::Little)), "a", Strict),
+            Ok(vec![0x61, 0, 0, 0])
+        );
+        assert_eq!(encode(Encoding::Cp1252, "€", Strict), Ok(vec![0x80]));
+        assert_eq!(encode(Encoding::Ascii, "aéé", Replace), Ok(b"a??".to_vec()));
+        assert_eq!(encode(Encoding::Ascii, "aé", Ignore), Ok(b"a".to_vec()));
+        assert_eq!(
+            encode(Encoding::Ascii, "é€", BackslashReplace),
+            Ok(b"\\xe9\\u20ac".to_vec())
+        );
+        assert_eq!(
+            encode(Encoding::Latin1, "€", XmlCharRefReplace),
+            Ok(b"&#8364;".to_vec())
+        );
+        assert_eq!(
+            encode(Encoding::Ascii, "aéé!", Strict),
+            Err(CodecError {
+                start: 1,
+                end: 3,
+                reason: "ordinal not in range(128)",
+                encoding: "ascii",
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode() {
+        use ErrorHandler::*;
+        assert_eq!(
+            decode(Encoding::Utf8, &[0xc3, 0xa9], Strict),
+            Ok("é".to_string())
+        );
+        assert_eq!(
+            decode(Encoding::Utf8, b"a\xffb", Replace),
+            Ok("a\u{fffd}b".to_string())
+        );
+        assert_eq!(
+            decode(Encoding::Utf8, b"a\xe9", BackslashReplace),
+            Ok("a\\xe9".to_string())
+        );
+        assert_eq!(
+            decode(Encoding::Utf8, b"a\xc3", Strict),
+            Err(CodecError {
+                start: 1,
+                end: 2,
+                reason: "unexpected end of data",
+                encoding: "utf-8",
+            })
+        );
+        assert_eq!(
+            decode(Encoding::Utf8, b"\xc3a", Strict).unwrap_err().reason,
+            "invalid continuation byte"
+        );
+        assert_eq!(
+            decode(Encoding::Utf8, b"\x80", Strict).unwrap_err().reason,
+            "invalid start byte"
+        );
+        assert_eq!(
+            decode(Encoding::Cp1252, b"\x80\x81", Replace),
+            Ok("€\u{fffd}".to_string())
+        );
+        assert_eq!(
+            decode(
+                Encoding::Utf16(Some(ByteOrder::Big)),
+                &[0xd8, 0x3d, 0xde, 0x00],
+                Strict
+            ),
+            Ok("\u{1f600}".to_string())
+        );
+        assert_eq!(
+            decode(Encoding::Utf16(None), &[0xfe, 0xff, 0, 0x61], Strict),
+            Ok("a".to_string())
+        );
+        assert_eq!(
+            decode(
+                Encoding::Utf32(None),
+                &[0xff, 0xfe, 0, 0, 0x61, 0, 0, 0],
+                Strict
+            ),
+            Ok("a".to_string())
+        );
+    }
+
+    #[test]
+    fn test_surrogateescape() {
+        // Unsupported until strings can hold lone surrogates, so that
+        // undecodable bytes round trip
+        assert_eq!(ErrorHandler::lookup("surrogateescape"), None);
+    }
+
+    #[test]
+    fn test_incremental() {
+        let mut decoder = IncrementalDecoder::new(Encoding::Utf8, ErrorHandler::Strict);
+        assert_eq!(decoder.decode(b"a\xe2\x82", false), Ok("a".to_string()));
+        assert_eq!(decoder.pending(), b"\xe2\x82");
+        assert_eq!(decoder.decode(b"\xac", false), Ok("€".to_string()));
+        assert_eq!(decoder.decode(b"\xe2", false), Ok("".to_string()));
+        assert!(decoder.decode(b"", true).is_err());
+
+        let mut decoder = IncrementalDecoder::new(Encoding::Utf16(None), ErrorHandler::Strict);
+        assert_eq!(decoder.decode(&[0xfe], false), Ok("".to_string()));
+        assert_eq!(decoder.decode(&[0xff, 0], false), Ok("".to_string()));
+        assert_eq!(decoder.decode(&[0x61], true), Ok("a".to_string()));
+
+        let mut encoder = IncrementalEncoder::new(Encoding::Utf16(None), ErrorHandler::Strict);
+        assert_eq!(encoder.encode("a").unwrap().len(), 4);
+        assert_eq!(encoder.encode("a").unwrap().len(), 2);
+        encoder.reset();
+        assert_eq!(encoder.encode("a").unwrap().len(), 4);
+    }
+}
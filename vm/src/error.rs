@@ -1,10 +1,59 @@
 use rustpython_parser::error::ParseError;
+use rustpython_parser::lexer::Location;
 
 use std::error::Error;
 use std::fmt;
 
 #[derive(Debug)]
-pub enum CompileError {
+pub struct CompileError {
+    pub error: CompileErrorType,
+    pub location: Location,
+    /// The file the error occurred in, filled in by `compile::compile`.
+    pub source_path: Option<String>,
+    /// The source line the error occurred on, filled in by `compile::compile`.
+    pub statement: Option<String>,
+}
+
+impl CompileError {
+    pub fn new(error: CompileErrorType, location: Location) -> Self {
+        CompileError {
+            error,
+            location,
+            source_path: None,
+            statement: None,
+        }
+    }
+
+    /// Attach the file name and the offending source line to this error.
+    pub fn with_source(mut self, source: &str, source_path: &str) -> Self {
+        if let CompileErrorType::Parse(ParseError::EOF(_)) = self.error {
+            // Report an unexpected end of input at the end of the last line,
+            // rather than on the empty lines following it:
+            let source = source.trim_end();
+            let row = source.lines().count().max(1);
+            if self.location.get_row() == 0 || self.location.get_row() > row {
+                let column = source.lines().last().map_or(0, str::len) + 1;
+                self.location = Location::new(row, column);
+            }
+        }
+        let row = self.location.get_row();
+        self.source_path = Some(source_path.to_string());
+        if row > 0 {
+            self.statement = source.lines().nth(row - 1).map(str::to_string);
+        }
+        self
+    }
+}
+
+impl From<ParseError> for CompileError {
+    fn from(error: ParseError) -> Self {
+        let location = error.location().unwrap_or_default();
+        CompileError::new(CompileErrorType::Parse(error), location)
+    }
+}
+
+#[derive(Debug)]
+pub enum CompileErrorType {
     /// Invalid assignment, cannot store value in target.
     Assign(&'static str),
     /// Invalid delete
@@ -21,20 +70,48 @@ pub enum CompileError {
     InvalidContinue,
     InvalidReturn,
     InvalidYield,
+    /// `await`, `async for` or `async with` outside of an async function.
+    InvalidAsync(&'static str),
+    /// Asynchronous list, set or dict comprehension outside of an async function.
+    AsyncComprehension,
+    /// `yield from` inside an async function.
+    AsyncYieldFrom,
+    /// Invalid use of names, as detected by the symbol table analysis.
+    SyntaxError(String),
 }
 
 impl fmt::Display for CompileError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            CompileError::Assign(target) => write!(f, "can't assign to {}", target),
-            CompileError::Delete(target) => write!(f, "can't delete {}", target),
-            CompileError::ExpectExpr => write!(f, "Expecting expression, got statement"),
-            CompileError::Parse(err) => write!(f, "{}", err),
-            CompileError::StarArgs => write!(f, "Two starred expressions in assignment"),
-            CompileError::InvalidBreak => write!(f, "'break' outside loop"),
-            CompileError::InvalidContinue => write!(f, "'continue' outside loop"),
-            CompileError::InvalidReturn => write!(f, "'return' outside function"),
-            CompileError::InvalidYield => write!(f, "'yield' outside function"),
+        match &self.error {
+            CompileErrorType::Assign(target) => write!(f, "can't assign to {}", target),
+            CompileErrorType::Delete(target) => write!(f, "can't delete {}", target),
+            CompileErrorType::ExpectExpr => write!(f, "Expecting expression, got statement"),
+            // The location is kept separately, so it isn't repeated here.
+            CompileErrorType::Parse(err) => match err {
+                ParseError::EOF(_) => write!(f, "Got unexpected EOF"),
+                ParseError::ExtraToken((_, token, _)) => {
+                    write!(f, "Got extraneous token: {:?}", token)
+                }
+                ParseError::InvalidToken(_) => write!(f, "Got invalid token"),
+                ParseError::UnrecognizedToken((_, token, _), _) => {
+                    write!(f, "Got unexpected token: {:?}", token)
+                }
+                ParseError::Lexical(error) => write!(f, "{}", error.error),
+            },
+            CompileErrorType::StarArgs => write!(f, "Two starred expressions in assignment"),
+            CompileErrorType::InvalidBreak => write!(f, "'break' outside loop"),
+            CompileErrorType::InvalidContinue => write!(f, "'continue' outside loop"),
+            CompileErrorType::InvalidReturn => write!(f, "'return' outside function"),
+            CompileErrorType::InvalidYield => write!(f, "'yield' outside function"),
+            CompileErrorType::InvalidAsync(keyword) => {
+                write!(f, "'{}' outside async function", keyword)
+            }
+            CompileErrorType::AsyncComprehension => write!(
+                f,
+                "asynchronous comprehension outside of an asynchronous function"
+            ),
+            CompileErrorType::AsyncYieldFrom => write!(f, "'yield from' inside async function"),
+            CompileErrorType::SyntaxError(err) => write!(f, "{}", err),
         }
     }
 }
@@ -1,8 +1,18 @@
+use std::collections::HashSet;
+
+use num_traits::ToPrimitive;
+
 use crate::function::PyFuncArgs;
+use crate::obj::objbool;
+use crate::obj::objint;
+use crate::obj::objobject;
 use crate::obj::objsequence;
-use crate::obj::objtype;
-use crate::obj::objtype::PyClassRef;
-use crate::pyobject::{create_type, PyContext, PyObjectRef, PyResult, TypeProtocol};
+use crate::obj::objstr;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{
+    create_type, IdProtocol, PyContext, PyObjectRef, PyResult, TryFromObject, TypeProtocol,
+};
+use crate::traceback::{self, PyTraceback, PyTracebackRef};
 use crate::vm::VirtualMachine;
 
 fn exception_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
@@ -12,62 +22,329 @@ fn exception_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     } else {
         vm.new_str("No msg".to_string())
     };
-    let traceback = vm.ctx.new_list(Vec::new());
     vm.ctx.set_attr(&zelf, "msg", msg);
-    vm.ctx.set_attr(&zelf, "__traceback__", traceback);
+    vm.ctx
+        .set_attr(&zelf, "args", vm.ctx.new_tuple(args.args[1..].to_vec()));
+    Ok(vm.get_none())
+}
+
+fn system_exit_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    exception_init(vm, args.clone())?;
+    let zelf = args.args[0].clone();
+    let code = match args.args.len() {
+        1 => vm.get_none(),
+        2 => args.args[1].clone(),
+        _ => vm.ctx.new_tuple(args.args[1..].to_vec()),
+    };
+    vm.ctx.set_attr(&zelf, "code", code);
+    Ok(vm.get_none())
+}
+
+/// `SyntaxError(msg, (filename, lineno, offset, text))` sets the location
+/// attributes from the tuple.
+fn syntax_error_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    exception_init(vm, args.clone())?;
+    let zelf = args.args[0].clone();
+    if args.args.len() == 3 {
+        let info = vm.extract_elements(&args.args[2])?;
+        if info.len() != 4 {
+            return Err(vm.new_type_error(format!(
+                "SyntaxError details must have 4 items, not {}",
+                info.len()
+            )));
+        }
+        for (name, value) in ["filename", "lineno", "offset", "text"].iter().zip(info) {
+            vm.ctx.set_attr(&zelf, name, value);
+        }
+    }
     Ok(vm.get_none())
 }
 
+fn stop_iteration_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    exception_init(vm, args.clone())?;
+    let zelf = args.args[0].clone();
+    let value = match args.args.get(1) {
+        Some(value) => value.clone(),
+        None => vm.get_none(),
+    };
+    vm.ctx.set_attr(&zelf, "value", value);
+    Ok(vm.get_none())
+}
+
+/// Map an `errno` value onto the matching subclass of `OSError`.
+#[cfg(unix)]
+fn os_error_subclass(errno: i32, zoo: &ExceptionZoo) -> Option<&PyClassRef> {
+    let exc_type = match errno {
+        libc::EAGAIN | libc::EALREADY | libc::EINPROGRESS => &zoo.blocking_io_error,
+        libc::ECHILD => &zoo.child_process_error,
+        libc::EPIPE | libc::ESHUTDOWN => &zoo.broken_pipe_error,
+        libc::ECONNABORTED => &zoo.connection_aborted_error,
+        libc::ECONNREFUSED => &zoo.connection_refused_error,
+        libc::ECONNRESET => &zoo.connection_reset_error,
+        libc::EEXIST => &zoo.file_exists_error,
+        libc::ENOENT => &zoo.file_not_found_error,
+        libc::EINTR => &zoo.interrupted_error,
+        libc::EISDIR => &zoo.is_a_directory_error,
+        libc::ENOTDIR => &zoo.not_a_directory_error,
+        libc::EACCES | libc::EPERM => &zoo.permission_error,
+        libc::ESRCH => &zoo.process_lookup_error,
+        libc::ETIMEDOUT => &zoo.timeout_error,
+        _ => return None,
+    };
+    Some(exc_type)
+}
+
+#[cfg(not(unix))]
+fn os_error_subclass(_errno: i32, _zoo: &ExceptionZoo) -> Option<&PyClassRef> {
+    None
+}
+
+/// The errno passed to `OSError(errno, strerror, ...)`, if any.
+fn os_error_errno(args: &[PyObjectRef], vm: &VirtualMachine) -> Option<i32> {
+    if args.len() >= 2 && objtype::isinstance(&args[0], &vm.ctx.int_type()) {
+        objint::get_value(&args[0]).to_i32()
+    } else {
+        None
+    }
+}
+
+/// `OSError(errno, strerror)` creates an instance of the subclass matching
+/// `errno`, such as `FileNotFoundError` for `ENOENT`.
+fn os_error_new(vm: &VirtualMachine, mut args: PyFuncArgs) -> PyResult {
+    let cls = PyClassRef::try_from_object(vm, args.shift())?;
+    let cls = match os_error_errno(&args.args, vm) {
+        Some(errno) if cls.is(&vm.ctx.exceptions.os_error) => {
+            os_error_subclass(errno, &vm.ctx.exceptions)
+                .cloned()
+                .unwrap_or(cls)
+        }
+        _ => cls,
+    };
+    objobject::new_instance(vm, vec![cls.into_object()].into())
+}
+
+fn os_error_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    exception_init(vm, args.clone())?;
+    let zelf = args.args[0].clone();
+    let os_args = &args.args[1..];
+    let (errno, strerror, filename) = if os_error_errno(os_args, vm).is_some() && os_args.len() <= 5
+    {
+        let filename = os_args.get(2).cloned().unwrap_or_else(|| vm.get_none());
+        let mut msg = format!(
+            "[Errno {}] {}",
+            vm.to_pystr(&os_args[0])?,
+            vm.to_pystr(&os_args[1])?
+        );
+        if !filename.is(&vm.get_none()) {
+            msg.push_str(&format!(": {}", vm.to_repr(&filename)?.value));
+        }
+        vm.ctx.set_attr(&zelf, "msg", vm.new_str(msg));
+        (os_args[0].clone(), os_args[1].clone(), filename)
+    } else {
+        (vm.get_none(), vm.get_none(), vm.get_none())
+    };
+    vm.ctx.set_attr(&zelf, "errno", errno);
+    vm.ctx.set_attr(&zelf, "strerror", strerror);
+    vm.ctx.set_attr(&zelf, "filename", filename);
+    Ok(vm.get_none())
+}
+
+/// Make `context` the context of `exc`, as `_PyErr_SetObject` does in
+/// CPython. If `exc` is already in the context chain of `context`, the
+/// chain is cut there so that it doesn't become a cycle.
+pub fn set_context(vm: &VirtualMachine, exc: &PyObjectRef, context: PyObjectRef) -> PyResult<()> {
+    if context.is(exc) {
+        return Ok(());
+    }
+    let mut seen = HashSet::new();
+    let mut current = context.clone();
+    loop {
+        let next = vm.get_attribute(current.clone(), "__context__")?;
+        if next.is(&vm.get_none()) || !seen.insert(next.get_id()) {
+            break;
+        }
+        if next.is(exc) {
+            vm.set_attr(
+                &current,
+                vm.new_str("__context__".to_string()),
+                vm.get_none(),
+            )?;
+            break;
+        }
+        current = next;
+    }
+    vm.set_attr(exc, vm.new_str("__context__".to_string()), context)?;
+    Ok(())
+}
+
+fn exception_with_traceback(zelf: PyObjectRef, tb: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    if !tb.is(&vm.get_none()) && tb.payload::<PyTraceback>().is_none() {
+        return Err(vm.new_type_error("__traceback__ must be a traceback or None".to_string()));
+    }
+    vm.ctx.set_attr(&zelf, "__traceback__", tb);
+    Ok(zelf)
+}
+
 // Print exception including traceback:
 pub fn print_exception(vm: &VirtualMachine, exc: &PyObjectRef) {
-    if let Ok(tb) = vm.get_attribute(exc.clone(), "__traceback__") {
-        println!("Traceback (most recent call last):");
-        if objtype::isinstance(&tb, &vm.ctx.list_type()) {
-            let mut elements = objsequence::get_elements(&tb).to_vec();
-            elements.reverse();
-            for element in elements.iter() {
-                if objtype::isinstance(&element, &vm.ctx.tuple_type()) {
-                    let element = objsequence::get_elements(&element);
-                    let filename = if let Ok(x) = vm.to_str(&element[0]) {
-                        x.value.clone()
-                    } else {
-                        "<error>".to_string()
-                    };
-
-                    let lineno = if let Ok(x) = vm.to_str(&element[1]) {
-                        x.value.clone()
-                    } else {
-                        "<error>".to_string()
-                    };
-
-                    let obj_name = if let Ok(x) = vm.to_str(&element[2]) {
-                        x.value.clone()
-                    } else {
-                        "<error>".to_string()
-                    };
-
-                    println!("  File {}, line {}, in {}", filename, lineno, obj_name);
-                } else {
-                    println!("  File ??");
-                }
-            }
-        }
+    for line in format_exception(vm, exc) {
+        print!("{}", line);
+    }
+}
+
+/// Format an exception, its traceback and the exceptions chained to it as
+/// a list of newline terminated strings.
+pub fn format_exception(vm: &VirtualMachine, exc: &PyObjectRef) -> Vec<String> {
+    format_exception_with_traceback(vm, exc, exception_traceback(vm, exc), None, true)
+}
+
+/// Like `format_exception`, but with an explicit traceback for `exc`. Each
+/// traceback is cut down to `limit` entries, as `traceback::entries` does,
+/// and chained exceptions are left out unless `chain` is set.
+pub fn format_exception_with_traceback(
+    vm: &VirtualMachine,
+    exc: &PyObjectRef,
+    tb: Option<PyTracebackRef>,
+    limit: Option<isize>,
+    chain: bool,
+) -> Vec<String> {
+    let mut lines = vec![];
+    if chain {
+        let mut seen = HashSet::new();
+        format_exception_chain(vm, exc, tb, limit, &mut lines, &mut seen);
     } else {
-        println!("No traceback set on exception");
+        format_traceback(vm, exc, tb, limit, &mut lines);
     }
+    lines
+}
+
+/// The `__traceback__` of an exception, if it has one.
+pub fn exception_traceback(vm: &VirtualMachine, exc: &PyObjectRef) -> Option<PyTracebackRef> {
+    vm.get_attribute(exc.clone(), "__traceback__")
+        .ok()
+        .and_then(|tb| tb.downcast().ok())
+}
+
+/// Format the cause or context of an exception before the exception itself,
+/// guarding against cycles in the chain.
+fn format_exception_chain(
+    vm: &VirtualMachine,
+    exc: &PyObjectRef,
+    tb: Option<PyTracebackRef>,
+    limit: Option<isize>,
+    lines: &mut Vec<String>,
+    seen: &mut HashSet<usize>,
+) {
+    seen.insert(exc.get_id());
+
+    let none = vm.get_none();
+    let chained_attr = |name: &str| {
+        vm.get_attribute(exc.clone(), name)
+            .ok()
+            .filter(|obj| !obj.is(&none))
+    };
+    if let Some(cause) = chained_attr("__cause__") {
+        if !seen.contains(&cause.get_id()) {
+            let cause_tb = exception_traceback(vm, &cause);
+            format_exception_chain(vm, &cause, cause_tb, limit, lines, seen);
+            lines.push(
+                "\nThe above exception was the direct cause of the following exception:\n\n"
+                    .to_string(),
+            );
+        }
+    } else if let Some(context) = chained_attr("__context__") {
+        let suppress_context = vm
+            .get_attribute(exc.clone(), "__suppress_context__")
+            .and_then(|obj| objbool::boolval(vm, obj))
+            .unwrap_or(false);
+        if !suppress_context && !seen.contains(&context.get_id()) {
+            let context_tb = exception_traceback(vm, &context);
+            format_exception_chain(vm, &context, context_tb, limit, lines, seen);
+            lines.push(
+                "\nDuring handling of the above exception, another exception occurred:\n\n"
+                    .to_string(),
+            );
+        }
+    }
+
+    format_traceback(vm, exc, tb, limit, lines);
+}
 
+/// Format a single exception with its traceback.
+fn format_traceback(
+    vm: &VirtualMachine,
+    exc: &PyObjectRef,
+    tb: Option<PyTracebackRef>,
+    limit: Option<isize>,
+    lines: &mut Vec<String>,
+) {
+    if let Some(tb) = tb {
+        lines.push("Traceback (most recent call last):\n".to_string());
+        lines.extend(traceback::format_tb(&tb, limit));
+    }
+    lines.push(format_exception_only(vm, exc));
+}
+
+/// Format the last line of a printed exception. Syntax errors are preceded
+/// by the location of the error and a caret under the offending column.
+pub fn format_exception_only(vm: &VirtualMachine, exc: &PyObjectRef) -> String {
+    let mut text = String::new();
+    if objtype::isinstance(exc, &vm.ctx.exceptions.syntax_error) {
+        text.push_str(&format_syntax_error_location(vm, exc));
+    }
     match vm.to_str(exc) {
-        Ok(txt) => println!("{}", txt.value),
-        Err(err) => println!("Error during error {:?}", err),
+        Ok(txt) => text.push_str(&format!("{}\n", txt.value)),
+        Err(err) => text.push_str(&format!("Error during error {:?}\n", err)),
+    }
+    text
+}
+
+fn format_syntax_error_location(vm: &VirtualMachine, exc: &PyObjectRef) -> String {
+    let attr = |name: &str, typ: &PyClassRef| {
+        vm.get_attribute(exc.clone(), name)
+            .ok()
+            .filter(|obj| objtype::isinstance(obj, typ))
+    };
+    let int_type = vm.ctx.int_type();
+    let str_type = vm.ctx.str_type();
+    let lineno = match attr("lineno", &int_type) {
+        Some(lineno) => objint::get_value(&lineno).to_string(),
+        None => return String::new(),
+    };
+    let filename = attr("filename", &str_type)
+        .map(|filename| objstr::get_value(&filename))
+        .unwrap_or_else(|| "<string>".to_string());
+    let mut location = format!("  File \"{}\", line {}\n", filename, lineno);
+
+    if let Some(text) = attr("text", &str_type) {
+        let text = objstr::get_value(&text);
+        let stripped = text.trim_start();
+        let indent = text.len() - stripped.len();
+        location.push_str(&format!("    {}\n", stripped.trim_end()));
+        if let Some(offset) =
+            attr("offset", &int_type).and_then(|offset| objint::get_value(&offset).to_usize())
+        {
+            let column = offset.saturating_sub(1).saturating_sub(indent);
+            location.push_str(&format!("    {}^\n", " ".repeat(column)));
+        }
     }
+    location
 }
 
 fn exception_str(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(
         vm,
         args,
-        required = [(exc, Some(vm.ctx.exceptions.exception_type.clone()))]
+        required = [(exc, Some(vm.ctx.exceptions.base_exception_type.clone()))]
     );
+    // An exception created without arguments shows only its type:
+    if let Ok(exc_args) = vm.get_attribute(exc.clone(), "args") {
+        if objtype::isinstance(&exc_args, &vm.ctx.tuple_type())
+            && objsequence::get_elements(&exc_args).is_empty()
+        {
+            return Ok(vm.new_str(exc.class().name.clone()));
+        }
+    }
     let msg = if let Ok(m) = vm.get_attribute(exc.clone(), "msg") {
         match vm.to_pystr(&m) {
             Ok(msg) => msg,
@@ -86,22 +363,65 @@ pub struct ExceptionZoo {
     pub assertion_error: PyClassRef,
     pub attribute_error: PyClassRef,
     pub base_exception_type: PyClassRef,
+    pub blocking_io_error: PyClassRef,
+    pub broken_pipe_error: PyClassRef,
+    pub buffer_error: PyClassRef,
+    pub bytes_warning: PyClassRef,
+    pub child_process_error: PyClassRef,
+    pub connection_aborted_error: PyClassRef,
+    pub connection_error: PyClassRef,
+    pub connection_refused_error: PyClassRef,
+    pub connection_reset_error: PyClassRef,
+    pub deprecation_warning: PyClassRef,
+    pub eof_error: PyClassRef,
     pub exception_type: PyClassRef,
+    pub file_exists_error: PyClassRef,
     pub file_not_found_error: PyClassRef,
+    pub floating_point_error: PyClassRef,
+    pub future_warning: PyClassRef,
+    pub generator_exit: PyClassRef,
     pub import_error: PyClassRef,
+    pub import_warning: PyClassRef,
+    pub indentation_error: PyClassRef,
     pub index_error: PyClassRef,
+    pub interrupted_error: PyClassRef,
+    pub is_a_directory_error: PyClassRef,
     pub key_error: PyClassRef,
+    pub keyboard_interrupt: PyClassRef,
+    pub lookup_error: PyClassRef,
+    pub memory_error: PyClassRef,
     pub module_not_found_error: PyClassRef,
     pub name_error: PyClassRef,
+    pub not_a_directory_error: PyClassRef,
     pub not_implemented_error: PyClassRef,
     pub os_error: PyClassRef,
     pub overflow_error: PyClassRef,
+    pub pending_deprecation_warning: PyClassRef,
     pub permission_error: PyClassRef,
+    pub process_lookup_error: PyClassRef,
+    pub recursion_error: PyClassRef,
+    pub reference_error: PyClassRef,
+    pub resource_warning: PyClassRef,
     pub runtime_error: PyClassRef,
+    pub runtime_warning: PyClassRef,
+    pub stop_async_iteration: PyClassRef,
     pub stop_iteration: PyClassRef,
     pub syntax_error: PyClassRef,
+    pub syntax_warning: PyClassRef,
+    pub system_error: PyClassRef,
+    pub system_exit: PyClassRef,
+    pub tab_error: PyClassRef,
+    pub timeout_error: PyClassRef,
     pub type_error: PyClassRef,
+    pub unbound_local_error: PyClassRef,
+    pub unicode_decode_error: PyClassRef,
+    pub unicode_encode_error: PyClassRef,
+    pub unicode_error: PyClassRef,
+    pub unicode_translate_error: PyClassRef,
+    pub unicode_warning: PyClassRef,
+    pub user_warning: PyClassRef,
     pub value_error: PyClassRef,
+    pub warning: PyClassRef,
     pub zero_division_error: PyClassRef,
 }
 
@@ -110,47 +430,138 @@ impl ExceptionZoo {
         // Sorted By Hierarchy then alphabetized.
         let base_exception_type = create_type("BaseException", &type_type, &object_type);
         let exception_type = create_type("Exception", &type_type, &base_exception_type);
+        let generator_exit = create_type("GeneratorExit", &type_type, &base_exception_type);
+        let keyboard_interrupt = create_type("KeyboardInterrupt", &type_type, &base_exception_type);
+        let system_exit = create_type("SystemExit", &type_type, &base_exception_type);
         let arithmetic_error = create_type("ArithmeticError", &type_type, &exception_type);
         let assertion_error = create_type("AssertionError", &type_type, &exception_type);
         let attribute_error = create_type("AttributeError", &type_type, &exception_type);
+        let buffer_error = create_type("BufferError", &type_type, &exception_type);
+        let eof_error = create_type("EOFError", &type_type, &exception_type);
         let import_error = create_type("ImportError", &type_type, &exception_type);
-        let index_error = create_type("IndexError", &type_type, &exception_type);
-        let key_error = create_type("KeyError", &type_type, &exception_type);
+        let lookup_error = create_type("LookupError", &type_type, &exception_type);
+        let memory_error = create_type("MemoryError", &type_type, &exception_type);
         let name_error = create_type("NameError", &type_type, &exception_type);
         let os_error = create_type("OSError", &type_type, &exception_type);
+        let reference_error = create_type("ReferenceError", &type_type, &exception_type);
         let runtime_error = create_type("RuntimeError", &type_type, &exception_type);
+        let stop_async_iteration = create_type("StopAsyncIteration", &type_type, &exception_type);
         let stop_iteration = create_type("StopIteration", &type_type, &exception_type);
         let syntax_error = create_type("SyntaxError", &type_type, &exception_type);
+        let system_error = create_type("SystemError", &type_type, &exception_type);
         let type_error = create_type("TypeError", &type_type, &exception_type);
         let value_error = create_type("ValueError", &type_type, &exception_type);
+        let warning = create_type("Warning", &type_type, &exception_type);
+        let floating_point_error = create_type("FloatingPointError", &type_type, &arithmetic_error);
         let overflow_error = create_type("OverflowError", &type_type, &arithmetic_error);
         let zero_division_error = create_type("ZeroDivisionError", &type_type, &arithmetic_error);
         let module_not_found_error = create_type("ModuleNotFoundError", &type_type, &import_error);
-        let not_implemented_error = create_type("NotImplementedError", &type_type, &runtime_error);
+        let index_error = create_type("IndexError", &type_type, &lookup_error);
+        let key_error = create_type("KeyError", &type_type, &lookup_error);
+        let unbound_local_error = create_type("UnboundLocalError", &type_type, &name_error);
+        let blocking_io_error = create_type("BlockingIOError", &type_type, &os_error);
+        let child_process_error = create_type("ChildProcessError", &type_type, &os_error);
+        let connection_error = create_type("ConnectionError", &type_type, &os_error);
+        let file_exists_error = create_type("FileExistsError", &type_type, &os_error);
         let file_not_found_error = create_type("FileNotFoundError", &type_type, &os_error);
+        let interrupted_error = create_type("InterruptedError", &type_type, &os_error);
+        let is_a_directory_error = create_type("IsADirectoryError", &type_type, &os_error);
+        let not_a_directory_error = create_type("NotADirectoryError", &type_type, &os_error);
         let permission_error = create_type("PermissionError", &type_type, &os_error);
+        let process_lookup_error = create_type("ProcessLookupError", &type_type, &os_error);
+        let timeout_error = create_type("TimeoutError", &type_type, &os_error);
+        let broken_pipe_error = create_type("BrokenPipeError", &type_type, &connection_error);
+        let connection_aborted_error =
+            create_type("ConnectionAbortedError", &type_type, &connection_error);
+        let connection_refused_error =
+            create_type("ConnectionRefusedError", &type_type, &connection_error);
+        let connection_reset_error =
+            create_type("ConnectionResetError", &type_type, &connection_error);
+        let not_implemented_error = create_type("NotImplementedError", &type_type, &runtime_error);
+        let recursion_error = create_type("RecursionError", &type_type, &runtime_error);
+        let indentation_error = create_type("IndentationError", &type_type, &syntax_error);
+        let tab_error = create_type("TabError", &type_type, &indentation_error);
+        let unicode_error = create_type("UnicodeError", &type_type, &value_error);
+        let unicode_decode_error = create_type("UnicodeDecodeError", &type_type, &unicode_error);
+        let unicode_encode_error = create_type("UnicodeEncodeError", &type_type, &unicode_error);
+        let unicode_translate_error =
+            create_type("UnicodeTranslateError", &type_type, &unicode_error);
+        let bytes_warning = create_type("BytesWarning", &type_type, &warning);
+        let deprecation_warning = create_type("DeprecationWarning", &type_type, &warning);
+        let future_warning = create_type("FutureWarning", &type_type, &warning);
+        let import_warning = create_type("ImportWarning", &type_type, &warning);
+        let pending_deprecation_warning =
+            create_type("PendingDeprecationWarning", &type_type, &warning);
+        let resource_warning = create_type("ResourceWarning", &type_type, &warning);
+        let runtime_warning = create_type("RuntimeWarning", &type_type, &warning);
+        let syntax_warning = create_type("SyntaxWarning", &type_type, &warning);
+        let unicode_warning = create_type("UnicodeWarning", &type_type, &warning);
+        let user_warning = create_type("UserWarning", &type_type, &warning);
 
         ExceptionZoo {
             arithmetic_error,
             assertion_error,
             attribute_error,
             base_exception_type,
+            blocking_io_error,
+            broken_pipe_error,
+            buffer_error,
+            bytes_warning,
+            child_process_error,
+            connection_aborted_error,
+            connection_error,
+            connection_refused_error,
+            connection_reset_error,
+            deprecation_warning,
+            eof_error,
             exception_type,
+            file_exists_error,
             file_not_found_error,
+            floating_point_error,
+            future_warning,
+            generator_exit,
             import_error,
+            import_warning,
+            indentation_error,
             index_error,
+            interrupted_error,
+            is_a_directory_error,
             key_error,
+            keyboard_interrupt,
+            lookup_error,
+            memory_error,
             module_not_found_error,
             name_error,
+            not_a_directory_error,
             not_implemented_error,
             os_error,
             overflow_error,
+            pending_deprecation_warning,
             permission_error,
+            process_lookup_error,
+            recursion_error,
+            reference_error,
+            resource_warning,
             runtime_error,
+            runtime_warning,
+            stop_async_iteration,
             stop_iteration,
             syntax_error,
+            syntax_warning,
+            system_error,
+            system_exit,
+            tab_error,
+            timeout_error,
             type_error,
+            unbound_local_error,
+            unicode_decode_error,
+            unicode_encode_error,
+            unicode_error,
+            unicode_translate_error,
+            unicode_warning,
+            user_warning,
             value_error,
+            warning,
             zero_division_error,
         }
     }
@@ -159,11 +570,38 @@ impl ExceptionZoo {
 pub fn init(context: &PyContext) {
     let base_exception_type = &context.exceptions.base_exception_type;
     extend_class!(context, base_exception_type, {
-        "__init__" => context.new_rustfunc(exception_init)
+        "__init__" => context.new_rustfunc(exception_init),
+        "__str__" => context.new_rustfunc(exception_str),
+        "__cause__" => context.none(),
+        "__context__" => context.none(),
+        "__suppress_context__" => context.new_bool(false),
+        "__traceback__" => context.none(),
+        "with_traceback" => context.new_rustfunc(exception_with_traceback)
+    });
+
+    let syntax_error_type = &context.exceptions.syntax_error;
+    extend_class!(context, syntax_error_type, {
+        "__init__" => context.new_rustfunc(syntax_error_init),
+        "filename" => context.none(),
+        "lineno" => context.none(),
+        "offset" => context.none(),
+        "text" => context.none()
+    });
+
+    let system_exit_type = &context.exceptions.system_exit;
+    extend_class!(context, system_exit_type, {
+        "__init__" => context.new_rustfunc(system_exit_init)
+    });
+
+    let stop_iteration_type = &context.exceptions.stop_iteration;
+    extend_class!(context, stop_iteration_type, {
+        "__init__" => context.new_rustfunc(stop_iteration_init),
+        "value" => context.none()
     });
 
-    let exception_type = &context.exceptions.exception_type;
-    extend_class!(context, exception_type, {
-        "__str__" => context.new_rustfunc(exception_str)
+    let os_error_type = &context.exceptions.os_error;
+    extend_class!(context, os_error_type, {
+        "__new__" => context.new_rustfunc(os_error_new),
+        "__init__" => context.new_rustfunc(os_error_init)
     });
 }
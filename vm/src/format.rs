@@ -1,8 +1,11 @@
 use num_bigint::{BigInt, Sign};
-use num_traits::Signed;
+use num_traits::{Signed, ToPrimitive};
 use std::cmp;
 use std::str::FromStr;
 
+use crate::cformat::{self, CFloatType, CFormatCase};
+use crate::obj::objfloat;
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FormatAlign {
     Left,
@@ -52,6 +55,7 @@ pub enum FormatType {
     GeneralFormatUpper,
     FixedPointLower,
     FixedPointUpper,
+    Percentage,
 }
 
 #[derive(Debug, PartialEq)]
@@ -133,11 +137,11 @@ fn parse_alternate_form(text: &str) -> (bool, &str) {
     }
 }
 
-fn parse_zero(text: &str) -> &str {
+fn parse_zero(text: &str) -> (bool, &str) {
     let mut chars = text.chars();
     match chars.next() {
-        Some('0') => chars.as_str(),
-        _ => text,
+        Some('0') => (true, chars.as_str()),
+        _ => (false, text),
     }
 }
 
@@ -181,6 +185,7 @@ fn parse_format_type(text: &str) -> (Option<FormatType>, &str) {
         Some('g') => (Some(FormatType::GeneralFormatLower), chars.as_str()),
         Some('G') => (Some(FormatType::GeneralFormatUpper), chars.as_str()),
         Some('n') => (Some(FormatType::Number), chars.as_str()),
+        Some('%') => (Some(FormatType::Percentage), chars.as_str()),
         _ => (None, text),
     }
 }
@@ -189,7 +194,14 @@ fn parse_format_spec(text: &str) -> FormatSpec {
     let (fill, align, after_align) = parse_fill_and_align(text);
     let (sign, after_sign) = parse_sign(after_align);
     let (alternate_form, after_alternate_form) = parse_alternate_form(after_sign);
-    let after_zero = parse_zero(after_alternate_form);
+    let (zero, after_zero) = parse_zero(after_alternate_form);
+    // A leading zero pads with zeros between the sign and the digits,
+    // unless a fill or an alignment is given explicitly.
+    let (fill, align) = if zero && fill.is_none() {
+        (Some('0'), align.or(Some(FormatAlign::AfterSign)))
+    } else {
+        (fill, align)
+    };
     let (width, after_width) = parse_number(after_zero);
     let (grouping_option, after_grouping_option) = parse_grouping_option(after_width);
     let (precision, after_precision) = parse_precision(after_grouping_option);
@@ -228,12 +240,13 @@ impl FormatSpec {
         if remaining == 0 {
             remaining = interval;
         }
-        for c in magnitude_string.chars() {
-            result.push(c);
-            if remaining == 0 {
+        for (i, c) in magnitude_string.chars().enumerate() {
+            if i > 0 && remaining == 0 {
                 result.push(separator);
                 remaining = interval;
             }
+            result.push(c);
+            remaining -= 1;
         }
         result
     }
@@ -268,14 +281,13 @@ impl FormatSpec {
     }
 
     pub fn format_int(&self, num: &BigInt) -> Result<String, &'static str> {
-        let fill_char = self.fill.unwrap_or(' ');
         let magnitude = num.abs();
         let prefix = if self.alternate_form {
             match self.format_type {
                 Some(FormatType::Binary) => "0b",
                 Some(FormatType::Octal) => "0o",
                 Some(FormatType::HexLower) => "0x",
-                Some(FormatType::HexUpper) => "0x",
+                Some(FormatType::HexUpper) => "0X",
                 _ => "",
             }
         } else {
@@ -294,23 +306,18 @@ impl FormatSpec {
             Some(FormatType::Number) => Ok(magnitude.to_str_radix(10)),
             Some(FormatType::String) => Err("Unknown format code 's' for object of type 'int'"),
             Some(FormatType::Character) => Err("Unknown format code 'c' for object of type 'int'"),
-            Some(FormatType::GeneralFormatUpper) => {
-                Err("Unknown format code 'G' for object of type 'int'")
-            }
-            Some(FormatType::GeneralFormatLower) => {
-                Err("Unknown format code 'g' for object of type 'int'")
-            }
-            Some(FormatType::ExponentUpper) => {
-                Err("Unknown format code 'E' for object of type 'int'")
-            }
-            Some(FormatType::ExponentLower) => {
-                Err("Unknown format code 'e' for object of type 'int'")
-            }
-            Some(FormatType::FixedPointUpper) => {
-                Err("Unknown format code 'F' for object of type 'int'")
-            }
-            Some(FormatType::FixedPointLower) => {
-                Err("Unknown format code 'f' for object of type 'int'")
+            // The float presentation types format the int as a float
+            Some(FormatType::GeneralFormatUpper)
+            | Some(FormatType::GeneralFormatLower)
+            | Some(FormatType::ExponentUpper)
+            | Some(FormatType::ExponentLower)
+            | Some(FormatType::FixedPointUpper)
+            | Some(FormatType::FixedPointLower)
+            | Some(FormatType::Percentage) => {
+                return match num.to_f64() {
+                    Some(float) => self.format_float(float),
+                    None => Err("int too large to convert to float"),
+                };
             }
             None => Ok(magnitude.to_str_radix(10)),
         };
@@ -322,25 +329,137 @@ impl FormatSpec {
             prefix,
             self.add_magnitude_separators(raw_magnitude_string_result.unwrap())
         );
-        let align = self.align.unwrap_or(FormatAlign::Right);
+        let sign_str = self.sign_string(num.sign() == Sign::Minus);
+        Ok(self.format_sign_and_align(&magnitude_string, sign_str))
+    }
 
-        // Use the byte length as the string length since we're in ascii
-        let num_chars = magnitude_string.len();
+    pub fn format_float(&self, num: f64) -> Result<String, &'static str> {
+        let precision = self.precision.unwrap_or(6);
+        let magnitude = num.abs();
+        let raw_magnitude_string_result: Result<String, &'static str> = match self.format_type {
+            Some(FormatType::FixedPointUpper) => Ok(cformat::format_float(
+                &CFloatType::PointDecimal(CFormatCase::Uppercase),
+                magnitude,
+                precision,
+                self.alternate_form,
+            )),
+            Some(FormatType::FixedPointLower) => Ok(cformat::format_float(
+                &CFloatType::PointDecimal(CFormatCase::Lowercase),
+                magnitude,
+                precision,
+                self.alternate_form,
+            )),
+            Some(FormatType::ExponentUpper) => Ok(cformat::format_float(
+                &CFloatType::Exponent(CFormatCase::Uppercase),
+                magnitude,
+                precision,
+                self.alternate_form,
+            )),
+            Some(FormatType::ExponentLower) => Ok(cformat::format_float(
+                &CFloatType::Exponent(CFormatCase::Lowercase),
+                magnitude,
+                precision,
+                self.alternate_form,
+            )),
+            Some(FormatType::GeneralFormatUpper) => Ok(cformat::format_float(
+                &CFloatType::General(CFormatCase::Uppercase),
+                magnitude,
+                precision,
+                self.alternate_form,
+            )),
+            Some(FormatType::GeneralFormatLower) | Some(FormatType::Number) => {
+                Ok(cformat::format_float(
+                    &CFloatType::General(CFormatCase::Lowercase),
+                    magnitude,
+                    precision,
+                    self.alternate_form,
+                ))
+            }
+            Some(FormatType::Percentage) => {
+                let digits = cformat::format_float(
+                    &CFloatType::PointDecimal(CFormatCase::Lowercase),
+                    magnitude * 100.0,
+                    precision,
+                    self.alternate_form,
+                );
+                Ok(format!("{}%", digits))
+            }
+            None => match self.precision {
+                // Like 'g', but with at least one digit after the point
+                Some(precision) => {
+                    let digits = cformat::format_float(
+                        &CFloatType::General(CFormatCase::Lowercase),
+                        magnitude,
+                        precision,
+                        self.alternate_form,
+                    );
+                    if magnitude.is_finite() && !digits.contains(|c| c == '.' || c == 'e') {
+                        Ok(format!("{}.0", digits))
+                    } else {
+                        Ok(digits)
+                    }
+                }
+                None => Ok(objfloat::float_repr(magnitude)),
+            },
+            Some(FormatType::String) => Err("Unknown format code 's' for object of type 'float'"),
+            Some(FormatType::Binary) => Err("Unknown format code 'b' for object of type 'float'"),
+            Some(FormatType::Character) => {
+                Err("Unknown format code 'c' for object of type 'float'")
+            }
+            Some(FormatType::Decimal) => Err("Unknown format code 'd' for object of type 'float'"),
+            Some(FormatType::Octal) => Err("Unknown format code 'o' for object of type 'float'"),
+            Some(FormatType::HexLower) => Err("Unknown format code 'x' for object of type 'float'"),
+            Some(FormatType::HexUpper) => Err("Unknown format code 'X' for object of type 'float'"),
+        };
+        let raw_magnitude_string = raw_magnitude_string_result?;
+        // Only the digits before the point are grouped
+        let magnitude_string = match raw_magnitude_string.find(|c: char| !c.is_ascii_digit()) {
+            Some(end) if magnitude.is_finite() => {
+                let (integer, rest) = raw_magnitude_string.split_at(end);
+                format!("{}{}", self.add_float_separators(integer.to_string()), rest)
+            }
+            _ if magnitude.is_finite() => self.add_float_separators(raw_magnitude_string),
+            _ => raw_magnitude_string,
+        };
+        let sign_str = self.sign_string(num.is_sign_negative() && !num.is_nan());
+        Ok(self.format_sign_and_align(&magnitude_string, sign_str))
+    }
+
+    fn add_float_separators(&self, magnitude_string: String) -> String {
+        match self.grouping_option {
+            Some(FormatGrouping::Comma) => {
+                FormatSpec::add_magnitude_separators_for_char(magnitude_string, 3, ',')
+            }
+            Some(FormatGrouping::Underscore) => {
+                FormatSpec::add_magnitude_separators_for_char(magnitude_string, 3, '_')
+            }
+            None => magnitude_string,
+        }
+    }
 
-        let format_sign = self.sign.unwrap_or(FormatSign::Minus);
-        let sign_str = match num.sign() {
-            Sign::Minus => "-",
-            _ => match format_sign {
+    fn sign_string(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else {
+            match self.sign.unwrap_or(FormatSign::Minus) {
                 FormatSign::Plus => "+",
                 FormatSign::Minus => "",
                 FormatSign::MinusOrSpace => " ",
-            },
-        };
+            }
+        }
+    }
+
+    fn format_sign_and_align(&self, magnitude_string: &str, sign_str: &str) -> String {
+        let fill_char = self.fill.unwrap_or(' ');
+        let align = self.align.unwrap_or(FormatAlign::Right);
+
+        // Use the byte length as the string length since we're in ascii
+        let num_chars = magnitude_string.len();
 
         let fill_chars_needed: i32 = self.width.map_or(0, |w| {
             cmp::max(0, (w as i32) - (num_chars as i32) - (sign_str.len() as i32))
         });
-        Ok(match align {
+        match align {
             FormatAlign::Left => format!(
                 "{}{}{}",
                 sign_str,
@@ -371,6 +490,53 @@ impl FormatSpec {
                     left_fill_string, sign_str, magnitude_string, right_fill_string
                 )
             }
+        }
+    }
+
+    pub fn format_string(&self, s: &str) -> Result<String, &'static str> {
+        match self.format_type {
+            Some(FormatType::String) | None => {}
+            _ => return Err("Unknown format code for object of type 'str'"),
+        }
+        if self.sign.is_some() {
+            return Err("Sign not allowed in string format specifier");
+        }
+        if self.alternate_form {
+            return Err("Alternate form (#) not allowed in string format specifier");
+        }
+        let value: String = match self.precision {
+            Some(precision) => s.chars().take(precision).collect(),
+            None => s.to_string(),
+        };
+        let fill_char = self.fill.unwrap_or(' ');
+        let num_chars = value.chars().count();
+        let fill_chars_needed: i32 = self
+            .width
+            .map_or(0, |w| cmp::max(0, (w as i32) - (num_chars as i32)));
+        Ok(match self.align.unwrap_or(FormatAlign::Left) {
+            FormatAlign::Left => format!(
+                "{}{}",
+                value,
+                FormatSpec::compute_fill_string(fill_char, fill_chars_needed)
+            ),
+            FormatAlign::Right => format!(
+                "{}{}",
+                FormatSpec::compute_fill_string(fill_char, fill_chars_needed),
+                value
+            ),
+            FormatAlign::AfterSign => {
+                return Err("'=' alignment not allowed in string format specifier");
+            }
+            FormatAlign::Center => {
+                let left_fill_chars_needed = fill_chars_needed / 2;
+                let right_fill_chars_needed = fill_chars_needed - left_fill_chars_needed;
+                format!(
+                    "{}{}{}",
+                    FormatSpec::compute_fill_string(fill_char, left_fill_chars_needed),
+                    value,
+                    FormatSpec::compute_fill_string(fill_char, right_fill_chars_needed)
+                )
+            }
         })
     }
 }
@@ -623,6 +789,42 @@ mod tests {
             parse_format_spec("0>+#10x").format_int(&BigInt::from_bytes_be(Sign::Plus, b"\x10")),
             Ok("00000+0x10".to_string())
         );
+        assert_eq!(
+            parse_format_spec(",").format_int(&BigInt::from(1_234_567)),
+            Ok("1,234,567".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_float() {
+        assert_eq!(
+            parse_format_spec(".2f").format_float(3.14159),
+            Ok("3.14".to_string())
+        );
+        assert_eq!(
+            parse_format_spec("+010.3e").format_float(-1234.5),
+            Ok("-1.234e+03".to_string())
+        );
+        assert_eq!(
+            parse_format_spec(",").format_float(1234567.891),
+            Ok("1,234,567.891".to_string())
+        );
+        assert_eq!(
+            parse_format_spec(".1%").format_float(0.125),
+            Ok("12.5%".to_string())
+        );
+        assert_eq!(
+            parse_format_spec(".3").format_float(2.0),
+            Ok("2.0".to_string())
+        );
+        assert_eq!(
+            parse_format_spec("").format_float(1e16),
+            Ok("1e+16".to_string())
+        );
+        assert_eq!(
+            parse_format_spec("d").format_float(1.0),
+            Err("Unknown format code 'd' for object of type 'float'")
+        );
     }
 
     #[test]
@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 use std::fmt;
-use std::rc::Rc;
+use std::mem;
 
 use num_bigint::BigInt;
 
@@ -8,10 +8,14 @@ use rustpython_parser::ast;
 
 use crate::builtins;
 use crate::bytecode;
+use crate::exceptions;
 use crate::function::PyFuncArgs;
+use crate::gc;
 use crate::obj::objbool;
 use crate::obj::objbuiltinfunc::PyBuiltinFunction;
+use crate::obj::objcell::{PyCell, PyCellRef};
 use crate::obj::objcode::PyCodeRef;
+use crate::obj::objcoroutine;
 use crate::obj::objdict::{self, PyDictRef};
 use crate::obj::objint::PyInt;
 use crate::obj::objiter;
@@ -24,62 +28,18 @@ use crate::pyobject::{
     DictProtocol, IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject,
     TypeProtocol,
 };
+use crate::traceback::PyTraceback;
 use crate::vm::VirtualMachine;
 
 /*
- * So a scope is a linked list of scopes.
- * When a name is looked up, it is check in its scope.
+ * A scope holds the namespaces a frame looks up names in, that is the
+ * locals of the frame itself and the globals of its module. Variables of
+ * enclosing functions are not part of the scope, they are reached through
+ * the cells of the frame instead.
  */
-#[derive(Debug)]
-struct RcListNode<T> {
-    elem: T,
-    next: Option<Rc<RcListNode<T>>>,
-}
-
-#[derive(Debug, Clone)]
-struct RcList<T> {
-    head: Option<Rc<RcListNode<T>>>,
-}
-
-struct Iter<'a, T: 'a> {
-    next: Option<&'a RcListNode<T>>,
-}
-
-impl<T> RcList<T> {
-    pub fn new() -> Self {
-        RcList { head: None }
-    }
-
-    pub fn insert(self, elem: T) -> Self {
-        RcList {
-            head: Some(Rc::new(RcListNode {
-                elem,
-                next: self.head,
-            })),
-        }
-    }
-
-    pub fn iter(&self) -> Iter<T> {
-        Iter {
-            next: self.head.as_ref().map(|node| &**node),
-        }
-    }
-}
-
-impl<'a, T> Iterator for Iter<'a, T> {
-    type Item = &'a T;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.next.map(|node| {
-            self.next = node.next.as_ref().map(|node| &**node);
-            &node.elem
-        })
-    }
-}
-
 #[derive(Clone)]
 pub struct Scope {
-    locals: RcList<PyDictRef>,
+    locals: Option<PyDictRef>,
     pub globals: PyDictRef,
 }
 
@@ -92,27 +52,23 @@ impl fmt::Debug for Scope {
 
 impl Scope {
     pub fn new(locals: Option<PyDictRef>, globals: PyDictRef) -> Scope {
-        let locals = match locals {
-            Some(dict) => RcList::new().insert(dict),
-            None => RcList::new(),
-        };
         Scope { locals, globals }
     }
 
     pub fn get_locals(&self) -> PyDictRef {
-        match self.locals.iter().next() {
-            Some(dict) => dict.clone(),
+        match self.locals {
+            Some(ref dict) => dict.clone(),
             None => self.globals.clone(),
         }
     }
 
     pub fn get_only_locals(&self) -> Option<PyDictRef> {
-        self.locals.iter().next().cloned()
+        self.locals.clone()
     }
 
     pub fn child_scope_with_locals(&self, locals: PyDictRef) -> Scope {
         Scope {
-            locals: self.locals.clone().insert(locals),
+            locals: Some(locals),
             globals: self.globals.clone(),
         }
     }
@@ -126,31 +82,18 @@ pub trait NameProtocol {
     fn load_name(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef>;
     fn store_name(&self, vm: &VirtualMachine, name: &str, value: PyObjectRef);
     fn delete_name(&self, vm: &VirtualMachine, name: &str);
-    fn load_cell(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef>;
+    fn load_global(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef>;
+    fn store_global(&self, vm: &VirtualMachine, name: &str, value: PyObjectRef);
+    fn delete_global(&self, vm: &VirtualMachine, name: &str);
 }
 
 impl NameProtocol for Scope {
     fn load_name(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef> {
-        for dict in self.locals.iter() {
-            if let Some(value) = dict.get_item(name) {
-                return Some(value);
-            }
-        }
-
-        if let Some(value) = self.globals.get_item(name) {
+        if let Some(value) = self.locals.as_ref().and_then(|dict| dict.get_item(name)) {
             return Some(value);
         }
 
-        vm.get_attribute(vm.builtins.clone(), name).ok()
-    }
-
-    fn load_cell(&self, _vm: &VirtualMachine, name: &str) -> Option<PyObjectRef> {
-        for dict in self.locals.iter().skip(1) {
-            if let Some(value) = dict.get_item(name) {
-                return Some(value);
-            }
-        }
-        None
+        self.load_global(vm, name)
     }
 
     fn store_name(&self, vm: &VirtualMachine, key: &str, value: PyObjectRef) {
@@ -160,6 +103,22 @@ impl NameProtocol for Scope {
     fn delete_name(&self, _vm: &VirtualMachine, key: &str) {
         self.get_locals().del_item(key)
     }
+
+    fn load_global(&self, vm: &VirtualMachine, name: &str) -> Option<PyObjectRef> {
+        if let Some(value) = self.globals.get_item(name) {
+            return Some(value);
+        }
+
+        vm.get_attribute(vm.builtins.clone(), name).ok()
+    }
+
+    fn store_global(&self, vm: &VirtualMachine, name: &str, value: PyObjectRef) {
+        self.globals.set_item(&vm.ctx, name, value)
+    }
+
+    fn delete_global(&self, _vm: &VirtualMachine, name: &str) {
+        self.globals.del_item(name)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -179,12 +138,36 @@ enum BlockType {
     TryExcept {
         handler: bytecode::Label,
     },
+    /// Active while running an except clause, so that exceptions raised in
+    /// it are chained to the exception being handled.
+    ExceptHandler,
+    Finally {
+        handler: bytecode::Label,
+    },
+    /// Active while running the body of a finally clause. Holds the reason
+    /// for entering it, which is resumed once the body has finished.
+    FinallyHandler {
+        reason: Option<UnwindReason>,
+    },
     With {
         end: bytecode::Label,
         context_manager: PyObjectRef,
     },
 }
 
+/// Why the block stack is being unwound.
+#[derive(Clone, Debug)]
+enum UnwindReason {
+    /// A return statement is leaving the frame with a value.
+    Returning { value: PyObjectRef },
+    /// An exception was raised.
+    Raising { exception: PyObjectRef },
+    /// A break statement is leaving the innermost loop.
+    Break,
+    /// A continue statement is starting the next iteration of the innermost loop.
+    Continue,
+}
+
 pub type FrameRef = PyRef<Frame>;
 
 pub struct Frame {
@@ -193,6 +176,8 @@ pub struct Frame {
     stack: RefCell<Vec<PyObjectRef>>, // The main data frame of the stack machine
     blocks: RefCell<Vec<Block>>,      // Block frames, for controlling loops and exceptions
     pub scope: Scope,                 // Variables
+    pub fastlocals: RefCell<Vec<Option<PyObjectRef>>>, // Function locals, indexed by varnames
+    pub cells: Vec<PyCellRef>,        // Cell variables, followed by free variables
     pub lasti: RefCell<usize>,        // index of last instruction ran
 }
 
@@ -212,7 +197,7 @@ pub enum ExecutionResult {
 pub type FrameResult = Result<Option<ExecutionResult>, PyObjectRef>;
 
 impl Frame {
-    pub fn new(code: PyCodeRef, scope: Scope) -> Frame {
+    pub fn new(code: PyCodeRef, scope: Scope, closure: &[PyCellRef], vm: &VirtualMachine) -> Frame {
         //populate the globals and locals
         //TODO: This is wrong, check https://github.com/nedbat/byterun/blob/31e6c4a8212c35b5157919abff43a7daa0f377c6/byterun/pyvm2.py#L95
         /*
@@ -224,6 +209,15 @@ impl Frame {
         // let locals = globals;
         // locals.extend(callargs);
 
+        let fastlocals = vec![None; code.code.varnames.len()];
+        let cells = code
+            .code
+            .cellvars
+            .iter()
+            .map(|_| PyCell::new(None).into_ref(vm))
+            .chain(closure.iter().cloned())
+            .collect();
+
         Frame {
             code: code.code.clone(),
             stack: RefCell::new(vec![]),
@@ -231,56 +225,187 @@ impl Frame {
             // save the callargs as locals
             // globals: locals.clone(),
             scope,
+            fastlocals: RefCell::new(fastlocals),
+            cells,
             lasti: RefCell::new(0),
         }
     }
 
     pub fn run(&self, vm: &VirtualMachine) -> Result<ExecutionResult, PyObjectRef> {
-        let filename = &self.code.source_path.to_string();
-
-        // This is the name of the object being run:
-        let run_obj_name = &self.code.obj_name.to_string();
-
         // Execute until return or exception:
         loop {
+            let lasti = *self.lasti.borrow();
             let lineno = self.get_lineno();
-            let result = self.execute_instruction(vm);
+            if gc::finalizers_pending() {
+                gc::run_finalizers(vm);
+            }
+            let result = vm
+                .check_signals()
+                .and_then(|()| self.execute_instruction(vm));
             match result {
                 Ok(None) => {}
                 Ok(Some(value)) => {
                     break Ok(value);
                 }
-                Err(exception) => {
-                    // unwind block stack on exception and find any handlers.
-                    // Add an entry in the traceback:
-                    assert!(objtype::isinstance(
-                        &exception,
-                        &vm.ctx.exceptions.base_exception_type
-                    ));
-                    let traceback = vm
-                        .get_attribute(exception.clone(), "__traceback__")
-                        .unwrap();
-                    trace!("Adding to traceback: {:?} {:?}", traceback, lineno);
-                    let pos = vm.ctx.new_tuple(vec![
-                        vm.ctx.new_str(filename.clone()),
-                        vm.ctx.new_int(lineno.get_row()),
-                        vm.ctx.new_str(run_obj_name.clone()),
-                    ]);
-                    objlist::PyListRef::try_from_object(vm, traceback)?.append(pos, vm);
-                    // exception.__trace
-                    match self.unwind_exception(vm, exception) {
-                        None => {}
-                        Some(exception) => {
-                            // TODO: append line number to traceback?
-                            // traceback.append();
-                            break Err(exception);
-                        }
-                    }
+                Err(exception) => match self.handle_exception(vm, exception, lasti, lineno) {
+                    Ok(None) => {}
+                    Ok(Some(result)) => break Ok(result),
+                    Err(exception) => break Err(exception),
+                },
+            }
+        }
+    }
+
+    /// Raise an exception at the point where this generator frame is
+    /// suspended, and continue running it if the exception is handled.
+    /// When suspended in a `yield from`, the exception is first thrown
+    /// into the iterator being delegated to.
+    pub fn throw(
+        &self,
+        vm: &VirtualMachine,
+        exception: PyObjectRef,
+    ) -> Result<ExecutionResult, PyObjectRef> {
+        let lasti = *self.lasti.borrow();
+        let result = match self.code.instructions.get(lasti) {
+            Some(bytecode::Instruction::YieldFrom) => {
+                *self.lasti.borrow_mut() += 1;
+                self.throw_into_delegate(vm, exception)
+                    .map_err(|exception| (exception, lasti))
+            }
+            _ => Err((exception, lasti.saturating_sub(1))),
+        };
+        let result = match result {
+            Ok(None) => return self.run(vm),
+            Ok(Some(result)) => return Ok(result),
+            Err((exception, lasti)) => {
+                let lineno = self.code.locations[lasti].clone();
+                self.handle_exception(vm, exception, lasti, lineno)
+            }
+        };
+        match result {
+            Ok(None) => self.run(vm),
+            Ok(Some(result)) => Ok(result),
+            Err(exception) => Err(exception),
+        }
+    }
+
+    /// Whether the frame is suspended in a `yield from` or `await`, rather
+    /// than at a plain `yield`.
+    pub fn is_delegating(&self) -> bool {
+        let lasti = *self.lasti.borrow();
+        match self.code.instructions.get(lasti) {
+            Some(bytecode::Instruction::YieldFrom) => true,
+            _ => false,
+        }
+    }
+
+    /// The iterator the frame is delegating to, if any.
+    pub fn delegate(&self) -> Option<PyObjectRef> {
+        if self.is_delegating() {
+            Some(self.last_value())
+        } else {
+            None
+        }
+    }
+
+    fn throw_into_delegate(&self, vm: &VirtualMachine, exception: PyObjectRef) -> FrameResult {
+        let iterator = self.last_value();
+        if objtype::isinstance(&exception, &vm.ctx.exceptions.generator_exit) {
+            // The iterator is closed, the frame itself gets the GeneratorExit:
+            if let Ok(close) = vm.get_attribute(iterator, "close") {
+                vm.invoke(close, vec![])?;
+            }
+            return Err(exception);
+        }
+        match vm.get_attribute(iterator, "throw") {
+            Ok(throw) => {
+                let result = vm.invoke(throw, vec![exception]);
+                self.delegate_result(vm, result)
+            }
+            Err(_) => Err(exception),
+        }
+    }
+
+    /// Handle the result of resuming the iterator a `yield from` delegates
+    /// to: yield its value, or replace it with the value it returned.
+    fn delegate_result(&self, vm: &VirtualMachine, result: PyResult) -> FrameResult {
+        match result {
+            Ok(value) => {
+                // Set back program counter:
+                *self.lasti.borrow_mut() -= 1;
+                Ok(Some(ExecutionResult::Yield(value)))
+            }
+            Err(exception) => {
+                if objtype::isinstance(&exception, &vm.ctx.exceptions.stop_iteration) {
+                    self.pop_value();
+                    self.push_value(objiter::stop_iteration_value(vm, &exception)?);
+                    Ok(None)
+                } else {
+                    Err(exception)
                 }
             }
         }
     }
 
+    fn handle_exception(
+        &self,
+        vm: &VirtualMachine,
+        exception: PyObjectRef,
+        lasti: usize,
+        lineno: ast::Location,
+    ) -> FrameResult {
+        // An exception raised while handling another one gets that one as
+        // its context. One passing up from a called frame already got it
+        // where it was raised, and a bare raise keeps the context it has.
+        let raised_here = match self.code.instructions.get(lasti) {
+            Some(bytecode::Instruction::Raise { argc }) => *argc != 0,
+            Some(bytecode::Instruction::EndFinally) => false,
+            _ => vm
+                .get_attribute(exception.clone(), "__traceback__")?
+                .is(&vm.get_none()),
+        };
+        if raised_here {
+            if let Some(context) = vm.current_exception() {
+                exceptions::set_context(vm, &exception, context)?;
+            }
+        }
+
+        // unwind block stack on exception and find any handlers.
+        // Add an entry in the traceback:
+        assert!(objtype::isinstance(
+            &exception,
+            &vm.ctx.exceptions.base_exception_type
+        ));
+        let next = vm
+            .get_attribute(exception.clone(), "__traceback__")?
+            .downcast::<PyTraceback>()
+            .ok();
+        let frame = vm
+            .current_frame()
+            .expect("running frame is not on the frame stack")
+            .clone();
+        // A bare raise, or the end of a finally clause, re-raises an
+        // exception which already has an entry for this frame:
+        let reraised = match self.code.instructions.get(lasti) {
+            Some(bytecode::Instruction::Raise { argc: 0 })
+            | Some(bytecode::Instruction::EndFinally) => match &next {
+                Some(next) => next.frame.is(&frame),
+                None => false,
+            },
+            _ => false,
+        };
+        if !reraised {
+            trace!("Adding to traceback: {:?} {:?}", next, lineno);
+            let traceback = PyTraceback::new(next, frame, lasti, lineno.get_row());
+            vm.set_attr(
+                &exception,
+                vm.new_str("__traceback__".to_string()),
+                traceback.into_ref(vm).into_object(),
+            )?;
+        }
+        self.unwind_blocks(vm, UnwindReason::Raising { exception })
+    }
+
     pub fn fetch_instruction(&self) -> &bytecode::Instruction {
         let ins2 = &self.code.instructions[*self.lasti.borrow()];
         *self.lasti.borrow_mut() += 1;
@@ -313,9 +438,64 @@ impl Frame {
                 ref symbol,
             } => self.import(vm, name, symbol),
             bytecode::Instruction::ImportStar { ref name } => self.import_star(vm, name),
-            bytecode::Instruction::LoadName { ref name } => self.load_name(vm, name),
-            bytecode::Instruction::StoreName { ref name } => self.store_name(vm, name),
-            bytecode::Instruction::DeleteName { ref name } => self.delete_name(vm, name),
+            bytecode::Instruction::LoadName {
+                ref name,
+                ref scope,
+            } => self.load_name(vm, name, scope),
+            bytecode::Instruction::StoreName {
+                ref name,
+                ref scope,
+            } => self.store_name(vm, name, scope),
+            bytecode::Instruction::DeleteName {
+                ref name,
+                ref scope,
+            } => self.delete_name(vm, name, scope),
+            bytecode::Instruction::LoadFast { idx } => {
+                let value = self.fastlocals.borrow()[*idx].clone();
+                match value {
+                    Some(value) => {
+                        self.push_value(value);
+                        Ok(None)
+                    }
+                    None => Err(self.unbound_local_error(vm, *idx)),
+                }
+            }
+            bytecode::Instruction::StoreFast { idx } => {
+                let value = self.pop_value();
+                self.fastlocals.borrow_mut()[*idx] = Some(value);
+                Ok(None)
+            }
+            bytecode::Instruction::DeleteFast { idx } => {
+                if self.fastlocals.borrow_mut()[*idx].take().is_none() {
+                    return Err(self.unbound_local_error(vm, *idx));
+                }
+                self.forget_local(&self.code.varnames[*idx]);
+                Ok(None)
+            }
+            bytecode::Instruction::LoadDeref { idx } => match self.cells[*idx].get() {
+                Some(value) => {
+                    self.push_value(value);
+                    Ok(None)
+                }
+                None => Err(self.unbound_cell_error(vm, *idx)),
+            },
+            bytecode::Instruction::StoreDeref { idx } => {
+                let value = self.pop_value();
+                self.cells[*idx].set(Some(value));
+                Ok(None)
+            }
+            bytecode::Instruction::DeleteDeref { idx } => {
+                if self.cells[*idx].get().is_none() {
+                    return Err(self.unbound_cell_error(vm, *idx));
+                }
+                self.cells[*idx].set(None);
+                self.forget_local(self.cell_name(*idx));
+                Ok(None)
+            }
+            bytecode::Instruction::LoadClosure { idx } => {
+                self.push_value(self.cells[*idx].clone().into_object());
+                Ok(None)
+            }
             bytecode::Instruction::StoreSubscript => self.execute_store_subscript(vm),
             bytecode::Instruction::DeleteSubscript => self.execute_delete_subscript(vm),
             bytecode::Instruction::Pop => {
@@ -392,11 +572,11 @@ impl Frame {
                         // Take all key-value pairs from the dict:
                         let dict_elements = objdict::get_key_value_pairs(&obj);
                         for (key, value) in dict_elements.iter() {
-                            objdict::set_item(&map_obj, vm, key, value);
+                            objdict::set_item(&map_obj, vm, key, value)?;
                         }
                     } else {
                         let key = self.pop_value();
-                        objdict::set_item(&map_obj, vm, &key, &obj);
+                        objdict::set_item(&map_obj, vm, &key, &obj)?;
                     }
                 }
                 self.push_value(map_obj);
@@ -456,11 +636,7 @@ impl Frame {
             bytecode::Instruction::CompareOperation { ref op } => self.execute_compare(vm, op),
             bytecode::Instruction::ReturnValue => {
                 let value = self.pop_value();
-                if let Some(exc) = self.unwind_blocks(vm) {
-                    Err(exc)
-                } else {
-                    Ok(Some(ExecutionResult::Return(value)))
-                }
+                self.unwind_blocks(vm, UnwindReason::Returning { value })
             }
             bytecode::Instruction::YieldValue => {
                 let value = self.pop_value();
@@ -468,22 +644,63 @@ impl Frame {
             }
             bytecode::Instruction::YieldFrom => {
                 // Value send into iterator:
-                self.pop_value();
-
-                let top_of_stack = self.last_value();
-                let next_obj = objiter::get_next_object(vm, &top_of_stack)?;
+                let value = self.pop_value();
 
-                match next_obj {
-                    Some(value) => {
-                        // Set back program counter:
-                        *self.lasti.borrow_mut() -= 1;
-                        Ok(Some(ExecutionResult::Yield(value)))
-                    }
-                    None => {
-                        // Pop iterator from stack:
-                        self.pop_value();
-                        Ok(None)
+                let iterator = self.last_value();
+                let result = if value.is(&vm.get_none()) {
+                    objiter::call_next(vm, &iterator)
+                } else {
+                    vm.call_method(&iterator, "send", vec![value])
+                };
+                self.delegate_result(vm, result)
+            }
+            bytecode::Instruction::GetAwaitable => {
+                let awaited = self.pop_value();
+                let iterator = objcoroutine::get_awaitable_iter(vm, &awaited)?;
+                self.push_value(iterator);
+                Ok(None)
+            }
+            bytecode::Instruction::GetAIter => {
+                let iterable = self.pop_value();
+                let aiter = vm.get_method(iterable.clone(), "__aiter__").map_err(|_| {
+                    vm.new_type_error(format!(
+                        "'async for' requires an object with __aiter__ method, got {}",
+                        iterable.class().name
+                    ))
+                })?;
+                let aiter = vm.invoke(aiter, vec![])?;
+                self.push_value(aiter);
+                Ok(None)
+            }
+            bytecode::Instruction::GetANext => {
+                let aiter = self.last_value();
+                let anext = vm.get_method(aiter.clone(), "__anext__").map_err(|_| {
+                    vm.new_type_error(format!(
+                        "'async for' requires an iterator with __anext__ method, got {}",
+                        aiter.class().name
+                    ))
+                })?;
+                let awaitable = vm.invoke(anext, vec![])?;
+                self.push_value(awaitable);
+                Ok(None)
+            }
+            bytecode::Instruction::EndAsyncFor => {
+                // The exception raised while awaiting the next item is on
+                // top of the stack:
+                let exception = self.pop_value();
+                if objtype::isinstance(&exception, &vm.ctx.exceptions.stop_async_iteration) {
+                    let block = self.pop_block().expect("no except handler block to pop");
+                    if let BlockType::ExceptHandler = block.typ {
+                        vm.pop_exception().expect("Should have exception in stack");
+                    } else {
+                        unreachable!("Block stack is incorrect, expected an except handler block");
                     }
+                    // Pop the awaitable and the asynchronous iterator:
+                    self.pop_value();
+                    self.pop_value();
+                    Ok(None)
+                } else {
+                    Err(exception)
                 }
             }
             bytecode::Instruction::SetupLoop { start, end } => {
@@ -497,6 +714,38 @@ impl Frame {
                 self.push_block(BlockType::TryExcept { handler: *handler });
                 Ok(None)
             }
+            bytecode::Instruction::SetupFinally { handler } => {
+                self.push_block(BlockType::Finally { handler: *handler });
+                Ok(None)
+            }
+            bytecode::Instruction::PopException => {
+                let block = self.pop_block().expect("no except handler block to pop");
+                if let BlockType::ExceptHandler = block.typ {
+                    vm.pop_exception().expect("Should have exception in stack");
+                    Ok(None)
+                } else {
+                    unreachable!("Block stack is incorrect, expected an except handler block");
+                }
+            }
+            bytecode::Instruction::EnterFinally => {
+                self.push_block(BlockType::FinallyHandler { reason: None });
+                Ok(None)
+            }
+            bytecode::Instruction::EndFinally => {
+                // Resume whatever caused the finally clause to run:
+                let block = self.pop_block().expect("no finally block to end");
+                if let BlockType::FinallyHandler { reason } = block.typ {
+                    if let Some(UnwindReason::Raising { .. }) = reason {
+                        vm.pop_exception().expect("Should have exception in stack");
+                    }
+                    match reason {
+                        Some(reason) => self.unwind_blocks(vm, reason),
+                        None => Ok(None),
+                    }
+                } else {
+                    unreachable!("Block stack is incorrect, expected a finally handler block");
+                }
+            }
             bytecode::Instruction::SetupWith { end } => {
                 let context_manager = self.pop_value();
                 // Call enter:
@@ -525,6 +774,63 @@ impl Frame {
 
                 Ok(None)
             }
+            bytecode::Instruction::BeforeAsyncWith => {
+                let context_manager = self.pop_value();
+                let aexit = vm.get_attribute(context_manager.clone(), "__aexit__")?;
+                let aenter = vm.call_method(&context_manager, "__aenter__", vec![])?;
+                self.push_value(aexit);
+                self.push_value(aenter);
+                Ok(None)
+            }
+            bytecode::Instruction::SetupAsyncWith { handler } => {
+                // The block is set up once __aenter__ has been awaited, below
+                // the value it returned:
+                let entered = self.pop_value();
+                self.push_block(BlockType::Finally { handler: *handler });
+                self.push_value(entered);
+                Ok(None)
+            }
+            bytecode::Instruction::WithCleanupStart => {
+                let reason = match self.current_block() {
+                    Some(Block {
+                        typ: BlockType::FinallyHandler { reason },
+                        ..
+                    }) => reason,
+                    _ => unreachable!("Block stack is incorrect, expected a finally handler block"),
+                };
+                let aexit = self.pop_value();
+                let args = match reason {
+                    Some(UnwindReason::Raising { exception }) => {
+                        let exc_tb = vm.get_attribute(exception.clone(), "__traceback__")?;
+                        vec![exception.class().into_object(), exception, exc_tb]
+                    }
+                    _ => vec![vm.get_none(), vm.get_none(), vm.get_none()],
+                };
+                let result = vm.invoke(aexit, args)?;
+                self.push_value(result);
+                Ok(None)
+            }
+            bytecode::Instruction::WithCleanupFinish => {
+                let result = self.pop_value();
+                let raising = match self.current_block() {
+                    Some(Block {
+                        typ: BlockType::FinallyHandler { reason },
+                        ..
+                    }) => match reason {
+                        Some(UnwindReason::Raising { .. }) => true,
+                        _ => false,
+                    },
+                    _ => unreachable!("Block stack is incorrect, expected a finally handler block"),
+                };
+                // A true result from __aexit__ swallows the exception:
+                if raising && objbool::boolval(vm, result)? {
+                    if let Some(block) = self.blocks.borrow_mut().last_mut() {
+                        block.typ = BlockType::FinallyHandler { reason: None };
+                    }
+                    vm.pop_exception().expect("Should have exception in stack");
+                }
+                Ok(None)
+            }
             bytecode::Instruction::PopBlock => {
                 self.pop_block().expect("no pop to block");
                 Ok(None)
@@ -568,6 +874,15 @@ impl Frame {
                     .downcast()
                     .expect("Second to top value on the stack must be a code object");
 
+                let closure = if flags.contains(bytecode::FunctionOpArg::HAS_CLOSURE) {
+                    vm.extract_elements(&self.pop_value())?
+                        .into_iter()
+                        .map(|cell| cell.downcast().expect("closure must contain cells"))
+                        .collect()
+                } else {
+                    vec![]
+                };
+
                 let annotations = if flags.contains(bytecode::FunctionOpArg::HAS_ANNOTATIONS) {
                     self.pop_value()
                 } else {
@@ -583,7 +898,7 @@ impl Frame {
                 // pop argc arguments
                 // argument: name, args, globals
                 let scope = self.scope.clone();
-                let obj = vm.ctx.new_function(code_obj, scope, defaults);
+                let obj = vm.ctx.new_function(code_obj, scope, defaults, closure);
 
                 vm.ctx.set_attr(&obj, "__annotations__", annotations);
 
@@ -613,11 +928,16 @@ impl Frame {
                     bytecode::CallType::Ex(has_kwargs) => {
                         let kwargs = if *has_kwargs {
                             let kw_dict = self.pop_value();
-                            let dict_elements = objdict::get_elements(&kw_dict).clone();
-                            dict_elements
-                                .into_iter()
-                                .map(|elem| (elem.0, (elem.1).1))
-                                .collect()
+                            let mut kwargs = vec![];
+                            for (key, value) in objdict::get_key_value_pairs(&kw_dict) {
+                                if !objtype::isinstance(&key, &vm.ctx.str_type()) {
+                                    return Err(
+                                        vm.new_type_error("keywords must be strings".to_string())
+                                    );
+                                }
+                                kwargs.push((objstr::get_value(&key), value));
+                            }
+                            kwargs
                         } else {
                             vec![]
                         };
@@ -656,56 +976,41 @@ impl Frame {
             }
 
             bytecode::Instruction::Raise { argc } => {
+                let cause = match argc {
+                    2 => Some(self.get_exception(vm, true)?),
+                    _ => None,
+                };
                 let exception = match argc {
-                    1 => self.pop_value(),
-                    0 | 2 | 3 => panic!("Not implemented!"),
+                    0 => match vm.current_exception() {
+                        Some(exception) => exception,
+                        None => {
+                            return Err(
+                                vm.new_runtime_error("No active exception to reraise".to_string())
+                            );
+                        }
+                    },
+                    1 | 2 => self.get_exception(vm, false)?,
+                    3 => panic!("Not implemented!"),
                     _ => panic!("Invalid parameter for RAISE_VARARGS, must be between 0 to 3"),
                 };
-                if objtype::isinstance(&exception, &vm.ctx.exceptions.base_exception_type) {
-                    info!("Exception raised: {:?}", exception);
-                    Err(exception)
-                } else if let Ok(exception) = PyClassRef::try_from_object(vm, exception) {
-                    if objtype::issubclass(&exception, &vm.ctx.exceptions.base_exception_type) {
-                        let exception = vm.new_empty_exception(exception)?;
-                        info!("Exception raised: {:?}", exception);
-                        Err(exception)
-                    } else {
-                        let msg = format!(
-                            "Can only raise BaseException derived types, not {}",
-                            exception
-                        );
-                        let type_error_type = vm.ctx.exceptions.type_error.clone();
-                        let type_error = vm.new_exception(type_error_type, msg);
-                        Err(type_error)
-                    }
-                } else {
-                    Err(vm.new_type_error("exceptions must derive from BaseException".to_string()))
+                if let Some(cause) = cause {
+                    vm.set_attr(&exception, vm.new_str("__cause__".to_string()), cause)?;
+                    vm.set_attr(
+                        &exception,
+                        vm.new_str("__suppress_context__".to_string()),
+                        vm.new_bool(true),
+                    )?;
                 }
+                info!("Exception raised: {:?}", exception);
+                Err(exception)
             }
 
-            bytecode::Instruction::Break => {
-                let block = self.unwind_loop(vm);
-                if let BlockType::Loop { end, .. } = block.typ {
-                    self.pop_block();
-                    self.jump(end);
-                } else {
-                    unreachable!()
-                }
-                Ok(None)
-            }
+            bytecode::Instruction::Break => self.unwind_blocks(vm, UnwindReason::Break),
             bytecode::Instruction::Pass => {
                 // Ah, this is nice, just relax!
                 Ok(None)
             }
-            bytecode::Instruction::Continue => {
-                let block = self.unwind_loop(vm);
-                if let BlockType::Loop { start, .. } = block.typ {
-                    self.jump(start);
-                } else {
-                    unreachable!();
-                }
-                Ok(None)
-            }
+            bytecode::Instruction::Continue => self.unwind_blocks(vm, UnwindReason::Continue),
             bytecode::Instruction::PrintExpr => {
                 let expr = self.pop_value();
                 if !expr.is(&vm.get_none()) {
@@ -846,94 +1151,128 @@ impl Frame {
         Ok(None)
     }
 
-    // Unwind all blocks:
-    fn unwind_blocks(&self, vm: &VirtualMachine) -> Option<PyObjectRef> {
-        while let Some(block) = self.pop_block() {
+    /// Unwind the block stack for the given reason, until a block is found
+    /// which handles it. Finally clauses on the way are run first.
+    fn unwind_blocks(&self, vm: &VirtualMachine, mut reason: UnwindReason) -> FrameResult {
+        while let Some(block) = self.current_block() {
             match block.typ {
-                BlockType::Loop { .. } => {}
-                BlockType::TryExcept { .. } => {
-                    // TODO: execute finally handler
-                }
-                BlockType::With {
-                    context_manager, ..
-                } => {
-                    match self.with_exit(vm, &context_manager, None) {
-                        Ok(..) => {}
-                        Err(exc) => {
-                            // __exit__ went wrong,
-                            return Some(exc);
-                        }
+                BlockType::Loop { start, end } => match reason {
+                    UnwindReason::Break => {
+                        self.pop_block();
+                        self.jump(end);
+                        return Ok(None);
                     }
-                }
-            }
-        }
-
-        None
-    }
-
-    fn unwind_loop(&self, vm: &VirtualMachine) -> Block {
-        loop {
-            let block = self.current_block().expect("not in a loop");
-            match block.typ {
-                BlockType::Loop { .. } => break block,
-                BlockType::TryExcept { .. } => {
-                    // TODO: execute finally handler
-                }
-                BlockType::With {
-                    context_manager, ..
-                } => match self.with_exit(vm, &context_manager, None) {
-                    Ok(..) => {}
-                    Err(exc) => {
-                        panic!("Exception in with __exit__ {:?}", exc);
+                    UnwindReason::Continue => {
+                        self.jump(start);
+                        return Ok(None);
+                    }
+                    _ => {
+                        self.pop_block();
                     }
                 },
-            }
-
-            self.pop_block();
-        }
-    }
-
-    fn unwind_exception(&self, vm: &VirtualMachine, exc: PyObjectRef) -> Option<PyObjectRef> {
-        // unwind block stack on exception and find any handlers:
-        while let Some(block) = self.pop_block() {
-            match block.typ {
                 BlockType::TryExcept { handler } => {
-                    self.push_value(exc);
+                    self.pop_block();
+                    if let UnwindReason::Raising { exception } = reason {
+                        self.push_block(BlockType::ExceptHandler);
+                        self.push_value(exception.clone());
+                        vm.push_exception(exception);
+                        self.jump(handler);
+                        return Ok(None);
+                    }
+                }
+                BlockType::ExceptHandler => {
+                    self.pop_block();
+                    vm.pop_exception().expect("Should have exception in stack");
+                }
+                BlockType::Finally { handler } => {
+                    self.pop_block();
+                    if let UnwindReason::Raising { exception } = &reason {
+                        vm.push_exception(exception.clone());
+                    }
+                    self.push_block(BlockType::FinallyHandler {
+                        reason: Some(reason),
+                    });
                     self.jump(handler);
-                    return None;
+                    return Ok(None);
+                }
+                BlockType::FinallyHandler { reason } => {
+                    // Leaving a finally clause early drops the reason it was
+                    // entered for.
+                    self.pop_block();
+                    if let Some(UnwindReason::Raising { .. }) = reason {
+                        vm.pop_exception().expect("Should have exception in stack");
+                    }
                 }
                 BlockType::With {
                     end,
                     context_manager,
                 } => {
-                    match self.with_exit(vm, &context_manager, Some(exc.clone())) {
+                    self.pop_block();
+                    let exception = match &reason {
+                        UnwindReason::Raising { exception } => Some(exception.clone()),
+                        _ => None,
+                    };
+                    let handled = exception.is_some();
+                    match self.with_exit(vm, &context_manager, exception) {
                         Ok(exit_action) => {
-                            match objbool::boolval(vm, exit_action) {
-                                Ok(handle_exception) => {
-                                    if handle_exception {
-                                        // We handle the exception, so return!
+                            if handled {
+                                match objbool::boolval(vm, exit_action) {
+                                    Ok(true) => {
+                                        // The exception is handled, so continue after the block.
                                         self.jump(end);
-                                        return None;
-                                    } else {
-                                        // go on with the stack unwinding.
+                                        return Ok(None);
+                                    }
+                                    Ok(false) => {}
+                                    Err(exit_exc) => {
+                                        reason = UnwindReason::Raising {
+                                            exception: exit_exc,
+                                        };
                                     }
-                                }
-                                Err(exit_exc) => {
-                                    return Some(exit_exc);
                                 }
                             }
-                            // if objtype::isinstance
                         }
                         Err(exit_exc) => {
                             // TODO: what about original exception?
-                            return Some(exit_exc);
+                            reason = UnwindReason::Raising {
+                                exception: exit_exc,
+                            };
                         }
                     }
                 }
-                BlockType::Loop { .. } => {}
             }
         }
-        Some(exc)
+
+        match reason {
+            UnwindReason::Raising { exception } => Err(exception),
+            UnwindReason::Returning { value } => Ok(Some(ExecutionResult::Return(value))),
+            UnwindReason::Break | UnwindReason::Continue => {
+                panic!("Internal error: break or continue must occur within a loop block.")
+            }
+        }
+    }
+
+    /// Pop an exception to raise, or to use as the cause of another exception.
+    /// Exception classes are instantiated.
+    fn get_exception(&self, vm: &VirtualMachine, none_allowed: bool) -> PyResult {
+        let exception = self.pop_value();
+        if none_allowed && vm.get_none().is(&exception)
+            || objtype::isinstance(&exception, &vm.ctx.exceptions.base_exception_type)
+        {
+            Ok(exception)
+        } else if let Ok(exc_type) = PyClassRef::try_from_object(vm, exception) {
+            if objtype::issubclass(&exc_type, &vm.ctx.exceptions.base_exception_type) {
+                let exception = vm.new_empty_exception(exc_type)?;
+                Ok(exception)
+            } else {
+                let msg = format!(
+                    "Can only raise BaseException derived types, not {}",
+                    exc_type
+                );
+                Err(vm.new_type_error(msg))
+            }
+        } else {
+            Err(vm.new_type_error("exceptions must derive from BaseException".to_string()))
+        }
     }
 
     fn with_exit(
@@ -961,19 +1300,45 @@ impl Frame {
         vm.call_method(context_manager, "__exit__", args)
     }
 
-    fn store_name(&self, vm: &VirtualMachine, name: &str) -> FrameResult {
+    fn store_name(
+        &self,
+        vm: &VirtualMachine,
+        name: &str,
+        name_scope: &bytecode::NameScope,
+    ) -> FrameResult {
         let obj = self.pop_value();
-        self.scope.store_name(&vm, name, obj);
+        match name_scope {
+            bytecode::NameScope::Global => self.scope.store_global(vm, name, obj),
+            bytecode::NameScope::Local => self.scope.store_name(vm, name, obj),
+        }
         Ok(None)
     }
 
-    fn delete_name(&self, vm: &VirtualMachine, name: &str) -> FrameResult {
-        self.scope.delete_name(vm, name);
+    fn delete_name(
+        &self,
+        vm: &VirtualMachine,
+        name: &str,
+        name_scope: &bytecode::NameScope,
+    ) -> FrameResult {
+        match name_scope {
+            bytecode::NameScope::Global => self.scope.delete_global(vm, name),
+            bytecode::NameScope::Local => self.scope.delete_name(vm, name),
+        }
         Ok(None)
     }
 
-    fn load_name(&self, vm: &VirtualMachine, name: &str) -> FrameResult {
-        match self.scope.load_name(&vm, name) {
+    fn load_name(
+        &self,
+        vm: &VirtualMachine,
+        name: &str,
+        name_scope: &bytecode::NameScope,
+    ) -> FrameResult {
+        let value = match name_scope {
+            bytecode::NameScope::Global => self.scope.load_global(vm, name),
+            bytecode::NameScope::Local => self.scope.load_name(vm, name),
+        };
+
+        match value {
             Some(value) => {
                 self.push_value(value);
                 Ok(None)
@@ -987,6 +1352,84 @@ impl Frame {
         }
     }
 
+    fn unbound_local_error(&self, vm: &VirtualMachine, idx: usize) -> PyObjectRef {
+        let unbound_local_error = vm.ctx.exceptions.unbound_local_error.clone();
+        let msg = format!(
+            "local variable '{}' referenced before assignment",
+            self.code.varnames[idx]
+        );
+        vm.new_exception(unbound_local_error, msg)
+    }
+
+    fn cell_name(&self, idx: usize) -> &str {
+        let ncells = self.code.cellvars.len();
+        if idx < ncells {
+            &self.code.cellvars[idx]
+        } else {
+            &self.code.freevars[idx - ncells]
+        }
+    }
+
+    fn unbound_cell_error(&self, vm: &VirtualMachine, idx: usize) -> PyObjectRef {
+        let name = self.cell_name(idx);
+        if idx < self.code.cellvars.len() {
+            let unbound_local_error = vm.ctx.exceptions.unbound_local_error.clone();
+            let msg = format!("local variable '{}' referenced before assignment", name);
+            vm.new_exception(unbound_local_error, msg)
+        } else {
+            let name_error = vm.ctx.exceptions.name_error.clone();
+            let msg = format!(
+                "free variable '{}' referenced before assignment in enclosing scope",
+                name
+            );
+            vm.new_exception(name_error, msg)
+        }
+    }
+
+    /// Get the value of a cell or free variable by name.
+    pub fn load_cell(&self, name: &str) -> Option<PyObjectRef> {
+        let names = self.code.cellvars.iter().chain(self.code.freevars.iter());
+        names
+            .zip(self.cells.iter())
+            .find(|(cell_name, _)| *cell_name == name)
+            .and_then(|(_, cell)| cell.get())
+    }
+
+    /// Forget a copy made by an earlier call to `locals()` of a deleted variable.
+    fn forget_local(&self, name: &str) {
+        if let Some(locals) = self.scope.get_only_locals() {
+            if locals.contains_key(name) {
+                locals.del_item(name);
+            }
+        }
+    }
+
+    /// Copy the fast locals and the variables held in cells into the locals
+    /// dictionary of the scope.
+    pub fn fast_to_locals(&self, vm: &VirtualMachine) {
+        if let Some(locals) = self.scope.get_only_locals() {
+            let fastlocals = self.fastlocals.borrow();
+            for (name, value) in self.code.varnames.iter().zip(fastlocals.iter()) {
+                if let Some(value) = value {
+                    locals.set_item(&vm.ctx, name, value.clone());
+                }
+            }
+
+            let names = self.code.cellvars.iter().chain(self.code.freevars.iter());
+            for (name, cell) in names.zip(self.cells.iter()) {
+                if let Some(value) = cell.get() {
+                    locals.set_item(&vm.ctx, name, value);
+                }
+            }
+        }
+    }
+
+    /// Get the locals dictionary, up to date with the fast locals.
+    pub fn get_locals(&self, vm: &VirtualMachine) -> PyDictRef {
+        self.fast_to_locals(vm);
+        self.scope.get_locals()
+    }
+
     fn subscript(&self, vm: &VirtualMachine, a: PyObjectRef, b: PyObjectRef) -> PyResult {
         vm.call_method(&a, "__getitem__", vec![b])
     }
@@ -1082,29 +1525,24 @@ impl Frame {
         needle: PyObjectRef,
         haystack: &PyObjectRef,
     ) -> PyResult {
-        vm.call_method(&haystack, "__contains__", vec![needle])
         // TODO: implement __iter__ and __getitem__ cases when __contains__ is
         // not implemented.
-    }
-
-    fn _in(&self, vm: &VirtualMachine, needle: PyObjectRef, haystack: PyObjectRef) -> PyResult {
-        match self._membership(vm, needle, &haystack) {
-            Ok(found) => Ok(found),
-            Err(_) => Err(vm.new_type_error(format!(
+        if !objtype::class_has_attr(&haystack.class(), "__contains__") {
+            return Err(vm.new_type_error(format!(
                 "{} has no __contains__ method",
                 haystack.class().name
-            ))),
+            )));
         }
+        vm.call_method(&haystack, "__contains__", vec![needle])
+    }
+
+    fn _in(&self, vm: &VirtualMachine, needle: PyObjectRef, haystack: PyObjectRef) -> PyResult {
+        self._membership(vm, needle, &haystack)
     }
 
     fn _not_in(&self, vm: &VirtualMachine, needle: PyObjectRef, haystack: PyObjectRef) -> PyResult {
-        match self._membership(vm, needle, &haystack) {
-            Ok(found) => Ok(vm.ctx.new_bool(!objbool::get_value(&found))),
-            Err(_) => Err(vm.new_type_error(format!(
-                "{} has no __contains__ method",
-                haystack.class().name
-            ))),
-        }
+        let found = self._membership(vm, needle, &haystack)?;
+        Ok(vm.ctx.new_bool(!objbool::get_value(&found)))
     }
 
     fn _is(&self, a: PyObjectRef, b: PyObjectRef) -> bool {
@@ -1184,6 +1622,51 @@ impl Frame {
         self.blocks.borrow().last().cloned()
     }
 
+    /// The objects this frame refers to, for the cycle collector. `None`
+    /// if the frame is being modified.
+    pub fn referents(&self) -> Option<Vec<PyObjectRef>> {
+        let mut referents = vec![self.scope.globals.as_object().clone()];
+        if let Some(locals) = self.scope.get_only_locals() {
+            referents.push(locals.into_object());
+        }
+        referents.extend(self.stack.try_borrow().ok()?.iter().cloned());
+        referents.extend(self.fastlocals.try_borrow().ok()?.iter().flatten().cloned());
+        referents.extend(self.cells.iter().map(|cell| cell.as_object().clone()));
+        for block in self.blocks.try_borrow().ok()?.iter() {
+            match &block.typ {
+                BlockType::With {
+                    context_manager, ..
+                } => referents.push(context_manager.clone()),
+                BlockType::FinallyHandler {
+                    reason: Some(UnwindReason::Returning { value }),
+                } => referents.push(value.clone()),
+                BlockType::FinallyHandler {
+                    reason: Some(UnwindReason::Raising { exception }),
+                } => referents.push(exception.clone()),
+                _ => {}
+            }
+        }
+        Some(referents)
+    }
+
+    /// Drop the values on the stack, the blocks and the fast locals of a
+    /// frame which is no longer reachable.
+    pub fn clear(&self) {
+        let stack = self
+            .stack
+            .try_borrow_mut()
+            .map(|mut s| mem::replace(&mut *s, vec![]));
+        let blocks = self
+            .blocks
+            .try_borrow_mut()
+            .map(|mut b| mem::replace(&mut *b, vec![]));
+        let fastlocals = self
+            .fastlocals
+            .try_borrow_mut()
+            .map(|mut locals| locals.iter_mut().map(Option::take).collect::<Vec<_>>());
+        drop((stack, blocks, fastlocals));
+    }
+
     pub fn push_value(&self, obj: PyObjectRef) {
         self.stack.borrow_mut().push(obj);
     }
@@ -0,0 +1,589 @@
+/*! Cycle collection.
+
+Objects are reference counted, so objects referring to each other are never
+freed by reference counting alone. Container objects are tracked when they
+are created, and collecting finds the tracked objects which are only
+referred to by other tracked objects and can't be reached from anything
+else. The references between those objects are then cleared, which frees
+them. Collections are started by the allocations which pass the threshold.
+
+As in CPython, tracked objects are kept in three generations. New objects
+start in the youngest one, and the objects which survive a collection move
+to the next. Most collections only look at the youngest generation, and so
+don't get slower with the number of long-lived objects. References from
+older generations count as references from elsewhere, so a cycle which
+reaches an older generation is only freed once that generation is
+collected too.
+
+Freeing an object can also need Python code to run: `__del__` methods and
+weak reference callbacks. Objects can be freed at any point, so these are
+queued and run by `run_finalizers` at the next safe point.
+
+A dropped object can't be kept alive any more, so objects whose class has a
+`__del__` method when they are created are held by the collector until
+nothing else refers to them. `__del__` is then called on the object itself,
+which may resurrect it, and the object is let go of. Checking for this takes
+time in proportion to the number of such objects, so with many of them it is
+done less often than before every instruction. Garbage found by a collection
+is still alive, and has its `__del__` called the same way.
+*/
+
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem;
+use std::rc::{Rc, Weak};
+
+use crate::exceptions;
+use crate::frame::Frame;
+use crate::obj::objcell::PyCell;
+use crate::obj::objdict::PyDict;
+use crate::obj::objfunction::{PyFunction, PyMethod};
+use crate::obj::objlist::PyList;
+use crate::obj::objobject::PyInstance;
+use crate::obj::objset::{PyFrozenSet, PySet};
+use crate::obj::objtuple::PyTuple;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::obj::objweakref::PyWeak;
+use crate::pyobject::{PyObject, PyObjectPayload, PyObjectRef, TypeProtocol};
+use crate::traceback::PyTraceback;
+use crate::vm::VirtualMachine;
+
+/// The number of generations. The last one holds the long-lived objects.
+pub const GENERATIONS: usize = 3;
+
+/// The tracked allocations which trigger a collection of the youngest
+/// generation, and the collections of each generation which trigger a
+/// collection of the next one.
+pub const DEFAULT_THRESHOLDS: [usize; GENERATIONS] = [700, 10, 10];
+
+type Tracked = Weak<PyObject<dyn PyObjectPayload>>;
+
+struct GcState {
+    generations: [RefCell<Vec<Tracked>>; GENERATIONS],
+    /// When the youngest generation gets this long without being collected,
+    /// dead entries are removed from it.
+    prune_at: Cell<usize>,
+    /// Tracked allocations since the youngest generation was collected,
+    /// then for each older generation, the collections of the generation
+    /// before it since it was collected.
+    counts: [Cell<usize>; GENERATIONS],
+    thresholds: [Cell<usize>; GENERATIONS],
+    /// Objects which survived the last full collection, and objects which
+    /// reached the oldest generation since, to limit full collections to
+    /// when enough new long-lived objects are waiting.
+    long_lived_total: Cell<usize>,
+    long_lived_pending: Cell<usize>,
+    enabled: Cell<bool>,
+    collecting: Cell<bool>,
+    /// Weak references with callbacks, by the address of their referent.
+    weakref_callbacks: RefCell<HashMap<usize, Vec<Weak<PyObject<dyn PyObjectPayload>>>>>,
+    /// The objects with a `__del__` method which hasn't been called yet, by
+    /// generation.
+    with_del: [RefCell<Vec<PyObjectRef>>; GENERATIONS],
+    /// The safe points until `with_del` is next checked, or 0 if it's empty.
+    del_check_in: Cell<usize>,
+    pending: RefCell<VecDeque<Finalizer>>,
+    /// Set when finalizers are queued, so that the interpreter loop can
+    /// check for them without borrowing `pending`.
+    finalizers_pending: Cell<bool>,
+}
+
+enum Finalizer {
+    Del(PyObjectRef),
+    Callback {
+        callback: PyObjectRef,
+        weakref: PyObjectRef,
+    },
+}
+
+thread_local! {
+    static STATE: GcState = GcState {
+        generations: [RefCell::new(vec![]), RefCell::new(vec![]), RefCell::new(vec![])],
+        prune_at: Cell::new(1024),
+        counts: [Cell::new(0), Cell::new(0), Cell::new(0)],
+        thresholds: [
+            Cell::new(DEFAULT_THRESHOLDS[0]),
+            Cell::new(DEFAULT_THRESHOLDS[1]),
+            Cell::new(DEFAULT_THRESHOLDS[2]),
+        ],
+        long_lived_total: Cell::new(0),
+        long_lived_pending: Cell::new(0),
+        enabled: Cell::new(true),
+        collecting: Cell::new(false),
+        weakref_callbacks: RefCell::new(HashMap::new()),
+        with_del: [RefCell::new(vec![]), RefCell::new(vec![]), RefCell::new(vec![])],
+        del_check_in: Cell::new(0),
+        pending: RefCell::new(VecDeque::new()),
+        finalizers_pending: Cell::new(false),
+    };
+}
+
+/// Whether objects with this payload can refer to other objects, and so
+/// should be tracked.
+pub fn is_container<T: 'static>() -> bool {
+    let id = TypeId::of::<T>();
+    id == TypeId::of::<PyList>()
+        || id == TypeId::of::<PyTuple>()
+        || id == TypeId::of::<PyDict>()
+        || id == TypeId::of::<PySet>()
+        || id == TypeId::of::<PyFrozenSet>()
+        || id == TypeId::of::<PyInstance>()
+        || id == TypeId::of::<PyFunction>()
+        || id == TypeId::of::<PyMethod>()
+        || id == TypeId::of::<PyCell>()
+        || id == TypeId::of::<Frame>()
+        || id == TypeId::of::<PyTraceback>()
+}
+
+pub fn track(obj: &PyObjectRef) {
+    STATE.with(|state| {
+        let mut young = state.generations[0].borrow_mut();
+        if young.len() >= state.prune_at.get() {
+            young.retain(|weak| weak.upgrade().is_some());
+            state.prune_at.set((young.len() * 2).max(1024));
+        }
+        young.push(Rc::downgrade(obj));
+        state.counts[0].set(state.counts[0].get() + 1);
+        if obj.dict.is_some() && has_del(&obj.typ) {
+            state.with_del[0].borrow_mut().push(obj.clone());
+            if state.del_check_in.get() == 0 {
+                schedule_del_check(state);
+            }
+        }
+    });
+    collect_if_needed();
+}
+
+pub fn is_enabled() -> bool {
+    STATE.with(|state| state.enabled.get())
+}
+
+pub fn set_enabled(enabled: bool) {
+    STATE.with(|state| state.enabled.set(enabled))
+}
+
+pub fn get_count() -> [usize; GENERATIONS] {
+    STATE.with(|state| {
+        let counts = &state.counts;
+        [counts[0].get(), counts[1].get(), counts[2].get()]
+    })
+}
+
+pub fn get_threshold() -> [usize; GENERATIONS] {
+    STATE.with(|state| {
+        let thresholds = &state.thresholds;
+        [
+            thresholds[0].get(),
+            thresholds[1].get(),
+            thresholds[2].get(),
+        ]
+    })
+}
+
+/// Set the thresholds of the first generations. A first threshold of 0
+/// disables automatic collection.
+pub fn set_threshold(thresholds: &[usize]) {
+    STATE.with(|state| {
+        for (threshold, &value) in state.thresholds.iter().zip(thresholds) {
+            threshold.set(value);
+        }
+    })
+}
+
+/// Collect the oldest generation whose count has passed its threshold, as
+/// CPython does. The oldest generation also waits for the objects which
+/// reached it since the last full collection to be a quarter of those which
+/// survived it, so that full collections stay rare with many objects alive.
+fn collect_if_needed() {
+    let generation = STATE.with(|state| {
+        if !state.enabled.get() || state.thresholds[0].get() == 0 {
+            return None;
+        }
+        (0..GENERATIONS).rev().find(|&i| {
+            state.counts[i].get() > state.thresholds[i].get()
+                && (i < GENERATIONS - 1
+                    || state.long_lived_pending.get() >= state.long_lived_total.get() / 4)
+        })
+    });
+    if let Some(generation) = generation {
+        collect(generation);
+    }
+}
+
+/// The live objects of the generations up to `generation`, which are taken
+/// out of them.
+fn take_generations(state: &GcState, generation: usize) -> Vec<PyObjectRef> {
+    state.generations[..=generation]
+        .iter()
+        .flat_map(|objects| mem::replace(&mut *objects.borrow_mut(), vec![]))
+        .filter_map(|weak| weak.upgrade())
+        .collect()
+}
+
+/// All the live tracked objects.
+pub fn get_objects() -> Vec<PyObjectRef> {
+    STATE.with(|state| {
+        let mut live = vec![];
+        for objects in &state.generations {
+            let mut objects = objects.borrow_mut();
+            objects.retain(|weak| weak.upgrade().is_some());
+            live.extend(objects.iter().filter_map(Weak::upgrade));
+        }
+        live
+    })
+}
+
+/// The objects directly referred to by `obj`, or `None` if they can't be
+/// found right now because the object is being modified.
+pub fn referents(obj: &PyObjectRef) -> Option<Vec<PyObjectRef>> {
+    let mut referents = vec![];
+    if let Some(dict) = &obj.dict {
+        referents.push(dict.as_object().clone());
+    }
+    if let Some(list) = obj.payload::<PyList>() {
+        referents.extend(list.elements.try_borrow().ok()?.iter().cloned());
+    } else if let Some(tuple) = obj.payload::<PyTuple>() {
+        referents.extend(tuple.elements.try_borrow().ok()?.iter().cloned());
+    } else if let Some(dict) = obj.payload::<PyDict>() {
+        for (key, value) in dict.entries.try_borrow().ok()?.values() {
+            referents.push(key.clone());
+            referents.push(value.clone());
+        }
+    } else if let Some(set) = obj.payload::<PySet>() {
+        referents.extend(set.try_elements()?);
+    } else if let Some(set) = obj.payload::<PyFrozenSet>() {
+        referents.extend(set.elements());
+    } else if let Some(function) = obj.payload::<PyFunction>() {
+        referents.push(function.scope.globals.as_object().clone());
+        if let Some(locals) = function.scope.get_only_locals() {
+            referents.push(locals.into_object());
+        }
+        referents.push(function.defaults.clone());
+        referents.extend(function.closure.iter().map(|cell| cell.as_object().clone()));
+    } else if let Some(method) = obj.payload::<PyMethod>() {
+        referents.push(method.object.clone());
+        referents.push(method.function.clone());
+    } else if let Some(cell) = obj.payload::<PyCell>() {
+        referents.extend(cell.try_get()?);
+    } else if let Some(frame) = obj.payload::<Frame>() {
+        referents.extend(frame.referents()?);
+    } else if let Some(traceback) = obj.payload::<PyTraceback>() {
+        if let Some(next) = &traceback.next {
+            referents.push(next.as_object().clone());
+        }
+        referents.push(traceback.frame.as_object().clone());
+    }
+    Some(referents)
+}
+
+/// Drop the references held by `obj`, as far as it can be changed.
+fn clear(obj: &PyObjectRef) {
+    // The contents are only dropped once the borrows are released, in case
+    // dropping them needs to look at this object.
+    if let Some(list) = obj.payload::<PyList>() {
+        let elements = list
+            .elements
+            .try_borrow_mut()
+            .map(|mut e| mem::replace(&mut *e, vec![]));
+        drop(elements);
+    } else if let Some(tuple) = obj.payload::<PyTuple>() {
+        let elements = tuple
+            .elements
+            .try_borrow_mut()
+            .map(|mut e| mem::replace(&mut *e, vec![]));
+        drop(elements);
+    } else if let Some(dict) = obj.payload::<PyDict>() {
+        let entries = dict
+            .entries
+            .try_borrow_mut()
+            .map(|mut e| mem::replace(&mut *e, HashMap::new()));
+        drop(entries);
+    } else if let Some(set) = obj.payload::<PySet>() {
+        let elements = set.try_take();
+        drop(elements);
+    } else if let Some(cell) = obj.payload::<PyCell>() {
+        let contents = cell.try_take();
+        drop(contents);
+    } else if let Some(frame) = obj.payload::<Frame>() {
+        frame.clear();
+    }
+}
+
+fn address(obj: &PyObjectRef) -> *const u8 {
+    &**obj as *const PyObject<dyn PyObjectPayload> as *const u8
+}
+
+fn has_del(class: &PyClassRef) -> bool {
+    // Classes being modified are assumed not to have one.
+    std::iter::once(class).chain(class.mro.iter()).any(|class| {
+        class
+            .attributes
+            .try_borrow()
+            .map(|attributes| attributes.contains_key("__del__"))
+            .unwrap_or(false)
+    })
+}
+
+/// Free the objects of the generations up to `generation` which can only be
+/// reached through reference cycles, returning how many were freed. The
+/// others move to the next generation. Cycles with `__del__` methods are
+/// only freed by the first collection after those have run.
+pub fn collect(generation: usize) -> usize {
+    let already_collecting = STATE.with(|state| state.collecting.replace(true));
+    if already_collecting {
+        return 0;
+    }
+    let (objects, waiting_for_del) = STATE.with(|state| {
+        if generation + 1 < GENERATIONS {
+            let older = &state.counts[generation + 1];
+            older.set(older.get() + 1);
+        }
+        for count in &state.counts[..=generation] {
+            count.set(0);
+        }
+        let with_del: Vec<PyObjectRef> = state.with_del[..=generation]
+            .iter()
+            .flat_map(|objects| mem::replace(&mut *objects.borrow_mut(), vec![]))
+            .collect();
+        (take_generations(state, generation), with_del)
+    });
+    let tracked: Vec<Tracked> = objects.iter().map(Rc::downgrade).collect();
+    let with_del: HashSet<*const u8> = waiting_for_del.iter().map(address).collect();
+
+    let index: HashMap<*const u8, usize> = objects
+        .iter()
+        .enumerate()
+        .map(|(i, obj)| (address(obj), i))
+        .collect();
+    // References not accounted for by other tracked objects come from
+    // elsewhere. `objects` itself holds one reference to each, and
+    // `waiting_for_del` another to the objects waiting for `__del__`.
+    let mut external: Vec<usize> = objects
+        .iter()
+        .map(|obj| {
+            let held = if with_del.contains(&address(obj)) {
+                2
+            } else {
+                1
+            };
+            Rc::strong_count(obj) - held
+        })
+        .collect();
+    let referents: Vec<Option<Vec<PyObjectRef>>> = objects.iter().map(referents).collect();
+    for children in referents.iter().flatten() {
+        for child in children {
+            if let Some(&i) = index.get(&address(child)) {
+                external[i] = external[i].saturating_sub(1);
+            }
+        }
+    }
+
+    // Everything reachable from an externally referenced object is alive.
+    // Objects which couldn't be inspected are kept alive too.
+    let mut reachable = vec![false; objects.len()];
+    let mut pending: Vec<usize> = (0..objects.len())
+        .filter(|&i| external[i] > 0 || referents[i].is_none())
+        .collect();
+    for &i in &pending {
+        reachable[i] = true;
+    }
+    while let Some(i) = pending.pop() {
+        for child in referents[i].iter().flatten() {
+            if let Some(&j) = index.get(&address(child)) {
+                if !reachable[j] {
+                    reachable[j] = true;
+                    pending.push(j);
+                }
+            }
+        }
+    }
+    drop(referents);
+
+    let garbage: Vec<PyObjectRef> = objects
+        .into_iter()
+        .zip(reachable)
+        .filter(|(_, reachable)| !reachable)
+        .map(|(obj, _)| obj)
+        .collect();
+    // `__del__` methods get to see the objects intact, and may resurrect
+    // them, so nothing is cleared until they have run and the objects are
+    // found again by a later collection.
+    let to_finalize: HashSet<*const u8> = garbage
+        .iter()
+        .map(address)
+        .filter(|obj| with_del.contains(obj))
+        .collect();
+    let collected = if to_finalize.is_empty() {
+        for obj in &garbage {
+            clear(obj);
+        }
+        garbage.len()
+    } else {
+        0
+    };
+    drop(garbage);
+
+    // Everything still alive, including objects waiting for `__del__`,
+    // moves on to the next generation.
+    let survivors: Vec<Tracked> = tracked
+        .into_iter()
+        .filter(|weak| weak.upgrade().is_some())
+        .collect();
+    STATE.with(|state| {
+        let older = (generation + 1).min(GENERATIONS - 1);
+        if generation == GENERATIONS - 1 {
+            state.long_lived_total.set(survivors.len());
+            state.long_lived_pending.set(0);
+        } else if older == GENERATIONS - 1 {
+            let pending = &state.long_lived_pending;
+            pending.set(pending.get() + survivors.len());
+        }
+        state.generations[older].borrow_mut().extend(survivors);
+        let mut pending = state.pending.borrow_mut();
+        let mut with_del = state.with_del[older].borrow_mut();
+        for obj in waiting_for_del {
+            if to_finalize.contains(&address(&obj)) {
+                pending.push_back(Finalizer::Del(obj));
+                state.finalizers_pending.set(true);
+            } else {
+                with_del.push(obj);
+            }
+        }
+        drop(with_del);
+        schedule_del_check(state);
+        state.collecting.set(false);
+    });
+    collected
+}
+
+/// Call `weakref`'s callback once `referent` is freed.
+pub fn add_weakref_callback(referent: &PyObjectRef, weakref: &PyObjectRef) {
+    STATE.with(|state| {
+        let mut callbacks = state.weakref_callbacks.borrow_mut();
+        let weakrefs = callbacks.entry(address(referent) as usize).or_default();
+        weakrefs.retain(|weak| weak.upgrade().is_some());
+        weakrefs.push(Rc::downgrade(weakref));
+    })
+}
+
+/// Called when an object is being dropped, to queue its finalizers.
+pub fn dropping<T: ?Sized + PyObjectPayload>(obj: &PyObject<T>) {
+    let id = obj as *const PyObject<T> as *const u8 as usize;
+    // Nothing can be run any more once the thread is shutting down.
+    let _ = STATE.try_with(|state| {
+        let mut finalizers = vec![];
+        let weakrefs = match state.weakref_callbacks.try_borrow_mut() {
+            Ok(mut callbacks) if !callbacks.is_empty() => callbacks.remove(&id),
+            _ => None,
+        };
+        for weakref in weakrefs.iter().flatten().filter_map(Weak::upgrade) {
+            let callback = weakref.payload::<PyWeak>().and_then(PyWeak::callback);
+            if let Some(callback) = callback {
+                finalizers.push(Finalizer::Callback { callback, weakref });
+            }
+        }
+
+        if !finalizers.is_empty() {
+            if let Ok(mut pending) = state.pending.try_borrow_mut() {
+                pending.extend(finalizers);
+                state.finalizers_pending.set(true);
+            }
+        }
+    });
+}
+
+/// The number of objects waiting for `__del__` which can be checked at
+/// every safe point. With more of them, they are checked after as many safe
+/// points as there are multiples of this number.
+const WITH_DEL_PER_CHECK: usize = 64;
+
+/// Whether objects freed since the last call to `run_finalizers` have
+/// finalizers to run, or the objects waiting for `__del__` are due to be
+/// checked. Called at every safe point.
+#[inline]
+pub fn finalizers_pending() -> bool {
+    STATE.with(|state| {
+        state.finalizers_pending.get()
+            || match state.del_check_in.get() {
+                0 => false,
+                1 => true,
+                check_in => {
+                    state.del_check_in.set(check_in - 1);
+                    false
+                }
+            }
+    })
+}
+
+fn schedule_del_check(state: &GcState) {
+    let waiting: usize = state
+        .with_del
+        .iter()
+        .map(|objects| objects.borrow().len())
+        .sum();
+    let check_in = if waiting == 0 {
+        0
+    } else {
+        1 + waiting / WITH_DEL_PER_CHECK
+    };
+    state.del_check_in.set(check_in);
+}
+
+/// Queue `__del__` for the objects which nothing else refers to any more,
+/// returning whether there were any.
+fn queue_unreferenced(state: &GcState) -> bool {
+    let mut pending = state.pending.borrow_mut();
+    let before = pending.len();
+    for with_del in &state.with_del {
+        with_del.borrow_mut().retain(|obj| {
+            let unreferenced = Rc::strong_count(obj) == 1;
+            if unreferenced {
+                pending.push_back(Finalizer::Del(obj.clone()));
+            }
+            !unreferenced
+        });
+    }
+    schedule_del_check(state);
+    pending.len() > before
+}
+
+/// Run the `__del__` methods and weak reference callbacks of the objects
+/// freed so far. Exceptions raised by them are printed and ignored.
+pub fn run_finalizers(vm: &VirtualMachine) -> bool {
+    STATE.with(|state| state.finalizers_pending.set(false));
+    let mut ran = false;
+    loop {
+        // Finalizers can let go of more objects waiting for `__del__`.
+        let finalizer = STATE.with(|state| {
+            let next = state.pending.borrow_mut().pop_front();
+            next.or_else(|| {
+                if queue_unreferenced(state) {
+                    state.pending.borrow_mut().pop_front()
+                } else {
+                    None
+                }
+            })
+        });
+        let (func, args) = match finalizer {
+            Some(Finalizer::Del(obj)) => match objtype::class_get_attr(&obj.class(), "__del__") {
+                Some(func) => (func, vec![obj]),
+                None => continue,
+            },
+            Some(Finalizer::Callback { callback, weakref }) => (callback, vec![weakref]),
+            None => break,
+        };
+        ran = true;
+        if let Err(exc) = vm.invoke(func.clone(), args) {
+            let repr = vm
+                .to_repr(&func)
+                .map(|repr| repr.value.clone())
+                .unwrap_or_else(|_| "<object repr() failed>".to_string());
+            eprintln!("Exception ignored in: {}", repr);
+            for line in exceptions::format_exception(vm, &exc) {
+                eprint!("{}", line);
+            }
+        }
+    }
+    ran
+}
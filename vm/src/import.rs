@@ -3,11 +3,17 @@
  */
 
 use std::error::Error;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
-use crate::compile;
+use crate::archive::{self, Archive};
+use crate::bytecode::{CodeObject, Constant, Instruction};
+use crate::compile::{self, CompileOptions};
 use crate::frame::Scope;
-use crate::obj::{objsequence, objstr};
+use crate::marshal;
+use crate::obj::objcode::PyCodeRef;
+use crate::obj::{objbool, objsequence, objstr};
 use crate::pyobject::{DictProtocol, PyResult};
 use crate::util;
 use crate::vm::VirtualMachine;
@@ -19,31 +25,48 @@ fn import_uncached_module(vm: &VirtualMachine, current_path: PathBuf, module: &s
     }
 
     let notfound_error = vm.context().exceptions.module_not_found_error.clone();
-    let import_error = vm.context().exceptions.import_error.clone();
 
     // Time to search for module in any place:
-    let file_path = find_source(vm, current_path, module)
-        .map_err(|e| vm.new_exception(notfound_error.clone(), e))?;
-    let source = util::read_file(file_path.as_path())
-        .map_err(|e| vm.new_exception(import_error.clone(), e.description().to_string()))?;
-    let code_obj = compile::compile(
-        vm,
-        &source,
-        &compile::Mode::Exec,
-        file_path.to_str().unwrap().to_string(),
-    )
-    .map_err(|err| {
-        let syntax_error = vm.context().exceptions.syntax_error.clone();
-        vm.new_exception(syntax_error, err.description().to_string())
-    })?;
+    let code_obj = match find_module(vm, current_path, module)
+        .map_err(|e| vm.new_exception(notfound_error.clone(), e))?
+    {
+        ModuleLocation::Source(file_path) => load_source(vm, &file_path)?,
+        ModuleLocation::Archived(code) => vm.ctx.new_code_object(code),
+    };
     // trace!("Code object: {:?}", code_obj);
 
     let attrs = vm.ctx.new_dict();
     attrs.set_item(&vm.ctx, "__name__", vm.new_str(module.to_string()));
+    attrs.set_item(&vm.ctx, "__doc__", vm.get_none());
     vm.run_code_obj(code_obj, Scope::new(None, attrs.clone()))?;
     Ok(vm.ctx.new_module(module, attrs))
 }
 
+/// Compile the module at `file_path`, or load it from its cache.
+fn load_source(vm: &VirtualMachine, file_path: &Path) -> PyResult<PyCodeRef> {
+    let import_error = vm.context().exceptions.import_error.clone();
+    let source_path = file_path.to_str().unwrap().to_string();
+    let stamp = source_stamp(file_path);
+    let options = vm.compile_options.get();
+    if let Some(mut code) = stamp
+        .as_ref()
+        .and_then(|stamp| read_cache(file_path, stamp, options))
+    {
+        set_source_path(&mut code, &source_path);
+        return Ok(vm.ctx.new_code_object(code));
+    }
+    let source = util::read_file(file_path)
+        .map_err(|e| vm.new_exception(import_error, e.description().to_string()))?;
+    let code_obj = compile::compile(vm, &source, &compile::Mode::Exec, source_path)
+        .map_err(|err| vm.new_syntax_error(&err))?;
+    if let Some(stamp) = stamp {
+        if !dont_write_bytecode(vm) {
+            write_cache(file_path, &stamp, options, &code_obj.code);
+        }
+    }
+    Ok(code_obj)
+}
+
 pub fn import_module(vm: &VirtualMachine, current_path: PathBuf, module_name: &str) -> PyResult {
     // First, see if we already loaded the module:
     let sys_modules = vm.get_attribute(vm.sys_module.clone(), "modules")?;
@@ -55,7 +78,129 @@ pub fn import_module(vm: &VirtualMachine, current_path: PathBuf, module_name: &s
     Ok(module)
 }
 
-fn find_source(vm: &VirtualMachine, current_path: PathBuf, name: &str) -> Result<PathBuf, String> {
+/// Compiled modules are cached next to their source, in a file starting
+/// with this magic, the modification time and size of the source and the
+/// options it was compiled with.
+const CACHE_MAGIC: &[u8] = b"RPYC";
+
+struct SourceStamp {
+    secs: u64,
+    nanos: u32,
+    size: u64,
+}
+
+/// Each set of options gets its own cache file, like `.opt-1.pyc` files in
+/// CPython, so that switching between them doesn't throw the cache away.
+fn cache_path(source: &Path, options: CompileOptions) -> PathBuf {
+    let mut extension = String::new();
+    if options.optimize > 0 {
+        extension.push_str(&format!("opt-{}.", options.optimize));
+    }
+    if !options.peephole {
+        extension.push_str("no-peephole.");
+    }
+    extension.push_str("rpyc");
+    source.with_extension(extension)
+}
+
+fn source_stamp(source: &Path) -> Option<SourceStamp> {
+    let metadata = fs::metadata(source).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(SourceStamp {
+        secs: modified.as_secs(),
+        nanos: modified.subsec_nanos(),
+        size: metadata.len(),
+    })
+}
+
+fn cache_header(stamp: &SourceStamp, options: CompileOptions) -> Vec<u8> {
+    let mut writer = marshal::Writer::new();
+    for &byte in CACHE_MAGIC {
+        writer.write_u8(byte);
+    }
+    writer.write_u64(stamp.secs);
+    writer.write_u32(stamp.nanos);
+    writer.write_u64(stamp.size);
+    writer.write_bool(options.peephole);
+    writer.write_u8(options.optimize);
+    writer.into_bytes()
+}
+
+/// Load the cached code of `source`, if it was compiled from its current contents.
+fn read_cache(source: &Path, stamp: &SourceStamp, options: CompileOptions) -> Option<CodeObject> {
+    let data = fs::read(cache_path(source, options)).ok()?;
+    let header = cache_header(stamp, options);
+    if !data.starts_with(&header) {
+        return None;
+    }
+    marshal::deserialize_code(&data[header.len()..]).ok()
+}
+
+// Failing to write the cache only means the module gets compiled again next time.
+fn write_cache(source: &Path, stamp: &SourceStamp, options: CompileOptions, code: &CodeObject) {
+    let mut data = cache_header(stamp, options);
+    data.extend(marshal::serialize_code(code));
+    if let Err(err) = fs::write(cache_path(source, options), data) {
+        info!("Could not write bytecode cache for {:?}: {}", source, err);
+    }
+}
+
+fn dont_write_bytecode(vm: &VirtualMachine) -> bool {
+    vm.get_attribute(vm.sys_module.clone(), "dont_write_bytecode")
+        .map(|value| objbool::boolval(vm, value).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+/// The module may have been found through another path than the one it was cached from.
+fn set_source_path(code: &mut CodeObject, source_path: &str) {
+    code.source_path = source_path.to_string();
+    for instruction in code.instructions.iter_mut() {
+        if let Instruction::LoadConst { value } = instruction {
+            set_constant_source_path(value, source_path);
+        }
+    }
+}
+
+fn set_constant_source_path(constant: &mut Constant, source_path: &str) {
+    match constant {
+        Constant::Code { code } => set_source_path(code, source_path),
+        Constant::Tuple { elements } => {
+            for element in elements.iter_mut() {
+                set_constant_source_path(element, source_path);
+            }
+        }
+        _ => {}
+    }
+}
+
+enum ModuleLocation {
+    Source(PathBuf),
+    Archived(CodeObject),
+}
+
+/// Look up the module `name` in the archive at `path`, loading the archive the first time.
+fn find_archived(vm: &VirtualMachine, path: &Path, name: &str) -> Option<CodeObject> {
+    let mut archives = vm.archives.borrow_mut();
+    if !archives.contains_key(path) {
+        if !path.is_file() || !archive::is_archive(path) {
+            return None;
+        }
+        match Archive::load(path) {
+            Ok(loaded) => archives.insert(path.to_path_buf(), loaded),
+            Err(err) => {
+                info!("Could not load archive {:?}: {}", path, err);
+                return None;
+            }
+        };
+    }
+    archives[path].get(name).cloned()
+}
+
+fn find_module(
+    vm: &VirtualMachine,
+    current_path: PathBuf,
+    name: &str,
+) -> Result<ModuleLocation, String> {
     let sys_path = vm.get_attribute(vm.sys_module.clone(), "path").unwrap();
     let mut paths: Vec<PathBuf> = objsequence::get_elements(&sys_path)
         .iter()
@@ -65,17 +210,18 @@ fn find_source(vm: &VirtualMachine, current_path: PathBuf, name: &str) -> Result
     paths.insert(0, current_path);
 
     let suffixes = [".py", "/__init__.py"];
-    let mut file_paths = vec![];
     for path in paths {
+        if let Some(code) = find_archived(vm, &path, name) {
+            return Ok(ModuleLocation::Archived(code));
+        }
         for suffix in suffixes.iter() {
             let mut file_path = path.clone();
             file_path.push(format!("{}{}", name, suffix));
-            file_paths.push(file_path);
+            if file_path.exists() {
+                return Ok(ModuleLocation::Source(file_path));
+            }
         }
     }
 
-    match file_paths.iter().find(|p| p.exists()) {
-        Some(path) => Ok(path.to_path_buf()),
-        None => Err(format!("No module named '{}'", name)),
-    }
+    Err(format!("No module named '{}'", name))
 }
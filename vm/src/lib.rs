@@ -39,24 +39,33 @@ extern crate rustpython_derive;
 #[macro_use]
 pub mod macros;
 
+pub mod archive;
 mod builtins;
 pub mod bytecode;
+pub mod cformat;
 pub mod compile;
+pub mod encodings;
 pub mod error;
 pub mod eval;
 mod exceptions;
 pub mod format;
 pub mod frame;
 pub mod function;
+pub mod gc;
 pub mod import;
+pub mod marshal;
 pub mod obj;
+mod peephole;
+pub mod pyhash;
 pub mod pyobject;
+mod stack;
 pub mod stdlib;
+pub mod symboltable;
 mod sysmodule;
-mod traceback;
+pub mod traceback;
 pub mod util;
 mod vm;
 
 // pub use self::pyobject::Executor;
 pub use self::exceptions::print_exception;
-pub use self::vm::VirtualMachine;
+pub use self::vm::{trigger_keyboard_interrupt, VirtualMachine};
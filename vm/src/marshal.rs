@@ -0,0 +1,914 @@
+//! Binary serialization of code objects and constants.
+//!
+//! Every value starts with a one byte tag, followed by its contents.
+//! Integers are little endian, lengths are written as `u64`, and strings
+//! and bytes are written as a length followed by their bytes. The tags of
+//! constants are shared with the python `marshal` module, which adds the
+//! mutable containers.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use rustpython_parser::ast;
+
+use crate::bytecode::{
+    BinaryOperator, CallType, CodeObject, ComparisonOperator, Constant, FunctionOpArg, Instruction,
+    NameScope, UnaryOperator, Varargs,
+};
+
+/// The version of the format, which changes whenever the bytecode does.
+pub const FORMAT_VERSION: u32 = 1;
+
+pub const TAG_NONE: u8 = b'N';
+pub const TAG_TRUE: u8 = b'T';
+pub const TAG_FALSE: u8 = b'F';
+pub const TAG_ELLIPSIS: u8 = b'.';
+pub const TAG_INT: u8 = b'i';
+pub const TAG_FLOAT: u8 = b'g';
+pub const TAG_COMPLEX: u8 = b'y';
+pub const TAG_STR: u8 = b'u';
+pub const TAG_BYTES: u8 = b's';
+pub const TAG_TUPLE: u8 = b'(';
+pub const TAG_LIST: u8 = b'[';
+pub const TAG_DICT: u8 = b'{';
+pub const TAG_SET: u8 = b'<';
+pub const TAG_FROZENSET: u8 = b'>';
+pub const TAG_CODE: u8 = b'c';
+
+#[derive(Debug, PartialEq)]
+pub enum MarshalError {
+    /// The data ended in the middle of a value.
+    Eof,
+    /// A tag which doesn't start any value, with where it was found.
+    InvalidTag(u8, usize),
+    InvalidUtf8,
+    /// The data was written by another version of the format.
+    BadVersion(u32),
+}
+
+impl fmt::Display for MarshalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarshalError::Eof => write!(f, "marshal data too short"),
+            MarshalError::InvalidTag(tag, position) => write!(
+                f,
+                "bad marshal data (unknown type code {:#x} at {})",
+                tag, position
+            ),
+            MarshalError::InvalidUtf8 => write!(f, "bad marshal data (invalid utf-8)"),
+            MarshalError::BadVersion(version) => {
+                write!(f, "bad marshal data (format version {})", version)
+            }
+        }
+    }
+}
+
+impl Error for MarshalError {}
+
+pub type MarshalResult<T> = Result<T, MarshalError>;
+
+#[derive(Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Writer { buf: vec![] }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_bytes(&mut self, value: &[u8]) {
+        self.write_usize(value.len());
+        self.buf.extend_from_slice(value);
+    }
+
+    pub fn write_str(&mut self, value: &str) {
+        self.write_bytes(value.as_bytes());
+    }
+
+    pub fn write_int(&mut self, value: &BigInt) {
+        self.write_bytes(&value.to_signed_bytes_le());
+    }
+
+    pub fn write_float(&mut self, value: f64) {
+        self.write_u64(value.to_bits());
+    }
+
+    fn write_strs(&mut self, values: &[String]) {
+        self.write_usize(values.len());
+        for value in values {
+            self.write_str(value);
+        }
+    }
+
+    fn write_option_str(&mut self, value: &Option<String>) {
+        match value {
+            Some(value) => {
+                self.write_bool(true);
+                self.write_str(value);
+            }
+            None => self.write_bool(false),
+        }
+    }
+
+    fn write_varargs(&mut self, varargs: &Varargs) {
+        match varargs {
+            Varargs::None => self.write_u8(0),
+            Varargs::Unnamed => self.write_u8(1),
+            Varargs::Named(name) => {
+                self.write_u8(2);
+                self.write_str(name);
+            }
+        }
+    }
+
+    pub fn write_constant(&mut self, constant: &Constant) {
+        match constant {
+            Constant::Integer { value } => {
+                self.write_u8(TAG_INT);
+                self.write_int(value);
+            }
+            Constant::Float { value } => {
+                self.write_u8(TAG_FLOAT);
+                self.write_float(*value);
+            }
+            Constant::Complex { value } => {
+                self.write_u8(TAG_COMPLEX);
+                self.write_float(value.re);
+                self.write_float(value.im);
+            }
+            Constant::Boolean { value } => {
+                self.write_u8(if *value { TAG_TRUE } else { TAG_FALSE });
+            }
+            Constant::String { value } => {
+                self.write_u8(TAG_STR);
+                self.write_str(value);
+            }
+            Constant::Bytes { value } => {
+                self.write_u8(TAG_BYTES);
+                self.write_bytes(value);
+            }
+            Constant::Code { code } => {
+                self.write_u8(TAG_CODE);
+                self.write_code(code);
+            }
+            Constant::Tuple { elements } => {
+                self.write_u8(TAG_TUPLE);
+                self.write_usize(elements.len());
+                for element in elements {
+                    self.write_constant(element);
+                }
+            }
+            Constant::None => self.write_u8(TAG_NONE),
+            Constant::Ellipsis => self.write_u8(TAG_ELLIPSIS),
+        }
+    }
+
+    /// Write the contents of a code object, without a tag.
+    pub fn write_code(&mut self, code: &CodeObject) {
+        self.write_usize(code.instructions.len());
+        for instruction in &code.instructions {
+            self.write_instruction(instruction);
+        }
+        // Sorted, so that the same code is always written the same way.
+        let mut labels: Vec<_> = code.label_map.iter().collect();
+        labels.sort();
+        self.write_usize(labels.len());
+        for (label, offset) in labels {
+            self.write_usize(*label);
+            self.write_usize(*offset);
+        }
+        self.write_usize(code.locations.len());
+        for location in &code.locations {
+            self.write_usize(location.get_row());
+            self.write_usize(location.get_column());
+        }
+        self.write_strs(&code.arg_names);
+        self.write_varargs(&code.varargs);
+        self.write_strs(&code.kwonlyarg_names);
+        self.write_varargs(&code.varkeywords);
+        self.write_strs(&code.varnames);
+        self.write_strs(&code.cellvars);
+        self.write_strs(&code.freevars);
+        self.write_str(&code.source_path);
+        self.write_usize(code.first_line_number);
+        self.write_str(&code.obj_name);
+        self.write_bool(code.is_generator);
+        self.write_bool(code.is_coroutine);
+    }
+
+    fn write_instruction(&mut self, instruction: &Instruction) {
+        use Instruction::*;
+        match instruction {
+            Import { name, symbol } => {
+                self.write_u8(0);
+                self.write_str(name);
+                self.write_option_str(symbol);
+            }
+            ImportStar { name } => {
+                self.write_u8(1);
+                self.write_str(name);
+            }
+            LoadName { name, scope } => {
+                self.write_u8(2);
+                self.write_str(name);
+                self.write_name_scope(scope);
+            }
+            StoreName { name, scope } => {
+                self.write_u8(3);
+                self.write_str(name);
+                self.write_name_scope(scope);
+            }
+            DeleteName { name, scope } => {
+                self.write_u8(4);
+                self.write_str(name);
+                self.write_name_scope(scope);
+            }
+            LoadFast { idx } => self.write_op_usize(5, *idx),
+            StoreFast { idx } => self.write_op_usize(6, *idx),
+            DeleteFast { idx } => self.write_op_usize(7, *idx),
+            LoadDeref { idx } => self.write_op_usize(8, *idx),
+            StoreDeref { idx } => self.write_op_usize(9, *idx),
+            DeleteDeref { idx } => self.write_op_usize(10, *idx),
+            LoadClosure { idx } => self.write_op_usize(11, *idx),
+            StoreSubscript => self.write_u8(12),
+            DeleteSubscript => self.write_u8(13),
+            StoreAttr { name } => {
+                self.write_u8(14);
+                self.write_str(name);
+            }
+            DeleteAttr { name } => {
+                self.write_u8(15);
+                self.write_str(name);
+            }
+            LoadConst { value } => {
+                self.write_u8(16);
+                self.write_constant(value);
+            }
+            UnaryOperation { op } => {
+                self.write_u8(17);
+                self.write_u8(match op {
+                    UnaryOperator::Not => 0,
+                    UnaryOperator::Invert => 1,
+                    UnaryOperator::Minus => 2,
+                    UnaryOperator::Plus => 3,
+                });
+            }
+            BinaryOperation { op, inplace } => {
+                self.write_u8(18);
+                self.write_u8(match op {
+                    BinaryOperator::Power => 0,
+                    BinaryOperator::Multiply => 1,
+                    BinaryOperator::MatrixMultiply => 2,
+                    BinaryOperator::Divide => 3,
+                    BinaryOperator::FloorDivide => 4,
+                    BinaryOperator::Modulo => 5,
+                    BinaryOperator::Add => 6,
+                    BinaryOperator::Subtract => 7,
+                    BinaryOperator::Subscript => 8,
+                    BinaryOperator::Lshift => 9,
+                    BinaryOperator::Rshift => 10,
+                    BinaryOperator::And => 11,
+                    BinaryOperator::Xor => 12,
+                    BinaryOperator::Or => 13,
+                });
+                self.write_bool(*inplace);
+            }
+            LoadAttr { name } => {
+                self.write_u8(19);
+                self.write_str(name);
+            }
+            CompareOperation { op } => {
+                self.write_u8(20);
+                self.write_u8(match op {
+                    ComparisonOperator::Greater => 0,
+                    ComparisonOperator::GreaterOrEqual => 1,
+                    ComparisonOperator::Less => 2,
+                    ComparisonOperator::LessOrEqual => 3,
+                    ComparisonOperator::Equal => 4,
+                    ComparisonOperator::NotEqual => 5,
+                    ComparisonOperator::In => 6,
+                    ComparisonOperator::NotIn => 7,
+                    ComparisonOperator::Is => 8,
+                    ComparisonOperator::IsNot => 9,
+                });
+            }
+            Pop => self.write_u8(21),
+            Rotate { amount } => self.write_op_usize(22, *amount),
+            Duplicate => self.write_u8(23),
+            GetIter => self.write_u8(24),
+            Pass => self.write_u8(25),
+            Continue => self.write_u8(26),
+            Break => self.write_u8(27),
+            Jump { target } => self.write_op_usize(28, *target),
+            JumpIf { target } => self.write_op_usize(29, *target),
+            JumpIfFalse { target } => self.write_op_usize(30, *target),
+            MakeFunction { flags } => {
+                self.write_u8(31);
+                self.write_u8(flags.bits());
+            }
+            CallFunction { typ } => {
+                self.write_u8(32);
+                match typ {
+                    CallType::Positional(count) => self.write_op_usize(0, *count),
+                    CallType::Keyword(count) => self.write_op_usize(1, *count),
+                    CallType::Ex(has_kwargs) => {
+                        self.write_u8(2);
+                        self.write_bool(*has_kwargs);
+                    }
+                }
+            }
+            ForIter { target } => self.write_op_usize(33, *target),
+            ReturnValue => self.write_u8(34),
+            YieldValue => self.write_u8(35),
+            YieldFrom => self.write_u8(36),
+            GetAwaitable => self.write_u8(37),
+            GetAIter => self.write_u8(38),
+            GetANext => self.write_u8(39),
+            EndAsyncFor => self.write_u8(40),
+            SetupLoop { start, end } => {
+                self.write_op_usize(41, *start);
+                self.write_usize(*end);
+            }
+            SetupExcept { handler } => self.write_op_usize(42, *handler),
+            SetupFinally { handler } => self.write_op_usize(43, *handler),
+            PopException => self.write_u8(44),
+            EnterFinally => self.write_u8(45),
+            EndFinally => self.write_u8(46),
+            SetupWith { end } => self.write_op_usize(47, *end),
+            CleanupWith { end } => self.write_op_usize(48, *end),
+            BeforeAsyncWith => self.write_u8(49),
+            SetupAsyncWith { handler } => self.write_op_usize(50, *handler),
+            WithCleanupStart => self.write_u8(51),
+            WithCleanupFinish => self.write_u8(52),
+            PopBlock => self.write_u8(53),
+            Raise { argc } => self.write_op_usize(54, *argc),
+            BuildString { size } => self.write_op_usize(55, *size),
+            BuildTuple { size, unpack } => self.write_build(56, *size, *unpack),
+            BuildList { size, unpack } => self.write_build(57, *size, *unpack),
+            BuildSet { size, unpack } => self.write_build(58, *size, *unpack),
+            BuildMap { size, unpack } => self.write_build(59, *size, *unpack),
+            BuildSlice { size } => self.write_op_usize(60, *size),
+            ListAppend { i } => self.write_op_usize(61, *i),
+            SetAdd { i } => self.write_op_usize(62, *i),
+            MapAdd { i } => self.write_op_usize(63, *i),
+            PrintExpr => self.write_u8(64),
+            LoadBuildClass => self.write_u8(65),
+            UnpackSequence { size } => self.write_op_usize(66, *size),
+            UnpackEx { before, after } => {
+                self.write_op_usize(67, *before);
+                self.write_usize(*after);
+            }
+            Unpack => self.write_u8(68),
+            FormatValue { conversion, spec } => {
+                self.write_u8(69);
+                self.write_u8(match conversion {
+                    None => 0,
+                    Some(ast::ConversionFlag::Str) => 1,
+                    Some(ast::ConversionFlag::Ascii) => 2,
+                    Some(ast::ConversionFlag::Repr) => 3,
+                });
+                self.write_str(spec);
+            }
+        }
+    }
+
+    fn write_op_usize(&mut self, op: u8, value: usize) {
+        self.write_u8(op);
+        self.write_usize(value);
+    }
+
+    fn write_build(&mut self, op: u8, size: usize, unpack: bool) {
+        self.write_op_usize(op, size);
+        self.write_bool(unpack);
+    }
+
+    fn write_name_scope(&mut self, scope: &NameScope) {
+        self.write_u8(match scope {
+            NameScope::Local => 0,
+            NameScope::Global => 1,
+        });
+    }
+}
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Reader { data, position: 0 }
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position == self.data.len()
+    }
+
+    fn take(&mut self, count: usize) -> MarshalResult<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|&end| end <= self.data.len())
+            .ok_or(MarshalError::Eof)?;
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn invalid_tag(&self, tag: u8) -> MarshalError {
+        MarshalError::InvalidTag(tag, self.position - 1)
+    }
+
+    pub fn read_u8(&mut self) -> MarshalResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_u32(&mut self) -> MarshalResult<u32> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    pub fn read_u64(&mut self) -> MarshalResult<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    pub fn read_usize(&mut self) -> MarshalResult<usize> {
+        let value = self.read_u64()?;
+        // Anything this large can't be a valid length or index.
+        if value > self.data.len() as u64 * 8 + (1 << 32) {
+            Err(MarshalError::Eof)
+        } else {
+            Ok(value as usize)
+        }
+    }
+
+    pub fn read_bool(&mut self) -> MarshalResult<bool> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(self.invalid_tag(tag)),
+        }
+    }
+
+    pub fn read_bytes(&mut self) -> MarshalResult<&'a [u8]> {
+        let len = self.read_usize()?;
+        self.take(len)
+    }
+
+    pub fn read_str(&mut self) -> MarshalResult<String> {
+        let bytes = self.read_bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| MarshalError::InvalidUtf8)
+    }
+
+    pub fn read_int(&mut self) -> MarshalResult<BigInt> {
+        Ok(BigInt::from_signed_bytes_le(self.read_bytes()?))
+    }
+
+    pub fn read_float(&mut self) -> MarshalResult<f64> {
+        Ok(f64::from_bits(self.read_u64()?))
+    }
+
+    fn read_strs(&mut self) -> MarshalResult<Vec<String>> {
+        let len = self.read_usize()?;
+        (0..len).map(|_| self.read_str()).collect()
+    }
+
+    fn read_option_str(&mut self) -> MarshalResult<Option<String>> {
+        if self.read_bool()? {
+            Ok(Some(self.read_str()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read_varargs(&mut self) -> MarshalResult<Varargs> {
+        match self.read_u8()? {
+            0 => Ok(Varargs::None),
+            1 => Ok(Varargs::Unnamed),
+            2 => Ok(Varargs::Named(self.read_str()?)),
+            tag => Err(self.invalid_tag(tag)),
+        }
+    }
+
+    pub fn read_constant(&mut self) -> MarshalResult<Constant> {
+        let tag = self.read_u8()?;
+        self.read_constant_with_tag(tag)
+    }
+
+    /// Read the constant starting with `tag`, which has been read already.
+    pub fn read_constant_with_tag(&mut self, tag: u8) -> MarshalResult<Constant> {
+        Ok(match tag {
+            TAG_INT => Constant::Integer {
+                value: self.read_int()?,
+            },
+            TAG_FLOAT => Constant::Float {
+                value: self.read_float()?,
+            },
+            TAG_COMPLEX => Constant::Complex {
+                value: Complex64::new(self.read_float()?, self.read_float()?),
+            },
+            TAG_TRUE => Constant::Boolean { value: true },
+            TAG_FALSE => Constant::Boolean { value: false },
+            TAG_STR => Constant::String {
+                value: self.read_str()?,
+            },
+            TAG_BYTES => Constant::Bytes {
+                value: self.read_bytes()?.to_vec(),
+            },
+            TAG_CODE => Constant::Code {
+                code: Box::new(self.read_code()?),
+            },
+            TAG_TUPLE => {
+                let len = self.read_usize()?;
+                Constant::Tuple {
+                    elements: (0..len)
+                        .map(|_| self.read_constant())
+                        .collect::<MarshalResult<_>>()?,
+                }
+            }
+            TAG_NONE => Constant::None,
+            TAG_ELLIPSIS => Constant::Ellipsis,
+            tag => return Err(self.invalid_tag(tag)),
+        })
+    }
+
+    /// Read the contents of a code object, written by `Writer::write_code`.
+    pub fn read_code(&mut self) -> MarshalResult<CodeObject> {
+        let len = self.read_usize()?;
+        let instructions = (0..len)
+            .map(|_| self.read_instruction())
+            .collect::<MarshalResult<_>>()?;
+        let len = self.read_usize()?;
+        let mut label_map = HashMap::new();
+        for _ in 0..len {
+            let label = self.read_usize()?;
+            label_map.insert(label, self.read_usize()?);
+        }
+        let len = self.read_usize()?;
+        let locations = (0..len)
+            .map(|_| Ok(ast::Location::new(self.read_usize()?, self.read_usize()?)))
+            .collect::<MarshalResult<_>>()?;
+        Ok(CodeObject {
+            instructions,
+            label_map,
+            locations,
+            arg_names: self.read_strs()?,
+            varargs: self.read_varargs()?,
+            kwonlyarg_names: self.read_strs()?,
+            varkeywords: self.read_varargs()?,
+            varnames: self.read_strs()?,
+            cellvars: self.read_strs()?,
+            freevars: self.read_strs()?,
+            source_path: self.read_str()?,
+            first_line_number: self.read_usize()?,
+            obj_name: self.read_str()?,
+            is_generator: self.read_bool()?,
+            is_coroutine: self.read_bool()?,
+        })
+    }
+
+    fn read_instruction(&mut self) -> MarshalResult<Instruction> {
+        use Instruction::*;
+        let op = self.read_u8()?;
+        Ok(match op {
+            0 => Import {
+                name: self.read_str()?,
+                symbol: self.read_option_str()?,
+            },
+            1 => ImportStar {
+                name: self.read_str()?,
+            },
+            2 => LoadName {
+                name: self.read_str()?,
+                scope: self.read_name_scope()?,
+            },
+            3 => StoreName {
+                name: self.read_str()?,
+                scope: self.read_name_scope()?,
+            },
+            4 => DeleteName {
+                name: self.read_str()?,
+                scope: self.read_name_scope()?,
+            },
+            5 => LoadFast {
+                idx: self.read_usize()?,
+            },
+            6 => StoreFast {
+                idx: self.read_usize()?,
+            },
+            7 => DeleteFast {
+                idx: self.read_usize()?,
+            },
+            8 => LoadDeref {
+                idx: self.read_usize()?,
+            },
+            9 => StoreDeref {
+                idx: self.read_usize()?,
+            },
+            10 => DeleteDeref {
+                idx: self.read_usize()?,
+            },
+            11 => LoadClosure {
+                idx: self.read_usize()?,
+            },
+            12 => StoreSubscript,
+            13 => DeleteSubscript,
+            14 => StoreAttr {
+                name: self.read_str()?,
+            },
+            15 => DeleteAttr {
+                name: self.read_str()?,
+            },
+            16 => LoadConst {
+                value: self.read_constant()?,
+            },
+            17 => UnaryOperation {
+                op: match self.read_u8()? {
+                    0 => UnaryOperator::Not,
+                    1 => UnaryOperator::Invert,
+                    2 => UnaryOperator::Minus,
+                    3 => UnaryOperator::Plus,
+                    tag => return Err(self.invalid_tag(tag)),
+                },
+            },
+            18 => BinaryOperation {
+                op: match self.read_u8()? {
+                    0 => BinaryOperator::Power,
+                    1 => BinaryOperator::Multiply,
+                    2 => BinaryOperator::MatrixMultiply,
+                    3 => BinaryOperator::Divide,
+                    4 => BinaryOperator::FloorDivide,
+                    5 => BinaryOperator::Modulo,
+                    6 => BinaryOperator::Add,
+                    7 => BinaryOperator::Subtract,
+                    8 => BinaryOperator::Subscript,
+                    9 => BinaryOperator::Lshift,
+                    10 => BinaryOperator::Rshift,
+                    11 => BinaryOperator::And,
+                    12 => BinaryOperator::Xor,
+                    13 => BinaryOperator::Or,
+                    tag => return Err(self.invalid_tag(tag)),
+                },
+                inplace: self.read_bool()?,
+            },
+            19 => LoadAttr {
+                name: self.read_str()?,
+            },
+            20 => CompareOperation {
+                op: match self.read_u8()? {
+                    0 => ComparisonOperator::Greater,
+                    1 => ComparisonOperator::GreaterOrEqual,
+                    2 => ComparisonOperator::Less,
+                    3 => ComparisonOperator::LessOrEqual,
+                    4 => ComparisonOperator::Equal,
+                    5 => ComparisonOperator::NotEqual,
+                    6 => ComparisonOperator::In,
+                    7 => ComparisonOperator::NotIn,
+                    8 => ComparisonOperator::Is,
+                    9 => ComparisonOperator::IsNot,
+                    tag => return Err(self.invalid_tag(tag)),
+                },
+            },
+            21 => Pop,
+            22 => Rotate {
+                amount: self.read_usize()?,
+            },
+            23 => Duplicate,
+            24 => GetIter,
+            25 => Pass,
+            26 => Continue,
+            27 => Break,
+            28 => Jump {
+                target: self.read_usize()?,
+            },
+            29 => JumpIf {
+                target: self.read_usize()?,
+            },
+            30 => JumpIfFalse {
+                target: self.read_usize()?,
+            },
+            31 => {
+                let bits = self.read_u8()?;
+                MakeFunction {
+                    flags: FunctionOpArg::from_bits(bits).ok_or_else(|| self.invalid_tag(bits))?,
+                }
+            }
+            32 => CallFunction {
+                typ: match self.read_u8()? {
+                    0 => CallType::Positional(self.read_usize()?),
+                    1 => CallType::Keyword(self.read_usize()?),
+                    2 => CallType::Ex(self.read_bool()?),
+                    tag => return Err(self.invalid_tag(tag)),
+                },
+            },
+            33 => ForIter {
+                target: self.read_usize()?,
+            },
+            34 => ReturnValue,
+            35 => YieldValue,
+            36 => YieldFrom,
+            37 => GetAwaitable,
+            38 => GetAIter,
+            39 => GetANext,
+            40 => EndAsyncFor,
+            41 => SetupLoop {
+                start: self.read_usize()?,
+                end: self.read_usize()?,
+            },
+            42 => SetupExcept {
+                handler: self.read_usize()?,
+            },
+            43 => SetupFinally {
+                handler: self.read_usize()?,
+            },
+            44 => PopException,
+            45 => EnterFinally,
+            46 => EndFinally,
+            47 => SetupWith {
+                end: self.read_usize()?,
+            },
+            48 => CleanupWith {
+                end: self.read_usize()?,
+            },
+            49 => BeforeAsyncWith,
+            50 => SetupAsyncWith {
+                handler: self.read_usize()?,
+            },
+            51 => WithCleanupStart,
+            52 => WithCleanupFinish,
+            53 => PopBlock,
+            54 => Raise {
+                argc: self.read_usize()?,
+            },
+            55 => BuildString {
+                size: self.read_usize()?,
+            },
+            56 => BuildTuple {
+                size: self.read_usize()?,
+                unpack: self.read_bool()?,
+            },
+            57 => BuildList {
+                size: self.read_usize()?,
+                unpack: self.read_bool()?,
+            },
+            58 => BuildSet {
+                size: self.read_usize()?,
+                unpack: self.read_bool()?,
+            },
+            59 => BuildMap {
+                size: self.read_usize()?,
+                unpack: self.read_bool()?,
+            },
+            60 => BuildSlice {
+                size: self.read_usize()?,
+            },
+            61 => ListAppend {
+                i: self.read_usize()?,
+            },
+            62 => SetAdd {
+                i: self.read_usize()?,
+            },
+            63 => MapAdd {
+                i: self.read_usize()?,
+            },
+            64 => PrintExpr,
+            65 => LoadBuildClass,
+            66 => UnpackSequence {
+                size: self.read_usize()?,
+            },
+            67 => UnpackEx {
+                before: self.read_usize()?,
+                after: self.read_usize()?,
+            },
+            68 => Unpack,
+            69 => FormatValue {
+                conversion: match self.read_u8()? {
+                    0 => None,
+                    1 => Some(ast::ConversionFlag::Str),
+                    2 => Some(ast::ConversionFlag::Ascii),
+                    3 => Some(ast::ConversionFlag::Repr),
+                    tag => return Err(self.invalid_tag(tag)),
+                },
+                spec: self.read_str()?,
+            },
+            op => return Err(self.invalid_tag(op)),
+        })
+    }
+
+    fn read_name_scope(&mut self) -> MarshalResult<NameScope> {
+        match self.read_u8()? {
+            0 => Ok(NameScope::Local),
+            1 => Ok(NameScope::Global),
+            tag => Err(self.invalid_tag(tag)),
+        }
+    }
+}
+
+/// Serialize a code object, preceded by the format version.
+pub fn serialize_code(code: &CodeObject) -> Vec<u8> {
+    let mut writer = Writer::new();
+    writer.write_u32(FORMAT_VERSION);
+    writer.write_code(code);
+    writer.into_bytes()
+}
+
+/// Deserialize a code object written by `serialize_code`.
+pub fn deserialize_code(data: &[u8]) -> MarshalResult<CodeObject> {
+    let mut reader = Reader::new(data);
+    let version = reader.read_u32()?;
+    if version != FORMAT_VERSION {
+        return Err(MarshalError::BadVersion(version));
+    }
+    let code = reader.read_code()?;
+    if reader.is_at_end() {
+        Ok(code)
+    } else {
+        Err(MarshalError::InvalidTag(
+            data[reader.position],
+            reader.position,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize_code, serialize_code, MarshalError, FORMAT_VERSION};
+    use crate::compile;
+    use crate::vm::VirtualMachine;
+
+    fn compile_source(source: &str) -> crate::bytecode::CodeObject {
+        let vm = VirtualMachine::new();
+        let code =
+            compile::compile(&vm, source, &compile::Mode::Exec, "<test>".to_string()).unwrap();
+        code.code.clone()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let code = compile_source(
+            "import sys\n\
+             def f(a, *args, b=2, **kwargs):\n    \
+                 x = [i ** 2 for i in range(a)]\n    \
+                 return (x, 1.5, 2j, b'bytes', None, ..., f'{a!r:>4}')\n\
+             class C:\n    \
+                 async def g(self):\n        \
+                     async with self as s:\n            \
+                         await s\n\
+             try:\n    \
+                 f(3)\n\
+             except Exception as e:\n    \
+                 raise ValueError from e\n\
+             finally:\n    \
+                 del sys\n",
+        );
+        let data = serialize_code(&code);
+        assert_eq!(deserialize_code(&data), Ok(code));
+    }
+
+    #[test]
+    fn test_bad_data() {
+        let code = compile_source("x = 1\n");
+        let data = serialize_code(&code);
+        assert_eq!(
+            deserialize_code(&data[..data.len() - 1]),
+            Err(MarshalError::Eof)
+        );
+        let mut other_version = data.clone();
+        other_version[0] = FORMAT_VERSION as u8 + 1;
+        assert_eq!(
+            deserialize_code(&other_version),
+            Err(MarshalError::BadVersion(FORMAT_VERSION + 1))
+        );
+    }
+}
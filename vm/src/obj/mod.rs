@@ -1,12 +1,16 @@
 //! This package contains the python basic/builtin types
 
+pub mod objasyncgenerator;
 pub mod objbool;
 pub mod objbuiltinfunc;
 pub mod objbytearray;
+pub mod objbyteinner;
 pub mod objbytes;
+pub mod objcell;
 pub mod objclassmethod;
 pub mod objcode;
 pub mod objcomplex;
+pub mod objcoroutine;
 pub mod objdict;
 pub mod objellipsis;
 pub mod objenumerate;
@@ -0,0 +1,243 @@
+/*
+ * Asynchronous generators, as created by calling an `async def` function
+ * which contains `yield`.
+ */
+
+use std::cell::Cell;
+
+use crate::frame::FrameRef;
+use crate::function::OptionalArg;
+use crate::obj::objgenerator::{GeneratorCore, GeneratorKind};
+use crate::obj::objiter;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::vm::VirtualMachine;
+
+pub type PyAsyncGenRef = PyRef<PyAsyncGen>;
+
+#[derive(Debug)]
+pub struct PyAsyncGen {
+    inner: GeneratorCore,
+}
+
+impl PyValue for PyAsyncGen {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.async_generator_type()
+    }
+}
+
+impl PyAsyncGenRef {
+    pub fn new(frame: FrameRef, vm: &VirtualMachine) -> PyAsyncGenRef {
+        PyAsyncGen {
+            inner: GeneratorCore::new(frame, GeneratorKind::AsyncGenerator),
+        }
+        .into_ref(vm)
+    }
+
+    fn aiter(self, _vm: &VirtualMachine) -> PyAsyncGenRef {
+        self
+    }
+
+    fn anext(self, vm: &VirtualMachine) -> PyAsyncGenASendRef {
+        self.awaitable(AwaitMode::Send(vm.get_none()), vm)
+    }
+
+    fn asend(self, value: PyObjectRef, vm: &VirtualMachine) -> PyAsyncGenASendRef {
+        self.awaitable(AwaitMode::Send(value), vm)
+    }
+
+    fn athrow(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg,
+        exc_tb: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyAsyncGenASendRef {
+        let mode = AwaitMode::Throw(exc_type, exc_val.into_option(), exc_tb.into_option());
+        self.awaitable(mode, vm)
+    }
+
+    fn aclose(self, vm: &VirtualMachine) -> PyAsyncGenASendRef {
+        self.awaitable(AwaitMode::Close, vm)
+    }
+
+    fn awaitable(self, mode: AwaitMode, vm: &VirtualMachine) -> PyAsyncGenASendRef {
+        PyAsyncGenASend {
+            ag: self,
+            mode,
+            state: Cell::new(AwaitState::Init),
+        }
+        .into_ref(vm)
+    }
+
+    fn ag_running(self, _vm: &VirtualMachine) -> bool {
+        self.inner.running()
+    }
+
+    fn ag_frame(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.inner.frame_or_none(vm)
+    }
+}
+
+/// What awaiting a `PyAsyncGenASend` does with the async generator.
+#[derive(Debug)]
+enum AwaitMode {
+    Send(PyObjectRef),
+    Throw(PyObjectRef, Option<PyObjectRef>, Option<PyObjectRef>),
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AwaitState {
+    Init,
+    Iter,
+    Closed,
+}
+
+pub type PyAsyncGenASendRef = PyRef<PyAsyncGenASend>;
+
+/// The awaitable returned by `__anext__()`, `asend()`, `athrow()` and
+/// `aclose()`. It runs the async generator until its next `yield`, passing
+/// on anything the generator awaits in the meantime.
+#[derive(Debug)]
+pub struct PyAsyncGenASend {
+    ag: PyAsyncGenRef,
+    mode: AwaitMode,
+    state: Cell<AwaitState>,
+}
+
+impl PyValue for PyAsyncGenASend {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.async_generator_asend_type()
+    }
+}
+
+impl PyAsyncGenASendRef {
+    fn await_(self, _vm: &VirtualMachine) -> PyAsyncGenASendRef {
+        self
+    }
+
+    fn next(self, vm: &VirtualMachine) -> PyResult {
+        self.send(vm.get_none(), vm)
+    }
+
+    fn send(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        let core = &self.ag.inner;
+        let result = match self.state.get() {
+            AwaitState::Closed => return Err(self.reused(vm)),
+            AwaitState::Iter => core.send(value, vm),
+            AwaitState::Init => {
+                self.state.set(AwaitState::Iter);
+                match &self.mode {
+                    AwaitMode::Send(initial) => {
+                        let value = if value.is(&vm.get_none()) {
+                            initial.clone()
+                        } else {
+                            value
+                        };
+                        core.send(value, vm)
+                    }
+                    AwaitMode::Throw(exc_type, exc_val, exc_tb) => {
+                        core.throw(exc_type.clone(), exc_val.clone(), exc_tb.clone(), vm)
+                    }
+                    AwaitMode::Close => {
+                        let generator_exit = vm.ctx.exceptions.generator_exit.clone();
+                        core.throw(generator_exit.into_object(), None, None, vm)
+                    }
+                }
+            }
+        };
+        self.handle_result(result, vm)
+    }
+
+    fn throw(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg,
+        exc_tb: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        if self.state.get() == AwaitState::Closed {
+            return Err(self.reused(vm));
+        }
+        self.state.set(AwaitState::Iter);
+        let result = self
+            .ag
+            .inner
+            .throw(exc_type, exc_val.into_option(), exc_tb.into_option(), vm);
+        self.handle_result(result, vm)
+    }
+
+    fn close(self, _vm: &VirtualMachine) {
+        self.state.set(AwaitState::Closed);
+    }
+
+    /// Turn the result of resuming the async generator into the result of
+    /// this awaitable: a value yielded by something the generator awaits is
+    /// passed on, while one yielded by the generator itself finishes the
+    /// awaitable.
+    fn handle_result(&self, result: PyResult, vm: &VirtualMachine) -> PyResult {
+        let is_close = match self.mode {
+            AwaitMode::Close => true,
+            _ => false,
+        };
+        match result {
+            Ok(value) => {
+                if self.ag.inner.frame().is_delegating() {
+                    return Ok(value);
+                }
+                self.state.set(AwaitState::Closed);
+                if is_close {
+                    Err(vm.new_runtime_error("async generator ignored GeneratorExit".to_string()))
+                } else {
+                    Err(objiter::new_stop_iteration_with_value(vm, value)?)
+                }
+            }
+            Err(exception) => {
+                self.state.set(AwaitState::Closed);
+                let exceptions = &vm.ctx.exceptions;
+                if objtype::isinstance(&exception, &exceptions.stop_iteration) {
+                    if is_close {
+                        Err(objiter::new_stop_iteration(vm))
+                    } else {
+                        Err(vm.new_empty_exception(exceptions.stop_async_iteration.clone())?)
+                    }
+                } else if is_close
+                    && (objtype::isinstance(&exception, &exceptions.generator_exit)
+                        || objtype::isinstance(&exception, &exceptions.stop_async_iteration))
+                {
+                    Err(objiter::new_stop_iteration(vm))
+                } else {
+                    Err(exception)
+                }
+            }
+        }
+    }
+
+    fn reused(&self, vm: &VirtualMachine) -> PyObjectRef {
+        vm.new_runtime_error(
+            "cannot reuse already awaited __anext__()/asend()/athrow()/aclose()".to_string(),
+        )
+    }
+}
+
+pub fn init(context: &PyContext) {
+    extend_class!(context, &context.async_generator_type, {
+        "__aiter__" => context.new_rustfunc(PyAsyncGenRef::aiter),
+        "__anext__" => context.new_rustfunc(PyAsyncGenRef::anext),
+        "asend" => context.new_rustfunc(PyAsyncGenRef::asend),
+        "athrow" => context.new_rustfunc(PyAsyncGenRef::athrow),
+        "aclose" => context.new_rustfunc(PyAsyncGenRef::aclose),
+        "ag_frame" => context.new_property(PyAsyncGenRef::ag_frame),
+        "ag_running" => context.new_property(PyAsyncGenRef::ag_running)
+    });
+
+    extend_class!(context, &context.async_generator_asend_type, {
+        "__await__" => context.new_rustfunc(PyAsyncGenASendRef::await_),
+        "__iter__" => context.new_rustfunc(PyAsyncGenASendRef::await_),
+        "__next__" => context.new_rustfunc(PyAsyncGenASendRef::next),
+        "send" => context.new_rustfunc(PyAsyncGenASendRef::send),
+        "throw" => context.new_rustfunc(PyAsyncGenASendRef::throw),
+        "close" => context.new_rustfunc(PyAsyncGenASendRef::close)
+    });
+}
@@ -1,22 +1,33 @@
 //! Implementation of the python bytearray object.
 
-use std::cell::RefCell;
-use std::fmt::Write;
+use std::cell::{Cell, RefCell};
+use std::cmp;
 use std::ops::{Deref, DerefMut};
 
 use num_traits::ToPrimitive;
 
 use crate::function::OptionalArg;
-use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::pyobject::{
+    PyBuffer, PyContext, PyIteratorValue, PyObject, PyObjectRef, PyRef, PyResult, PyValue,
+    TypeProtocol,
+};
 use crate::vm::VirtualMachine;
 
-use super::objint;
-use super::objtype::PyClassRef;
+use super::objbyteinner::{self, ByteSequence};
+use super::objbytes::{ByteStringArgs, DecodeArgs};
+use super::objclassmethod::PyClassMethod;
+use super::objint::PyInt;
+use super::objobject;
+use super::objslice::PySlice;
+use super::objstaticmethod::PyStaticMethod;
+use super::objtype::{self, PyClassRef};
 
 #[derive(Debug)]
 pub struct PyByteArray {
     // TODO: shouldn't be public
     pub value: RefCell<Vec<u8>>,
+    /// The number of views currently exporting the data.
+    exports: Cell<usize>,
 }
 type PyByteArrayRef = PyRef<PyByteArray>;
 
@@ -24,6 +35,19 @@ impl PyByteArray {
     pub fn new(data: Vec<u8>) -> Self {
         PyByteArray {
             value: RefCell::new(data),
+            exports: Cell::new(0),
+        }
+    }
+
+    /// Fail if the size of the data can't change, because a view refers to it.
+    fn check_resizable(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.exports.get() > 0 {
+            Err(vm.new_exception(
+                vm.ctx.exceptions.buffer_error.clone(),
+                "Existing exports of data: object cannot be re-sized".to_string(),
+            ))
+        } else {
+            Ok(())
         }
     }
 }
@@ -32,6 +56,22 @@ impl PyValue for PyByteArray {
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.bytearray_type()
     }
+
+    fn as_buffer(&self) -> Option<&dyn PyBuffer> {
+        Some(self)
+    }
+}
+
+impl ByteSequence for PyByteArray {
+    const NAME: &'static str = "bytearray";
+
+    fn with_data<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        f(&self.value.borrow())
+    }
+
+    fn from_data(data: Vec<u8>) -> Self {
+        PyByteArray::new(data)
+    }
 }
 
 pub fn get_value<'a>(obj: &'a PyObjectRef) -> impl Deref<Target = Vec<u8>> + 'a {
@@ -42,14 +82,37 @@ pub fn get_mut_value<'a>(obj: &'a PyObjectRef) -> impl DerefMut<Target = Vec<u8>
     obj.payload::<PyByteArray>().unwrap().value.borrow_mut()
 }
 
+impl PyBuffer for PyByteArray {
+    fn readonly(&self) -> bool {
+        false
+    }
+
+    fn with_bytes(&self, _vm: &VirtualMachine, f: &mut dyn FnMut(&[u8])) -> PyResult<()> {
+        f(&self.value.borrow());
+        Ok(())
+    }
+
+    fn with_bytes_mut(&self, _vm: &VirtualMachine, f: &mut dyn FnMut(&mut [u8])) -> PyResult<()> {
+        f(&mut self.value.borrow_mut());
+        Ok(())
+    }
+
+    fn export(&self) {
+        self.exports.set(self.exports.get() + 1);
+    }
+
+    fn release(&self) {
+        self.exports.set(self.exports.get() - 1);
+    }
+}
+
 // Binary data support
 
 /// Fill bytearray class methods dictionary.
 pub fn init(context: &PyContext) {
     let bytearray_type = &context.bytearray_type;
 
-    let bytearray_doc =
-        "bytearray(iterable_of_ints) -> bytearray\n\
+    let bytearray_doc = "bytearray(iterable_of_ints) -> bytearray\n\
          bytearray(string, encoding[, errors]) -> bytearray\n\
          bytearray(bytes_or_buffer) -> mutable copy of bytes_or_buffer\n\
          bytearray(int) -> bytes array of size given by the parameter initialized with null bytes\n\
@@ -64,174 +127,318 @@ pub fn init(context: &PyContext) {
     extend_class!(context, bytearray_type, {
         "__doc__" => context.new_str(bytearray_doc.to_string()),
         "__new__" => context.new_rustfunc(bytearray_new),
-        "__eq__" => context.new_rustfunc(PyByteArrayRef::eq),
-        "__len__" => context.new_rustfunc(PyByteArrayRef::len),
+        "__add__" => context.new_rustfunc(objbyteinner::add::<PyByteArray>),
+        "__contains__" => context.new_rustfunc(objbyteinner::contains::<PyByteArray>),
+        "__delitem__" => context.new_rustfunc(PyByteArrayRef::delitem),
+        "__eq__" => context.new_rustfunc(objbyteinner::eq::<PyByteArray>),
+        "__ne__" => context.new_rustfunc(objbyteinner::ne::<PyByteArray>),
+        "__lt__" => context.new_rustfunc(objbyteinner::lt::<PyByteArray>),
+        "__le__" => context.new_rustfunc(objbyteinner::le::<PyByteArray>),
+        "__gt__" => context.new_rustfunc(objbyteinner::gt::<PyByteArray>),
+        "__ge__" => context.new_rustfunc(objbyteinner::ge::<PyByteArray>),
+        "__getitem__" => context.new_rustfunc(objbyteinner::getitem::<PyByteArray>),
+        "__hash__" => context.new_rustfunc(objobject::unhashable),
+        "__iadd__" => context.new_rustfunc(PyByteArrayRef::iadd),
+        "__imul__" => context.new_rustfunc(PyByteArrayRef::imul),
+        "__iter__" => context.new_rustfunc(PyByteArrayRef::iter),
+        "__len__" => context.new_rustfunc(objbyteinner::len::<PyByteArray>),
+        "__mod__" => context.new_rustfunc(objbyteinner::modulo::<PyByteArray>),
+        "__mul__" => context.new_rustfunc(objbyteinner::mul::<PyByteArray>),
+        "__rmul__" => context.new_rustfunc(objbyteinner::mul::<PyByteArray>),
         "__repr__" => context.new_rustfunc(PyByteArrayRef::repr),
+        "__rmod__" => context.new_rustfunc(objbyteinner::rmodulo::<PyByteArray>),
+        "__setitem__" => context.new_rustfunc(PyByteArrayRef::setitem),
+        "append" => context.new_rustfunc(PyByteArrayRef::append),
+        "capitalize" => context.new_rustfunc(objbyteinner::capitalize::<PyByteArray>),
+        "center" => context.new_rustfunc(objbyteinner::center::<PyByteArray>),
         "clear" => context.new_rustfunc(PyByteArrayRef::clear),
-        "isalnum" => context.new_rustfunc(PyByteArrayRef::isalnum),
-        "isalpha" => context.new_rustfunc(PyByteArrayRef::isalpha),
-        "isascii" => context.new_rustfunc(PyByteArrayRef::isascii),
-        "isdigit" => context.new_rustfunc(PyByteArrayRef::isdigit),
-        "islower" => context.new_rustfunc(PyByteArrayRef::islower),
-        "isspace" => context.new_rustfunc(PyByteArrayRef::isspace),
-        "istitle" =>context.new_rustfunc(PyByteArrayRef::istitle),
-        "isupper" => context.new_rustfunc(PyByteArrayRef::isupper),
-        "lower" => context.new_rustfunc(PyByteArrayRef::lower),
+        "copy" => context.new_rustfunc(PyByteArrayRef::copy),
+        "count" => context.new_rustfunc(objbyteinner::count::<PyByteArray>),
+        "decode" => context.new_rustfunc(PyByteArrayRef::decode),
+        "endswith" => context.new_rustfunc(objbyteinner::endswith::<PyByteArray>),
+        "expandtabs" => context.new_rustfunc(objbyteinner::expandtabs::<PyByteArray>),
+        "extend" => context.new_rustfunc(PyByteArrayRef::extend),
+        "find" => context.new_rustfunc(objbyteinner::find::<PyByteArray>),
+        "fromhex" => PyObject::new(
+            PyClassMethod {
+                callable: context.new_rustfunc(objbyteinner::fromhex::<PyByteArray>),
+            },
+            context.classmethod_type(),
+            None,
+        ),
+        "hex" => context.new_rustfunc(objbyteinner::hex::<PyByteArray>),
+        "index" => context.new_rustfunc(objbyteinner::index::<PyByteArray>),
+        "insert" => context.new_rustfunc(PyByteArrayRef::insert),
+        "isalnum" => context.new_rustfunc(objbyteinner::isalnum::<PyByteArray>),
+        "isalpha" => context.new_rustfunc(objbyteinner::isalpha::<PyByteArray>),
+        "isascii" => context.new_rustfunc(objbyteinner::isascii::<PyByteArray>),
+        "isdigit" => context.new_rustfunc(objbyteinner::isdigit::<PyByteArray>),
+        "islower" => context.new_rustfunc(objbyteinner::islower::<PyByteArray>),
+        "isspace" => context.new_rustfunc(objbyteinner::isspace::<PyByteArray>),
+        "istitle" => context.new_rustfunc(objbyteinner::istitle::<PyByteArray>),
+        "isupper" => context.new_rustfunc(objbyteinner::isupper::<PyByteArray>),
+        "join" => context.new_rustfunc(objbyteinner::join::<PyByteArray>),
+        "ljust" => context.new_rustfunc(objbyteinner::ljust::<PyByteArray>),
+        "lower" => context.new_rustfunc(objbyteinner::lower::<PyByteArray>),
+        "lstrip" => context.new_rustfunc(objbyteinner::lstrip::<PyByteArray>),
+        "maketrans" => PyObject::new(
+            PyStaticMethod {
+                callable: context.new_rustfunc(objbyteinner::maketrans),
+            },
+            context.staticmethod_type(),
+            None,
+        ),
+        "partition" => context.new_rustfunc(objbyteinner::partition::<PyByteArray>),
         "pop" => context.new_rustfunc(PyByteArrayRef::pop),
-        "upper" => context.new_rustfunc(PyByteArrayRef::upper)
+        "remove" => context.new_rustfunc(PyByteArrayRef::remove),
+        "replace" => context.new_rustfunc(objbyteinner::replace::<PyByteArray>),
+        "reverse" => context.new_rustfunc(PyByteArrayRef::reverse),
+        "rfind" => context.new_rustfunc(objbyteinner::rfind::<PyByteArray>),
+        "rindex" => context.new_rustfunc(objbyteinner::rindex::<PyByteArray>),
+        "rjust" => context.new_rustfunc(objbyteinner::rjust::<PyByteArray>),
+        "rpartition" => context.new_rustfunc(objbyteinner::rpartition::<PyByteArray>),
+        "rsplit" => context.new_rustfunc(objbyteinner::rsplit::<PyByteArray>),
+        "rstrip" => context.new_rustfunc(objbyteinner::rstrip::<PyByteArray>),
+        "split" => context.new_rustfunc(objbyteinner::split::<PyByteArray>),
+        "splitlines" => context.new_rustfunc(objbyteinner::splitlines::<PyByteArray>),
+        "startswith" => context.new_rustfunc(objbyteinner::startswith::<PyByteArray>),
+        "strip" => context.new_rustfunc(objbyteinner::strip::<PyByteArray>),
+        "swapcase" => context.new_rustfunc(objbyteinner::swapcase::<PyByteArray>),
+        "title" => context.new_rustfunc(objbyteinner::title::<PyByteArray>),
+        "translate" => context.new_rustfunc(objbyteinner::translate::<PyByteArray>),
+        "upper" => context.new_rustfunc(objbyteinner::upper::<PyByteArray>),
+        "zfill" => context.new_rustfunc(objbyteinner::zfill::<PyByteArray>)
     });
 }
 
 fn bytearray_new(
     cls: PyClassRef,
-    val_option: OptionalArg<PyObjectRef>,
+    args: ByteStringArgs,
     vm: &VirtualMachine,
 ) -> PyResult<PyByteArrayRef> {
-    // Create bytes data:
-    let value = if let OptionalArg::Present(ival) = val_option {
-        let elements = vm.extract_elements(&ival)?;
-        let mut data_bytes = vec![];
-        for elem in elements.iter() {
-            let v = objint::to_int(vm, elem, 10)?;
-            if let Some(i) = v.to_u8() {
-                data_bytes.push(i);
-            } else {
-                return Err(vm.new_value_error("byte must be in range(0, 256)".to_string()));
-            }
-        }
-        data_bytes
-    // return Err(vm.new_type_error("Cannot construct bytes".to_string()));
+    PyByteArray::new(args.data(vm)?).into_ref_with_type(vm, cls)
+}
+
+/// Get the position of `index` in a bytearray of length `len`, if it is in
+/// range.
+fn position(index: &PyInt, len: usize) -> Option<usize> {
+    let index = index.as_bigint().to_isize()?;
+    let index = if index < 0 {
+        index + len as isize
     } else {
-        vec![]
+        index
     };
-    PyByteArray::new(value).into_ref_with_type(vm, cls.clone())
+    if index >= 0 && (index as usize) < len {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
+
+/// Get the positions a slice with the given adjusted indices selects.
+fn slice_positions(start: isize, stop: isize, step: isize) -> Vec<usize> {
+    let mut positions = vec![];
+    let mut index = start;
+    while (step > 0 && index < stop) || (step < 0 && index > stop) {
+        positions.push(index as usize);
+        index = match index.checked_add(step) {
+            Some(index) => index,
+            None => break,
+        };
+    }
+    positions
 }
 
 impl PyByteArrayRef {
-    fn len(self, _vm: &VirtualMachine) -> usize {
-        self.value.borrow().len()
+    fn decode(self, args: DecodeArgs, vm: &VirtualMachine) -> PyResult<String> {
+        args.decode(&self.value.borrow(), vm)
     }
 
-    fn eq(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        if let Ok(other) = other.downcast::<PyByteArray>() {
-            vm.ctx
-                .new_bool(self.value.borrow().as_slice() == other.value.borrow().as_slice())
-        } else {
-            vm.ctx.not_implemented()
+    fn iter(self, _vm: &VirtualMachine) -> PyIteratorValue {
+        PyIteratorValue {
+            position: Cell::new(0),
+            iterated_obj: self.into_object(),
         }
     }
 
-    fn isalnum(self, _vm: &VirtualMachine) -> bool {
-        let bytes = self.value.borrow();
-        !bytes.is_empty() && bytes.iter().all(|x| char::from(*x).is_alphanumeric())
+    fn setitem(self, needle: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        if let Some(index) = needle.payload::<PyInt>() {
+            let byte = objbyteinner::byte_value(&value, vm)?;
+            let mut bytes = self.value.borrow_mut();
+            let position = position(index, bytes.len())
+                .ok_or_else(|| vm.new_index_error("bytearray index out of range".to_string()))?;
+            bytes[position] = byte;
+            return Ok(());
+        }
+        if let Some(slice) = needle.payload::<PySlice>() {
+            if objtype::isinstance(&value, &vm.ctx.int_type()) {
+                return Err(vm.new_type_error(
+                    "can assign only bytes, buffers, or iterables of ints in range(0, 256)"
+                        .to_string(),
+                ));
+            }
+            // Get the data first, since it may come from this bytearray
+            let data = objbyteinner::from_source(&value, vm)?;
+            let mut bytes = self.value.borrow_mut();
+            let (start, stop, step) = slice.indices(bytes.len(), vm)?;
+            if step == 1 {
+                let stop = cmp::max(start, stop);
+                if data.len() != (stop - start) as usize {
+                    self.check_resizable(vm)?;
+                }
+                bytes.splice(start as usize..stop as usize, data);
+            } else {
+                let positions = slice_positions(start, stop, step);
+                if positions.len() != data.len() {
+                    return Err(vm.new_value_error(format!(
+                        "attempt to assign bytes of size {} to extended slice of size {}",
+                        data.len(),
+                        positions.len()
+                    )));
+                }
+                for (position, byte) in positions.into_iter().zip(data) {
+                    bytes[position] = byte;
+                }
+            }
+            return Ok(());
+        }
+        Err(vm.new_type_error(format!(
+            "bytearray indices must be integers or slices, not {}",
+            needle.class().name
+        )))
     }
 
-    fn isalpha(self, _vm: &VirtualMachine) -> bool {
-        let bytes = self.value.borrow();
-        !bytes.is_empty() && bytes.iter().all(|x| char::from(*x).is_alphabetic())
+    fn delitem(self, needle: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_resizable(vm)?;
+        let mut bytes = self.value.borrow_mut();
+        if let Some(index) = needle.payload::<PyInt>() {
+            let position = position(index, bytes.len())
+                .ok_or_else(|| vm.new_index_error("bytearray index out of range".to_string()))?;
+            bytes.remove(position);
+            return Ok(());
+        }
+        if let Some(slice) = needle.payload::<PySlice>() {
+            let (start, stop, step) = slice.indices(bytes.len(), vm)?;
+            if step == 1 {
+                let stop = cmp::max(start, stop);
+                bytes.drain(start as usize..stop as usize);
+            } else {
+                let mut positions = slice_positions(start, stop, step);
+                // Remove from the end, so the other positions stay valid
+                positions.sort_unstable_by(|a, b| b.cmp(a));
+                for position in positions {
+                    bytes.remove(position);
+                }
+            }
+            return Ok(());
+        }
+        Err(vm.new_type_error(format!(
+            "bytearray indices must be integers or slices, not {}",
+            needle.class().name
+        )))
     }
 
-    fn isascii(self, _vm: &VirtualMachine) -> bool {
-        let bytes = self.value.borrow();
-        !bytes.is_empty() && bytes.iter().all(|x| char::from(*x).is_ascii())
+    fn iadd(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult<Self> {
+        let data = objbyteinner::bytes_like(&other, vm).map_err(|_| {
+            vm.new_type_error(format!("can't concat {} to bytearray", other.class().name))
+        })?;
+        self.check_resizable(vm)?;
+        self.value.borrow_mut().extend(data);
+        Ok(self)
     }
 
-    fn isdigit(self, _vm: &VirtualMachine) -> bool {
-        let bytes = self.value.borrow();
-        !bytes.is_empty() && bytes.iter().all(|x| char::from(*x).is_digit(10))
+    fn imul(self, counter: isize, vm: &VirtualMachine) -> PyResult<Self> {
+        let repeated = self.value.borrow().repeat(cmp::max(counter, 0) as usize);
+        if repeated.len() != self.value.borrow().len() {
+            self.check_resizable(vm)?;
+        }
+        *self.value.borrow_mut() = repeated;
+        Ok(self)
     }
 
-    fn islower(self, _vm: &VirtualMachine) -> bool {
-        let bytes = self.value.borrow();
-        !bytes.is_empty()
-            && bytes
-                .iter()
-                .filter(|x| !char::from(**x).is_whitespace())
-                .all(|x| char::from(*x).is_lowercase())
+    fn append(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let byte = objbyteinner::byte_value(&value, vm)?;
+        self.check_resizable(vm)?;
+        self.value.borrow_mut().push(byte);
+        Ok(())
     }
 
-    fn isspace(self, _vm: &VirtualMachine) -> bool {
-        let bytes = self.value.borrow();
-        !bytes.is_empty() && bytes.iter().all(|x| char::from(*x).is_whitespace())
+    fn extend(self, iterable: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        if objtype::isinstance(&iterable, &vm.ctx.int_type()) {
+            return Err(vm.new_type_error(format!(
+                "can't extend bytearray with {}",
+                iterable.class().name
+            )));
+        }
+        let data = objbyteinner::from_source(&iterable, vm)?;
+        self.check_resizable(vm)?;
+        self.value.borrow_mut().extend(data);
+        Ok(())
     }
 
-    fn isupper(self, _vm: &VirtualMachine) -> bool {
-        let bytes = self.value.borrow();
-        !bytes.is_empty()
-            && bytes
-                .iter()
-                .filter(|x| !char::from(**x).is_whitespace())
-                .all(|x| char::from(*x).is_uppercase())
+    fn insert(self, index: isize, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let byte = objbyteinner::byte_value(&value, vm)?;
+        self.check_resizable(vm)?;
+        let mut bytes = self.value.borrow_mut();
+        let len = bytes.len() as isize;
+        let position = if index < 0 {
+            cmp::max(index + len, 0)
+        } else {
+            cmp::min(index, len)
+        };
+        bytes.insert(position as usize, byte);
+        Ok(())
     }
 
-    fn istitle(self, _vm: &VirtualMachine) -> bool {
-        let bytes = self.value.borrow();
-        if bytes.is_empty() {
-            return false;
-        }
-
-        let mut iter = bytes.iter().peekable();
-        let mut prev_cased = false;
-
-        while let Some(c) = iter.next() {
-            let current = char::from(*c);
-            let next = if let Some(k) = iter.peek() {
-                char::from(**k)
-            } else if current.is_uppercase() {
-                return !prev_cased;
-            } else {
-                return prev_cased;
-            };
-
-            if (is_cased(current) && next.is_uppercase() && !prev_cased)
-                || (!is_cased(current) && next.is_lowercase())
-            {
-                return false;
-            }
+    fn remove(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let byte = objbyteinner::byte_value(&value, vm)?;
+        self.check_resizable(vm)?;
+        let mut bytes = self.value.borrow_mut();
+        let position = bytes
+            .iter()
+            .position(|b| *b == byte)
+            .ok_or_else(|| vm.new_value_error("value not found in bytearray".to_string()))?;
+        bytes.remove(position);
+        Ok(())
+    }
 
-            prev_cased = is_cased(current);
-        }
+    fn copy(self, _vm: &VirtualMachine) -> PyByteArray {
+        PyByteArray::new(self.value.borrow().clone())
+    }
 
-        true
+    fn reverse(self, _vm: &VirtualMachine) {
+        self.value.borrow_mut().reverse();
     }
 
     fn repr(self, _vm: &VirtualMachine) -> String {
-        let bytes = self.value.borrow();
-        let data = String::from_utf8(bytes.to_vec()).unwrap_or_else(|_| to_hex(&bytes.to_vec()));
-        format!("bytearray(b'{}')", data)
+        format!(
+            "bytearray({})",
+            objbyteinner::repr_data(&self.value.borrow())
+        )
     }
 
-    fn clear(self, _vm: &VirtualMachine) {
+    fn clear(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_resizable(vm)?;
         self.value.borrow_mut().clear();
+        Ok(())
     }
 
-    fn pop(self, vm: &VirtualMachine) -> PyResult<u8> {
+    fn pop(self, index: OptionalArg<isize>, vm: &VirtualMachine) -> PyResult<u8> {
+        self.check_resizable(vm)?;
         let mut bytes = self.value.borrow_mut();
-        bytes
-            .pop()
-            .ok_or_else(|| vm.new_index_error("pop from empty bytearray".to_string()))
-    }
-
-    fn lower(self, _vm: &VirtualMachine) -> PyByteArray {
-        let bytes = self.value.borrow().clone().to_ascii_lowercase();
-        PyByteArray {
-            value: RefCell::new(bytes),
+        if bytes.is_empty() {
+            return Err(vm.new_index_error("pop from empty bytearray".to_string()));
         }
-    }
-
-    fn upper(self, _vm: &VirtualMachine) -> PyByteArray {
-        let bytes = self.value.borrow().clone().to_ascii_uppercase();
-        PyByteArray {
-            value: RefCell::new(bytes),
+        let len = bytes.len() as isize;
+        let index = index.into_option().unwrap_or(-1);
+        let index = if index < 0 { index + len } else { index };
+        if index < 0 || index >= len {
+            return Err(vm.new_index_error("pop index out of range".to_string()));
         }
+        Ok(bytes.remove(index as usize))
     }
 }
 
-// helper function for istitle
-fn is_cased(c: char) -> bool {
-    c.to_uppercase().next().unwrap() != c || c.to_lowercase().next().unwrap() != c
-}
-
 /*
 fn getitem(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(
@@ -249,20 +456,14 @@ fn set_value(obj: &PyObjectRef, value: Vec<u8>) {
 }
 */
 
-/// Return a lowercase hex representation of a bytearray
-fn to_hex(bytearray: &[u8]) -> String {
-    bytearray.iter().fold(String::new(), |mut s, b| {
-        let _ = write!(s, "\\x{:02x}", b);
-        s
-    })
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn bytearray_to_hex_formatting() {
-        assert_eq!(&to_hex(&[11u8, 222u8]), "\\x0b\\xde");
+    fn bytearray_slice_positions() {
+        assert_eq!(slice_positions(1, 6, 2), vec![1, 3, 5]);
+        assert_eq!(slice_positions(4, -1, -2), vec![4, 2, 0]);
+        assert_eq!(slice_positions(3, 3, 1), Vec::<usize>::new());
     }
 }
@@ -0,0 +1,1249 @@
+//! The methods which `bytes` and `bytearray` have in common, implemented
+//! generically over the two payloads.
+
+use std::cmp;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::ops::Deref;
+
+use num_traits::ToPrimitive;
+
+use crate::cformat::{CFormatPreconversor, CFormatSpec, CFormatString, CFormatType};
+use crate::function::OptionalArg;
+use crate::pyobject::{
+    BufferProtocol, PyIterable, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
+};
+use crate::vm::VirtualMachine;
+
+use super::objint::{self, PyInt};
+use super::objsequence::PySliceableSequence;
+use super::objslice::PySlice;
+use super::objstr::{self, PyStringRef};
+use super::objtuple::PyTuple;
+use super::objtype::{self, PyClassRef};
+
+/// The payload of `bytes` or `bytearray`.
+pub trait ByteSequence: PyValue {
+    /// The name of the type, for error messages and `repr`.
+    const NAME: &'static str;
+
+    /// Call `f` with the current contents.
+    fn with_data<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R;
+
+    fn from_data(data: Vec<u8>) -> Self;
+}
+
+/// Get the data of a bytes-like object.
+pub fn bytes_like(obj: &PyObjectRef, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+    match obj.as_buffer() {
+        Some(buffer) => buffer.to_vec(vm),
+        None => Err(vm.new_type_error(format!(
+            "a bytes-like object is required, not '{}'",
+            obj.class().name
+        ))),
+    }
+}
+
+/// A bytes-like argument, such as the separator given to `split`.
+pub struct BytesLike(Vec<u8>);
+
+impl TryFromObject for BytesLike {
+    fn try_from_object(vm: &VirtualMachine, obj: PyObjectRef) -> PyResult<Self> {
+        bytes_like(&obj, vm).map(BytesLike)
+    }
+}
+
+impl Deref for BytesLike {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Convert an int to a byte, as stored in `bytes` and `bytearray`.
+pub fn byte_value(obj: &PyObjectRef, vm: &VirtualMachine) -> PyResult<u8> {
+    if !objtype::isinstance(obj, &vm.ctx.int_type()) {
+        return Err(vm.new_type_error(format!(
+            "'{}' object cannot be interpreted as an integer",
+            obj.class().name
+        )));
+    }
+    objint::get_value(obj)
+        .to_u8()
+        .ok_or_else(|| vm.new_value_error("byte must be in range(0, 256)".to_string()))
+}
+
+/// Get the contents of a new `bytes` or `bytearray` from the source object
+/// given to the constructor: a size, a bytes-like object or an iterable of
+/// ints.
+pub fn from_source(source: &PyObjectRef, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+    if let Some(int) = source.payload::<PyInt>() {
+        return match int.as_bigint().to_isize() {
+            Some(size) if size >= 0 => Ok(vec![0; size as usize]),
+            Some(_) => Err(vm.new_value_error("negative count".to_string())),
+            None => {
+                Err(vm
+                    .new_overflow_error("cannot fit 'int' into an index-sized integer".to_string()))
+            }
+        };
+    }
+    if let Some(buffer) = source.as_buffer() {
+        return buffer.to_vec(vm);
+    }
+    let elements = vm.extract_elements(source)?;
+    elements.iter().map(|elem| byte_value(elem, vm)).collect()
+}
+
+/// Whether `byte` is whitespace, by the ASCII definition Python uses.
+fn is_space(byte: u8) -> bool {
+    match byte {
+        b' ' | b'\t' | b'\n' | b'\r' | b'\x0b' | b'\x0c' => true,
+        _ => false,
+    }
+}
+
+fn find_sub(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    (0..=haystack.len() - needle.len()).find(|&i| haystack[i..].starts_with(needle))
+}
+
+fn rfind_sub(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    (0..=haystack.len() - needle.len())
+        .rev()
+        .find(|&i| haystack[i..].starts_with(needle))
+}
+
+fn count_sub(haystack: &[u8], needle: &[u8]) -> usize {
+    if needle.is_empty() {
+        return haystack.len() + 1;
+    }
+    let mut count = 0;
+    let mut rest = haystack;
+    while let Some(index) = find_sub(rest, needle) {
+        count += 1;
+        rest = &rest[index + needle.len()..];
+    }
+    count
+}
+
+fn split_sep<'a>(data: &'a [u8], sep: &[u8], maxsplit: isize) -> Vec<&'a [u8]> {
+    let mut parts = vec![];
+    let mut rest = data;
+    while maxsplit < 0 || parts.len() < maxsplit as usize {
+        match find_sub(rest, sep) {
+            Some(index) => {
+                parts.push(&rest[..index]);
+                rest = &rest[index + sep.len()..];
+            }
+            None => break,
+        }
+    }
+    parts.push(rest);
+    parts
+}
+
+/// Split on `sep` from the end, giving the parts in reverse.
+fn rsplit_sep<'a>(data: &'a [u8], sep: &[u8], maxsplit: isize) -> Vec<&'a [u8]> {
+    let mut parts = vec![];
+    let mut rest = data;
+    while maxsplit < 0 || parts.len() < maxsplit as usize {
+        match rfind_sub(rest, sep) {
+            Some(index) => {
+                parts.push(&rest[index + sep.len()..]);
+                rest = &rest[..index];
+            }
+            None => break,
+        }
+    }
+    parts.push(rest);
+    parts
+}
+
+fn split_whitespace(data: &[u8], maxsplit: isize) -> Vec<&[u8]> {
+    let mut parts = vec![];
+    let mut rest = lstrip_data(data, None);
+    while !rest.is_empty() {
+        if maxsplit >= 0 && parts.len() == maxsplit as usize {
+            parts.push(rest);
+            break;
+        }
+        match rest.iter().position(|&b| is_space(b)) {
+            Some(end) => {
+                parts.push(&rest[..end]);
+                rest = lstrip_data(&rest[end..], None);
+            }
+            None => {
+                parts.push(rest);
+                break;
+            }
+        }
+    }
+    parts
+}
+
+/// Split on runs of whitespace from the end, giving the parts in reverse.
+fn rsplit_whitespace(data: &[u8], maxsplit: isize) -> Vec<&[u8]> {
+    let mut parts = vec![];
+    let mut rest = rstrip_data(data, None);
+    while !rest.is_empty() {
+        if maxsplit >= 0 && parts.len() == maxsplit as usize {
+            parts.push(rest);
+            break;
+        }
+        match rest.iter().rposition(|&b| is_space(b)) {
+            Some(start) => {
+                parts.push(&rest[start + 1..]);
+                rest = rstrip_data(&rest[..start], None);
+            }
+            None => {
+                parts.push(rest);
+                break;
+            }
+        }
+    }
+    parts
+}
+
+/// Whether `byte` should be stripped: if it is one of `chars`, or whitespace
+/// when no `chars` are given.
+fn is_stripped(byte: u8, chars: Option<&[u8]>) -> bool {
+    match chars {
+        Some(chars) => chars.contains(&byte),
+        None => is_space(byte),
+    }
+}
+
+fn lstrip_data<'a>(data: &'a [u8], chars: Option<&[u8]>) -> &'a [u8] {
+    let start = data
+        .iter()
+        .position(|&b| !is_stripped(b, chars))
+        .unwrap_or_else(|| data.len());
+    &data[start..]
+}
+
+fn rstrip_data<'a>(data: &'a [u8], chars: Option<&[u8]>) -> &'a [u8] {
+    let end = data
+        .iter()
+        .rposition(|&b| !is_stripped(b, chars))
+        .map_or(0, |i| i + 1);
+    &data[..end]
+}
+
+fn replace_data(data: &[u8], old: &[u8], new: &[u8], count: isize) -> Vec<u8> {
+    let mut replaced = Vec::with_capacity(data.len());
+    let mut rest = data;
+    let mut replacements = 0;
+    while count < 0 || replacements < count {
+        if old.is_empty() {
+            // An empty pattern matches before every byte and at the end
+            replaced.extend_from_slice(new);
+            match rest.split_first() {
+                Some((&first, tail)) => {
+                    replaced.push(first);
+                    rest = tail;
+                }
+                None => return replaced,
+            }
+        } else {
+            match find_sub(rest, old) {
+                Some(index) => {
+                    replaced.extend_from_slice(&rest[..index]);
+                    replaced.extend_from_slice(new);
+                    rest = &rest[index + old.len()..];
+                }
+                None => break,
+            }
+        }
+        replacements += 1;
+    }
+    replaced.extend_from_slice(rest);
+    replaced
+}
+
+fn title_data(data: &[u8]) -> Vec<u8> {
+    let mut prev_cased = false;
+    data.iter()
+        .map(|&byte| {
+            let titled = if prev_cased {
+                byte.to_ascii_lowercase()
+            } else {
+                byte.to_ascii_uppercase()
+            };
+            prev_cased = byte.is_ascii_alphabetic();
+            titled
+        })
+        .collect()
+}
+
+fn capitalize_data(data: &[u8]) -> Vec<u8> {
+    let mut capitalized = data.to_ascii_lowercase();
+    if let Some(first) = capitalized.first_mut() {
+        first.make_ascii_uppercase();
+    }
+    capitalized
+}
+
+fn swapcase_data(data: &[u8]) -> Vec<u8> {
+    data.iter()
+        .map(|&byte| {
+            if byte.is_ascii_lowercase() {
+                byte.to_ascii_uppercase()
+            } else {
+                byte.to_ascii_lowercase()
+            }
+        })
+        .collect()
+}
+
+/// Whether uppercase bytes only start, and lowercase bytes only continue,
+/// runs of letters, with at least one letter.
+fn is_title_data(data: &[u8]) -> bool {
+    let mut cased = false;
+    let mut prev_cased = false;
+    for &byte in data {
+        if byte.is_ascii_uppercase() {
+            if prev_cased {
+                return false;
+            }
+            prev_cased = true;
+            cased = true;
+        } else if byte.is_ascii_lowercase() {
+            if !prev_cased {
+                return false;
+            }
+            prev_cased = true;
+            cased = true;
+        } else {
+            prev_cased = false;
+        }
+    }
+    cased
+}
+
+fn expand_tabs(data: &[u8], tabsize: isize) -> Vec<u8> {
+    let tabsize = cmp::max(tabsize, 0) as usize;
+    let mut expanded = Vec::with_capacity(data.len());
+    let mut column = 0;
+    for &byte in data {
+        match byte {
+            b'\t' => {
+                if tabsize > 0 {
+                    let spaces = tabsize - column % tabsize;
+                    expanded.extend(iter::repeat(b' ').take(spaces));
+                    column += spaces;
+                }
+            }
+            b'\n' | b'\r' => {
+                expanded.push(byte);
+                column = 0;
+            }
+            _ => {
+                expanded.push(byte);
+                column += 1;
+            }
+        }
+    }
+    expanded
+}
+
+/// Split `data` at `\n`, `\r` and `\r\n`, the only line boundaries of
+/// binary data.
+fn split_lines(data: &[u8], keepends: bool) -> Vec<&[u8]> {
+    let mut lines = vec![];
+    let mut line_start = 0;
+    let mut index = 0;
+    while index < data.len() {
+        let line_end = match data[index] {
+            b'\r' if data.get(index + 1) == Some(&b'\n') => index + 2,
+            b'\n' | b'\r' => index + 1,
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+        let end = if keepends { line_end } else { index };
+        lines.push(&data[line_start..end]);
+        line_start = line_end;
+        index = line_end;
+    }
+    if line_start < data.len() {
+        lines.push(&data[line_start..]);
+    }
+    lines
+}
+
+/// Get the data `repr` shows for `bytes`, like `b'ab\x00'`.
+pub fn repr_data(data: &[u8]) -> String {
+    let quote = if data.contains(&b'\'') && !data.contains(&b'"') {
+        '"'
+    } else {
+        '\''
+    };
+    let mut repr = String::with_capacity(data.len() + 3);
+    repr.push('b');
+    repr.push(quote);
+    for &byte in data {
+        match byte {
+            b'\t' => repr.push_str("\\t"),
+            b'\n' => repr.push_str("\\n"),
+            b'\r' => repr.push_str("\\r"),
+            b'\\' => repr.push_str("\\\\"),
+            _ if char::from(byte) == quote => {
+                repr.push('\\');
+                repr.push(quote);
+            }
+            0x20..=0x7e => repr.push(char::from(byte)),
+            _ => {
+                let _ = write!(repr, "\\x{:02x}", byte);
+            }
+        }
+    }
+    repr.push(quote);
+    repr
+}
+
+/// Get the lowercase hex digits of `data`, as `bytes.hex` does.
+pub fn hash(data: &[u8]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+fn from_hex(string: &str, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+    let mut data = vec![];
+    let mut chars = string.chars().enumerate();
+    while let Some((position, c)) = chars.next() {
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+        let high = c.to_digit(16);
+        let (low_position, low) = match chars.next() {
+            Some((position, c)) => (position, c.to_digit(16)),
+            None => (position + 1, None),
+        };
+        match (high, low) {
+            (Some(high), Some(low)) => data.push((high * 16 + low) as u8),
+            (None, _) => return Err(non_hex_error(position, vm)),
+            (_, None) => return Err(non_hex_error(low_position, vm)),
+        }
+    }
+    Ok(data)
+}
+
+fn non_hex_error(position: usize, vm: &VirtualMachine) -> PyObjectRef {
+    vm.new_value_error(format!(
+        "non-hexadecimal number found in fromhex() arg at position {}",
+        position
+    ))
+}
+
+/// Get the subsequence to search for from an argument of `find` and
+/// similar methods, which may also be a single byte given as an int.
+fn search_arg(sub: &PyObjectRef, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+    if objtype::isinstance(sub, &vm.ctx.int_type()) {
+        return Ok(vec![byte_value(sub, vm)?]);
+    }
+    bytes_like(sub, vm).map_err(|_| {
+        vm.new_type_error(format!(
+            "argument should be integer or bytes-like object, not '{}'",
+            sub.class().name
+        ))
+    })
+}
+
+/// Get the prefixes or suffixes given to `startswith` or `endswith`.
+fn affixes(affix: &PyObjectRef, method: &str, vm: &VirtualMachine) -> PyResult<Vec<Vec<u8>>> {
+    let error = || {
+        vm.new_type_error(format!(
+            "{} first arg must be bytes or a tuple of bytes, not {}",
+            method,
+            affix.class().name
+        ))
+    };
+    if let Some(tuple) = affix.payload::<PyTuple>() {
+        let elements = tuple.elements.borrow();
+        elements
+            .iter()
+            .map(|elem| bytes_like(elem, vm).map_err(|_| error()))
+            .collect()
+    } else {
+        Ok(vec![bytes_like(affix, vm).map_err(|_| error())?])
+    }
+}
+
+fn new_sequence<T: ByteSequence>(vm: &VirtualMachine, data: &[u8]) -> PyObjectRef {
+    T::from_data(data.to_vec()).into_ref(vm).into_object()
+}
+
+fn new_list<T: ByteSequence>(vm: &VirtualMachine, parts: Vec<&[u8]>) -> PyObjectRef {
+    vm.ctx.new_list(
+        parts
+            .into_iter()
+            .map(|part| new_sequence::<T>(vm, part))
+            .collect(),
+    )
+}
+
+fn compare<T, F>(zelf: PyRef<T>, other: PyObjectRef, vm: &VirtualMachine, op: F) -> PyObjectRef
+where
+    T: ByteSequence,
+    F: Fn(&[u8], &[u8]) -> bool,
+{
+    match bytes_like(&other, vm) {
+        Ok(other) => vm.ctx.new_bool(zelf.with_data(|data| op(data, &other))),
+        Err(_) => vm.ctx.not_implemented(),
+    }
+}
+
+pub fn eq<T: ByteSequence>(zelf: PyRef<T>, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    compare(zelf, other, vm, |a, b| a == b)
+}
+
+pub fn ne<T: ByteSequence>(zelf: PyRef<T>, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    compare(zelf, other, vm, |a, b| a != b)
+}
+
+pub fn lt<T: ByteSequence>(zelf: PyRef<T>, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    compare(zelf, other, vm, |a, b| a < b)
+}
+
+pub fn le<T: ByteSequence>(zelf: PyRef<T>, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    compare(zelf, other, vm, |a, b| a <= b)
+}
+
+pub fn gt<T: ByteSequence>(zelf: PyRef<T>, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    compare(zelf, other, vm, |a, b| a > b)
+}
+
+pub fn ge<T: ByteSequence>(zelf: PyRef<T>, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    compare(zelf, other, vm, |a, b| a >= b)
+}
+
+pub fn len<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> usize {
+    zelf.with_data(<[u8]>::len)
+}
+
+pub fn getitem<T: ByteSequence>(
+    zelf: PyRef<T>,
+    needle: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult {
+    if let Some(int) = needle.payload::<PyInt>() {
+        let byte = int.as_bigint().to_isize().and_then(|index| {
+            zelf.with_data(|data| {
+                let len = data.len() as isize;
+                let index = if index < 0 { index + len } else { index };
+                if index >= 0 && index < len {
+                    Some(data[index as usize])
+                } else {
+                    None
+                }
+            })
+        });
+        return match byte {
+            Some(byte) => Ok(vm.new_int(byte)),
+            None => Err(vm.new_index_error(format!("{} index out of range", T::NAME))),
+        };
+    }
+    if needle.payload::<PySlice>().is_some() {
+        let data = zelf.with_data(|data| data.to_vec().get_slice_items(vm, &needle))?;
+        return Ok(T::from_data(data).into_ref(vm).into_object());
+    }
+    Err(vm.new_type_error(format!(
+        "{} indices must be integers or slices, not {}",
+        T::NAME,
+        needle.class().name
+    )))
+}
+
+pub fn contains<T: ByteSequence>(
+    zelf: PyRef<T>,
+    needle: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<bool> {
+    if objtype::isinstance(&needle, &vm.ctx.int_type()) {
+        let byte = byte_value(&needle, vm)?;
+        return Ok(zelf.with_data(|data| data.contains(&byte)));
+    }
+    let needle = bytes_like(&needle, vm)?;
+    Ok(zelf.with_data(|data| find_sub(data, &needle).is_some()))
+}
+
+pub fn add<T: ByteSequence>(
+    zelf: PyRef<T>,
+    other: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<T> {
+    let other = bytes_like(&other, vm).map_err(|_| {
+        vm.new_type_error(format!(
+            "can't concat {} to {}",
+            other.class().name,
+            T::NAME
+        ))
+    })?;
+    Ok(T::from_data(zelf.with_data(|data| [data, &other].concat())))
+}
+
+pub fn mul<T: ByteSequence>(zelf: PyRef<T>, counter: isize, _vm: &VirtualMachine) -> T {
+    T::from_data(zelf.with_data(|data| data.repeat(cmp::max(counter, 0) as usize)))
+}
+
+pub fn modulo<T: ByteSequence>(
+    zelf: PyRef<T>,
+    values: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<T> {
+    // Formatting works on the characters with the code points of the bytes
+    let text: String = zelf.with_data(|data| data.iter().cloned().map(char::from).collect());
+    let format_string =
+        CFormatString::parse_bytes(&text).map_err(|err| vm.new_value_error(err.to_string()))?;
+    let formatted = objstr::do_cformat(
+        vm,
+        format_string,
+        values,
+        |vm, key| vm.ctx.new_bytes(key.chars().map(|c| c as u8).collect()),
+        format_bytes_cspec,
+    )?;
+    Ok(T::from_data(formatted.chars().map(|c| c as u8).collect()))
+}
+
+/// `__rmod__`, which only formats with the same type as the left operand,
+/// as CPython's does.
+pub fn rmodulo<T: ByteSequence>(
+    zelf: PyRef<T>,
+    other: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult {
+    if !objtype::isinstance(&other, &T::class(vm)) {
+        return Ok(vm.ctx.not_implemented());
+    }
+    let other = PyRef::<T>::try_from_object(vm, other)?;
+    Ok(modulo(other, zelf.into_object(), vm)?
+        .into_ref(vm)
+        .into_object())
+}
+
+/// Format `value` as the specifier of a `bytes` format string says, giving
+/// the bytes as the characters with their code points.
+fn format_bytes_cspec(
+    vm: &VirtualMachine,
+    spec: &CFormatSpec,
+    value: PyObjectRef,
+) -> PyResult<String> {
+    let to_text = |data: &[u8]| data.iter().cloned().map(char::from).collect::<String>();
+    match spec.format_type {
+        CFormatType::String(CFormatPreconversor::Str)
+        | CFormatType::String(CFormatPreconversor::Bytes) => {
+            let data = if objtype::class_has_attr(&value.class(), "__bytes__") {
+                let bytes = vm.call_method(&value, "__bytes__", vec![])?;
+                bytes_like(&bytes, vm)?
+            } else {
+                bytes_like(&value, vm).map_err(|_| {
+                    vm.new_type_error(format!(
+                        "%b requires a bytes-like object, or an object that implements \
+                         __bytes__, not '{}'",
+                        value.class().name
+                    ))
+                })?
+            };
+            Ok(spec.format_string(to_text(&data)))
+        }
+        CFormatType::String(_) => {
+            let repr = objstr::ascii_escape(&vm.to_repr(&value)?.value);
+            Ok(spec.format_string(repr))
+        }
+        CFormatType::Character => {
+            let byte = if objtype::isinstance(&value, &vm.ctx.int_type()) {
+                let byte = objint::get_value(&value)
+                    .to_u8()
+                    .ok_or_else(|| vm.new_overflow_error("%c arg not in range(256)".to_string()))?;
+                Some(byte)
+            } else {
+                match bytes_like(&value, vm) {
+                    Ok(ref data) if data.len() == 1 => Some(data[0]),
+                    _ => None,
+                }
+            };
+            match byte {
+                Some(byte) => Ok(spec.fill_string(to_text(&[byte]))),
+                None => Err(vm.new_type_error(
+                    "%c requires an integer in range(256) or a single byte".to_string(),
+                )),
+            }
+        }
+        _ => objstr::format_cspec(vm, spec, value),
+    }
+}
+
+/// Find `sub` in the part of `zelf` between `start` and `end`, with `search`
+/// giving the index of a match within that part.
+fn search<T, F>(
+    zelf: &PyRef<T>,
+    sub: &PyObjectRef,
+    start: OptionalArg<isize>,
+    end: OptionalArg<isize>,
+    vm: &VirtualMachine,
+    search: F,
+) -> PyResult<Option<usize>>
+where
+    T: ByteSequence,
+    F: Fn(&[u8], &[u8]) -> Option<usize>,
+{
+    let sub = search_arg(sub, vm)?;
+    Ok(zelf.with_data(|data| {
+        let (start, end) = objstr::adjust_indices(start, end, data.len())?;
+        search(&data[start..end], &sub).map(|index| start + index)
+    }))
+}
+
+pub fn find<T: ByteSequence>(
+    zelf: PyRef<T>,
+    sub: PyObjectRef,
+    start: OptionalArg<isize>,
+    end: OptionalArg<isize>,
+    vm: &VirtualMachine,
+) -> PyResult<isize> {
+    let index = search(&zelf, &sub, start, end, vm, find_sub)?;
+    Ok(index.map_or(-1, |index| index as isize))
+}
+
+pub fn rfind<T: ByteSequence>(
+    zelf: PyRef<T>,
+    sub: PyObjectRef,
+    start: OptionalArg<isize>,
+    end: OptionalArg<isize>,
+    vm: &VirtualMachine,
+) -> PyResult<isize> {
+    let index = search(&zelf, &sub, start, end, vm, rfind_sub)?;
+    Ok(index.map_or(-1, |index| index as isize))
+}
+
+pub fn index<T: ByteSequence>(
+    zelf: PyRef<T>,
+    sub: PyObjectRef,
+    start: OptionalArg<isize>,
+    end: OptionalArg<isize>,
+    vm: &VirtualMachine,
+) -> PyResult<usize> {
+    search(&zelf, &sub, start, end, vm, find_sub)?
+        .ok_or_else(|| vm.new_value_error("subsection not found".to_string()))
+}
+
+pub fn rindex<T: ByteSequence>(
+    zelf: PyRef<T>,
+    sub: PyObjectRef,
+    start: OptionalArg<isize>,
+    end: OptionalArg<isize>,
+    vm: &VirtualMachine,
+) -> PyResult<usize> {
+    search(&zelf, &sub, start, end, vm, rfind_sub)?
+        .ok_or_else(|| vm.new_value_error("subsection not found".to_string()))
+}
+
+pub fn count<T: ByteSequence>(
+    zelf: PyRef<T>,
+    sub: PyObjectRef,
+    start: OptionalArg<isize>,
+    end: OptionalArg<isize>,
+    vm: &VirtualMachine,
+) -> PyResult<usize> {
+    let sub = search_arg(&sub, vm)?;
+    Ok(zelf.with_data(|data| {
+        objstr::adjust_indices(start, end, data.len())
+            .map_or(0, |(start, end)| count_sub(&data[start..end], &sub))
+    }))
+}
+
+pub fn startswith<T: ByteSequence>(
+    zelf: PyRef<T>,
+    prefix: PyObjectRef,
+    start: OptionalArg<isize>,
+    end: OptionalArg<isize>,
+    vm: &VirtualMachine,
+) -> PyResult<bool> {
+    let prefixes = affixes(&prefix, "startswith", vm)?;
+    Ok(zelf.with_data(|data| {
+        objstr::adjust_indices(start, end, data.len()).map_or(false, |(start, end)| {
+            prefixes
+                .iter()
+                .any(|prefix| data[start..end].starts_with(prefix))
+        })
+    }))
+}
+
+pub fn endswith<T: ByteSequence>(
+    zelf: PyRef<T>,
+    suffix: PyObjectRef,
+    start: OptionalArg<isize>,
+    end: OptionalArg<isize>,
+    vm: &VirtualMachine,
+) -> PyResult<bool> {
+    let suffixes = affixes(&suffix, "endswith", vm)?;
+    Ok(zelf.with_data(|data| {
+        objstr::adjust_indices(start, end, data.len()).map_or(false, |(start, end)| {
+            suffixes
+                .iter()
+                .any(|suffix| data[start..end].ends_with(suffix))
+        })
+    }))
+}
+
+#[derive(FromArgs)]
+pub struct SplitArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    sep: Option<BytesLike>,
+    #[pyarg(positional_or_keyword, default = "-1")]
+    maxsplit: isize,
+}
+
+pub fn split<T: ByteSequence>(zelf: PyRef<T>, args: SplitArgs, vm: &VirtualMachine) -> PyResult {
+    zelf.with_data(|data| {
+        let parts = match args.sep {
+            Some(ref sep) if sep.is_empty() => {
+                return Err(vm.new_value_error("empty separator".to_string()));
+            }
+            Some(ref sep) => split_sep(data, sep, args.maxsplit),
+            None => split_whitespace(data, args.maxsplit),
+        };
+        Ok(new_list::<T>(vm, parts))
+    })
+}
+
+pub fn rsplit<T: ByteSequence>(zelf: PyRef<T>, args: SplitArgs, vm: &VirtualMachine) -> PyResult {
+    zelf.with_data(|data| {
+        let mut parts = match args.sep {
+            Some(ref sep) if sep.is_empty() => {
+                return Err(vm.new_value_error("empty separator".to_string()));
+            }
+            Some(ref sep) => rsplit_sep(data, sep, args.maxsplit),
+            None => rsplit_whitespace(data, args.maxsplit),
+        };
+        parts.reverse();
+        Ok(new_list::<T>(vm, parts))
+    })
+}
+
+pub fn join<T: ByteSequence>(
+    zelf: PyRef<T>,
+    iterable: PyIterable,
+    vm: &VirtualMachine,
+) -> PyResult<T> {
+    let sep = zelf.with_data(<[u8]>::to_vec);
+    let mut joined = vec![];
+    for (index, elem) in iterable.iter(vm)?.enumerate() {
+        let elem = elem?;
+        let data = bytes_like(&elem, vm).map_err(|_| {
+            vm.new_type_error(format!(
+                "sequence item {}: expected a bytes-like object, {} found",
+                index,
+                elem.class().name
+            ))
+        })?;
+        if index != 0 {
+            joined.extend_from_slice(&sep);
+        }
+        joined.extend(data);
+    }
+    Ok(T::from_data(joined))
+}
+
+fn strip_with<T, F>(zelf: PyRef<T>, chars: OptionalArg<Option<BytesLike>>, strip: F) -> T
+where
+    T: ByteSequence,
+    F: for<'a, 'b> Fn(&'a [u8], Option<&'b [u8]>) -> &'a [u8],
+{
+    let chars = chars.into_option().and_then(|chars| chars);
+    let chars = chars.as_ref().map(|chars| &chars[..]);
+    T::from_data(zelf.with_data(|data| strip(data, chars).to_vec()))
+}
+
+pub fn strip<T: ByteSequence>(
+    zelf: PyRef<T>,
+    chars: OptionalArg<Option<BytesLike>>,
+    _vm: &VirtualMachine,
+) -> T {
+    strip_with(zelf, chars, |data, chars| {
+        rstrip_data(lstrip_data(data, chars), chars)
+    })
+}
+
+pub fn lstrip<T: ByteSequence>(
+    zelf: PyRef<T>,
+    chars: OptionalArg<Option<BytesLike>>,
+    _vm: &VirtualMachine,
+) -> T {
+    strip_with(zelf, chars, lstrip_data)
+}
+
+pub fn rstrip<T: ByteSequence>(
+    zelf: PyRef<T>,
+    chars: OptionalArg<Option<BytesLike>>,
+    _vm: &VirtualMachine,
+) -> T {
+    strip_with(zelf, chars, rstrip_data)
+}
+
+pub fn replace<T: ByteSequence>(
+    zelf: PyRef<T>,
+    old: BytesLike,
+    new: BytesLike,
+    count: OptionalArg<isize>,
+    _vm: &VirtualMachine,
+) -> T {
+    let count = count.into_option().unwrap_or(-1);
+    T::from_data(zelf.with_data(|data| replace_data(data, &old, &new, count)))
+}
+
+fn map_data<T, F>(zelf: PyRef<T>, f: F) -> T
+where
+    T: ByteSequence,
+    F: FnOnce(&[u8]) -> Vec<u8>,
+{
+    T::from_data(zelf.with_data(f))
+}
+
+pub fn lower<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> T {
+    map_data(zelf, <[u8]>::to_ascii_lowercase)
+}
+
+pub fn upper<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> T {
+    map_data(zelf, <[u8]>::to_ascii_uppercase)
+}
+
+pub fn title<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> T {
+    map_data(zelf, title_data)
+}
+
+pub fn capitalize<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> T {
+    map_data(zelf, capitalize_data)
+}
+
+pub fn swapcase<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> T {
+    map_data(zelf, swapcase_data)
+}
+
+fn all_bytes<T, F>(zelf: PyRef<T>, f: F) -> bool
+where
+    T: ByteSequence,
+    F: Fn(&u8) -> bool,
+{
+    zelf.with_data(|data| !data.is_empty() && data.iter().all(f))
+}
+
+pub fn isalnum<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> bool {
+    all_bytes(zelf, u8::is_ascii_alphanumeric)
+}
+
+pub fn isalpha<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> bool {
+    all_bytes(zelf, u8::is_ascii_alphabetic)
+}
+
+pub fn isdigit<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> bool {
+    all_bytes(zelf, u8::is_ascii_digit)
+}
+
+pub fn isspace<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> bool {
+    all_bytes(zelf, |byte| is_space(*byte))
+}
+
+pub fn isascii<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> bool {
+    zelf.with_data(|data| data.is_ascii())
+}
+
+pub fn islower<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> bool {
+    zelf.with_data(|data| {
+        data.iter().any(u8::is_ascii_lowercase) && !data.iter().any(u8::is_ascii_uppercase)
+    })
+}
+
+pub fn isupper<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> bool {
+    zelf.with_data(|data| {
+        data.iter().any(u8::is_ascii_uppercase) && !data.iter().any(u8::is_ascii_lowercase)
+    })
+}
+
+pub fn istitle<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> bool {
+    zelf.with_data(is_title_data)
+}
+
+/// Get the byte to pad with from the `fillchar` argument of `method`.
+fn fill_byte(
+    fillchar: OptionalArg<PyObjectRef>,
+    method: &str,
+    vm: &VirtualMachine,
+) -> PyResult<u8> {
+    let fillchar = match fillchar {
+        OptionalArg::Present(fillchar) => fillchar,
+        OptionalArg::Missing => return Ok(b' '),
+    };
+    match bytes_like(&fillchar, vm) {
+        Ok(ref data) if data.len() == 1 => Ok(data[0]),
+        _ => Err(vm.new_type_error(format!(
+            "{}() argument 2 must be a byte string of length 1, not {}",
+            method,
+            fillchar.class().name
+        ))),
+    }
+}
+
+/// Pad to `width` with `fill`, putting `left(padding)` of the padding on the
+/// left.
+fn pad<T, F>(zelf: PyRef<T>, width: isize, fill: u8, left: F) -> T
+where
+    T: ByteSequence,
+    F: FnOnce(usize) -> usize,
+{
+    map_data(zelf, |data| {
+        let padding = (cmp::max(width, 0) as usize).saturating_sub(data.len());
+        let left = left(padding);
+        let mut padded = vec![fill; left];
+        padded.extend_from_slice(data);
+        padded.extend(iter::repeat(fill).take(padding - left));
+        padded
+    })
+}
+
+pub fn center<T: ByteSequence>(
+    zelf: PyRef<T>,
+    width: isize,
+    fillchar: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult<T> {
+    let fill = fill_byte(fillchar, "center", vm)?;
+    // Like CPython, an odd padding goes to the left for an odd width
+    Ok(pad(zelf, width, fill, |padding| {
+        padding / 2 + (padding & width as usize & 1)
+    }))
+}
+
+pub fn ljust<T: ByteSequence>(
+    zelf: PyRef<T>,
+    width: isize,
+    fillchar: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult<T> {
+    let fill = fill_byte(fillchar, "ljust", vm)?;
+    Ok(pad(zelf, width, fill, |_| 0))
+}
+
+pub fn rjust<T: ByteSequence>(
+    zelf: PyRef<T>,
+    width: isize,
+    fillchar: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult<T> {
+    let fill = fill_byte(fillchar, "rjust", vm)?;
+    Ok(pad(zelf, width, fill, |padding| padding))
+}
+
+pub fn zfill<T: ByteSequence>(zelf: PyRef<T>, width: isize, _vm: &VirtualMachine) -> T {
+    map_data(zelf, |data| {
+        let padding = (cmp::max(width, 0) as usize).saturating_sub(data.len());
+        let (sign, digits) = match data.first() {
+            Some(b'+') | Some(b'-') => data.split_at(1),
+            _ => data.split_at(0),
+        };
+        [sign, &vec![b'0'; padding], digits].concat()
+    })
+}
+
+#[derive(FromArgs)]
+pub struct ExpandTabsArgs {
+    #[pyarg(positional_or_keyword, default = "8")]
+    tabsize: isize,
+}
+
+pub fn expandtabs<T: ByteSequence>(
+    zelf: PyRef<T>,
+    args: ExpandTabsArgs,
+    _vm: &VirtualMachine,
+) -> T {
+    map_data(zelf, |data| expand_tabs(data, args.tabsize))
+}
+
+#[derive(FromArgs)]
+pub struct SplitLinesArgs {
+    #[pyarg(positional_or_keyword, default = "false")]
+    keepends: bool,
+}
+
+pub fn splitlines<T: ByteSequence>(
+    zelf: PyRef<T>,
+    args: SplitLinesArgs,
+    vm: &VirtualMachine,
+) -> PyObjectRef {
+    zelf.with_data(|data| new_list::<T>(vm, split_lines(data, args.keepends)))
+}
+
+/// Split the data into a tuple of three around the bounds of `sep` that
+/// `search` finds, an empty range at one end if it is absent.
+fn partition_with<T, F>(zelf: PyRef<T>, sep: BytesLike, vm: &VirtualMachine, search: F) -> PyResult
+where
+    T: ByteSequence,
+    F: FnOnce(&[u8], &[u8]) -> (usize, usize),
+{
+    if sep.is_empty() {
+        return Err(vm.new_value_error("empty separator".to_string()));
+    }
+    let parts = zelf.with_data(|data| {
+        let (start, end) = search(data, &sep);
+        [&data[..start], &data[start..end], &data[end..]]
+            .iter()
+            .map(|part| new_sequence::<T>(vm, part))
+            .collect()
+    });
+    Ok(vm.ctx.new_tuple(parts))
+}
+
+pub fn partition<T: ByteSequence>(zelf: PyRef<T>, sep: BytesLike, vm: &VirtualMachine) -> PyResult {
+    partition_with(zelf, sep, vm, |data, sep| {
+        find_sub(data, sep).map_or((data.len(), data.len()), |index| (index, index + sep.len()))
+    })
+}
+
+pub fn rpartition<T: ByteSequence>(
+    zelf: PyRef<T>,
+    sep: BytesLike,
+    vm: &VirtualMachine,
+) -> PyResult {
+    partition_with(zelf, sep, vm, |data, sep| {
+        rfind_sub(data, sep).map_or((0, 0), |index| (index, index + sep.len()))
+    })
+}
+
+#[derive(FromArgs)]
+pub struct TranslateArgs {
+    #[pyarg(positional_only, optional = false)]
+    table: Option<BytesLike>,
+    #[pyarg(positional_or_keyword, optional = true)]
+    delete: OptionalArg<BytesLike>,
+}
+
+pub fn translate<T: ByteSequence>(
+    zelf: PyRef<T>,
+    args: TranslateArgs,
+    vm: &VirtualMachine,
+) -> PyResult<T> {
+    let TranslateArgs { table, delete } = args;
+    if let Some(ref table) = table {
+        if table.len() != 256 {
+            return Err(
+                vm.new_value_error("translation table must be 256 characters long".to_string())
+            );
+        }
+    }
+    let delete = delete.into_option();
+    let delete = delete.as_ref().map_or(&[][..], |delete| &delete[..]);
+    Ok(map_data(zelf, |data| {
+        data.iter()
+            .filter(|byte| !delete.contains(byte))
+            .map(|&byte| table.as_ref().map_or(byte, |table| table[byte as usize]))
+            .collect()
+    }))
+}
+
+/// The `maketrans` static method, which gives `bytes` for both types.
+pub fn maketrans(from: BytesLike, to: BytesLike, vm: &VirtualMachine) -> PyResult {
+    if from.len() != to.len() {
+        return Err(vm.new_value_error("maketrans arguments must have same length".to_string()));
+    }
+    let mut table: Vec<u8> = (0..=255).collect();
+    for (&from, &to) in from.iter().zip(to.iter()) {
+        table[from as usize] = to;
+    }
+    Ok(vm.ctx.new_bytes(table))
+}
+
+pub fn hex<T: ByteSequence>(zelf: PyRef<T>, _vm: &VirtualMachine) -> String {
+    zelf.with_data(to_hex)
+}
+
+/// The `fromhex` class method.
+pub fn fromhex<T: ByteSequence>(
+    cls: PyClassRef,
+    string: PyStringRef,
+    vm: &VirtualMachine,
+) -> PyResult<PyRef<T>> {
+    T::from_data(from_hex(&string.value, vm)?).into_ref_with_type(vm, cls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        assert_eq!(find_sub(b"abcabc", b"bc"), Some(1));
+        assert_eq!(rfind_sub(b"abcabc", b"bc"), Some(4));
+        assert_eq!(find_sub(b"abc", b""), Some(0));
+        assert_eq!(rfind_sub(b"abc", b""), Some(3));
+        assert_eq!(find_sub(b"ab", b"abc"), None);
+        assert_eq!(count_sub(b"aaaa", b"aa"), 2);
+        assert_eq!(count_sub(b"ab", b""), 3);
+    }
+
+    #[test]
+    fn test_split() {
+        let parts: Vec<&[u8]> = vec![b"a", b"", b"b,c"];
+        assert_eq!(split_sep(b"a,,b,c", b",", 2), parts);
+        let parts: Vec<&[u8]> = vec![b"c", b"b", b"a,"];
+        assert_eq!(rsplit_sep(b"a,,b,c", b",", 2), parts);
+        let parts: Vec<&[u8]> = vec![b"a", b"b c "];
+        assert_eq!(split_whitespace(b" \x0ba \tb c ", 1), parts);
+        let parts: Vec<&[u8]> = vec![b"c", b" a b"];
+        assert_eq!(rsplit_whitespace(b" a b c\n", 1), parts);
+    }
+
+    #[test]
+    fn test_replace() {
+        assert_eq!(replace_data(b"aXbXc", b"X", b"--", -1), b"a--b--c");
+        assert_eq!(replace_data(b"aXbXc", b"X", b"", 1), b"abXc");
+        assert_eq!(replace_data(b"ab", b"", b"-", -1), b"-a-b-");
+        assert_eq!(replace_data(b"ab", b"", b"-", 2), b"-a-b");
+    }
+
+    #[test]
+    fn test_case() {
+        assert_eq!(title_data(b"ab cD1e'f"), b"Ab Cd1E'F");
+        assert_eq!(capitalize_data(b"aBC d"), b"Abc d");
+        assert_eq!(swapcase_data(b"aB\xe9"), b"Ab\xe9");
+        assert!(is_title_data(b"Ab Cd1E"));
+        assert!(!is_title_data(b"AB"));
+        assert!(!is_title_data(b"1"));
+    }
+
+    #[test]
+    fn test_lines() {
+        let lines: Vec<&[u8]> = vec![b"a", b"b", b"", b"c\x0b"];
+        assert_eq!(split_lines(b"a\r\nb\r\rc\x0b", false), lines);
+        let lines: Vec<&[u8]> = vec![b"a\r\n", b"b\n"];
+        assert_eq!(split_lines(b"a\r\nb\n", true), lines);
+        assert_eq!(expand_tabs(b"a\tbc\n\t", 4), b"a   bc\n    ");
+    }
+
+    #[test]
+    fn test_repr() {
+        assert_eq!(repr_data(b"ab\x00\xff\n"), "b'ab\\x00\\xff\\n'");
+        assert_eq!(repr_data(b"it's"), "b\"it's\"");
+        assert_eq!(repr_data(b"'\""), "b'\\'\"'");
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0x0b, 0xde]), "0bde");
+    }
+}
@@ -1,23 +1,24 @@
 use std::cell::Cell;
-use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 
-use num_traits::ToPrimitive;
-
-use crate::function::{OptionalArg, PyFuncArgs};
+use crate::function::OptionalArg;
 use crate::pyobject::{
-    PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+    PyBuffer, PyContext, PyIteratorValue, PyObject, PyObjectRef, PyRef, PyResult, PyValue,
 };
+use crate::stdlib::codecs;
 use crate::vm::VirtualMachine;
 
-use super::objint;
+use super::objbyteinner::{self, ByteSequence};
+use super::objclassmethod::PyClassMethod;
+use super::objstaticmethod::PyStaticMethod;
+use super::objstr::{self, PyStringRef};
 use super::objtype::{self, PyClassRef};
 
 #[derive(Debug)]
 pub struct PyBytes {
     value: Vec<u8>,
 }
-type PyBytesRef = PyRef<PyBytes>;
+pub type PyBytesRef = PyRef<PyBytes>;
 
 impl PyBytes {
     pub fn new(data: Vec<u8>) -> Self {
@@ -37,6 +38,29 @@ impl PyValue for PyBytes {
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.bytes_type()
     }
+
+    fn as_buffer(&self) -> Option<&dyn PyBuffer> {
+        Some(self)
+    }
+}
+
+impl ByteSequence for PyBytes {
+    const NAME: &'static str = "bytes";
+
+    fn with_data<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+        f(&self.value)
+    }
+
+    fn from_data(data: Vec<u8>) -> Self {
+        PyBytes::new(data)
+    }
+}
+
+impl PyBuffer for PyBytes {
+    fn with_bytes(&self, _vm: &VirtualMachine, f: &mut dyn FnMut(&[u8])) -> PyResult<()> {
+        f(&self.value);
+        Ok(())
+    }
 }
 
 // Binary data support
@@ -45,8 +69,7 @@ impl PyValue for PyBytes {
 pub fn init(context: &PyContext) {
     let bytes_type = context.bytes_type.as_object();
 
-    let bytes_doc =
-        "bytes(iterable_of_ints) -> bytes\n\
+    let bytes_doc = "bytes(iterable_of_ints) -> bytes\n\
          bytes(string, encoding[, errors]) -> bytes\n\
          bytes(bytes_or_buffer) -> immutable copy of bytes_or_buffer\n\
          bytes(int) -> bytes object of size given by the parameter initialized with null bytes\n\
@@ -57,142 +80,163 @@ pub fn init(context: &PyContext) {
          - an integer";
 
     extend_class!(context, bytes_type, {
-        "__eq__" => context.new_rustfunc(bytes_eq),
-        "__lt__" => context.new_rustfunc(bytes_lt),
-        "__le__" => context.new_rustfunc(bytes_le),
-        "__gt__" => context.new_rustfunc(bytes_gt),
-        "__ge__" => context.new_rustfunc(bytes_ge),
+        "__add__" => context.new_rustfunc(objbyteinner::add::<PyBytes>),
+        "__contains__" => context.new_rustfunc(objbyteinner::contains::<PyBytes>),
+        "__eq__" => context.new_rustfunc(objbyteinner::eq::<PyBytes>),
+        "__ne__" => context.new_rustfunc(objbyteinner::ne::<PyBytes>),
+        "__lt__" => context.new_rustfunc(objbyteinner::lt::<PyBytes>),
+        "__le__" => context.new_rustfunc(objbyteinner::le::<PyBytes>),
+        "__gt__" => context.new_rustfunc(objbyteinner::gt::<PyBytes>),
+        "__ge__" => context.new_rustfunc(objbyteinner::ge::<PyBytes>),
+        "__getitem__" => context.new_rustfunc(objbyteinner::getitem::<PyBytes>),
         "__hash__" => context.new_rustfunc(bytes_hash),
         "__new__" => context.new_rustfunc(bytes_new),
         "__repr__" => context.new_rustfunc(bytes_repr),
-        "__len__" => context.new_rustfunc(bytes_len),
+        "__len__" => context.new_rustfunc(objbyteinner::len::<PyBytes>),
         "__iter__" => context.new_rustfunc(bytes_iter),
-        "__doc__" => context.new_str(bytes_doc.to_string())
+        "__mod__" => context.new_rustfunc(objbyteinner::modulo::<PyBytes>),
+        "__mul__" => context.new_rustfunc(objbyteinner::mul::<PyBytes>),
+        "__rmod__" => context.new_rustfunc(objbyteinner::rmodulo::<PyBytes>),
+        "__rmul__" => context.new_rustfunc(objbyteinner::mul::<PyBytes>),
+        "__doc__" => context.new_str(bytes_doc.to_string()),
+        "capitalize" => context.new_rustfunc(objbyteinner::capitalize::<PyBytes>),
+        "center" => context.new_rustfunc(objbyteinner::center::<PyBytes>),
+        "count" => context.new_rustfunc(objbyteinner::count::<PyBytes>),
+        "decode" => context.new_rustfunc(bytes_decode),
+        "endswith" => context.new_rustfunc(objbyteinner::endswith::<PyBytes>),
+        "expandtabs" => context.new_rustfunc(objbyteinner::expandtabs::<PyBytes>),
+        "find" => context.new_rustfunc(objbyteinner::find::<PyBytes>),
+        "fromhex" => PyObject::new(
+            PyClassMethod {
+                callable: context.new_rustfunc(objbyteinner::fromhex::<PyBytes>),
+            },
+            context.classmethod_type(),
+            None,
+        ),
+        "hex" => context.new_rustfunc(objbyteinner::hex::<PyBytes>),
+        "index" => context.new_rustfunc(objbyteinner::index::<PyBytes>),
+        "isalnum" => context.new_rustfunc(objbyteinner::isalnum::<PyBytes>),
+        "isalpha" => context.new_rustfunc(objbyteinner::isalpha::<PyBytes>),
+        "isascii" => context.new_rustfunc(objbyteinner::isascii::<PyBytes>),
+        "isdigit" => context.new_rustfunc(objbyteinner::isdigit::<PyBytes>),
+        "islower" => context.new_rustfunc(objbyteinner::islower::<PyBytes>),
+        "isspace" => context.new_rustfunc(objbyteinner::isspace::<PyBytes>),
+        "istitle" => context.new_rustfunc(objbyteinner::istitle::<PyBytes>),
+        "isupper" => context.new_rustfunc(objbyteinner::isupper::<PyBytes>),
+        "join" => context.new_rustfunc(objbyteinner::join::<PyBytes>),
+        "ljust" => context.new_rustfunc(objbyteinner::ljust::<PyBytes>),
+        "lower" => context.new_rustfunc(objbyteinner::lower::<PyBytes>),
+        "lstrip" => context.new_rustfunc(objbyteinner::lstrip::<PyBytes>),
+        "maketrans" => PyObject::new(
+            PyStaticMethod {
+                callable: context.new_rustfunc(objbyteinner::maketrans),
+            },
+            context.staticmethod_type(),
+            None,
+        ),
+        "partition" => context.new_rustfunc(objbyteinner::partition::<PyBytes>),
+        "replace" => context.new_rustfunc(objbyteinner::replace::<PyBytes>),
+        "rfind" => context.new_rustfunc(objbyteinner::rfind::<PyBytes>),
+        "rindex" => context.new_rustfunc(objbyteinner::rindex::<PyBytes>),
+        "rjust" => context.new_rustfunc(objbyteinner::rjust::<PyBytes>),
+        "rpartition" => context.new_rustfunc(objbyteinner::rpartition::<PyBytes>),
+        "rsplit" => context.new_rustfunc(objbyteinner::rsplit::<PyBytes>),
+        "rstrip" => context.new_rustfunc(objbyteinner::rstrip::<PyBytes>),
+        "split" => context.new_rustfunc(objbyteinner::split::<PyBytes>),
+        "splitlines" => context.new_rustfunc(objbyteinner::splitlines::<PyBytes>),
+        "startswith" => context.new_rustfunc(objbyteinner::startswith::<PyBytes>),
+        "strip" => context.new_rustfunc(objbyteinner::strip::<PyBytes>),
+        "swapcase" => context.new_rustfunc(objbyteinner::swapcase::<PyBytes>),
+        "title" => context.new_rustfunc(objbyteinner::title::<PyBytes>),
+        "translate" => context.new_rustfunc(objbyteinner::translate::<PyBytes>),
+        "upper" => context.new_rustfunc(objbyteinner::upper::<PyBytes>),
+        "zfill" => context.new_rustfunc(objbyteinner::zfill::<PyBytes>)
     });
 }
 
-fn bytes_new(
-    cls: PyClassRef,
-    val_option: OptionalArg<PyObjectRef>,
-    vm: &VirtualMachine,
-) -> PyResult<PyBytesRef> {
-    // Create bytes data:
-    let value = if let OptionalArg::Present(ival) = val_option {
-        let elements = vm.extract_elements(&ival)?;
-        let mut data_bytes = vec![];
-        for elem in elements.iter() {
-            let v = objint::to_int(vm, elem, 10)?;
-            data_bytes.push(v.to_u8().unwrap());
+/// The `encoding` and `errors` arguments of `bytes.decode` and
+/// `bytearray.decode`.
+#[derive(FromArgs)]
+pub struct DecodeArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    encoding: Option<PyStringRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    errors: Option<PyStringRef>,
+}
+
+impl DecodeArgs {
+    pub fn decode(&self, data: &[u8], vm: &VirtualMachine) -> PyResult<String> {
+        let encoding = self.encoding.as_ref().map_or("utf-8", |s| &s.value);
+        let errors = self.errors.as_ref().map_or("strict", |s| &s.value);
+        codecs::decode_bytes(data, encoding, errors, vm)
+    }
+}
+
+/// The arguments of the `bytes` and `bytearray` constructors.
+#[derive(FromArgs)]
+pub struct ByteStringArgs {
+    #[pyarg(positional_or_keyword, optional = true)]
+    source: OptionalArg<PyObjectRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    encoding: Option<PyStringRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    errors: Option<PyStringRef>,
+}
+
+impl ByteStringArgs {
+    /// Get the data for `bytes(string, encoding[, errors])`, if those are
+    /// the arguments given.
+    fn encode(&self, vm: &VirtualMachine) -> PyResult<Option<Vec<u8>>> {
+        let is_str = match self.source {
+            OptionalArg::Present(ref source) => objtype::isinstance(source, &vm.ctx.str_type()),
+            OptionalArg::Missing => false,
+        };
+        match (&self.source, &self.encoding) {
+            (OptionalArg::Present(source), Some(encoding)) if is_str => {
+                let errors = self.errors.as_ref().map_or("strict", |s| &s.value);
+                codecs::encode_text(objstr::borrow_value(source), &encoding.value, errors, vm)
+                    .map(Some)
+            }
+            (_, Some(_)) => {
+                Err(vm.new_type_error("encoding without a string argument".to_string()))
+            }
+            _ if is_str => {
+                Err(vm.new_type_error("string argument without an encoding".to_string()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Get the contents of the new object.
+    pub fn data(&self, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+        if let Some(data) = self.encode(vm)? {
+            return Ok(data);
+        }
+        match self.source {
+            OptionalArg::Present(ref source) => objbyteinner::from_source(source, vm),
+            OptionalArg::Missing => Ok(vec![]),
         }
-        data_bytes
-    // return Err(vm.new_type_error("Cannot construct bytes".to_string()));
-    } else {
-        vec![]
-    };
-
-    PyBytes::new(value).into_ref_with_type(vm, cls)
-}
-
-fn bytes_eq(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        required = [(a, Some(vm.ctx.bytes_type())), (b, None)]
-    );
-
-    let result = if objtype::isinstance(b, &vm.ctx.bytes_type()) {
-        get_value(a).to_vec() == get_value(b).to_vec()
-    } else {
-        false
-    };
-    Ok(vm.ctx.new_bool(result))
-}
-
-fn bytes_ge(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        required = [(a, Some(vm.ctx.bytes_type())), (b, None)]
-    );
-
-    let result = if objtype::isinstance(b, &vm.ctx.bytes_type()) {
-        get_value(a).to_vec() >= get_value(b).to_vec()
-    } else {
-        return Err(vm.new_type_error(format!("Cannot compare {} and {} using '>'", a, b)));
-    };
-    Ok(vm.ctx.new_bool(result))
-}
-
-fn bytes_gt(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        required = [(a, Some(vm.ctx.bytes_type())), (b, None)]
-    );
-
-    let result = if objtype::isinstance(b, &vm.ctx.bytes_type()) {
-        get_value(a).to_vec() > get_value(b).to_vec()
-    } else {
-        return Err(vm.new_type_error(format!("Cannot compare {} and {} using '>='", a, b)));
-    };
-    Ok(vm.ctx.new_bool(result))
-}
-
-fn bytes_le(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        required = [(a, Some(vm.ctx.bytes_type())), (b, None)]
-    );
-
-    let result = if objtype::isinstance(b, &vm.ctx.bytes_type()) {
-        get_value(a).to_vec() <= get_value(b).to_vec()
-    } else {
-        return Err(vm.new_type_error(format!("Cannot compare {} and {} using '<'", a, b)));
-    };
-    Ok(vm.ctx.new_bool(result))
-}
-
-fn bytes_lt(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        required = [(a, Some(vm.ctx.bytes_type())), (b, None)]
-    );
-
-    let result = if objtype::isinstance(b, &vm.ctx.bytes_type()) {
-        get_value(a).to_vec() < get_value(b).to_vec()
-    } else {
-        return Err(vm.new_type_error(format!("Cannot compare {} and {} using '<='", a, b)));
-    };
-    Ok(vm.ctx.new_bool(result))
-}
-
-fn bytes_len(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(vm, args, required = [(a, Some(vm.ctx.bytes_type()))]);
-
-    let byte_vec = get_value(a).to_vec();
-    Ok(vm.ctx.new_int(byte_vec.len()))
-}
-
-fn bytes_hash(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(vm, args, required = [(zelf, Some(vm.ctx.bytes_type()))]);
-    let data = get_value(zelf);
-    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-    data.hash(&mut hasher);
-    let hash = hasher.finish();
-    Ok(vm.ctx.new_int(hash))
+    }
+}
+
+fn bytes_new(cls: PyClassRef, args: ByteStringArgs, vm: &VirtualMachine) -> PyResult<PyBytesRef> {
+    PyBytes::new(args.data(vm)?).into_ref_with_type(vm, cls)
+}
+
+fn bytes_hash(zelf: PyBytesRef, _vm: &VirtualMachine) -> u64 {
+    objbyteinner::hash(&zelf.value)
 }
 
 pub fn get_value<'a>(obj: &'a PyObjectRef) -> impl Deref<Target = Vec<u8>> + 'a {
     &obj.payload::<PyBytes>().unwrap().value
 }
 
-fn bytes_repr(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(vm, args, required = [(obj, Some(vm.ctx.bytes_type()))]);
-    let value = get_value(obj);
-    let data = String::from_utf8(value.to_vec()).unwrap();
-    Ok(vm.new_str(format!("b'{}'", data)))
+fn bytes_repr(zelf: PyBytesRef, _vm: &VirtualMachine) -> String {
+    objbyteinner::repr_data(&zelf.value)
+}
+
+fn bytes_decode(zelf: PyBytesRef, args: DecodeArgs, vm: &VirtualMachine) -> PyResult<String> {
+    args.decode(&zelf, vm)
 }
 
 fn bytes_iter(obj: PyBytesRef, _vm: &VirtualMachine) -> PyIteratorValue {
@@ -0,0 +1,84 @@
+/*! The python `cell` type.
+
+A cell holds a variable which is shared between a function and the
+functions nested in it.
+*/
+
+use std::cell::RefCell;
+
+use super::objtype::PyClassRef;
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+pub type PyCellRef = PyRef<PyCell>;
+
+#[derive(Debug)]
+pub struct PyCell {
+    contents: RefCell<Option<PyObjectRef>>,
+}
+
+impl PyCell {
+    pub fn new(contents: Option<PyObjectRef>) -> Self {
+        PyCell {
+            contents: RefCell::new(contents),
+        }
+    }
+
+    pub fn get(&self) -> Option<PyObjectRef> {
+        self.contents.borrow().clone()
+    }
+
+    pub fn set(&self, contents: Option<PyObjectRef>) {
+        *self.contents.borrow_mut() = contents;
+    }
+
+    /// The contents, or `None` if the cell is being modified.
+    pub fn try_get(&self) -> Option<Option<PyObjectRef>> {
+        self.contents
+            .try_borrow()
+            .ok()
+            .map(|contents| contents.clone())
+    }
+
+    /// Empty the cell, returning the contents, unless it is being modified.
+    pub fn try_take(&self) -> Option<PyObjectRef> {
+        self.contents.try_borrow_mut().ok()?.take()
+    }
+}
+
+impl PyValue for PyCell {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.cell_type()
+    }
+}
+
+impl PyCellRef {
+    fn new(_cls: PyClassRef, vm: &VirtualMachine) -> PyResult {
+        Err(vm.new_type_error("cannot create 'cell' instances".to_string()))
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        match self.get() {
+            Some(contents) => format!(
+                "<cell at 0x{:x}: {} object at 0x{:x}>",
+                self.get_id(),
+                contents.class().name,
+                contents.get_id()
+            ),
+            None => format!("<cell at 0x{:x}: empty>", self.get_id()),
+        }
+    }
+
+    fn cell_contents(self, vm: &VirtualMachine) -> PyResult {
+        self.get()
+            .ok_or_else(|| vm.new_value_error("Cell is empty".to_string()))
+    }
+}
+
+pub fn init(context: &PyContext) {
+    extend_class!(context, &context.cell_type, {
+        "__new__" => context.new_rustfunc(PyCellRef::new),
+        "__repr__" => context.new_rustfunc(PyCellRef::repr),
+        "cell_contents" => context.new_property(PyCellRef::cell_contents),
+    });
+}
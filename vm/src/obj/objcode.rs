@@ -77,6 +77,23 @@ impl PyCodeRef {
     fn co_name(self, _vm: &VirtualMachine) -> String {
         self.code.obj_name.clone()
     }
+
+    fn co_varnames(self, vm: &VirtualMachine) -> PyObjectRef {
+        names_tuple(&self.code.varnames, vm)
+    }
+
+    fn co_cellvars(self, vm: &VirtualMachine) -> PyObjectRef {
+        names_tuple(&self.code.cellvars, vm)
+    }
+
+    fn co_freevars(self, vm: &VirtualMachine) -> PyObjectRef {
+        names_tuple(&self.code.freevars, vm)
+    }
+}
+
+fn names_tuple(names: &[String], vm: &VirtualMachine) -> PyObjectRef {
+    let names = names.iter().map(|name| vm.new_str(name.clone())).collect();
+    vm.ctx.new_tuple(names)
 }
 
 pub fn init(context: &PyContext) {
@@ -86,10 +103,13 @@ pub fn init(context: &PyContext) {
         "__repr__" => context.new_rustfunc(PyCodeRef::repr),
 
         "co_argcount" => context.new_property(PyCodeRef::co_argcount),
+        "co_cellvars" => context.new_property(PyCodeRef::co_cellvars),
         "co_consts" => context.new_property(PyCodeRef::co_consts),
         "co_filename" => context.new_property(PyCodeRef::co_filename),
         "co_firstlineno" => context.new_property(PyCodeRef::co_firstlineno),
+        "co_freevars" => context.new_property(PyCodeRef::co_freevars),
         "co_kwonlyargcount" => context.new_property(PyCodeRef::co_kwonlyargcount),
         "co_name" => context.new_property(PyCodeRef::co_name),
+        "co_varnames" => context.new_property(PyCodeRef::co_varnames),
     });
 }
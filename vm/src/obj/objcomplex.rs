@@ -2,6 +2,7 @@ use num_complex::Complex64;
 use num_traits::ToPrimitive;
 
 use crate::function::{OptionalArg, PyFuncArgs};
+use crate::pyhash;
 use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol};
 use crate::vm::VirtualMachine;
 
@@ -39,6 +40,7 @@ pub fn init(context: &PyContext) {
         "__add__" => context.new_rustfunc(complex_add),
         "__doc__" => context.new_str(complex_doc.to_string()),
         "__eq__" => context.new_rustfunc(complex_eq),
+        "__hash__" => context.new_rustfunc(complex_hash),
         "__neg__" => context.new_rustfunc(complex_neg),
         "__new__" => context.new_rustfunc(complex_new),
         "__radd__" => context.new_rustfunc(complex_radd),
@@ -138,6 +140,11 @@ fn complex_conjugate(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.ctx.new_complex(v1.conj()))
 }
 
+fn complex_hash(zelf: PyComplexRef, _vm: &VirtualMachine) -> pyhash::PyHash {
+    let Complex64 { re, im } = zelf.value;
+    pyhash::hash_complex(re, im)
+}
+
 fn complex_eq(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(
         vm,
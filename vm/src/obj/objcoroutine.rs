@@ -0,0 +1,168 @@
+/*
+ * Coroutines, as created by calling an `async def` function.
+ */
+
+use crate::frame::FrameRef;
+use crate::function::OptionalArg;
+use crate::obj::objgenerator::{GeneratorCore, GeneratorKind, PyGenerator};
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+pub type PyCoroutineRef = PyRef<PyCoroutine>;
+
+#[derive(Debug)]
+pub struct PyCoroutine {
+    inner: GeneratorCore,
+}
+
+impl PyValue for PyCoroutine {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.coroutine_type()
+    }
+}
+
+impl PyCoroutineRef {
+    pub fn new(frame: FrameRef, vm: &VirtualMachine) -> PyCoroutineRef {
+        PyCoroutine {
+            inner: GeneratorCore::new(frame, GeneratorKind::Coroutine),
+        }
+        .into_ref(vm)
+    }
+
+    fn send(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.inner.send(value, vm)
+    }
+
+    fn throw(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg,
+        exc_tb: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        self.inner
+            .throw(exc_type, exc_val.into_option(), exc_tb.into_option(), vm)
+    }
+
+    fn close(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.inner.close(vm)
+    }
+
+    fn await_(self, vm: &VirtualMachine) -> PyCoroutineWrapperRef {
+        PyCoroutineWrapper { coro: self }.into_ref(vm)
+    }
+
+    fn cr_running(self, _vm: &VirtualMachine) -> bool {
+        self.inner.running()
+    }
+
+    fn cr_frame(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.inner.frame_or_none(vm)
+    }
+
+    fn cr_await(self, vm: &VirtualMachine) -> PyObjectRef {
+        if self.inner.finished() {
+            return vm.get_none();
+        }
+        match self.inner.frame().delegate() {
+            Some(awaitable) => awaitable,
+            None => vm.get_none(),
+        }
+    }
+}
+
+pub type PyCoroutineWrapperRef = PyRef<PyCoroutineWrapper>;
+
+/// The iterator returned by `coroutine.__await__()`.
+#[derive(Debug)]
+pub struct PyCoroutineWrapper {
+    coro: PyCoroutineRef,
+}
+
+impl PyValue for PyCoroutineWrapper {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.coroutine_wrapper_type()
+    }
+}
+
+impl PyCoroutineWrapperRef {
+    fn iter(self, _vm: &VirtualMachine) -> PyCoroutineWrapperRef {
+        self
+    }
+
+    fn next(self, vm: &VirtualMachine) -> PyResult {
+        self.coro.inner.send(vm.get_none(), vm)
+    }
+
+    fn send(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.coro.inner.send(value, vm)
+    }
+
+    fn throw(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg,
+        exc_tb: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        self.coro
+            .inner
+            .throw(exc_type, exc_val.into_option(), exc_tb.into_option(), vm)
+    }
+
+    fn close(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.coro.inner.close(vm)
+    }
+}
+
+/// Get the iterator to delegate to when awaiting `obj`. Besides objects with
+/// an `__await__` method, generators are accepted, so that generator-based
+/// coroutines using `yield from` can be awaited.
+pub fn get_awaitable_iter(vm: &VirtualMachine, obj: &PyObjectRef) -> PyResult {
+    if let Some(coro) = obj.payload::<PyCoroutine>() {
+        if coro.inner.running() {
+            return Err(vm.new_runtime_error("coroutine is being awaited already".to_string()));
+        }
+    }
+    if obj.payload_is::<PyGenerator>() {
+        return Ok(obj.clone());
+    }
+    let await_method = vm.get_method(obj.clone(), "__await__").map_err(|_| {
+        vm.new_type_error(format!(
+            "object {} can't be used in 'await' expression",
+            obj.class().name
+        ))
+    })?;
+    let iter = vm.invoke(await_method, vec![])?;
+    if iter.payload_is::<PyCoroutine>() {
+        return Err(vm.new_type_error("__await__() returned a coroutine".to_string()));
+    }
+    if !objtype::class_has_attr(&iter.class(), "__next__") {
+        return Err(vm.new_type_error(format!(
+            "__await__() returned non-iterator of type '{}'",
+            iter.class().name
+        )));
+    }
+    Ok(iter)
+}
+
+pub fn init(context: &PyContext) {
+    extend_class!(context, &context.coroutine_type, {
+        "send" => context.new_rustfunc(PyCoroutineRef::send),
+        "throw" => context.new_rustfunc(PyCoroutineRef::throw),
+        "close" => context.new_rustfunc(PyCoroutineRef::close),
+        "__await__" => context.new_rustfunc(PyCoroutineRef::await_),
+        "cr_await" => context.new_property(PyCoroutineRef::cr_await),
+        "cr_frame" => context.new_property(PyCoroutineRef::cr_frame),
+        "cr_running" => context.new_property(PyCoroutineRef::cr_running)
+    });
+
+    extend_class!(context, &context.coroutine_wrapper_type, {
+        "__iter__" => context.new_rustfunc(PyCoroutineWrapperRef::iter),
+        "__next__" => context.new_rustfunc(PyCoroutineWrapperRef::next),
+        "send" => context.new_rustfunc(PyCoroutineWrapperRef::send),
+        "throw" => context.new_rustfunc(PyCoroutineWrapperRef::throw),
+        "close" => context.new_rustfunc(PyCoroutineWrapperRef::close)
+    });
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::fmt;
@@ -6,11 +7,14 @@ use std::ops::{Deref, DerefMut};
 use crate::function::{KwArgs, OptionalArg};
 use crate::pyobject::{
     DictProtocol, PyAttributes, PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult, PyValue,
+    TypeProtocol,
 };
 use crate::vm::{ReprGuard, VirtualMachine};
 
+use super::objint::PyInt;
 use super::objiter;
-use super::objstr::{self, PyStringRef};
+use super::objobject;
+use super::objstr;
 use super::objtype;
 use crate::obj::objtype::PyClassRef;
 
@@ -44,27 +48,59 @@ pub fn get_mut_elements<'a>(obj: &'a PyObjectRef) -> impl DerefMut<Target = Dict
     obj.payload::<PyDict>().unwrap().entries.borrow_mut()
 }
 
+/// Entries are stored by strings made from their keys. Only str and int keys
+/// are supported: int keys start with `KEY_PREFIX`, and str keys starting
+/// with it get another one, so that the two can't collide.
+const KEY_PREFIX: char = '\0';
+
+pub fn str_key(key: &str) -> Cow<'_, str> {
+    if key.starts_with(KEY_PREFIX) {
+        Cow::Owned(format!("{}{}", KEY_PREFIX, key))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// The string an entry with this key is stored by, if it is a supported key.
+fn content_key(needle: &PyObjectRef) -> Option<String> {
+    // TODO: invoke __hash__ function here!
+    if let Some(string) = needle.payload::<objstr::PyString>() {
+        Some(str_key(&string.value).into_owned())
+    } else if let Some(int) = needle.payload::<PyInt>() {
+        Some(format!("{}i{}", KEY_PREFIX, int.as_bigint()))
+    } else {
+        None
+    }
+}
+
+fn checked_content_key(needle: &PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+    content_key(needle).ok_or_else(|| {
+        vm.new_type_error(format!(
+            "unsupported dict key type: '{}'",
+            needle.class().name
+        ))
+    })
+}
+
 pub fn set_item(
     dict: &PyObjectRef,
-    _vm: &VirtualMachine,
+    vm: &VirtualMachine,
     needle: &PyObjectRef,
     value: &PyObjectRef,
-) {
+) -> PyResult<()> {
     // TODO: use vm to call eventual __hash__ and __eq__methods.
-    let mut elements = get_mut_elements(dict);
-    set_item_in_content(&mut elements, needle, value);
+    let key = checked_content_key(needle, vm)?;
+    get_mut_elements(dict).insert(key, (needle.clone(), value.clone()));
+    Ok(())
 }
 
+/// Set an item with a str key.
 pub fn set_item_in_content(
     elements: &mut DictContentType,
     needle: &PyObjectRef,
     value: &PyObjectRef,
 ) {
-    // XXX: Currently, we only support String keys, so we have to unwrap the
-    // PyObject (and ensure it is a String).
-
-    // TODO: invoke __hash__ function here!
-    let needle_str = objstr::get_value(needle);
+    let needle_str = str_key(objstr::borrow_value(needle)).into_owned();
     elements.insert(needle_str, (needle.clone(), value.clone()));
 }
 
@@ -85,8 +121,10 @@ pub fn get_key_value_pairs_from_content(
 }
 
 pub fn get_item(dict: &PyObjectRef, key: &PyObjectRef) -> Option<PyObjectRef> {
-    let needle_str = objstr::get_value(key);
-    get_key_str(dict, &needle_str)
+    let needle_str = content_key(key)?;
+    get_elements(dict)
+        .get(&needle_str)
+        .map(|entry| entry.1.clone())
 }
 
 // Special case for the case when requesting a str key from a dict:
@@ -98,7 +136,7 @@ pub fn get_key_str(dict: &PyObjectRef, key: &str) -> Option<PyObjectRef> {
 /// Retrieve a key from dict contents:
 pub fn content_get_key_str(elements: &DictContentType, key: &str) -> Option<PyObjectRef> {
     // TODO: let hash: usize = key;
-    match elements.get(key) {
+    match elements.get(str_key(key).as_ref()) {
         Some(v) => Some(v.1.clone()),
         None => None,
     }
@@ -111,15 +149,16 @@ pub fn contains_key_str(dict: &PyObjectRef, key: &str) -> bool {
 
 pub fn content_contains_key_str(elements: &DictContentType, key: &str) -> bool {
     // TODO: let hash: usize = key;
-    elements.get(key).is_some()
+    elements.get(str_key(key).as_ref()).is_some()
 }
 
 /// Take a python dictionary and convert it to attributes.
 pub fn py_dict_to_attributes(dict: &PyObjectRef) -> PyAttributes {
     let mut attrs = PyAttributes::new();
     for (key, value) in get_key_value_pairs(dict) {
-        let key = objstr::get_value(&key);
-        attrs.insert(key, value);
+        if let Some(key) = key.payload::<objstr::PyString>() {
+            attrs.insert(key.value.clone(), value);
+        }
     }
     attrs
 }
@@ -127,16 +166,16 @@ pub fn py_dict_to_attributes(dict: &PyObjectRef) -> PyAttributes {
 // Python dict methods:
 impl PyDictRef {
     fn new(
-        _class: PyClassRef, // TODO Support subclasses of int.
+        class: PyClassRef,
         dict_obj: OptionalArg<PyObjectRef>,
         kwargs: KwArgs,
         vm: &VirtualMachine,
     ) -> PyResult<PyDictRef> {
-        let dict = vm.ctx.new_dict();
+        let dict = PyDict::default().into_ref_with_type(vm, class)?;
         if let OptionalArg::Present(dict_obj) = dict_obj {
             if objtype::isinstance(&dict_obj, &vm.ctx.dict_type()) {
                 for (needle, value) in get_key_value_pairs(&dict_obj) {
-                    set_item(dict.as_object(), vm, &needle, &value);
+                    set_item(dict.as_object(), vm, &needle, &value)?;
                 }
             } else {
                 let iter = objiter::get_iter(vm, &dict_obj)?;
@@ -155,13 +194,13 @@ impl PyDictRef {
                     if objiter::get_next_object(vm, &elem_iter)?.is_some() {
                         return Err(err(vm));
                     }
-                    set_item(dict.as_object(), vm, &needle, &value);
+                    set_item(dict.as_object(), vm, &needle, &value)?;
                 }
             }
         }
         for (needle, value) in kwargs.into_iter() {
             let py_needle = vm.new_str(needle);
-            set_item(&dict.as_object(), vm, &py_needle, &value);
+            set_item(&dict.as_object(), vm, &py_needle, &value)?;
         }
         Ok(dict)
     }
@@ -191,17 +230,20 @@ impl PyDictRef {
         Ok(vm.new_str(s))
     }
 
-    fn contains(self, key: PyStringRef, _vm: &VirtualMachine) -> bool {
-        self.entries.borrow().contains_key(&key.value)
+    fn contains(self, key: PyObjectRef, _vm: &VirtualMachine) -> bool {
+        match content_key(&key) {
+            Some(key) => self.entries.borrow().contains_key(&key),
+            None => false,
+        }
     }
 
-    fn delitem(self, key: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
-        let key = &key.value;
+    fn delitem(self, key: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let needle = checked_content_key(&key, vm)?;
         // Delete the item:
         let mut elements = self.entries.borrow_mut();
-        match elements.remove(key) {
+        match elements.remove(&needle) {
             Some(_) => Ok(()),
-            None => Err(vm.new_value_error(format!("Key not found: {}", key))),
+            None => Err(vm.new_key_error(vm.to_repr(&key)?.value.clone())),
         }
     }
 
@@ -255,41 +297,40 @@ impl PyDictRef {
         }
     }
 
-    fn setitem(self, needle: PyObjectRef, value: PyObjectRef, _vm: &VirtualMachine) {
-        let mut elements = self.entries.borrow_mut();
-        set_item_in_content(&mut elements, &needle, &value)
+    fn setitem(self, needle: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        set_item(self.as_object(), vm, &needle, &value)
     }
 
-    fn getitem(self, key: PyStringRef, vm: &VirtualMachine) -> PyResult {
-        let key = &key.value;
+    fn getitem(self, key: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        let needle = checked_content_key(&key, vm)?;
 
         // What we are looking for:
         let elements = self.entries.borrow();
-        if elements.contains_key(key) {
-            Ok(elements[key].1.clone())
+        if elements.contains_key(&needle) {
+            Ok(elements[&needle].1.clone())
         } else {
-            Err(vm.new_value_error(format!("Key not found: {}", key)))
+            Err(vm.new_key_error(vm.to_repr(&key)?.value.clone()))
         }
     }
 
     fn get(
         self,
-        key: PyStringRef,
+        key: PyObjectRef,
         default: OptionalArg<PyObjectRef>,
         vm: &VirtualMachine,
-    ) -> PyObjectRef {
+    ) -> PyResult {
         // What we are looking for:
-        let key = &key.value;
+        let needle = checked_content_key(&key, vm)?;
 
         let elements = self.entries.borrow();
-        if elements.contains_key(key) {
-            elements[key].1.clone()
+        Ok(if elements.contains_key(&needle) {
+            elements[&needle].1.clone()
         } else {
             match default {
                 OptionalArg::Present(value) => value,
                 OptionalArg::Missing => vm.ctx.none(),
             }
-        }
+        })
     }
 }
 
@@ -314,7 +355,7 @@ impl DictProtocol for PyDictRef {
 
     fn del_item(&self, key: &str) {
         let mut elements = get_mut_elements(self.as_object());
-        elements.remove(key).unwrap();
+        elements.remove(str_key(key).as_ref()).unwrap();
     }
 }
 
@@ -325,6 +366,7 @@ pub fn init(context: &PyContext) {
         "__contains__" => context.new_rustfunc(PyDictRef::contains),
         "__delitem__" => context.new_rustfunc(PyDictRef::delitem),
         "__getitem__" => context.new_rustfunc(PyDictRef::getitem),
+        "__hash__" => context.new_rustfunc(objobject::unhashable),
         "__iter__" => context.new_rustfunc(PyDictRef::iter),
         "__new__" => context.new_rustfunc(PyDictRef::new),
         "__repr__" => context.new_rustfunc(PyDictRef::repr),
@@ -1,15 +1,23 @@
 use super::objbytes;
+use super::objclassmethod::PyClassMethod;
 use super::objint;
-use super::objstr;
+use super::objstr::{self, PyStringRef};
 use super::objtype;
+use crate::format::FormatSpec;
+use crate::function::OptionalArg;
 use crate::obj::objtype::PyClassRef;
+use crate::pyhash;
 use crate::pyobject::{
-    IntoPyObject, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+    IdProtocol, IntoPyObject, PyContext, PyObject, PyObjectRef, PyRef, PyResult, PyValue,
+    TypeProtocol,
 };
 use crate::vm::VirtualMachine;
-use num_bigint::ToBigInt;
+use num_bigint::{BigInt, ToBigInt};
+use num_complex::Complex64;
+use num_integer::Integer;
 use num_rational::Ratio;
-use num_traits::ToPrimitive;
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct PyFloat {
@@ -36,88 +44,294 @@ impl From<f64> for PyFloat {
 
 pub type PyFloatRef = PyRef<PyFloat>;
 
+/// The shortest string that converts back to the same float, written the
+/// way CPython's `repr` writes it.
+pub fn float_repr(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    } else if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    // Rust's exponent formatting gives the shortest round-tripping digits
+    let text = format!("{:e}", value);
+    let (mantissa, exponent) = text.split_at(text.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let (sign, mantissa) = if mantissa.starts_with('-') {
+        ("-", &mantissa[1..])
+    } else {
+        ("", mantissa)
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    if exponent < -4 || exponent >= 16 {
+        let mantissa = if digits.len() > 1 {
+            format!("{}.{}", &digits[..1], &digits[1..])
+        } else {
+            digits
+        };
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        format!(
+            "{}{}e{}{:02}",
+            sign,
+            mantissa,
+            exponent_sign,
+            exponent.abs()
+        )
+    } else if exponent < 0 {
+        let zeros = "0".repeat((-exponent - 1) as usize);
+        format!("{}0.{}{}", sign, zeros, digits)
+    } else {
+        let point = exponent as usize + 1;
+        if digits.len() > point {
+            format!("{}{}.{}", sign, &digits[..point], &digits[point..])
+        } else {
+            let zeros = "0".repeat(point - digits.len());
+            format!("{}{}{}.0", sign, digits, zeros)
+        }
+    }
+}
+
+/// Convert a float to an int, truncating towards zero.
+pub fn try_bigint(value: f64, vm: &VirtualMachine) -> PyResult<BigInt> {
+    match value.to_bigint() {
+        Some(int) => Ok(int),
+        None if value.is_infinite() => {
+            Err(vm.new_overflow_error("cannot convert float infinity to integer".to_string()))
+        }
+        None => Err(vm.new_value_error("cannot convert float NaN to integer".to_string())),
+    }
+}
+
+/// Compare a float with an int exactly, even if the int doesn't fit in a
+/// float. `None` when the float is NaN.
+fn compare_float_int(value: f64, int: &BigInt) -> Option<Ordering> {
+    if value.is_nan() {
+        None
+    } else if value.is_infinite() {
+        Some(if value > 0.0 {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        })
+    } else {
+        let floor = value.floor();
+        match floor.to_bigint().unwrap().cmp(int) {
+            Ordering::Equal if value > floor => Some(Ordering::Greater),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+/// The value of a float or int operand of an arithmetic operation, or
+/// `None` if floats don't support the operation with its type.
+fn to_float(vm: &VirtualMachine, obj: &PyObjectRef) -> PyResult<Option<f64>> {
+    if objtype::isinstance(obj, &vm.ctx.float_type()) {
+        Ok(Some(get_value(obj)))
+    } else if objtype::isinstance(obj, &vm.ctx.int_type()) {
+        objint::to_float(objint::get_value(obj))
+            .map(Some)
+            .ok_or_else(|| vm.new_overflow_error("int too large to convert to float".to_string()))
+    } else {
+        Ok(None)
+    }
+}
+
+fn float_op<F>(vm: &VirtualMachine, other: &PyObjectRef, op: F) -> PyResult
+where
+    F: FnOnce(f64) -> PyResult,
+{
+    match to_float(vm, other)? {
+        Some(other) => op(other),
+        None => Ok(vm.ctx.not_implemented()),
+    }
+}
+
+fn inner_mod(v1: f64, v2: f64, vm: &VirtualMachine) -> PyResult<f64> {
+    if v2 == 0.0 {
+        return Err(vm.new_zero_division_error("float modulo".to_string()));
+    }
+    Ok(divmod_nonzero(v1, v2).1)
+}
+
+fn inner_floordiv(v1: f64, v2: f64, vm: &VirtualMachine) -> PyResult<f64> {
+    if v2 == 0.0 {
+        return Err(vm.new_zero_division_error("float floor division by zero".to_string()));
+    }
+    Ok(divmod_nonzero(v1, v2).0)
+}
+
+fn inner_divmod(v1: f64, v2: f64, vm: &VirtualMachine) -> PyResult {
+    if v2 == 0.0 {
+        return Err(vm.new_zero_division_error("float divmod()".to_string()));
+    }
+    let (div, mod_) = divmod_nonzero(v1, v2);
+    Ok(vm
+        .ctx
+        .new_tuple(vec![vm.ctx.new_float(div), vm.ctx.new_float(mod_)]))
+}
+
+/// Floor division and modulo, with the signs and rounding of CPython's
+/// `float_divmod`.
+fn divmod_nonzero(v1: f64, v2: f64) -> (f64, f64) {
+    let mut mod_ = v1 % v2;
+    let mut div = (v1 - mod_) / v2;
+    if mod_ != 0.0 {
+        // The remainder takes the sign of the divisor
+        if (v2 < 0.0) != (mod_ < 0.0) {
+            mod_ += v2;
+            div -= 1.0;
+        }
+    } else {
+        mod_ = 0.0f64.copysign(v2);
+    }
+    let floordiv = if div != 0.0 {
+        let floor = div.floor();
+        if div - floor > 0.5 {
+            floor + 1.0
+        } else {
+            floor
+        }
+    } else {
+        0.0f64.copysign(v1 / v2)
+    };
+    (floordiv, mod_)
+}
+
+pub fn inner_pow(v1: f64, v2: f64, vm: &VirtualMachine) -> PyResult {
+    if v1 == 0.0 && v2 < 0.0 {
+        return Err(
+            vm.new_zero_division_error("0.0 cannot be raised to a negative power".to_string())
+        );
+    }
+    if v1 < 0.0 && v2.is_finite() && v2.fract() != 0.0 {
+        // A negative number to a fractional power is complex
+        let result = Complex64::new(v1, 0.0).powf(v2);
+        return Ok(vm.ctx.new_complex(result));
+    }
+    let result = v1.powf(v2);
+    if result.is_infinite() && v1.is_finite() && v2.is_finite() {
+        return Err(vm.new_overflow_error("(34, 'Numerical result out of range')".to_string()));
+    }
+    Ok(vm.ctx.new_float(result))
+}
+
+fn check_no_modulus(modulus: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+    match modulus {
+        OptionalArg::Present(ref modulus) if !vm.get_none().is(modulus) => Err(vm.new_type_error(
+            "pow() 3rd argument not allowed unless all arguments are integers".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Round to the nearest integer, with ties going to the even one.
+fn round_half_even(value: f64) -> f64 {
+    let rounded = value.round();
+    if (rounded - value).abs() == 0.5 {
+        2.0 * (value / 2.0).round()
+    } else {
+        rounded
+    }
+}
+
+/// Round to `ndigits` decimal digits, correctly rounding the exact value.
+fn round_digits(value: f64, ndigits: isize, vm: &VirtualMachine) -> PyResult<f64> {
+    if !value.is_finite() || value == 0.0 || ndigits > 325 {
+        return Ok(value);
+    }
+    if ndigits < -310 {
+        return Ok(0.0f64.copysign(value));
+    }
+    let rounded = if ndigits >= 0 {
+        // Formatting rounds half to even, based on the exact binary value
+        format!("{:.*}", ndigits as usize, value)
+    } else {
+        let scale = BigInt::from(10).pow((-ndigits) as usize);
+        let scaled = Ratio::from_float(value).unwrap() / Ratio::from_integer(scale);
+        let mut int = scaled.floor().to_integer();
+        let fraction = scaled - Ratio::from_integer(int.clone());
+        let half = Ratio::new(BigInt::one(), BigInt::from(2));
+        if fraction > half || (fraction == half && int.is_odd()) {
+            int += 1;
+        }
+        format!("{}e{}", int, -ndigits)
+    };
+    let result: f64 = rounded.parse().unwrap();
+    if result.is_infinite() {
+        return Err(vm.new_overflow_error("rounded value too large to represent".to_string()));
+    }
+    // A result of zero keeps the sign of the value, as in `round(-2.5, -1)`
+    if result == 0.0 {
+        Ok(result.copysign(value))
+    } else {
+        Ok(result)
+    }
+}
+
 impl PyFloatRef {
+    fn hash(self, _vm: &VirtualMachine) -> pyhash::PyHash {
+        pyhash::hash_float(self.value)
+    }
+
     fn eq(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
         let value = self.value;
         let result = if objtype::isinstance(&other, &vm.ctx.float_type()) {
-            let other = get_value(&other);
-            value == other
+            value == get_value(&other)
         } else if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let other_int = objint::get_value(&other);
-
-            if let (Some(self_int), Some(other_float)) = (value.to_bigint(), other_int.to_f64()) {
-                value == other_float && self_int == *other_int
-            } else {
-                false
-            }
+            compare_float_int(value, objint::get_value(&other)) == Some(Ordering::Equal)
         } else {
             return vm.ctx.not_implemented();
         };
         vm.ctx.new_bool(result)
     }
 
-    fn lt(self, i2: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        let v1 = self.value;
-        if objtype::isinstance(&i2, &vm.ctx.float_type()) {
-            vm.ctx.new_bool(v1 < get_value(&i2))
-        } else if objtype::isinstance(&i2, &vm.ctx.int_type()) {
-            vm.ctx
-                .new_bool(v1 < objint::get_value(&i2).to_f64().unwrap())
+    fn ne(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+        let value = self.value;
+        let result = if objtype::isinstance(&other, &vm.ctx.float_type()) {
+            value != get_value(&other)
+        } else if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            compare_float_int(value, objint::get_value(&other)) != Some(Ordering::Equal)
         } else {
-            vm.ctx.not_implemented()
-        }
+            return vm.ctx.not_implemented();
+        };
+        vm.ctx.new_bool(result)
     }
 
-    fn le(self, i2: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        let v1 = self.value;
-        if objtype::isinstance(&i2, &vm.ctx.float_type()) {
-            vm.ctx.new_bool(v1 <= get_value(&i2))
-        } else if objtype::isinstance(&i2, &vm.ctx.int_type()) {
-            vm.ctx
-                .new_bool(v1 <= objint::get_value(&i2).to_f64().unwrap())
+    fn compare<F>(self, other: PyObjectRef, vm: &VirtualMachine, accept: F) -> PyObjectRef
+    where
+        F: Fn(Ordering) -> bool,
+    {
+        let ordering = if objtype::isinstance(&other, &vm.ctx.float_type()) {
+            self.value.partial_cmp(&get_value(&other))
+        } else if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            compare_float_int(self.value, objint::get_value(&other))
         } else {
-            vm.ctx.not_implemented()
-        }
+            return vm.ctx.not_implemented();
+        };
+        vm.ctx.new_bool(ordering.map_or(false, accept))
     }
 
-    fn gt(self, i2: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        let v1 = self.value;
-        if objtype::isinstance(&i2, &vm.ctx.float_type()) {
-            vm.ctx.new_bool(v1 > get_value(&i2))
-        } else if objtype::isinstance(&i2, &vm.ctx.int_type()) {
-            vm.ctx
-                .new_bool(v1 > objint::get_value(&i2).to_f64().unwrap())
-        } else {
-            vm.ctx.not_implemented()
-        }
+    fn lt(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+        self.compare(other, vm, |ordering| ordering == Ordering::Less)
     }
 
-    fn ge(self, i2: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        let v1 = self.value;
-        if objtype::isinstance(&i2, &vm.ctx.float_type()) {
-            vm.ctx.new_bool(v1 >= get_value(&i2))
-        } else if objtype::isinstance(&i2, &vm.ctx.int_type()) {
-            vm.ctx
-                .new_bool(v1 >= objint::get_value(&i2).to_f64().unwrap())
-        } else {
-            vm.ctx.not_implemented()
-        }
+    fn le(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+        self.compare(other, vm, |ordering| ordering != Ordering::Greater)
+    }
+
+    fn gt(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+        self.compare(other, vm, |ordering| ordering == Ordering::Greater)
+    }
+
+    fn ge(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+        self.compare(other, vm, |ordering| ordering != Ordering::Less)
     }
 
     fn abs(self, _vm: &VirtualMachine) -> f64 {
         self.value.abs()
     }
 
-    fn add(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        let v1 = self.value;
-        if objtype::isinstance(&other, &vm.ctx.float_type()) {
-            vm.ctx.new_float(v1 + get_value(&other))
-        } else if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            vm.ctx
-                .new_float(v1 + objint::get_value(&other).to_f64().unwrap())
-        } else {
-            vm.ctx.not_implemented()
-        }
+    fn add(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        float_op(vm, &other, |other| Ok(vm.ctx.new_float(self.value + other)))
     }
 
     fn bool(self, _vm: &VirtualMachine) -> bool {
@@ -125,41 +339,38 @@ impl PyFloatRef {
     }
 
     fn divmod(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        if objtype::isinstance(&other, &vm.ctx.float_type())
-            || objtype::isinstance(&other, &vm.ctx.int_type())
-        {
-            let r1 = PyFloatRef::floordiv(self.clone(), other.clone(), vm)?;
-            let r2 = PyFloatRef::mod_(self, other, vm)?;
-            Ok(vm.ctx.new_tuple(vec![r1, r2]))
-        } else {
-            Ok(vm.ctx.not_implemented())
-        }
+        float_op(vm, &other, |other| inner_divmod(self.value, other, vm))
+    }
+
+    fn rdivmod(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        float_op(vm, &other, |other| inner_divmod(other, self.value, vm))
     }
 
     fn floordiv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        let v1 = self.value;
-        let v2 = if objtype::isinstance(&other, &vm.ctx.float_type) {
-            get_value(&other)
-        } else if objtype::isinstance(&other, &vm.ctx.int_type) {
-            objint::get_value(&other).to_f64().ok_or_else(|| {
-                vm.new_overflow_error("int too large to convert to float".to_string())
-            })?
-        } else {
-            return Ok(vm.ctx.not_implemented());
-        };
+        float_op(vm, &other, |other| {
+            Ok(vm.ctx.new_float(inner_floordiv(self.value, other, vm)?))
+        })
+    }
 
-        if v2 != 0.0 {
-            Ok(vm.ctx.new_float((v1 / v2).floor()))
-        } else {
-            Err(vm.new_zero_division_error("float floordiv by zero".to_string()))
-        }
+    fn rfloordiv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        float_op(vm, &other, |other| {
+            Ok(vm.ctx.new_float(inner_floordiv(other, self.value, vm)?))
+        })
     }
 
-    fn new_float(cls: PyClassRef, arg: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyFloatRef> {
+    fn new_float(
+        cls: PyClassRef,
+        arg: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyFloatRef> {
+        let arg = match arg {
+            OptionalArg::Present(arg) => arg,
+            OptionalArg::Missing => return PyFloat { value: 0.0 }.into_ref_with_type(vm, cls),
+        };
         let value = if objtype::isinstance(&arg, &vm.ctx.float_type()) {
             get_value(&arg)
         } else if objtype::isinstance(&arg, &vm.ctx.int_type()) {
-            match objint::get_value(&arg).to_f64() {
+            match objint::to_float(objint::get_value(&arg)) {
                 Some(f) => f,
                 None => {
                     return Err(
@@ -168,158 +379,171 @@ impl PyFloatRef {
                 }
             }
         } else if objtype::isinstance(&arg, &vm.ctx.str_type()) {
-            match lexical::try_parse(objstr::get_value(&arg)) {
-                Ok(f) => f,
-                Err(_) => {
-                    let arg_repr = vm.to_pystr(&arg)?;
+            match parse_str(&objstr::get_value(&arg)) {
+                Some(f) => f,
+                None => {
+                    let arg_repr = vm.to_repr(&arg)?;
                     return Err(vm.new_value_error(format!(
                         "could not convert string to float: {}",
-                        arg_repr
+                        arg_repr.value
                     )));
                 }
             }
         } else if objtype::isinstance(&arg, &vm.ctx.bytes_type()) {
-            match lexical::try_parse(objbytes::get_value(&arg).as_slice()) {
-                Ok(f) => f,
-                Err(_) => {
-                    let arg_repr = vm.to_pystr(&arg)?;
+            let parsed = std::str::from_utf8(&objbytes::get_value(&arg))
+                .ok()
+                .and_then(parse_str);
+            match parsed {
+                Some(f) => f,
+                None => {
+                    let arg_repr = vm.to_repr(&arg)?;
                     return Err(vm.new_value_error(format!(
                         "could not convert string to float: {}",
-                        arg_repr
+                        arg_repr.value
                     )));
                 }
             }
+        } else if vm.get_method(arg.clone(), "__float__").is_ok() {
+            make_float(vm, &arg)?
         } else {
-            return Err(vm.new_type_error(format!("can't convert {} to float", arg.class().name)));
+            return Err(vm.new_type_error(format!(
+                "float() argument must be a string or a real number, not '{}'",
+                arg.class().name
+            )));
         };
         PyFloat { value }.into_ref_with_type(vm, cls)
     }
 
     fn mod_(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        let v1 = self.value;
-        let v2 = if objtype::isinstance(&other, &vm.ctx.float_type) {
-            get_value(&other)
-        } else if objtype::isinstance(&other, &vm.ctx.int_type) {
-            objint::get_value(&other).to_f64().ok_or_else(|| {
-                vm.new_overflow_error("int too large to convert to float".to_string())
-            })?
-        } else {
-            return Ok(vm.ctx.not_implemented());
-        };
+        float_op(vm, &other, |other| {
+            Ok(vm.ctx.new_float(inner_mod(self.value, other, vm)?))
+        })
+    }
 
-        if v2 != 0.0 {
-            Ok(vm.ctx.new_float(v1 % v2))
-        } else {
-            Err(vm.new_zero_division_error("float mod by zero".to_string()))
-        }
+    fn rmod(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        float_op(vm, &other, |other| {
+            Ok(vm.ctx.new_float(inner_mod(other, self.value, vm)?))
+        })
+    }
+
+    fn pos(self, _vm: &VirtualMachine) -> f64 {
+        self.value
     }
 
     fn neg(self, _vm: &VirtualMachine) -> f64 {
         -self.value
     }
 
-    fn pow(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
-        let v1 = self.value;
-        if objtype::isinstance(&other, &vm.ctx.float_type()) {
-            vm.ctx.new_float(v1.powf(get_value(&other)))
-        } else if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let result = v1.powf(objint::get_value(&other).to_f64().unwrap());
-            vm.ctx.new_float(result)
-        } else {
-            vm.ctx.not_implemented()
-        }
+    fn pow(
+        self,
+        other: PyObjectRef,
+        modulus: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        check_no_modulus(modulus, vm)?;
+        float_op(vm, &other, |other| inner_pow(self.value, other, vm))
+    }
+
+    fn rpow(
+        self,
+        other: PyObjectRef,
+        modulus: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        check_no_modulus(modulus, vm)?;
+        float_op(vm, &other, |other| inner_pow(other, self.value, vm))
     }
 
     fn sub(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        let v1 = self.value;
-        if objtype::isinstance(&other, &vm.ctx.float_type()) {
-            Ok(vm.ctx.new_float(v1 - get_value(&other)))
-        } else if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            Ok(vm
-                .ctx
-                .new_float(v1 - objint::get_value(&other).to_f64().unwrap()))
-        } else {
-            Ok(vm.ctx.not_implemented())
-        }
+        float_op(vm, &other, |other| Ok(vm.ctx.new_float(self.value - other)))
     }
 
     fn rsub(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        let v1 = self.value;
-        if objtype::isinstance(&other, &vm.ctx.float_type()) {
-            Ok(vm.ctx.new_float(get_value(&other) - v1))
-        } else if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            Ok(vm
-                .ctx
-                .new_float(objint::get_value(&other).to_f64().unwrap() - v1))
-        } else {
-            Ok(vm.ctx.not_implemented())
-        }
+        float_op(vm, &other, |other| Ok(vm.ctx.new_float(other - self.value)))
     }
 
     fn repr(self, _vm: &VirtualMachine) -> String {
-        self.value.to_string()
+        float_repr(self.value)
     }
 
-    fn truediv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        let v1 = self.value;
-        let v2 = if objtype::isinstance(&other, &vm.ctx.float_type) {
-            get_value(&other)
-        } else if objtype::isinstance(&other, &vm.ctx.int_type) {
-            objint::get_value(&other).to_f64().ok_or_else(|| {
-                vm.new_overflow_error("int too large to convert to float".to_string())
-            })?
-        } else {
-            return Ok(vm.ctx.not_implemented());
-        };
+    fn format(self, spec: PyStringRef, vm: &VirtualMachine) -> PyResult<String> {
+        FormatSpec::parse(&spec.value)
+            .format_float(self.value)
+            .map_err(|err| vm.new_value_error(err.to_string()))
+    }
 
-        if v2 != 0.0 {
-            Ok(vm.ctx.new_float(v1 / v2))
-        } else {
-            Err(vm.new_zero_division_error("float division by zero".to_string()))
-        }
+    fn truediv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        float_op(vm, &other, |other| {
+            if other != 0.0 {
+                Ok(vm.ctx.new_float(self.value / other))
+            } else {
+                Err(vm.new_zero_division_error("float division by zero".to_string()))
+            }
+        })
     }
 
     fn rtruediv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        let v1 = self.value;
-        let v2 = if objtype::isinstance(&other, &vm.ctx.float_type) {
-            get_value(&other)
-        } else if objtype::isinstance(&other, &vm.ctx.int_type) {
-            objint::get_value(&other).to_f64().ok_or_else(|| {
-                vm.new_overflow_error("int too large to convert to float".to_string())
-            })?
-        } else {
-            return Ok(vm.ctx.not_implemented());
-        };
-
-        if v1 != 0.0 {
-            Ok(vm.ctx.new_float(v2 / v1))
-        } else {
-            Err(vm.new_zero_division_error("float division by zero".to_string()))
-        }
+        float_op(vm, &other, |other| {
+            if self.value != 0.0 {
+                Ok(vm.ctx.new_float(other / self.value))
+            } else {
+                Err(vm.new_zero_division_error("float division by zero".to_string()))
+            }
+        })
     }
 
     fn mul(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        let v1 = self.value;
-        if objtype::isinstance(&other, &vm.ctx.float_type) {
-            Ok(vm.ctx.new_float(v1 * get_value(&other)))
-        } else if objtype::isinstance(&other, &vm.ctx.int_type) {
-            Ok(vm
-                .ctx
-                .new_float(v1 * objint::get_value(&other).to_f64().unwrap()))
-        } else {
-            Ok(vm.ctx.not_implemented())
+        float_op(vm, &other, |other| Ok(vm.ctx.new_float(self.value * other)))
+    }
+
+    fn float(self, _vm: &VirtualMachine) -> f64 {
+        self.value
+    }
+
+    fn int(self, vm: &VirtualMachine) -> PyResult<BigInt> {
+        try_bigint(self.value, vm)
+    }
+
+    fn round(self, ndigits: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult {
+        let ndigits = match ndigits {
+            OptionalArg::Present(ref ndigits) if !vm.get_none().is(ndigits) => ndigits,
+            _ => {
+                let int = try_bigint(round_half_even(self.value), vm)?;
+                return Ok(vm.ctx.new_int(int));
+            }
+        };
+        if !objtype::isinstance(ndigits, &vm.ctx.int_type()) {
+            return Err(vm.new_type_error(format!(
+                "'{}' object cannot be interpreted as an integer",
+                ndigits.class().name
+            )));
         }
+        let ndigits = objint::get_value(ndigits).to_isize().unwrap_or_else(|| {
+            if objint::get_value(ndigits).is_negative() {
+                isize::min_value()
+            } else {
+                isize::max_value()
+            }
+        });
+        Ok(vm.ctx.new_float(round_digits(self.value, ndigits, vm)?))
     }
 
     fn is_integer(self, _vm: &VirtualMachine) -> bool {
-        let v = self.value;
-        (v - v.round()).abs() < std::f64::EPSILON
+        self.value.is_finite() && self.value.fract() == 0.0
     }
 
     fn real(self, _vm: &VirtualMachine) -> Self {
         self
     }
 
+    fn imag(self, _vm: &VirtualMachine) -> f64 {
+        0.0
+    }
+
+    fn conjugate(self, _vm: &VirtualMachine) -> Self {
+        self
+    }
+
     fn as_integer_ratio(self, vm: &VirtualMachine) -> PyResult {
         let value = self.value;
         if value.is_infinite() {
@@ -336,6 +560,167 @@ impl PyFloatRef {
         let denom = vm.ctx.new_int(ratio.denom().clone());
         Ok(vm.ctx.new_tuple(vec![numer, denom]))
     }
+
+    fn hex(self, _vm: &VirtualMachine) -> String {
+        to_hex(self.value)
+    }
+}
+
+/// Parse the text of a float, as accepted by `float()`.
+fn parse_str(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (sign, unsigned) = match text.chars().next() {
+        Some('-') => (-1.0, &text[1..]),
+        Some('+') => (1.0, &text[1..]),
+        _ => (1.0, text),
+    };
+    match unsigned.to_lowercase().as_str() {
+        "inf" | "infinity" => return Some(sign * std::f64::INFINITY),
+        "nan" => return Some(std::f64::NAN),
+        _ => {}
+    }
+    // Underscores may only separate digits
+    let bytes = text.as_bytes();
+    for (index, byte) in bytes.iter().enumerate() {
+        if *byte == b'_' {
+            let digit_before = index > 0 && bytes[index - 1].is_ascii_digit();
+            let digit_after = bytes.get(index + 1).map_or(false, u8::is_ascii_digit);
+            if !digit_before || !digit_after {
+                return None;
+            }
+        }
+    }
+    let text = text.replace('_', "");
+    if !text
+        .bytes()
+        .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+    {
+        return None;
+    }
+    lexical::try_parse(text).ok()
+}
+
+/// Format like `float.hex`, with all 13 hexadecimal digits of the mantissa.
+fn to_hex(value: f64) -> String {
+    if !value.is_finite() {
+        return float_repr(value);
+    }
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value == 0.0 {
+        return format!("{}0x0.0p+0", sign);
+    }
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    let mantissa = bits & ((1 << 52) - 1);
+    let (leading, exponent) = if exponent == 0 {
+        // Subnormal
+        (0, -1022)
+    } else {
+        (1, exponent - 1023)
+    };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+    format!(
+        "{}0x{}.{:013x}p{}{}",
+        sign,
+        leading,
+        mantissa,
+        exponent_sign,
+        exponent.abs()
+    )
+}
+
+/// Parse the output of `float.hex`, rounding to the nearest float.
+fn from_hex(text: &str) -> Result<f64, &'static str> {
+    const INVALID: &str = "invalid hexadecimal floating-point string";
+    let text = text.trim();
+    let (negative, unsigned) = match text.chars().next() {
+        Some('-') => (true, &text[1..]),
+        Some('+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let sign = if negative { -1.0 } else { 1.0 };
+    match unsigned.to_lowercase().as_str() {
+        "inf" | "infinity" => return Ok(sign * std::f64::INFINITY),
+        "nan" => return Ok(std::f64::NAN),
+        _ => {}
+    }
+    let unsigned = if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+        &unsigned[2..]
+    } else {
+        unsigned
+    };
+    let (digits, exponent) = match unsigned.find(|c| c == 'p' || c == 'P') {
+        Some(index) => {
+            let exponent: i64 = unsigned[index + 1..].parse().map_err(|_| INVALID)?;
+            (&unsigned[..index], exponent)
+        }
+        None => (unsigned, 0),
+    };
+    let (integer, fraction) = match digits.find('.') {
+        Some(index) => (&digits[..index], &digits[index + 1..]),
+        None => (digits, ""),
+    };
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(INVALID);
+    }
+    let all_digits = format!("{}{}", integer, fraction);
+    if !all_digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(INVALID);
+    }
+    let mantissa = BigInt::parse_bytes(all_digits.as_bytes(), 16).ok_or(INVALID)?;
+    if mantissa.is_zero() {
+        return Ok(sign * 0.0);
+    }
+    // The value is mantissa * 2 ** exponent
+    let exponent = exponent - 4 * fraction.len() as i64;
+    let bits = mantissa.bits() as i64;
+    if exponent + bits > 1024 {
+        return Err("hexadecimal value too large to represent as a float");
+    }
+    // Drop the bits beyond the precision of a float, or of a subnormal
+    let shift = std::cmp::max(bits - 53, -1074 - exponent);
+    let mut mantissa = mantissa;
+    let mut exponent = exponent;
+    if shift > 0 {
+        let quotient: BigInt = &mantissa >> shift as usize;
+        let remainder = &mantissa - (&quotient << shift as usize);
+        let half = BigInt::one() << (shift - 1) as usize;
+        mantissa = quotient;
+        if remainder > half || (remainder == half && mantissa.is_odd()) {
+            mantissa += 1;
+        }
+        exponent += shift;
+    }
+    // Scale in steps that are exact, since the result is representable
+    let mut result = mantissa.to_f64().unwrap();
+    while exponent < -1000 {
+        result *= 2.0f64.powi(-1000);
+        exponent += 1000;
+    }
+    while exponent > 1000 {
+        result *= 2.0f64.powi(1000);
+        exponent -= 1000;
+    }
+    result *= 2.0f64.powi(exponent as i32);
+    if result.is_infinite() {
+        return Err("hexadecimal value too large to represent as a float");
+    }
+    Ok(sign * result)
+}
+
+fn float_fromhex(cls: PyClassRef, string: PyStringRef, vm: &VirtualMachine) -> PyResult {
+    let value = from_hex(&string.value).map_err(|err| {
+        if err.starts_with("invalid") {
+            vm.new_value_error(err.to_string())
+        } else {
+            vm.new_overflow_error(err.to_string())
+        }
+    })?;
+    if cls.is(&vm.ctx.float_type()) {
+        Ok(vm.ctx.new_float(value))
+    } else {
+        vm.invoke(cls.into_object(), vec![vm.ctx.new_float(value)])
+    }
 }
 
 // Retrieve inner float value:
@@ -348,7 +733,15 @@ pub fn make_float(vm: &VirtualMachine, obj: &PyObjectRef) -> PyResult<f64> {
         Ok(get_value(obj))
     } else if let Ok(method) = vm.get_method(obj.clone(), "__float__") {
         let res = vm.invoke(method, vec![])?;
-        Ok(get_value(&res))
+        if objtype::isinstance(&res, &vm.ctx.float_type()) {
+            Ok(get_value(&res))
+        } else {
+            Err(vm.new_type_error(format!(
+                "{}.__float__ returned non-float (type {})",
+                obj.class().name,
+                res.class().name
+            )))
+        }
     } else {
         Err(vm.new_type_error(format!("Cannot cast {} to float", obj)))
     }
@@ -362,6 +755,8 @@ pub fn init(context: &PyContext) {
 
     extend_class!(context, float_type, {
         "__eq__" => context.new_rustfunc(PyFloatRef::eq),
+        "__ne__" => context.new_rustfunc(PyFloatRef::ne),
+        "__hash__" => context.new_rustfunc(PyFloatRef::hash),
         "__lt__" => context.new_rustfunc(PyFloatRef::lt),
         "__le__" => context.new_rustfunc(PyFloatRef::le),
         "__gt__" => context.new_rustfunc(PyFloatRef::gt),
@@ -371,21 +766,92 @@ pub fn init(context: &PyContext) {
         "__radd__" => context.new_rustfunc(PyFloatRef::add),
         "__bool__" => context.new_rustfunc(PyFloatRef::bool),
         "__divmod__" => context.new_rustfunc(PyFloatRef::divmod),
+        "__rdivmod__" => context.new_rustfunc(PyFloatRef::rdivmod),
         "__floordiv__" => context.new_rustfunc(PyFloatRef::floordiv),
+        "__rfloordiv__" => context.new_rustfunc(PyFloatRef::rfloordiv),
         "__new__" => context.new_rustfunc(PyFloatRef::new_float),
         "__mod__" => context.new_rustfunc(PyFloatRef::mod_),
+        "__rmod__" => context.new_rustfunc(PyFloatRef::rmod),
+        "__pos__" => context.new_rustfunc(PyFloatRef::pos),
         "__neg__" => context.new_rustfunc(PyFloatRef::neg),
         "__pow__" => context.new_rustfunc(PyFloatRef::pow),
+        "__rpow__" => context.new_rustfunc(PyFloatRef::rpow),
         "__sub__" => context.new_rustfunc(PyFloatRef::sub),
         "__rsub__" => context.new_rustfunc(PyFloatRef::rsub),
         "__repr__" => context.new_rustfunc(PyFloatRef::repr),
+        "__format__" => context.new_rustfunc(PyFloatRef::format),
         "__doc__" => context.new_str(float_doc.to_string()),
         "__truediv__" => context.new_rustfunc(PyFloatRef::truediv),
         "__rtruediv__" => context.new_rustfunc(PyFloatRef::rtruediv),
         "__mul__" => context.new_rustfunc(PyFloatRef::mul),
         "__rmul__" => context.new_rustfunc(PyFloatRef::mul),
+        "__float__" => context.new_rustfunc(PyFloatRef::float),
+        "__int__" => context.new_rustfunc(PyFloatRef::int),
+        "__trunc__" => context.new_rustfunc(PyFloatRef::int),
+        "__round__" => context.new_rustfunc(PyFloatRef::round),
         "real" => context.new_property(PyFloatRef::real),
+        "imag" => context.new_property(PyFloatRef::imag),
+        "conjugate" => context.new_rustfunc(PyFloatRef::conjugate),
         "is_integer" => context.new_rustfunc(PyFloatRef::is_integer),
-        "as_integer_ratio" => context.new_rustfunc(PyFloatRef::as_integer_ratio)
+        "as_integer_ratio" => context.new_rustfunc(PyFloatRef::as_integer_ratio),
+        "hex" => context.new_rustfunc(PyFloatRef::hex),
+        "fromhex" => PyObject::new(
+            PyClassMethod {
+                callable: context.new_rustfunc(float_fromhex),
+            },
+            context.classmethod_type(),
+            None,
+        )
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_repr() {
+        assert_eq!(float_repr(1.0), "1.0");
+        assert_eq!(float_repr(-0.0), "-0.0");
+        assert_eq!(float_repr(0.1), "0.1");
+        assert_eq!(float_repr(1e16), "1e+16");
+        assert_eq!(float_repr(1234567890123456.0), "1234567890123456.0");
+        assert_eq!(float_repr(0.0001), "0.0001");
+        assert_eq!(float_repr(0.00001), "1e-05");
+        assert_eq!(float_repr(1.5e-300), "1.5e-300");
+        assert_eq!(float_repr(std::f64::NEG_INFINITY), "-inf");
+    }
+
+    #[test]
+    fn test_float_hex() {
+        assert_eq!(to_hex(1.5), "0x1.8000000000000p+0");
+        assert_eq!(to_hex(-0.0), "-0x0.0p+0");
+        assert_eq!(to_hex(5e-324), "0x0.0000000000001p-1022");
+        for value in &[1.5, 0.1, -3.75e-310, 5e-324, std::f64::MAX, 1e100] {
+            assert_eq!(from_hex(&to_hex(*value)), Ok(*value));
+        }
+        assert_eq!(from_hex("0x1p-1075"), Ok(0.0));
+        assert_eq!(from_hex("0x3p-1075"), Ok(1e-323));
+        assert!(from_hex("0x1p1024").is_err());
+        assert!(from_hex("0x").is_err());
+    }
+
+    #[test]
+    fn test_divmod() {
+        assert_eq!(divmod_nonzero(7.0, 2.0), (3.0, 1.0));
+        assert_eq!(divmod_nonzero(-7.0, 2.0), (-4.0, 1.0));
+        assert_eq!(divmod_nonzero(7.0, -2.0), (-4.0, -1.0));
+        assert_eq!(divmod_nonzero(0.0, -2.0).1.to_bits(), (-0.0f64).to_bits());
+    }
+
+    #[test]
+    fn test_round_digits() {
+        let vm = VirtualMachine::new();
+        assert_eq!(round_digits(2.675, 2, &vm).unwrap(), 2.67);
+        assert_eq!(round_digits(1250.0, -2, &vm).unwrap(), 1200.0);
+        let zero = round_digits(-2.5, -1, &vm).unwrap();
+        assert_eq!(zero.to_bits(), (-0.0f64).to_bits());
+        let zero = round_digits(-0.001, 2, &vm).unwrap();
+        assert_eq!(zero.to_bits(), (-0.0f64).to_bits());
+    }
+}
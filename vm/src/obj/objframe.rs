@@ -26,8 +26,8 @@ impl FrameRef {
         "<frame object at .. >".to_string()
     }
 
-    fn flocals(self, _vm: &VirtualMachine) -> PyDictRef {
-        self.scope.get_locals()
+    fn flocals(self, vm: &VirtualMachine) -> PyDictRef {
+        self.get_locals(vm)
     }
 
     fn fcode(self, vm: &VirtualMachine) -> PyCodeRef {
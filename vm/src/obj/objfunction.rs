@@ -1,4 +1,5 @@
 use crate::frame::Scope;
+use crate::obj::objcell::PyCellRef;
 use crate::obj::objcode::PyCodeRef;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol};
@@ -12,14 +13,21 @@ pub struct PyFunction {
     pub code: PyCodeRef,
     pub scope: Scope,
     pub defaults: PyObjectRef,
+    pub closure: Vec<PyCellRef>,
 }
 
 impl PyFunction {
-    pub fn new(code: PyCodeRef, scope: Scope, defaults: PyObjectRef) -> Self {
+    pub fn new(
+        code: PyCodeRef,
+        scope: Scope,
+        defaults: PyObjectRef,
+        closure: Vec<PyCellRef>,
+    ) -> Self {
         PyFunction {
             code,
             scope,
             defaults,
+            closure,
         }
     }
 }
@@ -34,8 +42,23 @@ impl PyFunctionRef {
     fn code(self, _vm: &VirtualMachine) -> PyCodeRef {
         self.code.clone()
     }
+
+    fn closure(self, vm: &VirtualMachine) -> PyObjectRef {
+        if self.closure.is_empty() {
+            vm.get_none()
+        } else {
+            let cells = self
+                .closure
+                .iter()
+                .map(|cell| cell.clone().into_object())
+                .collect();
+            vm.ctx.new_tuple(cells)
+        }
+    }
 }
 
+pub type PyMethodRef = PyRef<PyMethod>;
+
 #[derive(Debug)]
 pub struct PyMethod {
     // TODO: these shouldn't be public
@@ -55,11 +78,25 @@ impl PyValue for PyMethod {
     }
 }
 
+impl PyMethodRef {
+    fn doc(self, vm: &VirtualMachine) -> PyResult {
+        vm.get_attribute(self.function.clone(), "__doc__")
+    }
+}
+
 pub fn init(context: &PyContext) {
     let function_type = &context.function_type;
     extend_class!(context, function_type, {
         "__get__" => context.new_rustfunc(bind_method),
-        "__code__" => context.new_property(PyFunctionRef::code)
+        "__code__" => context.new_property(PyFunctionRef::code),
+        "__closure__" => context.new_property(PyFunctionRef::closure),
+        // Replaced by the docstring of functions which have one:
+        "__doc__" => context.none()
+    });
+
+    let bound_method_type = &context.bound_method_type;
+    extend_class!(context, bound_method_type, {
+        "__doc__" => context.new_property(PyMethodRef::doc)
     });
 
     let builtin_function_or_method_type = &context.builtin_function_or_method_type;
@@ -2,16 +2,214 @@
  * The mythical generator.
  */
 
+use std::cell::Cell;
+
 use crate::frame::{ExecutionResult, FrameRef};
-use crate::obj::objtype::PyClassRef;
-use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::function::OptionalArg;
+use crate::obj::objiter;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{
+    IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
+};
+use crate::traceback::PyTraceback;
 use crate::vm::VirtualMachine;
 
 pub type PyGeneratorRef = PyRef<PyGenerator>;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GeneratorState {
+    Created,
+    Suspended,
+    Running,
+    Finished,
+}
+
+/// The kinds of objects which run a suspendable frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeneratorKind {
+    Generator,
+    Coroutine,
+    AsyncGenerator,
+}
+
+impl GeneratorKind {
+    fn name(self) -> &'static str {
+        match self {
+            GeneratorKind::Generator => "generator",
+            GeneratorKind::Coroutine => "coroutine",
+            GeneratorKind::AsyncGenerator => "async generator",
+        }
+    }
+}
+
+/// The frame of a generator, coroutine or async generator, and the state it
+/// is in. Resuming the frame through this makes sure it is never run twice
+/// at the same time, nor after it has finished.
 #[derive(Debug)]
-pub struct PyGenerator {
+pub struct GeneratorCore {
     frame: FrameRef,
+    state: Cell<GeneratorState>,
+    kind: GeneratorKind,
+}
+
+impl GeneratorCore {
+    pub fn new(frame: FrameRef, kind: GeneratorKind) -> Self {
+        GeneratorCore {
+            frame,
+            state: Cell::new(GeneratorState::Created),
+            kind,
+        }
+    }
+
+    pub fn frame(&self) -> &FrameRef {
+        &self.frame
+    }
+
+    pub fn running(&self) -> bool {
+        self.state.get() == GeneratorState::Running
+    }
+
+    pub fn finished(&self) -> bool {
+        self.state.get() == GeneratorState::Finished
+    }
+
+    /// The frame, unless it has finished running.
+    pub fn frame_or_none(&self, vm: &VirtualMachine) -> PyObjectRef {
+        if self.finished() {
+            vm.get_none()
+        } else {
+            self.frame.clone().into_object()
+        }
+    }
+
+    pub fn send(&self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        match self.state.get() {
+            GeneratorState::Created => {
+                if !value.is(&vm.get_none()) {
+                    return Err(vm.new_type_error(format!(
+                        "can't send non-None value to a just-started {}",
+                        self.kind.name()
+                    )));
+                }
+            }
+            GeneratorState::Suspended => self.frame.push_value(value),
+            GeneratorState::Running => return Err(self.already_executing(vm)),
+            GeneratorState::Finished => {
+                return Err(if self.kind == GeneratorKind::Coroutine {
+                    vm.new_runtime_error("cannot reuse already awaited coroutine".to_string())
+                } else {
+                    objiter::new_stop_iteration(vm)
+                });
+            }
+        }
+        self.resume(vm, |frame| vm.run_frame(frame))
+    }
+
+    pub fn throw(
+        &self,
+        exc_type: PyObjectRef,
+        exc_val: Option<PyObjectRef>,
+        exc_tb: Option<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        let exception = make_exception(exc_type, exc_val, vm)?;
+        if let Some(tb) = exc_tb {
+            if !tb.is(&vm.get_none()) {
+                if tb.payload_is::<PyTraceback>() {
+                    vm.set_attr(&exception, vm.new_str("__traceback__".to_string()), tb)?;
+                } else {
+                    return Err(vm.new_type_error(
+                        "throw() third argument must be a traceback object".to_string(),
+                    ));
+                }
+            }
+        }
+        match self.state.get() {
+            GeneratorState::Running => Err(self.already_executing(vm)),
+            GeneratorState::Finished => Err(exception),
+            GeneratorState::Created | GeneratorState::Suspended => self.resume(vm, |frame| {
+                vm.with_frame(frame, |frame| frame.throw(vm, exception))
+            }),
+        }
+    }
+
+    pub fn close(&self, vm: &VirtualMachine) -> PyResult<()> {
+        match self.state.get() {
+            GeneratorState::Created | GeneratorState::Finished => {
+                self.state.set(GeneratorState::Finished);
+                return Ok(());
+            }
+            GeneratorState::Running => return Err(self.already_executing(vm)),
+            GeneratorState::Suspended => {}
+        }
+        let generator_exit = vm.ctx.exceptions.generator_exit.clone().into_object();
+        match self.throw(generator_exit, None, None, vm) {
+            Ok(_) => {
+                Err(vm.new_runtime_error(format!("{} ignored GeneratorExit", self.kind.name())))
+            }
+            Err(exception) => {
+                if objtype::isinstance(&exception, &vm.ctx.exceptions.generator_exit)
+                    || objtype::isinstance(&exception, &vm.ctx.exceptions.stop_iteration)
+                {
+                    Ok(())
+                } else {
+                    Err(exception)
+                }
+            }
+        }
+    }
+
+    /// Run the frame until it yields, returns or raises.
+    fn resume<F>(&self, vm: &VirtualMachine, run: F) -> PyResult
+    where
+        F: FnOnce(FrameRef) -> PyResult<ExecutionResult>,
+    {
+        self.state.set(GeneratorState::Running);
+        let result = run(self.frame.clone());
+        match result {
+            Ok(ExecutionResult::Yield(value)) => {
+                self.state.set(GeneratorState::Suspended);
+                Ok(value)
+            }
+            Ok(ExecutionResult::Return(value)) => {
+                self.state.set(GeneratorState::Finished);
+                Err(objiter::new_stop_iteration_with_value(vm, value)?)
+            }
+            Err(exception) => {
+                self.state.set(GeneratorState::Finished);
+                // A StopIteration escaping the body would silently end the
+                // iteration, so it is replaced:
+                let escaped = if objtype::isinstance(&exception, &vm.ctx.exceptions.stop_iteration)
+                {
+                    Some("StopIteration")
+                } else if self.kind == GeneratorKind::AsyncGenerator
+                    && objtype::isinstance(&exception, &vm.ctx.exceptions.stop_async_iteration)
+                {
+                    Some("StopAsyncIteration")
+                } else {
+                    None
+                };
+                match escaped {
+                    Some(name) => {
+                        let error =
+                            vm.new_runtime_error(format!("{} raised {}", self.kind.name(), name));
+                        vm.set_attr(&error, vm.new_str("__cause__".to_string()), exception)?;
+                        Err(error)
+                    }
+                    None => Err(exception),
+                }
+            }
+        }
+    }
+
+    fn already_executing(&self, vm: &VirtualMachine) -> PyObjectRef {
+        vm.new_value_error(format!("{} already executing", self.kind.name()))
+    }
+}
+
+#[derive(Debug)]
+pub struct PyGenerator {
+    inner: GeneratorCore,
 }
 
 impl PyValue for PyGenerator {
@@ -22,7 +220,10 @@ impl PyValue for PyGenerator {
 
 impl PyGeneratorRef {
     pub fn new(frame: FrameRef, vm: &VirtualMachine) -> PyGeneratorRef {
-        PyGenerator { frame }.into_ref(vm)
+        PyGenerator {
+            inner: GeneratorCore::new(frame, GeneratorKind::Generator),
+        }
+        .into_ref(vm)
     }
 
     fn iter(self, _vm: &VirtualMachine) -> PyGeneratorRef {
@@ -34,16 +235,68 @@ impl PyGeneratorRef {
     }
 
     fn send(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        self.frame.push_value(value.clone());
+        self.inner.send(value, vm)
+    }
 
-        match vm.run_frame(self.frame.clone())? {
-            ExecutionResult::Yield(value) => Ok(value),
-            ExecutionResult::Return(_value) => {
-                // Stop iteration!
-                let stop_iteration = vm.ctx.exceptions.stop_iteration.clone();
-                Err(vm.new_exception(stop_iteration, "End of generator".to_string()))
-            }
+    fn throw(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg,
+        exc_tb: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        self.inner
+            .throw(exc_type, exc_val.into_option(), exc_tb.into_option(), vm)
+    }
+
+    fn close(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.inner.close(vm)
+    }
+
+    fn gi_running(self, _vm: &VirtualMachine) -> bool {
+        self.inner.running()
+    }
+
+    fn gi_frame(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.inner.frame_or_none(vm)
+    }
+}
+
+/// Build the exception to throw from the arguments of `throw()`, which
+/// follow the old `raise type, value` form.
+pub fn make_exception(
+    exc_type: PyObjectRef,
+    exc_val: Option<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    let base_exception_type = &vm.ctx.exceptions.base_exception_type;
+    let exc_val = exc_val.filter(|value| !value.is(&vm.get_none()));
+    if objtype::isinstance(&exc_type, base_exception_type) {
+        if exc_val.is_some() {
+            return Err(
+                vm.new_type_error("instance exception may not have a separate value".to_string())
+            );
         }
+        return Ok(exc_type);
+    }
+    let cls = PyClassRef::try_from_object(vm, exc_type.clone())
+        .ok()
+        .filter(|cls| objtype::issubclass(cls, base_exception_type));
+    match cls {
+        Some(cls) => match exc_val {
+            Some(value) => {
+                if objtype::isinstance(&value, &cls) {
+                    Ok(value)
+                } else {
+                    vm.invoke(cls.into_object(), vec![value])
+                }
+            }
+            None => vm.new_empty_exception(cls),
+        },
+        None => Err(vm.new_type_error(format!(
+            "exceptions must be classes or instances deriving from BaseException, not {}",
+            exc_type.class().name
+        ))),
     }
 }
 
@@ -52,6 +305,10 @@ pub fn init(context: &PyContext) {
     extend_class!(context, generator_type, {
         "__iter__" => context.new_rustfunc(PyGeneratorRef::iter),
         "__next__" => context.new_rustfunc(PyGeneratorRef::next),
-        "send" => context.new_rustfunc(PyGeneratorRef::send)
+        "send" => context.new_rustfunc(PyGeneratorRef::send),
+        "throw" => context.new_rustfunc(PyGeneratorRef::throw),
+        "close" => context.new_rustfunc(PyGeneratorRef::close),
+        "gi_frame" => context.new_property(PyGeneratorRef::gi_frame),
+        "gi_running" => context.new_property(PyGeneratorRef::gi_running)
     });
 }
@@ -1,19 +1,23 @@
 use std::fmt;
-use std::hash::{Hash, Hasher};
 
-use num_bigint::{BigInt, ToBigInt};
+use num_bigint::{BigInt, Sign};
 use num_integer::Integer;
-use num_traits::{Pow, Signed, ToPrimitive, Zero};
+use num_traits::{One, Pow, Signed, ToPrimitive, Zero};
 
 use crate::format::FormatSpec;
 use crate::function::OptionalArg;
+use crate::pyhash;
 use crate::pyobject::{
-    IntoPyObject, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
+    IdProtocol, IntoPyObject, PyContext, PyObject, PyObjectRef, PyRef, PyResult, PyValue,
+    TryFromObject, TypeProtocol,
 };
 use crate::vm::VirtualMachine;
 
+use super::objbyteinner;
+use super::objbytes::PyBytes;
+use super::objclassmethod::PyClassMethod;
 use super::objfloat;
-use super::objstr;
+use super::objstr::{self, PyStringRef};
 use super::objtype;
 use crate::obj::objtype::PyClassRef;
 
@@ -62,7 +66,7 @@ macro_rules! impl_into_pyobject_int {
     )*};
 }
 
-impl_into_pyobject_int!(isize i8 i16 i32 i64 usize u8 u16 u32 u64) ;
+impl_into_pyobject_int!(isize i8 i16 i32 i64 usize u8 u16 u32 u64);
 
 macro_rules! impl_try_from_object_int {
     ($(($t:ty, $to_prim:ident),)*) => {$(
@@ -198,52 +202,49 @@ impl PyIntRef {
 
     fn floordiv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            if *v2 != BigInt::zero() {
-                Ok(vm.ctx.new_int((&self.value) / v2))
-            } else {
-                Err(vm.new_zero_division_error("integer floordiv by zero".to_string()))
-            }
+            inner_floordiv(&self.value, get_value(&other), vm)
         } else {
             Ok(vm.ctx.not_implemented())
         }
     }
 
-    fn lshift(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        if !objtype::isinstance(&other, &vm.ctx.int_type()) {
-            return Ok(vm.ctx.not_implemented());
+    fn rfloordiv(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            inner_floordiv(get_value(&other), &self.value, vm)
+        } else {
+            Ok(vm.ctx.not_implemented())
         }
+    }
 
-        if let Some(n_bits) = get_value(&other).to_usize() {
-            return Ok(vm.ctx.new_int((&self.value) << n_bits));
+    fn lshift(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            inner_lshift(&self.value, get_value(&other), vm)
+        } else {
+            Ok(vm.ctx.not_implemented())
         }
+    }
 
-        // i2 failed `to_usize()` conversion
-        match get_value(&other) {
-            v if *v < BigInt::zero() => Err(vm.new_value_error("negative shift count".to_string())),
-            v if *v > BigInt::from(usize::max_value()) => {
-                Err(vm.new_overflow_error("the number is too large to convert to int".to_string()))
-            }
-            _ => panic!("Failed converting {} to rust usize", get_value(&other)),
+    fn rlshift(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            inner_lshift(get_value(&other), &self.value, vm)
+        } else {
+            Ok(vm.ctx.not_implemented())
         }
     }
 
     fn rshift(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-        if !objtype::isinstance(&other, &vm.ctx.int_type()) {
-            return Ok(vm.ctx.not_implemented());
-        }
-
-        if let Some(n_bits) = get_value(&other).to_usize() {
-            return Ok(vm.ctx.new_int((&self.value) >> n_bits));
+        if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            inner_rshift(&self.value, get_value(&other), vm)
+        } else {
+            Ok(vm.ctx.not_implemented())
         }
+    }
 
-        // i2 failed `to_usize()` conversion
-        match get_value(&other) {
-            v if *v < BigInt::zero() => Err(vm.new_value_error("negative shift count".to_string())),
-            v if *v > BigInt::from(usize::max_value()) => {
-                Err(vm.new_overflow_error("the number is too large to convert to int".to_string()))
-            }
-            _ => panic!("Failed converting {} to rust usize", get_value(&other)),
+    fn rrshift(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            inner_rshift(get_value(&other), &self.value, vm)
+        } else {
+            Ok(vm.ctx.not_implemented())
         }
     }
 
@@ -280,26 +281,43 @@ impl PyIntRef {
         }
     }
 
-    fn pow(self, other: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    fn pow(
+        self,
+        other: PyObjectRef,
+        modulus: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other).to_u32().unwrap();
-            vm.ctx.new_int(self.value.pow(v2))
-        } else if objtype::isinstance(&other, &vm.ctx.float_type()) {
-            let v2 = objfloat::get_value(&other);
-            vm.ctx.new_float((self.value.to_f64().unwrap()).powf(v2))
+            inner_pow(&self.value, get_value(&other), modulus, vm)
         } else {
-            vm.ctx.not_implemented()
+            Ok(vm.ctx.not_implemented())
+        }
+    }
+
+    fn rpow(
+        self,
+        other: PyObjectRef,
+        modulus: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            inner_pow(get_value(&other), &self.value, modulus, vm)
+        } else {
+            Ok(vm.ctx.not_implemented())
         }
     }
 
     fn mod_(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            if *v2 != BigInt::zero() {
-                Ok(vm.ctx.new_int((&self.value) % v2))
-            } else {
-                Err(vm.new_zero_division_error("integer modulo by zero".to_string()))
-            }
+            inner_mod(&self.value, get_value(&other), vm)
+        } else {
+            Ok(vm.ctx.not_implemented())
+        }
+    }
+
+    fn rmod(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            inner_mod(get_value(&other), &self.value, vm)
         } else {
             Ok(vm.ctx.not_implemented())
         }
@@ -307,15 +325,15 @@ impl PyIntRef {
 
     fn divmod(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
         if objtype::isinstance(&other, &vm.ctx.int_type()) {
-            let v2 = get_value(&other);
-            if *v2 != BigInt::zero() {
-                let (r1, r2) = self.value.div_rem(v2);
-                Ok(vm
-                    .ctx
-                    .new_tuple(vec![vm.ctx.new_int(r1), vm.ctx.new_int(r2)]))
-            } else {
-                Err(vm.new_zero_division_error("integer divmod by zero".to_string()))
-            }
+            inner_divmod(&self.value, get_value(&other), vm)
+        } else {
+            Ok(vm.ctx.not_implemented())
+        }
+    }
+
+    fn rdivmod(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if objtype::isinstance(&other, &vm.ctx.int_type()) {
+            inner_divmod(get_value(&other), &self.value, vm)
         } else {
             Ok(vm.ctx.not_implemented())
         }
@@ -325,22 +343,49 @@ impl PyIntRef {
         -(&self.value)
     }
 
-    fn hash(self, _vm: &VirtualMachine) -> u64 {
-        let mut hasher = std::collections::hash_map::DefaultHasher::new();
-        self.value.hash(&mut hasher);
-        hasher.finish()
+    fn hash(self, _vm: &VirtualMachine) -> pyhash::PyHash {
+        pyhash::hash_bigint(&self.value)
     }
 
     fn abs(self, _vm: &VirtualMachine) -> BigInt {
         self.value.abs()
     }
 
-    fn round(self, _precision: OptionalArg<PyObjectRef>, _vm: &VirtualMachine) -> Self {
-        self
+    fn round(self, ndigits: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult<BigInt> {
+        let ndigits = match ndigits {
+            OptionalArg::Present(ref ndigits) if !vm.get_none().is(ndigits) => ndigits,
+            _ => return Ok(self.value.clone()),
+        };
+        if !objtype::isinstance(ndigits, &vm.ctx.int_type()) {
+            return Err(vm.new_type_error(format!(
+                "'{}' object cannot be interpreted as an integer",
+                ndigits.class().name
+            )));
+        }
+        let ndigits = get_value(ndigits);
+        if !ndigits.is_negative() {
+            return Ok(self.value.clone());
+        }
+        // Anything below half of 10 ** -ndigits rounds to zero
+        let exponent = match (-ndigits).to_usize() {
+            Some(exponent) if exponent <= self.value.bits() => exponent,
+            _ => return Ok(BigInt::zero()),
+        };
+        let scale = BigInt::from(10).pow(exponent);
+        let (quotient, remainder) = self.value.div_mod_floor(&scale);
+        let doubled = remainder * 2;
+        // Ties go to the even multiple
+        let quotient = if doubled > scale || (doubled == scale && quotient.is_odd()) {
+            quotient + 1
+        } else {
+            quotient
+        };
+        Ok(quotient * scale)
     }
 
-    fn float(self, _vm: &VirtualMachine) -> f64 {
-        self.value.to_f64().unwrap()
+    fn float(self, vm: &VirtualMachine) -> PyResult<f64> {
+        to_float(&self.value)
+            .ok_or_else(|| vm.new_overflow_error("int too large to convert to float".to_string()))
     }
 
     fn invert(self, _vm: &VirtualMachine) -> BigInt {
@@ -370,6 +415,112 @@ impl PyIntRef {
     fn imag(self, _vm: &VirtualMachine) -> usize {
         0
     }
+
+    fn denominator(self, _vm: &VirtualMachine) -> usize {
+        1
+    }
+
+    fn as_integer_ratio(self, vm: &VirtualMachine) -> PyObjectRef {
+        vm.ctx
+            .new_tuple(vec![self.into_object(), vm.ctx.new_int(1)])
+    }
+
+    fn to_bytes(self, args: IntToBytesArgs, vm: &VirtualMachine) -> PyResult<PyBytes> {
+        let length = match args.length {
+            OptionalArg::Present(length) => match length.value.to_usize() {
+                Some(length) => length,
+                None if length.value.is_negative() => {
+                    return Err(
+                        vm.new_value_error("length argument must be non-negative".to_string())
+                    );
+                }
+                None => {
+                    return Err(vm.new_overflow_error(
+                        "Python int too large to convert to C ssize_t".to_string(),
+                    ));
+                }
+            },
+            OptionalArg::Missing => 1,
+        };
+        let little_endian = parse_byteorder(args.byteorder, vm)?;
+        let signed = args.signed.into_option().unwrap_or(false);
+        if !signed && self.value.is_negative() {
+            return Err(vm.new_overflow_error("can't convert negative int to unsigned".to_string()));
+        }
+
+        let mut bytes = if self.value.is_zero() {
+            vec![]
+        } else if signed {
+            self.value.to_signed_bytes_le()
+        } else {
+            self.value.to_bytes_le().1
+        };
+        if bytes.len() > length {
+            return Err(vm.new_overflow_error("int too big to convert".to_string()));
+        }
+        let padding = if self.value.is_negative() { 0xff } else { 0 };
+        bytes.resize(length, padding);
+        if !little_endian {
+            bytes.reverse();
+        }
+        Ok(PyBytes::new(bytes))
+    }
+}
+
+#[derive(FromArgs)]
+struct IntToBytesArgs {
+    #[pyarg(positional_or_keyword, optional = true)]
+    length: OptionalArg<PyIntRef>,
+    #[pyarg(positional_or_keyword, optional = true)]
+    byteorder: OptionalArg<PyStringRef>,
+    #[pyarg(keyword_only, optional = true)]
+    signed: OptionalArg<bool>,
+}
+
+#[derive(FromArgs)]
+struct IntFromBytesArgs {
+    #[pyarg(positional_or_keyword)]
+    bytes: PyObjectRef,
+    #[pyarg(positional_or_keyword, optional = true)]
+    byteorder: OptionalArg<PyStringRef>,
+    #[pyarg(keyword_only, optional = true)]
+    signed: OptionalArg<bool>,
+}
+
+/// Whether a `byteorder` argument is 'little', rather than 'big'.
+fn parse_byteorder(byteorder: OptionalArg<PyStringRef>, vm: &VirtualMachine) -> PyResult<bool> {
+    match byteorder {
+        OptionalArg::Present(ref byteorder) if byteorder.value == "little" => Ok(true),
+        OptionalArg::Present(ref byteorder) if byteorder.value != "big" => {
+            Err(vm.new_value_error("byteorder must be either 'little' or 'big'".to_string()))
+        }
+        _ => Ok(false),
+    }
+}
+
+fn int_from_bytes(cls: PyClassRef, args: IntFromBytesArgs, vm: &VirtualMachine) -> PyResult {
+    if objtype::isinstance(&args.bytes, &vm.ctx.int_type())
+        || objtype::isinstance(&args.bytes, &vm.ctx.str_type())
+    {
+        return Err(vm.new_type_error(format!(
+            "cannot convert '{}' object to bytes",
+            args.bytes.class().name
+        )));
+    }
+    let bytes = objbyteinner::from_source(&args.bytes, vm)?;
+    let little_endian = parse_byteorder(args.byteorder, vm)?;
+    let value = match (little_endian, args.signed.into_option().unwrap_or(false)) {
+        (true, true) => BigInt::from_signed_bytes_le(&bytes),
+        (true, false) => BigInt::from_bytes_le(Sign::Plus, &bytes),
+        (false, true) => BigInt::from_signed_bytes_be(&bytes),
+        (false, false) => BigInt::from_bytes_be(Sign::Plus, &bytes),
+    };
+    let int = vm.ctx.new_int(value);
+    if cls.is(&vm.ctx.int_type()) {
+        Ok(int)
+    } else {
+        vm.invoke(cls.into_object(), vec![int])
+    }
 }
 
 #[derive(FromArgs)]
@@ -377,19 +528,29 @@ struct IntOptions {
     #[pyarg(positional_only, optional = true)]
     val_options: OptionalArg<PyObjectRef>,
     #[pyarg(positional_or_keyword, optional = true)]
-    base: OptionalArg<u32>,
+    base: OptionalArg<PyIntRef>,
 }
 
 impl IntOptions {
     fn get_int_value(self, vm: &VirtualMachine) -> PyResult<BigInt> {
         if let OptionalArg::Present(val) = self.val_options {
             let base = if let OptionalArg::Present(base) = self.base {
-                if !objtype::isinstance(&val, &vm.ctx.str_type) {
+                if !(objtype::isinstance(&val, &vm.ctx.str_type())
+                    || objtype::isinstance(&val, &vm.ctx.bytes_type())
+                    || objtype::isinstance(&val, &vm.ctx.bytearray_type()))
+                {
                     return Err(vm.new_type_error(
                         "int() can't convert non-string with explicit base".to_string(),
                     ));
                 }
-                base
+                match base.value.to_u32() {
+                    Some(base) if base == 0 || base >= 2 && base <= 36 => base,
+                    _ => {
+                        return Err(vm.new_value_error(
+                            "int() base must be >= 2 and <= 36, or 0".to_string(),
+                        ));
+                    }
+                }
             } else {
                 10
             };
@@ -408,29 +569,252 @@ fn int_new(cls: PyClassRef, options: IntOptions, vm: &VirtualMachine) -> PyResul
 
 // Casting function:
 pub fn to_int(vm: &VirtualMachine, obj: &PyObjectRef, base: u32) -> PyResult<BigInt> {
-    let val = if objtype::isinstance(obj, &vm.ctx.int_type()) {
-        get_value(obj).clone()
+    let literal = if objtype::isinstance(obj, &vm.ctx.int_type()) {
+        return Ok(get_value(obj).clone());
     } else if objtype::isinstance(obj, &vm.ctx.float_type()) {
-        objfloat::get_value(obj).to_bigint().unwrap()
+        return objfloat::try_bigint(objfloat::get_value(obj), vm);
     } else if objtype::isinstance(obj, &vm.ctx.str_type()) {
-        let s = objstr::get_value(obj);
-        match i32::from_str_radix(&s, base) {
-            Ok(v) => v.to_bigint().unwrap(),
-            Err(err) => {
-                trace!("Error occurred during int conversion {:?}", err);
-                return Err(vm.new_value_error(format!(
-                    "invalid literal for int() with base {}: '{}'",
-                    base, s
-                )));
+        Some(objstr::get_value(obj))
+    } else if objtype::isinstance(obj, &vm.ctx.bytes_type())
+        || objtype::isinstance(obj, &vm.ctx.bytearray_type())
+    {
+        let bytes = objbyteinner::bytes_like(obj, vm)?;
+        String::from_utf8(bytes).ok()
+    } else {
+        for method_name in &["__int__", "__index__"] {
+            if let Ok(method) = vm.get_method(obj.clone(), method_name) {
+                let result = vm.invoke(method, vec![])?;
+                if !objtype::isinstance(&result, &vm.ctx.int_type()) {
+                    return Err(vm.new_type_error(format!(
+                        "{} returned non-int (type {})",
+                        method_name,
+                        result.class().name
+                    )));
+                }
+                return Ok(get_value(&result).clone());
             }
         }
-    } else {
         return Err(vm.new_type_error(format!(
-            "int() argument must be a string or a number, not '{}'",
+            "int() argument must be a string, a bytes-like object or a real number, not '{}'",
             obj.class().name
         )));
     };
-    Ok(val)
+    match literal.and_then(|literal| parse_int_literal(&literal, base)) {
+        Some(value) => Ok(value),
+        None => {
+            let repr = vm.to_repr(obj)?;
+            Err(vm.new_value_error(format!(
+                "invalid literal for int() with base {}: {}",
+                base, repr.value
+            )))
+        }
+    }
+}
+
+/// Parse an int literal the way `int()` does, with an optional sign,
+/// surrounding whitespace and underscores between digits. A base of 0 takes
+/// the base from the prefix, as in Python source.
+fn parse_int_literal(literal: &str, base: u32) -> Option<BigInt> {
+    let literal = literal.trim();
+    let (negative, unsigned) = match literal.chars().next() {
+        Some('-') => (true, &literal[1..]),
+        Some('+') => (false, &literal[1..]),
+        _ => (false, literal),
+    };
+    let prefix_base = match unsigned.get(..2).map(str::to_ascii_lowercase) {
+        Some(ref prefix) if prefix == "0x" => 16,
+        Some(ref prefix) if prefix == "0o" => 8,
+        Some(ref prefix) if prefix == "0b" => 2,
+        _ => 0,
+    };
+    let (base, digits) = if prefix_base != 0 && (base == 0 || base == prefix_base) {
+        // An underscore may follow the prefix
+        let digits = &unsigned[2..];
+        let digits = if digits.starts_with('_') {
+            &digits[1..]
+        } else {
+            digits
+        };
+        (prefix_base, digits)
+    } else {
+        (base, unsigned)
+    };
+
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    if digits.contains("__") {
+        return None;
+    }
+    let digits = digits.replace('_', "");
+    let radix = if base == 0 { 10 } else { base };
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    // Leading zeros are ambiguous without a prefix, as in Python source
+    if base == 0 && digits.starts_with('0') && digits.chars().any(|c| c != '0') {
+        return None;
+    }
+    let value = BigInt::parse_bytes(digits.as_bytes(), radix)?;
+    Some(if negative { -value } else { value })
+}
+
+/// Convert to the nearest float, or `None` if that would be infinite.
+pub fn to_float(value: &BigInt) -> Option<f64> {
+    let bits = value.bits();
+    if bits <= 64 {
+        return value.to_f64();
+    }
+    // Keep two bits beyond the precision of a float, and a sticky bit for
+    // whatever was shifted out, so that the conversion rounds correctly.
+    let shift = bits - 55;
+    let magnitude = value.abs();
+    let high = &magnitude >> shift;
+    let sticky = if (high.clone() << shift) == magnitude {
+        0
+    } else {
+        1
+    };
+    let mantissa = (high.to_u64().unwrap() | sticky) as f64;
+    let result = mantissa * 2f64.powi(shift as i32);
+    if result.is_infinite() {
+        None
+    } else if value.is_negative() {
+        Some(-result)
+    } else {
+        Some(result)
+    }
+}
+
+fn inner_floordiv(int1: &BigInt, int2: &BigInt, vm: &VirtualMachine) -> PyResult {
+    if int2.is_zero() {
+        return Err(vm.new_zero_division_error("integer division or modulo by zero".to_string()));
+    }
+    Ok(vm.ctx.new_int(int1.div_floor(int2)))
+}
+
+fn inner_mod(int1: &BigInt, int2: &BigInt, vm: &VirtualMachine) -> PyResult {
+    if int2.is_zero() {
+        return Err(vm.new_zero_division_error("integer modulo by zero".to_string()));
+    }
+    Ok(vm.ctx.new_int(int1.mod_floor(int2)))
+}
+
+fn inner_divmod(int1: &BigInt, int2: &BigInt, vm: &VirtualMachine) -> PyResult {
+    if int2.is_zero() {
+        return Err(vm.new_zero_division_error("integer division or modulo by zero".to_string()));
+    }
+    let (div, mod_) = int1.div_mod_floor(int2);
+    Ok(vm
+        .ctx
+        .new_tuple(vec![vm.ctx.new_int(div), vm.ctx.new_int(mod_)]))
+}
+
+fn inner_lshift(int1: &BigInt, int2: &BigInt, vm: &VirtualMachine) -> PyResult {
+    if int2.is_negative() {
+        return Err(vm.new_value_error("negative shift count".to_string()));
+    }
+    if int1.is_zero() {
+        return Ok(vm.ctx.new_int(0));
+    }
+    match int2.to_usize() {
+        Some(n_bits) => Ok(vm.ctx.new_int(int1 << n_bits)),
+        None => Err(vm.new_overflow_error("too many digits in integer".to_string())),
+    }
+}
+
+fn inner_rshift(int1: &BigInt, int2: &BigInt, vm: &VirtualMachine) -> PyResult {
+    if int2.is_negative() {
+        return Err(vm.new_value_error("negative shift count".to_string()));
+    }
+    // Shifting rounds towards negative infinity, like floor division
+    let result = match int2.to_usize() {
+        Some(n_bits) if n_bits < int1.bits() => int1.div_floor(&(BigInt::one() << n_bits)),
+        _ if int1.is_negative() => BigInt::from(-1),
+        _ => BigInt::zero(),
+    };
+    Ok(vm.ctx.new_int(result))
+}
+
+fn inner_pow(
+    int1: &BigInt,
+    int2: &BigInt,
+    modulus: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    if let OptionalArg::Present(ref modulus) = modulus {
+        if !vm.get_none().is(modulus) {
+            if !objtype::isinstance(modulus, &vm.ctx.int_type()) {
+                return Ok(vm.ctx.not_implemented());
+            }
+            return inner_modpow(int1, int2, get_value(modulus), vm);
+        }
+    }
+    if int2.is_negative() {
+        // A negative power of an int is a float
+        let float1 = to_float(int1).ok_or_else(|| {
+            vm.new_overflow_error("int too large to convert to float".to_string())
+        })?;
+        let float2 = to_float(int2).ok_or_else(|| {
+            vm.new_overflow_error("int too large to convert to float".to_string())
+        })?;
+        return objfloat::inner_pow(float1, float2, vm);
+    }
+    let result = match int2.to_usize() {
+        Some(exponent) => int1.pow(exponent),
+        None if int1.is_zero() || int1.is_one() => int1.clone(),
+        None if *int1 == BigInt::from(-1) => {
+            if int2.is_even() {
+                BigInt::one()
+            } else {
+                int1.clone()
+            }
+        }
+        None => return Err(vm.new_overflow_error("exponent too large".to_string())),
+    };
+    Ok(vm.ctx.new_int(result))
+}
+
+fn inner_modpow(int1: &BigInt, int2: &BigInt, modulus: &BigInt, vm: &VirtualMachine) -> PyResult {
+    if modulus.is_zero() {
+        return Err(vm.new_value_error("pow() 3rd argument cannot be 0".to_string()));
+    }
+    let abs_modulus = modulus.abs();
+    let base = int1.mod_floor(&abs_modulus);
+    // A negative exponent is a power of the inverse
+    let base = if int2.is_negative() {
+        mod_inverse(&base, &abs_modulus).ok_or_else(|| {
+            vm.new_value_error("base is not invertible for the given modulus".to_string())
+        })?
+    } else {
+        base
+    };
+    let result = base.modpow(&int2.abs(), &abs_modulus) % &abs_modulus;
+    // The result takes the sign of the modulus
+    let result = if modulus.is_negative() && !result.is_zero() {
+        result - abs_modulus
+    } else {
+        result
+    };
+    Ok(vm.ctx.new_int(result))
+}
+
+/// The inverse of `value` modulo `modulus`, if they are coprime.
+fn mod_inverse(value: &BigInt, modulus: &BigInt) -> Option<BigInt> {
+    let (mut old_remainder, mut remainder) = (value.clone(), modulus.clone());
+    let (mut old_coefficient, mut coefficient) = (BigInt::one(), BigInt::zero());
+    while !remainder.is_zero() {
+        let quotient = &old_remainder / &remainder;
+        let next_remainder = &old_remainder - &quotient * &remainder;
+        old_remainder = std::mem::replace(&mut remainder, next_remainder);
+        let next_coefficient = &old_coefficient - &quotient * &coefficient;
+        old_coefficient = std::mem::replace(&mut coefficient, next_coefficient);
+    }
+    if old_remainder.is_one() {
+        Some(old_coefficient.mod_floor(modulus))
+    } else {
+        None
+    }
 }
 
 // Retrieve inner int value:
@@ -499,7 +883,9 @@ Base 0 means to interpret the base from the string as an integer literal.
         "__add__" => context.new_rustfunc(PyIntRef::add),
         "__radd__" => context.new_rustfunc(PyIntRef::add),
         "__and__" => context.new_rustfunc(PyIntRef::and),
+        "__rand__" => context.new_rustfunc(PyIntRef::and),
         "__divmod__" => context.new_rustfunc(PyIntRef::divmod),
+        "__rdivmod__" => context.new_rustfunc(PyIntRef::rdivmod),
         "__float__" => context.new_rustfunc(PyIntRef::float),
         "__round__" => context.new_rustfunc(PyIntRef::round),
         "__ceil__" => context.new_rustfunc(PyIntRef::pass_value),
@@ -508,17 +894,23 @@ Base 0 means to interpret the base from the string as an integer literal.
         "__trunc__" => context.new_rustfunc(PyIntRef::pass_value),
         "__int__" => context.new_rustfunc(PyIntRef::pass_value),
         "__floordiv__" => context.new_rustfunc(PyIntRef::floordiv),
+        "__rfloordiv__" => context.new_rustfunc(PyIntRef::rfloordiv),
         "__hash__" => context.new_rustfunc(PyIntRef::hash),
         "__lshift__" => context.new_rustfunc(PyIntRef::lshift),
+        "__rlshift__" => context.new_rustfunc(PyIntRef::rlshift),
         "__rshift__" => context.new_rustfunc(PyIntRef::rshift),
+        "__rrshift__" => context.new_rustfunc(PyIntRef::rrshift),
         "__new__" => context.new_rustfunc(int_new),
         "__mod__" => context.new_rustfunc(PyIntRef::mod_),
+        "__rmod__" => context.new_rustfunc(PyIntRef::rmod),
         "__mul__" => context.new_rustfunc(PyIntRef::mul),
         "__rmul__" => context.new_rustfunc(PyIntRef::mul),
         "__or__" => context.new_rustfunc(PyIntRef::or),
+        "__ror__" => context.new_rustfunc(PyIntRef::or),
         "__neg__" => context.new_rustfunc(PyIntRef::neg),
         "__pos__" => context.new_rustfunc(PyIntRef::pass_value),
         "__pow__" => context.new_rustfunc(PyIntRef::pow),
+        "__rpow__" => context.new_rustfunc(PyIntRef::rpow),
         "__repr__" => context.new_rustfunc(PyIntRef::repr),
         "__sub__" => context.new_rustfunc(PyIntRef::sub),
         "__rsub__" => context.new_rustfunc(PyIntRef::rsub),
@@ -531,7 +923,52 @@ Base 0 means to interpret the base from the string as an integer literal.
         "__invert__" => context.new_rustfunc(PyIntRef::invert),
         "bit_length" => context.new_rustfunc(PyIntRef::bit_length),
         "conjugate" => context.new_rustfunc(PyIntRef::pass_value),
+        "as_integer_ratio" => context.new_rustfunc(PyIntRef::as_integer_ratio),
+        "to_bytes" => context.new_rustfunc(PyIntRef::to_bytes),
+        "from_bytes" => PyObject::new(
+            PyClassMethod {
+                callable: context.new_rustfunc(int_from_bytes),
+            },
+            context.classmethod_type(),
+            None,
+        ),
         "real" => context.new_property(PyIntRef::pass_value),
-        "imag" => context.new_property(PyIntRef::imag)
+        "imag" => context.new_property(PyIntRef::imag),
+        "numerator" => context.new_property(PyIntRef::pass_value),
+        "denominator" => context.new_property(PyIntRef::denominator)
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_int_literal() {
+        assert_eq!(parse_int_literal(" -1_000 ", 10), Some(BigInt::from(-1000)));
+        assert_eq!(parse_int_literal("0x_ff", 0), Some(BigInt::from(255)));
+        assert_eq!(parse_int_literal("0b101", 2), Some(BigInt::from(5)));
+        assert_eq!(parse_int_literal("0b101", 16), Some(BigInt::from(0xb101)));
+        assert_eq!(parse_int_literal("000", 0), Some(BigInt::zero()));
+        assert_eq!(parse_int_literal("010", 0), None);
+        assert_eq!(parse_int_literal("1__0", 10), None);
+        assert_eq!(parse_int_literal("_1", 10), None);
+        assert_eq!(parse_int_literal("12", 2), None);
+        assert_eq!(parse_int_literal("- 1", 10), None);
+    }
+
+    #[test]
+    fn test_to_float() {
+        let big = BigInt::one() << 80;
+        assert_eq!(
+            to_float(&(&big + (BigInt::one() << 27) + 1)),
+            Some(2f64.powi(80) + 2f64.powi(28))
+        );
+        assert_eq!(
+            to_float(&(&big + (BigInt::one() << 27))),
+            Some(2f64.powi(80))
+        );
+        assert_eq!(to_float(&-big), Some(-2f64.powi(80)));
+        assert_eq!(to_float(&(BigInt::one() << 1024)), None);
+    }
+}
@@ -3,14 +3,18 @@
  */
 
 use crate::function::PyFuncArgs;
-use crate::pyobject::{PyContext, PyIteratorValue, PyObjectRef, PyResult, TypeProtocol};
+use crate::pyobject::{
+    IdProtocol, PyContext, PyIteratorValue, PyObjectRef, PyResult, TypeProtocol,
+};
 use crate::vm::VirtualMachine;
 
 use super::objbool;
 use super::objbytearray::PyByteArray;
 use super::objbytes::PyBytes;
+use super::objmemory::PyMemoryView;
 use super::objrange::PyRange;
 use super::objsequence;
+use super::objstr::PyString;
 use super::objtype;
 use crate::obj::objtype::PyClassRef;
 
@@ -67,7 +71,22 @@ pub fn get_all(vm: &VirtualMachine, iter_obj: &PyObjectRef) -> PyResult<Vec<PyOb
 
 pub fn new_stop_iteration(vm: &VirtualMachine) -> PyObjectRef {
     let stop_iteration_type = vm.ctx.exceptions.stop_iteration.clone();
-    vm.new_exception(stop_iteration_type, "End of iterator".to_string())
+    vm.new_empty_exception(stop_iteration_type).unwrap()
+}
+
+/// Create a `StopIteration` carrying the return value of a generator.
+pub fn new_stop_iteration_with_value(vm: &VirtualMachine, value: PyObjectRef) -> PyResult {
+    if value.is(&vm.get_none()) {
+        Ok(new_stop_iteration(vm))
+    } else {
+        let stop_iteration_type = vm.ctx.exceptions.stop_iteration.clone();
+        vm.invoke(stop_iteration_type.into_object(), vec![value])
+    }
+}
+
+/// The value a `StopIteration` was raised with, `None` if it had none.
+pub fn stop_iteration_value(vm: &VirtualMachine, exception: &PyObjectRef) -> PyResult {
+    vm.get_attribute(exception.clone(), "value")
 }
 
 fn contains(vm: &VirtualMachine, args: PyFuncArgs, iter_type: PyClassRef) -> PyResult {
@@ -154,6 +173,21 @@ fn iter_next(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
             } else {
                 Err(new_stop_iteration(vm))
             }
+        } else if let Some(view) = iterated_obj_ref.payload::<PyMemoryView>() {
+            if let Some(item) = view.get(position.get(), vm)? {
+                position.set(position.get() + 1);
+                Ok(item)
+            } else {
+                Err(new_stop_iteration(vm))
+            }
+        } else if let Some(string) = iterated_obj_ref.payload::<PyString>() {
+            // The position is a byte offset into the utf-8 encoded value.
+            if let Some(c) = string.value[position.get()..].chars().next() {
+                position.set(position.get() + c.len_utf8());
+                Ok(vm.new_str(c.to_string()))
+            } else {
+                Err(new_stop_iteration(vm))
+            }
         } else {
             let elements = objsequence::get_elements(iterated_obj_ref);
             if position.get() < elements.len() {
@@ -11,6 +11,7 @@ use crate::vm::{ReprGuard, VirtualMachine};
 
 use super::objbool;
 use super::objint;
+use super::objobject;
 use super::objsequence::{
     get_elements, get_elements_cell, get_item, seq_equal, seq_ge, seq_gt, seq_le, seq_lt, seq_mul,
     PySliceableSequence,
@@ -426,6 +427,7 @@ pub fn init(context: &PyContext) {
         "__bool__" => context.new_rustfunc(PyListRef::bool),
         "__contains__" => context.new_rustfunc(PyListRef::contains),
         "__eq__" => context.new_rustfunc(PyListRef::eq),
+        "__hash__" => context.new_rustfunc(objobject::unhashable),
         "__lt__" => context.new_rustfunc(PyListRef::lt),
         "__gt__" => context.new_rustfunc(PyListRef::gt),
         "__le__" => context.new_rustfunc(PyListRef::le),
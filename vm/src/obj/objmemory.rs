@@ -1,32 +1,659 @@
-use crate::obj::objtype::PyClassRef;
-use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue};
+//! Implementation of the python memoryview object, a view of the data of an
+//! object that supports the buffer protocol.
+
+use std::cell::Cell;
+use std::mem;
+use std::os::raw::c_long;
+
+use byteorder::{ByteOrder, NativeEndian};
+use num_traits::ToPrimitive;
+
+use crate::function::{Args, OptionalArg};
+use crate::pyobject::{
+    BufferProtocol, IdProtocol, PyBuffer, PyContext, PyIteratorValue, PyObjectRef, PyRef, PyResult,
+    PyValue, TryFromObject, TypeProtocol,
+};
 use crate::vm::VirtualMachine;
 
-pub type PyMemoryViewRef = PyRef<PyMemoryView>;
+use super::objbool;
+use super::objbyteinner;
+use super::objbytes::PyBytes;
+use super::objfloat;
+use super::objint::PyInt;
+use super::objslice::PySlice;
+use super::objstr::PyStringRef;
+use super::objtype::{self, PyClassRef};
 
+/// A one-dimensional view of items laid out at a fixed distance from each
+/// other in the data of an exporter.
 #[derive(Debug)]
 pub struct PyMemoryView {
+    /// The exporting object. Views of views refer to the original exporter.
     obj: PyObjectRef,
+    /// The offset in bytes of the first item.
+    start: usize,
+    /// The number of items.
+    length: usize,
+    /// The distance in bytes between items, negative for reversed slices.
+    stride: isize,
+    format: char,
+    itemsize: usize,
+    readonly: bool,
+    released: Cell<bool>,
 }
+pub type PyMemoryViewRef = PyRef<PyMemoryView>;
 
 impl PyValue for PyMemoryView {
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.memoryview_type()
     }
+
+    fn as_buffer(&self) -> Option<&dyn PyBuffer> {
+        Some(self)
+    }
+}
+
+impl Drop for PyMemoryView {
+    fn drop(&mut self) {
+        if !self.released.get() {
+            self.buffer().release();
+        }
+    }
+}
+
+impl PyBuffer for PyMemoryView {
+    fn readonly(&self) -> bool {
+        self.readonly
+    }
+
+    fn with_bytes(&self, vm: &VirtualMachine, f: &mut dyn FnMut(&[u8])) -> PyResult<()> {
+        self.check_released(vm)?;
+        self.buffer().with_bytes(vm, &mut |data| {
+            if self.is_contiguous() {
+                f(&data[self.start..self.start + self.nbytes()]);
+            } else {
+                f(&self.gather(data));
+            }
+        })
+    }
+
+    fn with_bytes_mut(&self, vm: &VirtualMachine, f: &mut dyn FnMut(&mut [u8])) -> PyResult<()> {
+        self.check_released(vm)?;
+        if self.readonly {
+            return Err(vm.new_type_error("cannot modify read-only memory".to_string()));
+        }
+        self.buffer().with_bytes_mut(vm, &mut |data| {
+            if self.is_contiguous() {
+                f(&mut data[self.start..self.start + self.nbytes()]);
+            } else {
+                let mut items = self.gather(data);
+                f(&mut items);
+                for (index, item) in items.chunks(self.itemsize).enumerate() {
+                    let offset = self.offset(index);
+                    data[offset..offset + self.itemsize].copy_from_slice(item);
+                }
+            }
+        })
+    }
+}
+
+impl PyMemoryView {
+    /// A byte view of all the data of `obj`.
+    fn from_object(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyMemoryView> {
+        if let Some(view) = obj.payload::<PyMemoryView>() {
+            view.check_released(vm)?;
+            return Ok(view.share());
+        }
+        let (length, readonly) = match obj.as_buffer() {
+            Some(buffer) => {
+                let length = buffer.nbytes(vm)?;
+                buffer.export();
+                (length, buffer.readonly())
+            }
+            None => {
+                return Err(vm.new_type_error(format!(
+                    "memoryview: a bytes-like object is required, not '{}'",
+                    obj.class().name
+                )));
+            }
+        };
+        Ok(PyMemoryView {
+            obj,
+            start: 0,
+            length,
+            stride: 1,
+            format: 'B',
+            itemsize: 1,
+            readonly,
+            released: Cell::new(false),
+        })
+    }
+
+    /// A new view of the same items, counted as another export of the data.
+    fn share(&self) -> PyMemoryView {
+        self.buffer().export();
+        PyMemoryView {
+            obj: self.obj.clone(),
+            start: self.start,
+            length: self.length,
+            stride: self.stride,
+            format: self.format,
+            itemsize: self.itemsize,
+            readonly: self.readonly,
+            released: Cell::new(false),
+        }
+    }
+
+    fn buffer(&self) -> &dyn PyBuffer {
+        self.obj
+            .as_buffer()
+            .expect("memoryview of an object without a buffer")
+    }
+
+    fn check_released(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.released.get() {
+            Err(vm.new_value_error("operation forbidden on released memoryview object".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn nbytes(&self) -> usize {
+        self.length * self.itemsize
+    }
+
+    fn is_contiguous(&self) -> bool {
+        self.length <= 1 || self.stride == self.itemsize as isize
+    }
+
+    /// The offset in bytes of the item at `index`.
+    fn offset(&self, index: usize) -> usize {
+        (self.start as isize + index as isize * self.stride) as usize
+    }
+
+    /// Copy the items of the view out of the exporter's `data`.
+    fn gather(&self, data: &[u8]) -> Vec<u8> {
+        let mut items = Vec::with_capacity(self.nbytes());
+        for index in 0..self.length {
+            let offset = self.offset(index);
+            items.extend_from_slice(&data[offset..offset + self.itemsize]);
+        }
+        items
+    }
+
+    fn item(&self, index: usize, vm: &VirtualMachine) -> PyResult {
+        let offset = self.offset(index);
+        let mut item = vec![];
+        self.buffer().with_bytes(vm, &mut |data| {
+            item.extend_from_slice(&data[offset..offset + self.itemsize])
+        })?;
+        Ok(unpack(self.format, &item, vm))
+    }
+
+    fn write(&self, offset: usize, item: &[u8], vm: &VirtualMachine) -> PyResult<()> {
+        self.buffer().with_bytes_mut(vm, &mut |data| {
+            data[offset..offset + item.len()].copy_from_slice(item)
+        })
+    }
+
+    /// The item at `index`, or `None` past the end of the view.
+    pub fn get(&self, index: usize, vm: &VirtualMachine) -> PyResult<Option<PyObjectRef>> {
+        self.check_released(vm)?;
+        if index < self.length {
+            self.item(index, vm).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// A copy of the items, laid out contiguously.
+    fn data(&self, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+        let buffer: &dyn PyBuffer = self;
+        buffer.to_vec(vm)
+    }
+
+    fn values(&self, vm: &VirtualMachine) -> PyResult<Vec<PyObjectRef>> {
+        Ok(self
+            .data(vm)?
+            .chunks(self.itemsize)
+            .map(|item| unpack(self.format, item, vm))
+            .collect())
+    }
+
+    fn position(&self, index: &PyInt, vm: &VirtualMachine) -> PyResult<usize> {
+        let length = self.length as isize;
+        match index.as_bigint().to_isize() {
+            Some(index) if index >= 0 && index < length => Ok(index as usize),
+            Some(index) if index < 0 && index + length >= 0 => Ok((index + length) as usize),
+            _ => Err(vm.new_index_error("index out of bounds on dimension 1".to_string())),
+        }
+    }
+
+    /// The first item, the number of items and the step of a slice.
+    fn slice_items(&self, slice: &PySlice, vm: &VirtualMachine) -> PyResult<(usize, usize, isize)> {
+        let (start, stop, step) = slice.indices(self.length, vm)?;
+        let length = if step > 0 && stop > start {
+            (stop - start - 1) / step + 1
+        } else if step < 0 && start > stop {
+            (start - stop - 1) / -step + 1
+        } else {
+            0
+        };
+        Ok((start.max(0) as usize, length as usize, step))
+    }
 }
 
-pub fn new_memory_view(
+impl PyMemoryViewRef {
+    fn len(self, vm: &VirtualMachine) -> PyResult<usize> {
+        self.check_released(vm)?;
+        Ok(self.length)
+    }
+
+    fn getitem(self, needle: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.check_released(vm)?;
+        if let Some(index) = needle.payload::<PyInt>() {
+            let index = self.position(index, vm)?;
+            return self.item(index, vm);
+        }
+        if let Some(slice) = needle.payload::<PySlice>() {
+            let (start, length, step) = self.slice_items(slice, vm)?;
+            let mut view = self.share();
+            if length > 0 {
+                view.start = self.offset(start);
+            }
+            view.length = length;
+            view.stride = self.stride * step;
+            return Ok(view.into_ref(vm).into_object());
+        }
+        Err(vm.new_type_error("memoryview: invalid slice key".to_string()))
+    }
+
+    fn setitem(self, needle: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_released(vm)?;
+        if self.readonly {
+            return Err(vm.new_type_error("cannot modify read-only memory".to_string()));
+        }
+        if let Some(index) = needle.payload::<PyInt>() {
+            let index = self.position(index, vm)?;
+            let item = pack(self.format, &value, vm)?;
+            return self.write(self.offset(index), &item, vm);
+        }
+        if let Some(slice) = needle.payload::<PySlice>() {
+            let (start, length, step) = self.slice_items(slice, vm)?;
+            let data = objbyteinner::bytes_like(&value, vm)?;
+            if data.len() != length * self.itemsize {
+                return Err(vm.new_value_error(
+                    "memoryview assignment: lvalue and rvalue have different structures"
+                        .to_string(),
+                ));
+            }
+            for (index, item) in data.chunks(self.itemsize).enumerate() {
+                let index = (start as isize + index as isize * step) as usize;
+                self.write(self.offset(index), item, vm)?;
+            }
+            return Ok(());
+        }
+        Err(vm.new_type_error("memoryview: invalid slice key".to_string()))
+    }
+
+    fn iter(self, vm: &VirtualMachine) -> PyResult<PyIteratorValue> {
+        self.check_released(vm)?;
+        Ok(PyIteratorValue {
+            position: Cell::new(0),
+            iterated_obj: self.into_object(),
+        })
+    }
+
+    fn eq(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if self.is(&other) {
+            return Ok(vm.new_bool(true));
+        }
+        let other_values = if let Some(other) = other.payload::<PyMemoryView>() {
+            if other.released.get() {
+                return Ok(vm.new_bool(false));
+            }
+            other.values(vm)?
+        } else if let Some(buffer) = other.as_buffer() {
+            let data = buffer.to_vec(vm)?;
+            data.into_iter().map(|byte| vm.ctx.new_int(byte)).collect()
+        } else {
+            return Ok(vm.ctx.not_implemented());
+        };
+        if self.released.get() {
+            return Ok(vm.new_bool(false));
+        }
+        let values = self.values(vm)?;
+        if values.len() != other_values.len() {
+            return Ok(vm.new_bool(false));
+        }
+        for (a, b) in values.into_iter().zip(other_values) {
+            if !objbool::boolval(vm, vm._eq(a, b)?)? {
+                return Ok(vm.new_bool(false));
+            }
+        }
+        Ok(vm.new_bool(true))
+    }
+
+    fn ne(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        let eq = self.eq(other, vm)?;
+        if eq.is(&vm.ctx.not_implemented()) {
+            Ok(eq)
+        } else {
+            Ok(vm.new_bool(!objbool::boolval(vm, eq)?))
+        }
+    }
+
+    fn hash(self, vm: &VirtualMachine) -> PyResult<u64> {
+        self.check_released(vm)?;
+        if !self.readonly {
+            return Err(vm.new_value_error("cannot hash writable memoryview object".to_string()));
+        }
+        if !is_byte_format(self.format) {
+            return Err(vm.new_value_error(
+                "memoryview: hashing is restricted to formats 'B', 'b' or 'c'".to_string(),
+            ));
+        }
+        Ok(objbyteinner::hash(&self.data(vm)?))
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        if self.released.get() {
+            format!("<released memory at 0x{:x}>", self.get_id())
+        } else {
+            format!("<memory at 0x{:x}>", self.get_id())
+        }
+    }
+
+    fn enter(self, vm: &VirtualMachine) -> PyResult<Self> {
+        self.check_released(vm)?;
+        Ok(self)
+    }
+
+    fn exit(self, _args: Args, _vm: &VirtualMachine) {
+        self.release_view();
+    }
+
+    fn release(self, _vm: &VirtualMachine) {
+        self.release_view();
+    }
+
+    fn release_view(&self) {
+        if !self.released.replace(true) {
+            self.buffer().release();
+        }
+    }
+
+    fn tobytes(self, vm: &VirtualMachine) -> PyResult<PyBytes> {
+        self.data(vm).map(PyBytes::new)
+    }
+
+    fn tolist(self, vm: &VirtualMachine) -> PyResult {
+        self.check_released(vm)?;
+        Ok(vm.ctx.new_list(self.values(vm)?))
+    }
+
+    fn hex(self, vm: &VirtualMachine) -> PyResult<String> {
+        Ok(objbyteinner::to_hex(&self.data(vm)?))
+    }
+
+    fn toreadonly(self, vm: &VirtualMachine) -> PyResult<PyMemoryViewRef> {
+        self.check_released(vm)?;
+        let mut view = self.share();
+        view.readonly = true;
+        Ok(view.into_ref(vm))
+    }
+
+    fn cast(
+        self,
+        format: PyStringRef,
+        shape: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyMemoryViewRef> {
+        self.check_released(vm)?;
+        if !self.is_contiguous() {
+            return Err(vm.new_type_error(
+                "memoryview: casts are restricted to C-contiguous views".to_string(),
+            ));
+        }
+        let format = parse_format(&format.value).ok_or_else(|| {
+            vm.new_value_error(
+                "memoryview: destination format must be a native single character format \
+                 prefixed with an optional '@'"
+                    .to_string(),
+            )
+        })?;
+        if !is_byte_format(self.format) && !is_byte_format(format) {
+            return Err(vm.new_type_error(
+                "memoryview: cannot cast between two non-byte formats".to_string(),
+            ));
+        }
+        let itemsize = format_size(format).unwrap();
+        if self.nbytes() % itemsize != 0 {
+            return Err(
+                vm.new_type_error("memoryview: length is not a multiple of itemsize".to_string())
+            );
+        }
+        let length = self.nbytes() / itemsize;
+        if let OptionalArg::Present(shape) = shape {
+            let shape = vm.extract_elements(&shape)?;
+            if shape.len() != 1 {
+                return Err(vm.new_not_implemented_error(
+                    "memoryview: only one-dimensional casts are supported".to_string(),
+                ));
+            }
+            if usize::try_from_object(vm, shape[0].clone())? != length {
+                return Err(vm.new_type_error(
+                    "memoryview: product(shape) * itemsize != buffer size".to_string(),
+                ));
+            }
+        }
+        let mut view = self.share();
+        view.length = length;
+        view.stride = itemsize as isize;
+        view.format = format;
+        view.itemsize = itemsize;
+        Ok(view.into_ref(vm))
+    }
+
+    fn obj(self, vm: &VirtualMachine) -> PyResult {
+        self.check_released(vm)?;
+        Ok(self.obj.clone())
+    }
+
+    fn nbytes_property(self, vm: &VirtualMachine) -> PyResult<usize> {
+        self.check_released(vm)?;
+        Ok(self.nbytes())
+    }
+
+    fn readonly_property(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.check_released(vm)?;
+        Ok(self.readonly)
+    }
+
+    fn itemsize(self, vm: &VirtualMachine) -> PyResult<usize> {
+        self.check_released(vm)?;
+        Ok(self.itemsize)
+    }
+
+    fn format(self, vm: &VirtualMachine) -> PyResult<String> {
+        self.check_released(vm)?;
+        Ok(self.format.to_string())
+    }
+
+    fn ndim(self, vm: &VirtualMachine) -> PyResult<usize> {
+        self.check_released(vm)?;
+        Ok(1)
+    }
+
+    fn shape(self, vm: &VirtualMachine) -> PyResult {
+        self.check_released(vm)?;
+        Ok(vm.ctx.new_tuple(vec![vm.ctx.new_int(self.length)]))
+    }
+
+    fn strides(self, vm: &VirtualMachine) -> PyResult {
+        self.check_released(vm)?;
+        Ok(vm.ctx.new_tuple(vec![vm.ctx.new_int(self.stride)]))
+    }
+
+    fn contiguous(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.check_released(vm)?;
+        Ok(self.is_contiguous())
+    }
+}
+
+fn memoryview_new(
     cls: PyClassRef,
-    bytes_object: PyObjectRef,
+    object: PyObjectRef,
     vm: &VirtualMachine,
 ) -> PyResult<PyMemoryViewRef> {
-    vm.ctx.set_attr(&cls, "obj", bytes_object.clone());
-    PyMemoryView { obj: bytes_object }.into_ref_with_type(vm, cls)
+    PyMemoryView::from_object(object, vm)?.into_ref_with_type(vm, cls)
+}
+
+fn is_byte_format(format: char) -> bool {
+    match format {
+        'B' | 'b' | 'c' => true,
+        _ => false,
+    }
+}
+
+/// The size of an item of a native struct format, if views support it.
+fn format_size(format: char) -> Option<usize> {
+    let size = match format {
+        'c' | 'b' | 'B' | '?' => 1,
+        'h' | 'H' => 2,
+        'i' | 'I' | 'f' => 4,
+        'l' | 'L' => mem::size_of::<c_long>(),
+        'q' | 'Q' | 'd' => 8,
+        'n' | 'N' => mem::size_of::<isize>(),
+        _ => return None,
+    };
+    Some(size)
+}
+
+/// Parse a format given to `cast`: a single character, optionally prefixed
+/// with '@' for native alignment.
+fn parse_format(format: &str) -> Option<char> {
+    let format = if format.starts_with('@') {
+        &format[1..]
+    } else {
+        format
+    };
+    let mut chars = format.chars();
+    match (chars.next(), chars.next()) {
+        (Some(format), None) if format_size(format).is_some() => Some(format),
+        _ => None,
+    }
+}
+
+fn unpack(format: char, item: &[u8], vm: &VirtualMachine) -> PyObjectRef {
+    match format {
+        'c' => vm.ctx.new_bytes(item.to_vec()),
+        '?' => vm.new_bool(item[0] != 0),
+        'f' => vm.ctx.new_float(f64::from(NativeEndian::read_f32(item))),
+        'd' => vm.ctx.new_float(NativeEndian::read_f64(item)),
+        'b' | 'h' | 'i' | 'l' | 'q' | 'n' => {
+            vm.ctx.new_int(NativeEndian::read_int(item, item.len()))
+        }
+        _ => vm.ctx.new_int(NativeEndian::read_uint(item, item.len())),
+    }
+}
+
+fn pack(format: char, value: &PyObjectRef, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+    let invalid_type =
+        || vm.new_type_error(format!("memoryview: invalid type for format '{}'", format));
+    let invalid_value =
+        || vm.new_value_error(format!("memoryview: invalid value for format '{}'", format));
+    let size = format_size(format).unwrap();
+    let mut item = vec![0; size];
+    match format {
+        'c' => match value.payload::<PyBytes>() {
+            Some(bytes) if bytes.len() == 1 => item[0] = bytes[0],
+            Some(_) => return Err(invalid_value()),
+            None => return Err(invalid_type()),
+        },
+        '?' => item[0] = objbool::boolval(vm, value.clone())? as u8,
+        'f' | 'd' => {
+            if value.payload::<PyInt>().is_none()
+                && !objtype::isinstance(value, &vm.ctx.float_type())
+            {
+                return Err(invalid_type());
+            }
+            let float = objfloat::make_float(vm, value)?;
+            if format == 'f' {
+                NativeEndian::write_f32(&mut item, float as f32);
+            } else {
+                NativeEndian::write_f64(&mut item, float);
+            }
+        }
+        'b' | 'h' | 'i' | 'l' | 'q' | 'n' => {
+            let int = value.payload::<PyInt>().ok_or_else(invalid_type)?;
+            let bits = size as u32 * 8;
+            let int = int
+                .as_bigint()
+                .to_i64()
+                .filter(|int| bits == 64 || (*int >= -(1 << (bits - 1)) && *int < 1 << (bits - 1)))
+                .ok_or_else(invalid_value)?;
+            NativeEndian::write_int(&mut item, int, size);
+        }
+        _ => {
+            let int = value.payload::<PyInt>().ok_or_else(invalid_type)?;
+            let bits = size as u32 * 8;
+            let int = int
+                .as_bigint()
+                .to_u64()
+                .filter(|int| bits == 64 || *int < 1 << bits)
+                .ok_or_else(invalid_value)?;
+            NativeEndian::write_uint(&mut item, int, size);
+        }
+    }
+    Ok(item)
 }
 
 pub fn init(ctx: &PyContext) {
     let memoryview_type = &ctx.memoryview_type;
     extend_class!(ctx, memoryview_type, {
-        "__new__" => ctx.new_rustfunc(new_memory_view)
+        "__new__" => ctx.new_rustfunc(memoryview_new),
+        "__enter__" => ctx.new_rustfunc(PyMemoryViewRef::enter),
+        "__eq__" => ctx.new_rustfunc(PyMemoryViewRef::eq),
+        "__exit__" => ctx.new_rustfunc(PyMemoryViewRef::exit),
+        "__getitem__" => ctx.new_rustfunc(PyMemoryViewRef::getitem),
+        "__hash__" => ctx.new_rustfunc(PyMemoryViewRef::hash),
+        "__iter__" => ctx.new_rustfunc(PyMemoryViewRef::iter),
+        "__len__" => ctx.new_rustfunc(PyMemoryViewRef::len),
+        "__ne__" => ctx.new_rustfunc(PyMemoryViewRef::ne),
+        "__repr__" => ctx.new_rustfunc(PyMemoryViewRef::repr),
+        "__setitem__" => ctx.new_rustfunc(PyMemoryViewRef::setitem),
+        "c_contiguous" => ctx.new_property(PyMemoryViewRef::contiguous),
+        "cast" => ctx.new_rustfunc(PyMemoryViewRef::cast),
+        "contiguous" => ctx.new_property(PyMemoryViewRef::contiguous),
+        "format" => ctx.new_property(PyMemoryViewRef::format),
+        "hex" => ctx.new_rustfunc(PyMemoryViewRef::hex),
+        "itemsize" => ctx.new_property(PyMemoryViewRef::itemsize),
+        "nbytes" => ctx.new_property(PyMemoryViewRef::nbytes_property),
+        "ndim" => ctx.new_property(PyMemoryViewRef::ndim),
+        "obj" => ctx.new_property(PyMemoryViewRef::obj),
+        "readonly" => ctx.new_property(PyMemoryViewRef::readonly_property),
+        "release" => ctx.new_rustfunc(PyMemoryViewRef::release),
+        "shape" => ctx.new_property(PyMemoryViewRef::shape),
+        "strides" => ctx.new_property(PyMemoryViewRef::strides),
+        "tobytes" => ctx.new_rustfunc(PyMemoryViewRef::tobytes),
+        "tolist" => ctx.new_rustfunc(PyMemoryViewRef::tolist),
+        "toreadonly" => ctx.new_rustfunc(PyMemoryViewRef::toreadonly)
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memoryview_parse_format() {
+        assert_eq!(parse_format("B"), Some('B'));
+        assert_eq!(parse_format("@i"), Some('i'));
+        assert_eq!(parse_format("<i"), None);
+        assert_eq!(parse_format("ii"), None);
+        assert_eq!(parse_format(""), None);
+        assert_eq!(format_size('h'), Some(2));
+        assert_eq!(format_size('x'), None);
+    }
+}
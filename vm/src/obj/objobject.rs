@@ -55,8 +55,13 @@ fn object_ge(_zelf: PyObjectRef, _other: PyObjectRef, vm: &VirtualMachine) -> Py
     vm.ctx.not_implemented()
 }
 
-fn object_hash(_zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-    Err(vm.new_type_error("unhashable type".to_string()))
+fn object_hash(zelf: PyObjectRef, _vm: &VirtualMachine) -> usize {
+    zelf.get_id()
+}
+
+/// `__hash__` for mutable types, which can't be hashed.
+pub fn unhashable(zelf: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    Err(vm.new_type_error(format!("unhashable type: '{}'", zelf.class().name)))
 }
 
 fn object_setattr(
@@ -16,6 +16,7 @@ use crate::vm::{ReprGuard, VirtualMachine};
 use super::objbool;
 use super::objint;
 use super::objiter;
+use super::objobject;
 use super::objtype;
 use super::objtype::PyClassRef;
 
@@ -45,6 +46,30 @@ impl fmt::Debug for PyFrozenSet {
     }
 }
 
+impl PySet {
+    /// The elements, or `None` if the set is being modified.
+    pub fn try_elements(&self) -> Option<Vec<PyObjectRef>> {
+        self.elements
+            .try_borrow()
+            .ok()
+            .map(|elements| elements.values().cloned().collect())
+    }
+
+    /// Empty the set, returning the elements, unless it is being modified.
+    pub fn try_take(&self) -> Option<HashMap<u64, PyObjectRef>> {
+        self.elements
+            .try_borrow_mut()
+            .ok()
+            .map(|mut elements| std::mem::replace(&mut *elements, HashMap::new()))
+    }
+}
+
+impl PyFrozenSet {
+    pub fn elements(&self) -> Vec<PyObjectRef> {
+        self.elements.values().cloned().collect()
+    }
+}
+
 impl PyValue for PySet {
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.set_type()
@@ -225,7 +250,17 @@ fn set_new(cls: PyClassRef, iterable: OptionalArg<PyObjectRef>, vm: &VirtualMach
         }
     };
 
-    create_set(vm, elements, cls.clone())
+    // Unlike the results of set operations, this has the requested class.
+    if objtype::issubclass(&cls, &vm.ctx.set_type()) {
+        let elements = RefCell::new(elements);
+        Ok(PySet { elements }
+            .into_ref_with_type(vm, cls)?
+            .into_object())
+    } else {
+        Ok(PyFrozenSet { elements }
+            .into_ref_with_type(vm, cls)?
+            .into_object())
+    }
 }
 
 fn set_len(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
@@ -280,6 +315,12 @@ pub fn set_contains(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.new_bool(false))
 }
 
+fn frozenset_hash(zelf: PyObjectRef, _vm: &VirtualMachine) -> u64 {
+    // The elements are stored by the hashes of their hashes, which are
+    // combined in a way which doesn't depend on their order.
+    get_elements(&zelf).keys().fold(0, |hash, key| hash ^ key)
+}
+
 fn set_eq(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     set_compare_inner(
         vm,
@@ -601,6 +642,7 @@ pub fn init(context: &PyContext) {
         "__new__" => context.new_rustfunc(set_new),
         "__repr__" => context.new_rustfunc(set_repr),
         "__eq__" => context.new_rustfunc(set_eq),
+        "__hash__" => context.new_rustfunc(objobject::unhashable),
         "__ge__" => context.new_rustfunc(set_ge),
         "__gt__" => context.new_rustfunc(set_gt),
         "__le__" => context.new_rustfunc(set_le),
@@ -642,6 +684,7 @@ pub fn init(context: &PyContext) {
     extend_class!(context, frozenset_type, {
         "__new__" => context.new_rustfunc(set_new),
         "__eq__" => context.new_rustfunc(set_eq),
+        "__hash__" => context.new_rustfunc(frozenset_hash),
         "__ge__" => context.new_rustfunc(set_ge),
         "__gt__" => context.new_rustfunc(set_gt),
         "__le__" => context.new_rustfunc(set_le),
@@ -1,4 +1,5 @@
 use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
 
 use crate::function::PyFuncArgs;
 use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol};
@@ -23,6 +24,45 @@ impl PyValue for PySlice {
 
 pub type PySliceRef = PyRef<PySlice>;
 
+impl PySlice {
+    /// The start, stop and step of this slice, adjusted to a sequence of
+    /// length `len` like CPython's `PySlice_GetIndicesEx`.
+    pub fn indices(&self, len: usize, vm: &VirtualMachine) -> PyResult<(isize, isize, isize)> {
+        let step = self.step.as_ref().map_or(1, saturate);
+        if step == 0 {
+            return Err(vm.new_value_error("slice step cannot be zero".to_string()));
+        }
+        let len = len as isize;
+        let (lower, upper) = if step < 0 { (-1, len - 1) } else { (0, len) };
+        let adjust = |bound: &Option<BigInt>, default: isize| match bound {
+            Some(bound) => {
+                let index = saturate(bound);
+                if index < 0 {
+                    (index + len).max(lower)
+                } else {
+                    index.min(upper)
+                }
+            }
+            None => default,
+        };
+        if step < 0 {
+            Ok((adjust(&self.start, upper), adjust(&self.stop, lower), step))
+        } else {
+            Ok((adjust(&self.start, lower), adjust(&self.stop, upper), step))
+        }
+    }
+}
+
+fn saturate(value: &BigInt) -> isize {
+    value.to_isize().unwrap_or_else(|| {
+        if value.is_negative() {
+            -isize::max_value()
+        } else {
+            isize::max_value()
+        }
+    })
+}
+
 fn slice_new(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     no_kwargs!(vm, args);
     let (cls, start, stop, step): (
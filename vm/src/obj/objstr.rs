@@ -1,23 +1,38 @@
+use std::cell::Cell;
+use std::cmp;
 use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::iter;
 use std::ops::Range;
 use std::str::FromStr;
 use std::string::ToString;
 
-use num_traits::ToPrimitive;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, ToPrimitive};
 use unicode_segmentation::UnicodeSegmentation;
+use unicode_xid::UnicodeXID;
 
-use crate::format::{FormatParseError, FormatPart, FormatString};
+use crate::cformat::{
+    CConversionFlags, CFormatPart, CFormatPreconversor, CFormatQuantity, CFormatSpec,
+    CFormatString, CFormatType, CNumberType,
+};
+use crate::format::{FormatParseError, FormatPart, FormatSpec, FormatString};
 use crate::function::{OptionalArg, PyFuncArgs};
 use crate::pyobject::{
-    IdProtocol, IntoPyObject, PyContext, PyIterable, PyObjectRef, PyRef, PyResult, PyValue,
-    TryFromObject, TryIntoRef, TypeProtocol,
+    DictProtocol, IdProtocol, IntoPyObject, PyContext, PyIterable, PyIteratorValue, PyObject,
+    PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TryIntoRef, TypeProtocol,
 };
+use crate::stdlib::codecs;
 use crate::vm::VirtualMachine;
 
+use super::objbyteinner;
+use super::objbytes::{PyBytes, PyBytesRef};
+use super::objdict;
+use super::objfloat;
 use super::objint;
-use super::objsequence::PySliceableSequence;
+use super::objsequence::{self, PySliceableSequence};
 use super::objslice::PySlice;
+use super::objstaticmethod::PyStaticMethod;
 use super::objtype::{self, PyClassRef};
 
 #[derive(Clone, Debug)]
@@ -69,6 +84,10 @@ impl PyStringRef {
         }
     }
 
+    fn ne(self, rhs: PyObjectRef, vm: &VirtualMachine) -> bool {
+        !self.eq(rhs, vm)
+    }
+
     fn contains(self, needle: PyStringRef, _vm: &VirtualMachine) -> bool {
         self.value.contains(&needle.value)
     }
@@ -119,17 +138,36 @@ impl PyStringRef {
         self.value.chars().count()
     }
 
-    fn mul(self, val: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
-        if objtype::isinstance(&val, &vm.ctx.int_type()) {
-            let value = &self.value;
-            let multiplier = objint::get_value(&val).to_i32().unwrap();
-            let mut result = String::new();
-            for _x in 0..multiplier {
-                result.push_str(value.as_str());
-            }
-            Ok(result)
-        } else {
-            Err(vm.new_type_error(format!("Cannot multiply {} and {}", self, val)))
+    fn mul(self, val: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if !objtype::isinstance(&val, &vm.ctx.int_type()) {
+            return Ok(vm.ctx.not_implemented());
+        }
+        let multiplier = objint::get_value(&val).to_isize().ok_or_else(|| {
+            vm.new_overflow_error("cannot fit 'int' into an index-sized integer".to_string())
+        })?;
+        Ok(vm.new_str(self.value.repeat(cmp::max(multiplier, 0) as usize)))
+    }
+
+    fn modulo(self, values: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+        let format_string = CFormatString::from_str(&self.value)
+            .map_err(|err| vm.new_value_error(err.to_string()))?;
+        do_cformat(
+            vm,
+            format_string,
+            values,
+            |vm, key| vm.new_str(key),
+            format_cspec,
+        )
+    }
+
+    fn rmod(self, _values: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+        vm.ctx.not_implemented()
+    }
+
+    fn iter(self, _vm: &VirtualMachine) -> PyIteratorValue {
+        PyIteratorValue {
+            position: Cell::new(0),
+            iterated_obj: self.into_object(),
         }
     }
 
@@ -137,6 +175,12 @@ impl PyStringRef {
         self
     }
 
+    fn format(self, spec: PyStringRef, vm: &VirtualMachine) -> PyResult<String> {
+        FormatSpec::parse(&spec.value)
+            .format_string(&self.value)
+            .map_err(|err| vm.new_value_error(err.to_string()))
+    }
+
     fn repr(self, _vm: &VirtualMachine) -> String {
         let value = &self.value;
         let quote_char = if count_char(value, '\'') > count_char(value, '"') {
@@ -181,89 +225,95 @@ impl PyStringRef {
     }
 
     fn capitalize(self, _vm: &VirtualMachine) -> String {
-        let (first_part, lower_str) = self.value.split_at(1);
-        format!("{}{}", first_part.to_uppercase(), lower_str)
+        let mut chars = self.value.chars();
+        match chars.next() {
+            Some(first) => format!("{}{}", first.to_uppercase(), chars.as_str().to_lowercase()),
+            None => String::new(),
+        }
     }
 
-    fn split(
-        self,
-        pattern: OptionalArg<Self>,
-        num: OptionalArg<usize>,
-        vm: &VirtualMachine,
-    ) -> PyObjectRef {
+    fn split(self, args: SplitArgs, vm: &VirtualMachine) -> PyResult {
         let value = &self.value;
-        let pattern = match pattern {
-            OptionalArg::Present(ref s) => &s.value,
-            OptionalArg::Missing => " ",
+        let elements: Vec<&str> = match args.sep {
+            Some(ref sep) if sep.value.is_empty() => {
+                return Err(vm.new_value_error("empty separator".to_string()));
+            }
+            Some(ref sep) if args.maxsplit < 0 => value.split(sep.value.as_str()).collect(),
+            Some(ref sep) => value
+                .splitn(args.maxsplit as usize + 1, sep.value.as_str())
+                .collect(),
+            None => split_whitespace(value, args.maxsplit),
         };
-        let num_splits = num
-            .into_option()
-            .unwrap_or_else(|| value.split(pattern).count());
-        let elements = value
-            .splitn(num_splits + 1, pattern)
-            .map(|o| vm.ctx.new_str(o.to_string()))
-            .collect();
-        vm.ctx.new_list(elements)
+        Ok(new_str_list(vm, elements))
     }
 
-    fn rsplit(
-        self,
-        pattern: OptionalArg<Self>,
-        num: OptionalArg<usize>,
-        vm: &VirtualMachine,
-    ) -> PyObjectRef {
+    fn rsplit(self, args: SplitArgs, vm: &VirtualMachine) -> PyResult {
         let value = &self.value;
-        let pattern = match pattern {
-            OptionalArg::Present(ref s) => &s.value,
-            OptionalArg::Missing => " ",
+        let mut elements: Vec<&str> = match args.sep {
+            Some(ref sep) if sep.value.is_empty() => {
+                return Err(vm.new_value_error("empty separator".to_string()));
+            }
+            Some(ref sep) if args.maxsplit < 0 => value.rsplit(sep.value.as_str()).collect(),
+            Some(ref sep) => value
+                .rsplitn(args.maxsplit as usize + 1, sep.value.as_str())
+                .collect(),
+            None => rsplit_whitespace(value, args.maxsplit),
         };
-        let num_splits = num
-            .into_option()
-            .unwrap_or_else(|| value.split(pattern).count());
-        let elements = value
-            .rsplitn(num_splits + 1, pattern)
-            .map(|o| vm.ctx.new_str(o.to_string()))
-            .collect();
-        vm.ctx.new_list(elements)
+        elements.reverse();
+        Ok(new_str_list(vm, elements))
     }
 
-    fn strip(self, _vm: &VirtualMachine) -> String {
-        self.value.trim().to_string()
+    fn strip(self, chars: OptionalArg<Option<PyStringRef>>, _vm: &VirtualMachine) -> String {
+        match chars.into_option().and_then(|chars| chars) {
+            Some(chars) => self.value.trim_matches(|c| chars.value.contains(c)),
+            None => self.value.trim(),
+        }
+        .to_string()
     }
 
-    fn lstrip(self, _vm: &VirtualMachine) -> String {
-        self.value.trim_start().to_string()
+    fn lstrip(self, chars: OptionalArg<Option<PyStringRef>>, _vm: &VirtualMachine) -> String {
+        match chars.into_option().and_then(|chars| chars) {
+            Some(chars) => self.value.trim_start_matches(|c| chars.value.contains(c)),
+            None => self.value.trim_start(),
+        }
+        .to_string()
     }
 
-    fn rstrip(self, _vm: &VirtualMachine) -> String {
-        self.value.trim_end().to_string()
+    fn rstrip(self, chars: OptionalArg<Option<PyStringRef>>, _vm: &VirtualMachine) -> String {
+        match chars.into_option().and_then(|chars| chars) {
+            Some(chars) => self.value.trim_end_matches(|c| chars.value.contains(c)),
+            None => self.value.trim_end(),
+        }
+        .to_string()
     }
 
     fn endswith(
         self,
-        suffix: PyStringRef,
+        suffix: PyObjectRef,
         start: OptionalArg<isize>,
         end: OptionalArg<isize>,
-        _vm: &VirtualMachine,
-    ) -> bool {
+        vm: &VirtualMachine,
+    ) -> PyResult<bool> {
         if let Some((start, end)) = adjust_indices(start, end, self.value.len()) {
-            self.value[start..end].ends_with(&suffix.value)
+            let value = &self.value[start..end];
+            any_affix(vm, suffix, "endswith", |suffix| value.ends_with(suffix))
         } else {
-            false
+            Ok(false)
         }
     }
 
     fn startswith(
         self,
-        prefix: PyStringRef,
+        prefix: PyObjectRef,
         start: OptionalArg<isize>,
         end: OptionalArg<isize>,
-        _vm: &VirtualMachine,
-    ) -> bool {
+        vm: &VirtualMachine,
+    ) -> PyResult<bool> {
         if let Some((start, end)) = adjust_indices(start, end, self.value.len()) {
-            self.value[start..end].starts_with(&prefix.value)
+            let value = &self.value[start..end];
+            any_affix(vm, prefix, "startswith", |prefix| value.starts_with(prefix))
         } else {
-            false
+            Ok(false)
         }
     }
 
@@ -306,11 +356,10 @@ impl PyStringRef {
     fn swapcase(self, _vm: &VirtualMachine) -> String {
         let mut swapped_str = String::with_capacity(self.value.len());
         for c in self.value.chars() {
-            // to_uppercase returns an iterator, to_ascii_uppercase returns the char
             if c.is_lowercase() {
-                swapped_str.push(c.to_ascii_uppercase());
+                swapped_str.extend(c.to_uppercase());
             } else if c.is_uppercase() {
-                swapped_str.push(c.to_ascii_lowercase());
+                swapped_str.extend(c.to_lowercase());
             } else {
                 swapped_str.push(c);
             }
@@ -319,7 +368,7 @@ impl PyStringRef {
     }
 
     fn isalpha(self, _vm: &VirtualMachine) -> bool {
-        !self.value.is_empty() && self.value.chars().all(char::is_alphanumeric)
+        !self.value.is_empty() && self.value.chars().all(char::is_alphabetic)
     }
 
     fn replace(
@@ -363,14 +412,29 @@ impl PyStringRef {
         !self.value.is_empty() && self.value.chars().all(|c| c.is_ascii())
     }
 
-    // doesn't implement keep new line delimiter just yet
-    fn splitlines(self, vm: &VirtualMachine) -> PyObjectRef {
-        let elements = self
-            .value
-            .split('\n')
-            .map(|e| vm.ctx.new_str(e.to_string()))
-            .collect();
-        vm.ctx.new_list(elements)
+    fn splitlines(self, keepends: OptionalArg<bool>, vm: &VirtualMachine) -> PyObjectRef {
+        let keepends = keepends.into_option().unwrap_or(false);
+        let value = &self.value;
+        let mut elements = vec![];
+        let mut line_start = 0;
+        let mut chars = value.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            if !is_line_boundary(c) {
+                continue;
+            }
+            let mut line_end = index + c.len_utf8();
+            if c == '\r' && chars.peek().map(|&(_, next)| next) == Some('\n') {
+                chars.next();
+                line_end += 1;
+            }
+            let end = if keepends { line_end } else { index };
+            elements.push(&value[line_start..end]);
+            line_start = line_end;
+        }
+        if line_start < value.len() {
+            elements.push(&value[line_start..]);
+        }
+        new_str_list(vm, elements)
     }
 
     fn join(self, iterable: PyIterable<PyStringRef>, vm: &VirtualMachine) -> PyResult<String> {
@@ -459,9 +523,12 @@ impl PyStringRef {
         }
     }
 
-    fn partition(self, sub: PyStringRef, vm: &VirtualMachine) -> PyObjectRef {
+    fn partition(self, sub: PyStringRef, vm: &VirtualMachine) -> PyResult {
         let value = &self.value;
         let sub = &sub.value;
+        if sub.is_empty() {
+            return Err(vm.new_value_error("empty separator".to_string()));
+        }
         let mut new_tup = Vec::new();
         if value.contains(sub) {
             new_tup = value
@@ -474,12 +541,15 @@ impl PyStringRef {
             new_tup.push(vm.ctx.new_str("".to_string()));
             new_tup.push(vm.ctx.new_str("".to_string()));
         }
-        vm.ctx.new_tuple(new_tup)
+        Ok(vm.ctx.new_tuple(new_tup))
     }
 
-    fn rpartition(self, sub: PyStringRef, vm: &VirtualMachine) -> PyObjectRef {
+    fn rpartition(self, sub: PyStringRef, vm: &VirtualMachine) -> PyResult {
         let value = &self.value;
         let sub = &sub.value;
+        if sub.is_empty() {
+            return Err(vm.new_value_error("empty separator".to_string()));
+        }
         let mut new_tup = Vec::new();
         if value.contains(sub) {
             new_tup = value
@@ -489,11 +559,11 @@ impl PyStringRef {
             new_tup.swap(0, 1); // so it's in the right order
             new_tup.insert(1, vm.ctx.new_str(sub.clone()));
         } else {
-            new_tup.push(vm.ctx.new_str(value.clone()));
             new_tup.push(vm.ctx.new_str("".to_string()));
             new_tup.push(vm.ctx.new_str("".to_string()));
+            new_tup.push(vm.ctx.new_str(value.clone()));
         }
-        vm.ctx.new_tuple(new_tup)
+        Ok(vm.ctx.new_tuple(new_tup))
     }
 
     fn istitle(self, _vm: &VirtualMachine) -> bool {
@@ -519,95 +589,179 @@ impl PyStringRef {
         }
     }
 
-    fn zfill(self, len: usize, _vm: &VirtualMachine) -> String {
+    fn zfill(self, width: isize, _vm: &VirtualMachine) -> String {
         let value = &self.value;
-        if len <= value.len() {
-            value.to_string()
-        } else {
-            format!("{}{}", "0".repeat(len - value.len()), value)
-        }
+        let padding = (width.max(0) as usize).saturating_sub(value.chars().count());
+        let (sign, digits) = match value.chars().next() {
+            Some(sign @ '+') | Some(sign @ '-') => (sign.to_string(), &value[1..]),
+            _ => (String::new(), value.as_str()),
+        };
+        format!("{}{}{}", sign, "0".repeat(padding), digits)
     }
 
-    fn get_fill_char<'a>(rep: &'a OptionalArg<Self>, vm: &VirtualMachine) -> PyResult<&'a str> {
+    fn get_fill_char(rep: OptionalArg<Self>, vm: &VirtualMachine) -> PyResult<char> {
         let rep_str = match rep {
             OptionalArg::Present(ref st) => &st.value,
             OptionalArg::Missing => " ",
         };
-        if rep_str.len() == 1 {
-            Ok(rep_str)
-        } else {
-            Err(vm.new_type_error(
+        let mut chars = rep_str.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(vm.new_type_error(
                 "The fill character must be exactly one character long".to_string(),
-            ))
+            )),
         }
     }
 
-    fn ljust(self, len: usize, rep: OptionalArg<Self>, vm: &VirtualMachine) -> PyResult<String> {
-        let value = &self.value;
-        let rep_char = PyStringRef::get_fill_char(&rep, vm)?;
-        Ok(format!("{}{}", value, rep_char.repeat(len)))
+    /// The number of fill characters needed to pad the string to `width`.
+    fn padding(&self, width: isize) -> usize {
+        (width.max(0) as usize).saturating_sub(self.value.chars().count())
     }
 
-    fn rjust(self, len: usize, rep: OptionalArg<Self>, vm: &VirtualMachine) -> PyResult<String> {
-        let value = &self.value;
-        let rep_char = PyStringRef::get_fill_char(&rep, vm)?;
-        Ok(format!("{}{}", rep_char.repeat(len), value))
+    fn ljust(self, width: isize, rep: OptionalArg<Self>, vm: &VirtualMachine) -> PyResult<String> {
+        let fill: String = iter::repeat(PyStringRef::get_fill_char(rep, vm)?)
+            .take(self.padding(width))
+            .collect();
+        Ok(format!("{}{}", self.value, fill))
     }
 
-    fn center(self, len: usize, rep: OptionalArg<Self>, vm: &VirtualMachine) -> PyResult<String> {
-        let value = &self.value;
-        let rep_char = PyStringRef::get_fill_char(&rep, vm)?;
-        let left_buff: usize = (len - value.len()) / 2;
-        let right_buff = len - value.len() - left_buff;
+    fn rjust(self, width: isize, rep: OptionalArg<Self>, vm: &VirtualMachine) -> PyResult<String> {
+        let fill: String = iter::repeat(PyStringRef::get_fill_char(rep, vm)?)
+            .take(self.padding(width))
+            .collect();
+        Ok(format!("{}{}", fill, self.value))
+    }
+
+    fn center(self, width: isize, rep: OptionalArg<Self>, vm: &VirtualMachine) -> PyResult<String> {
+        let fill_char = PyStringRef::get_fill_char(rep, vm)?;
+        let padding = self.padding(width);
+        // Like CPython, an odd padding goes to the left for an odd width:
+        let left = padding / 2 + (padding & width as usize & 1);
+        let fill = |count| iter::repeat(fill_char).take(count).collect::<String>();
         Ok(format!(
             "{}{}{}",
-            rep_char.repeat(left_buff),
-            value,
-            rep_char.repeat(right_buff)
+            fill(left),
+            self.value,
+            fill(padding - left)
         ))
     }
 
-    fn expandtabs(self, tab_stop: OptionalArg<usize>, _vm: &VirtualMachine) -> String {
-        let tab_stop = tab_stop.into_option().unwrap_or(8 as usize);
-        let mut expanded_str = String::new();
-        let mut tab_size = tab_stop;
-        let mut col_count = 0 as usize;
+    fn expandtabs(self, tab_stop: OptionalArg<isize>, _vm: &VirtualMachine) -> String {
+        let tab_stop = tab_stop.into_option().unwrap_or(8).max(0) as usize;
+        let mut expanded_str = String::with_capacity(self.value.len());
+        let mut column = 0;
         for ch in self.value.chars() {
-            // 0x0009 is tab
-            if ch == 0x0009 as char {
-                let num_spaces = tab_size - col_count;
-                col_count += num_spaces;
-                let expand = " ".repeat(num_spaces);
-                expanded_str.push_str(&expand);
-            } else {
-                expanded_str.push(ch);
-                col_count += 1;
-            }
-            if col_count >= tab_size {
-                tab_size += tab_stop;
+            match ch {
+                '\t' => {
+                    if tab_stop > 0 {
+                        let num_spaces = tab_stop - column % tab_stop;
+                        expanded_str.push_str(&" ".repeat(num_spaces));
+                        column += num_spaces;
+                    }
+                }
+                '\n' | '\r' => {
+                    expanded_str.push(ch);
+                    column = 0;
+                }
+                _ => {
+                    expanded_str.push(ch);
+                    column += 1;
+                }
             }
         }
         expanded_str
     }
 
     fn isidentifier(self, _vm: &VirtualMachine) -> bool {
-        let value = &self.value;
-        // a string is not an identifier if it has whitespace or starts with a number
-        if !value.chars().any(|c| c.is_ascii_whitespace())
-            && !value.chars().nth(0).unwrap().is_digit(10)
-        {
-            for c in value.chars() {
-                if c != "_".chars().nth(0).unwrap() && !c.is_digit(10) && !c.is_alphabetic() {
-                    return false;
+        let mut chars = self.value.chars();
+        match chars.next() {
+            Some(first) => {
+                (first == '_' || UnicodeXID::is_xid_start(first))
+                    && chars.all(UnicodeXID::is_xid_continue)
+            }
+            None => false,
+        }
+    }
+
+    fn isprintable(self, _vm: &VirtualMachine) -> bool {
+        self.value
+            .chars()
+            .all(|c| c == ' ' || !(c.is_control() || c.is_whitespace() || is_format_char(c)))
+    }
+
+    fn format_map(self, mapping: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        let format_string = parse_format_string(&self.value, vm)?;
+        let parts = &format_string.format_parts;
+        if parts.iter().any(|part| part.is_auto() || part.is_index()) {
+            return Err(vm.new_value_error("Format string contains positional fields".to_string()));
+        }
+        perform_format(vm, &format_string, &[], |keyword| {
+            vm.call_method(
+                &mapping,
+                "__getitem__",
+                vec![vm.new_str(keyword.to_string())],
+            )
+        })
+    }
+
+    fn encode(self, args: EncodeArgs, vm: &VirtualMachine) -> PyResult<PyBytesRef> {
+        let encoding = args.encoding.as_ref().map_or("utf-8", |s| s.value.as_str());
+        let errors = args.errors.as_ref().map_or("strict", |s| s.value.as_str());
+        let data = codecs::encode_text(&self.value, encoding, errors, vm)?;
+        Ok(PyBytes::new(data).into_ref(vm))
+    }
+
+    fn translate(self, table: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+        let mut translated = String::with_capacity(self.value.len());
+        for c in self.value.chars() {
+            let key = vm.new_int(c as u32);
+            let replacement = match vm.call_method(&table, "__getitem__", vec![key]) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    if objtype::isinstance(&err, &vm.ctx.exceptions.lookup_error) {
+                        None
+                    } else {
+                        return Err(err);
+                    }
+                }
+            };
+            match replacement {
+                None => translated.push(c),
+                Some(value) => {
+                    if vm.get_none().is(&value) {
+                        continue;
+                    } else if objtype::isinstance(&value, &vm.ctx.str_type()) {
+                        translated.push_str(borrow_value(&value));
+                    } else if objtype::isinstance(&value, &vm.ctx.int_type()) {
+                        translated.push(int_to_char(objint::get_value(&value), vm)?);
+                    } else {
+                        return Err(vm.new_type_error(
+                            "character mapping must return integer, None or str".to_string(),
+                        ));
+                    }
                 }
             }
-            true
-        } else {
-            false
         }
+        Ok(translated)
     }
 }
 
+#[derive(FromArgs)]
+struct SplitArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    sep: Option<PyStringRef>,
+    #[pyarg(positional_or_keyword, default = "-1")]
+    maxsplit: isize,
+}
+
+#[derive(FromArgs)]
+struct EncodeArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    encoding: Option<PyStringRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    errors: Option<PyStringRef>,
+}
+
 impl PyValue for PyString {
     fn class(vm: &VirtualMachine) -> PyClassRef {
         vm.ctx.str_type()
@@ -641,24 +795,32 @@ pub fn init(context: &PyContext) {
         "__doc__" => context.new_str(str_doc.to_string()),
         "__eq__" => context.new_rustfunc(PyStringRef::eq),
         "__ge__" => context.new_rustfunc(PyStringRef::ge),
+        "__format__" => context.new_rustfunc(PyStringRef::format),
         "__getitem__" => context.new_rustfunc(PyStringRef::getitem),
         "__gt__" => context.new_rustfunc(PyStringRef::gt),
         "__hash__" => context.new_rustfunc(PyStringRef::hash),
         "__lt__" => context.new_rustfunc(PyStringRef::lt),
         "__le__" => context.new_rustfunc(PyStringRef::le),
         "__len__" => context.new_rustfunc(PyStringRef::len),
+        "__iter__" => context.new_rustfunc(PyStringRef::iter),
+        "__mod__" => context.new_rustfunc(PyStringRef::modulo),
         "__mul__" => context.new_rustfunc(PyStringRef::mul),
+        "__ne__" => context.new_rustfunc(PyStringRef::ne),
         "__new__" => context.new_rustfunc(str_new),
         "__repr__" => context.new_rustfunc(PyStringRef::repr),
+        "__rmod__" => context.new_rustfunc(PyStringRef::rmod),
+        "__rmul__" => context.new_rustfunc(PyStringRef::mul),
         "__str__" => context.new_rustfunc(PyStringRef::str),
         "capitalize" => context.new_rustfunc(PyStringRef::capitalize),
         "casefold" => context.new_rustfunc(PyStringRef::casefold),
         "center" => context.new_rustfunc(PyStringRef::center),
         "count" => context.new_rustfunc(PyStringRef::count),
+        "encode" => context.new_rustfunc(PyStringRef::encode),
         "endswith" => context.new_rustfunc(PyStringRef::endswith),
         "expandtabs" => context.new_rustfunc(PyStringRef::expandtabs),
         "find" => context.new_rustfunc(PyStringRef::find),
         "format" => context.new_rustfunc(str_format),
+        "format_map" => context.new_rustfunc(PyStringRef::format_map),
         "index" => context.new_rustfunc(PyStringRef::index),
         "isalnum" => context.new_rustfunc(PyStringRef::isalnum),
         "isalpha" => context.new_rustfunc(PyStringRef::isalpha),
@@ -668,6 +830,7 @@ pub fn init(context: &PyContext) {
         "isidentifier" => context.new_rustfunc(PyStringRef::isidentifier),
         "islower" => context.new_rustfunc(PyStringRef::islower),
         "isnumeric" => context.new_rustfunc(PyStringRef::isnumeric),
+        "isprintable" => context.new_rustfunc(PyStringRef::isprintable),
         "isspace" => context.new_rustfunc(PyStringRef::isspace),
         "isupper" => context.new_rustfunc(PyStringRef::isupper),
         "istitle" => context.new_rustfunc(PyStringRef::istitle),
@@ -675,6 +838,13 @@ pub fn init(context: &PyContext) {
         "lower" => context.new_rustfunc(PyStringRef::lower),
         "ljust" => context.new_rustfunc(PyStringRef::ljust),
         "lstrip" => context.new_rustfunc(PyStringRef::lstrip),
+        "maketrans" => PyObject::new(
+            PyStaticMethod {
+                callable: context.new_rustfunc(str_maketrans),
+            },
+            context.staticmethod_type(),
+            None,
+        ),
         "partition" => context.new_rustfunc(PyStringRef::partition),
         "replace" => context.new_rustfunc(PyStringRef::replace),
         "rfind" => context.new_rustfunc(PyStringRef::rfind),
@@ -689,6 +859,7 @@ pub fn init(context: &PyContext) {
         "strip" => context.new_rustfunc(PyStringRef::strip),
         "swapcase" => context.new_rustfunc(PyStringRef::swapcase),
         "title" => context.new_rustfunc(PyStringRef::title),
+        "translate" => context.new_rustfunc(PyStringRef::translate),
         "upper" => context.new_rustfunc(PyStringRef::upper),
         "zfill" => context.new_rustfunc(PyStringRef::zfill),
     });
@@ -722,16 +893,20 @@ fn str_format(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
             actual_type
         )));
     }
-    let format_string_text = get_value(zelf);
-    match FormatString::from_str(format_string_text.as_str()) {
-        Ok(format_string) => perform_format(vm, &format_string, &args),
-        Err(err) => match err {
-            FormatParseError::UnmatchedBracket => {
-                Err(vm.new_value_error("expected '}' before end of string".to_string()))
-            }
-            _ => Err(vm.new_value_error("Unexpected error parsing format string".to_string())),
-        },
-    }
+    let format_string = parse_format_string(borrow_value(zelf), vm)?;
+    perform_format(vm, &format_string, &args.args[1..], |keyword| {
+        args.get_optional_kwarg(keyword)
+            .ok_or_else(|| vm.new_key_error(format!("'{}'", keyword)))
+    })
+}
+
+fn parse_format_string(text: &str, vm: &VirtualMachine) -> PyResult<FormatString> {
+    FormatString::from_str(text).map_err(|err| match err {
+        FormatParseError::UnmatchedBracket => {
+            vm.new_value_error("expected '}' before end of string".to_string())
+        }
+        _ => vm.new_value_error("Unexpected error parsing format string".to_string()),
+    })
 }
 
 fn call_object_format(vm: &VirtualMachine, argument: PyObjectRef, format_spec: &str) -> PyResult {
@@ -745,11 +920,17 @@ fn call_object_format(vm: &VirtualMachine, argument: PyObjectRef, format_spec: &
     Ok(result)
 }
 
-fn perform_format(
+/// Format with the positional `arguments`, and with `get_keyword` to look up
+/// the keyword arguments.
+fn perform_format<F>(
     vm: &VirtualMachine,
     format_string: &FormatString,
-    arguments: &PyFuncArgs,
-) -> PyResult {
+    arguments: &[PyObjectRef],
+    get_keyword: F,
+) -> PyResult
+where
+    F: Fn(&str) -> PyResult,
+{
     let mut final_string = String::new();
     if format_string.format_parts.iter().any(FormatPart::is_auto)
         && format_string.format_parts.iter().any(FormatPart::is_index)
@@ -759,11 +940,11 @@ fn perform_format(
                 .to_string(),
         ));
     }
-    let mut auto_argument_index: usize = 1;
+    let mut auto_argument_index: usize = 0;
     for part in &format_string.format_parts {
         let result_string: String = match part {
             FormatPart::AutoSpec(format_spec) => {
-                let result = match arguments.args.get(auto_argument_index) {
+                let result = match arguments.get(auto_argument_index) {
                     Some(argument) => call_object_format(vm, argument.clone(), &format_spec)?,
                     None => {
                         return Err(vm.new_index_error("tuple index out of range".to_string()));
@@ -773,7 +954,7 @@ fn perform_format(
                 get_value(&result)
             }
             FormatPart::IndexSpec(index, format_spec) => {
-                let result = match arguments.args.get(*index + 1) {
+                let result = match arguments.get(*index) {
                     Some(argument) => call_object_format(vm, argument.clone(), &format_spec)?,
                     None => {
                         return Err(vm.new_index_error("tuple index out of range".to_string()));
@@ -782,13 +963,8 @@ fn perform_format(
                 get_value(&result)
             }
             FormatPart::KeywordSpec(keyword, format_spec) => {
-                let result = match arguments.get_optional_kwarg(&keyword) {
-                    Some(argument) => call_object_format(vm, argument.clone(), &format_spec)?,
-                    None => {
-                        return Err(vm.new_key_error(format!("'{}'", keyword)));
-                    }
-                };
-                get_value(&result)
+                let argument = get_keyword(keyword)?;
+                get_value(&call_object_format(vm, argument, &format_spec)?)
             }
             FormatPart::Literal(literal) => literal.clone(),
         };
@@ -800,12 +976,33 @@ fn perform_format(
 // TODO: should with following format
 // class str(object='')
 // class str(object=b'', encoding='utf-8', errors='strict')
-fn str_new(
-    cls: PyClassRef,
+#[derive(FromArgs)]
+struct StrArgs {
+    #[pyarg(positional_or_keyword, optional = true)]
     object: OptionalArg<PyObjectRef>,
-    vm: &VirtualMachine,
-) -> PyResult<PyStringRef> {
-    let string = match object {
+    #[pyarg(positional_or_keyword, default = "None")]
+    encoding: Option<PyStringRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    errors: Option<PyStringRef>,
+}
+
+fn str_new(cls: PyClassRef, args: StrArgs, vm: &VirtualMachine) -> PyResult<PyStringRef> {
+    let decoding = args.encoding.is_some() || args.errors.is_some();
+    let string = match args.object {
+        OptionalArg::Present(ref input) if decoding => {
+            if objtype::isinstance(input, &vm.ctx.str_type()) {
+                return Err(vm.new_type_error("decoding str is not supported".to_string()));
+            }
+            let data = objbyteinner::bytes_like(input, vm).map_err(|_| {
+                vm.new_type_error(format!(
+                    "decoding to str: need a bytes-like object, {} found",
+                    input.class().name
+                ))
+            })?;
+            let encoding = args.encoding.as_ref().map_or("utf-8", |s| &s.value);
+            let errors = args.errors.as_ref().map_or("strict", |s| &s.value);
+            vm.new_str(codecs::decode_bytes(&data, encoding, errors, vm)?)
+        }
         OptionalArg::Present(ref input) => vm.to_str(input)?.into_object(),
         OptionalArg::Missing => vm.new_str("".to_string()),
     };
@@ -906,7 +1103,7 @@ pub fn subscript(vm: &VirtualMachine, value: &str, b: PyObjectRef) -> PyResult {
 }
 
 // help get optional string indices
-fn adjust_indices(
+pub fn adjust_indices(
     start: OptionalArg<isize>,
     end: OptionalArg<isize>,
     len: usize,
@@ -941,9 +1138,9 @@ fn make_title(s: &str) -> String {
     for c in s.chars() {
         if c.is_alphabetic() {
             if !capitalize_char {
-                titled_str.push(c);
+                titled_str.extend(c.to_lowercase());
             } else if capitalize_char {
-                titled_str.push(c.to_ascii_uppercase());
+                titled_str.extend(c.to_uppercase());
                 capitalize_char = false;
             }
         } else {
@@ -953,3 +1150,340 @@ fn make_title(s: &str) -> String {
     }
     titled_str
 }
+
+fn new_str_list(vm: &VirtualMachine, elements: Vec<&str>) -> PyObjectRef {
+    let elements = elements
+        .into_iter()
+        .map(|element| vm.new_str(element.to_string()))
+        .collect();
+    vm.ctx.new_list(elements)
+}
+
+/// Split on runs of whitespace, ignoring the whitespace at the start.
+fn split_whitespace(value: &str, maxsplit: isize) -> Vec<&str> {
+    let mut elements = vec![];
+    let mut rest = value.trim_start();
+    while !rest.is_empty() {
+        if maxsplit >= 0 && elements.len() == maxsplit as usize {
+            elements.push(rest);
+            break;
+        }
+        match rest.find(char::is_whitespace) {
+            Some(end) => {
+                elements.push(&rest[..end]);
+                rest = rest[end..].trim_start();
+            }
+            None => {
+                elements.push(rest);
+                break;
+            }
+        }
+    }
+    elements
+}
+
+/// Split on runs of whitespace from the end, giving the parts in reverse.
+fn rsplit_whitespace(value: &str, maxsplit: isize) -> Vec<&str> {
+    let mut elements = vec![];
+    let mut rest = value.trim_end();
+    while !rest.is_empty() {
+        if maxsplit >= 0 && elements.len() == maxsplit as usize {
+            elements.push(rest);
+            break;
+        }
+        match rest.char_indices().rev().find(|(_, c)| c.is_whitespace()) {
+            Some((start, c)) => {
+                elements.push(&rest[start + c.len_utf8()..]);
+                rest = rest[..start].trim_end();
+            }
+            None => {
+                elements.push(rest);
+                break;
+            }
+        }
+    }
+    elements
+}
+
+/// Whether `test` holds for `affix`, or for any string in a tuple of them.
+fn any_affix<F>(vm: &VirtualMachine, affix: PyObjectRef, method: &str, test: F) -> PyResult<bool>
+where
+    F: Fn(&str) -> bool,
+{
+    if objtype::isinstance(&affix, &vm.ctx.str_type()) {
+        return Ok(test(borrow_value(&affix)));
+    }
+    if objtype::isinstance(&affix, &vm.ctx.tuple_type()) {
+        for element in objsequence::get_elements(&affix).iter() {
+            if !objtype::isinstance(element, &vm.ctx.str_type()) {
+                return Err(vm.new_type_error(format!(
+                    "tuple for {} must only contain str, not {}",
+                    method,
+                    element.class().name
+                )));
+            }
+            if test(borrow_value(element)) {
+                return Ok(true);
+            }
+        }
+        return Ok(false);
+    }
+    Err(vm.new_type_error(format!(
+        "{} first arg must be str or a tuple of str, not {}",
+        method,
+        affix.class().name
+    )))
+}
+
+fn is_line_boundary(c: char) -> bool {
+    match c {
+        '\n' | '\r' | '\x0b' | '\x0c' | '\x1c' | '\x1d' | '\x1e' | '\u{85}' | '\u{2028}'
+        | '\u{2029}' => true,
+        _ => false,
+    }
+}
+
+/// Whether `c` is an invisible formatting character, such as a zero width
+/// space or a directional mark.
+fn is_format_char(c: char) -> bool {
+    match c {
+        '\u{ad}' | '\u{600}'..='\u{605}' | '\u{61c}' | '\u{6dd}' | '\u{70f}' | '\u{180e}' => true,
+        '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{206f}' => true,
+        '\u{feff}' | '\u{fff9}'..='\u{fffb}' | '\u{e0001}' | '\u{e0020}'..='\u{e007f}' => true,
+        _ => false,
+    }
+}
+
+fn int_to_char(value: &BigInt, vm: &VirtualMachine) -> PyResult<char> {
+    value.to_u32().and_then(std::char::from_u32).ok_or_else(|| {
+        vm.new_value_error("character mapping must be in range(0x110000)".to_string())
+    })
+}
+
+/// Replace the non-ASCII characters of `text` with escapes, like `ascii()`.
+pub fn ascii_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        let code = c as u32;
+        if c.is_ascii() {
+            escaped.push(c);
+        } else if code < 0x100 {
+            escaped.push_str(&format!("\\x{:02x}", code));
+        } else if code < 0x10000 {
+            escaped.push_str(&format!("\\u{:04x}", code));
+        } else {
+            escaped.push_str(&format!("\\U{:08x}", code));
+        }
+    }
+    escaped
+}
+
+fn str_maketrans(
+    from: PyObjectRef,
+    to: OptionalArg<PyStringRef>,
+    delete: OptionalArg<PyStringRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    let table = vm.ctx.new_dict();
+    let to = match to {
+        OptionalArg::Present(to) => to,
+        OptionalArg::Missing => {
+            if !objtype::isinstance(&from, &vm.ctx.dict_type()) {
+                return Err(vm.new_type_error(
+                    "if you give only one argument to maketrans it must be a dict".to_string(),
+                ));
+            }
+            for (key, value) in from.get_key_value_pairs() {
+                let key = if objtype::isinstance(&key, &vm.ctx.str_type()) {
+                    let mut chars = borrow_value(&key).chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => vm.new_int(c as u32),
+                        _ => {
+                            return Err(vm.new_value_error(
+                                "string keys in translate table must be of length 1".to_string(),
+                            ));
+                        }
+                    }
+                } else if objtype::isinstance(&key, &vm.ctx.int_type()) {
+                    key
+                } else {
+                    return Err(vm.new_type_error(
+                        "keys in translate table must be strings or integers".to_string(),
+                    ));
+                };
+                objdict::set_item(table.as_object(), vm, &key, &value)?;
+            }
+            return Ok(table.into_object());
+        }
+    };
+    let from = PyStringRef::try_from_object(vm, from)?;
+    if from.value.chars().count() != to.value.chars().count() {
+        return Err(vm.new_value_error(
+            "the first two maketrans arguments must have equal length".to_string(),
+        ));
+    }
+    for (from, to) in from.value.chars().zip(to.value.chars()) {
+        let key = vm.new_int(from as u32);
+        objdict::set_item(table.as_object(), vm, &key, &vm.new_int(to as u32))?;
+    }
+    if let OptionalArg::Present(delete) = delete {
+        for c in delete.value.chars() {
+            objdict::set_item(table.as_object(), vm, &vm.new_int(c as u32), &vm.get_none())?;
+        }
+    }
+    Ok(table.into_object())
+}
+
+/// Format `values` as `format_string` says, for the `%` operator of `str` and
+/// `bytes`. `new_key` makes the keys of mapping lookups, and `format_cspec`
+/// formats each value.
+pub fn do_cformat(
+    vm: &VirtualMachine,
+    mut format_string: CFormatString,
+    values: PyObjectRef,
+    new_key: fn(&VirtualMachine, String) -> PyObjectRef,
+    format_cspec: fn(&VirtualMachine, &CFormatSpec, PyObjectRef) -> PyResult<String>,
+) -> PyResult<String> {
+    let is_tuple = objtype::isinstance(&values, &vm.ctx.tuple_type());
+    // Like CPython, anything else which can be indexed by a key is a mapping:
+    let is_mapping = !is_tuple
+        && !objtype::isinstance(&values, &vm.ctx.str_type())
+        && !objtype::isinstance(&values, &vm.ctx.bytes_type())
+        && !objtype::isinstance(&values, &vm.ctx.bytearray_type())
+        && objtype::class_has_attr(&values.class(), "__getitem__");
+    let positional = if is_tuple {
+        objsequence::get_elements(&values).to_vec()
+    } else {
+        vec![values.clone()]
+    };
+    let mut positional = positional.into_iter();
+    let not_enough = || vm.new_type_error("not enough arguments for format string".to_string());
+    let mut formatted = String::new();
+    for part in format_string.format_parts.iter_mut() {
+        let spec = match part {
+            CFormatPart::Literal(literal) => {
+                formatted.push_str(literal);
+                continue;
+            }
+            CFormatPart::Spec(spec) => spec,
+        };
+        if let Some(CFormatQuantity::FromValuesTuple) = spec.min_field_width {
+            let width = star_argument(vm, positional.next().ok_or_else(not_enough)?)?;
+            if width < 0 {
+                spec.flags |= CConversionFlags::LEFT_ADJUST;
+            }
+            spec.min_field_width = Some(CFormatQuantity::Amount(width.abs() as usize));
+        }
+        if let Some(CFormatQuantity::FromValuesTuple) = spec.precision {
+            let precision = star_argument(vm, positional.next().ok_or_else(not_enough)?)?;
+            spec.precision = Some(CFormatQuantity::Amount(precision.max(0) as usize));
+        }
+        let value = match spec.mapping_key {
+            Some(ref key) => {
+                if !is_mapping {
+                    return Err(vm.new_type_error("format requires a mapping".to_string()));
+                }
+                // As in CPython, the mapping only stands in for a positional
+                // argument until a key has been looked up in it.
+                positional = vec![].into_iter();
+                vm.call_method(&values, "__getitem__", vec![new_key(vm, key.clone())])?
+            }
+            None => positional.next().ok_or_else(not_enough)?,
+        };
+        formatted.push_str(&format_cspec(vm, spec, value)?);
+    }
+    if !is_mapping && positional.next().is_some() {
+        return Err(
+            vm.new_type_error("not all arguments converted during string formatting".to_string())
+        );
+    }
+    Ok(formatted)
+}
+
+fn star_argument(vm: &VirtualMachine, value: PyObjectRef) -> PyResult<isize> {
+    if !objtype::isinstance(&value, &vm.ctx.int_type()) {
+        return Err(vm.new_type_error("* wants int".to_string()));
+    }
+    objint::get_value(&value).to_isize().ok_or_else(|| {
+        vm.new_overflow_error("Python int too large to convert to C long".to_string())
+    })
+}
+
+/// Format `value` as the specifier says, after converting it to the type
+/// the specifier needs.
+pub fn format_cspec(
+    vm: &VirtualMachine,
+    spec: &CFormatSpec,
+    value: PyObjectRef,
+) -> PyResult<String> {
+    match spec.format_type {
+        CFormatType::String(ref preconversor) => {
+            let string = match preconversor {
+                CFormatPreconversor::Str | CFormatPreconversor::Bytes => {
+                    vm.to_str(&value)?.value.clone()
+                }
+                CFormatPreconversor::Repr => vm.to_repr(&value)?.value.clone(),
+                CFormatPreconversor::Ascii => ascii_escape(&vm.to_repr(&value)?.value),
+            };
+            Ok(spec.format_string(string))
+        }
+        CFormatType::Number(ref number_type) => {
+            if objtype::isinstance(&value, &vm.ctx.int_type()) {
+                return Ok(spec.format_number(objint::get_value(&value)));
+            }
+            if let CNumberType::Decimal = number_type {
+                if objtype::isinstance(&value, &vm.ctx.float_type()) {
+                    let float = objfloat::get_value(&value);
+                    let number = BigInt::from_f64(float.trunc()).ok_or_else(|| {
+                        vm.new_overflow_error(format!("cannot convert float {} to integer", float))
+                    })?;
+                    return Ok(spec.format_number(&number));
+                }
+            }
+            let required = match number_type {
+                CNumberType::Decimal => "a real number",
+                _ => "an integer",
+            };
+            Err(vm.new_type_error(format!(
+                "%{} format: {} is required, not {}",
+                spec.format_char,
+                required,
+                value.class().name
+            )))
+        }
+        CFormatType::Float(_) => {
+            let number = if objtype::isinstance(&value, &vm.ctx.float_type()) {
+                objfloat::get_value(&value)
+            } else if objtype::isinstance(&value, &vm.ctx.int_type()) {
+                objint::get_value(&value).to_f64().ok_or_else(|| {
+                    vm.new_overflow_error("int too large to convert to float".to_string())
+                })?
+            } else {
+                return Err(
+                    vm.new_type_error(format!("must be real number, not {}", value.class().name))
+                );
+            };
+            Ok(spec.format_float(number))
+        }
+        CFormatType::Character => {
+            let c = if objtype::isinstance(&value, &vm.ctx.int_type()) {
+                objint::get_value(&value)
+                    .to_u32()
+                    .and_then(std::char::from_u32)
+                    .ok_or_else(|| {
+                        vm.new_overflow_error("%c arg not in range(0x110000)".to_string())
+                    })?
+            } else {
+                let mut chars = match value.payload::<PyString>() {
+                    Some(string) => string.value.chars(),
+                    None => "".chars(),
+                };
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(vm.new_type_error("%c requires int or char".to_string())),
+                }
+            };
+            Ok(spec.fill_string(c.to_string()))
+        }
+    }
+}
@@ -6,7 +6,6 @@ https://github.com/python/cpython/blob/50b48572d9a90c5bb36e2bef6179548ea927a35a/
 
 */
 
-use crate::frame::NameProtocol;
 use crate::function::{OptionalArg, PyFuncArgs};
 use crate::obj::objfunction::PyMethod;
 use crate::obj::objstr;
@@ -100,7 +99,8 @@ fn super_new(
     let py_type = if let OptionalArg::Present(ty) = py_type {
         ty.clone()
     } else {
-        match vm.current_scope().load_cell(vm, "__class__") {
+        let frame = vm.current_frame().expect("no current frame for super()");
+        match frame.load_cell("__class__") {
             Some(obj) => PyClassRef::try_from_object(vm, obj)?,
             _ => {
                 return Err(vm.new_type_error(
@@ -127,8 +127,10 @@ fn super_new(
             match vm.get_locals().get_item(first_arg) {
                 Some(obj) => obj.clone(),
                 _ => {
-                    return Err(vm
-                        .new_type_error(format!("super arguement {} was not supplied", first_arg)));
+                    return Err(vm.new_type_error(format!(
+                        "super arguement {} was not supplied",
+                        first_arg
+                    )));
                 }
             }
         } else {
@@ -244,12 +244,17 @@ pub fn type_new_class(
 ) -> PyResult<PyClassRef> {
     let mut bases: Vec<PyClassRef> = bases.iter(vm)?.collect::<Result<Vec<_>, _>>()?;
     bases.push(vm.ctx.object());
-    new(
-        typ.clone(),
-        &name.value,
-        bases,
-        objdict::py_dict_to_attributes(dict.as_object()),
-    )
+    let mut attributes = objdict::py_dict_to_attributes(dict.as_object());
+    // Instances which compare equal must hash alike, so a class defining
+    // its own equality can't use the inherited hash.
+    if attributes.contains_key("__eq__") && !attributes.contains_key("__hash__") {
+        attributes.insert("__hash__".to_string(), vm.get_none());
+    }
+    // Docstrings aren't inherited.
+    attributes
+        .entry("__doc__".to_string())
+        .or_insert_with(|| vm.get_none());
+    new(typ.clone(), &name.value, bases, attributes)
 }
 
 pub fn type_call(class: PyClassRef, args: Args, kwargs: KwArgs, vm: &VirtualMachine) -> PyResult {
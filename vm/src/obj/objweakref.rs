@@ -1,6 +1,10 @@
+use crate::function::OptionalArg;
+use crate::gc;
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::PyValue;
-use crate::pyobject::{PyContext, PyObject, PyObjectPayload, PyObjectRef, PyRef, PyResult};
+use crate::pyobject::{
+    IdProtocol, PyContext, PyObject, PyObjectPayload, PyObjectRef, PyRef, PyResult, TypeProtocol,
+};
 use crate::vm::VirtualMachine;
 
 use std::rc::{Rc, Weak};
@@ -8,18 +12,39 @@ use std::rc::{Rc, Weak};
 #[derive(Debug)]
 pub struct PyWeak {
     referent: Weak<PyObject<dyn PyObjectPayload>>,
+    callback: Option<PyObjectRef>,
 }
 
 impl PyWeak {
     pub fn downgrade(obj: &PyObjectRef) -> PyWeak {
         PyWeak {
             referent: Rc::downgrade(obj),
+            callback: None,
         }
     }
 
+    /// A weak reference object which calls `callback` with itself once `obj`
+    /// is freed.
+    pub fn with_callback(
+        obj: &PyObjectRef,
+        callback: PyObjectRef,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyWeakRef> {
+        PyWeakRef::create(
+            vm.ctx.weakref_type(),
+            obj.clone(),
+            OptionalArg::Present(callback),
+            vm,
+        )
+    }
+
     pub fn upgrade(&self) -> Option<PyObjectRef> {
         self.referent.upgrade()
     }
+
+    pub fn callback(&self) -> Option<PyObjectRef> {
+        self.callback.clone()
+    }
 }
 
 impl PyValue for PyWeak {
@@ -31,19 +56,54 @@ impl PyValue for PyWeak {
 pub type PyWeakRef = PyRef<PyWeak>;
 
 impl PyWeakRef {
-    // TODO callbacks
-    fn create(cls: PyClassRef, referent: PyObjectRef, vm: &VirtualMachine) -> PyResult<Self> {
-        PyWeak::downgrade(&referent).into_ref_with_type(vm, cls)
+    fn create(
+        cls: PyClassRef,
+        referent: PyObjectRef,
+        callback: OptionalArg<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<Self> {
+        let callback = callback
+            .into_option()
+            .filter(|callback| !vm.get_none().is(callback));
+        let has_callback = callback.is_some();
+        let weakref = PyWeak {
+            referent: Rc::downgrade(&referent),
+            callback,
+        }
+        .into_ref_with_type(vm, cls)?;
+        if has_callback {
+            gc::add_weakref_callback(&referent, weakref.as_object());
+        }
+        Ok(weakref)
     }
 
     fn call(self, vm: &VirtualMachine) -> PyObjectRef {
         self.referent.upgrade().unwrap_or_else(|| vm.get_none())
     }
+
+    fn callback_attr(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.callback.clone().unwrap_or_else(|| vm.get_none())
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        let id = self.as_object().get_id();
+        match self.referent.upgrade() {
+            Some(referent) => format!(
+                "<weakref at 0x{:x}; to '{}' at 0x{:x}>",
+                id,
+                referent.class().name,
+                referent.get_id()
+            ),
+            None => format!("<weakref at 0x{:x}; dead>", id),
+        }
+    }
 }
 
 pub fn init(context: &PyContext) {
     extend_class!(context, &context.weakref_type, {
         "__new__" => context.new_rustfunc(PyWeakRef::create),
-        "__call__" => context.new_rustfunc(PyWeakRef::call)
+        "__call__" => context.new_rustfunc(PyWeakRef::call),
+        "__callback__" => context.new_property(PyWeakRef::callback_attr),
+        "__repr__" => context.new_rustfunc(PyWeakRef::repr)
     });
 }
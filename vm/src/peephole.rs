@@ -0,0 +1,532 @@
+//! A peephole optimizer, run over the code objects made by the compiler.
+//!
+//! Operations on constants are folded, jumps to unconditional jumps go to
+//! their final target, and instructions which can't be reached are removed.
+//! Jumps refer to labels rather than to positions, so instructions can be
+//! removed as long as the labels are moved along with them.
+//!
+//! Only operations which give the same result at runtime are folded, and
+//! results are kept small, so that code objects don't grow.
+
+use std::collections::HashSet;
+
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_traits::{Signed, ToPrimitive, Zero};
+
+use crate::bytecode::{BinaryOperator, CodeObject, Constant, Instruction, UnaryOperator};
+
+/// The largest int, in bits, a folded operation may produce.
+const MAX_INT_BITS: u64 = 128;
+/// The largest string, in bytes, a folded operation may produce.
+const MAX_STR_SIZE: usize = 4096;
+
+/// Optimize `code` and the code objects nested in it.
+pub fn optimize(code: &mut CodeObject) {
+    for instruction in code.instructions.iter_mut() {
+        if let Instruction::LoadConst {
+            value: Constant::Code { code },
+        } = instruction
+        {
+            optimize(code);
+        }
+    }
+
+    loop {
+        let mut removed = vec![false; code.instructions.len()];
+        let mut changed = fold_constants(code, &mut removed);
+        changed |= compact(code, &removed);
+        changed |= thread_jumps(code);
+        let mut removed = vec![false; code.instructions.len()];
+        remove_unreachable(code, &mut removed);
+        changed |= compact(code, &removed);
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// The positions labels point to, which can be reached by a jump. Labels no
+/// instruction refers to anymore are left out.
+fn jump_targets(code: &CodeObject) -> HashSet<usize> {
+    code.instructions
+        .iter()
+        .flat_map(Instruction::labels)
+        .map(|label| code.label_map[&label])
+        .collect()
+}
+
+/// Fold operations on constants, merge a `not` into the jump following it,
+/// and resolve jumps on a constant condition.
+fn fold_constants(code: &mut CodeObject, removed: &mut [bool]) -> bool {
+    let targets = jump_targets(code);
+    let mut changed = false;
+    // The positions of the instructions which are kept so far.
+    let mut kept: Vec<usize> = vec![];
+    for position in 0..code.instructions.len() {
+        let operands = match &code.instructions[position] {
+            Instruction::BinaryOperation { inplace: false, .. } => 2,
+            Instruction::UnaryOperation { .. } => 1,
+            Instruction::BuildTuple {
+                size,
+                unpack: false,
+            } => *size,
+            Instruction::JumpIf { .. } | Instruction::JumpIfFalse { .. } => {
+                if let Some(&previous) = kept.last() {
+                    if !targets.contains(&position) {
+                        if merge_not(code, previous, position) {
+                            removed[previous] = true;
+                            kept.pop();
+                            changed = true;
+                        } else if let Some(taken) = fold_jump(code, previous, position) {
+                            removed[previous] = true;
+                            kept.pop();
+                            changed = true;
+                            if !taken {
+                                removed[position] = true;
+                                continue;
+                            }
+                        }
+                    }
+                }
+                kept.push(position);
+                continue;
+            }
+            _ => {
+                kept.push(position);
+                continue;
+            }
+        };
+        if operands > kept.len() {
+            kept.push(position);
+            continue;
+        }
+        let operand_positions = kept.split_off(kept.len() - operands);
+        // The folded constant replaces the first operand, or the operation
+        // when there are none. A jump to any of the others would skip it.
+        let first = operand_positions.first().cloned().unwrap_or(position);
+        let jumped_into = ((first + 1)..=position).any(|p| targets.contains(&p));
+        let constants = operand_positions
+            .iter()
+            .map(|&p| match &code.instructions[p] {
+                Instruction::LoadConst { value } => Some(value),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        let folded = match constants {
+            Some(ref constants) if !jumped_into => fold(&code.instructions[position], constants),
+            _ => None,
+        };
+        match folded {
+            Some(value) => {
+                for &p in operand_positions.iter().chain(Some(&position)) {
+                    removed[p] = p != first;
+                }
+                code.instructions[first] = Instruction::LoadConst { value };
+                kept.push(first);
+                changed = true;
+            }
+            None => {
+                kept.extend(operand_positions);
+                kept.push(position);
+            }
+        }
+    }
+    changed
+}
+
+/// Replace a `not` followed by a conditional jump with the opposite jump.
+fn merge_not(code: &mut CodeObject, not: usize, jump: usize) -> bool {
+    match code.instructions[not] {
+        Instruction::UnaryOperation {
+            op: UnaryOperator::Not,
+        } => {}
+        _ => return false,
+    }
+    code.instructions[jump] = match code.instructions[jump] {
+        Instruction::JumpIf { target } => Instruction::JumpIfFalse { target },
+        Instruction::JumpIfFalse { target } => Instruction::JumpIf { target },
+        _ => return false,
+    };
+    true
+}
+
+/// Replace a jump on a constant condition with an unconditional jump, and
+/// tell whether it is taken, or `None` when the condition isn't constant.
+fn fold_jump(code: &mut CodeObject, load: usize, jump: usize) -> Option<bool> {
+    let truth = match &code.instructions[load] {
+        Instruction::LoadConst { value } => match value {
+            Constant::None => false,
+            Constant::Boolean { value } => *value,
+            Constant::Integer { value } => !value.is_zero(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let (target, taken) = match code.instructions[jump] {
+        Instruction::JumpIf { target } => (target, truth),
+        Instruction::JumpIfFalse { target } => (target, !truth),
+        _ => return None,
+    };
+    if taken {
+        code.instructions[jump] = Instruction::Jump { target };
+    }
+    Some(taken)
+}
+
+fn fold(instruction: &Instruction, constants: &[&Constant]) -> Option<Constant> {
+    match instruction {
+        Instruction::BinaryOperation { op, .. } => fold_binary(op, constants[0], constants[1]),
+        Instruction::UnaryOperation { op } => fold_unary(op, constants[0]),
+        Instruction::BuildTuple { .. } => Some(Constant::Tuple {
+            elements: constants.iter().map(|&constant| constant.clone()).collect(),
+        }),
+        _ => None,
+    }
+}
+
+fn fold_unary(op: &UnaryOperator, operand: &Constant) -> Option<Constant> {
+    match (op, operand) {
+        (UnaryOperator::Minus, Constant::Integer { value }) => {
+            Some(Constant::Integer { value: -value })
+        }
+        (UnaryOperator::Plus, Constant::Integer { value }) => Some(Constant::Integer {
+            value: value.clone(),
+        }),
+        (UnaryOperator::Invert, Constant::Integer { value }) => {
+            Some(Constant::Integer { value: !value })
+        }
+        (UnaryOperator::Minus, Constant::Float { value }) => {
+            Some(Constant::Float { value: -value })
+        }
+        (UnaryOperator::Minus, Constant::Complex { value }) => {
+            Some(Constant::Complex { value: -value })
+        }
+        _ => None,
+    }
+}
+
+/// Convert an int to a float, if that can be done exactly.
+fn exact_float(value: &BigInt) -> Option<f64> {
+    if value.bits() <= 53 {
+        value.to_f64()
+    } else {
+        None
+    }
+}
+
+fn fold_binary(op: &BinaryOperator, left: &Constant, right: &Constant) -> Option<Constant> {
+    use BinaryOperator::*;
+    match (left, right) {
+        (Constant::Integer { value: a }, Constant::Integer { value: b }) => {
+            let value = match op {
+                Add => a + b,
+                Subtract => a - b,
+                Multiply if a.bits() + b.bits() <= MAX_INT_BITS as usize => a * b,
+                And => a & b,
+                Or => a | b,
+                Xor => a ^ b,
+                Lshift if !b.is_negative() => {
+                    let shift = b.to_u64().filter(|&shift| shift <= MAX_INT_BITS)?;
+                    if a.bits() as u64 + shift > MAX_INT_BITS {
+                        return None;
+                    }
+                    a << shift as usize
+                }
+                Rshift if !b.is_negative() => a >> b.to_usize()?,
+                Power if !b.is_negative() => {
+                    let exponent = b.to_u32()?;
+                    if a.bits() as u64 * u64::from(exponent) > MAX_INT_BITS {
+                        return None;
+                    }
+                    num_traits::pow(a.clone(), exponent as usize)
+                }
+                _ => return None,
+            };
+            Some(Constant::Integer { value })
+        }
+        (Constant::Float { value: a }, Constant::Float { value: b }) => fold_float(op, *a, *b),
+        (Constant::Float { value: a }, Constant::Integer { value: b }) => {
+            fold_float(op, *a, exact_float(b)?)
+        }
+        (Constant::Integer { value: a }, Constant::Float { value: b }) => {
+            fold_float(op, exact_float(a)?, *b)
+        }
+        (Constant::Complex { value: a }, Constant::Complex { value: b }) => match op {
+            Add => Some(Constant::Complex { value: a + b }),
+            _ => None,
+        },
+        (Constant::Complex { value: a }, Constant::Integer { value: b })
+        | (Constant::Integer { value: b }, Constant::Complex { value: a }) => match op {
+            Add => Some(Constant::Complex {
+                value: Complex64::new(a.re + exact_float(b)?, a.im),
+            }),
+            _ => None,
+        },
+        (Constant::String { value: a }, Constant::String { value: b }) => match op {
+            Add if a.len() + b.len() <= MAX_STR_SIZE => Some(Constant::String {
+                value: format!("{}{}", a, b),
+            }),
+            _ => None,
+        },
+        (Constant::String { value: a }, Constant::Integer { value: b }) => match op {
+            Multiply if !b.is_negative() => {
+                let count = b.to_usize()?;
+                if a.len().checked_mul(count)? > MAX_STR_SIZE {
+                    return None;
+                }
+                Some(Constant::String {
+                    value: a.repeat(count),
+                })
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn fold_float(op: &BinaryOperator, a: f64, b: f64) -> Option<Constant> {
+    let value = match op {
+        BinaryOperator::Add => a + b,
+        BinaryOperator::Subtract => a - b,
+        BinaryOperator::Multiply => a * b,
+        _ => return None,
+    };
+    Some(Constant::Float { value })
+}
+
+/// Make jumps to an unconditional jump go to where that one goes, and drop
+/// jumps to the next instruction.
+fn thread_jumps(code: &mut CodeObject) -> bool {
+    let mut changed = false;
+    for position in 0..code.instructions.len() {
+        let target = match code.instructions[position] {
+            Instruction::Jump { target }
+            | Instruction::JumpIf { target }
+            | Instruction::JumpIfFalse { target } => target,
+            _ => continue,
+        };
+        let mut final_target = target;
+        // Bounded, in case of an endless loop of jumps.
+        for _ in 0..code.instructions.len() {
+            match code.instructions.get(code.label_map[&final_target]) {
+                Some(Instruction::Jump { target }) if *target != final_target => {
+                    final_target = *target;
+                }
+                _ => break,
+            }
+        }
+        if final_target != target {
+            match &mut code.instructions[position] {
+                Instruction::Jump { target }
+                | Instruction::JumpIf { target }
+                | Instruction::JumpIfFalse { target } => *target = final_target,
+                _ => unreachable!(),
+            }
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Whether the instruction following `instruction` can only be reached by a jump.
+fn ends_block(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::ReturnValue
+        | Instruction::Jump { .. }
+        | Instruction::Raise { .. }
+        | Instruction::Break
+        | Instruction::Continue => true,
+        _ => false,
+    }
+}
+
+fn remove_unreachable(code: &CodeObject, removed: &mut [bool]) {
+    let targets = jump_targets(code);
+    let mut reachable = true;
+    for (position, instruction) in code.instructions.iter().enumerate() {
+        if targets.contains(&position) {
+            reachable = true;
+        }
+        match instruction {
+            // A jump to the next instruction does nothing.
+            Instruction::Jump { target } if code.label_map[target] == position + 1 => {
+                removed[position] = true;
+                continue;
+            }
+            _ => {}
+        }
+        if !reachable {
+            removed[position] = true;
+        } else if ends_block(instruction) {
+            reachable = false;
+        }
+    }
+}
+
+/// Drop the removed instructions and their locations, and move the labels
+/// to the next instruction which is kept.
+fn compact(code: &mut CodeObject, removed: &[bool]) -> bool {
+    if !removed.contains(&true) {
+        return false;
+    }
+    let mut new_positions = Vec::with_capacity(removed.len() + 1);
+    let mut kept = 0;
+    for &is_removed in removed {
+        new_positions.push(kept);
+        if !is_removed {
+            kept += 1;
+        }
+    }
+    new_positions.push(kept);
+    for position in code.label_map.values_mut() {
+        *position = new_positions[*position];
+    }
+    let mut removed_iter = removed.iter();
+    code.instructions.retain(|_| !removed_iter.next().unwrap());
+    let mut removed_iter = removed.iter();
+    code.locations.retain(|_| !removed_iter.next().unwrap());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimize;
+    use crate::bytecode::CodeObject;
+    use crate::bytecode::Constant::*;
+    use crate::bytecode::Instruction::*;
+    use crate::compile;
+    use crate::vm::VirtualMachine;
+    use num_bigint::BigInt;
+
+    fn compile_exec(source: &str) -> CodeObject {
+        let vm = VirtualMachine::new();
+        let mut options = vm.compile_options.get();
+        options.peephole = false;
+        vm.compile_options.set(options);
+        let mut code = compile::compile(&vm, source, &compile::Mode::Exec, "<test>".to_string())
+            .unwrap()
+            .code
+            .clone();
+        optimize(&mut code);
+        assert_eq!(code.instructions.len(), code.locations.len());
+        code
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let code = compile_exec("x = (1 + 2 * 3, -4, 'a' * 2, ~0)\n");
+        assert_eq!(
+            vec![
+                LoadConst {
+                    value: Tuple {
+                        elements: vec![
+                            Integer {
+                                value: BigInt::from(7)
+                            },
+                            Integer {
+                                value: BigInt::from(-4)
+                            },
+                            String {
+                                value: "aa".to_string()
+                            },
+                            Integer {
+                                value: BigInt::from(-1)
+                            },
+                        ]
+                    }
+                },
+                StoreName {
+                    name: "x".to_string(),
+                    scope: crate::bytecode::NameScope::Local
+                },
+                LoadConst { value: None },
+                ReturnValue
+            ],
+            code.instructions
+        );
+    }
+
+    #[test]
+    fn test_no_folding_of_errors_or_large_results() {
+        let code = compile_exec("x = 1 // 0\ny = 2 ** 1000\nz = 'a' * 10000\n");
+        assert_eq!(
+            3,
+            code.instructions
+                .iter()
+                .filter(|instruction| match instruction {
+                    BinaryOperation { .. } => true,
+                    _ => false,
+                })
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_not_jump_and_dead_code() {
+        let code = compile_exec("def f(a):\n    if not a:\n        return 1\n    return 2\n");
+        let function = match code.get_constants().next() {
+            Some(Code { code }) => code,
+            _ => panic!("expected a code object constant"),
+        };
+        let target = match function.instructions[1] {
+            JumpIf { target } => target,
+            _ => panic!("expected the `not` to be merged into the jump"),
+        };
+        assert_eq!(
+            vec![
+                LoadFast { idx: 0 },
+                JumpIf { target },
+                LoadConst {
+                    value: Integer {
+                        value: BigInt::from(1)
+                    }
+                },
+                ReturnValue,
+                LoadConst {
+                    value: Integer {
+                        value: BigInt::from(2)
+                    }
+                },
+                ReturnValue,
+            ],
+            function.instructions
+        );
+        assert_eq!(function.label_map[&target], 4);
+    }
+
+    #[test]
+    fn test_thread_jumps() {
+        let code = compile_exec("while x:\n    if y:\n        z\n");
+        for instruction in &code.instructions {
+            if let JumpIfFalse { target } | Jump { target } = instruction {
+                let position = code.label_map[target];
+                match code.instructions.get(position) {
+                    Some(Jump { .. }) => panic!("jump to a jump at {}", position),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_constant_conditions() {
+        let code = compile_exec("if __debug__:\n    x = 1\nelse:\n    x = 2\n");
+        assert_eq!(
+            vec![
+                LoadConst {
+                    value: Integer {
+                        value: BigInt::from(1)
+                    }
+                },
+                StoreName {
+                    name: "x".to_string(),
+                    scope: crate::bytecode::NameScope::Local,
+                },
+                LoadConst { value: None },
+                ReturnValue,
+            ],
+            code.instructions
+        );
+    }
+}
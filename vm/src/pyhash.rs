@@ -0,0 +1,144 @@
+/*! Hashing of numbers, computed the way CPython does it.
+
+Numbers of different types which compare equal must hash the same, so ints,
+floats and complex numbers are all hashed as their value modulo the prime
+`MODULUS`. As in CPython, -1 is never returned as a hash.
+*/
+
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+
+pub type PyHash = i64;
+
+/// The number of bits in `MODULUS`.
+pub const BITS: u32 = 61;
+/// The prime modulus which numbers are reduced by.
+pub const MODULUS: u64 = (1 << BITS) - 1;
+/// The hash of positive infinity. Negative infinity hashes to its negation.
+pub const INF: PyHash = 314_159;
+/// The hash of NaN.
+pub const NAN: PyHash = 0;
+/// The multiplier for the imaginary part of complex numbers.
+pub const IMAG: PyHash = 1_000_003;
+
+pub fn hash_float(value: f64) -> PyHash {
+    if !value.is_finite() {
+        return if value.is_nan() {
+            NAN
+        } else if value > 0.0 {
+            INF
+        } else {
+            -INF
+        };
+    }
+
+    // Go through the binary digits of the mantissa 28 at a time, as
+    // CPython does, to get the same result.
+    let (mut mantissa, mut exponent) = frexp(value);
+    let sign = if mantissa < 0.0 {
+        mantissa = -mantissa;
+        -1
+    } else {
+        1
+    };
+    let mut x: u64 = 0;
+    while mantissa != 0.0 {
+        x = ((x << 28) & MODULUS) | x >> (BITS - 28);
+        mantissa *= 268_435_456.0; // 2 ** 28
+        exponent -= 28;
+        let digit = mantissa as u64;
+        mantissa -= digit as f64;
+        x += digit;
+        if x >= MODULUS {
+            x -= MODULUS;
+        }
+    }
+
+    // Multiplying by 2 ** exponent is a rotation modulo 2 ** BITS - 1.
+    let bits = BITS as i32;
+    let exponent = if exponent >= 0 {
+        exponent % bits
+    } else {
+        bits - 1 - ((-1 - exponent) % bits)
+    } as u32;
+    x = ((x << exponent) & MODULUS) | x >> (BITS - exponent);
+
+    fix_sentinel(x as PyHash * sign)
+}
+
+pub fn hash_bigint(value: &BigInt) -> PyHash {
+    let reduced = (value.abs() % MODULUS).to_i64().unwrap();
+    fix_sentinel(if value.is_negative() {
+        -reduced
+    } else {
+        reduced
+    })
+}
+
+/// The hash of a complex number, combined from the hashes of its parts so
+/// that a complex number with no imaginary part hashes as its real part.
+pub fn hash_complex(real: f64, imag: f64) -> PyHash {
+    let real = hash_float(real);
+    let imag = hash_float(imag);
+    fix_sentinel(real.wrapping_add(IMAG.wrapping_mul(imag)))
+}
+
+/// -1 signals an error in CPython's C API, so it is never a hash.
+fn fix_sentinel(hash: PyHash) -> PyHash {
+    if hash == -1 {
+        -2
+    } else {
+        hash
+    }
+}
+
+/// Split a finite `value` into a mantissa between 0.5 and 1 in magnitude,
+/// and a power of two, like C's `frexp`.
+fn frexp(value: f64) -> (f64, i32) {
+    if value == 0.0 {
+        return (value, 0);
+    }
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i32;
+    if exponent == 0 {
+        // Subnormal numbers are scaled up to get a normal mantissa.
+        let (mantissa, exponent) = frexp(value * 2f64.powi(54));
+        return (mantissa, exponent - 54);
+    }
+    let mantissa = f64::from_bits((bits & !(0x7ff << 52)) | (1022 << 52));
+    (mantissa, exponent - 1022)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_float() {
+        assert_eq!(hash_float(0.0), 0);
+        assert_eq!(hash_float(-0.0), 0);
+        assert_eq!(hash_float(1.5), 1_152_921_504_606_846_977);
+        assert_eq!(hash_float(-1.0), -2);
+        assert_eq!(hash_float(1e300), 1_224_995_262_755_759_164);
+        assert_eq!(hash_float(-3.75e200), -88_253_901_309_920_080);
+        assert_eq!(hash_float(2.5e-10), 699_647_011_998_930_896);
+        assert_eq!(hash_float(std::f64::INFINITY), 314_159);
+        assert_eq!(hash_float(std::f64::NEG_INFINITY), -314_159);
+        assert_eq!(hash_float(5e-324), 16_777_216);
+    }
+
+    #[test]
+    fn test_hash_bigint() {
+        assert_eq!(hash_bigint(&BigInt::from(-1)), -2);
+        assert_eq!(hash_bigint(&BigInt::from(MODULUS)), 0);
+        assert_eq!(hash_bigint(&BigInt::from(12345)), hash_float(12345.0));
+        assert_eq!(hash_bigint(&-BigInt::from(MODULUS + 5)), -5);
+    }
+
+    #[test]
+    fn test_hash_complex() {
+        assert_eq!(hash_complex(1.0, 2.0), 2_000_007);
+        assert_eq!(hash_complex(-1.0, 0.0), -2);
+        assert_eq!(hash_complex(0.0, -1.0), -2_000_006);
+    }
+}
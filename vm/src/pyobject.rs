@@ -16,14 +16,18 @@ use crate::bytecode;
 use crate::exceptions;
 use crate::frame::Scope;
 use crate::function::{IntoPyNativeFunc, PyFuncArgs};
+use crate::gc;
+use crate::obj::objasyncgenerator;
 use crate::obj::objbool;
 use crate::obj::objbuiltinfunc::PyBuiltinFunction;
 use crate::obj::objbytearray;
 use crate::obj::objbytes;
+use crate::obj::objcell::{self, PyCellRef};
 use crate::obj::objclassmethod;
 use crate::obj::objcode;
 use crate::obj::objcode::PyCodeRef;
 use crate::obj::objcomplex::{self, PyComplex};
+use crate::obj::objcoroutine;
 use crate::obj::objdict::{self, PyDict, PyDictRef};
 use crate::obj::objellipsis;
 use crate::obj::objenumerate;
@@ -52,6 +56,7 @@ use crate::obj::objtuple::{self, PyTuple};
 use crate::obj::objtype::{self, PyClass, PyClassRef};
 use crate::obj::objweakref;
 use crate::obj::objzip;
+use crate::traceback;
 use crate::vm::VirtualMachine;
 
 /* Python objects and references.
@@ -110,6 +115,7 @@ pub struct PyContext {
     pub bytes_type: PyClassRef,
     pub bytearray_type: PyClassRef,
     pub bool_type: PyClassRef,
+    pub cell_type: PyClassRef,
     pub classmethod_type: PyClassRef,
     pub code_type: PyClassRef,
     pub dict_type: PyClassRef,
@@ -120,6 +126,10 @@ pub struct PyContext {
     pub frame_type: PyClassRef,
     pub frozenset_type: PyClassRef,
     pub generator_type: PyClassRef,
+    pub coroutine_type: PyClassRef,
+    pub coroutine_wrapper_type: PyClassRef,
+    pub async_generator_type: PyClassRef,
+    pub async_generator_asend_type: PyClassRef,
     pub int_type: PyClassRef,
     pub iter_type: PyClassRef,
     pub complex_type: PyClassRef,
@@ -136,6 +146,7 @@ pub struct PyContext {
     pub staticmethod_type: PyClassRef,
     pub super_type: PyClassRef,
     pub str_type: PyClassRef,
+    pub traceback_type: PyClassRef,
     pub range_type: PyClassRef,
     pub slice_type: PyClassRef,
     pub type_type: PyClassRef,
@@ -185,7 +196,7 @@ fn init_type_hierarchy() -> (PyClassRef, PyClassRef) {
     // (and yes, this will never get dropped. TODO?)
     let (type_type, object_type) = unsafe {
         let object_type = PyObject {
-            typ: mem::uninitialized(), // !
+            typ: mem::transmute::<[usize; 2], PyClassRef>([8, 8]), // !
             dict: None,
             payload: PyClass {
                 name: String::from("object"),
@@ -197,7 +208,7 @@ fn init_type_hierarchy() -> (PyClassRef, PyClassRef) {
         .into_ref();
 
         let type_type = PyObject {
-            typ: mem::uninitialized(), // !
+            typ: mem::transmute::<[usize; 2], PyClassRef>([8, 8]), // !
             dict: None,
             payload: PyClass {
                 name: String::from("type"),
@@ -245,6 +256,11 @@ impl PyContext {
         let super_type = create_type("super", &type_type, &object_type);
         let weakref_type = create_type("ref", &type_type, &object_type);
         let generator_type = create_type("generator", &type_type, &object_type);
+        let coroutine_type = create_type("coroutine", &type_type, &object_type);
+        let coroutine_wrapper_type = create_type("coroutine_wrapper", &type_type, &object_type);
+        let async_generator_type = create_type("async_generator", &type_type, &object_type);
+        let async_generator_asend_type =
+            create_type("async_generator_asend", &type_type, &object_type);
         let bound_method_type = create_type("method", &type_type, &object_type);
         let str_type = create_type("str", &type_type, &object_type);
         let list_type = create_type("list", &type_type, &object_type);
@@ -253,6 +269,8 @@ impl PyContext {
         let int_type = create_type("int", &type_type, &object_type);
         let float_type = create_type("float", &type_type, &object_type);
         let frame_type = create_type("frame", &type_type, &object_type);
+        let cell_type = create_type("cell", &type_type, &object_type);
+        let traceback_type = create_type("traceback", &type_type, &object_type);
         let complex_type = create_type("complex", &type_type, &object_type);
         let bytes_type = create_type("bytes", &type_type, &object_type);
         let bytearray_type = create_type("bytearray", &type_type, &object_type);
@@ -292,6 +310,7 @@ impl PyContext {
             memoryview_type,
             bytearray_type,
             bytes_type,
+            cell_type,
             code_type,
             complex_type,
             classmethod_type,
@@ -316,6 +335,7 @@ impl PyContext {
             ellipsis,
             not_implemented,
             str_type,
+            traceback_type,
             range_type,
             slice_type,
             object: object_type,
@@ -325,6 +345,10 @@ impl PyContext {
             property_type,
             readonly_property_type,
             generator_type,
+            coroutine_type,
+            coroutine_wrapper_type,
+            async_generator_type,
+            async_generator_asend_type,
             module_type,
             bound_method_type,
             weakref_type,
@@ -341,6 +365,8 @@ impl PyContext {
         objstaticmethod::init(&context);
         objclassmethod::init(&context);
         objgenerator::init(&context);
+        objcoroutine::init(&context);
+        objasyncgenerator::init(&context);
         objint::init(&context);
         objfloat::init(&context);
         objcomplex::init(&context);
@@ -362,6 +388,8 @@ impl PyContext {
         objbool::init(&context);
         objcode::init(&context);
         objframe::init(&context);
+        objcell::init(&context);
+        traceback::init(&context);
         objweakref::init(&context);
         objnone::init(&context);
         objmodule::init(&context);
@@ -377,6 +405,10 @@ impl PyContext {
         self.bytes_type.clone()
     }
 
+    pub fn cell_type(&self) -> PyClassRef {
+        self.cell_type.clone()
+    }
+
     pub fn code_type(&self) -> PyClassRef {
         self.code_type.clone()
     }
@@ -461,6 +493,10 @@ impl PyContext {
         self.str_type.clone()
     }
 
+    pub fn traceback_type(&self) -> PyClassRef {
+        self.traceback_type.clone()
+    }
+
     pub fn super_type(&self) -> PyClassRef {
         self.super_type.clone()
     }
@@ -493,6 +529,22 @@ impl PyContext {
         self.generator_type.clone()
     }
 
+    pub fn coroutine_type(&self) -> PyClassRef {
+        self.coroutine_type.clone()
+    }
+
+    pub fn coroutine_wrapper_type(&self) -> PyClassRef {
+        self.coroutine_wrapper_type.clone()
+    }
+
+    pub fn async_generator_type(&self) -> PyClassRef {
+        self.async_generator_type.clone()
+    }
+
+    pub fn async_generator_asend_type(&self) -> PyClassRef {
+        self.async_generator_asend_type.clone()
+    }
+
     pub fn bound_method_type(&self) -> PyClassRef {
         self.bound_method_type.clone()
     }
@@ -624,9 +676,10 @@ impl PyContext {
         code_obj: PyCodeRef,
         scope: Scope,
         defaults: PyObjectRef,
+        closure: Vec<PyCellRef>,
     ) -> PyObjectRef {
         PyObject::new(
-            PyFunction::new(code_obj, scope, defaults),
+            PyFunction::new(code_obj, scope, defaults, closure),
             self.function_type(),
             Some(self.new_dict()),
         )
@@ -719,6 +772,15 @@ where
     pub payload: T,
 }
 
+impl<T> Drop for PyObject<T>
+where
+    T: ?Sized + PyObjectPayload,
+{
+    fn drop(&mut self) {
+        gc::dropping(self);
+    }
+}
+
 impl PyObject<dyn PyObjectPayload> {
     /// Attempt to downcast this reference to a subclass.
     ///
@@ -950,21 +1012,54 @@ impl DictProtocol for PyObjectRef {
 
     fn del_item(&self, key: &str) {
         let mut elements = objdict::get_mut_elements(self);
-        elements.remove(key).unwrap();
+        elements.remove(objdict::str_key(key).as_ref()).unwrap();
+    }
+}
+
+/// A payload that exposes its contents as a block of raw bytes.
+///
+/// Exporters hand out access through closures, so that types keeping their
+/// data behind a `RefCell` only borrow it for the duration of the access.
+pub trait PyBuffer {
+    fn readonly(&self) -> bool {
+        true
+    }
+
+    fn with_bytes(&self, vm: &VirtualMachine, f: &mut dyn FnMut(&[u8])) -> PyResult<()>;
+
+    fn with_bytes_mut(&self, vm: &VirtualMachine, _f: &mut dyn FnMut(&mut [u8])) -> PyResult<()> {
+        Err(vm.new_type_error("cannot modify read-only memory".to_string()))
+    }
+
+    /// Called when a view starts referring to the data, which must then not
+    /// be resized until the matching `release`.
+    fn export(&self) {}
+
+    fn release(&self) {}
+}
+
+impl<'a> dyn PyBuffer + 'a {
+    pub fn nbytes(&self, vm: &VirtualMachine) -> PyResult<usize> {
+        let mut nbytes = 0;
+        self.with_bytes(vm, &mut |bytes| nbytes = bytes.len())?;
+        Ok(nbytes)
+    }
+
+    pub fn to_vec(&self, vm: &VirtualMachine) -> PyResult<Vec<u8>> {
+        let mut data = vec![];
+        self.with_bytes(vm, &mut |bytes| data.extend_from_slice(bytes))?;
+        Ok(data)
     }
 }
 
 pub trait BufferProtocol {
-    fn readonly(&self) -> bool;
+    /// The buffer exported by this object, if its type supports the protocol.
+    fn as_buffer(&self) -> Option<&dyn PyBuffer>;
 }
 
 impl BufferProtocol for PyObjectRef {
-    fn readonly(&self) -> bool {
-        match self.class().name.as_str() {
-            "bytes" => false,
-            "bytearray" | "memoryview" => true,
-            _ => panic!("Bytes-Like type expected not {:?}", self),
-        }
+    fn as_buffer(&self) -> Option<&dyn PyBuffer> {
+        self.payload.as_buffer()
     }
 }
 
@@ -1156,7 +1251,12 @@ where
 
     // Move this object into a reference object, transferring ownership.
     pub fn into_ref(self) -> PyObjectRef {
-        Rc::new(self)
+        let track = gc::is_container::<T>() || self.dict.is_some();
+        let obj: PyObjectRef = Rc::new(self);
+        if track {
+            gc::track(&obj);
+        }
+        obj
     }
 }
 
@@ -1175,6 +1275,11 @@ impl PyObject<dyn PyObjectPayload> {
 pub trait PyValue: fmt::Debug + Sized + 'static {
     fn class(vm: &VirtualMachine) -> PyClassRef;
 
+    /// Payloads supporting the buffer protocol return themselves here.
+    fn as_buffer(&self) -> Option<&dyn PyBuffer> {
+        None
+    }
+
     fn into_ref(self, vm: &VirtualMachine) -> PyRef<Self> {
         PyRef {
             obj: PyObject::new(self, Self::class(vm), None),
@@ -1204,6 +1309,8 @@ pub trait PyValue: fmt::Debug + Sized + 'static {
 
 pub trait PyObjectPayload: Any + fmt::Debug + 'static {
     fn as_any(&self) -> &dyn Any;
+
+    fn as_buffer(&self) -> Option<&dyn PyBuffer>;
 }
 
 impl<T: PyValue + 'static> PyObjectPayload for T {
@@ -1211,6 +1318,11 @@ impl<T: PyValue + 'static> PyObjectPayload for T {
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    #[inline]
+    fn as_buffer(&self) -> Option<&dyn PyBuffer> {
+        PyValue::as_buffer(self)
+    }
 }
 
 pub enum Either<A, B> {
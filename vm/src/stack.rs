@@ -0,0 +1,63 @@
+//! Native stack bounds, so that deep recursion raises `RecursionError`
+//! before the thread running the interpreter overflows its stack.
+
+/// Stack kept free below the limit, for raising the `RecursionError` and
+/// for the native calls made between two frame pushes.
+const STACK_HEADROOM: usize = 512 * 1024;
+
+/// The lowest stack address the current thread may reach before a new
+/// frame is refused, or `None` when the stack bounds are unknown.
+pub fn stack_limit() -> Option<usize> {
+    let (low, size) = stack_bounds()?;
+    if size <= STACK_HEADROOM * 2 {
+        // Too small to reserve the headroom; only the recursion limit applies.
+        return None;
+    }
+    Some(low + STACK_HEADROOM)
+}
+
+/// An address near the top of the current native stack.
+#[inline(never)]
+pub fn stack_pointer() -> usize {
+    let marker = 0u8;
+    &marker as *const u8 as usize
+}
+
+/// The lowest address and the size of the current thread's stack.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn stack_bounds() -> Option<(usize, usize)> {
+    use std::mem;
+
+    unsafe {
+        let mut attr: libc::pthread_attr_t = mem::zeroed();
+        if libc::pthread_getattr_np(libc::pthread_self(), &mut attr) != 0 {
+            return None;
+        }
+        let mut addr: *mut libc::c_void = std::ptr::null_mut();
+        let mut size: libc::size_t = 0;
+        let result = libc::pthread_attr_getstack(&attr, &mut addr, &mut size);
+        libc::pthread_attr_destroy(&mut attr);
+        if result == 0 {
+            Some((addr as usize, size as usize))
+        } else {
+            None
+        }
+    }
+}
+
+/// The lowest address and the size of the current thread's stack.
+#[cfg(target_os = "macos")]
+fn stack_bounds() -> Option<(usize, usize)> {
+    unsafe {
+        let thread = libc::pthread_self();
+        // This is the highest address; the stack grows down from it.
+        let top = libc::pthread_get_stackaddr_np(thread) as usize;
+        let size = libc::pthread_get_stacksize_np(thread) as usize;
+        Some((top - size, size))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn stack_bounds() -> Option<(usize, usize)> {
+    None
+}
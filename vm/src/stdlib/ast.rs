@@ -92,13 +92,18 @@ fn statement_to_ast(vm: &VirtualMachine, statement: &ast::LocatedStatement) -> P
             node
         }
         ast::Statement::FunctionDef {
+            is_async,
             name,
             args,
             body,
             decorator_list,
             returns,
         } => {
-            let node = create_node(vm, "FunctionDef");
+            let node = if *is_async {
+                create_node(vm, "AsyncFunctionDef")
+            } else {
+                create_node(vm, "FunctionDef")
+            };
 
             // Set name:
             vm.ctx
@@ -179,12 +184,17 @@ fn statement_to_ast(vm: &VirtualMachine, statement: &ast::LocatedStatement) -> P
             node
         }
         ast::Statement::For {
+            is_async,
             target,
             iter,
             body,
             orelse,
         } => {
-            let node = create_node(vm, "For");
+            let node = if *is_async {
+                create_node(vm, "AsyncFor")
+            } else {
+                create_node(vm, "For")
+            };
 
             let py_target = expression_to_ast(vm, target);
             vm.ctx.set_attr(&node, "target", py_target);
@@ -504,6 +514,14 @@ fn expression_to_ast(vm: &VirtualMachine, expression: &ast::Expression) -> PyObj
 
             node
         }
+        ast::Expression::Await { value } => {
+            let node = create_node(vm, "Await");
+
+            let py_value = expression_to_ast(vm, value);
+            vm.ctx.set_attr(&node, "value", py_value);
+
+            node
+        }
         ast::Expression::Subscript { a, b } => {
             let node = create_node(vm, "Subscript");
 
@@ -598,6 +616,9 @@ fn comprehension_to_ast(vm: &VirtualMachine, comprehension: &ast::Comprehension)
     let py_ifs = expressions_to_ast(vm, &comprehension.ifs);
     vm.ctx.set_attr(&node, "ifs", py_ifs);
 
+    let py_is_async = vm.ctx.new_bool(comprehension.is_async);
+    vm.ctx.set_attr(&node, "is_async", py_is_async);
+
     node
 }
 
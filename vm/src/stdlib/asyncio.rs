@@ -0,0 +1,2020 @@
+/*
+ * A subset of the asyncio module.
+ *
+ * The event loop runs callbacks, timers and callbacks waiting for sockets to
+ * become ready. Tasks drive coroutines, both `async def` coroutines and
+ * generator based ones using `yield from`: each time a coroutine yields a
+ * future, the task resumes it when that future is done.
+ */
+
+use std::cell::{Cell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt;
+use std::rc::Rc;
+
+use crate::exceptions;
+use crate::function::{Args, KwArgs, OptionalArg};
+use crate::obj::objbytes::PyBytesRef;
+use crate::obj::objcoroutine::{self, PyCoroutine};
+use crate::obj::objfloat;
+use crate::obj::objgenerator::{self, PyGenerator};
+use crate::obj::objint;
+use crate::obj::objiter;
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{
+    IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
+};
+use crate::stdlib::time_module;
+use crate::vm::VirtualMachine;
+
+use num_traits::ToPrimitive;
+
+fn module_attr(vm: &VirtualMachine, name: &str) -> PyResult {
+    let module = vm.import("asyncio")?;
+    vm.get_attribute(module, name)
+}
+
+fn set_module_attr(vm: &VirtualMachine, name: &str, value: PyObjectRef) -> PyResult<()> {
+    let module = vm.import("asyncio")?;
+    vm.ctx.set_attr(&module, name, value);
+    Ok(())
+}
+
+fn new_cancelled_error(vm: &VirtualMachine) -> PyResult {
+    vm.new_empty_exception(vm.class("asyncio", "CancelledError"))
+}
+
+fn is_cancelled_error(vm: &VirtualMachine, exc: &PyObjectRef) -> bool {
+    objtype::isinstance(exc, &vm.class("asyncio", "CancelledError"))
+}
+
+fn new_invalid_state_error(vm: &VirtualMachine, msg: &str) -> PyObjectRef {
+    vm.new_exception(vm.class("asyncio", "InvalidStateError"), msg.to_string())
+}
+
+fn is_blocking_io_error(vm: &VirtualMachine, exc: &PyObjectRef) -> bool {
+    objtype::isinstance(exc, &vm.ctx.exceptions.blocking_io_error)
+}
+
+fn report_exception(vm: &VirtualMachine, message: &str, exc: &PyObjectRef) {
+    eprintln!("{}", message);
+    for line in exceptions::format_exception(vm, exc) {
+        eprint!("{}", line);
+    }
+}
+
+/// A callback scheduled on the event loop, either to run soon or, for timer
+/// handles, at a given time.
+#[derive(Debug)]
+struct PyHandle {
+    callback: PyObjectRef,
+    args: Vec<PyObjectRef>,
+    when: Option<f64>,
+    cancelled: Cell<bool>,
+}
+
+type PyHandleRef = PyRef<PyHandle>;
+
+impl PyValue for PyHandle {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "Handle")
+    }
+}
+
+impl PyHandle {
+    /// Run the callback. Exceptions are reported rather than stopping the
+    /// loop, unless they aren't `Exception`s (such as `KeyboardInterrupt`).
+    fn run(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.cancelled.get() {
+            return Ok(());
+        }
+        match vm.invoke(self.callback.clone(), self.args.clone()) {
+            Ok(_) => Ok(()),
+            Err(exc) => {
+                if objtype::isinstance(&exc, &vm.ctx.exceptions.exception_type) {
+                    let callback = vm.to_repr(&self.callback)?;
+                    let message = format!("Exception in callback {}", callback.value);
+                    report_exception(vm, &message, &exc);
+                    Ok(())
+                } else {
+                    Err(exc)
+                }
+            }
+        }
+    }
+}
+
+impl PyHandleRef {
+    fn cancel(self, _vm: &VirtualMachine) {
+        self.cancelled.set(true);
+    }
+
+    fn cancelled(self, _vm: &VirtualMachine) -> bool {
+        self.cancelled.get()
+    }
+
+    fn when(self, _vm: &VirtualMachine) -> Option<f64> {
+        self.when
+    }
+
+    fn repr(self, vm: &VirtualMachine) -> PyResult<String> {
+        let state = if self.cancelled.get() {
+            " cancelled"
+        } else {
+            ""
+        };
+        let callback = vm.to_repr(&self.callback)?;
+        Ok(format!(
+            "<{}{} {}>",
+            self.as_object().class().name,
+            state,
+            callback.value
+        ))
+    }
+}
+
+#[derive(Debug)]
+struct Timer {
+    when: f64,
+    seq: u64,
+    handle: PyHandleRef,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    // `BinaryHeap` pops the greatest element, so the order is reversed to get
+    // the earliest timer first, and the first scheduled of equal timers.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .when
+            .partial_cmp(&self.when)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct PyEventLoop {
+    ready: RefCell<VecDeque<PyHandleRef>>,
+    timers: RefCell<BinaryHeap<Timer>>,
+    timer_count: Cell<u64>,
+    readers: RefCell<HashMap<i64, PyHandleRef>>,
+    writers: RefCell<HashMap<i64, PyHandleRef>>,
+    tasks: RefCell<Vec<PyFutureRef>>,
+    running: Cell<bool>,
+    stopping: Cell<bool>,
+    closed: Cell<bool>,
+}
+
+type PyEventLoopRef = PyRef<PyEventLoop>;
+
+// The loop refers to tasks, which refer back to the loop, so only a summary
+// is printed.
+impl fmt::Debug for PyEventLoop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PyEventLoop {{ running: {}, closed: {} }}",
+            self.running.get(),
+            self.closed.get()
+        )
+    }
+}
+
+impl PyValue for PyEventLoop {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "EventLoop")
+    }
+}
+
+impl PyEventLoop {
+    fn new() -> Self {
+        PyEventLoop {
+            ready: RefCell::new(VecDeque::new()),
+            timers: RefCell::new(BinaryHeap::new()),
+            timer_count: Cell::new(0),
+            readers: RefCell::new(HashMap::new()),
+            writers: RefCell::new(HashMap::new()),
+            tasks: RefCell::new(vec![]),
+            running: Cell::new(false),
+            stopping: Cell::new(false),
+            closed: Cell::new(false),
+        }
+    }
+
+    fn check_closed(&self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.closed.get() {
+            Err(vm.new_runtime_error("Event loop is closed".to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn schedule(
+        &self,
+        callback: PyObjectRef,
+        args: Vec<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyHandleRef {
+        let handle = PyHandle {
+            callback,
+            args,
+            when: None,
+            cancelled: Cell::new(false),
+        }
+        .into_ref(vm);
+        self.ready.borrow_mut().push_back(handle.clone());
+        handle
+    }
+
+    fn add_timer(
+        &self,
+        when: f64,
+        callback: PyObjectRef,
+        args: Vec<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyHandleRef> {
+        self.check_closed(vm)?;
+        let handle = PyHandle {
+            callback,
+            args,
+            when: Some(when),
+            cancelled: Cell::new(false),
+        }
+        .into_ref(vm);
+        let seq = self.timer_count.get();
+        self.timer_count.set(seq + 1);
+        self.timers.borrow_mut().push(Timer {
+            when,
+            seq,
+            handle: handle.clone(),
+        });
+        Ok(handle)
+    }
+
+    fn io_handlers(&self, write: bool) -> &RefCell<HashMap<i64, PyHandleRef>> {
+        if write {
+            &self.writers
+        } else {
+            &self.readers
+        }
+    }
+
+    fn add_io(
+        &self,
+        fd: i64,
+        write: bool,
+        callback: PyObjectRef,
+        args: Vec<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<()> {
+        self.check_closed(vm)?;
+        let handle = PyHandle {
+            callback,
+            args,
+            when: None,
+            cancelled: Cell::new(false),
+        }
+        .into_ref(vm);
+        if let Some(old) = self.io_handlers(write).borrow_mut().insert(fd, handle) {
+            old.cancelled.set(true);
+        }
+        Ok(())
+    }
+
+    fn remove_io(&self, fd: i64, write: bool) -> bool {
+        match self.io_handlers(write).borrow_mut().remove(&fd) {
+            Some(handle) => {
+                handle.cancelled.set(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Run one iteration of the loop: wait for sockets until the next timer
+    /// is due, then run the callbacks which are ready.
+    fn run_once(&self, vm: &VirtualMachine) -> PyResult<()> {
+        let timeout = if !self.ready.borrow().is_empty() || self.stopping.get() {
+            Some(0.0)
+        } else {
+            self.timers
+                .borrow()
+                .peek()
+                .map(|timer| (timer.when - time_module::monotonic()).max(0.0))
+        };
+        self.wait_for_io(timeout, vm)?;
+
+        let now = time_module::monotonic();
+        loop {
+            let due = match self.timers.borrow().peek() {
+                Some(timer) => timer.when <= now,
+                None => false,
+            };
+            if !due {
+                break;
+            }
+            let timer = self.timers.borrow_mut().pop().unwrap();
+            if !timer.handle.cancelled.get() {
+                self.ready.borrow_mut().push_back(timer.handle);
+            }
+        }
+
+        // Callbacks scheduled by these callbacks wait for the next iteration.
+        let count = self.ready.borrow().len();
+        for _ in 0..count {
+            let handle = self.ready.borrow_mut().pop_front();
+            match handle {
+                Some(handle) => handle.run(vm)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn has_io(&self) -> bool {
+        !self.readers.borrow().is_empty() || !self.writers.borrow().is_empty()
+    }
+
+    #[cfg(unix)]
+    fn wait_for_io(&self, timeout: Option<f64>, vm: &VirtualMachine) -> PyResult<()> {
+        if !self.has_io() && timeout == Some(0.0) {
+            return Ok(());
+        }
+        let mut fds: Vec<libc::pollfd> = vec![];
+        for &fd in self.readers.borrow().keys() {
+            fds.push(libc::pollfd {
+                fd: fd as libc::c_int,
+                events: libc::POLLIN,
+                revents: 0,
+            });
+        }
+        for &fd in self.writers.borrow().keys() {
+            match fds.iter_mut().find(|pollfd| i64::from(pollfd.fd) == fd) {
+                Some(pollfd) => pollfd.events |= libc::POLLOUT,
+                None => fds.push(libc::pollfd {
+                    fd: fd as libc::c_int,
+                    events: libc::POLLOUT,
+                    revents: 0,
+                }),
+            }
+        }
+        let timeout = match timeout {
+            Some(timeout) => (timeout * 1000.0)
+                .ceil()
+                .min(f64::from(libc::c_int::max_value()))
+                as libc::c_int,
+            None => -1,
+        };
+        let result = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+        if result < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() != std::io::ErrorKind::Interrupted {
+                return Err(vm.new_os_error(err.to_string()));
+            }
+        }
+        vm.check_signals()?;
+
+        for pollfd in fds {
+            let fd = i64::from(pollfd.fd);
+            let failed = libc::POLLERR | libc::POLLHUP | libc::POLLNVAL;
+            if pollfd.revents & (libc::POLLIN | failed) != 0 {
+                if let Some(handle) = self.readers.borrow().get(&fd) {
+                    self.ready.borrow_mut().push_back(handle.clone());
+                }
+            }
+            if pollfd.revents & (libc::POLLOUT | failed) != 0 {
+                if let Some(handle) = self.writers.borrow().get(&fd) {
+                    self.ready.borrow_mut().push_back(handle.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Without `poll()`, sockets are retried every few milliseconds.
+    #[cfg(not(unix))]
+    fn wait_for_io(&self, timeout: Option<f64>, vm: &VirtualMachine) -> PyResult<()> {
+        let has_io = self.has_io();
+        let timeout = match timeout {
+            Some(timeout) if has_io => timeout.min(0.01),
+            Some(timeout) => timeout,
+            None if has_io => 0.01,
+            None => 1.0,
+        };
+        if timeout > 0.0 {
+            let secs = timeout.trunc() as u64;
+            let nanos = (timeout.fract() * 1e9) as u32;
+            std::thread::sleep(std::time::Duration::new(secs, nanos));
+        }
+        vm.check_signals()?;
+        if has_io {
+            let mut ready = self.ready.borrow_mut();
+            ready.extend(self.readers.borrow().values().cloned());
+            ready.extend(self.writers.borrow().values().cloned());
+        }
+        Ok(())
+    }
+}
+
+impl PyEventLoopRef {
+    fn new_loop(cls: PyClassRef, vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+        PyEventLoop::new().into_ref_with_type(vm, cls)
+    }
+
+    fn time(self, _vm: &VirtualMachine) -> f64 {
+        time_module::monotonic()
+    }
+
+    fn call_soon(
+        self,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyHandleRef> {
+        self.check_closed(vm)?;
+        Ok(self.schedule(callback, args.into_iter().collect(), vm))
+    }
+
+    fn call_later(
+        self,
+        delay: PyObjectRef,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyHandleRef> {
+        let when = time_module::monotonic() + objfloat::make_float(vm, &delay)?;
+        self.add_timer(when, callback, args.into_iter().collect(), vm)
+    }
+
+    fn call_at(
+        self,
+        when: PyObjectRef,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyHandleRef> {
+        let when = objfloat::make_float(vm, &when)?;
+        self.add_timer(when, callback, args.into_iter().collect(), vm)
+    }
+
+    fn create_future(self, vm: &VirtualMachine) -> PyFutureRef {
+        new_future(&self, vm)
+    }
+
+    fn create_task(self, coro: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyFutureRef> {
+        new_task(vm, &self, coro, None)
+    }
+
+    fn run_forever(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.check_closed(vm)?;
+        if self.running.get() {
+            return Err(vm.new_runtime_error("This event loop is already running".to_string()));
+        }
+        if running_loop(vm)?.is_some() {
+            return Err(vm.new_runtime_error(
+                "Cannot run the event loop while another loop is running".to_string(),
+            ));
+        }
+        self.running.set(true);
+        set_module_attr(vm, "_running_loop", self.clone().into_object())?;
+        let result = loop {
+            if let Err(exc) = self.run_once(vm) {
+                break Err(exc);
+            }
+            if self.stopping.get() {
+                break Ok(());
+            }
+        };
+        self.stopping.set(false);
+        self.running.set(false);
+        set_module_attr(vm, "_running_loop", vm.get_none())?;
+        result
+    }
+
+    fn run_until_complete(self, future: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.check_closed(vm)?;
+        let future = ensure_future(vm, future, Some(&self))?;
+        if !future.loop_.is(&self) {
+            return Err(vm.new_value_error(
+                "The future belongs to a different loop than the one specified as the loop argument"
+                    .to_string(),
+            ));
+        }
+        let stopper = {
+            let zelf = self.clone();
+            vm.ctx
+                .new_rustfunc(move |_future: PyObjectRef, _vm: &VirtualMachine| {
+                    zelf.stopping.set(true)
+                })
+        };
+        future.add_callback(stopper.clone(), vm);
+        let result = self.clone().run_forever(vm);
+        future.remove_callback(&stopper);
+        result?;
+        if !future.is_done() {
+            return Err(
+                vm.new_runtime_error("Event loop stopped before Future completed.".to_string())
+            );
+        }
+        future.get_result(vm)
+    }
+
+    fn stop(self, _vm: &VirtualMachine) {
+        self.stopping.set(true);
+    }
+
+    fn is_running(self, _vm: &VirtualMachine) -> bool {
+        self.running.get()
+    }
+
+    fn is_closed(self, _vm: &VirtualMachine) -> bool {
+        self.closed.get()
+    }
+
+    fn close(self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.running.get() {
+            return Err(vm.new_runtime_error("Cannot close a running event loop".to_string()));
+        }
+        self.closed.set(true);
+        self.ready.borrow_mut().clear();
+        self.timers.borrow_mut().clear();
+        self.readers.borrow_mut().clear();
+        self.writers.borrow_mut().clear();
+        self.tasks.borrow_mut().clear();
+        Ok(())
+    }
+
+    fn add_reader(
+        self,
+        fileobj: PyObjectRef,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<()> {
+        let fd = fileobj_to_fd(vm, &fileobj)?;
+        self.add_io(fd, false, callback, args.into_iter().collect(), vm)
+    }
+
+    fn remove_reader(self, fileobj: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+        Ok(self.remove_io(fileobj_to_fd(vm, &fileobj)?, false))
+    }
+
+    fn add_writer(
+        self,
+        fileobj: PyObjectRef,
+        callback: PyObjectRef,
+        args: Args,
+        vm: &VirtualMachine,
+    ) -> PyResult<()> {
+        let fd = fileobj_to_fd(vm, &fileobj)?;
+        self.add_io(fd, true, callback, args.into_iter().collect(), vm)
+    }
+
+    fn remove_writer(self, fileobj: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+        Ok(self.remove_io(fileobj_to_fd(vm, &fileobj)?, true))
+    }
+
+    fn sock_recv(
+        self,
+        sock: PyObjectRef,
+        nbytes: PyObjectRef,
+        vm: &VirtualMachine,
+    ) -> PyNativeCoroutineRef {
+        sock_operation(vm, self, sock.clone(), false, move |vm| {
+            vm.call_method(&sock, "recv", vec![nbytes.clone()])
+                .map(Some)
+        })
+    }
+
+    fn sock_sendall(
+        self,
+        sock: PyObjectRef,
+        data: PyBytesRef,
+        vm: &VirtualMachine,
+    ) -> PyNativeCoroutineRef {
+        let sent = Cell::new(0);
+        sock_operation(vm, self, sock.clone(), true, move |vm| {
+            let rest = vm.ctx.new_bytes(data[sent.get()..].to_vec());
+            let count = vm.call_method(&sock, "send", vec![rest])?;
+            let count = objint::get_value(&count).to_usize().unwrap_or(0);
+            sent.set(sent.get() + count);
+            if sent.get() < data.len() {
+                Ok(None)
+            } else {
+                Ok(Some(vm.get_none()))
+            }
+        })
+    }
+
+    fn sock_accept(self, sock: PyObjectRef, vm: &VirtualMachine) -> PyNativeCoroutineRef {
+        sock_operation(vm, self, sock.clone(), false, move |vm| {
+            let pair = vm.call_method(&sock, "accept", vec![])?;
+            let conn = vm.call_method(&pair, "__getitem__", vec![vm.new_int(0)])?;
+            vm.call_method(&conn, "setblocking", vec![vm.new_bool(false)])?;
+            Ok(Some(pair))
+        })
+    }
+
+    /// Connecting isn't done asynchronously: the underlying socket connects
+    /// in blocking mode.
+    fn sock_connect(
+        self,
+        sock: PyObjectRef,
+        address: PyObjectRef,
+        vm: &VirtualMachine,
+    ) -> PyNativeCoroutineRef {
+        sock_operation(vm, self, sock.clone(), true, move |vm| {
+            vm.call_method(&sock, "connect", vec![address.clone()])
+                .map(Some)
+        })
+    }
+}
+
+fn fileobj_to_fd(vm: &VirtualMachine, fileobj: &PyObjectRef) -> PyResult<i64> {
+    let fd = if objtype::isinstance(fileobj, &vm.ctx.int_type()) {
+        fileobj.clone()
+    } else {
+        vm.call_method(fileobj, "fileno", vec![])?
+    };
+    let fd = i64::try_from_object(vm, fd)?;
+    if fd < 0 {
+        return Err(vm.new_value_error(format!("Invalid file descriptor: {}", fd)));
+    }
+    Ok(fd)
+}
+
+/// A coroutine which runs `operation` on a non-blocking socket, waiting for
+/// the socket to be ready each time the operation raises `BlockingIOError`
+/// or returns `None` to ask to be retried.
+fn sock_operation<F>(
+    vm: &VirtualMachine,
+    loop_: PyEventLoopRef,
+    sock: PyObjectRef,
+    write: bool,
+    operation: F,
+) -> PyNativeCoroutineRef
+where
+    F: Fn(&VirtualMachine) -> PyResult<Option<PyObjectRef>> + 'static,
+{
+    let poll = move |vm: &VirtualMachine, _running: &PyEventLoopRef| {
+        match operation(vm) {
+            Ok(Some(value)) => return Ok(Poll::Ready(value)),
+            Ok(None) => {}
+            Err(exc) => {
+                if !is_blocking_io_error(vm, &exc) {
+                    return Err(exc);
+                }
+            }
+        }
+        let fd = fileobj_to_fd(vm, &sock)?;
+        let ready = new_future(&loop_, vm);
+        let on_ready = {
+            let ready = ready.clone();
+            move |vm: &VirtualMachine| -> PyResult<()> {
+                if !ready.is_done() {
+                    ready.finish(Some(vm.get_none()), None, vm)?;
+                }
+                Ok(())
+            }
+        };
+        loop_.add_io(fd, write, vm.ctx.new_rustfunc(on_ready), vec![], vm)?;
+        // Stop waiting for the socket once the future is done, including
+        // when it is cancelled.
+        let on_done = {
+            let loop_ = loop_.clone();
+            move |_future: PyObjectRef, _vm: &VirtualMachine| {
+                loop_.remove_io(fd, write);
+            }
+        };
+        ready.add_callback(vm.ctx.new_rustfunc(on_done), vm);
+        Ok(Poll::Wait(ready))
+    };
+    PyNativeCoroutine::new(Box::new(poll)).into_ref(vm)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FutureState {
+    Pending,
+    Cancelled,
+    Finished,
+}
+
+#[derive(Debug)]
+enum FutureKind {
+    Plain,
+    /// A task, which runs a coroutine.
+    Task {
+        coro: PyObjectRef,
+        waiter: RefCell<Option<PyFutureRef>>,
+        must_cancel: Cell<bool>,
+    },
+    /// A future waiting for other futures, which cancels them when it is
+    /// cancelled.
+    Gathering {
+        children: Vec<PyFutureRef>,
+        cancel_requested: Cell<bool>,
+    },
+}
+
+/// The payload of both `Future` and `Task` objects.
+#[derive(Debug)]
+struct PyFuture {
+    loop_: PyEventLoopRef,
+    state: Cell<FutureState>,
+    result: RefCell<Option<PyObjectRef>>,
+    exception: RefCell<Option<PyObjectRef>>,
+    callbacks: RefCell<Vec<PyObjectRef>>,
+    kind: FutureKind,
+}
+
+type PyFutureRef = PyRef<PyFuture>;
+
+impl PyValue for PyFuture {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "Future")
+    }
+}
+
+impl PyFuture {
+    fn new(loop_: PyEventLoopRef, kind: FutureKind) -> Self {
+        PyFuture {
+            loop_,
+            state: Cell::new(FutureState::Pending),
+            result: RefCell::new(None),
+            exception: RefCell::new(None),
+            callbacks: RefCell::new(vec![]),
+            kind,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.state.get() != FutureState::Pending
+    }
+
+    fn get_result(&self, vm: &VirtualMachine) -> PyResult {
+        match self.state.get() {
+            FutureState::Pending => Err(new_invalid_state_error(vm, "Result is not ready.")),
+            FutureState::Cancelled => Err(new_cancelled_error(vm)?),
+            FutureState::Finished => match self.exception.borrow().as_ref() {
+                Some(exception) => Err(exception.clone()),
+                None => Ok(self
+                    .result
+                    .borrow()
+                    .clone()
+                    .unwrap_or_else(|| vm.get_none())),
+            },
+        }
+    }
+
+    fn remove_callback(&self, callback: &PyObjectRef) -> usize {
+        let mut callbacks = self.callbacks.borrow_mut();
+        let before = callbacks.len();
+        callbacks.retain(|existing| !existing.is(callback));
+        before - callbacks.len()
+    }
+}
+
+fn new_future(loop_: &PyEventLoopRef, vm: &VirtualMachine) -> PyFutureRef {
+    PyFuture::new(loop_.clone(), FutureKind::Plain).into_ref(vm)
+}
+
+impl PyFutureRef {
+    fn py_new(cls: PyClassRef, kwargs: KwArgs, vm: &VirtualMachine) -> PyResult<PyFutureRef> {
+        let loop_ = loop_argument(vm, kwargs)?;
+        PyFuture::new(loop_, FutureKind::Plain).into_ref_with_type(vm, cls)
+    }
+
+    fn done(self, _vm: &VirtualMachine) -> bool {
+        self.is_done()
+    }
+
+    fn cancelled(self, _vm: &VirtualMachine) -> bool {
+        self.state.get() == FutureState::Cancelled
+    }
+
+    fn result(self, vm: &VirtualMachine) -> PyResult {
+        self.get_result(vm)
+    }
+
+    fn exception(self, vm: &VirtualMachine) -> PyResult {
+        match self.state.get() {
+            FutureState::Pending => Err(new_invalid_state_error(vm, "Exception is not set.")),
+            FutureState::Cancelled => Err(new_cancelled_error(vm)?),
+            FutureState::Finished => Ok(self
+                .exception
+                .borrow()
+                .clone()
+                .unwrap_or_else(|| vm.get_none())),
+        }
+    }
+
+    fn set_result(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        if let FutureKind::Task { .. } = self.kind {
+            return Err(
+                vm.new_runtime_error("Task does not support set_result operation".to_string())
+            );
+        }
+        self.finish(Some(value), None, vm)
+    }
+
+    fn set_exception(self, exception: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        if let FutureKind::Task { .. } = self.kind {
+            return Err(
+                vm.new_runtime_error("Task does not support set_exception operation".to_string())
+            );
+        }
+        let exception = objgenerator::make_exception(exception, None, vm)?;
+        if objtype::isinstance(&exception, &vm.ctx.exceptions.stop_iteration) {
+            return Err(vm.new_type_error(
+                "StopIteration interacts badly with generators and cannot be raised into a Future"
+                    .to_string(),
+            ));
+        }
+        self.finish(None, Some(exception), vm)
+    }
+
+    fn cancel(self, vm: &VirtualMachine) -> PyResult<bool> {
+        self.cancel_future(vm)
+    }
+
+    fn add_done_callback(self, callback: PyObjectRef, vm: &VirtualMachine) {
+        self.add_callback(callback, vm);
+    }
+
+    fn remove_done_callback(self, callback: PyObjectRef, _vm: &VirtualMachine) -> usize {
+        self.remove_callback(&callback)
+    }
+
+    fn get_loop(self, _vm: &VirtualMachine) -> PyEventLoopRef {
+        self.loop_.clone()
+    }
+
+    fn iter(self, vm: &VirtualMachine) -> PyFutureIterRef {
+        PyFutureIter {
+            future: self,
+            yielded: Cell::new(false),
+        }
+        .into_ref(vm)
+    }
+
+    fn repr(self, vm: &VirtualMachine) -> PyResult<String> {
+        let state = match self.state.get() {
+            FutureState::Pending => "pending".to_string(),
+            FutureState::Cancelled => "cancelled".to_string(),
+            FutureState::Finished => match self.exception.borrow().as_ref() {
+                Some(exception) => format!("finished exception={}", vm.to_repr(exception)?.value),
+                None => {
+                    let result = self
+                        .result
+                        .borrow()
+                        .clone()
+                        .unwrap_or_else(|| vm.get_none());
+                    format!("finished result={}", vm.to_repr(&result)?.value)
+                }
+            },
+        };
+        Ok(format!("<{} {}>", self.as_object().class().name, state))
+    }
+
+    fn get_coro(self, vm: &VirtualMachine) -> PyObjectRef {
+        match &self.kind {
+            FutureKind::Task { coro, .. } => coro.clone(),
+            _ => vm.get_none(),
+        }
+    }
+
+    /// Mark the future as finished and schedule its callbacks.
+    fn finish(
+        &self,
+        result: Option<PyObjectRef>,
+        exception: Option<PyObjectRef>,
+        vm: &VirtualMachine,
+    ) -> PyResult<()> {
+        if self.is_done() {
+            return Err(new_invalid_state_error(vm, "invalid state"));
+        }
+        self.result.replace(result);
+        self.exception.replace(exception);
+        self.state.set(FutureState::Finished);
+        self.schedule_callbacks(vm);
+        Ok(())
+    }
+
+    fn cancel_plain(&self, vm: &VirtualMachine) -> bool {
+        if self.is_done() {
+            return false;
+        }
+        self.state.set(FutureState::Cancelled);
+        self.schedule_callbacks(vm);
+        true
+    }
+
+    fn cancel_future(&self, vm: &VirtualMachine) -> PyResult<bool> {
+        if self.is_done() {
+            return Ok(false);
+        }
+        match &self.kind {
+            FutureKind::Plain => Ok(self.cancel_plain(vm)),
+            FutureKind::Task {
+                waiter,
+                must_cancel,
+                ..
+            } => {
+                let waiter = waiter.borrow().clone();
+                if let Some(waiter) = waiter {
+                    if waiter.cancel_future(vm)? {
+                        // The task is woken up with `CancelledError` thrown in.
+                        return Ok(true);
+                    }
+                }
+                must_cancel.set(true);
+                Ok(true)
+            }
+            FutureKind::Gathering {
+                children,
+                cancel_requested,
+            } => {
+                let mut cancelled = false;
+                for child in children {
+                    if child.cancel_future(vm)? {
+                        cancelled = true;
+                    }
+                }
+                if cancelled {
+                    cancel_requested.set(true);
+                }
+                Ok(cancelled)
+            }
+        }
+    }
+
+    /// Finish in the same state as `other`.
+    fn copy_state(&self, other: &PyFuture, vm: &VirtualMachine) -> PyResult<()> {
+        match other.state.get() {
+            FutureState::Pending => Ok(()),
+            FutureState::Cancelled => {
+                self.cancel_plain(vm);
+                Ok(())
+            }
+            FutureState::Finished => self.finish(
+                other.result.borrow().clone(),
+                other.exception.borrow().clone(),
+                vm,
+            ),
+        }
+    }
+
+    fn schedule_callbacks(&self, vm: &VirtualMachine) {
+        let callbacks = self.callbacks.replace(vec![]);
+        for callback in callbacks {
+            self.loop_
+                .schedule(callback, vec![self.clone().into_object()], vm);
+        }
+    }
+
+    fn add_callback(&self, callback: PyObjectRef, vm: &VirtualMachine) {
+        if self.is_done() {
+            self.loop_
+                .schedule(callback, vec![self.clone().into_object()], vm);
+        } else {
+            self.callbacks.borrow_mut().push(callback);
+        }
+    }
+
+    /// Resume the task's coroutine, throwing `exc` into it if given.
+    fn step(&self, exc: Option<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+        let (coro, waiter, must_cancel) = match &self.kind {
+            FutureKind::Task {
+                coro,
+                waiter,
+                must_cancel,
+            } => (coro, waiter, must_cancel),
+            _ => unreachable!("only tasks have steps"),
+        };
+        if self.is_done() {
+            return Ok(());
+        }
+        let mut exc = exc;
+        if must_cancel.get() {
+            if !exc
+                .as_ref()
+                .map_or(false, |exc| is_cancelled_error(vm, exc))
+            {
+                exc = Some(new_cancelled_error(vm)?);
+            }
+            must_cancel.set(false);
+        }
+        waiter.replace(None);
+
+        let previous_task = module_attr(vm, "_current_task")?;
+        set_module_attr(vm, "_current_task", self.clone().into_object())?;
+        let result = match exc {
+            None => vm.call_method(coro, "send", vec![vm.get_none()]),
+            Some(exc) => vm.call_method(coro, "throw", vec![exc]),
+        };
+        set_module_attr(vm, "_current_task", previous_task)?;
+
+        match result {
+            Ok(yielded) => self.wait_on(yielded, vm),
+            Err(exc) => {
+                if objtype::isinstance(&exc, &vm.ctx.exceptions.stop_iteration) {
+                    if must_cancel.get() {
+                        // The task was cancelled just before it finished.
+                        must_cancel.set(false);
+                        self.cancel_plain(vm);
+                        Ok(())
+                    } else {
+                        let value = objiter::stop_iteration_value(vm, &exc)?;
+                        self.finish(Some(value), None, vm)
+                    }
+                } else if is_cancelled_error(vm, &exc) {
+                    self.cancel_plain(vm);
+                    Ok(())
+                } else if objtype::isinstance(&exc, &vm.ctx.exceptions.exception_type) {
+                    self.finish(None, Some(exc), vm)
+                } else {
+                    self.finish(None, Some(exc.clone()), vm)?;
+                    Err(exc)
+                }
+            }
+        }
+    }
+
+    /// Handle a value yielded by the task's coroutine, which should be a
+    /// future to wait for, or `None` to just let other tasks run.
+    fn wait_on(&self, yielded: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        if yielded.is(&vm.get_none()) {
+            schedule_step(self, None, vm);
+            return Ok(());
+        }
+        let future = match yielded.clone().downcast::<PyFuture>() {
+            Ok(future) => future,
+            Err(_) => {
+                let msg = format!("Task got bad yield: {}", vm.to_repr(&yielded)?.value);
+                schedule_step(self, Some(vm.new_runtime_error(msg)), vm);
+                return Ok(());
+            }
+        };
+        if future.is(self) {
+            let msg = format!(
+                "Task cannot await on itself: {}",
+                vm.to_repr(&yielded)?.value
+            );
+            schedule_step(self, Some(vm.new_runtime_error(msg)), vm);
+            return Ok(());
+        }
+        if !future.loop_.is(&self.loop_) {
+            let msg = "Task got Future attached to a different loop".to_string();
+            schedule_step(self, Some(vm.new_runtime_error(msg)), vm);
+            return Ok(());
+        }
+        if let FutureKind::Task {
+            waiter,
+            must_cancel,
+            ..
+        } = &self.kind
+        {
+            let wakeup = {
+                let zelf = self.clone();
+                move |future: PyFutureRef, vm: &VirtualMachine| zelf.wakeup(&future, vm)
+            };
+            future.add_callback(vm.ctx.new_rustfunc(wakeup), vm);
+            waiter.replace(Some(future.clone()));
+            if must_cancel.get() && future.cancel_future(vm)? {
+                must_cancel.set(false);
+            }
+        }
+        Ok(())
+    }
+
+    fn wakeup(&self, future: &PyFuture, vm: &VirtualMachine) -> PyResult<()> {
+        match future.get_result(vm) {
+            Ok(_) => self.step(None, vm),
+            Err(exc) => self.step(Some(exc), vm),
+        }
+    }
+}
+
+fn schedule_step(task: &PyFutureRef, exc: Option<PyObjectRef>, vm: &VirtualMachine) {
+    let step = {
+        let task = task.clone();
+        move |vm: &VirtualMachine| task.step(exc.clone(), vm)
+    };
+    task.loop_.schedule(vm.ctx.new_rustfunc(step), vec![], vm);
+}
+
+fn is_coroutine(obj: &PyObjectRef) -> bool {
+    obj.payload_is::<PyCoroutine>()
+        || obj.payload_is::<PyGenerator>()
+        || obj.payload_is::<PyNativeCoroutine>()
+}
+
+fn new_task(
+    vm: &VirtualMachine,
+    loop_: &PyEventLoopRef,
+    coro: PyObjectRef,
+    cls: Option<PyClassRef>,
+) -> PyResult<PyFutureRef> {
+    loop_.check_closed(vm)?;
+    if !is_coroutine(&coro) {
+        return Err(vm.new_type_error(format!(
+            "a coroutine was expected, got {}",
+            vm.to_repr(&coro)?.value
+        )));
+    }
+    let kind = FutureKind::Task {
+        coro,
+        waiter: RefCell::new(None),
+        must_cancel: Cell::new(false),
+    };
+    let cls = cls.unwrap_or_else(|| vm.class("asyncio", "Task"));
+    let task = PyFuture::new(loop_.clone(), kind).into_ref_with_type(vm, cls)?;
+    {
+        let mut tasks = loop_.tasks.borrow_mut();
+        tasks.retain(|task| !task.is_done());
+        tasks.push(task.clone());
+    }
+    schedule_step(&task, None, vm);
+    Ok(task)
+}
+
+fn task_new(
+    cls: PyClassRef,
+    coro: PyObjectRef,
+    kwargs: KwArgs,
+    vm: &VirtualMachine,
+) -> PyResult<PyFutureRef> {
+    let loop_ = loop_argument(vm, kwargs)?;
+    new_task(vm, &loop_, coro, Some(cls))
+}
+
+/// The event loop given as the `loop` keyword argument, or the current one.
+fn loop_argument(vm: &VirtualMachine, kwargs: KwArgs) -> PyResult<PyEventLoopRef> {
+    let mut loop_ = None;
+    for (name, value) in kwargs {
+        if name != "loop" {
+            return Err(vm.new_type_error(format!("'{}' is an invalid keyword argument", name)));
+        }
+        if !value.is(&vm.get_none()) {
+            loop_ = Some(PyEventLoopRef::try_from_object(vm, value)?);
+        }
+    }
+    match loop_ {
+        Some(loop_) => Ok(loop_),
+        None => get_event_loop(vm),
+    }
+}
+
+/// The iterator returned by `Future.__await__()`: it yields the future until
+/// it is done, and then returns its result.
+#[derive(Debug)]
+struct PyFutureIter {
+    future: PyFutureRef,
+    yielded: Cell<bool>,
+}
+
+type PyFutureIterRef = PyRef<PyFutureIter>;
+
+impl PyValue for PyFutureIter {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "FutureIter")
+    }
+}
+
+impl PyFutureIterRef {
+    fn iter(self, _vm: &VirtualMachine) -> PyFutureIterRef {
+        self
+    }
+
+    fn next(self, vm: &VirtualMachine) -> PyResult {
+        if !self.future.is_done() {
+            if self.yielded.get() {
+                return Err(vm.new_runtime_error("await wasn't used with future".to_string()));
+            }
+            self.yielded.set(true);
+            return Ok(self.future.clone().into_object());
+        }
+        let result = self.future.get_result(vm)?;
+        Err(objiter::new_stop_iteration_with_value(vm, result)?)
+    }
+
+    fn send(self, _value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.next(vm)
+    }
+
+    fn throw(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg,
+        _exc_tb: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        Err(objgenerator::make_exception(
+            exc_type,
+            exc_val.into_option(),
+            vm,
+        )?)
+    }
+
+    fn close(self, _vm: &VirtualMachine) {}
+}
+
+enum Poll {
+    Ready(PyObjectRef),
+    Wait(PyFutureRef),
+}
+
+type PollFn = Box<dyn FnMut(&VirtualMachine, &PyEventLoopRef) -> PyResult<Poll>>;
+
+/// A coroutine implemented in Rust, for the coroutines of this module. Each
+/// time it is resumed it polls, either returning a value or asking to wait
+/// for a future before polling again. Polling starts once it is awaited in a
+/// running event loop.
+struct PyNativeCoroutine {
+    poll: RefCell<Option<PollFn>>,
+    waiting: RefCell<Option<PyFutureRef>>,
+}
+
+type PyNativeCoroutineRef = PyRef<PyNativeCoroutine>;
+
+impl fmt::Debug for PyNativeCoroutine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("PyNativeCoroutine")
+    }
+}
+
+impl PyValue for PyNativeCoroutine {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "NativeCoroutine")
+    }
+}
+
+impl PyNativeCoroutine {
+    fn new(poll: PollFn) -> Self {
+        PyNativeCoroutine {
+            poll: RefCell::new(Some(poll)),
+            waiting: RefCell::new(None),
+        }
+    }
+
+    /// A coroutine which waits for the future created by `start`.
+    fn waiting_for<F>(start: F) -> Self
+    where
+        F: FnOnce(&VirtualMachine, &PyEventLoopRef) -> PyResult<PyFutureRef> + 'static,
+    {
+        let mut start = Some(start);
+        let mut future = None;
+        PyNativeCoroutine::new(Box::new(move |vm, loop_| {
+            if let Some(start) = start.take() {
+                let started = start(vm, loop_)?;
+                future = Some(started.clone());
+                if !started.is_done() {
+                    return Ok(Poll::Wait(started));
+                }
+            }
+            let future: &PyFutureRef = future.as_ref().unwrap();
+            future.get_result(vm).map(Poll::Ready)
+        }))
+    }
+
+    /// Forget about the future being waited for, cancelling it if it isn't
+    /// done yet.
+    fn abandon(&self, vm: &VirtualMachine) -> PyResult<()> {
+        self.poll.replace(None);
+        let waiting = self.waiting.replace(None);
+        if let Some(future) = waiting {
+            future.cancel_future(vm)?;
+        }
+        Ok(())
+    }
+}
+
+impl PyNativeCoroutineRef {
+    fn await_(self, _vm: &VirtualMachine) -> PyNativeCoroutineRef {
+        self
+    }
+
+    fn next(self, vm: &VirtualMachine) -> PyResult {
+        self.send(vm.get_none(), vm)
+    }
+
+    fn send(self, _value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        if let Some(future) = self.waiting.borrow().as_ref() {
+            if !future.is_done() {
+                return Err(vm.new_runtime_error("await wasn't used with future".to_string()));
+            }
+        }
+        let mut poll = match self.poll.replace(None) {
+            Some(poll) => poll,
+            None => {
+                return Err(
+                    vm.new_runtime_error("cannot reuse already awaited coroutine".to_string())
+                )
+            }
+        };
+        let loop_ = get_running_loop(vm)?;
+        match poll(vm, &loop_)? {
+            Poll::Ready(value) => {
+                self.waiting.replace(None);
+                Err(objiter::new_stop_iteration_with_value(vm, value)?)
+            }
+            Poll::Wait(future) => {
+                self.poll.replace(Some(poll));
+                self.waiting.replace(Some(future.clone()));
+                Ok(future.into_object())
+            }
+        }
+    }
+
+    fn throw(
+        self,
+        exc_type: PyObjectRef,
+        exc_val: OptionalArg,
+        _exc_tb: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult {
+        let exc = objgenerator::make_exception(exc_type, exc_val.into_option(), vm)?;
+        self.abandon(vm)?;
+        Err(exc)
+    }
+
+    fn close(self, vm: &VirtualMachine) -> PyResult<()> {
+        self.abandon(vm)
+    }
+}
+
+fn running_loop(vm: &VirtualMachine) -> PyResult<Option<PyEventLoopRef>> {
+    Ok(module_attr(vm, "_running_loop")?.downcast().ok())
+}
+
+fn get_running_loop(vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+    running_loop(vm)?.ok_or_else(|| vm.new_runtime_error("no running event loop".to_string()))
+}
+
+fn get_event_loop(vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+    if let Some(loop_) = running_loop(vm)? {
+        return Ok(loop_);
+    }
+    match module_attr(vm, "_event_loop")?.downcast() {
+        Ok(loop_) => Ok(loop_),
+        Err(_) => {
+            let loop_ = PyEventLoop::new().into_ref(vm);
+            set_module_attr(vm, "_event_loop", loop_.clone().into_object())?;
+            Ok(loop_)
+        }
+    }
+}
+
+/// Wrap coroutines and other awaitables in tasks; futures are returned as
+/// they are.
+fn ensure_future(
+    vm: &VirtualMachine,
+    obj: PyObjectRef,
+    loop_: Option<&PyEventLoopRef>,
+) -> PyResult<PyFutureRef> {
+    let obj = match obj.downcast::<PyFuture>() {
+        Ok(future) => return Ok(future),
+        Err(obj) => obj,
+    };
+    let loop_ = match loop_ {
+        Some(loop_) => loop_.clone(),
+        None => get_event_loop(vm)?,
+    };
+    if is_coroutine(&obj) {
+        new_task(vm, &loop_, obj, None)
+    } else if objtype::class_has_attr(&obj.class(), "__await__") {
+        let iter = objcoroutine::get_awaitable_iter(vm, &obj)?;
+        new_task(vm, &loop_, iter, None)
+    } else {
+        Err(vm.new_type_error(
+            "An asyncio.Future, a coroutine or an awaitable is required".to_string(),
+        ))
+    }
+}
+
+fn gather_futures(
+    vm: &VirtualMachine,
+    loop_: &PyEventLoopRef,
+    children: Vec<PyFutureRef>,
+    return_exceptions: bool,
+) -> PyResult<PyFutureRef> {
+    let kind = FutureKind::Gathering {
+        children: children.clone(),
+        cancel_requested: Cell::new(false),
+    };
+    let outer = PyFuture::new(loop_.clone(), kind).into_ref(vm);
+    if children.is_empty() {
+        outer.finish(Some(vm.ctx.new_list(vec![])), None, vm)?;
+        return Ok(outer);
+    }
+    let remaining = Rc::new(Cell::new(children.len()));
+    for child in &children {
+        let on_done = {
+            let outer = outer.clone();
+            let remaining = remaining.clone();
+            move |child: PyFutureRef, vm: &VirtualMachine| {
+                gather_child_done(&outer, &child, &remaining, return_exceptions, vm)
+            }
+        };
+        child.add_callback(vm.ctx.new_rustfunc(on_done), vm);
+    }
+    Ok(outer)
+}
+
+fn gather_child_done(
+    outer: &PyFutureRef,
+    child: &PyFuture,
+    remaining: &Cell<usize>,
+    return_exceptions: bool,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    if outer.is_done() {
+        return Ok(());
+    }
+    let (children, cancel_requested) = match &outer.kind {
+        FutureKind::Gathering {
+            children,
+            cancel_requested,
+        } => (children, cancel_requested.get()),
+        _ => unreachable!("gather() creates gathering futures"),
+    };
+    if !return_exceptions {
+        if child.state.get() == FutureState::Cancelled {
+            return outer.finish(None, Some(new_cancelled_error(vm)?), vm);
+        }
+        let exception = child.exception.borrow().clone();
+        if let Some(exception) = exception {
+            return outer.finish(None, Some(exception), vm);
+        }
+    }
+    remaining.set(remaining.get() - 1);
+    if remaining.get() > 0 {
+        return Ok(());
+    }
+    if cancel_requested {
+        outer.cancel_plain(vm);
+        return Ok(());
+    }
+    let mut results = vec![];
+    for child in children {
+        let result = match child.state.get() {
+            FutureState::Cancelled => new_cancelled_error(vm)?,
+            _ => match child.exception.borrow().as_ref() {
+                Some(exception) => exception.clone(),
+                None => child
+                    .result
+                    .borrow()
+                    .clone()
+                    .unwrap_or_else(|| vm.get_none()),
+            },
+        };
+        results.push(result);
+    }
+    outer.finish(Some(vm.ctx.new_list(results)), None, vm)
+}
+
+/// Cancel the tasks left when `run()` finishes, and wait for them.
+fn cancel_all_tasks(vm: &VirtualMachine, loop_: &PyEventLoopRef) -> PyResult<()> {
+    let pending: Vec<PyFutureRef> = loop_
+        .tasks
+        .borrow()
+        .iter()
+        .filter(|task| !task.is_done())
+        .cloned()
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+    for task in &pending {
+        task.cancel_future(vm)?;
+    }
+    let gathered = gather_futures(vm, loop_, pending.clone(), true)?;
+    loop_
+        .clone()
+        .run_until_complete(gathered.into_object(), vm)?;
+    for task in pending {
+        if let Some(exception) = task.exception.borrow().as_ref() {
+            report_exception(
+                vm,
+                "unhandled exception during asyncio.run() shutdown",
+                exception,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn asyncio_run(main: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    if running_loop(vm)?.is_some() {
+        return Err(vm.new_runtime_error(
+            "asyncio.run() cannot be called from a running event loop".to_string(),
+        ));
+    }
+    if !is_coroutine(&main) {
+        return Err(vm.new_value_error(format!(
+            "a coroutine was expected, got {}",
+            vm.to_repr(&main)?.value
+        )));
+    }
+    let loop_ = PyEventLoop::new().into_ref(vm);
+    set_module_attr(vm, "_event_loop", loop_.clone().into_object())?;
+    let result = loop_.clone().run_until_complete(main, vm);
+    let cleanup = cancel_all_tasks(vm, &loop_);
+    set_module_attr(vm, "_event_loop", vm.get_none())?;
+    loop_.close(vm)?;
+    let result = result?;
+    cleanup?;
+    Ok(result)
+}
+
+fn asyncio_get_event_loop(vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+    get_event_loop(vm)
+}
+
+fn asyncio_get_running_loop(vm: &VirtualMachine) -> PyResult<PyEventLoopRef> {
+    get_running_loop(vm)
+}
+
+fn asyncio_new_event_loop(vm: &VirtualMachine) -> PyEventLoopRef {
+    PyEventLoop::new().into_ref(vm)
+}
+
+fn asyncio_set_event_loop(loop_: Option<PyEventLoopRef>, vm: &VirtualMachine) -> PyResult<()> {
+    let loop_ = loop_.map_or_else(|| vm.get_none(), PyRef::into_object);
+    set_module_attr(vm, "_event_loop", loop_)
+}
+
+fn asyncio_create_task(coro: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyFutureRef> {
+    new_task(vm, &get_running_loop(vm)?, coro, None)
+}
+
+fn asyncio_ensure_future(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyFutureRef> {
+    ensure_future(vm, obj, None)
+}
+
+fn asyncio_iscoroutine(obj: PyObjectRef, _vm: &VirtualMachine) -> bool {
+    is_coroutine(&obj)
+}
+
+fn asyncio_current_task(vm: &VirtualMachine) -> PyResult {
+    module_attr(vm, "_current_task")
+}
+
+#[derive(FromArgs)]
+struct GatherOptions {
+    #[pyarg(keyword_only, default = "false")]
+    return_exceptions: bool,
+}
+
+fn asyncio_gather(
+    awaitables: Args,
+    options: GatherOptions,
+    vm: &VirtualMachine,
+) -> PyResult<PyFutureRef> {
+    let loop_ = get_event_loop(vm)?;
+    let mut children = vec![];
+    for awaitable in awaitables {
+        children.push(ensure_future(vm, awaitable, Some(&loop_))?);
+    }
+    gather_futures(vm, &loop_, children, options.return_exceptions)
+}
+
+fn asyncio_sleep(
+    delay: PyObjectRef,
+    result: OptionalArg,
+    vm: &VirtualMachine,
+) -> PyResult<PyNativeCoroutineRef> {
+    let delay = objfloat::make_float(vm, &delay)?;
+    let result = result.into_option().unwrap_or_else(|| vm.get_none());
+    let coro = PyNativeCoroutine::waiting_for(move |vm, loop_| {
+        let future = new_future(loop_, vm);
+        let wakeup = {
+            let future = future.clone();
+            move |vm: &VirtualMachine| -> PyResult<()> {
+                if !future.is_done() {
+                    future.finish(Some(result.clone()), None, vm)?;
+                }
+                Ok(())
+            }
+        };
+        let wakeup = vm.ctx.new_rustfunc(wakeup);
+        if delay <= 0.0 {
+            loop_.schedule(wakeup, vec![], vm);
+        } else {
+            let timer = loop_.add_timer(time_module::monotonic() + delay, wakeup, vec![], vm)?;
+            let cancel_timer =
+                move |_future: PyObjectRef, _vm: &VirtualMachine| timer.cancelled.set(true);
+            future.add_callback(vm.ctx.new_rustfunc(cancel_timer), vm);
+        }
+        Ok(future)
+    });
+    Ok(coro.into_ref(vm))
+}
+
+fn asyncio_wait_for(
+    awaitable: PyObjectRef,
+    timeout: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyNativeCoroutineRef {
+    PyNativeCoroutine::waiting_for(move |vm, loop_| {
+        let inner = ensure_future(vm, awaitable, Some(loop_))?;
+        if timeout.is(&vm.get_none()) {
+            return Ok(inner);
+        }
+        let timeout = objfloat::make_float(vm, &timeout)?;
+        // Cancelling the outer future cancels the inner one as a child.
+        let kind = FutureKind::Gathering {
+            children: vec![inner.clone()],
+            cancel_requested: Cell::new(false),
+        };
+        let outer = PyFuture::new(loop_.clone(), kind).into_ref(vm);
+        let timed_out = Rc::new(Cell::new(false));
+        let on_timeout = {
+            let inner = inner.clone();
+            let timed_out = timed_out.clone();
+            move |vm: &VirtualMachine| -> PyResult<()> {
+                timed_out.set(true);
+                inner.cancel_future(vm)?;
+                Ok(())
+            }
+        };
+        let when = time_module::monotonic() + timeout;
+        let timer = loop_.add_timer(when, vm.ctx.new_rustfunc(on_timeout), vec![], vm)?;
+        let on_done = {
+            let outer = outer.clone();
+            move |inner: PyFutureRef, vm: &VirtualMachine| -> PyResult<()> {
+                timer.cancelled.set(true);
+                if outer.is_done() {
+                    Ok(())
+                } else if timed_out.get() {
+                    let timeout_error = vm.ctx.exceptions.timeout_error.clone();
+                    outer.finish(None, Some(vm.new_empty_exception(timeout_error)?), vm)
+                } else {
+                    outer.copy_state(&inner, vm)
+                }
+            }
+        };
+        inner.add_callback(vm.ctx.new_rustfunc(on_done), vm);
+        Ok(outer)
+    })
+    .into_ref(vm)
+}
+
+/// Complete the first of `waiters` which is still pending.
+fn wake_next(waiters: &RefCell<VecDeque<PyFutureRef>>, vm: &VirtualMachine) -> PyResult<()> {
+    loop {
+        let waiter = waiters.borrow_mut().pop_front();
+        match waiter {
+            Some(waiter) => {
+                if !waiter.is_done() {
+                    return waiter.finish(Some(vm.get_none()), None, vm);
+                }
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PyEvent {
+    value: Cell<bool>,
+    waiters: RefCell<VecDeque<PyFutureRef>>,
+}
+
+type PyEventRef = PyRef<PyEvent>;
+
+impl PyValue for PyEvent {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "Event")
+    }
+}
+
+impl PyEventRef {
+    fn new_event(cls: PyClassRef, vm: &VirtualMachine) -> PyResult<PyEventRef> {
+        PyEvent {
+            value: Cell::new(false),
+            waiters: RefCell::new(VecDeque::new()),
+        }
+        .into_ref_with_type(vm, cls)
+    }
+
+    fn is_set(self, _vm: &VirtualMachine) -> bool {
+        self.value.get()
+    }
+
+    fn set(self, vm: &VirtualMachine) -> PyResult<()> {
+        if self.value.get() {
+            return Ok(());
+        }
+        self.value.set(true);
+        let waiters = self.waiters.replace(VecDeque::new());
+        for waiter in waiters {
+            if !waiter.is_done() {
+                waiter.finish(Some(vm.new_bool(true)), None, vm)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(self, _vm: &VirtualMachine) {
+        self.value.set(false);
+    }
+
+    fn wait(self, vm: &VirtualMachine) -> PyNativeCoroutineRef {
+        let mut woken = false;
+        let poll = move |vm: &VirtualMachine, loop_: &PyEventLoopRef| {
+            if woken || self.value.get() {
+                return Ok(Poll::Ready(vm.new_bool(true)));
+            }
+            woken = true;
+            let waiter = new_future(loop_, vm);
+            self.waiters.borrow_mut().push_back(waiter.clone());
+            Ok(Poll::Wait(waiter))
+        };
+        PyNativeCoroutine::new(Box::new(poll)).into_ref(vm)
+    }
+}
+
+#[derive(Debug)]
+struct PyQueue {
+    maxsize: usize,
+    items: RefCell<VecDeque<PyObjectRef>>,
+    getters: RefCell<VecDeque<PyFutureRef>>,
+    putters: RefCell<VecDeque<PyFutureRef>>,
+    unfinished_tasks: Cell<usize>,
+    joiners: RefCell<VecDeque<PyFutureRef>>,
+}
+
+type PyQueueRef = PyRef<PyQueue>;
+
+impl PyValue for PyQueue {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("asyncio", "Queue")
+    }
+}
+
+impl PyQueue {
+    fn is_full(&self) -> bool {
+        self.maxsize > 0 && self.items.borrow().len() >= self.maxsize
+    }
+}
+
+#[derive(FromArgs)]
+struct QueueOptions {
+    #[pyarg(positional_or_keyword, default = "0")]
+    maxsize: isize,
+}
+
+impl PyQueueRef {
+    fn new_queue(
+        cls: PyClassRef,
+        options: QueueOptions,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyQueueRef> {
+        PyQueue {
+            maxsize: options.maxsize.max(0) as usize,
+            items: RefCell::new(VecDeque::new()),
+            getters: RefCell::new(VecDeque::new()),
+            putters: RefCell::new(VecDeque::new()),
+            unfinished_tasks: Cell::new(0),
+            joiners: RefCell::new(VecDeque::new()),
+        }
+        .into_ref_with_type(vm, cls)
+    }
+
+    fn maxsize(self, _vm: &VirtualMachine) -> usize {
+        self.maxsize
+    }
+
+    fn qsize(self, _vm: &VirtualMachine) -> usize {
+        self.items.borrow().len()
+    }
+
+    fn empty(self, _vm: &VirtualMachine) -> bool {
+        self.items.borrow().is_empty()
+    }
+
+    fn full(self, _vm: &VirtualMachine) -> bool {
+        self.is_full()
+    }
+
+    fn put_nowait(self, item: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        if self.is_full() {
+            return Err(vm.new_empty_exception(vm.class("asyncio", "QueueFull"))?);
+        }
+        self.items.borrow_mut().push_back(item);
+        self.unfinished_tasks.set(self.unfinished_tasks.get() + 1);
+        wake_next(&self.getters, vm)
+    }
+
+    fn get_nowait(self, vm: &VirtualMachine) -> PyResult {
+        let item = self.items.borrow_mut().pop_front();
+        match item {
+            Some(item) => {
+                wake_next(&self.putters, vm)?;
+                Ok(item)
+            }
+            None => Err(vm.new_empty_exception(vm.class("asyncio", "QueueEmpty"))?),
+        }
+    }
+
+    fn put(self, item: PyObjectRef, vm: &VirtualMachine) -> PyNativeCoroutineRef {
+        let poll = move |vm: &VirtualMachine, loop_: &PyEventLoopRef| {
+            if self.is_full() {
+                let putter = new_future(loop_, vm);
+                self.putters.borrow_mut().push_back(putter.clone());
+                return Ok(Poll::Wait(putter));
+            }
+            self.clone().put_nowait(item.clone(), vm)?;
+            Ok(Poll::Ready(vm.get_none()))
+        };
+        PyNativeCoroutine::new(Box::new(poll)).into_ref(vm)
+    }
+
+    fn get(self, vm: &VirtualMachine) -> PyNativeCoroutineRef {
+        let poll = move |vm: &VirtualMachine, loop_: &PyEventLoopRef| {
+            if self.items.borrow().is_empty() {
+                let getter = new_future(loop_, vm);
+                self.getters.borrow_mut().push_back(getter.clone());
+                return Ok(Poll::Wait(getter));
+            }
+            self.clone().get_nowait(vm).map(Poll::Ready)
+        };
+        PyNativeCoroutine::new(Box::new(poll)).into_ref(vm)
+    }
+
+    fn task_done(self, vm: &VirtualMachine) -> PyResult<()> {
+        let unfinished = self.unfinished_tasks.get();
+        if unfinished == 0 {
+            return Err(vm.new_value_error("task_done() called too many times".to_string()));
+        }
+        self.unfinished_tasks.set(unfinished - 1);
+        if unfinished == 1 {
+            let joiners = self.joiners.replace(VecDeque::new());
+            for joiner in joiners {
+                if !joiner.is_done() {
+                    joiner.finish(Some(vm.get_none()), None, vm)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn join(self, vm: &VirtualMachine) -> PyNativeCoroutineRef {
+        let poll = move |vm: &VirtualMachine, loop_: &PyEventLoopRef| {
+            if self.unfinished_tasks.get() == 0 {
+                return Ok(Poll::Ready(vm.get_none()));
+            }
+            let joiner = new_future(loop_, vm);
+            self.joiners.borrow_mut().push_back(joiner.clone());
+            Ok(Poll::Wait(joiner))
+        };
+        PyNativeCoroutine::new(Box::new(poll)).into_ref(vm)
+    }
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let handle_type = py_class!(ctx, "Handle", ctx.object(), {
+        "cancel" => ctx.new_rustfunc(PyHandleRef::cancel),
+        "cancelled" => ctx.new_rustfunc(PyHandleRef::cancelled),
+        "when" => ctx.new_rustfunc(PyHandleRef::when),
+        "__repr__" => ctx.new_rustfunc(PyHandleRef::repr)
+    });
+
+    let event_loop_type = py_class!(ctx, "EventLoop", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyEventLoopRef::new_loop),
+        "time" => ctx.new_rustfunc(PyEventLoopRef::time),
+        "call_soon" => ctx.new_rustfunc(PyEventLoopRef::call_soon),
+        "call_later" => ctx.new_rustfunc(PyEventLoopRef::call_later),
+        "call_at" => ctx.new_rustfunc(PyEventLoopRef::call_at),
+        "create_future" => ctx.new_rustfunc(PyEventLoopRef::create_future),
+        "create_task" => ctx.new_rustfunc(PyEventLoopRef::create_task),
+        "run_forever" => ctx.new_rustfunc(PyEventLoopRef::run_forever),
+        "run_until_complete" => ctx.new_rustfunc(PyEventLoopRef::run_until_complete),
+        "stop" => ctx.new_rustfunc(PyEventLoopRef::stop),
+        "is_running" => ctx.new_rustfunc(PyEventLoopRef::is_running),
+        "is_closed" => ctx.new_rustfunc(PyEventLoopRef::is_closed),
+        "close" => ctx.new_rustfunc(PyEventLoopRef::close),
+        "add_reader" => ctx.new_rustfunc(PyEventLoopRef::add_reader),
+        "remove_reader" => ctx.new_rustfunc(PyEventLoopRef::remove_reader),
+        "add_writer" => ctx.new_rustfunc(PyEventLoopRef::add_writer),
+        "remove_writer" => ctx.new_rustfunc(PyEventLoopRef::remove_writer),
+        "sock_recv" => ctx.new_rustfunc(PyEventLoopRef::sock_recv),
+        "sock_sendall" => ctx.new_rustfunc(PyEventLoopRef::sock_sendall),
+        "sock_accept" => ctx.new_rustfunc(PyEventLoopRef::sock_accept),
+        "sock_connect" => ctx.new_rustfunc(PyEventLoopRef::sock_connect)
+    });
+
+    let future_type = py_class!(ctx, "Future", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyFutureRef::py_new),
+        "done" => ctx.new_rustfunc(PyFutureRef::done),
+        "cancelled" => ctx.new_rustfunc(PyFutureRef::cancelled),
+        "result" => ctx.new_rustfunc(PyFutureRef::result),
+        "exception" => ctx.new_rustfunc(PyFutureRef::exception),
+        "set_result" => ctx.new_rustfunc(PyFutureRef::set_result),
+        "set_exception" => ctx.new_rustfunc(PyFutureRef::set_exception),
+        "cancel" => ctx.new_rustfunc(PyFutureRef::cancel),
+        "add_done_callback" => ctx.new_rustfunc(PyFutureRef::add_done_callback),
+        "remove_done_callback" => ctx.new_rustfunc(PyFutureRef::remove_done_callback),
+        "get_loop" => ctx.new_rustfunc(PyFutureRef::get_loop),
+        "__await__" => ctx.new_rustfunc(PyFutureRef::iter),
+        "__iter__" => ctx.new_rustfunc(PyFutureRef::iter),
+        "__repr__" => ctx.new_rustfunc(PyFutureRef::repr)
+    });
+
+    let task_type = py_class!(ctx, "Task", future_type.clone(), {
+        "__new__" => ctx.new_rustfunc(task_new),
+        "get_coro" => ctx.new_rustfunc(PyFutureRef::get_coro)
+    });
+
+    let future_iter_type = py_class!(ctx, "FutureIter", ctx.object(), {
+        "__iter__" => ctx.new_rustfunc(PyFutureIterRef::iter),
+        "__next__" => ctx.new_rustfunc(PyFutureIterRef::next),
+        "send" => ctx.new_rustfunc(PyFutureIterRef::send),
+        "throw" => ctx.new_rustfunc(PyFutureIterRef::throw),
+        "close" => ctx.new_rustfunc(PyFutureIterRef::close)
+    });
+
+    let native_coroutine_type = py_class!(ctx, "NativeCoroutine", ctx.object(), {
+        "__await__" => ctx.new_rustfunc(PyNativeCoroutineRef::await_),
+        "__iter__" => ctx.new_rustfunc(PyNativeCoroutineRef::await_),
+        "__next__" => ctx.new_rustfunc(PyNativeCoroutineRef::next),
+        "send" => ctx.new_rustfunc(PyNativeCoroutineRef::send),
+        "throw" => ctx.new_rustfunc(PyNativeCoroutineRef::throw),
+        "close" => ctx.new_rustfunc(PyNativeCoroutineRef::close)
+    });
+
+    let event_type = py_class!(ctx, "Event", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyEventRef::new_event),
+        "is_set" => ctx.new_rustfunc(PyEventRef::is_set),
+        "set" => ctx.new_rustfunc(PyEventRef::set),
+        "clear" => ctx.new_rustfunc(PyEventRef::clear),
+        "wait" => ctx.new_rustfunc(PyEventRef::wait)
+    });
+
+    let queue_type = py_class!(ctx, "Queue", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyQueueRef::new_queue),
+        "maxsize" => ctx.new_property(PyQueueRef::maxsize),
+        "qsize" => ctx.new_rustfunc(PyQueueRef::qsize),
+        "empty" => ctx.new_rustfunc(PyQueueRef::empty),
+        "full" => ctx.new_rustfunc(PyQueueRef::full),
+        "put_nowait" => ctx.new_rustfunc(PyQueueRef::put_nowait),
+        "get_nowait" => ctx.new_rustfunc(PyQueueRef::get_nowait),
+        "put" => ctx.new_rustfunc(PyQueueRef::put),
+        "get" => ctx.new_rustfunc(PyQueueRef::get),
+        "task_done" => ctx.new_rustfunc(PyQueueRef::task_done),
+        "join" => ctx.new_rustfunc(PyQueueRef::join)
+    });
+
+    let exceptions = &ctx.exceptions;
+    let cancelled_error = ctx.new_class("CancelledError", exceptions.base_exception_type.clone());
+    let invalid_state_error = ctx.new_class("InvalidStateError", exceptions.exception_type.clone());
+    let queue_empty = ctx.new_class("QueueEmpty", exceptions.exception_type.clone());
+    let queue_full = ctx.new_class("QueueFull", exceptions.exception_type.clone());
+
+    py_module!(ctx, "asyncio", {
+        "run" => ctx.new_rustfunc(asyncio_run),
+        "get_event_loop" => ctx.new_rustfunc(asyncio_get_event_loop),
+        "get_running_loop" => ctx.new_rustfunc(asyncio_get_running_loop),
+        "new_event_loop" => ctx.new_rustfunc(asyncio_new_event_loop),
+        "set_event_loop" => ctx.new_rustfunc(asyncio_set_event_loop),
+        "create_task" => ctx.new_rustfunc(asyncio_create_task),
+        "ensure_future" => ctx.new_rustfunc(asyncio_ensure_future),
+        "iscoroutine" => ctx.new_rustfunc(asyncio_iscoroutine),
+        "current_task" => ctx.new_rustfunc(asyncio_current_task),
+        "gather" => ctx.new_rustfunc(asyncio_gather),
+        "sleep" => ctx.new_rustfunc(asyncio_sleep),
+        "wait_for" => ctx.new_rustfunc(asyncio_wait_for),
+        "Handle" => handle_type.clone(),
+        "TimerHandle" => handle_type,
+        "EventLoop" => event_loop_type,
+        "Future" => future_type,
+        "Task" => task_type,
+        "FutureIter" => future_iter_type,
+        "NativeCoroutine" => native_coroutine_type,
+        "Event" => event_type,
+        "Queue" => queue_type,
+        "CancelledError" => cancelled_error,
+        "InvalidStateError" => invalid_state_error,
+        "TimeoutError" => exceptions.timeout_error.clone(),
+        "QueueEmpty" => queue_empty,
+        "QueueFull" => queue_full,
+        "_event_loop" => ctx.none(),
+        "_running_loop" => ctx.none(),
+        "_current_task" => ctx.none()
+    })
+}
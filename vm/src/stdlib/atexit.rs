@@ -0,0 +1,44 @@
+/*
+ * The python atexit module, for running functions when the interpreter exits.
+ */
+
+use crate::function::PyFuncArgs;
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyResult};
+use crate::vm::VirtualMachine;
+
+fn atexit_register(vm: &VirtualMachine, mut args: PyFuncArgs) -> PyResult {
+    if args.args.is_empty() {
+        return Err(vm.new_type_error("register() takes at least 1 argument (0 given)".to_string()));
+    }
+    let func = args.shift();
+    vm.atexit_funcs.borrow_mut().push((func.clone(), args));
+    Ok(func)
+}
+
+fn atexit_unregister(func: PyObjectRef, vm: &VirtualMachine) {
+    vm.atexit_funcs
+        .borrow_mut()
+        .retain(|(registered, _)| !registered.is(&func));
+}
+
+fn atexit_run_exitfuncs(vm: &VirtualMachine) {
+    vm.run_atexit_funcs();
+}
+
+fn atexit_clear(vm: &VirtualMachine) {
+    vm.atexit_funcs.borrow_mut().clear();
+}
+
+fn atexit_ncallbacks(vm: &VirtualMachine) -> usize {
+    vm.atexit_funcs.borrow().len()
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    py_module!(ctx, "atexit", {
+        "register" => ctx.new_rustfunc(atexit_register),
+        "unregister" => ctx.new_rustfunc(atexit_unregister),
+        "_run_exitfuncs" => ctx.new_rustfunc(atexit_run_exitfuncs),
+        "_clear" => ctx.new_rustfunc(atexit_clear),
+        "_ncallbacks" => ctx.new_rustfunc(atexit_ncallbacks),
+    })
+}
@@ -0,0 +1,599 @@
+/*
+ * The codecs module: the codec registry, the built in codecs and
+ * incremental encoders and decoders.
+ */
+
+use std::cell::RefCell;
+
+use crate::encodings::{self, backslash_escape, ByteOrder, CodecError, Encoding, ErrorHandler};
+use crate::function::OptionalArg;
+use crate::obj::objbyteinner::bytes_like;
+use crate::obj::objbytes::PyBytes;
+use crate::obj::objstr::{PyString, PyStringRef};
+use crate::obj::objtuple::PyTuple;
+use crate::obj::objtype::PyClassRef;
+use crate::pyobject::{
+    IdProtocol, PyContext, PyObject, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
+};
+use crate::vm::VirtualMachine;
+
+fn error_handler(errors: &str) -> ErrorHandler {
+    ErrorHandler::lookup(errors).unwrap_or(ErrorHandler::Strict)
+}
+
+fn unknown_error_handler(errors: &str, vm: &VirtualMachine) -> PyObjectRef {
+    vm.new_lookup_error(format!("unknown error handler name '{}'", errors))
+}
+
+fn set_error_attributes(
+    exception: &PyObjectRef,
+    err: &CodecError,
+    object: PyObjectRef,
+    vm: &VirtualMachine,
+) {
+    vm.ctx
+        .set_attr(exception, "encoding", vm.new_str(err.encoding.to_string()));
+    vm.ctx.set_attr(exception, "object", object);
+    vm.ctx.set_attr(exception, "start", vm.new_int(err.start));
+    vm.ctx.set_attr(exception, "end", vm.new_int(err.end));
+    vm.ctx
+        .set_attr(exception, "reason", vm.new_str(err.reason.to_string()));
+}
+
+fn encode_error(err: CodecError, errors: &str, text: &str, vm: &VirtualMachine) -> PyObjectRef {
+    if ErrorHandler::lookup(errors).is_none() {
+        return unknown_error_handler(errors, vm);
+    }
+    let position = if err.end - err.start == 1 {
+        let c = text.chars().nth(err.start).unwrap();
+        let c = if c.is_ascii() && !c.is_ascii_control() {
+            c.to_string()
+        } else {
+            backslash_escape(c)
+        };
+        format!("character '{}' in position {}", c, err.start)
+    } else {
+        format!("characters in position {}-{}", err.start, err.end - 1)
+    };
+    let message = format!(
+        "'{}' codec can't encode {}: {}",
+        err.encoding, position, err.reason
+    );
+    let exception = vm.new_exception(vm.ctx.exceptions.unicode_encode_error.clone(), message);
+    set_error_attributes(&exception, &err, vm.new_str(text.to_string()), vm);
+    exception
+}
+
+fn decode_error(err: CodecError, errors: &str, data: &[u8], vm: &VirtualMachine) -> PyObjectRef {
+    match ErrorHandler::lookup(errors) {
+        None => return unknown_error_handler(errors, vm),
+        Some(ErrorHandler::XmlCharRefReplace) => {
+            return vm.new_type_error(
+                "don't know how to handle UnicodeDecodeError in error callback".to_string(),
+            );
+        }
+        Some(_) => {}
+    }
+    let position = if err.end - err.start == 1 {
+        format!("byte 0x{:02x} in position {}", data[err.start], err.start)
+    } else {
+        format!("bytes in position {}-{}", err.start, err.end - 1)
+    };
+    let message = format!(
+        "'{}' codec can't decode {}: {}",
+        err.encoding, position, err.reason
+    );
+    let exception = vm.new_exception(vm.ctx.exceptions.unicode_decode_error.clone(), message);
+    set_error_attributes(&exception, &err, vm.ctx.new_bytes(data.to_vec()), vm);
+    exception
+}
+
+/// Encode `text`, as `str.encode` does.
+pub fn encode_text(
+    text: &str,
+    encoding: &str,
+    errors: &str,
+    vm: &VirtualMachine,
+) -> PyResult<Vec<u8>> {
+    if let Some(builtin) = Encoding::lookup(encoding) {
+        return encodings::encode(builtin, text, error_handler(errors))
+            .map_err(|err| encode_error(err, errors, text, vm));
+    }
+    let encoder = vm.get_attribute(lookup(encoding, vm)?, "encode")?;
+    let result = vm.invoke(
+        encoder,
+        vec![vm.new_str(text.to_string()), vm.new_str(errors.to_string())],
+    )?;
+    let encoded = codec_result(&result, vm)?;
+    match encoded.payload::<PyBytes>() {
+        Some(bytes) => Ok(bytes.to_vec()),
+        None => Err(vm.new_type_error(format!(
+            "'{}' encoder returned '{}' instead of 'bytes'; use codecs.encode() to encode to arbitrary types",
+            encoding,
+            encoded.class().name
+        ))),
+    }
+}
+
+/// Decode `data`, as `bytes.decode` does.
+pub fn decode_bytes(
+    data: &[u8],
+    encoding: &str,
+    errors: &str,
+    vm: &VirtualMachine,
+) -> PyResult<String> {
+    if let Some(builtin) = Encoding::lookup(encoding) {
+        return encodings::decode(builtin, data, error_handler(errors))
+            .map_err(|err| decode_error(err, errors, data, vm));
+    }
+    let decoder = vm.get_attribute(lookup(encoding, vm)?, "decode")?;
+    let result = vm.invoke(
+        decoder,
+        vec![
+            vm.ctx.new_bytes(data.to_vec()),
+            vm.new_str(errors.to_string()),
+        ],
+    )?;
+    let decoded = codec_result(&result, vm)?;
+    match decoded.payload::<PyString>() {
+        Some(string) => Ok(string.value.clone()),
+        None => Err(vm.new_type_error(format!(
+            "'{}' decoder returned '{}' instead of 'str'; use codecs.decode() to decode to arbitrary types",
+            encoding,
+            decoded.class().name
+        ))),
+    }
+}
+
+/// Get the output from the `(output, length consumed)` tuple a codec
+/// function returns.
+fn codec_result(result: &PyObjectRef, vm: &VirtualMachine) -> PyResult {
+    match result.payload::<PyTuple>() {
+        Some(tuple) if tuple.elements.borrow().len() == 2 => Ok(tuple.elements.borrow()[0].clone()),
+        _ => Err(vm.new_type_error("codec must return a tuple (object, integer)".to_string())),
+    }
+}
+
+/// Find the `CodecInfo` for `encoding`, trying the built in codecs before
+/// the registered search functions.
+pub fn lookup(encoding: &str, vm: &VirtualMachine) -> PyResult {
+    if let Some(builtin) = Encoding::lookup(encoding) {
+        return Ok(builtin_codec_info(builtin, vm));
+    }
+    let normalized = vm.new_str(encoding.to_lowercase().replace(' ', "_"));
+    let search_functions = vm.codec_search_functions.borrow().clone();
+    for search_function in search_functions {
+        let result = vm.invoke(search_function, vec![normalized.clone()])?;
+        if result.is(&vm.get_none()) {
+            continue;
+        }
+        if !is_codec_info(&result) {
+            return Err(
+                vm.new_type_error("codec search functions must return 4-tuples".to_string())
+            );
+        }
+        return Ok(result);
+    }
+    Err(vm.new_lookup_error(format!("unknown encoding: {}", encoding)))
+}
+
+fn is_codec_info(obj: &PyObjectRef) -> bool {
+    match obj.payload::<PyTuple>() {
+        Some(tuple) => tuple.elements.borrow().len() == 4,
+        None => false,
+    }
+}
+
+fn codec_info_type(vm: &VirtualMachine) -> PyClassRef {
+    vm.class("codecs", "CodecInfo")
+}
+
+#[derive(FromArgs)]
+struct CodecInfoArgs {
+    encode: PyObjectRef,
+    decode: PyObjectRef,
+    #[pyarg(positional_or_keyword, default = "None")]
+    streamreader: Option<PyObjectRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    streamwriter: Option<PyObjectRef>,
+    #[pyarg(keyword_only, default = "None")]
+    incrementalencoder: Option<PyObjectRef>,
+    #[pyarg(keyword_only, default = "None")]
+    incrementaldecoder: Option<PyObjectRef>,
+    #[pyarg(keyword_only, default = "None")]
+    name: Option<PyStringRef>,
+}
+
+fn new_codec_info(cls: PyClassRef, args: CodecInfoArgs, vm: &VirtualMachine) -> PyObjectRef {
+    let optional = |value: Option<PyObjectRef>| value.unwrap_or_else(|| vm.get_none());
+    let streamreader = optional(args.streamreader);
+    let streamwriter = optional(args.streamwriter);
+    let elements = vec![
+        args.encode.clone(),
+        args.decode.clone(),
+        streamreader.clone(),
+        streamwriter.clone(),
+    ];
+    let info = PyObject::new(PyTuple::from(elements), cls, Some(vm.ctx.new_dict()));
+    let name = args.name.map_or_else(|| vm.get_none(), PyRef::into_object);
+    vm.ctx.set_attr(&info, "name", name);
+    vm.ctx.set_attr(&info, "encode", args.encode);
+    vm.ctx.set_attr(&info, "decode", args.decode);
+    vm.ctx.set_attr(&info, "streamreader", streamreader);
+    vm.ctx.set_attr(&info, "streamwriter", streamwriter);
+    vm.ctx.set_attr(
+        &info,
+        "incrementalencoder",
+        optional(args.incrementalencoder),
+    );
+    vm.ctx.set_attr(
+        &info,
+        "incrementaldecoder",
+        optional(args.incrementaldecoder),
+    );
+    info
+}
+
+#[derive(FromArgs)]
+struct ErrorsArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    errors: Option<PyStringRef>,
+}
+
+impl ErrorsArgs {
+    fn errors(&self) -> &str {
+        self.errors
+            .as_ref()
+            .map_or("strict", |errors| &errors.value)
+    }
+}
+
+fn builtin_codec_info(encoding: Encoding, vm: &VirtualMachine) -> PyObjectRef {
+    let ctx = &vm.ctx;
+    let encode = move |input: PyStringRef, args: ErrorsArgs, vm: &VirtualMachine| -> PyResult {
+        let errors = args.errors();
+        let data = encodings::encode(encoding, &input.value, error_handler(errors))
+            .map_err(|err| encode_error(err, errors, &input.value, vm))?;
+        let length = input.value.chars().count();
+        Ok(vm
+            .ctx
+            .new_tuple(vec![vm.ctx.new_bytes(data), vm.new_int(length)]))
+    };
+    let decode = move |input: PyObjectRef, args: ErrorsArgs, vm: &VirtualMachine| -> PyResult {
+        let errors = args.errors();
+        let data = bytes_like(&input, vm)?;
+        let text = encodings::decode(encoding, &data, error_handler(errors))
+            .map_err(|err| decode_error(err, errors, &data, vm))?;
+        Ok(vm
+            .ctx
+            .new_tuple(vec![vm.new_str(text), vm.new_int(data.len())]))
+    };
+    let incrementalencoder = move |args: ErrorsArgs, vm: &VirtualMachine| {
+        PyIncrementalEncoder {
+            errors: args.errors().to_string(),
+            encoder: RefCell::new(encodings::IncrementalEncoder::new(
+                encoding,
+                error_handler(args.errors()),
+            )),
+        }
+        .into_ref(vm)
+    };
+    let incrementaldecoder = move |args: ErrorsArgs, vm: &VirtualMachine| {
+        PyIncrementalDecoder {
+            errors: args.errors().to_string(),
+            decoder: RefCell::new(encodings::IncrementalDecoder::new(
+                encoding,
+                error_handler(args.errors()),
+            )),
+        }
+        .into_ref(vm)
+    };
+    let args = CodecInfoArgs {
+        encode: ctx.new_rustfunc(encode),
+        decode: ctx.new_rustfunc(decode),
+        streamreader: None,
+        streamwriter: None,
+        incrementalencoder: Some(ctx.new_rustfunc(incrementalencoder)),
+        incrementaldecoder: Some(ctx.new_rustfunc(incrementaldecoder)),
+        name: Some(
+            PyString {
+                value: encoding.name().to_string(),
+            }
+            .into_ref(vm),
+        ),
+    };
+    new_codec_info(codec_info_type(vm), args, vm)
+}
+
+#[derive(Debug)]
+struct PyIncrementalEncoder {
+    errors: String,
+    encoder: RefCell<encodings::IncrementalEncoder>,
+}
+type PyIncrementalEncoderRef = PyRef<PyIncrementalEncoder>;
+
+impl PyValue for PyIncrementalEncoder {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("codecs", "_IncrementalEncoder")
+    }
+}
+
+#[derive(FromArgs)]
+struct EncodeArgs {
+    input: PyStringRef,
+    /// Accepted for compatibility: the encoders never hold back partial
+    /// input, so there is nothing to flush at the end.
+    #[pyarg(positional_or_keyword, name = "final", default = "false")]
+    _is_final: bool,
+}
+
+impl PyIncrementalEncoderRef {
+    fn encode(self, args: EncodeArgs, vm: &VirtualMachine) -> PyResult {
+        let text = &args.input.value;
+        let data = self
+            .encoder
+            .borrow_mut()
+            .encode(text)
+            .map_err(|err| encode_error(err, &self.errors, text, vm))?;
+        Ok(vm.ctx.new_bytes(data))
+    }
+
+    fn reset(self, _vm: &VirtualMachine) {
+        self.encoder.borrow_mut().reset();
+    }
+
+    fn errors(self, _vm: &VirtualMachine) -> String {
+        self.errors.clone()
+    }
+}
+
+#[derive(Debug)]
+struct PyIncrementalDecoder {
+    errors: String,
+    decoder: RefCell<encodings::IncrementalDecoder>,
+}
+type PyIncrementalDecoderRef = PyRef<PyIncrementalDecoder>;
+
+impl PyValue for PyIncrementalDecoder {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("codecs", "_IncrementalDecoder")
+    }
+}
+
+#[derive(FromArgs)]
+struct DecodeArgs {
+    input: PyObjectRef,
+    #[pyarg(positional_or_keyword, name = "final", default = "false")]
+    is_final: bool,
+}
+
+impl PyIncrementalDecoderRef {
+    fn decode(self, args: DecodeArgs, vm: &VirtualMachine) -> PyResult<String> {
+        let data = bytes_like(&args.input, vm)?;
+        let mut decoder = self.decoder.borrow_mut();
+        let mut input = decoder.pending().to_vec();
+        input.extend_from_slice(&data);
+        decoder
+            .decode(&data, args.is_final)
+            .map_err(|err| decode_error(err, &self.errors, &input, vm))
+    }
+
+    fn reset(self, _vm: &VirtualMachine) {
+        self.decoder.borrow_mut().reset();
+    }
+
+    fn getstate(self, vm: &VirtualMachine) -> PyObjectRef {
+        let pending = self.decoder.borrow().pending().to_vec();
+        vm.ctx
+            .new_tuple(vec![vm.ctx.new_bytes(pending), vm.new_int(0)])
+    }
+
+    fn errors(self, _vm: &VirtualMachine) -> String {
+        self.errors.clone()
+    }
+}
+
+fn codecs_register(search_function: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    if !vm.is_callable(&search_function) {
+        return Err(vm.new_type_error("argument must be callable".to_string()));
+    }
+    vm.codec_search_functions.borrow_mut().push(search_function);
+    Ok(())
+}
+
+fn codecs_lookup(encoding: PyStringRef, vm: &VirtualMachine) -> PyResult {
+    lookup(&encoding.value, vm)
+}
+
+#[derive(FromArgs)]
+struct CodecArgs {
+    obj: PyObjectRef,
+    #[pyarg(positional_or_keyword, default = "None")]
+    encoding: Option<PyStringRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    errors: Option<PyStringRef>,
+}
+
+impl CodecArgs {
+    /// Run the codec function `name` of the requested codec on the object.
+    fn call(self, name: &str, vm: &VirtualMachine) -> PyResult {
+        let encoding = self.encoding.as_ref().map_or("utf-8", |s| &s.value);
+        let errors = self
+            .errors
+            .map_or_else(|| vm.new_str("strict".to_string()), PyRef::into_object);
+        let function = vm.get_attribute(lookup(encoding, vm)?, name)?;
+        let result = vm.invoke(function, vec![self.obj, errors])?;
+        codec_result(&result, vm)
+    }
+}
+
+fn codecs_encode(args: CodecArgs, vm: &VirtualMachine) -> PyResult {
+    args.call("encode", vm)
+}
+
+fn codecs_decode(args: CodecArgs, vm: &VirtualMachine) -> PyResult {
+    args.call("decode", vm)
+}
+
+fn codec_attribute(encoding: &str, name: &str, vm: &VirtualMachine) -> PyResult {
+    let info = lookup(encoding, vm)?;
+    vm.get_attribute(info, name)
+}
+
+fn codecs_getencoder(encoding: PyStringRef, vm: &VirtualMachine) -> PyResult {
+    codec_attribute(&encoding.value, "encode", vm)
+}
+
+fn codecs_getdecoder(encoding: PyStringRef, vm: &VirtualMachine) -> PyResult {
+    codec_attribute(&encoding.value, "decode", vm)
+}
+
+/// Get the incremental encoder or decoder class of `encoding`, which the
+/// codec may not have.
+fn incremental_codec(encoding: &str, name: &str, vm: &VirtualMachine) -> PyResult {
+    let codec = codec_attribute(encoding, name, vm)?;
+    if codec.is(&vm.get_none()) {
+        return Err(vm.new_lookup_error(encoding.to_string()));
+    }
+    Ok(codec)
+}
+
+/// Create an incremental encoder, as
+/// `codecs.getincrementalencoder(encoding)(errors)` does.
+pub fn incremental_encoder(encoding: &str, errors: &str, vm: &VirtualMachine) -> PyResult {
+    let encoder = incremental_codec(encoding, "incrementalencoder", vm)?;
+    vm.invoke(encoder, vec![vm.new_str(errors.to_string())])
+}
+
+/// Create an incremental decoder, as
+/// `codecs.getincrementaldecoder(encoding)(errors)` does.
+pub fn incremental_decoder(encoding: &str, errors: &str, vm: &VirtualMachine) -> PyResult {
+    let decoder = incremental_codec(encoding, "incrementaldecoder", vm)?;
+    vm.invoke(decoder, vec![vm.new_str(errors.to_string())])
+}
+
+fn codecs_getincrementalencoder(encoding: PyStringRef, vm: &VirtualMachine) -> PyResult {
+    incremental_codec(&encoding.value, "incrementalencoder", vm)
+}
+
+fn codecs_getincrementaldecoder(encoding: PyStringRef, vm: &VirtualMachine) -> PyResult {
+    incremental_codec(&encoding.value, "incrementaldecoder", vm)
+}
+
+// The base classes for incremental codecs written in python.
+
+fn incremental_init(
+    zelf: PyObjectRef,
+    errors: OptionalArg<PyStringRef>,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    let errors = match errors {
+        OptionalArg::Present(errors) => errors.into_object(),
+        OptionalArg::Missing => vm.new_str("strict".to_string()),
+    };
+    vm.set_attr(&zelf, vm.new_str("errors".to_string()), errors)?;
+    Ok(())
+}
+
+fn incremental_encoder_encode(
+    _zelf: PyObjectRef,
+    _input: PyObjectRef,
+    _final: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    Err(vm.new_not_implemented_error("encode".to_string()))
+}
+
+fn incremental_decoder_decode(
+    _zelf: PyObjectRef,
+    _input: PyObjectRef,
+    _final: OptionalArg<PyObjectRef>,
+    vm: &VirtualMachine,
+) -> PyResult {
+    Err(vm.new_not_implemented_error("decode".to_string()))
+}
+
+fn incremental_reset(_zelf: PyObjectRef, _vm: &VirtualMachine) {}
+
+fn incremental_setstate(_zelf: PyObjectRef, _state: PyObjectRef, _vm: &VirtualMachine) {}
+
+fn incremental_encoder_getstate(_zelf: PyObjectRef, _vm: &VirtualMachine) -> usize {
+    0
+}
+
+fn incremental_decoder_getstate(_zelf: PyObjectRef, vm: &VirtualMachine) -> PyObjectRef {
+    vm.ctx
+        .new_tuple(vec![vm.ctx.new_bytes(vec![]), vm.new_int(0)])
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let codec_info = py_class!(ctx, "CodecInfo", ctx.tuple_type(), {
+        "__new__" => ctx.new_rustfunc(new_codec_info),
+    });
+
+    let incremental_encoder = py_class!(ctx, "IncrementalEncoder", ctx.object(), {
+        "__init__" => ctx.new_rustfunc(incremental_init),
+        "encode" => ctx.new_rustfunc(incremental_encoder_encode),
+        "reset" => ctx.new_rustfunc(incremental_reset),
+        "getstate" => ctx.new_rustfunc(incremental_encoder_getstate),
+        "setstate" => ctx.new_rustfunc(incremental_setstate),
+    });
+    let incremental_decoder = py_class!(ctx, "IncrementalDecoder", ctx.object(), {
+        "__init__" => ctx.new_rustfunc(incremental_init),
+        "decode" => ctx.new_rustfunc(incremental_decoder_decode),
+        "reset" => ctx.new_rustfunc(incremental_reset),
+        "getstate" => ctx.new_rustfunc(incremental_decoder_getstate),
+        "setstate" => ctx.new_rustfunc(incremental_setstate),
+    });
+
+    // The incremental codecs of the built in codecs.
+    let builtin_encoder = py_class!(ctx, "IncrementalEncoder", incremental_encoder.clone(), {
+        "encode" => ctx.new_rustfunc(PyIncrementalEncoderRef::encode),
+        "reset" => ctx.new_rustfunc(PyIncrementalEncoderRef::reset),
+        "errors" => ctx.new_property(PyIncrementalEncoderRef::errors),
+    });
+    let builtin_decoder = py_class!(ctx, "IncrementalDecoder", incremental_decoder.clone(), {
+        "decode" => ctx.new_rustfunc(PyIncrementalDecoderRef::decode),
+        "reset" => ctx.new_rustfunc(PyIncrementalDecoderRef::reset),
+        "getstate" => ctx.new_rustfunc(PyIncrementalDecoderRef::getstate),
+        "errors" => ctx.new_property(PyIncrementalDecoderRef::errors),
+    });
+
+    let bom = |encoding: Encoding| {
+        ctx.new_bytes(encodings::encode(encoding, "\u{feff}", ErrorHandler::Strict).unwrap())
+    };
+    let utf16_le = bom(Encoding::Utf16(Some(ByteOrder::Little)));
+    let utf16_be = bom(Encoding::Utf16(Some(ByteOrder::Big)));
+    let utf32_le = bom(Encoding::Utf32(Some(ByteOrder::Little)));
+    let utf32_be = bom(Encoding::Utf32(Some(ByteOrder::Big)));
+    let (utf16, utf32) = match ByteOrder::native() {
+        ByteOrder::Little => (utf16_le.clone(), utf32_le.clone()),
+        ByteOrder::Big => (utf16_be.clone(), utf32_be.clone()),
+    };
+
+    py_module!(ctx, "codecs", {
+        "BOM" => utf16.clone(),
+        "BOM_BE" => utf16_be.clone(),
+        "BOM_LE" => utf16_le.clone(),
+        "BOM_UTF8" => bom(Encoding::Utf8),
+        "BOM_UTF16" => utf16,
+        "BOM_UTF16_BE" => utf16_be,
+        "BOM_UTF16_LE" => utf16_le,
+        "BOM_UTF32" => utf32,
+        "BOM_UTF32_BE" => utf32_be,
+        "BOM_UTF32_LE" => utf32_le,
+        "CodecInfo" => codec_info,
+        "IncrementalDecoder" => incremental_decoder,
+        "IncrementalEncoder" => incremental_encoder,
+        "_IncrementalDecoder" => builtin_decoder,
+        "_IncrementalEncoder" => builtin_encoder,
+        "decode" => ctx.new_rustfunc(codecs_decode),
+        "encode" => ctx.new_rustfunc(codecs_encode),
+        "getdecoder" => ctx.new_rustfunc(codecs_getdecoder),
+        "getencoder" => ctx.new_rustfunc(codecs_getencoder),
+        "getincrementaldecoder" => ctx.new_rustfunc(codecs_getincrementaldecoder),
+        "getincrementalencoder" => ctx.new_rustfunc(codecs_getincrementalencoder),
+        "lookup" => ctx.new_rustfunc(codecs_lookup),
+        "register" => ctx.new_rustfunc(codecs_register),
+    })
+}
@@ -1,25 +1,559 @@
+/*! Disassembler of code objects, to debug the compiler's output.
+
+*/
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::bytecode::{
+    BinaryOperator, CallType, CodeObject, ComparisonOperator, Constant, FunctionOpArg, Instruction,
+    Label, UnaryOperator, Varargs,
+};
+use crate::compile;
 use crate::obj::objcode::PyCodeRef;
-use crate::pyobject::{PyContext, PyObjectRef, PyResult, TryFromObject};
+use crate::obj::objstr::PyStringRef;
+use crate::obj::objtuple::{PyTuple, PyTupleRef};
+use crate::obj::objtype::{self, PyClassRef};
+use crate::pyobject::{PyContext, PyObject, PyObjectRef, PyResult, TryFromObject, TypeProtocol};
 use crate::vm::VirtualMachine;
 
-fn dis_dis(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-    // Method or function:
-    if let Ok(co) = vm.get_attribute(obj.clone(), "__code__") {
-        return dis_disassemble(co, vm);
+/// The fields of an `Instruction`, in order.
+const INSTRUCTION_FIELDS: &[&str] = &[
+    "opname",
+    "arg",
+    "argval",
+    "argrepr",
+    "offset",
+    "starts_line",
+    "is_jump_target",
+];
+
+#[derive(FromArgs)]
+struct DisOptions {
+    #[pyarg(keyword_only, default = "None")]
+    file: Option<PyObjectRef>,
+}
+
+/// The operand of an instruction: the number it is encoded with, the value
+/// this stands for, and how that value is shown.
+struct Operand {
+    arg: Option<usize>,
+    argval: PyObjectRef,
+    argrepr: String,
+}
+
+impl Operand {
+    fn none(vm: &VirtualMachine) -> Operand {
+        Operand {
+            arg: None,
+            argval: vm.get_none(),
+            argrepr: String::new(),
+        }
+    }
+
+    fn number(number: usize, vm: &VirtualMachine) -> Operand {
+        Operand::described(number, String::new(), vm)
+    }
+
+    fn described(number: usize, argrepr: String, vm: &VirtualMachine) -> Operand {
+        Operand {
+            arg: Some(number),
+            argval: vm.new_int(number),
+            argrepr,
+        }
+    }
+
+    fn name(arg: Option<usize>, name: &str, vm: &VirtualMachine) -> Operand {
+        Operand {
+            arg,
+            argval: vm.new_str(name.to_string()),
+            argrepr: name.to_string(),
+        }
+    }
+
+    fn jump(code: &CodeObject, label: Label, vm: &VirtualMachine) -> Operand {
+        let offset = code.label_map[&label];
+        Operand::described(offset, format!("to {}", offset), vm)
+    }
+}
+
+/// An instruction, with its operand resolved against the code object.
+struct DisInstruction<'a> {
+    instruction: &'a Instruction,
+    operand: Operand,
+    offset: usize,
+    starts_line: Option<usize>,
+    is_jump_target: bool,
+}
+
+fn instructions<'a>(
+    code: &'a CodeObject,
+    vm: &VirtualMachine,
+) -> PyResult<Vec<DisInstruction<'a>>> {
+    let targets: HashSet<usize> = code
+        .instructions
+        .iter()
+        .flat_map(Instruction::labels)
+        .map(|label| code.label_map[&label])
+        .collect();
+    let mut previous_line = None;
+    let mut result = vec![];
+    for (offset, instruction) in code.instructions.iter().enumerate() {
+        let line = code
+            .locations
+            .get(offset)
+            .map(|location| location.get_row());
+        let starts_line = if line != previous_line { line } else { None };
+        previous_line = line;
+        result.push(DisInstruction {
+            instruction,
+            operand: operand(code, instruction, vm)?,
+            offset,
+            starts_line,
+            is_jump_target: targets.contains(&offset),
+        });
+    }
+    Ok(result)
+}
+
+/// The name of the instruction's variant, as in `Instruction::LoadConst`.
+fn opname(instruction: &Instruction) -> String {
+    let debug = format!("{:?}", instruction);
+    debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn operand(code: &CodeObject, instruction: &Instruction, vm: &VirtualMachine) -> PyResult<Operand> {
+    use Instruction::*;
+    let cell_name = |idx: usize| {
+        let name = code.cellvars.iter().chain(&code.freevars).nth(idx);
+        Operand::name(Some(idx), name.map_or("?", String::as_str), vm)
+    };
+    Ok(match instruction {
+        Import { name, symbol } => match symbol {
+            Some(symbol) => Operand {
+                arg: None,
+                argval: vm.new_str(name.clone()),
+                argrepr: format!("{} from {}", symbol, name),
+            },
+            None => Operand::name(None, name, vm),
+        },
+        ImportStar { name }
+        | LoadName { name, .. }
+        | StoreName { name, .. }
+        | DeleteName { name, .. }
+        | LoadAttr { name }
+        | StoreAttr { name }
+        | DeleteAttr { name } => Operand::name(None, name, vm),
+        LoadFast { idx } | StoreFast { idx } | DeleteFast { idx } => {
+            let name = code.varnames.get(*idx);
+            Operand::name(Some(*idx), name.map_or("?", String::as_str), vm)
+        }
+        LoadDeref { idx } | StoreDeref { idx } | DeleteDeref { idx } | LoadClosure { idx } => {
+            cell_name(*idx)
+        }
+        LoadConst { value } => {
+            let argval = vm.ctx.unwrap_constant(value);
+            let argrepr = match value {
+                Constant::Code { code } => format!("<code object {}>", code.obj_name),
+                _ => vm.to_repr(&argval)?.value.clone(),
+            };
+            Operand {
+                arg: None,
+                argval,
+                argrepr,
+            }
+        }
+        UnaryOperation { op } => {
+            let symbol = match op {
+                UnaryOperator::Not => "not",
+                UnaryOperator::Invert => "~",
+                UnaryOperator::Minus => "-",
+                UnaryOperator::Plus => "+",
+            };
+            Operand::name(None, symbol, vm)
+        }
+        BinaryOperation { op, inplace } => {
+            let symbol = binary_symbol(op);
+            if *inplace {
+                Operand::name(None, &format!("{}=", symbol), vm)
+            } else {
+                Operand::name(None, symbol, vm)
+            }
+        }
+        CompareOperation { op } => Operand::name(None, compare_symbol(op), vm),
+        Jump { target } | JumpIf { target } | JumpIfFalse { target } | ForIter { target } => {
+            Operand::jump(code, *target, vm)
+        }
+        SetupLoop { end, .. } => Operand::jump(code, *end, vm),
+        SetupExcept { handler } | SetupFinally { handler } | SetupAsyncWith { handler } => {
+            Operand::jump(code, *handler, vm)
+        }
+        SetupWith { end } | CleanupWith { end } => Operand::jump(code, *end, vm),
+        MakeFunction { flags } => {
+            let names = [
+                (FunctionOpArg::HAS_DEFAULTS, "defaults"),
+                (FunctionOpArg::HAS_ANNOTATIONS, "annotations"),
+                (FunctionOpArg::HAS_CLOSURE, "closure"),
+            ];
+            let argrepr = names
+                .iter()
+                .filter(|(flag, _)| flags.contains(*flag))
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>()
+                .join(", ");
+            Operand::described(flags.bits() as usize, argrepr, vm)
+        }
+        CallFunction { typ } => match typ {
+            CallType::Positional(count) => Operand::number(*count, vm),
+            CallType::Keyword(count) => Operand::described(*count, "keywords".to_string(), vm),
+            CallType::Ex(has_kwargs) => {
+                let argrepr = if *has_kwargs {
+                    "unpacked, with keywords"
+                } else {
+                    "unpacked"
+                };
+                Operand::described(*has_kwargs as usize, argrepr.to_string(), vm)
+            }
+        },
+        BuildTuple { size, unpack }
+        | BuildList { size, unpack }
+        | BuildSet { size, unpack }
+        | BuildMap { size, unpack } => {
+            let argrepr = if *unpack { "unpack" } else { "" };
+            Operand::described(*size, argrepr.to_string(), vm)
+        }
+        Rotate { amount: number }
+        | Raise { argc: number }
+        | BuildString { size: number }
+        | BuildSlice { size: number }
+        | UnpackSequence { size: number }
+        | ListAppend { i: number }
+        | SetAdd { i: number }
+        | MapAdd { i: number } => Operand::number(*number, vm),
+        UnpackEx { before, after } => Operand::described(
+            before | (after << 8),
+            format!("{} before, {} after", before, after),
+            vm,
+        ),
+        FormatValue { conversion, spec } => {
+            let conversion = match conversion {
+                Some(conversion) => format!("{:?}", conversion).to_lowercase(),
+                None => String::new(),
+            };
+            let argrepr = match (conversion.is_empty(), spec.is_empty()) {
+                (true, _) => spec.clone(),
+                (false, true) => conversion,
+                (false, false) => format!("{}, {}", conversion, spec),
+            };
+            Operand {
+                arg: None,
+                argval: vm.new_str(spec.clone()),
+                argrepr,
+            }
+        }
+        _ => Operand::none(vm),
+    })
+}
+
+fn binary_symbol(op: &BinaryOperator) -> &'static str {
+    match op {
+        BinaryOperator::Power => "**",
+        BinaryOperator::Multiply => "*",
+        BinaryOperator::MatrixMultiply => "@",
+        BinaryOperator::Divide => "/",
+        BinaryOperator::FloorDivide => "//",
+        BinaryOperator::Modulo => "%",
+        BinaryOperator::Add => "+",
+        BinaryOperator::Subtract => "-",
+        BinaryOperator::Subscript => "[]",
+        BinaryOperator::Lshift => "<<",
+        BinaryOperator::Rshift => ">>",
+        BinaryOperator::And => "&",
+        BinaryOperator::Xor => "^",
+        BinaryOperator::Or => "|",
+    }
+}
+
+fn compare_symbol(op: &ComparisonOperator) -> &'static str {
+    match op {
+        ComparisonOperator::Greater => ">",
+        ComparisonOperator::GreaterOrEqual => ">=",
+        ComparisonOperator::Less => "<",
+        ComparisonOperator::LessOrEqual => "<=",
+        ComparisonOperator::Equal => "==",
+        ComparisonOperator::NotEqual => "!=",
+        ComparisonOperator::In => "in",
+        ComparisonOperator::NotIn => "not in",
+        ComparisonOperator::Is => "is",
+        ComparisonOperator::IsNot => "is not",
+    }
+}
+
+/// Disassemble `code`, with a line for each instruction, and a blank line
+/// before each new source line.
+fn disassemble(code: &CodeObject, vm: &VirtualMachine) -> PyResult<String> {
+    let mut output = String::new();
+    for instruction in instructions(code, vm)? {
+        let line = match instruction.starts_line {
+            Some(line) => {
+                if instruction.offset > 0 {
+                    output.push('\n');
+                }
+                format!("{:>4}", line)
+            }
+            None => String::new(),
+        };
+        let marker = if instruction.is_jump_target { ">>" } else { "" };
+        let arg = instruction
+            .operand
+            .arg
+            .map_or_else(String::new, |arg| arg.to_string());
+        let mut text = format!(
+            "{:>4} {:>3} {:>5} {:<20} {:>5}",
+            line,
+            marker,
+            instruction.offset,
+            opname(instruction.instruction),
+            arg
+        );
+        if !instruction.operand.argrepr.is_empty() {
+            write!(text, " ({})", instruction.operand.argrepr).unwrap();
+        }
+        output.push_str(text.trim_end());
+        output.push('\n');
     }
+    Ok(output)
+}
 
-    dis_disassemble(obj, vm)
+/// Disassemble `code` and then the code objects nested in it.
+fn disassemble_recursive(code: &CodeObject, vm: &VirtualMachine) -> PyResult<String> {
+    let mut output = disassemble(code, vm)?;
+    for constant in code.get_constants() {
+        if let Constant::Code { code } = constant {
+            write!(
+                output,
+                "\nDisassembly of <code object {}>:\n",
+                code.obj_name
+            )
+            .unwrap();
+            output.push_str(&disassemble_recursive(code, vm)?);
+        }
+    }
+    Ok(output)
 }
 
-fn dis_disassemble(co: PyObjectRef, vm: &VirtualMachine) -> PyResult {
-    let code = &PyCodeRef::try_from_object(vm, co)?.code;
-    print!("{}", code);
-    Ok(vm.get_none())
+/// The code object of a function, method, source string or code object.
+fn get_code(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult<PyCodeRef> {
+    let obj = vm.get_attribute(obj.clone(), "__func__").unwrap_or(obj);
+    let obj = vm.get_attribute(obj.clone(), "__code__").unwrap_or(obj);
+    if let Ok(source) = PyStringRef::try_from_object(vm, obj.clone()) {
+        // Like CPython, take the source for an expression if it is one:
+        let source = format!("{}\n", source.value);
+        let code = compile::compile(vm, &source, &compile::Mode::Eval, "<dis>".to_string())
+            .or_else(|_| compile::compile(vm, &source, &compile::Mode::Exec, "<dis>".to_string()))
+            .map_err(|err| vm.new_syntax_error(&err))?;
+        return Ok(code);
+    }
+    PyCodeRef::try_from_object(vm, obj.clone()).map_err(|_| {
+        vm.new_type_error(format!(
+            "don't know how to disassemble {} objects",
+            obj.class().name
+        ))
+    })
+}
+
+fn write_output(output: String, file: Option<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+    match file {
+        Some(file) => {
+            vm.call_method(&file, "write", vec![vm.new_str(output)])?;
+        }
+        None => print!("{}", output),
+    }
+    Ok(())
+}
+
+fn dis_dis(obj: PyObjectRef, options: DisOptions, vm: &VirtualMachine) -> PyResult<()> {
+    let output = if objtype::isinstance(&obj, &vm.ctx.type_type()) {
+        // Disassemble each method of a class:
+        let class = PyClassRef::try_from_object(vm, obj)?;
+        let mut methods: Vec<_> = class
+            .attributes
+            .borrow()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        methods.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut output = String::new();
+        for (name, value) in methods {
+            if let Ok(code) = vm.get_attribute(value, "__code__") {
+                let code = get_code(code, vm)?;
+                writeln!(output, "Disassembly of {}:", name).unwrap();
+                output.push_str(&disassemble_recursive(&code.code, vm)?);
+                output.push('\n');
+            }
+        }
+        output
+    } else {
+        disassemble_recursive(&get_code(obj, vm)?.code, vm)?
+    };
+    write_output(output, options.file, vm)
+}
+
+fn dis_disassemble(obj: PyObjectRef, options: DisOptions, vm: &VirtualMachine) -> PyResult<()> {
+    let code = get_code(obj, vm)?;
+    write_output(disassemble(&code.code, vm)?, options.file, vm)
+}
+
+fn code_info(code: &CodeObject, vm: &VirtualMachine) -> PyResult<String> {
+    let mut flags = vec![];
+    if let Varargs::Named(_) = code.varargs {
+        flags.push("VARARGS");
+    }
+    if let Varargs::Named(_) = code.varkeywords {
+        flags.push("VARKEYWORDS");
+    }
+    if code.is_generator {
+        flags.push("GENERATOR");
+    }
+    if code.is_coroutine {
+        flags.push("COROUTINE");
+    }
+    if code.cellvars.is_empty() && code.freevars.is_empty() {
+        flags.push("NOFREE");
+    }
+
+    let mut info = String::new();
+    writeln!(info, "Name:              {}", code.obj_name).unwrap();
+    writeln!(info, "Filename:          {}", code.source_path).unwrap();
+    writeln!(info, "Argument count:    {}", code.arg_names.len()).unwrap();
+    writeln!(info, "Kw-only arguments: {}", code.kwonlyarg_names.len()).unwrap();
+    writeln!(info, "Number of locals:  {}", code.varnames.len()).unwrap();
+    write!(info, "Flags:             {}", flags.join(", ")).unwrap();
+
+    let constants = code
+        .get_constants()
+        .map(|constant| match constant {
+            Constant::Code { code } => Ok(format!("<code object {}>", code.obj_name)),
+            _ => Ok(vm.to_repr(&vm.ctx.unwrap_constant(constant))?.value.clone()),
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let mut names: Vec<&str> = vec![];
+    for instruction in &code.instructions {
+        match instruction {
+            Instruction::Import { name, .. }
+            | Instruction::ImportStar { name }
+            | Instruction::LoadName { name, .. }
+            | Instruction::StoreName { name, .. }
+            | Instruction::DeleteName { name, .. }
+            | Instruction::LoadAttr { name }
+            | Instruction::StoreAttr { name }
+            | Instruction::DeleteAttr { name } => {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            _ => {}
+        }
+    }
+    let sections: [(&str, Vec<&str>); 5] = [
+        ("Constants", constants.iter().map(String::as_str).collect()),
+        ("Names", names),
+        (
+            "Variable names",
+            code.varnames.iter().map(String::as_str).collect(),
+        ),
+        (
+            "Cell variables",
+            code.cellvars.iter().map(String::as_str).collect(),
+        ),
+        (
+            "Free variables",
+            code.freevars.iter().map(String::as_str).collect(),
+        ),
+    ];
+    for (title, items) in sections.iter() {
+        if !items.is_empty() {
+            write!(info, "\n{}:", title).unwrap();
+            for (index, item) in items.iter().enumerate() {
+                write!(info, "\n{:>4}: {}", index, item).unwrap();
+            }
+        }
+    }
+    Ok(info)
+}
+
+fn dis_code_info(obj: PyObjectRef, vm: &VirtualMachine) -> PyResult<String> {
+    code_info(&get_code(obj, vm)?.code, vm)
+}
+
+fn dis_show_code(obj: PyObjectRef, options: DisOptions, vm: &VirtualMachine) -> PyResult<()> {
+    let mut info = code_info(&get_code(obj, vm)?.code, vm)?;
+    info.push('\n');
+    write_output(info, options.file, vm)
+}
+
+fn dis_get_instructions(
+    obj: PyObjectRef,
+    instruction_type: &PyClassRef,
+    vm: &VirtualMachine,
+) -> PyResult {
+    let code = get_code(obj, vm)?;
+    let optional = |value: Option<usize>| value.map_or_else(|| vm.get_none(), |n| vm.new_int(n));
+    let elements = instructions(&code.code, vm)?
+        .into_iter()
+        .map(|instruction| {
+            let fields = vec![
+                vm.new_str(opname(instruction.instruction)),
+                optional(instruction.operand.arg),
+                instruction.operand.argval,
+                vm.new_str(instruction.operand.argrepr),
+                vm.new_int(instruction.offset),
+                optional(instruction.starts_line),
+                vm.new_bool(instruction.is_jump_target),
+            ];
+            PyObject::new(PyTuple::from(fields), instruction_type.clone(), None)
+        })
+        .collect();
+    let list = vm.ctx.new_list(elements);
+    vm.call_method(&list, "__iter__", vec![])
+}
+
+fn make_instruction_type(ctx: &PyContext) -> PyClassRef {
+    let instruction_type = py_class!(ctx, "Instruction", ctx.tuple_type(), {
+        "__repr__" => ctx.new_rustfunc(|zelf: PyTupleRef, vm: &VirtualMachine| {
+            let mut fields = vec![];
+            for (name, value) in INSTRUCTION_FIELDS.iter().zip(zelf.elements.borrow().iter()) {
+                fields.push(format!("{}={}", name, vm.to_repr(value)?.value));
+            }
+            Ok(format!("Instruction({})", fields.join(", ")))
+        }),
+    });
+    for (index, name) in INSTRUCTION_FIELDS.iter().enumerate() {
+        let getter =
+            move |zelf: PyTupleRef, _vm: &VirtualMachine| zelf.elements.borrow()[index].clone();
+        ctx.set_attr(&instruction_type, name, ctx.new_property(getter));
+    }
+    instruction_type
 }
 
 pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let instruction_type = make_instruction_type(ctx);
+    let get_instructions = {
+        let instruction_type = instruction_type.clone();
+        move |obj: PyObjectRef, vm: &VirtualMachine| {
+            dis_get_instructions(obj, &instruction_type, vm)
+        }
+    };
     py_module!(ctx, "dis", {
+        "code_info" => ctx.new_rustfunc(dis_code_info),
         "dis" => ctx.new_rustfunc(dis_dis),
-        "disassemble" => ctx.new_rustfunc(dis_disassemble)
+        "disassemble" => ctx.new_rustfunc(dis_disassemble),
+        "get_instructions" => ctx.new_rustfunc(get_instructions),
+        "Instruction" => instruction_type.into_object(),
+        "show_code" => ctx.new_rustfunc(dis_show_code),
     })
 }
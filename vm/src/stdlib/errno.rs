@@ -0,0 +1,80 @@
+//! The python `errno` module, exposing the platform's error codes.
+
+use crate::pyobject::{PyContext, PyObjectRef};
+
+macro_rules! errno_codes {
+    ($($name:ident),* $(,)?) => {
+        &[$((stringify!($name), libc::$name)),*]
+    };
+}
+
+const ERROR_CODES: &[(&str, i32)] = errno_codes![
+    E2BIG,
+    EACCES,
+    EADDRINUSE,
+    EADDRNOTAVAIL,
+    EAFNOSUPPORT,
+    EAGAIN,
+    EALREADY,
+    EBADF,
+    EBUSY,
+    ECHILD,
+    ECONNABORTED,
+    ECONNREFUSED,
+    ECONNRESET,
+    EDEADLK,
+    EDESTADDRREQ,
+    EDOM,
+    EEXIST,
+    EFAULT,
+    EFBIG,
+    EHOSTUNREACH,
+    EINPROGRESS,
+    EINTR,
+    EINVAL,
+    EIO,
+    EISCONN,
+    EISDIR,
+    ELOOP,
+    EMFILE,
+    EMLINK,
+    EMSGSIZE,
+    ENAMETOOLONG,
+    ENETDOWN,
+    ENETRESET,
+    ENETUNREACH,
+    ENFILE,
+    ENOBUFS,
+    ENODEV,
+    ENOENT,
+    ENOEXEC,
+    ENOLCK,
+    ENOMEM,
+    ENOSPC,
+    ENOSYS,
+    ENOTCONN,
+    ENOTDIR,
+    ENOTEMPTY,
+    ENOTSOCK,
+    ENOTTY,
+    ENXIO,
+    EPERM,
+    EPIPE,
+    ERANGE,
+    EROFS,
+    ESHUTDOWN,
+    ESPIPE,
+    ESRCH,
+    ETIMEDOUT,
+    ETXTBSY,
+    EWOULDBLOCK,
+    EXDEV,
+];
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    let module = py_module!(ctx, "errno", {});
+    for (name, code) in ERROR_CODES {
+        ctx.set_attr(&module, name, ctx.new_int(*code));
+    }
+    module
+}
@@ -0,0 +1,105 @@
+/*
+ * The python gc module, to control the cycle collector.
+ */
+
+use crate::function::{Args, OptionalArg};
+use crate::gc;
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyResult};
+use crate::vm::VirtualMachine;
+
+fn gc_collect(generation: OptionalArg<i32>, vm: &VirtualMachine) -> PyResult<usize> {
+    let generation = match generation {
+        OptionalArg::Present(generation) => generation,
+        OptionalArg::Missing => gc::GENERATIONS as i32 - 1,
+    };
+    if generation < 0 || generation >= gc::GENERATIONS as i32 {
+        return Err(vm.new_value_error("invalid generation".to_string()));
+    }
+    let generation = generation as usize;
+    let mut collected = gc::collect(generation);
+    // Objects with a `__del__` method are only freed once it has run, and
+    // have moved on to the next generation by then.
+    if gc::run_finalizers(vm) {
+        collected += gc::collect((generation + 1).min(gc::GENERATIONS - 1));
+    }
+    Ok(collected)
+}
+
+fn gc_enable(_vm: &VirtualMachine) {
+    gc::set_enabled(true);
+}
+
+fn gc_disable(_vm: &VirtualMachine) {
+    gc::set_enabled(false);
+}
+
+fn gc_isenabled(_vm: &VirtualMachine) -> bool {
+    gc::is_enabled()
+}
+
+fn gc_get_count(vm: &VirtualMachine) -> PyObjectRef {
+    let counts = gc::get_count();
+    let counts = counts.iter().map(|&count| vm.new_int(count));
+    vm.ctx.new_tuple(counts.collect())
+}
+
+fn gc_get_threshold(vm: &VirtualMachine) -> PyObjectRef {
+    let thresholds = gc::get_threshold();
+    let thresholds = thresholds.iter().map(|&threshold| vm.new_int(threshold));
+    vm.ctx.new_tuple(thresholds.collect())
+}
+
+fn gc_set_threshold(
+    threshold0: usize,
+    threshold1: OptionalArg<usize>,
+    threshold2: OptionalArg<usize>,
+    _vm: &VirtualMachine,
+) {
+    let thresholds: Vec<usize> = std::iter::once(threshold0)
+        .chain(threshold1.into_option())
+        .chain(threshold2.into_option())
+        .collect();
+    gc::set_threshold(&thresholds);
+}
+
+fn gc_get_objects(vm: &VirtualMachine) -> PyObjectRef {
+    vm.ctx.new_list(gc::get_objects())
+}
+
+fn gc_get_referrers(objs: Args, vm: &VirtualMachine) -> PyObjectRef {
+    let objs: Vec<PyObjectRef> = objs.into_iter().collect();
+    let referrers = gc::get_objects()
+        .into_iter()
+        .filter(|obj| match gc::referents(obj) {
+            Some(referents) => referents
+                .iter()
+                .any(|referent| objs.iter().any(|target| referent.is(target))),
+            None => false,
+        })
+        .collect();
+    vm.ctx.new_list(referrers)
+}
+
+fn gc_get_referents(objs: Args, vm: &VirtualMachine) -> PyObjectRef {
+    let referents = objs
+        .into_iter()
+        .filter_map(|obj| gc::referents(&obj))
+        .flatten()
+        .collect();
+    vm.ctx.new_list(referents)
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    py_module!(ctx, "gc", {
+        "collect" => ctx.new_rustfunc(gc_collect),
+        "enable" => ctx.new_rustfunc(gc_enable),
+        "disable" => ctx.new_rustfunc(gc_disable),
+        "isenabled" => ctx.new_rustfunc(gc_isenabled),
+        "get_count" => ctx.new_rustfunc(gc_get_count),
+        "get_threshold" => ctx.new_rustfunc(gc_get_threshold),
+        "set_threshold" => ctx.new_rustfunc(gc_set_threshold),
+        "get_objects" => ctx.new_rustfunc(gc_get_objects),
+        "get_referrers" => ctx.new_rustfunc(gc_get_referrers),
+        "get_referents" => ctx.new_rustfunc(gc_get_referents)
+    })
+}
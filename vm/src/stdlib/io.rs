@@ -12,13 +12,14 @@ use std::path::PathBuf;
 use num_bigint::ToBigInt;
 use num_traits::ToPrimitive;
 
+use super::codecs;
 use super::os;
 use crate::function::PyFuncArgs;
 use crate::import;
 use crate::obj::objbytearray::PyByteArray;
-use crate::obj::objbytes;
+use crate::obj::objbyteinner;
 use crate::obj::objint;
-use crate::obj::objstr;
+use crate::obj::objstr::{self, PyStringRef};
 use crate::obj::objtype::PyClassRef;
 use crate::pyobject::{
     BufferProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol,
@@ -110,24 +111,25 @@ fn buffered_reader_read(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 
     //buffer method
     let mut result = vec![];
-    let mut length = buff_size;
 
     let raw = vm.get_attribute(buffered.clone(), "raw").unwrap();
 
     //Iterates through the raw class, invoking the readinto method
-    //to obtain buff_size many bytes. Exit when less than buff_size many
-    //bytes are returned (when the end of the file is reached).
-    while length == buff_size {
-        vm.call_method(&raw, "readinto", vec![buffer.clone()])
+    //to obtain up to buff_size many bytes. Exit when no bytes are
+    //read (when the end of the file is reached).
+    loop {
+        let py_len = vm
+            .call_method(&raw, "readinto", vec![buffer.clone()])
             .map_err(|_| vm.new_value_error("IO Error".to_string()))?;
+        let length = objint::get_value(&py_len).to_usize().unwrap();
+        if length == 0 {
+            break;
+        }
 
-        //Copy bytes from the buffer vector into the results vector
+        //Copy the bytes read from the buffer vector into the results vector
         if let Some(bytes) = buffer.payload::<PyByteArray>() {
-            result.extend_from_slice(&bytes.value.borrow());
+            result.extend_from_slice(&bytes.value.borrow()[..length]);
         };
-
-        let py_len = vm.call_method(&buffer, "__len__", PyFuncArgs::default())?;
-        length = objint::get_value(&py_len).to_usize().unwrap();
     }
 
     Ok(vm.ctx.new_bytes(result))
@@ -186,45 +188,35 @@ fn file_io_read(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 fn file_io_readinto(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(file_io, None), (obj, None)]);
 
-    if !obj.readonly() {
-        return Ok(vm.new_type_error(
-            "readinto() argument must be read-write bytes-like object".to_string(),
-        ));
-    }
-
-    //extract length of buffer
-    let py_length = vm.call_method(obj, "__len__", PyFuncArgs::default())?;
-    let length = objint::get_value(&py_length).to_u64().unwrap();
+    let buffer = match obj.as_buffer() {
+        Some(buffer) if !buffer.readonly() => buffer,
+        _ => {
+            return Err(vm.new_type_error(
+                "readinto() argument must be read-write bytes-like object".to_string(),
+            ));
+        }
+    };
 
     let file_no = vm.get_attribute(file_io.clone(), "fileno")?;
     let raw_fd = objint::get_value(&file_no).to_i64().unwrap();
 
     //extract unix file descriptor.
-    let handle = os::rust_file(raw_fd);
-
-    let mut f = handle.take(length);
-    if let Some(bytes) = obj.payload::<PyByteArray>() {
-        //TODO: Implement for MemoryView
+    let mut handle = os::rust_file(raw_fd);
 
-        let mut value_mut = bytes.value.borrow_mut();
-        value_mut.clear();
-        match f.read_to_end(&mut value_mut) {
-            Ok(_) => {}
-            Err(_) => return Err(vm.new_value_error("Error reading from Take".to_string())),
-        }
-    };
+    //read directly into the memory of the buffer
+    let mut result = Ok(0);
+    buffer.with_bytes_mut(vm, &mut |data| result = handle.read(data))?;
 
-    let updated = os::raw_file_number(f.into_inner());
+    let updated = os::raw_file_number(handle);
     vm.ctx.set_attr(file_io, "fileno", vm.ctx.new_int(updated));
-    Ok(vm.get_none())
+    match result {
+        Ok(length) => Ok(vm.ctx.new_int(length)),
+        Err(_) => Err(vm.new_value_error("Error reading from file".to_string())),
+    }
 }
 
 fn file_io_write(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        required = [(file_io, None), (obj, Some(vm.ctx.bytes_type()))]
-    );
+    arg_check!(vm, args, required = [(file_io, None), (obj, None)]);
 
     let file_no = vm.get_attribute(file_io.clone(), "fileno")?;
     let raw_fd = objint::get_value(&file_no).to_i64().unwrap();
@@ -234,22 +226,17 @@ fn file_io_write(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     //to support windows - i.e. raw file_handles
     let mut handle = os::rust_file(raw_fd);
 
-    match obj.payload::<PyByteArray>() {
-        Some(bytes) => {
-            let value_mut = bytes.value.borrow();
-            match handle.write(&value_mut[..]) {
-                Ok(len) => {
-                    //reset raw fd on the FileIO object
-                    let updated = os::raw_file_number(handle);
-                    vm.ctx.set_attr(file_io, "fileno", vm.ctx.new_int(updated));
-
-                    //return number of bytes written
-                    Ok(vm.ctx.new_int(len))
-                }
-                Err(_) => Err(vm.new_value_error("Error Writing Bytes to Handle".to_string())),
-            }
+    let bytes = objbyteinner::bytes_like(obj, vm)?;
+    match handle.write(&bytes) {
+        Ok(len) => {
+            //reset raw fd on the FileIO object
+            let updated = os::raw_file_number(handle);
+            vm.ctx.set_attr(file_io, "fileno", vm.ctx.new_int(updated));
+
+            //return number of bytes written
+            Ok(vm.ctx.new_int(len))
         }
-        None => Err(vm.new_value_error("Expected Bytes Object".to_string())),
+        Err(_) => Err(vm.new_value_error("Error Writing Bytes to Handle".to_string())),
     }
 }
 
@@ -266,15 +253,51 @@ fn buffered_writer_write(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     vm.call_method(&raw, "write", vec![obj.clone()])
 }
 
-fn text_io_wrapper_init(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
-    arg_check!(
-        vm,
-        args,
-        required = [(text_io_wrapper, None), (buffer, None)]
+#[derive(FromArgs)]
+struct TextIOWrapperArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    encoding: Option<PyStringRef>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    errors: Option<PyStringRef>,
+}
+
+fn text_io_wrapper_init(
+    text_io_wrapper: PyObjectRef,
+    buffer: PyObjectRef,
+    args: TextIOWrapperArgs,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    let encoding = args.encoding.as_ref().map_or("utf-8", |s| &s.value);
+    let errors = args.errors.as_ref().map_or("strict", |s| &s.value);
+    // One encoder and decoder for the life of the wrapper, so that state
+    // such as a byte order mark carries over from one call to the next.
+    let encoder = codecs::incremental_encoder(encoding, errors, vm)?;
+    let decoder = codecs::incremental_decoder(encoding, errors, vm)?;
+
+    vm.ctx.set_attr(&text_io_wrapper, "buffer", buffer);
+    vm.ctx.set_attr(
+        &text_io_wrapper,
+        "encoding",
+        vm.new_str(encoding.to_string()),
     );
+    vm.ctx
+        .set_attr(&text_io_wrapper, "errors", vm.new_str(errors.to_string()));
+    vm.ctx.set_attr(&text_io_wrapper, "_encoder", encoder);
+    vm.ctx.set_attr(&text_io_wrapper, "_decoder", decoder);
+    Ok(())
+}
 
-    vm.ctx.set_attr(text_io_wrapper, "buffer", buffer.clone());
-    Ok(vm.get_none())
+fn text_io_wrapper_write(
+    text_io_wrapper: PyObjectRef,
+    text: PyStringRef,
+    vm: &VirtualMachine,
+) -> PyResult<usize> {
+    let raw = vm.get_attribute(text_io_wrapper.clone(), "buffer")?;
+    let encoder = vm.get_attribute(text_io_wrapper.clone(), "_encoder")?;
+    let length = text.value.chars().count();
+    let bytes = vm.call_method(&encoder, "encode", vec![text.into_object()])?;
+    vm.call_method(&raw, "write", vec![bytes])?;
+    Ok(length)
 }
 
 fn text_io_base_read(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
@@ -283,11 +306,9 @@ fn text_io_base_read(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let raw = vm.get_attribute(text_io_base.clone(), "buffer").unwrap();
 
     if let Ok(bytes) = vm.call_method(&raw, "read", PyFuncArgs::default()) {
-        let value = objbytes::get_value(&bytes).to_vec();
-
-        //format bytes into string
-        let rust_string = String::from_utf8(value).unwrap();
-        Ok(vm.ctx.new_str(rust_string))
+        // Reading to the end leaves nothing for a partial character to wait on
+        let decoder = vm.get_attribute(text_io_base.clone(), "_decoder")?;
+        vm.call_method(&decoder, "decode", vec![bytes, vm.new_bool(true)])
     } else {
         Err(vm.new_value_error("Error unpacking Bytes".to_string()))
     }
@@ -301,6 +322,9 @@ pub fn io_open(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         optional = [(mode, Some(vm.ctx.str_type()))]
     );
 
+    let encoding = args.get_optional_kwarg_with_type("encoding", vm.ctx.str_type(), vm)?;
+    let errors = args.get_optional_kwarg_with_type("errors", vm.ctx.str_type(), vm)?;
+
     let module = import::import_module(vm, PathBuf::default(), "io").unwrap();
 
     //mode is optional: 'rt' is the default mode (open from reading text)
@@ -353,11 +377,27 @@ pub fn io_open(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         //TODO: updating => PyBufferedRandom
     };
 
-    if rust_mode.contains('t') {
+    if !rust_mode.contains('b') {
         //If the mode is text this buffer type is consumed on construction of
         //a TextIOWrapper which is subsequently returned.
-        vm.invoke(text_io_wrapper_class, vec![buffered.unwrap()])
+        let mut kwargs = vec![];
+        if let Some(encoding) = encoding {
+            kwargs.push(("encoding".to_string(), encoding));
+        }
+        if let Some(errors) = errors {
+            kwargs.push(("errors".to_string(), errors));
+        }
+        let args = PyFuncArgs {
+            args: vec![buffered?],
+            kwargs,
+        };
+        vm.invoke(text_io_wrapper_class, args)
     } else {
+        if encoding.is_some() {
+            return Err(
+                vm.new_value_error("binary mode doesn't take an encoding argument".to_string())
+            );
+        }
         // If the mode is binary this Buffered class is returned directly at
         // this point.
         //For Buffered class construct "raw" IO class e.g. FileIO and pass this into corresponding field
@@ -405,7 +445,8 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
 
     //TextIOBase Subclass
     let text_io_wrapper = py_class!(ctx, "TextIOWrapper", text_io_base.clone(), {
-        "__init__" => ctx.new_rustfunc(text_io_wrapper_init)
+        "__init__" => ctx.new_rustfunc(text_io_wrapper_init),
+        "write" => ctx.new_rustfunc(text_io_wrapper_write)
     });
 
     //StringIO: in-memory text
@@ -63,10 +63,16 @@ impl<'s> serde::Serialize for PyObjectSerializer<'s> {
             let elements = objsequence::get_elements(self.pyobject);
             serialize_seq_elements(serializer, &elements)
         } else if objtype::isinstance(self.pyobject, &self.vm.ctx.dict_type()) {
-            let pairs = objdict::get_elements(self.pyobject);
+            let pairs = objdict::get_key_value_pairs(self.pyobject);
             let mut map = serializer.serialize_map(Some(pairs.len()))?;
             for (key, e) in pairs.iter() {
-                map.serialize_entry(&key, &self.clone_with_object(&e.1))?;
+                // Dict keys are strs or ints, and ints are written as strings.
+                let key = if objtype::isinstance(key, &self.vm.ctx.str_type()) {
+                    objstr::get_value(key)
+                } else {
+                    objint::get_value(key).to_string()
+                };
+                map.serialize_entry(&key, &self.clone_with_object(e))?;
             }
             map.end()
         } else if self.pyobject.is(&self.vm.get_none()) {
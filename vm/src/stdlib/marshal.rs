@@ -0,0 +1,162 @@
+/*
+ * The python marshal module, to serialize code objects and the values they are made of.
+ */
+
+use crate::function::OptionalArg;
+use crate::marshal::{self, MarshalError, Reader, Writer};
+use crate::obj::objbytes::PyBytesRef;
+use crate::obj::objcode::PyCode;
+use crate::obj::{
+    objbool, objbytes, objcomplex, objdict, objfloat, objint, objsequence, objset, objstr,
+};
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyResult, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+fn write_object(writer: &mut Writer, obj: &PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+    let ctx = &vm.ctx;
+    let class = obj.class();
+    let write_elements = |writer: &mut Writer, tag, elements: &[PyObjectRef]| -> PyResult<()> {
+        writer.write_u8(tag);
+        writer.write_usize(elements.len());
+        for element in elements {
+            write_object(writer, element, vm)?;
+        }
+        Ok(())
+    };
+    // Subclasses of the builtin types can't be marshalled, like in CPython.
+    if obj.is(&ctx.none()) {
+        writer.write_u8(marshal::TAG_NONE);
+    } else if obj.is(&ctx.ellipsis()) {
+        writer.write_u8(marshal::TAG_ELLIPSIS);
+    } else if class.is(&ctx.bool_type()) {
+        writer.write_u8(if objbool::get_value(obj) {
+            marshal::TAG_TRUE
+        } else {
+            marshal::TAG_FALSE
+        });
+    } else if class.is(&ctx.int_type()) {
+        writer.write_u8(marshal::TAG_INT);
+        writer.write_int(objint::get_value(obj));
+    } else if class.is(&ctx.float_type()) {
+        writer.write_u8(marshal::TAG_FLOAT);
+        writer.write_float(objfloat::get_value(obj));
+    } else if class.is(&ctx.complex_type()) {
+        let value = objcomplex::get_value(obj);
+        writer.write_u8(marshal::TAG_COMPLEX);
+        writer.write_float(value.re);
+        writer.write_float(value.im);
+    } else if class.is(&ctx.str_type()) {
+        writer.write_u8(marshal::TAG_STR);
+        writer.write_str(&objstr::get_value(obj));
+    } else if class.is(&ctx.bytes_type()) {
+        writer.write_u8(marshal::TAG_BYTES);
+        writer.write_bytes(&objbytes::get_value(obj));
+    } else if class.is(&ctx.tuple_type()) {
+        write_elements(writer, marshal::TAG_TUPLE, &objsequence::get_elements(obj))?;
+    } else if class.is(&ctx.list_type()) {
+        write_elements(writer, marshal::TAG_LIST, &objsequence::get_elements(obj))?;
+    } else if class.is(&ctx.set_type()) || class.is(&ctx.frozenset_type()) {
+        let tag = if class.is(&ctx.set_type()) {
+            marshal::TAG_SET
+        } else {
+            marshal::TAG_FROZENSET
+        };
+        let elements: Vec<_> = objset::get_elements(obj).into_iter().map(|e| e.1).collect();
+        write_elements(writer, tag, &elements)?;
+    } else if class.is(&ctx.dict_type()) {
+        let pairs: Vec<_> = objdict::get_elements(obj).values().cloned().collect();
+        writer.write_u8(marshal::TAG_DICT);
+        writer.write_usize(pairs.len());
+        for (key, value) in pairs {
+            write_object(writer, &key, vm)?;
+            write_object(writer, &value, vm)?;
+        }
+    } else if let Some(code) = obj.payload::<PyCode>() {
+        writer.write_u8(marshal::TAG_CODE);
+        writer.write_code(&code.code);
+    } else {
+        return Err(vm.new_value_error("unmarshallable object".to_string()));
+    }
+    Ok(())
+}
+
+fn read_object(reader: &mut Reader, vm: &VirtualMachine) -> Result<PyObjectRef, PyReadError> {
+    let ctx = &vm.ctx;
+    let tag = reader.read_u8()?;
+    let read_elements = |reader: &mut Reader| {
+        let len = reader.read_usize()?;
+        (0..len)
+            .map(|_| read_object(reader, vm))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    Ok(match tag {
+        marshal::TAG_TUPLE => ctx.new_tuple(read_elements(reader)?),
+        marshal::TAG_LIST => ctx.new_list(read_elements(reader)?),
+        marshal::TAG_SET | marshal::TAG_FROZENSET => {
+            let elements = ctx.new_list(read_elements(reader)?);
+            let class = if tag == marshal::TAG_SET {
+                ctx.set_type()
+            } else {
+                ctx.frozenset_type()
+            };
+            vm.invoke(class.into_object(), vec![elements])?
+        }
+        marshal::TAG_DICT => {
+            let dict = ctx.new_dict().into_object();
+            let len = reader.read_usize()?;
+            for _ in 0..len {
+                let key = read_object(reader, vm)?;
+                let value = read_object(reader, vm)?;
+                vm.call_method(&dict, "__setitem__", vec![key, value])?;
+            }
+            dict
+        }
+        tag => ctx.unwrap_constant(&reader.read_constant_with_tag(tag)?),
+    })
+}
+
+/// Reading fails either on bad data, or on an exception from building a value.
+enum PyReadError {
+    Marshal(MarshalError),
+    Exception(PyObjectRef),
+}
+
+impl From<MarshalError> for PyReadError {
+    fn from(err: MarshalError) -> Self {
+        PyReadError::Marshal(err)
+    }
+}
+
+impl From<PyObjectRef> for PyReadError {
+    fn from(err: PyObjectRef) -> Self {
+        PyReadError::Exception(err)
+    }
+}
+
+fn marshal_dumps(value: PyObjectRef, _version: OptionalArg<i32>, vm: &VirtualMachine) -> PyResult {
+    let mut writer = Writer::new();
+    write_object(&mut writer, &value, vm)?;
+    Ok(vm.ctx.new_bytes(writer.into_bytes()))
+}
+
+fn marshal_loads(data: PyBytesRef, vm: &VirtualMachine) -> PyResult {
+    let data = objbytes::get_value(data.as_object());
+    let mut reader = Reader::new(&data);
+    // Trailing data is ignored, like in CPython.
+    read_object(&mut reader, vm).map_err(|err| match err {
+        PyReadError::Marshal(MarshalError::Eof) => vm.new_exception(
+            vm.ctx.exceptions.eof_error.clone(),
+            MarshalError::Eof.to_string(),
+        ),
+        PyReadError::Marshal(err) => vm.new_value_error(err.to_string()),
+        PyReadError::Exception(err) => err,
+    })
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    py_module!(ctx, "marshal", {
+        "dumps" => ctx.new_rustfunc(marshal_dumps),
+        "loads" => ctx.new_rustfunc(marshal_loads),
+        "version" => ctx.new_int(marshal::FORMAT_VERSION)
+    })
+}
@@ -1,7 +1,11 @@
 mod ast;
+mod atexit;
+pub mod codecs;
 mod dis;
+mod gc;
 pub(crate) mod json;
 mod keyword;
+mod marshal;
 mod math;
 mod platform;
 mod pystruct;
@@ -9,12 +13,17 @@ mod random;
 mod re;
 pub mod socket;
 mod string;
-mod time_module;
+pub mod time_module;
 mod tokenize;
+mod traceback;
 mod types;
 mod weakref;
 use std::collections::HashMap;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod asyncio;
+#[cfg(unix)]
+mod errno;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod io;
 #[cfg(not(target_arch = "wasm32"))]
@@ -30,9 +39,13 @@ pub fn get_module_inits() -> HashMap<String, StdlibInitFunc> {
         "ast".to_string(),
         Box::new(ast::make_module) as StdlibInitFunc,
     );
+    modules.insert("atexit".to_string(), Box::new(atexit::make_module));
+    modules.insert("codecs".to_string(), Box::new(codecs::make_module));
     modules.insert("dis".to_string(), Box::new(dis::make_module));
+    modules.insert("gc".to_string(), Box::new(gc::make_module));
     modules.insert("json".to_string(), Box::new(json::make_module));
     modules.insert("keyword".to_string(), Box::new(keyword::make_module));
+    modules.insert("marshal".to_string(), Box::new(marshal::make_module));
     modules.insert("math".to_string(), Box::new(math::make_module));
     modules.insert("platform".to_string(), Box::new(platform::make_module));
     modules.insert("re".to_string(), Box::new(re::make_module));
@@ -41,12 +54,21 @@ pub fn get_module_inits() -> HashMap<String, StdlibInitFunc> {
     modules.insert("struct".to_string(), Box::new(pystruct::make_module));
     modules.insert("time".to_string(), Box::new(time_module::make_module));
     modules.insert("tokenize".to_string(), Box::new(tokenize::make_module));
+    modules.insert("traceback".to_string(), Box::new(traceback::make_module));
     modules.insert("types".to_string(), Box::new(types::make_module));
     modules.insert("_weakref".to_string(), Box::new(weakref::make_module));
+    modules.insert(
+        "weakref".to_string(),
+        Box::new(weakref::make_weakref_module),
+    );
+
+    #[cfg(unix)]
+    modules.insert("errno".to_string(), Box::new(errno::make_module));
 
     // disable some modules on WASM
     #[cfg(not(target_arch = "wasm32"))]
     {
+        modules.insert("asyncio".to_string(), Box::new(asyncio::make_module));
         modules.insert("io".to_string(), Box::new(io::make_module));
         modules.insert("os".to_string(), Box::new(os::make_module));
         modules.insert("socket".to_string(), Box::new(socket::make_module));
@@ -6,7 +6,7 @@ use num_traits::cast::ToPrimitive;
 
 use crate::function::PyFuncArgs;
 use crate::obj::objint;
-use crate::obj::objstr;
+use crate::obj::objstr::{self, PyStringRef};
 use crate::pyobject::{PyContext, PyObjectRef, PyResult, TypeProtocol};
 use crate::vm::VirtualMachine;
 
@@ -78,7 +78,11 @@ pub fn os_open(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let handle = match objint::get_value(mode).to_u16().unwrap() {
         0 => OpenOptions::new().read(true).open(&fname),
         1 => OpenOptions::new().write(true).open(&fname),
-        512 => OpenOptions::new().write(true).create(true).open(&fname),
+        512 => OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&fname),
         _ => OpenOptions::new().read(true).open(&fname),
     }
     .map_err(|err| match err.kind() {
@@ -96,6 +100,20 @@ pub fn os_open(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.ctx.new_int(raw_file_number(handle)))
 }
 
+fn os_remove(path: PyStringRef, vm: &VirtualMachine) -> PyResult<()> {
+    std::fs::remove_file(&path.value).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => {
+            let exc_type = vm.ctx.exceptions.file_not_found_error.clone();
+            vm.new_exception(exc_type, format!("No such file or directory: {}", path))
+        }
+        ErrorKind::PermissionDenied => {
+            let exc_type = vm.ctx.exceptions.permission_error.clone();
+            vm.new_exception(exc_type, format!("Permission denied: {}", path))
+        }
+        _ => vm.new_os_error(err.to_string()),
+    })
+}
+
 fn os_error(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(
         vm,
@@ -118,6 +136,8 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
         "open" => ctx.new_rustfunc(os_open),
         "close" => ctx.new_rustfunc(os_close),
         "error" => ctx.new_rustfunc(os_error),
+        "remove" => ctx.new_rustfunc(os_remove),
+        "unlink" => ctx.new_rustfunc(os_remove),
         "O_RDONLY" => ctx.new_int(0),
         "O_WRONLY" => ctx.new_int(1),
         "O_RDWR" => ctx.new_int(2),
@@ -1,17 +1,17 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::io;
 use std::io::Read;
 use std::io::Write;
 use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
 use std::ops::Deref;
 
-use crate::function::PyFuncArgs;
+use crate::function::{OptionalArg, PyFuncArgs};
 use crate::obj::objbytes;
 use crate::obj::objint;
 use crate::obj::objsequence::get_elements;
 use crate::obj::objstr;
 use crate::pyobject::{
-    PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
+    BufferProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TryFromObject, TypeProtocol,
 };
 use crate::vm::VirtualMachine;
 
@@ -75,6 +75,41 @@ impl Connection {
         }
     }
 
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Connection::TcpListener(con) => con.set_nonblocking(nonblocking),
+            Connection::UdpSocket(con) => con.set_nonblocking(nonblocking),
+            Connection::TcpStream(con) => con.set_nonblocking(nonblocking),
+        }
+    }
+
+    #[cfg(unix)]
+    fn fileno(&self) -> i64 {
+        use std::os::unix::io::AsRawFd;
+        let fd = match self {
+            Connection::TcpListener(con) => con.as_raw_fd(),
+            Connection::UdpSocket(con) => con.as_raw_fd(),
+            Connection::TcpStream(con) => con.as_raw_fd(),
+        };
+        i64::from(fd)
+    }
+
+    #[cfg(windows)]
+    fn fileno(&self) -> i64 {
+        use std::os::windows::io::AsRawSocket;
+        let handle = match self {
+            Connection::TcpListener(con) => con.as_raw_socket(),
+            Connection::UdpSocket(con) => con.as_raw_socket(),
+            Connection::TcpStream(con) => con.as_raw_socket(),
+        };
+        handle as i64
+    }
+
+    #[cfg(all(not(unix), not(windows)))]
+    fn fileno(&self) -> i64 {
+        unimplemented!();
+    }
+
     fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
         match self {
             Connection::UdpSocket(con) => con.recv_from(buf),
@@ -118,6 +153,7 @@ pub struct Socket {
     address_family: AddressFamily,
     socket_kind: SocketKind,
     con: RefCell<Option<Connection>>,
+    blocking: Cell<bool>,
 }
 
 impl PyValue for Socket {
@@ -132,8 +168,32 @@ impl Socket {
             address_family,
             socket_kind,
             con: RefCell::new(None),
+            blocking: Cell::new(true),
         }
     }
+
+    /// Use `con` from now on, in the blocking mode set for this socket.
+    fn set_connection(&self, con: Connection) -> io::Result<()> {
+        if !self.blocking.get() {
+            con.set_nonblocking(true)?;
+        }
+        self.con.borrow_mut().replace(con);
+        Ok(())
+    }
+}
+
+/// Convert an I/O error into the `OSError` subclass matching its errno, so
+/// that for example a non-blocking socket which isn't ready raises
+/// `BlockingIOError`.
+fn convert_io_error(vm: &VirtualMachine, err: io::Error) -> PyObjectRef {
+    match err.raw_os_error() {
+        Some(errno) => {
+            let os_error = vm.ctx.exceptions.os_error.clone().into_object();
+            let args = vec![vm.new_int(errno), vm.new_str(err.to_string())];
+            vm.invoke(os_error, args).unwrap_or_else(|exc| exc)
+        }
+        None => vm.new_os_error(err.to_string()),
+    }
 }
 
 fn get_socket<'a>(obj: &'a PyObjectRef) -> impl Deref<Target = Socket> + 'a {
@@ -166,9 +226,8 @@ fn socket_connect(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         SocketKind::Stream => match TcpStream::connect(address_string) {
             Ok(stream) => {
                 socket
-                    .con
-                    .borrow_mut()
-                    .replace(Connection::TcpStream(stream));
+                    .set_connection(Connection::TcpStream(stream))
+                    .map_err(|err| convert_io_error(vm, err))?;
                 Ok(vm.get_none())
             }
             Err(s) => Err(vm.new_os_error(s.to_string())),
@@ -201,9 +260,8 @@ fn socket_bind(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         SocketKind::Stream => match TcpListener::bind(address_string) {
             Ok(stream) => {
                 socket
-                    .con
-                    .borrow_mut()
-                    .replace(Connection::TcpListener(stream));
+                    .set_connection(Connection::TcpListener(stream))
+                    .map_err(|err| convert_io_error(vm, err))?;
                 Ok(vm.get_none())
             }
             Err(s) => Err(vm.new_os_error(s.to_string())),
@@ -211,9 +269,8 @@ fn socket_bind(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
         SocketKind::Dgram => match UdpSocket::bind(address_string) {
             Ok(dgram) => {
                 socket
-                    .con
-                    .borrow_mut()
-                    .replace(Connection::UdpSocket(dgram));
+                    .set_connection(Connection::UdpSocket(dgram))
+                    .map_err(|err| convert_io_error(vm, err))?;
                 Ok(vm.get_none())
             }
             Err(s) => Err(vm.new_os_error(s.to_string())),
@@ -263,15 +320,16 @@ fn socket_accept(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
 
     let (tcp_stream, addr) = match ret {
         Ok((socket, addr)) => (socket, addr),
-        Err(s) => return Err(vm.new_os_error(s.to_string())),
+        Err(s) => return Err(convert_io_error(vm, s)),
     };
+    // The accepted socket is blocking, whatever the mode of the listener:
+    tcp_stream
+        .set_nonblocking(false)
+        .map_err(|err| convert_io_error(vm, err))?;
 
-    let socket = Socket {
-        address_family: socket.address_family,
-        socket_kind: socket.socket_kind,
-        con: RefCell::new(Some(Connection::TcpStream(tcp_stream))),
-    }
-    .into_ref(vm);
+    let socket = Socket::new(socket.address_family, socket.socket_kind);
+    socket.con.replace(Some(Connection::TcpStream(tcp_stream)));
+    let socket = socket.into_ref(vm);
 
     let addr_tuple = get_addr_tuple(vm, addr)?;
 
@@ -287,16 +345,54 @@ fn socket_recv(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     let socket = get_socket(zelf);
 
     let mut buffer = vec![0u8; objint::get_value(bufsize).to_usize().unwrap()];
-    match socket.con.borrow_mut().as_mut() {
-        Some(v) => match v.read_exact(&mut buffer) {
-            Ok(_) => (),
-            Err(s) => return Err(vm.new_os_error(s.to_string())),
+    let count = match socket.con.borrow_mut().as_mut() {
+        Some(v) => match v.read(&mut buffer) {
+            Ok(count) => count,
+            Err(s) => return Err(convert_io_error(vm, s)),
         },
         None => return Err(vm.new_type_error("".to_string())),
     };
+    buffer.truncate(count);
     Ok(vm.ctx.new_bytes(buffer))
 }
 
+fn socket_recv_into(
+    zelf: SocketRef,
+    buffer: PyObjectRef,
+    nbytes: OptionalArg<isize>,
+    _flags: OptionalArg<i32>,
+    vm: &VirtualMachine,
+) -> PyResult<usize> {
+    let writable = match buffer.as_buffer() {
+        Some(writable) if !writable.readonly() => writable,
+        _ => {
+            return Err(vm.new_type_error(format!(
+                "recv_into() argument 'buffer' must be read-write bytes-like object, not {}",
+                buffer.class().name
+            )));
+        }
+    };
+    let size = writable.nbytes(vm)?;
+    let nbytes = match nbytes.into_option().unwrap_or(0) {
+        0 => size,
+        nbytes if nbytes < 0 => {
+            return Err(vm.new_value_error("negative buffersize in recv_into".to_string()));
+        }
+        nbytes if nbytes as usize > size => {
+            return Err(vm.new_value_error("buffer too small for requested bytes".to_string()));
+        }
+        nbytes => nbytes as usize,
+    };
+
+    let mut con = zelf.con.borrow_mut();
+    let con = con
+        .as_mut()
+        .ok_or_else(|| vm.new_type_error("".to_string()))?;
+    let mut result = Ok(0);
+    writable.with_bytes_mut(vm, &mut |data| result = con.read(&mut data[..nbytes]))?;
+    result.map_err(|err| convert_io_error(vm, err))
+}
+
 fn socket_recvfrom(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(
         vm,
@@ -330,10 +426,28 @@ fn socket_send(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     );
     let socket = get_socket(zelf);
 
-    match socket.con.borrow_mut().as_mut() {
+    let count = match socket.con.borrow_mut().as_mut() {
         Some(v) => match v.write(&objbytes::get_value(&bytes)) {
+            Ok(count) => count,
+            Err(s) => return Err(convert_io_error(vm, s)),
+        },
+        None => return Err(vm.new_type_error("".to_string())),
+    };
+    Ok(vm.new_int(count))
+}
+
+fn socket_sendall(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
+    arg_check!(
+        vm,
+        args,
+        required = [(zelf, None), (bytes, Some(vm.ctx.bytes_type()))]
+    );
+    let socket = get_socket(zelf);
+
+    match socket.con.borrow_mut().as_mut() {
+        Some(v) => match v.write_all(&objbytes::get_value(&bytes)) {
             Ok(_) => (),
-            Err(s) => return Err(vm.new_os_error(s.to_string())),
+            Err(s) => return Err(convert_io_error(vm, s)),
         },
         None => return Err(vm.new_type_error("".to_string())),
     };
@@ -367,9 +481,8 @@ fn socket_sendto(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
                 Ok(dgram) => match dgram.send_to(&objbytes::get_value(&bytes), address_string) {
                     Ok(_) => {
                         socket
-                            .con
-                            .borrow_mut()
-                            .replace(Connection::UdpSocket(dgram));
+                            .set_connection(Connection::UdpSocket(dgram))
+                            .map_err(|err| convert_io_error(vm, err))?;
                         Ok(vm.get_none())
                     }
                     Err(s) => Err(vm.new_os_error(s.to_string())),
@@ -389,6 +502,26 @@ fn socket_close(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.get_none())
 }
 
+fn socket_setblocking(zelf: SocketRef, flag: bool, vm: &VirtualMachine) -> PyResult<()> {
+    zelf.blocking.set(flag);
+    if let Some(con) = zelf.con.borrow().as_ref() {
+        con.set_nonblocking(!flag)
+            .map_err(|err| convert_io_error(vm, err))?;
+    }
+    Ok(())
+}
+
+fn socket_getblocking(zelf: SocketRef, _vm: &VirtualMachine) -> bool {
+    zelf.blocking.get()
+}
+
+fn socket_fileno(zelf: SocketRef, _vm: &VirtualMachine) -> i64 {
+    match zelf.con.borrow().as_ref() {
+        Some(con) => con.fileno(),
+        None => -1,
+    }
+}
+
 fn socket_getsockname(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(zelf, None)]);
     let socket = get_socket(zelf);
@@ -416,12 +549,17 @@ pub fn make_module(ctx: &PyContext) -> PyObjectRef {
          "__new__" => ctx.new_rustfunc(socket_new),
          "connect" => ctx.new_rustfunc(socket_connect),
          "recv" => ctx.new_rustfunc(socket_recv),
+         "recv_into" => ctx.new_rustfunc(socket_recv_into),
          "send" => ctx.new_rustfunc(socket_send),
+         "sendall" => ctx.new_rustfunc(socket_sendall),
          "bind" => ctx.new_rustfunc(socket_bind),
          "accept" => ctx.new_rustfunc(socket_accept),
          "listen" => ctx.new_rustfunc(socket_listen),
          "close" => ctx.new_rustfunc(socket_close),
          "getsockname" => ctx.new_rustfunc(socket_getsockname),
+         "setblocking" => ctx.new_rustfunc(socket_setblocking),
+         "getblocking" => ctx.new_rustfunc(socket_getblocking),
+         "fileno" => ctx.new_rustfunc(socket_fileno),
          "sendto" => ctx.new_rustfunc(socket_sendto),
          "recvfrom" => ctx.new_rustfunc(socket_recvfrom),
     });
@@ -1,7 +1,7 @@
 //! The python `time` module.
 
 use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::function::PyFuncArgs;
 use crate::obj::objfloat;
@@ -32,8 +32,23 @@ fn time_time(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(value)
 }
 
+lazy_static! {
+    static ref CLOCK_START: Instant = Instant::now();
+}
+
+/// Seconds since an arbitrary point in time, from a clock which never goes
+/// backwards.
+pub fn monotonic() -> f64 {
+    duration_to_f64(CLOCK_START.elapsed())
+}
+
+fn time_monotonic(_vm: &VirtualMachine) -> f64 {
+    monotonic()
+}
+
 pub fn make_module(ctx: &PyContext) -> PyObjectRef {
     py_module!(ctx, "time", {
+        "monotonic" => ctx.new_rustfunc(time_monotonic),
         "sleep" => ctx.new_rustfunc(time_sleep),
         "time" => ctx.new_rustfunc(time_time)
     })
@@ -0,0 +1,199 @@
+/*
+ * The python traceback module, for formatting and printing tracebacks.
+ */
+
+use crate::exceptions;
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyResult};
+use crate::traceback::{self, PyTracebackRef};
+use crate::vm::VirtualMachine;
+
+#[derive(FromArgs)]
+struct LimitArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    limit: Option<isize>,
+}
+
+#[derive(FromArgs)]
+struct PrintTbArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    limit: Option<isize>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    file: Option<PyObjectRef>,
+}
+
+#[derive(FromArgs)]
+struct FormatArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    limit: Option<isize>,
+    #[pyarg(positional_or_keyword, default = "true")]
+    chain: bool,
+}
+
+#[derive(FromArgs)]
+struct PrintArgs {
+    #[pyarg(positional_or_keyword, default = "None")]
+    limit: Option<isize>,
+    #[pyarg(positional_or_keyword, default = "None")]
+    file: Option<PyObjectRef>,
+    #[pyarg(positional_or_keyword, default = "true")]
+    chain: bool,
+}
+
+fn traceback_arg(tb: PyObjectRef, vm: &VirtualMachine) -> PyResult<Option<PyTracebackRef>> {
+    if tb.is(&vm.get_none()) {
+        Ok(None)
+    } else {
+        tb.downcast()
+            .map(Some)
+            .map_err(|_| vm.new_type_error("expected a traceback object or None".to_string()))
+    }
+}
+
+fn new_str_list(lines: Vec<String>, vm: &VirtualMachine) -> PyObjectRef {
+    vm.ctx
+        .new_list(lines.into_iter().map(|line| vm.new_str(line)).collect())
+}
+
+/// Write `lines` to `file`, or to `sys.stderr` when no file is given. The
+/// process's standard error is used while `sys` has no `stderr`.
+fn write_lines(lines: Vec<String>, file: Option<PyObjectRef>, vm: &VirtualMachine) -> PyResult<()> {
+    let file = match file {
+        Some(file) => file,
+        None => match vm.get_attribute(vm.sys_module.clone(), "stderr") {
+            Ok(stderr) => stderr,
+            Err(_) => {
+                for line in lines {
+                    eprint!("{}", line);
+                }
+                return Ok(());
+            }
+        },
+    };
+    for line in lines {
+        vm.call_method(&file, "write", vec![vm.new_str(line)])?;
+    }
+    Ok(())
+}
+
+fn format_exception_value(
+    value: &PyObjectRef,
+    tb: Option<PyTracebackRef>,
+    limit: Option<isize>,
+    chain: bool,
+    vm: &VirtualMachine,
+) -> Vec<String> {
+    if value.is(&vm.get_none()) {
+        vec!["NoneType: None\n".to_string()]
+    } else {
+        exceptions::format_exception_with_traceback(vm, value, tb, limit, chain)
+    }
+}
+
+fn traceback_extract_tb(tb: PyObjectRef, args: LimitArgs, vm: &VirtualMachine) -> PyResult {
+    let entries = match traceback_arg(tb, vm)? {
+        Some(tb) => traceback::entries(&tb, args.limit)
+            .into_iter()
+            .map(|tb| {
+                let (filename, lineno, name) = tb.location();
+                let line = match traceback::source_line(&filename, lineno) {
+                    Some(line) => vm.new_str(line),
+                    None => vm.get_none(),
+                };
+                vm.ctx.new_tuple(vec![
+                    vm.new_str(filename),
+                    vm.ctx.new_int(lineno),
+                    vm.new_str(name),
+                    line,
+                ])
+            })
+            .collect(),
+        None => vec![],
+    };
+    Ok(vm.ctx.new_list(entries))
+}
+
+fn traceback_format_tb(tb: PyObjectRef, args: LimitArgs, vm: &VirtualMachine) -> PyResult {
+    let lines = match traceback_arg(tb, vm)? {
+        Some(tb) => traceback::format_tb(&tb, args.limit),
+        None => vec![],
+    };
+    Ok(new_str_list(lines, vm))
+}
+
+fn traceback_print_tb(tb: PyObjectRef, args: PrintTbArgs, vm: &VirtualMachine) -> PyResult<()> {
+    let lines = match traceback_arg(tb, vm)? {
+        Some(tb) => traceback::format_tb(&tb, args.limit),
+        None => vec![],
+    };
+    write_lines(lines, args.file, vm)
+}
+
+fn traceback_format_exception_only(
+    _etype: PyObjectRef,
+    value: PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyObjectRef {
+    let line = if value.is(&vm.get_none()) {
+        "NoneType: None\n".to_string()
+    } else {
+        exceptions::format_exception_only(vm, &value)
+    };
+    new_str_list(vec![line], vm)
+}
+
+fn traceback_format_exception(
+    _etype: PyObjectRef,
+    value: PyObjectRef,
+    tb: PyObjectRef,
+    args: FormatArgs,
+    vm: &VirtualMachine,
+) -> PyResult {
+    let tb = traceback_arg(tb, vm)?;
+    let lines = format_exception_value(&value, tb, args.limit, args.chain, vm);
+    Ok(new_str_list(lines, vm))
+}
+
+fn traceback_print_exception(
+    _etype: PyObjectRef,
+    value: PyObjectRef,
+    tb: PyObjectRef,
+    args: PrintArgs,
+    vm: &VirtualMachine,
+) -> PyResult<()> {
+    let tb = traceback_arg(tb, vm)?;
+    let lines = format_exception_value(&value, tb, args.limit, args.chain, vm);
+    write_lines(lines, args.file, vm)
+}
+
+/// Format the exception currently being handled.
+fn format_current_exception(limit: Option<isize>, chain: bool, vm: &VirtualMachine) -> Vec<String> {
+    match vm.current_exception() {
+        Some(exc) => {
+            let tb = exceptions::exception_traceback(vm, &exc);
+            format_exception_value(&exc, tb, limit, chain, vm)
+        }
+        None => vec!["NoneType: None\n".to_string()],
+    }
+}
+
+fn traceback_format_exc(args: FormatArgs, vm: &VirtualMachine) -> String {
+    format_current_exception(args.limit, args.chain, vm).concat()
+}
+
+fn traceback_print_exc(args: PrintArgs, vm: &VirtualMachine) -> PyResult<()> {
+    let lines = format_current_exception(args.limit, args.chain, vm);
+    write_lines(lines, args.file, vm)
+}
+
+pub fn make_module(ctx: &PyContext) -> PyObjectRef {
+    py_module!(ctx, "traceback", {
+        "extract_tb" => ctx.new_rustfunc(traceback_extract_tb),
+        "format_exc" => ctx.new_rustfunc(traceback_format_exc),
+        "format_exception" => ctx.new_rustfunc(traceback_format_exception),
+        "format_exception_only" => ctx.new_rustfunc(traceback_format_exception_only),
+        "format_tb" => ctx.new_rustfunc(traceback_format_tb),
+        "print_exc" => ctx.new_rustfunc(traceback_print_exc),
+        "print_exception" => ctx.new_rustfunc(traceback_print_exception),
+        "print_tb" => ctx.new_rustfunc(traceback_print_tb),
+    })
+}
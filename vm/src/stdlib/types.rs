@@ -25,12 +25,22 @@ fn types_new_class(
     objtype::type_new_class(vm, vm.ctx.type_type(), name, bases, dict)
 }
 
+/// Generators can be awaited as they are, so the generator function is
+/// returned unchanged.
+fn types_coroutine(func: PyObjectRef, _vm: &VirtualMachine) -> PyObjectRef {
+    func
+}
+
 pub fn make_module(ctx: &PyContext) -> PyObjectRef {
     py_module!(ctx, "types", {
         "new_class" => ctx.new_rustfunc(types_new_class),
+        "coroutine" => ctx.new_rustfunc(types_coroutine),
         "FunctionType" => ctx.function_type(),
         "LambdaType" => ctx.function_type(),
         "CodeType" => ctx.code_type(),
-        "FrameType" => ctx.frame_type()
+        "FrameType" => ctx.frame_type(),
+        "GeneratorType" => ctx.generator_type(),
+        "CoroutineType" => ctx.coroutine_type(),
+        "AsyncGeneratorType" => ctx.async_generator_type()
     })
 }
@@ -5,10 +5,826 @@
 //! - [rust weak struct](https://doc.rust-lang.org/std/rc/struct.Weak.html)
 //!
 
-use super::super::pyobject::{PyContext, PyObjectRef};
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::function::{Args, KwArgs, OptionalArg, PyFuncArgs};
+use crate::obj::objbool;
+use crate::obj::objint;
+use crate::obj::objiter;
+use crate::obj::objlist::PyList;
+use crate::obj::objproperty::PropertyBuilder;
+use crate::obj::objtype::PyClassRef;
+use crate::obj::objweakref::{PyWeak, PyWeakRef};
+use crate::pyobject::{IdProtocol, PyContext, PyObjectRef, PyRef, PyResult, PyValue, TypeProtocol};
+use crate::vm::VirtualMachine;
+
+fn module_attr(vm: &VirtualMachine, name: &str) -> PyResult {
+    let module = vm.import("weakref")?;
+    vm.get_attribute(module, name)
+}
+
+fn set_module_attr(vm: &VirtualMachine, name: &str, value: PyObjectRef) -> PyResult<()> {
+    let module = vm.import("weakref")?;
+    vm.ctx.set_attr(&module, name, value);
+    Ok(())
+}
+
+fn hash(vm: &VirtualMachine, obj: &PyObjectRef) -> PyResult<u64> {
+    let hash = vm.call_method(obj, "__hash__", vec![])?;
+    let mut hasher = DefaultHasher::new();
+    objint::get_value(&hash).hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// A container holding weak references, which removes their entries once
+/// the referents are freed.
+trait WeakContainer: PyValue {
+    /// Remove the entry for `hash`, if `weakref` is still what refers to it.
+    fn remove_dead(&self, hash: u64, weakref: &PyObjectRef);
+}
+
+/// A weak reference to `obj` in `container`, stored under `hash`.
+fn weak_entry<T: WeakContainer>(
+    container: &PyRef<T>,
+    hash: u64,
+    obj: &PyObjectRef,
+    vm: &VirtualMachine,
+) -> PyResult<PyWeakRef> {
+    // The callback must not keep the container alive.
+    let container = Rc::downgrade(container.as_object());
+    let callback = vm
+        .ctx
+        .new_rustfunc(move |weakref: PyObjectRef, _vm: &VirtualMachine| {
+            if let Some(container) = container.upgrade() {
+                if let Some(container) = container.payload::<T>() {
+                    container.remove_dead(hash, &weakref);
+                }
+            }
+        });
+    PyWeak::with_callback(obj, callback, vm)
+}
+
+fn iter_list(vm: &VirtualMachine, elements: Vec<PyObjectRef>) -> PyResult {
+    objiter::get_iter(vm, &vm.ctx.new_list(elements))
+}
+
+/// The key and value pairs of a mapping or an iterable of pairs.
+fn extract_pairs(
+    vm: &VirtualMachine,
+    other: &PyObjectRef,
+) -> PyResult<Vec<(PyObjectRef, PyObjectRef)>> {
+    let items = if vm.get_method(other.clone(), "items").is_ok() {
+        vm.call_method(other, "items", vec![])?
+    } else {
+        other.clone()
+    };
+    vm.extract_elements(&items)?
+        .iter()
+        .map(|item| match vm.extract_elements(item)?.as_slice() {
+            [key, value] => Ok((key.clone(), value.clone())),
+            _ => Err(vm.new_value_error("expected a sequence of pairs".to_string())),
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct FinalizeInfo {
+    weakref: PyWeakRef,
+    func: PyObjectRef,
+    args: PyFuncArgs,
+}
+
+/// Calls a function once an object is freed, or at exit.
+#[derive(Debug)]
+struct PyFinalize {
+    info: RefCell<Option<FinalizeInfo>>,
+    atexit: Cell<bool>,
+}
+
+type PyFinalizeRef = PyRef<PyFinalize>;
+
+impl PyValue for PyFinalize {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("weakref", "finalize")
+    }
+}
+
+fn finalize_registry(vm: &VirtualMachine) -> PyResult<PyRef<PyList>> {
+    module_attr(vm, "_finalize_registry")?
+        .downcast()
+        .map_err(|_| vm.new_type_error("the finalize registry must be a list".to_string()))
+}
+
+/// Call the finalizers still alive which should be called at exit, most
+/// recently created first.
+fn finalize_exitfunc(vm: &VirtualMachine) -> PyResult<()> {
+    let finalizers: Vec<PyObjectRef> = finalize_registry(vm)?.elements.borrow().clone();
+    for finalizer in finalizers.into_iter().rev() {
+        if let Ok(finalizer) = finalizer.downcast::<PyFinalize>() {
+            if finalizer.atexit.get() {
+                finalizer.call(OptionalArg::Missing, vm)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl PyFinalizeRef {
+    fn new_finalize(
+        cls: PyClassRef,
+        obj: PyObjectRef,
+        func: PyObjectRef,
+        args: Args,
+        kwargs: KwArgs,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyFinalizeRef> {
+        let finalizer = PyFinalize {
+            info: RefCell::new(None),
+            atexit: Cell::new(true),
+        }
+        .into_ref_with_type(vm, cls)?;
+        let weakref = PyWeak::with_callback(&obj, finalizer.as_object().clone(), vm)?;
+        finalizer.info.replace(Some(FinalizeInfo {
+            weakref,
+            func,
+            args: PyFuncArgs::from((&args, &kwargs)),
+        }));
+
+        // The registry keeps the finalizers alive until they are called.
+        finalize_registry(vm)?
+            .elements
+            .borrow_mut()
+            .push(finalizer.as_object().clone());
+        if !objbool::boolval(vm, module_attr(vm, "_registered_with_atexit")?)? {
+            let exitfunc = vm.ctx.new_rustfunc(finalize_exitfunc);
+            vm.atexit_funcs
+                .borrow_mut()
+                .push((exitfunc, PyFuncArgs::default()));
+            set_module_attr(vm, "_registered_with_atexit", vm.new_bool(true))?;
+        }
+        Ok(finalizer)
+    }
+
+    fn unregister(&self, vm: &VirtualMachine) -> PyResult<()> {
+        finalize_registry(vm)?
+            .elements
+            .borrow_mut()
+            .retain(|finalizer| !finalizer.is(self));
+        Ok(())
+    }
+
+    /// The object and the call, as long as both are still alive.
+    fn info_tuple(&self, vm: &VirtualMachine) -> Option<PyObjectRef> {
+        let info = self.info.borrow();
+        let info = info.as_ref()?;
+        let obj = info.weakref.upgrade()?;
+        let kwargs = vm.ctx.new_dict();
+        for (name, value) in &info.args.kwargs {
+            vm.ctx.set_item(kwargs.as_object(), name, value.clone());
+        }
+        Some(vm.ctx.new_tuple(vec![
+            obj,
+            info.func.clone(),
+            vm.ctx.new_tuple(info.args.args.clone()),
+            kwargs.into_object(),
+        ]))
+    }
+
+    fn call(self, _weakref: OptionalArg, vm: &VirtualMachine) -> PyResult {
+        let info = self.info.borrow_mut().take();
+        match info {
+            Some(info) => {
+                self.unregister(vm)?;
+                vm.invoke(info.func, info.args)
+            }
+            None => Ok(vm.get_none()),
+        }
+    }
+
+    fn detach(self, vm: &VirtualMachine) -> PyResult {
+        match self.info_tuple(vm) {
+            Some(info) => {
+                self.info.replace(None);
+                self.unregister(vm)?;
+                Ok(info)
+            }
+            None => Ok(vm.get_none()),
+        }
+    }
+
+    fn peek(self, vm: &VirtualMachine) -> PyObjectRef {
+        self.info_tuple(vm).unwrap_or_else(|| vm.get_none())
+    }
+
+    fn alive(self, _vm: &VirtualMachine) -> bool {
+        self.info.borrow().is_some()
+    }
+
+    fn atexit(self, _vm: &VirtualMachine) -> bool {
+        self.atexit.get()
+    }
+
+    fn set_atexit(self, value: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        self.atexit.set(objbool::boolval(vm, value)?);
+        Ok(vm.get_none())
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        let id = self.as_object().get_id();
+        let info = self.info.borrow();
+        match info.as_ref().and_then(|info| info.weakref.upgrade()) {
+            Some(obj) => format!(
+                "<finalize object at 0x{:x}; for '{}' at 0x{:x}>",
+                id,
+                obj.class().name,
+                obj.get_id()
+            ),
+            None => format!("<finalize object at 0x{:x}; dead>", id),
+        }
+    }
+}
+
+/// A mapping which refers to its values weakly.
+#[derive(Debug)]
+struct PyWeakValueDictionary {
+    entries: RefCell<HashMap<u64, (PyObjectRef, PyWeakRef)>>,
+}
+
+type PyWeakValueDictionaryRef = PyRef<PyWeakValueDictionary>;
+
+impl PyValue for PyWeakValueDictionary {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("weakref", "WeakValueDictionary")
+    }
+}
+
+impl WeakContainer for PyWeakValueDictionary {
+    fn remove_dead(&self, hash: u64, weakref: &PyObjectRef) {
+        let mut entries = self.entries.borrow_mut();
+        let dead = match entries.get(&hash) {
+            Some((_, value)) => value.is(weakref),
+            None => false,
+        };
+        let removed = if dead { entries.remove(&hash) } else { None };
+        drop(entries);
+        drop(removed);
+    }
+}
+
+impl PyWeakValueDictionary {
+    fn live_items(&self) -> Vec<(PyObjectRef, PyObjectRef)> {
+        self.entries
+            .borrow()
+            .values()
+            .filter_map(|(key, value)| Some((key.clone(), value.upgrade()?)))
+            .collect()
+    }
+
+    fn lookup(&self, vm: &VirtualMachine, key: &PyObjectRef) -> PyResult<Option<PyObjectRef>> {
+        let hash = hash(vm, key)?;
+        Ok(self
+            .entries
+            .borrow()
+            .get(&hash)
+            .and_then(|(_, value)| value.upgrade()))
+    }
+}
+
+impl PyWeakValueDictionaryRef {
+    fn new_dict(
+        cls: PyClassRef,
+        other: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyWeakValueDictionaryRef> {
+        let dict = PyWeakValueDictionary {
+            entries: RefCell::new(HashMap::new()),
+        }
+        .into_ref_with_type(vm, cls)?;
+        if let OptionalArg::Present(other) = other {
+            dict.clone().update(other, vm)?;
+        }
+        Ok(dict)
+    }
+
+    fn getitem(self, key: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        match self.lookup(vm, &key)? {
+            Some(value) => Ok(value),
+            None => Err(vm.new_key_error(vm.to_repr(&key)?.value.clone())),
+        }
+    }
+
+    fn setitem(self, key: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let hash = hash(vm, &key)?;
+        let weakref = weak_entry(&self, hash, &value, vm)?;
+        let replaced = self.entries.borrow_mut().insert(hash, (key, weakref));
+        drop(replaced);
+        Ok(())
+    }
+
+    fn delitem(self, key: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let hash = hash(vm, &key)?;
+        let removed = self.entries.borrow_mut().remove(&hash);
+        match removed {
+            Some((_, value)) if value.upgrade().is_some() => Ok(()),
+            _ => Err(vm.new_key_error(vm.to_repr(&key)?.value.clone())),
+        }
+    }
+
+    fn contains(self, key: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+        Ok(self.lookup(vm, &key)?.is_some())
+    }
+
+    fn len(self, _vm: &VirtualMachine) -> usize {
+        self.live_items().len()
+    }
+
+    fn iter(self, vm: &VirtualMachine) -> PyResult {
+        self.keys(vm)
+    }
+
+    fn get(self, key: PyObjectRef, default: OptionalArg, vm: &VirtualMachine) -> PyResult {
+        match self.lookup(vm, &key)? {
+            Some(value) => Ok(value),
+            None => Ok(default.into_option().unwrap_or_else(|| vm.get_none())),
+        }
+    }
+
+    fn keys(self, vm: &VirtualMachine) -> PyResult {
+        let keys = self.live_items().into_iter().map(|(key, _)| key).collect();
+        iter_list(vm, keys)
+    }
+
+    fn values(self, vm: &VirtualMachine) -> PyResult {
+        let values = self
+            .live_items()
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        iter_list(vm, values)
+    }
+
+    fn items(self, vm: &VirtualMachine) -> PyResult {
+        let items = self
+            .live_items()
+            .into_iter()
+            .map(|(key, value)| vm.ctx.new_tuple(vec![key, value]))
+            .collect();
+        iter_list(vm, items)
+    }
+
+    fn pop(self, key: PyObjectRef, default: OptionalArg, vm: &VirtualMachine) -> PyResult {
+        let hash = hash(vm, &key)?;
+        let removed = self.entries.borrow_mut().remove(&hash);
+        match (removed.and_then(|(_, value)| value.upgrade()), default) {
+            (Some(value), _) => Ok(value),
+            (None, OptionalArg::Present(default)) => Ok(default),
+            (None, OptionalArg::Missing) => Err(vm.new_key_error(vm.to_repr(&key)?.value.clone())),
+        }
+    }
+
+    fn setdefault(self, key: PyObjectRef, default: OptionalArg, vm: &VirtualMachine) -> PyResult {
+        match self.lookup(vm, &key)? {
+            Some(value) => Ok(value),
+            None => {
+                let default = default.into_option().unwrap_or_else(|| vm.get_none());
+                self.setitem(key, default.clone(), vm)?;
+                Ok(default)
+            }
+        }
+    }
+
+    fn update(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        for (key, value) in extract_pairs(vm, &other)? {
+            self.clone().setitem(key, value, vm)?;
+        }
+        Ok(())
+    }
+
+    fn clear(self, _vm: &VirtualMachine) {
+        let entries = self.entries.replace(HashMap::new());
+        drop(entries);
+    }
+
+    fn copy(self, vm: &VirtualMachine) -> PyResult<PyWeakValueDictionaryRef> {
+        let copy = PyWeakValueDictionary {
+            entries: RefCell::new(HashMap::new()),
+        }
+        .into_ref_with_type(vm, self.as_object().class())?;
+        for (key, value) in self.live_items() {
+            copy.clone().setitem(key, value, vm)?;
+        }
+        Ok(copy)
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        format!(
+            "<{} at 0x{:x}>",
+            self.as_object().class().name,
+            self.as_object().get_id()
+        )
+    }
+}
+
+/// A mapping which refers to its keys weakly.
+#[derive(Debug)]
+struct PyWeakKeyDictionary {
+    entries: RefCell<HashMap<u64, (PyWeakRef, PyObjectRef)>>,
+}
+
+type PyWeakKeyDictionaryRef = PyRef<PyWeakKeyDictionary>;
+
+impl PyValue for PyWeakKeyDictionary {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("weakref", "WeakKeyDictionary")
+    }
+}
+
+impl WeakContainer for PyWeakKeyDictionary {
+    fn remove_dead(&self, hash: u64, weakref: &PyObjectRef) {
+        let mut entries = self.entries.borrow_mut();
+        let dead = match entries.get(&hash) {
+            Some((key, _)) => key.is(weakref),
+            None => false,
+        };
+        let removed = if dead { entries.remove(&hash) } else { None };
+        drop(entries);
+        drop(removed);
+    }
+}
+
+impl PyWeakKeyDictionary {
+    fn live_items(&self) -> Vec<(PyObjectRef, PyObjectRef)> {
+        self.entries
+            .borrow()
+            .values()
+            .filter_map(|(key, value)| Some((key.upgrade()?, value.clone())))
+            .collect()
+    }
+
+    fn lookup(&self, vm: &VirtualMachine, key: &PyObjectRef) -> PyResult<Option<PyObjectRef>> {
+        let hash = hash(vm, key)?;
+        Ok(self
+            .entries
+            .borrow()
+            .get(&hash)
+            .filter(|(key, _)| key.upgrade().is_some())
+            .map(|(_, value)| value.clone()))
+    }
+}
+
+impl PyWeakKeyDictionaryRef {
+    fn new_dict(
+        cls: PyClassRef,
+        other: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyWeakKeyDictionaryRef> {
+        let dict = PyWeakKeyDictionary {
+            entries: RefCell::new(HashMap::new()),
+        }
+        .into_ref_with_type(vm, cls)?;
+        if let OptionalArg::Present(other) = other {
+            dict.clone().update(other, vm)?;
+        }
+        Ok(dict)
+    }
+
+    fn getitem(self, key: PyObjectRef, vm: &VirtualMachine) -> PyResult {
+        match self.lookup(vm, &key)? {
+            Some(value) => Ok(value),
+            None => Err(vm.new_key_error(vm.to_repr(&key)?.value.clone())),
+        }
+    }
+
+    fn setitem(self, key: PyObjectRef, value: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let hash = hash(vm, &key)?;
+        let weakref = weak_entry(&self, hash, &key, vm)?;
+        let replaced = self.entries.borrow_mut().insert(hash, (weakref, value));
+        drop(replaced);
+        Ok(())
+    }
+
+    fn delitem(self, key: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let hash = hash(vm, &key)?;
+        let removed = self.entries.borrow_mut().remove(&hash);
+        match removed {
+            Some((key, _)) if key.upgrade().is_some() => Ok(()),
+            _ => Err(vm.new_key_error(vm.to_repr(&key)?.value.clone())),
+        }
+    }
+
+    fn contains(self, key: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+        Ok(self.lookup(vm, &key)?.is_some())
+    }
+
+    fn len(self, _vm: &VirtualMachine) -> usize {
+        self.live_items().len()
+    }
+
+    fn iter(self, vm: &VirtualMachine) -> PyResult {
+        self.keys(vm)
+    }
+
+    fn get(self, key: PyObjectRef, default: OptionalArg, vm: &VirtualMachine) -> PyResult {
+        match self.lookup(vm, &key)? {
+            Some(value) => Ok(value),
+            None => Ok(default.into_option().unwrap_or_else(|| vm.get_none())),
+        }
+    }
+
+    fn keys(self, vm: &VirtualMachine) -> PyResult {
+        let keys = self.live_items().into_iter().map(|(key, _)| key).collect();
+        iter_list(vm, keys)
+    }
+
+    fn values(self, vm: &VirtualMachine) -> PyResult {
+        let values = self
+            .live_items()
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect();
+        iter_list(vm, values)
+    }
+
+    fn items(self, vm: &VirtualMachine) -> PyResult {
+        let items = self
+            .live_items()
+            .into_iter()
+            .map(|(key, value)| vm.ctx.new_tuple(vec![key, value]))
+            .collect();
+        iter_list(vm, items)
+    }
+
+    fn pop(self, key: PyObjectRef, default: OptionalArg, vm: &VirtualMachine) -> PyResult {
+        let hash = hash(vm, &key)?;
+        let removed = self.entries.borrow_mut().remove(&hash);
+        match (removed.filter(|(key, _)| key.upgrade().is_some()), default) {
+            (Some((_, value)), _) => Ok(value),
+            (None, OptionalArg::Present(default)) => Ok(default),
+            (None, OptionalArg::Missing) => Err(vm.new_key_error(vm.to_repr(&key)?.value.clone())),
+        }
+    }
+
+    fn setdefault(self, key: PyObjectRef, default: OptionalArg, vm: &VirtualMachine) -> PyResult {
+        match self.lookup(vm, &key)? {
+            Some(value) => Ok(value),
+            None => {
+                let default = default.into_option().unwrap_or_else(|| vm.get_none());
+                self.setitem(key, default.clone(), vm)?;
+                Ok(default)
+            }
+        }
+    }
+
+    fn update(self, other: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        for (key, value) in extract_pairs(vm, &other)? {
+            self.clone().setitem(key, value, vm)?;
+        }
+        Ok(())
+    }
+
+    fn clear(self, _vm: &VirtualMachine) {
+        let entries = self.entries.replace(HashMap::new());
+        drop(entries);
+    }
+
+    fn copy(self, vm: &VirtualMachine) -> PyResult<PyWeakKeyDictionaryRef> {
+        let copy = PyWeakKeyDictionary {
+            entries: RefCell::new(HashMap::new()),
+        }
+        .into_ref_with_type(vm, self.as_object().class())?;
+        for (key, value) in self.live_items() {
+            copy.clone().setitem(key, value, vm)?;
+        }
+        Ok(copy)
+    }
+
+    fn repr(self, _vm: &VirtualMachine) -> String {
+        format!(
+            "<{} at 0x{:x}>",
+            self.as_object().class().name,
+            self.as_object().get_id()
+        )
+    }
+}
+
+/// A set which refers to its elements weakly.
+#[derive(Debug)]
+struct PyWeakSet {
+    elements: RefCell<HashMap<u64, PyWeakRef>>,
+}
+
+type PyWeakSetRef = PyRef<PyWeakSet>;
+
+impl PyValue for PyWeakSet {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.class("weakref", "WeakSet")
+    }
+}
+
+impl WeakContainer for PyWeakSet {
+    fn remove_dead(&self, hash: u64, weakref: &PyObjectRef) {
+        let mut elements = self.elements.borrow_mut();
+        let dead = match elements.get(&hash) {
+            Some(element) => element.is(weakref),
+            None => false,
+        };
+        let removed = if dead { elements.remove(&hash) } else { None };
+        drop(elements);
+        drop(removed);
+    }
+}
+
+impl PyWeakSet {
+    fn live_elements(&self) -> Vec<PyObjectRef> {
+        self.elements
+            .borrow()
+            .values()
+            .filter_map(|element| element.upgrade())
+            .collect()
+    }
+}
+
+impl PyWeakSetRef {
+    fn new_set(
+        cls: PyClassRef,
+        iterable: OptionalArg,
+        vm: &VirtualMachine,
+    ) -> PyResult<PyWeakSetRef> {
+        let set = PyWeakSet {
+            elements: RefCell::new(HashMap::new()),
+        }
+        .into_ref_with_type(vm, cls)?;
+        if let OptionalArg::Present(iterable) = iterable {
+            set.clone().update(iterable, vm)?;
+        }
+        Ok(set)
+    }
+
+    fn add(self, item: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        let hash = hash(vm, &item)?;
+        let weakref = weak_entry(&self, hash, &item, vm)?;
+        let replaced = self.elements.borrow_mut().insert(hash, weakref);
+        drop(replaced);
+        Ok(())
+    }
+
+    fn discard(self, item: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+        let hash = hash(vm, &item)?;
+        let removed = self.elements.borrow_mut().remove(&hash);
+        Ok(removed.and_then(|element| element.upgrade()).is_some())
+    }
+
+    fn remove(self, item: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        if self.discard(item.clone(), vm)? {
+            Ok(())
+        } else {
+            Err(vm.new_key_error(vm.to_repr(&item)?.value.clone()))
+        }
+    }
+
+    fn contains(self, item: PyObjectRef, vm: &VirtualMachine) -> PyResult<bool> {
+        let hash = hash(vm, &item)?;
+        Ok(self
+            .elements
+            .borrow()
+            .get(&hash)
+            .and_then(|element| element.upgrade())
+            .is_some())
+    }
+
+    fn len(self, _vm: &VirtualMachine) -> usize {
+        self.live_elements().len()
+    }
+
+    fn iter(self, vm: &VirtualMachine) -> PyResult {
+        iter_list(vm, self.live_elements())
+    }
+
+    fn update(self, iterable: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+        for item in vm.extract_elements(&iterable)? {
+            self.clone().add(item, vm)?;
+        }
+        Ok(())
+    }
+
+    fn clear(self, _vm: &VirtualMachine) {
+        let elements = self.elements.replace(HashMap::new());
+        drop(elements);
+    }
+
+    fn copy(self, vm: &VirtualMachine) -> PyResult<PyWeakSetRef> {
+        let copy = PyWeakSet {
+            elements: RefCell::new(HashMap::new()),
+        }
+        .into_ref_with_type(vm, self.as_object().class())?;
+        for item in self.live_elements() {
+            copy.clone().add(item, vm)?;
+        }
+        Ok(copy)
+    }
+
+    fn repr(self, vm: &VirtualMachine) -> PyResult<String> {
+        let mut reprs = vec![];
+        for element in self.live_elements() {
+            reprs.push(vm.to_repr(&element)?.value.clone());
+        }
+        Ok(format!(
+            "{}({{{}}})",
+            self.as_object().class().name,
+            reprs.join(", ")
+        ))
+    }
+}
 
 pub fn make_module(ctx: &PyContext) -> PyObjectRef {
     py_module!(ctx, "_weakref", {
         "ref" => ctx.weakref_type()
     })
 }
+
+/// The `weakref` module, which adds finalizers and weak containers to what
+/// `_weakref` provides.
+pub fn make_weakref_module(ctx: &PyContext) -> PyObjectRef {
+    let finalize_type = py_class!(ctx, "finalize", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyFinalizeRef::new_finalize),
+        "__call__" => ctx.new_rustfunc(PyFinalizeRef::call),
+        "detach" => ctx.new_rustfunc(PyFinalizeRef::detach),
+        "peek" => ctx.new_rustfunc(PyFinalizeRef::peek),
+        "alive" => ctx.new_property(PyFinalizeRef::alive),
+        "atexit" =>
+            PropertyBuilder::new(ctx)
+                .add_getter(PyFinalizeRef::atexit)
+                .add_setter(PyFinalizeRef::set_atexit)
+                .create(),
+        "__repr__" => ctx.new_rustfunc(PyFinalizeRef::repr)
+    });
+
+    let weak_value_dictionary_type = py_class!(ctx, "WeakValueDictionary", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyWeakValueDictionaryRef::new_dict),
+        "__getitem__" => ctx.new_rustfunc(PyWeakValueDictionaryRef::getitem),
+        "__setitem__" => ctx.new_rustfunc(PyWeakValueDictionaryRef::setitem),
+        "__delitem__" => ctx.new_rustfunc(PyWeakValueDictionaryRef::delitem),
+        "__contains__" => ctx.new_rustfunc(PyWeakValueDictionaryRef::contains),
+        "__len__" => ctx.new_rustfunc(PyWeakValueDictionaryRef::len),
+        "__iter__" => ctx.new_rustfunc(PyWeakValueDictionaryRef::iter),
+        "__repr__" => ctx.new_rustfunc(PyWeakValueDictionaryRef::repr),
+        "get" => ctx.new_rustfunc(PyWeakValueDictionaryRef::get),
+        "keys" => ctx.new_rustfunc(PyWeakValueDictionaryRef::keys),
+        "values" => ctx.new_rustfunc(PyWeakValueDictionaryRef::values),
+        "items" => ctx.new_rustfunc(PyWeakValueDictionaryRef::items),
+        "pop" => ctx.new_rustfunc(PyWeakValueDictionaryRef::pop),
+        "setdefault" => ctx.new_rustfunc(PyWeakValueDictionaryRef::setdefault),
+        "update" => ctx.new_rustfunc(PyWeakValueDictionaryRef::update),
+        "clear" => ctx.new_rustfunc(PyWeakValueDictionaryRef::clear),
+        "copy" => ctx.new_rustfunc(PyWeakValueDictionaryRef::copy)
+    });
+
+    let weak_key_dictionary_type = py_class!(ctx, "WeakKeyDictionary", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::new_dict),
+        "__getitem__" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::getitem),
+        "__setitem__" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::setitem),
+        "__delitem__" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::delitem),
+        "__contains__" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::contains),
+        "__len__" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::len),
+        "__iter__" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::iter),
+        "__repr__" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::repr),
+        "get" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::get),
+        "keys" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::keys),
+        "values" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::values),
+        "items" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::items),
+        "pop" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::pop),
+        "setdefault" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::setdefault),
+        "update" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::update),
+        "clear" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::clear),
+        "copy" => ctx.new_rustfunc(PyWeakKeyDictionaryRef::copy)
+    });
+
+    let weak_set_type = py_class!(ctx, "WeakSet", ctx.object(), {
+        "__new__" => ctx.new_rustfunc(PyWeakSetRef::new_set),
+        "add" => ctx.new_rustfunc(PyWeakSetRef::add),
+        "discard" => ctx.new_rustfunc(PyWeakSetRef::discard),
+        "remove" => ctx.new_rustfunc(PyWeakSetRef::remove),
+        "__contains__" => ctx.new_rustfunc(PyWeakSetRef::contains),
+        "__len__" => ctx.new_rustfunc(PyWeakSetRef::len),
+        "__iter__" => ctx.new_rustfunc(PyWeakSetRef::iter),
+        "__repr__" => ctx.new_rustfunc(PyWeakSetRef::repr),
+        "update" => ctx.new_rustfunc(PyWeakSetRef::update),
+        "clear" => ctx.new_rustfunc(PyWeakSetRef::clear),
+        "copy" => ctx.new_rustfunc(PyWeakSetRef::copy)
+    });
+
+    py_module!(ctx, "weakref", {
+        "ref" => ctx.weakref_type(),
+        "ReferenceType" => ctx.weakref_type(),
+        "finalize" => finalize_type,
+        "WeakValueDictionary" => weak_value_dictionary_type,
+        "WeakKeyDictionary" => weak_key_dictionary_type,
+        "WeakSet" => weak_set_type,
+        "_finalize_registry" => ctx.new_list(vec![]),
+        "_registered_with_atexit" => ctx.new_bool(false)
+    })
+}
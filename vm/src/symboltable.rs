@@ -0,0 +1,801 @@
+//! This module implements a symbol table pass over the AST.
+//!
+//! Every name used in a scope is classified as local, global, free or cell,
+//! so that the compiler can emit the right kind of load and store
+//! instructions for it.
+//!
+//! Inspirational file: https://github.com/python/cpython/blob/master/Python/symtable.c
+
+use crate::error::{CompileError, CompileErrorType};
+use rustpython_parser::ast;
+use std::collections::{HashMap, HashSet};
+
+/// Build the symbol table of a complete module.
+pub fn make_symbol_table(program: &ast::Program) -> Result<SymbolTable, SymbolTableError> {
+    statements_to_symbol_table(&program.statements)
+}
+
+/// Build the symbol table of a sequence of top level statements.
+pub fn statements_to_symbol_table(
+    statements: &[ast::LocatedStatement],
+) -> Result<SymbolTable, SymbolTableError> {
+    let mut builder = SymbolTableBuilder::new();
+    builder.enter_scope("top", ScopeType::Module, 0);
+    builder.scan_statements(statements)?;
+    builder.finish()
+}
+
+/// Kind of block a symbol table was created for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScopeType {
+    Module,
+    Class,
+    Function,
+}
+
+/// Where a name lives at runtime, as determined by the analysis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SymbolScope {
+    Unknown,
+    Local,
+    GlobalExplicit,
+    GlobalImplicit,
+    Free,
+    Cell,
+}
+
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: SymbolScope,
+    pub is_referenced: bool,
+    pub is_assigned: bool,
+    pub is_parameter: bool,
+    pub is_global: bool,
+    pub is_nonlocal: bool,
+    // Where the nonlocal declaration was made, for error reporting.
+    pub nonlocal_location: Option<ast::Location>,
+    // The name is bound in a class body, while functions nested in the class
+    // use the variable of the same name from an enclosing function.
+    pub is_free_class: bool,
+}
+
+impl Symbol {
+    fn new(name: &str) -> Self {
+        Symbol {
+            name: name.to_string(),
+            scope: SymbolScope::Unknown,
+            is_referenced: false,
+            is_assigned: false,
+            is_parameter: false,
+            is_global: false,
+            is_nonlocal: false,
+            nonlocal_location: None,
+            is_free_class: false,
+        }
+    }
+
+    /// Whether this name is bound in the scope it belongs to.
+    pub fn is_bound(&self) -> bool {
+        self.is_assigned || self.is_parameter
+    }
+
+    /// Whether the scope takes this name from the closure of its function.
+    pub fn is_free(&self) -> bool {
+        self.scope == SymbolScope::Free || self.is_free_class
+    }
+}
+
+/// Symbol table of a single scope, together with the tables of the scopes
+/// nested in it, in the order the compiler will visit them.
+#[derive(Clone, Debug)]
+pub struct SymbolTable {
+    pub name: String,
+    pub typ: ScopeType,
+    pub line_number: usize,
+    pub symbols: HashMap<String, Symbol>,
+    pub sub_tables: Vec<SymbolTable>,
+}
+
+impl SymbolTable {
+    fn new(name: String, typ: ScopeType, line_number: usize) -> Self {
+        SymbolTable {
+            name,
+            typ,
+            line_number,
+            symbols: HashMap::new(),
+            sub_tables: vec![],
+        }
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.get(name)
+    }
+}
+
+#[derive(Debug)]
+pub struct SymbolTableError {
+    pub error: String,
+    pub location: ast::Location,
+}
+
+impl From<SymbolTableError> for CompileError {
+    fn from(error: SymbolTableError) -> Self {
+        CompileError::new(CompileErrorType::SyntaxError(error.error), error.location)
+    }
+}
+
+type SymbolTableResult = Result<(), SymbolTableError>;
+
+/// Name of the implicit cell holding the class being defined.
+const CLASS_CELL: &str = "__class__";
+
+/// Resolve the final scope of every symbol once the whole tree is known.
+///
+/// `enclosing` holds, innermost last, the names bound in the enclosing
+/// function scopes. Class and module scopes never contribute to it, since
+/// their names are not visible from nested functions.
+fn analyze_symbol_table(
+    table: &mut SymbolTable,
+    enclosing: &[HashSet<String>],
+) -> SymbolTableResult {
+    let location = ast::Location::new(table.line_number, 0);
+    for symbol in table.symbols.values_mut() {
+        symbol.scope = if symbol.is_global {
+            SymbolScope::GlobalExplicit
+        } else if symbol.is_nonlocal {
+            let location = symbol
+                .nonlocal_location
+                .clone()
+                .unwrap_or_else(|| location.clone());
+            if table.typ == ScopeType::Module {
+                return Err(SymbolTableError {
+                    error: "nonlocal declaration not allowed at module level".to_string(),
+                    location,
+                });
+            }
+            if !enclosing.iter().any(|names| names.contains(&symbol.name)) {
+                return Err(SymbolTableError {
+                    error: format!("no binding for nonlocal '{}' found", symbol.name),
+                    location,
+                });
+            }
+            SymbolScope::Free
+        } else if symbol.is_bound() {
+            SymbolScope::Local
+        } else if enclosing.iter().any(|names| names.contains(&symbol.name)) {
+            SymbolScope::Free
+        } else {
+            SymbolScope::GlobalImplicit
+        };
+    }
+
+    let mut child_enclosing = enclosing.to_vec();
+    match table.typ {
+        ScopeType::Function => child_enclosing.push(
+            table
+                .symbols
+                .values()
+                .filter(|symbol| match symbol.scope {
+                    SymbolScope::Local | SymbolScope::Free => true,
+                    _ => false,
+                })
+                .map(|symbol| symbol.name.clone())
+                .collect(),
+        ),
+        // The only name a class body exposes to nested functions is the
+        // implicit `__class__` cell used by `super()`.
+        ScopeType::Class => {
+            child_enclosing.push(vec![CLASS_CELL.to_string()].into_iter().collect())
+        }
+        ScopeType::Module => {}
+    }
+
+    for sub_table in table.sub_tables.iter_mut() {
+        analyze_symbol_table(sub_table, &child_enclosing)?;
+
+        // Names that are free in a nested scope either live in this scope,
+        // in which case they become cells, or pass through this scope on
+        // their way to an outer one.
+        for free in sub_table.symbols.values().filter(|symbol| symbol.is_free()) {
+            let symbol = table
+                .symbols
+                .entry(free.name.clone())
+                .or_insert_with(|| Symbol::new(&free.name));
+            match symbol.scope {
+                SymbolScope::Local if table.typ == ScopeType::Function => {
+                    symbol.scope = SymbolScope::Cell;
+                }
+                _ if table.typ == ScopeType::Class && free.name == CLASS_CELL => {
+                    symbol.scope = SymbolScope::Cell;
+                }
+                SymbolScope::Local if table.typ == ScopeType::Class => {
+                    symbol.is_free_class = true;
+                }
+                SymbolScope::Unknown => {
+                    symbol.scope = SymbolScope::Free;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct SymbolTableBuilder {
+    // Scope stack, the innermost scope is the last one.
+    scopes: Vec<SymbolTable>,
+}
+
+impl SymbolTableBuilder {
+    fn new() -> Self {
+        SymbolTableBuilder { scopes: vec![] }
+    }
+
+    fn finish(&mut self) -> Result<SymbolTable, SymbolTableError> {
+        assert_eq!(self.scopes.len(), 1);
+        let mut table = self.scopes.pop().unwrap();
+        analyze_symbol_table(&mut table, &[])?;
+        Ok(table)
+    }
+
+    fn enter_scope(&mut self, name: &str, typ: ScopeType, line_number: usize) {
+        let table = SymbolTable::new(name.to_string(), typ, line_number);
+        self.scopes.push(table);
+    }
+
+    fn leave_scope(&mut self) {
+        // Pop the current scope and register it with its parent:
+        let table = self.scopes.pop().unwrap();
+        self.scopes.last_mut().unwrap().sub_tables.push(table);
+    }
+
+    fn scan_statements(&mut self, statements: &[ast::LocatedStatement]) -> SymbolTableResult {
+        for statement in statements {
+            self.scan_statement(statement)?;
+        }
+        Ok(())
+    }
+
+    fn scan_parameters(&mut self, parameters: &[ast::Parameter]) -> SymbolTableResult {
+        for parameter in parameters {
+            self.register_parameter(&parameter.arg)?;
+        }
+        Ok(())
+    }
+
+    fn scan_varargs(&mut self, varargs: &ast::Varargs) -> SymbolTableResult {
+        if let ast::Varargs::Named(parameter) = varargs {
+            self.register_parameter(&parameter.arg)?;
+        }
+        Ok(())
+    }
+
+    fn scan_statement(&mut self, statement: &ast::LocatedStatement) -> SymbolTableResult {
+        let location = &statement.location;
+        match &statement.node {
+            ast::Statement::Global { names } => {
+                for name in names {
+                    self.register_global(name, location)?;
+                }
+            }
+            ast::Statement::Nonlocal { names } => {
+                for name in names {
+                    self.register_nonlocal(name, location)?;
+                }
+            }
+            ast::Statement::FunctionDef {
+                name,
+                body,
+                args,
+                decorator_list,
+                returns,
+                ..
+            } => {
+                // The order here mirrors the order in which the compiler
+                // visits the nested scopes.
+                self.register_assignment(name)?;
+                self.enter_function(name, args, location.get_row())?;
+                self.scan_statements(body)?;
+                self.leave_scope();
+                self.scan_expressions(decorator_list)?;
+                if let Some(expression) = returns {
+                    self.scan_expression(expression)?;
+                }
+                self.scan_parameter_annotations(args)?;
+            }
+            ast::Statement::ClassDef {
+                name,
+                body,
+                bases,
+                keywords,
+                decorator_list,
+            } => {
+                self.register_assignment(name)?;
+                self.scan_expressions(decorator_list)?;
+                self.enter_scope(name, ScopeType::Class, location.get_row());
+                self.scan_statements(body)?;
+                self.leave_scope();
+                self.scan_expressions(bases)?;
+                for keyword in keywords {
+                    self.scan_expression(&keyword.value)?;
+                }
+            }
+            ast::Statement::Expression { expression } => self.scan_expression(expression)?,
+            ast::Statement::If { test, body, orelse } => {
+                self.scan_expression(test)?;
+                self.scan_statements(body)?;
+                if let Some(code) = orelse {
+                    self.scan_statements(code)?;
+                }
+            }
+            ast::Statement::For {
+                target,
+                iter,
+                body,
+                orelse,
+                ..
+            } => {
+                self.scan_expressions(iter)?;
+                self.scan_store(target)?;
+                self.scan_statements(body)?;
+                if let Some(code) = orelse {
+                    self.scan_statements(code)?;
+                }
+            }
+            ast::Statement::While { test, body, orelse } => {
+                self.scan_expression(test)?;
+                self.scan_statements(body)?;
+                if let Some(code) = orelse {
+                    self.scan_statements(code)?;
+                }
+            }
+            ast::Statement::Break | ast::Statement::Continue | ast::Statement::Pass => {
+                // No symbols here.
+            }
+            ast::Statement::Import { import_parts } => {
+                for part in import_parts {
+                    if let Some(alias) = &part.alias {
+                        self.register_assignment(alias)?;
+                    } else if let Some(symbol) = &part.symbol {
+                        if symbol != "*" {
+                            self.register_assignment(symbol)?;
+                        }
+                    } else {
+                        self.register_assignment(&part.module)?;
+                    }
+                }
+            }
+            ast::Statement::Return { value } => {
+                if let Some(expressions) = value {
+                    self.scan_expressions(expressions)?;
+                }
+            }
+            ast::Statement::Assert { test, msg } => {
+                self.scan_expression(test)?;
+                if let Some(expression) = msg {
+                    self.scan_expression(expression)?;
+                }
+            }
+            ast::Statement::Delete { targets } => {
+                for target in targets {
+                    self.scan_store(target)?;
+                }
+            }
+            ast::Statement::Assign { targets, value } => {
+                self.scan_expression(value)?;
+                for target in targets {
+                    self.scan_store(target)?;
+                }
+            }
+            ast::Statement::AugAssign { target, value, .. } => {
+                self.scan_expression(target)?;
+                self.scan_expression(value)?;
+                self.scan_store(target)?;
+            }
+            ast::Statement::With { items, body, .. } => {
+                for item in items {
+                    self.scan_expression(&item.context_expr)?;
+                    if let Some(expression) = &item.optional_vars {
+                        self.scan_store(expression)?;
+                    }
+                }
+                self.scan_statements(body)?;
+            }
+            ast::Statement::Try {
+                body,
+                handlers,
+                orelse,
+                finalbody,
+            } => {
+                self.scan_statements(body)?;
+                for handler in handlers {
+                    if let Some(expression) = &handler.typ {
+                        self.scan_expression(expression)?;
+                    }
+                    if let Some(name) = &handler.name {
+                        self.register_assignment(name)?;
+                    }
+                    self.scan_statements(&handler.body)?;
+                }
+                if let Some(code) = orelse {
+                    self.scan_statements(code)?;
+                }
+                if let Some(code) = finalbody {
+                    self.scan_statements(code)?;
+                }
+            }
+            ast::Statement::Raise { exception, cause } => {
+                if let Some(expression) = exception {
+                    self.scan_expression(expression)?;
+                }
+                if let Some(expression) = cause {
+                    self.scan_expression(expression)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_expressions(&mut self, expressions: &[ast::Expression]) -> SymbolTableResult {
+        for expression in expressions {
+            self.scan_expression(expression)?;
+        }
+        Ok(())
+    }
+
+    /// Scan the target of an assignment, registering the bound names.
+    fn scan_store(&mut self, target: &ast::Expression) -> SymbolTableResult {
+        match target {
+            ast::Expression::Identifier { name } => self.register_assignment(name),
+            ast::Expression::Tuple { elements } | ast::Expression::List { elements } => {
+                for element in elements {
+                    self.scan_store(element)?;
+                }
+                Ok(())
+            }
+            ast::Expression::Starred { value } => self.scan_store(value),
+            _ => self.scan_expression(target),
+        }
+    }
+
+    fn scan_expression(&mut self, expression: &ast::Expression) -> SymbolTableResult {
+        match expression {
+            ast::Expression::Binop { a, b, .. }
+            | ast::Expression::BoolOp { a, b, .. }
+            | ast::Expression::Compare { a, b, .. }
+            | ast::Expression::Subscript { a, b } => {
+                self.scan_expression(a)?;
+                self.scan_expression(b)?;
+            }
+            ast::Expression::Attribute { value, .. } => {
+                self.scan_expression(value)?;
+            }
+            ast::Expression::Dict { elements } => {
+                for (key, value) in elements {
+                    self.scan_expression(key)?;
+                    self.scan_expression(value)?;
+                }
+            }
+            ast::Expression::Starred { value } => {
+                self.scan_expression(value)?;
+            }
+            ast::Expression::Bytes { .. }
+            | ast::Expression::Number { .. }
+            | ast::Expression::True
+            | ast::Expression::False
+            | ast::Expression::None
+            | ast::Expression::Ellipsis => {}
+            ast::Expression::Tuple { elements }
+            | ast::Expression::Set { elements }
+            | ast::Expression::List { elements }
+            | ast::Expression::Slice { elements } => {
+                self.scan_expressions(elements)?;
+            }
+            ast::Expression::Comprehension { kind, generators } => {
+                self.scan_comprehension(kind, generators)?;
+            }
+            ast::Expression::Call {
+                function,
+                args,
+                keywords,
+            } => {
+                self.scan_expression(function)?;
+                self.scan_expressions(args)?;
+                for keyword in keywords {
+                    self.scan_expression(&keyword.value)?;
+                }
+            }
+            ast::Expression::String { value } => {
+                self.scan_string_group(value)?;
+            }
+            ast::Expression::Yield { value } => {
+                if let Some(expression) = value {
+                    self.scan_expression(expression)?;
+                }
+            }
+            ast::Expression::YieldFrom { value } | ast::Expression::Await { value } => {
+                self.scan_expression(value)?;
+            }
+            ast::Expression::Unop { a, .. } => {
+                self.scan_expression(a)?;
+            }
+            ast::Expression::Identifier { name } => {
+                self.register_reference(name)?;
+            }
+            ast::Expression::Lambda { args, body } => {
+                self.enter_function("<lambda>", args, 0)?;
+                self.scan_expression(body)?;
+                self.leave_scope();
+            }
+            ast::Expression::IfExpression { test, body, orelse } => {
+                self.scan_expression(test)?;
+                self.scan_expression(body)?;
+                self.scan_expression(orelse)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_comprehension(
+        &mut self,
+        kind: &ast::ComprehensionKind,
+        generators: &[ast::Comprehension],
+    ) -> SymbolTableResult {
+        // The comprehension body runs in its own function scope, which
+        // receives the first iterator as its `.0` argument. The first
+        // iterable itself is evaluated in the enclosing scope.
+        self.enter_scope("<comprehension>", ScopeType::Function, 0);
+        self.register_parameter(".0")?;
+        for (index, generator) in generators.iter().enumerate() {
+            if index > 0 {
+                self.scan_expression(&generator.iter)?;
+            }
+            self.scan_store(&generator.target)?;
+            self.scan_expressions(&generator.ifs)?;
+        }
+        match kind {
+            ast::ComprehensionKind::GeneratorExpression { element }
+            | ast::ComprehensionKind::List { element }
+            | ast::ComprehensionKind::Set { element } => {
+                self.scan_expression(element)?;
+            }
+            ast::ComprehensionKind::Dict { key, value } => {
+                self.scan_expression(value)?;
+                self.scan_expression(key)?;
+            }
+        }
+        self.leave_scope();
+
+        if let Some(generator) = generators.first() {
+            self.scan_expression(&generator.iter)?;
+        }
+        Ok(())
+    }
+
+    fn scan_string_group(&mut self, group: &ast::StringGroup) -> SymbolTableResult {
+        match group {
+            ast::StringGroup::Constant { .. } => {}
+            ast::StringGroup::FormattedValue { value, .. } => {
+                self.scan_expression(value)?;
+            }
+            ast::StringGroup::Joined { values } => {
+                for subgroup in values {
+                    self.scan_string_group(subgroup)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn enter_function(
+        &mut self,
+        name: &str,
+        args: &ast::Parameters,
+        line_number: usize,
+    ) -> SymbolTableResult {
+        // Default values are evaluated in the enclosing scope:
+        self.scan_expressions(&args.defaults)?;
+
+        self.enter_scope(name, ScopeType::Function, line_number);
+
+        self.scan_parameters(&args.args)?;
+        self.scan_varargs(&args.vararg)?;
+        self.scan_parameters(&args.kwonlyargs)?;
+        self.scan_varargs(&args.kwarg)?;
+        Ok(())
+    }
+
+    fn scan_parameter_annotations(&mut self, args: &ast::Parameters) -> SymbolTableResult {
+        for parameter in args.args.iter() {
+            if let Some(annotation) = &parameter.annotation {
+                self.scan_expression(annotation)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn current_symbol(&mut self, name: &str) -> &mut Symbol {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .symbols
+            .entry(name.to_string())
+            .or_insert_with(|| Symbol::new(name))
+    }
+
+    fn register_parameter(&mut self, name: &str) -> SymbolTableResult {
+        let symbol = self.current_symbol(name);
+        symbol.is_parameter = true;
+        Ok(())
+    }
+
+    fn register_assignment(&mut self, name: &str) -> SymbolTableResult {
+        let symbol = self.current_symbol(name);
+        symbol.is_assigned = true;
+        Ok(())
+    }
+
+    fn register_reference(&mut self, name: &str) -> SymbolTableResult {
+        let symbol = self.current_symbol(name);
+        symbol.is_referenced = true;
+        // A bare `super()` call needs the class cell to find its arguments:
+        if name == "super" && self.scopes.last().unwrap().typ == ScopeType::Function {
+            self.current_symbol(CLASS_CELL).is_referenced = true;
+        }
+        Ok(())
+    }
+
+    fn register_global(&mut self, name: &str, location: &ast::Location) -> SymbolTableResult {
+        let symbol = self.current_symbol(name);
+        let error = if symbol.is_parameter {
+            Some(format!("name '{}' is parameter and global", name))
+        } else if symbol.is_nonlocal {
+            Some(format!("name '{}' is nonlocal and global", name))
+        } else if symbol.is_assigned {
+            Some(format!(
+                "name '{}' is assigned to before global declaration",
+                name
+            ))
+        } else if symbol.is_referenced {
+            Some(format!(
+                "name '{}' is used prior to global declaration",
+                name
+            ))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return Err(SymbolTableError {
+                error,
+                location: location.clone(),
+            });
+        }
+        symbol.is_global = true;
+        Ok(())
+    }
+
+    fn register_nonlocal(&mut self, name: &str, location: &ast::Location) -> SymbolTableResult {
+        let symbol = self.current_symbol(name);
+        let error = if symbol.is_parameter {
+            Some(format!("name '{}' is parameter and nonlocal", name))
+        } else if symbol.is_global {
+            Some(format!("name '{}' is nonlocal and global", name))
+        } else if symbol.is_assigned {
+            Some(format!(
+                "name '{}' is assigned to before nonlocal declaration",
+                name
+            ))
+        } else if symbol.is_referenced {
+            Some(format!(
+                "name '{}' is used prior to nonlocal declaration",
+                name
+            ))
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return Err(SymbolTableError {
+                error,
+                location: location.clone(),
+            });
+        }
+        symbol.is_nonlocal = true;
+        symbol.nonlocal_location = Some(location.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{make_symbol_table, ScopeType, SymbolScope, SymbolTable};
+    use rustpython_parser::parser;
+
+    fn symbol_table(source: &str) -> SymbolTable {
+        let program = parser::parse_program(source).unwrap();
+        make_symbol_table(&program).unwrap()
+    }
+
+    fn scope_of(table: &SymbolTable, name: &str) -> SymbolScope {
+        table.lookup(name).unwrap().scope
+    }
+
+    #[test]
+    fn test_global_and_local() {
+        let table = symbol_table("x = 1\ndef f(a):\n global y\n y = a + x\n z = 2\n");
+        let function = &table.sub_tables[0];
+        assert_eq!(function.typ, ScopeType::Function);
+        assert_eq!(scope_of(function, "a"), SymbolScope::Local);
+        assert_eq!(scope_of(function, "x"), SymbolScope::GlobalImplicit);
+        assert_eq!(scope_of(function, "y"), SymbolScope::GlobalExplicit);
+        assert_eq!(scope_of(function, "z"), SymbolScope::Local);
+    }
+
+    #[test]
+    fn test_free_and_cell() {
+        let table =
+            symbol_table("def f():\n x = 1\n def g():\n  def h():\n   return x\n  return h\n");
+        let f = &table.sub_tables[0];
+        let g = &f.sub_tables[0];
+        let h = &g.sub_tables[0];
+        assert_eq!(scope_of(f, "x"), SymbolScope::Cell);
+        assert_eq!(scope_of(g, "x"), SymbolScope::Free);
+        assert_eq!(scope_of(h, "x"), SymbolScope::Free);
+    }
+
+    #[test]
+    fn test_nonlocal() {
+        let table = symbol_table("def f():\n x = 1\n def g():\n  nonlocal x\n  x = 2\n");
+        let f = &table.sub_tables[0];
+        let g = &f.sub_tables[0];
+        assert_eq!(scope_of(f, "x"), SymbolScope::Cell);
+        assert_eq!(scope_of(g, "x"), SymbolScope::Free);
+    }
+
+    #[test]
+    fn test_class_scope_is_not_enclosing() {
+        let table = symbol_table("def f():\n class A:\n  y = 1\n  def g(self):\n   return y\n");
+        let class = &table.sub_tables[0].sub_tables[0];
+        let method = &class.sub_tables[0];
+        assert_eq!(scope_of(class, "y"), SymbolScope::Local);
+        assert_eq!(scope_of(method, "y"), SymbolScope::GlobalImplicit);
+    }
+
+    #[test]
+    fn test_class_free_variable() {
+        let table =
+            symbol_table("def f():\n y = 1\n class A:\n  y = 2\n  def g(self):\n   return y\n");
+        let f = &table.sub_tables[0];
+        let class = &f.sub_tables[0];
+        let method = &class.sub_tables[0];
+        assert_eq!(scope_of(f, "y"), SymbolScope::Cell);
+        assert_eq!(scope_of(class, "y"), SymbolScope::Local);
+        assert!(class.lookup("y").unwrap().is_free_class);
+        assert_eq!(scope_of(method, "y"), SymbolScope::Free);
+    }
+
+    #[test]
+    fn test_class_cell() {
+        let table = symbol_table("class A:\n def f(self):\n  return super().f()\n");
+        let class = &table.sub_tables[0];
+        let method = &class.sub_tables[0];
+        assert_eq!(scope_of(class, "__class__"), SymbolScope::Cell);
+        assert_eq!(scope_of(method, "__class__"), SymbolScope::Free);
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let sources = vec![
+            "def f():\n x = 1\n global x\n",
+            "def f():\n print(x)\n global x\n",
+            "def f(x):\n global x\n",
+            "nonlocal x\n",
+            "def f():\n nonlocal x\n",
+        ];
+        for source in sources {
+            let program = parser::parse_program(source).unwrap();
+            assert!(make_symbol_table(&program).is_err(), "{}", source);
+        }
+    }
+}
@@ -26,6 +26,51 @@ fn getframe(offset: OptionalArg<usize>, vm: &VirtualMachine) -> PyResult<FrameRe
     Ok(frame.clone())
 }
 
+fn sys_exc_info(vm: &VirtualMachine) -> PyObjectRef {
+    let exc_info = match vm.current_exception() {
+        Some(exception) => vec![
+            exception.class().into_object(),
+            exception.clone(),
+            vm.get_attribute(exception, "__traceback__")
+                .unwrap_or_else(|_| vm.get_none()),
+        ],
+        None => vec![vm.get_none(), vm.get_none(), vm.get_none()],
+    };
+    vm.ctx.new_tuple(exc_info)
+}
+
+fn sys_exit(code: OptionalArg<PyObjectRef>, vm: &VirtualMachine) -> PyResult {
+    let args = code.into_option().into_iter().collect::<Vec<_>>();
+    let system_exit = vm.ctx.exceptions.system_exit.clone();
+    Err(vm.invoke(system_exit.into_object(), args)?)
+}
+
+fn sys_getrecursionlimit(vm: &VirtualMachine) -> usize {
+    vm.recursion_limit.get()
+}
+
+fn sys_setrecursionlimit(limit: isize, vm: &VirtualMachine) -> PyResult<()> {
+    if limit < 1 {
+        return Err(
+            vm.new_value_error("recursion limit must be greater or equal than 1".to_string())
+        );
+    }
+    let limit = limit as usize;
+    let depth = vm.frames.borrow().len();
+    if limit <= depth {
+        let recursion_error = vm.ctx.exceptions.recursion_error.clone();
+        return Err(vm.new_exception(
+            recursion_error,
+            format!(
+                "cannot set the recursion limit to {} at the recursion depth {}: the limit is too low",
+                limit, depth
+            ),
+        ));
+    }
+    vm.recursion_limit.set(limit);
+    Ok(())
+}
+
 fn sys_getrefcount(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     arg_check!(vm, args, required = [(object, None)]);
     let size = Rc::strong_count(&object);
@@ -39,6 +84,32 @@ fn sys_getsizeof(vm: &VirtualMachine, args: PyFuncArgs) -> PyResult {
     Ok(vm.ctx.new_int(size))
 }
 
+fn flags_optimize(_zelf: PyObjectRef, vm: &VirtualMachine) -> u8 {
+    vm.compile_options.get().optimize
+}
+
+fn make_flags(ctx: &PyContext) -> PyObjectRef {
+    let zero = || ctx.new_int(0);
+    let flags_type = py_class!(ctx, "flags", ctx.object(), {
+        "bytes_warning" => zero(),
+        "debug" => zero(),
+        "dont_write_bytecode" => ctx.new_int(i32::from(dont_write_bytecode())),
+        "ignore_environment" => zero(),
+        "inspect" => zero(),
+        "interactive" => zero(),
+        "no_site" => zero(),
+        "no_user_site" => zero(),
+        "optimize" => ctx.new_property(flags_optimize),
+        "quiet" => zero(),
+        "verbose" => zero(),
+    });
+    ctx.new_instance(flags_type, None)
+}
+
+fn dont_write_bytecode() -> bool {
+    env::var_os("PYTHONDONTWRITEBYTECODE").map_or(false, |value| !value.is_empty())
+}
+
 pub fn make_module(ctx: &PyContext, builtins: PyObjectRef) -> PyObjectRef {
     let path_list = match env::var_os("PYTHONPATH") {
         Some(paths) => env::split_paths(&paths)
@@ -127,6 +198,11 @@ settrace() -- set the global debug tracing function
     let sys_name = "sys";
     let sys_mod = py_module!(ctx, sys_name, {
       "argv" => argv(ctx),
+      "dont_write_bytecode" => ctx.new_bool(dont_write_bytecode()),
+      "exc_info" => ctx.new_rustfunc(sys_exc_info),
+      "exit" => ctx.new_rustfunc(sys_exit),
+      "flags" => make_flags(ctx),
+      "getrecursionlimit" => ctx.new_rustfunc(sys_getrecursionlimit),
       "getrefcount" => ctx.new_rustfunc(sys_getrefcount),
       "getsizeof" => ctx.new_rustfunc(sys_getsizeof),
       "maxsize" => ctx.new_int(std::usize::MAX),
@@ -134,6 +210,7 @@ settrace() -- set the global debug tracing function
       "ps1" => ctx.new_str(">>>>> ".to_string()),
       "ps2" => ctx.new_str("..... ".to_string()),
       "__doc__" => ctx.new_str(sys_doc.to_string()),
+      "setrecursionlimit" => ctx.new_rustfunc(sys_setrecursionlimit),
       "_getframe" => ctx.new_rustfunc(getframe),
     });
 
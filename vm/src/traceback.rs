@@ -1,2 +1,174 @@
-// python tracebacks
-//
+/*! The python `traceback` type.
+
+A traceback records the frames an exception has propagated through. Each
+entry is prepended while the exception unwinds, so `tb_next` points towards
+the frame where the exception was raised.
+*/
+
+use std::fs;
+
+use crate::frame::FrameRef;
+use crate::obj::objtype::PyClassRef;
+use crate::pyobject::{PyContext, PyObjectRef, PyRef, PyResult, PyValue};
+use crate::vm::VirtualMachine;
+
+pub type PyTracebackRef = PyRef<PyTraceback>;
+
+#[derive(Debug)]
+pub struct PyTraceback {
+    pub next: Option<PyTracebackRef>,
+    pub frame: FrameRef,
+    pub lasti: usize,
+    pub lineno: usize,
+}
+
+impl PyValue for PyTraceback {
+    fn class(vm: &VirtualMachine) -> PyClassRef {
+        vm.ctx.traceback_type()
+    }
+}
+
+impl PyTraceback {
+    pub fn new(next: Option<PyTracebackRef>, frame: FrameRef, lasti: usize, lineno: usize) -> Self {
+        PyTraceback {
+            next,
+            frame,
+            lasti,
+            lineno,
+        }
+    }
+
+    /// Iterate over this entry and the ones following it, outermost first.
+    pub fn iter(zelf: &PyTracebackRef) -> impl Iterator<Item = PyTracebackRef> {
+        let mut next = Some(zelf.clone());
+        std::iter::from_fn(move || {
+            let current = next.take()?;
+            next = current.next.clone();
+            Some(current)
+        })
+    }
+
+    /// The file name, line number and code name of this entry.
+    pub fn location(&self) -> (String, usize, String) {
+        (
+            self.frame.code.source_path.clone(),
+            self.lineno,
+            self.frame.code.obj_name.clone(),
+        )
+    }
+}
+
+impl PyTracebackRef {
+    fn new(_cls: PyClassRef, vm: &VirtualMachine) -> PyResult {
+        Err(vm.new_type_error("cannot create 'traceback' instances".to_string()))
+    }
+
+    fn tb_frame(self, _vm: &VirtualMachine) -> FrameRef {
+        self.frame.clone()
+    }
+
+    fn tb_lasti(self, _vm: &VirtualMachine) -> usize {
+        self.lasti
+    }
+
+    fn tb_lineno(self, _vm: &VirtualMachine) -> usize {
+        self.lineno
+    }
+
+    fn tb_next(self, vm: &VirtualMachine) -> PyObjectRef {
+        match self.next {
+            Some(ref next) => next.clone().into_object(),
+            None => vm.get_none(),
+        }
+    }
+}
+
+/// Read a single line from a source file, without surrounding whitespace.
+pub fn source_line(filename: &str, lineno: usize) -> Option<String> {
+    if lineno == 0 {
+        return None;
+    }
+    let source = fs::read_to_string(filename).ok()?;
+    let line = source.lines().nth(lineno - 1)?.trim();
+    if line.is_empty() {
+        None
+    } else {
+        Some(line.to_string())
+    }
+}
+
+/// Format a stack entry the way CPython prints it, including the source
+/// line when it can be read.
+pub fn format_entry(filename: &str, lineno: usize, name: &str) -> String {
+    let mut entry = format!("  File \"{}\", line {}, in {}\n", filename, lineno, name);
+    if let Some(line) = source_line(filename, lineno) {
+        entry.push_str(&format!("    {}\n", line));
+    }
+    entry
+}
+
+/// Identical consecutive entries beyond this many are summarized in a
+/// single line, as deep recursion would otherwise flood the output.
+const RECURSIVE_CUTOFF: usize = 3;
+
+/// The entries of a traceback, outermost first. A `limit` of `n` keeps
+/// only the first `n` of them, and a `limit` of `-n` only the last `n`,
+/// as in CPython's `traceback` module.
+pub fn entries(tb: &PyTracebackRef, limit: Option<isize>) -> Vec<PyTracebackRef> {
+    let mut entries: Vec<PyTracebackRef> = PyTraceback::iter(tb).collect();
+    match limit {
+        Some(limit) if limit >= 0 => entries.truncate(limit as usize),
+        Some(limit) => {
+            let keep = (-limit as usize).min(entries.len());
+            entries.drain(..entries.len() - keep);
+        }
+        None => {}
+    }
+    entries
+}
+
+/// Format the entries of a traceback chosen by `limit`, outermost first.
+pub fn format_tb(tb: &PyTracebackRef, limit: Option<isize>) -> Vec<String> {
+    let mut lines = vec![];
+    let mut last = None;
+    let mut count = 0;
+    for tb in entries(tb, limit) {
+        let location = tb.location();
+        if last.as_ref() == Some(&location) {
+            count += 1;
+        } else {
+            lines.extend(format_repeated(count));
+            last = Some(location.clone());
+            count = 1;
+        }
+        if count <= RECURSIVE_CUTOFF {
+            let (filename, lineno, name) = location;
+            lines.push(format_entry(&filename, lineno, &name));
+        }
+    }
+    lines.extend(format_repeated(count));
+    lines
+}
+
+fn format_repeated(count: usize) -> Option<String> {
+    if count > RECURSIVE_CUTOFF {
+        let repeated = count - RECURSIVE_CUTOFF;
+        let plural = if repeated == 1 { "" } else { "s" };
+        Some(format!(
+            "  [Previous line repeated {} more time{}]\n",
+            repeated, plural
+        ))
+    } else {
+        None
+    }
+}
+
+pub fn init(context: &PyContext) {
+    extend_class!(context, &context.traceback_type, {
+        "__new__" => context.new_rustfunc(PyTracebackRef::new),
+        "tb_frame" => context.new_property(PyTracebackRef::tb_frame),
+        "tb_lasti" => context.new_property(PyTracebackRef::tb_lasti),
+        "tb_lineno" => context.new_property(PyTracebackRef::tb_lineno),
+        "tb_next" => context.new_property(PyTracebackRef::tb_next),
+    });
+}
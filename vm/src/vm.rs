@@ -6,19 +6,28 @@
 
 extern crate rustpython_parser;
 
-use std::cell::{Ref, RefCell};
+use std::cell::{Cell, Ref, RefCell};
 use std::collections::hash_map::HashMap;
 use std::collections::hash_set::HashSet;
+use std::path::PathBuf;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Mutex, MutexGuard};
 
+use crate::archive::Archive;
 use crate::builtins;
 use crate::bytecode;
+use crate::compile;
+use crate::error::CompileError;
+use crate::exceptions;
 use crate::frame::{ExecutionResult, Frame, FrameRef, Scope};
 use crate::function::PyFuncArgs;
+use crate::obj::objasyncgenerator::PyAsyncGenRef;
 use crate::obj::objbool;
 use crate::obj::objbuiltinfunc::PyBuiltinFunction;
+use crate::obj::objcell::PyCellRef;
 use crate::obj::objcode::PyCodeRef;
+use crate::obj::objcoroutine::PyCoroutineRef;
 use crate::obj::objdict::PyDictRef;
 use crate::obj::objfunction::{PyFunction, PyMethod};
 use crate::obj::objgenerator::PyGeneratorRef;
@@ -33,6 +42,7 @@ use crate::pyobject::{
     DictProtocol, IdProtocol, PyContext, PyObjectRef, PyResult, PyValue, TryFromObject, TryIntoRef,
     TypeProtocol,
 };
+use crate::stack;
 use crate::stdlib;
 use crate::sysmodule;
 use num_bigint::BigInt;
@@ -50,6 +60,30 @@ pub struct VirtualMachine {
     pub ctx: PyContext,
     pub frames: RefCell<Vec<FrameRef>>,
     pub wasm_id: Option<String>,
+    pub exceptions: RefCell<Vec<PyObjectRef>>,
+    pub atexit_funcs: RefCell<Vec<(PyObjectRef, PyFuncArgs)>>,
+    /// The search functions given to `codecs.register`.
+    pub codec_search_functions: RefCell<Vec<PyObjectRef>>,
+    pub recursion_limit: Cell<usize>,
+    /// The lowest native stack address a new frame may start below, for
+    /// the thread the VM was created on.
+    stack_limit: Option<usize>,
+    /// The archives of precompiled modules on `sys.path`, by path.
+    pub archives: RefCell<HashMap<PathBuf, Archive>>,
+    pub compile_options: Cell<compile::CompileOptions>,
+}
+
+/// The default maximum depth of the Python call stack, as in CPython.
+pub const DEFAULT_RECURSION_LIMIT: usize = 1000;
+
+/// Set when the user presses Ctrl-C. The running frame picks it up between
+/// instructions and raises `KeyboardInterrupt`.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Request a `KeyboardInterrupt`. This only sets a flag, so it is safe to
+/// call from a signal handler.
+pub fn trigger_keyboard_interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
 }
 
 impl VirtualMachine {
@@ -69,11 +103,18 @@ impl VirtualMachine {
             ctx,
             frames: RefCell::new(vec![]),
             wasm_id: None,
+            exceptions: RefCell::new(vec![]),
+            atexit_funcs: RefCell::new(vec![]),
+            codec_search_functions: RefCell::new(vec![]),
+            recursion_limit: Cell::new(DEFAULT_RECURSION_LIMIT),
+            stack_limit: stack::stack_limit(),
+            archives: RefCell::new(HashMap::new()),
+            compile_options: Cell::new(compile::CompileOptions::default()),
         }
     }
 
     pub fn run_code_obj(&self, code: PyCodeRef, scope: Scope) -> PyResult {
-        let frame = Frame::new(code, scope).into_ref(self);
+        let frame = Frame::new(code, scope, &[], self).into_ref(self);
         self.run_frame_full(frame)
     }
 
@@ -85,12 +126,41 @@ impl VirtualMachine {
     }
 
     pub fn run_frame(&self, frame: FrameRef) -> PyResult<ExecutionResult> {
+        self.with_frame(frame, |frame| frame.run(self))
+    }
+
+    /// Call `f` with `frame` pushed on the frame stack.
+    pub fn with_frame<R, F: FnOnce(FrameRef) -> PyResult<R>>(
+        &self,
+        frame: FrameRef,
+        f: F,
+    ) -> PyResult<R> {
+        self.check_recursive_call()?;
         self.frames.borrow_mut().push(frame.clone());
-        let result = frame.run(self);
+        let result = f(frame);
         self.frames.borrow_mut().pop();
         result
     }
 
+    /// Refuse to push another frame once the recursion limit is reached, or
+    /// once the native stack is nearly exhausted, so that runaway recursion
+    /// raises `RecursionError` instead of overflowing the native stack.
+    fn check_recursive_call(&self) -> PyResult<()> {
+        let stack_exhausted = match self.stack_limit {
+            Some(limit) => stack::stack_pointer() < limit,
+            None => false,
+        };
+        if stack_exhausted || self.frames.borrow().len() >= self.recursion_limit.get() {
+            let recursion_error = self.ctx.exceptions.recursion_error.clone();
+            Err(self.new_exception(
+                recursion_error,
+                "maximum recursion depth exceeded".to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn current_frame(&self) -> Option<Ref<FrameRef>> {
         let frames = self.frames.borrow();
         if frames.is_empty() {
@@ -102,6 +172,50 @@ impl VirtualMachine {
         }
     }
 
+    /// Mark an exception as being handled, by an except or finally clause.
+    pub fn push_exception(&self, exc: PyObjectRef) {
+        self.exceptions.borrow_mut().push(exc)
+    }
+
+    pub fn pop_exception(&self) -> Option<PyObjectRef> {
+        self.exceptions.borrow_mut().pop()
+    }
+
+    /// The exception which is currently being handled, if any.
+    pub fn current_exception(&self) -> Option<PyObjectRef> {
+        self.exceptions.borrow().last().cloned()
+    }
+
+    /// Raise `KeyboardInterrupt` if an interrupt was requested since the
+    /// last check.
+    pub fn check_signals(&self) -> PyResult<()> {
+        // A plain load is cheap enough for every instruction; only clear the
+        // flag once it has been set.
+        if INTERRUPTED.load(Ordering::Relaxed) && INTERRUPTED.swap(false, Ordering::SeqCst) {
+            let keyboard_interrupt = self.ctx.exceptions.keyboard_interrupt.clone();
+            Err(self.new_empty_exception(keyboard_interrupt)?)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Run the functions registered with `atexit`, most recently registered
+    /// first. Errors are printed and otherwise ignored.
+    pub fn run_atexit_funcs(&self) {
+        loop {
+            let (func, args) = match self.atexit_funcs.borrow_mut().pop() {
+                Some(entry) => entry,
+                None => break,
+            };
+            if let Err(exc) = self.invoke(func, args) {
+                if !objtype::isinstance(&exc, &self.ctx.exceptions.system_exit) {
+                    println!("Error in atexit._run_exitfuncs:");
+                    exceptions::print_exception(self, &exc);
+                }
+            }
+        }
+    }
+
     pub fn current_scope(&self) -> Ref<Scope> {
         let frame = self
             .current_frame()
@@ -169,6 +283,26 @@ impl VirtualMachine {
         self.new_exception(type_error, msg)
     }
 
+    pub fn new_syntax_error(&self, error: &CompileError) -> PyObjectRef {
+        let syntax_error_type = self.ctx.exceptions.syntax_error.clone();
+        let syntax_error = self.new_exception(syntax_error_type, error.to_string());
+        let lineno = self.new_int(error.location.get_row());
+        let offset = self.new_int(error.location.get_column());
+        let filename = match error.source_path {
+            Some(ref source_path) => self.new_str(source_path.clone()),
+            None => self.get_none(),
+        };
+        let text = match error.statement {
+            Some(ref statement) => self.new_str(format!("{}\n", statement)),
+            None => self.get_none(),
+        };
+        self.ctx.set_attr(&syntax_error, "filename", filename);
+        self.ctx.set_attr(&syntax_error, "lineno", lineno);
+        self.ctx.set_attr(&syntax_error, "offset", offset);
+        self.ctx.set_attr(&syntax_error, "text", text);
+        syntax_error
+    }
+
     pub fn new_unsupported_operand_error(
         &self,
         a: PyObjectRef,
@@ -195,11 +329,21 @@ impl VirtualMachine {
         self.new_exception(value_error, msg)
     }
 
+    pub fn new_runtime_error(&self, msg: String) -> PyObjectRef {
+        let runtime_error = self.ctx.exceptions.runtime_error.clone();
+        self.new_exception(runtime_error, msg)
+    }
+
     pub fn new_key_error(&self, msg: String) -> PyObjectRef {
         let key_error = self.ctx.exceptions.key_error.clone();
         self.new_exception(key_error, msg)
     }
 
+    pub fn new_lookup_error(&self, msg: String) -> PyObjectRef {
+        let lookup_error = self.ctx.exceptions.lookup_error.clone();
+        self.new_exception(lookup_error, msg)
+    }
+
     pub fn new_index_error(&self, msg: String) -> PyObjectRef {
         let index_error = self.ctx.exceptions.index_error.clone();
         self.new_exception(index_error, msg)
@@ -233,7 +377,10 @@ impl VirtualMachine {
     }
 
     pub fn get_locals(&self) -> PyDictRef {
-        self.current_scope().get_locals().clone()
+        let frame = self
+            .current_frame()
+            .expect("called get_locals but no frames on the stack");
+        frame.get_locals(self)
     }
 
     pub fn context(&self) -> &PyContext {
@@ -328,9 +475,10 @@ impl VirtualMachine {
             ref code,
             ref scope,
             ref defaults,
+            ref closure,
         }) = func_ref.payload()
         {
-            return self.invoke_python_function(code, scope, defaults, args);
+            return self.invoke_python_function(code, scope, defaults, closure, args);
         }
         if let Some(PyMethod {
             ref function,
@@ -348,43 +496,52 @@ impl VirtualMachine {
         self.call_method(&func_ref, "__call__", args)
     }
 
+    /// Whether `invoke` can call the object.
+    pub fn is_callable(&self, obj: &PyObjectRef) -> bool {
+        obj.payload_is::<PyFunction>()
+            || obj.payload_is::<PyMethod>()
+            || obj.payload_is::<PyBuiltinFunction>()
+            || objtype::class_has_attr(&obj.class(), "__call__")
+    }
+
     fn invoke_python_function(
         &self,
         code: &PyCodeRef,
         scope: &Scope,
         defaults: &PyObjectRef,
+        closure: &[PyCellRef],
         args: PyFuncArgs,
     ) -> PyResult {
-        let scope = scope.child_scope(&self.ctx);
-        self.fill_locals_from_args(&code.code, &scope.get_locals(), args, defaults)?;
-
         // Construct frame:
-        let frame = Frame::new(code.clone(), scope).into_ref(self);
-
-        // If we have a generator, create a new generator
-        if code.code.is_generator {
-            Ok(PyGeneratorRef::new(frame, self).into_object())
-        } else {
-            self.run_frame_full(frame)
+        let scope = scope.child_scope(&self.ctx);
+        let frame = Frame::new(code.clone(), scope, closure, self);
+        self.fill_locals_from_args(
+            &code.code,
+            &mut frame.fastlocals.borrow_mut(),
+            args,
+            defaults,
+        )?;
+        let frame = frame.into_ref(self);
+
+        // Generators and coroutines run their frame when resumed:
+        match (code.code.is_generator, code.code.is_coroutine) {
+            (true, true) => Ok(PyAsyncGenRef::new(frame, self).into_object()),
+            (false, true) => Ok(PyCoroutineRef::new(frame, self).into_object()),
+            (true, false) => Ok(PyGeneratorRef::new(frame, self).into_object()),
+            (false, false) => self.run_frame_full(frame),
         }
     }
 
-    pub fn invoke_with_locals(
-        &self,
-        function: PyObjectRef,
-        cells: PyDictRef,
-        locals: PyDictRef,
-    ) -> PyResult {
+    pub fn invoke_with_locals(&self, function: PyObjectRef, locals: PyDictRef) -> PyResult {
         if let Some(PyFunction {
             code,
             scope,
             defaults: _,
+            closure,
         }) = &function.payload()
         {
-            let scope = scope
-                .child_scope_with_locals(cells)
-                .child_scope_with_locals(locals);
-            let frame = Frame::new(code.clone(), scope).into_ref(self);
+            let scope = scope.child_scope_with_locals(locals);
+            let frame = Frame::new(code.clone(), scope, closure, self).into_ref(self);
             return self.run_frame_full(frame);
         }
         panic!(
@@ -396,12 +553,13 @@ impl VirtualMachine {
     fn fill_locals_from_args(
         &self,
         code_object: &bytecode::CodeObject,
-        locals: &PyDictRef,
+        locals: &mut [Option<PyObjectRef>],
         args: PyFuncArgs,
         defaults: &PyObjectRef,
     ) -> PyResult<()> {
         let nargs = args.args.len();
         let nexpected_args = code_object.arg_names.len();
+        let nkwonly_args = code_object.kwonlyarg_names.len();
 
         // This parses the arguments from args and kwargs into
         // the proper variables keeping into account default values
@@ -415,16 +573,17 @@ impl VirtualMachine {
             nargs
         };
 
-        // Copy positional arguments into local variables
+        // Copy positional arguments into local variables. The arguments occupy
+        // the first slots of the fast locals, in the order of `varnames`.
         for i in 0..n {
-            let arg_name = &code_object.arg_names[i];
             let arg = &args.args[i];
-            locals.set_item(&self.ctx, arg_name, arg.clone());
+            locals[i] = Some(arg.clone());
         }
 
         // Pack other positional arguments in to *args:
+        let mut next_slot = nexpected_args + nkwonly_args;
         match code_object.varargs {
-            bytecode::Varargs::Named(ref vararg_name) => {
+            bytecode::Varargs::Named(_) => {
                 let mut last_args = vec![];
                 for i in n..nargs {
                     let arg = &args.args[i];
@@ -432,7 +591,8 @@ impl VirtualMachine {
                 }
                 let vararg_value = self.ctx.new_tuple(last_args);
 
-                locals.set_item(&self.ctx, vararg_name, vararg_value);
+                locals[next_slot] = Some(vararg_value);
+                next_slot += 1;
             }
             bytecode::Varargs::Unnamed => {
                 // just ignore the rest of the args
@@ -450,9 +610,9 @@ impl VirtualMachine {
 
         // Do we support `**kwargs` ?
         let kwargs = match code_object.varkeywords {
-            bytecode::Varargs::Named(ref kwargs_name) => {
+            bytecode::Varargs::Named(_) => {
                 let d = self.ctx.new_dict();
-                locals.set_item(&self.ctx, kwargs_name, d.as_object().clone());
+                locals[next_slot] = Some(d.as_object().clone());
                 Some(d)
             }
             bytecode::Varargs::Unnamed => Some(self.ctx.new_dict()),
@@ -462,15 +622,25 @@ impl VirtualMachine {
         // Handle keyword arguments
         for (name, value) in args.kwargs {
             // Check if we have a parameter with this name:
-            if code_object.arg_names.contains(&name) || code_object.kwonlyarg_names.contains(&name)
-            {
-                if locals.contains_key(&name) {
+            let slot = code_object
+                .arg_names
+                .iter()
+                .position(|arg| arg == &name)
+                .or_else(|| {
+                    code_object
+                        .kwonlyarg_names
+                        .iter()
+                        .position(|arg| arg == &name)
+                        .map(|i| nexpected_args + i)
+                });
+            if let Some(slot) = slot {
+                if locals[slot].is_some() {
                     return Err(
                         self.new_type_error(format!("Got multiple values for argument '{}'", name))
                     );
                 }
 
-                locals.set_item(&self.ctx, &name, value);
+                locals[slot] = Some(value);
             } else if let Some(d) = &kwargs {
                 d.set_item(&self.ctx, &name, value);
             } else {
@@ -499,7 +669,7 @@ impl VirtualMachine {
             let mut missing = vec![];
             for i in 0..required_args {
                 let variable_name = &code_object.arg_names[i];
-                if !locals.contains_key(variable_name) {
+                if locals[i].is_none() {
                     missing.push(variable_name)
                 }
             }
@@ -514,21 +684,16 @@ impl VirtualMachine {
             // We have sufficient defaults, so iterate over the corresponding names and use
             // the default if we don't already have a value
             for (default_index, i) in (required_args..nexpected_args).enumerate() {
-                let arg_name = &code_object.arg_names[i];
-                if !locals.contains_key(arg_name) {
-                    locals.set_item(
-                        &self.ctx,
-                        arg_name,
-                        available_defaults[default_index].clone(),
-                    );
+                if locals[i].is_none() {
+                    locals[i] = Some(available_defaults[default_index].clone());
                 }
             }
         };
 
         // Check if kw only arguments are all present:
         let kwdefs: HashMap<String, String> = HashMap::new();
-        for arg_name in &code_object.kwonlyarg_names {
-            if !locals.contains_key(arg_name) {
+        for (i, arg_name) in code_object.kwonlyarg_names.iter().enumerate() {
+            if locals[nexpected_args + i].is_none() {
                 if kwdefs.contains_key(arg_name) {
                     // If not yet specified, take the default value
                     unimplemented!();
@@ -914,8 +1079,10 @@ impl Drop for ReprGuard {
 #[cfg(test)]
 mod tests {
     use super::VirtualMachine;
-    use crate::obj::{objint, objstr};
+    use crate::compile;
+    use crate::obj::{objint, objstr, objtype};
     use num_bigint::ToBigInt;
+    use std::thread;
 
     #[test]
     fn test_add_py_integers() {
@@ -936,4 +1103,25 @@ mod tests {
         let value = objstr::get_value(&res);
         assert_eq!(value, String::from("Hello Hello Hello Hello "))
     }
+
+    #[test]
+    fn test_deep_recursion_on_small_stack() {
+        // The native stack runs out long before this recursion limit does.
+        let source =
+            "import sys\nsys.setrecursionlimit(1000000)\ndef f(n):\n    return f(n + 1)\nf(0)\n";
+        let raised_recursion_error = thread::Builder::new()
+            .stack_size(8 * 1024 * 1024)
+            .spawn(move || {
+                let vm = VirtualMachine::new();
+                let code =
+                    compile::compile(&vm, source, &compile::Mode::Exec, "<unittest>".to_string())
+                        .unwrap();
+                let exc = vm.run_code_obj(code, vm.ctx.new_scope()).unwrap_err();
+                objtype::isinstance(&exc, &vm.ctx.exceptions.recursion_error)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert!(raised_recursion_error);
+    }
 }
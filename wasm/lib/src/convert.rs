@@ -3,8 +3,9 @@ use num_traits::cast::ToPrimitive;
 use wasm_bindgen::{closure::Closure, prelude::*, JsCast};
 
 use rustpython_vm::function::PyFuncArgs;
-use rustpython_vm::obj::{objbytes, objint, objsequence, objtype};
+use rustpython_vm::obj::{objbytes, objtype};
 use rustpython_vm::pyobject::{DictProtocol, PyObjectRef, PyResult, PyValue};
+use rustpython_vm::traceback::PyTraceback;
 use rustpython_vm::VirtualMachine;
 
 use crate::browser_module;
@@ -39,18 +40,11 @@ pub fn py_err_to_js_err(vm: &VirtualMachine, py_err: &PyObjectRef) -> JsValue {
         &vm.ctx.exceptions.syntax_error => js_sys::SyntaxError::new,
     });
     if let Ok(tb) = vm.get_attribute(py_err.clone(), "__traceback__") {
-        if objtype::isinstance(&tb, &vm.ctx.list_type()) {
-            let elements = objsequence::get_elements(&tb).to_vec();
-            if let Some(top) = elements.get(0) {
-                if objtype::isinstance(&top, &vm.ctx.tuple_type()) {
-                    let element = objsequence::get_elements(&top);
-
-                    if let Some(lineno) = objint::to_int(vm, &element[1], 10)
-                        .ok()
-                        .and_then(|lineno| lineno.to_u32())
-                    {
-                        let _ = Reflect::set(&js_err, &"row".into(), &lineno.into());
-                    }
+        if let Ok(tb) = tb.downcast::<PyTraceback>() {
+            // The last entry is the frame the exception was raised in:
+            if let Some(top) = PyTraceback::iter(&tb).last() {
+                if let Some(lineno) = top.lineno.to_u32() {
+                    let _ = Reflect::set(&js_err, &"row".into(), &lineno.into());
                 }
             }
         }
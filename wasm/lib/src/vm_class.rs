@@ -287,24 +287,20 @@ impl WASMVirtualMachine {
                 let code = compile::compile(vm, &source, &mode, "<wasm>".to_string());
                 let code = code.map_err(|err| {
                     let js_err = SyntaxError::new(&format!("Error parsing Python code: {}", err));
-                    if let rustpython_vm::error::CompileError::Parse(ref parse_error) = err {
+                    let _ = Reflect::set(
+                        &js_err,
+                        &"row".into(),
+                        &(err.location.get_row() as u32).into(),
+                    );
+                    let _ = Reflect::set(
+                        &js_err,
+                        &"col".into(),
+                        &(err.location.get_column() as u32).into(),
+                    );
+                    if let rustpython_vm::error::CompileErrorType::Parse(ref parse_error) =
+                        err.error
+                    {
                         use rustpython_parser::error::ParseError;
-                        if let ParseError::EOF(Some(ref loc))
-                        | ParseError::ExtraToken((ref loc, ..))
-                        | ParseError::InvalidToken(ref loc)
-                        | ParseError::UnrecognizedToken((ref loc, ..), _) = parse_error
-                        {
-                            let _ = Reflect::set(
-                                &js_err,
-                                &"row".into(),
-                                &(loc.get_row() as u32).into(),
-                            );
-                            let _ = Reflect::set(
-                                &js_err,
-                                &"col".into(),
-                                &(loc.get_column() as u32).into(),
-                            );
-                        }
                         if let ParseError::ExtraToken((_, _, ref loc))
                         | ParseError::UnrecognizedToken((_, _, ref loc), _) = parse_error
                         {